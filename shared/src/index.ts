@@ -92,6 +92,8 @@ export type MessageRole = 'user' | 'assistant' | 'system' | 'tool'
 export type UsagePeriod = 'daily' | 'weekly' | 'monthly'
 export type SettingType = 'string' | 'number' | 'boolean' | 'json'
 export type SortMode = 'free' | 'status' | 'name'
+// 'missing' when the directory is gone, 'broken' when git no longer knows it
+export type WorktreeState = 'ok' | 'missing' | 'broken'
 
 // API types (camelCase, for frontend use)
 export interface Workspace {
@@ -115,6 +117,11 @@ export interface Worktree {
   isMain: boolean
   createdAt: string
   updatedAt: string
+  pinned?: boolean
+  // Set while a scan can't find the checkout
+  missingSince?: string
+  // Filled in from a health check
+  state?: WorktreeState
 }
 
 export interface Agent {
@@ -132,6 +139,8 @@ export interface Agent {
   updatedAt: string
   startedAt: string | null
   stoppedAt: string | null
+  // Length of the current run, or of the last one once stopped
+  runDurationMs?: number
   deletedAt: string | null
   parentAgentId: string | null
 }
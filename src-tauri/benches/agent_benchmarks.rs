@@ -94,7 +94,7 @@ fn bench_list_agents(c: &mut Criterion) {
     c.bench_function("list_100_agents", |b| {
         b.iter(|| {
             service
-                .list_agents(&worktree_id, false)
+                .list_agents(&worktree_id, false, &[])
                 .expect("Should list agents")
         })
     });
@@ -145,6 +145,11 @@ fn bench_update_agent(c: &mut Criterion) {
                         mode: None,
                         permissions: None,
                         display_order: None,
+                        system_prompt: None,
+                        append_system_prompt: None,
+                        allowed_tools: None,
+                        disallowed_tools: None,
+                        expected_updated_at: None,
                     },
                 )
                 .expect("Should update agent")
@@ -169,7 +174,7 @@ fn bench_fork_agent(c: &mut Criterion) {
     c.bench_function("fork_agent", |b| {
         b.iter(|| {
             service
-                .fork_agent(&agent.id, None)
+                .fork_agent(&agent.id, None, false)
                 .expect("Should fork agent")
         })
     });
@@ -198,7 +203,7 @@ fn bench_list_agents_scaling(c: &mut Criterion) {
         group.bench_with_input(BenchmarkId::from_parameter(size), size, |b, _| {
             b.iter(|| {
                 service
-                    .list_agents(&worktree_id, false)
+                    .list_agents(&worktree_id, false, &[])
                     .expect("Should list agents")
             })
         });
@@ -0,0 +1,318 @@
+//! Headless command-line interface for Claude Manager
+//!
+//! Everything goes through the running app, authenticated with its API
+//! token: listing over its REST API, and starting, stopping and tailing
+//! agents over its WebSocket server, since the app owns the agent processes.
+//! Only when the app is not running does listing read the database directly,
+//! and never migrates it, which is left to the app.
+
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use claude_manager_lib::db::{self, AgentRepository, WorkspaceRepository, WorktreeRepository};
+use claude_manager_lib::types::{Agent, Workspace, Worktree};
+use futures::{SinkExt, StreamExt};
+use tokio::net::TcpStream;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::http::header::AUTHORIZATION;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+
+const USAGE: &str = "\
+Usage: ccmanager-cli [OPTIONS] <COMMAND>
+
+Commands:
+  workspaces                     List workspaces
+  worktrees <workspace-id>       List worktrees of a workspace
+  agents <worktree-id>           List agents of a worktree
+  start <agent-id> [prompt]      Start an agent in the running app
+  stop <agent-id> [--force]      Stop an agent in the running app
+  tail <agent-id>                Stream an agent's terminal output
+
+Options:
+  --data-dir <dir>   Application data directory (env: CLAUDE_MANAGER_DATA_DIR)
+  --url <url>        WebSocket server URL (env: CLAUDE_MANAGER_WS_URL)
+  --token <token>    API token of the app (env: CLAUDE_MANAGER_API_TOKEN,
+                     default: read from the data directory)
+  --json             Print results as JSON
+  -h, --help         Print this help";
+
+const DEFAULT_WS_URL: &str = "ws://127.0.0.1:3001";
+const APP_IDENTIFIER: &str = "com.claude-manager.app";
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+struct Options {
+    data_dir: PathBuf,
+    url: String,
+    token: Option<String>,
+    json: bool,
+    command: Vec<String>,
+}
+
+fn parse_args() -> Result<Options, String> {
+    let mut data_dir = std::env::var("CLAUDE_MANAGER_DATA_DIR")
+        .ok()
+        .map(PathBuf::from);
+    let mut url = std::env::var("CLAUDE_MANAGER_WS_URL").ok();
+    let mut token = std::env::var("CLAUDE_MANAGER_API_TOKEN").ok();
+    let mut json = false;
+    let mut command = Vec::new();
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--data-dir" => {
+                data_dir = Some(args.next().ok_or("--data-dir requires a value")?.into());
+            }
+            "--url" => url = Some(args.next().ok_or("--url requires a value")?),
+            "--token" => token = Some(args.next().ok_or("--token requires a value")?),
+            "--json" => json = true,
+            "-h" | "--help" => return Err(String::new()),
+            _ => command.push(arg),
+        }
+    }
+
+    // Same location Tauri resolves for app_data_dir()
+    let data_dir = match data_dir {
+        Some(dir) => dir,
+        None => dirs::data_dir()
+            .ok_or("Could not determine the data directory, pass --data-dir")?
+            .join(APP_IDENTIFIER),
+    };
+
+    Ok(Options {
+        data_dir,
+        url: url
+            .unwrap_or_else(|| DEFAULT_WS_URL.to_string())
+            .trim_end_matches('/')
+            .to_string(),
+        token,
+        json,
+        command,
+    })
+}
+
+#[tokio::main]
+async fn main() {
+    let options = match parse_args() {
+        Ok(options) => options,
+        Err(message) => {
+            if !message.is_empty() {
+                eprintln!("error: {}\n", message);
+            }
+            eprintln!("{}", USAGE);
+            std::process::exit(2);
+        }
+    };
+
+    if let Err(e) = run(options).await {
+        eprintln!("error: {}", e);
+        std::process::exit(1);
+    }
+}
+
+async fn run(options: Options) -> anyhow::Result<()> {
+    let args: Vec<&str> = options.command.iter().map(|s| s.as_str()).collect();
+
+    match args.as_slice() {
+        ["workspaces"] => {
+            let workspaces: Vec<Workspace> = match app_get(&options, "/workspaces").await? {
+                Some(mut body) => serde_json::from_value(body["workspaces"].take())?,
+                None => WorkspaceRepository::new(open_database(&options)?).find_all()?,
+            };
+            print_rows(&options, &workspaces, |w| {
+                format!(
+                    "{}\t{}\t{} worktrees\t{} agents",
+                    w.id, w.path, w.worktree_count, w.agent_count
+                )
+            })
+        }
+        ["worktrees", workspace_id] => {
+            let path = format!("/workspaces/{}/worktrees", workspace_id);
+            let worktrees: Vec<Worktree> = match app_get(&options, &path).await? {
+                Some(mut body) => serde_json::from_value(body["worktrees"].take())?,
+                None => WorktreeRepository::new(open_database(&options)?)
+                    .find_by_workspace_id(workspace_id)?,
+            };
+            print_rows(&options, &worktrees, |w| {
+                format!("{}\t{}\t{}", w.id, w.branch, w.path)
+            })
+        }
+        ["agents", worktree_id] => {
+            let path = format!("/worktrees/{}/agents", worktree_id);
+            let agents: Vec<Agent> = match app_get(&options, &path).await? {
+                Some(mut body) => serde_json::from_value(body["agents"].take())?,
+                None => AgentRepository::new(open_database(&options)?)
+                    .find_by_worktree_id(worktree_id, false)?,
+            };
+            print_rows(&options, &agents, |a| {
+                format!("{}\t{}\t{}", a.id, a.status.as_str(), a.name)
+            })
+        }
+        ["start", agent_id, prompt @ ..] => {
+            let prompt = (!prompt.is_empty()).then(|| prompt.join(" "));
+            let request = serde_json::json!({
+                "type": "agent:start",
+                "payload": { "agentId": agent_id, "initialPrompt": prompt },
+            });
+            send_agent_request(&options, agent_id, request).await
+        }
+        ["stop", agent_id, flags @ ..] => {
+            let request = serde_json::json!({
+                "type": "agent:stop",
+                "payload": { "agentId": agent_id, "force": flags.contains(&"--force") },
+            });
+            send_agent_request(&options, agent_id, request).await
+        }
+        ["tail", agent_id] => tail_agent(&options, agent_id).await,
+        _ => {
+            eprintln!("{}", USAGE);
+            std::process::exit(2);
+        }
+    }
+}
+
+/// The app's API token, which it writes to the data directory on first start
+fn api_token(options: &Options) -> anyhow::Result<String> {
+    if let Some(token) = &options.token {
+        return Ok(token.clone());
+    }
+    let path = options.data_dir.join("api-token");
+    let token = std::fs::read_to_string(&path).map_err(|e| {
+        anyhow::anyhow!(
+            "Could not read the API token from {}: {}; start the app once or pass --token",
+            path.display(),
+            e
+        )
+    })?;
+    Ok(token.trim().to_string())
+}
+
+/// GET a REST API path from the running app, or `None` if it is not running
+async fn app_get(options: &Options, path: &str) -> anyhow::Result<Option<serde_json::Value>> {
+    // The REST API shares its port with the WebSocket server
+    let base = match options.url.strip_prefix("ws") {
+        Some(rest) => format!("http{}", rest),
+        None => options.url.clone(),
+    };
+    let response = match reqwest::Client::new()
+        .get(format!("{}/api{}", base, path))
+        .bearer_auth(api_token(options)?)
+        .timeout(REQUEST_TIMEOUT)
+        .send()
+        .await
+    {
+        Ok(response) => response,
+        Err(e) if e.is_connect() => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+
+    let status = response.status();
+    let body: serde_json::Value = response.json().await?;
+    if !status.is_success() {
+        anyhow::bail!("{}", body["message"].as_str().unwrap_or(status.as_str()));
+    }
+    Ok(Some(body))
+}
+
+/// Open the database while the app is not running. A database that would
+/// need migrating is refused, so it is only ever migrated by the app.
+fn open_database(options: &Options) -> anyhow::Result<db::DbPool> {
+    match db::open_database(&options.data_dir) {
+        Ok(pool) => Ok(pool),
+        Err(db::DbError::NotFound) => {
+            anyhow::bail!("No database found in {}", options.data_dir.display())
+        }
+        Err(db::DbError::Migration(message)) => {
+            anyhow::bail!("{}; start the app and the CLI will ask it instead", message)
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Open a WebSocket to the app, authenticated with its API token
+async fn connect(
+    options: &Options,
+    path: &str,
+) -> anyhow::Result<WebSocketStream<MaybeTlsStream<TcpStream>>> {
+    let mut request = format!("{}{}", options.url, path).into_client_request()?;
+    request.headers_mut().insert(
+        AUTHORIZATION,
+        format!("Bearer {}", api_token(options)?).parse()?,
+    );
+    let (socket, _) = tokio_tungstenite::connect_async(request).await?;
+    Ok(socket)
+}
+
+fn print_rows<T: serde::Serialize>(
+    options: &Options,
+    rows: &[T],
+    format_row: impl Fn(&T) -> String,
+) -> anyhow::Result<()> {
+    if options.json {
+        println!("{}", serde_json::to_string_pretty(rows)?);
+    } else {
+        for row in rows {
+            println!("{}", format_row(row));
+        }
+    }
+    Ok(())
+}
+
+/// Send a start/stop request and wait for the server's reply for that agent
+async fn send_agent_request(
+    options: &Options,
+    agent_id: &str,
+    request: serde_json::Value,
+) -> anyhow::Result<()> {
+    let mut socket = connect(options, "/ws").await?;
+    socket.send(Message::Text(request.to_string())).await?;
+
+    let reply = tokio::time::timeout(REQUEST_TIMEOUT, async {
+        while let Some(message) = socket.next().await {
+            let Message::Text(text) = message? else {
+                continue;
+            };
+            let value: serde_json::Value = serde_json::from_str(&text)?;
+            if value["agentId"] == agent_id {
+                return Ok(value);
+            }
+        }
+        anyhow::bail!("Connection closed before the server replied")
+    })
+    .await
+    .map_err(|_| anyhow::anyhow!("Timed out waiting for the server"))??;
+
+    let _ = socket.close(None).await;
+
+    match reply["type"].as_str() {
+        Some("agent:error") => anyhow::bail!("{}", reply["error"].as_str().unwrap_or("unknown")),
+        _ if options.json => println!("{}", reply),
+        _ => println!(
+            "{}\t{}",
+            agent_id,
+            reply["status"].as_str().unwrap_or("unknown")
+        ),
+    }
+    Ok(())
+}
+
+/// Copy the agent's PTY output to stdout until the agent exits
+async fn tail_agent(options: &Options, agent_id: &str) -> anyhow::Result<()> {
+    let socket = connect(options, &format!("/ws/pty/{}", agent_id)).await?;
+    let (_sender, mut receiver) = socket.split();
+    let mut stdout = std::io::stdout();
+
+    while let Some(message) = receiver.next().await {
+        match message? {
+            Message::Binary(bytes) => {
+                stdout.write_all(&bytes)?;
+                stdout.flush()?;
+            }
+            Message::Close(_) => break,
+            _ => {}
+        }
+    }
+    Ok(())
+}
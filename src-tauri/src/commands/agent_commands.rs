@@ -2,24 +2,78 @@
 
 use tauri::State;
 
+use super::audit_commands::authorize;
+use super::run_blocking;
+use crate::error::{AppError, AppResult};
 use crate::types::{
-    Agent, AgentListResponse, AgentMode, CreateAgentInput, Permission, ReorderAgentsInput,
-    UpdateAgentInput,
+    Agent, AgentActivitySummary, AgentListResponse, AgentMode, AgentRun, AgentRuntimeInfo,
+    Capability, CreateAgentInput, EntityKind, ExternalSession, MessageDraft, MessageListResponse,
+    Permission, ReorderAgentsInput, SessionHistoryImport, StopAgentsResponse, UpdateAgentInput,
+    WorkspaceAgentLimit,
 };
 use crate::AppState;
 
-/// List all agents for a worktree
+/// Runs returned when the caller doesn't say
+const DEFAULT_RUN_HISTORY_LIMIT: usize = 20;
+/// Messages returned per page when the caller doesn't say
+const DEFAULT_MESSAGE_PAGE_LIMIT: usize = 100;
+
+/// List all agents for a worktree, optionally only those carrying every given tag
 #[tauri::command]
 pub async fn list_agents(
     worktree_id: String,
     include_deleted: Option<bool>,
+    tags: Option<Vec<String>>,
+    state: State<'_, AppState>,
+) -> AppResult<AgentListResponse> {
+    authorize(&state, "list_agents", Capability::Read, None)?;
+
+    state
+        .agent_service
+        .list_agents(
+            &worktree_id,
+            include_deleted.unwrap_or(false),
+            &tags.unwrap_or_default(),
+        )
+        .map(|agents| AgentListResponse { agents })
+        .map_err(AppError::from)
+}
+
+/// List agents carrying a tag across all worktrees
+#[tauri::command]
+pub async fn list_agents_by_tag(
+    tag: String,
+    include_deleted: Option<bool>,
     state: State<'_, AppState>,
-) -> Result<AgentListResponse, String> {
+) -> AppResult<AgentListResponse> {
+    authorize(&state, "list_agents_by_tag", Capability::Read, None)?;
+
     state
         .agent_service
-        .list_agents(&worktree_id, include_deleted.unwrap_or(false))
+        .list_agents_by_tag(&tag, include_deleted.unwrap_or(false))
         .map(|agents| AgentListResponse { agents })
-        .map_err(|e| e.to_string())
+        .map_err(AppError::from)
+}
+
+/// Replace an agent's tags
+#[tauri::command]
+pub async fn set_agent_tags(
+    id: String,
+    tags: Vec<String>,
+    state: State<'_, AppState>,
+) -> AppResult<Agent> {
+    authorize(
+        &state,
+        "set_agent_tags",
+        Capability::AgentControl,
+        Some(&id),
+    )?;
+
+    let agent = state.agent_service.set_agent_tags(&id, &tags)?;
+    state
+        .change_feed
+        .updated(EntityKind::Agent, &agent.id, &agent);
+    Ok(agent)
 }
 
 /// Get a single agent by ID
@@ -27,11 +81,235 @@ pub async fn list_agents(
 pub async fn get_agent(
     id: String,
     state: State<'_, AppState>,
-) -> Result<Agent, String> {
+) -> AppResult<Agent> {
+    authorize(&state, "get_agent", Capability::Read, None)?;
+
     state
         .agent_service
         .get_agent(&id)
-        .map_err(|e| e.to_string())
+        .map_err(AppError::from)
+}
+
+/// Get the end of an agent's terminal output as plain text
+#[tauri::command]
+pub async fn get_agent_terminal_text(
+    agent_id: String,
+    lines: usize,
+    state: State<'_, AppState>,
+) -> AppResult<String> {
+    authorize(&state, "get_agent_terminal_text", Capability::Read, None)?;
+
+    state
+        .agent_service
+        .get_terminal_text(&agent_id, lines)
+        .map_err(AppError::from)
+}
+
+/// Send a message to a running agent, as if typed into its terminal
+#[tauri::command]
+pub async fn send_message(
+    agent_id: String,
+    message: String,
+    state: State<'_, AppState>,
+) -> AppResult<()> {
+    authorize(
+        &state,
+        "send_message",
+        Capability::AgentControl,
+        Some(&agent_id),
+    )?;
+
+    state
+        .agent_service
+        .send_message(&agent_id, &message)
+        .map_err(AppError::from)
+}
+
+/// List an agent's process runs and how each ended, most recent first
+#[tauri::command]
+pub async fn get_agent_runs(
+    agent_id: String,
+    limit: Option<usize>,
+    state: State<'_, AppState>,
+) -> AppResult<Vec<AgentRun>> {
+    authorize(&state, "get_agent_runs", Capability::Read, None)?;
+
+    let agent_service = state.agent_service.clone();
+    run_blocking(move || {
+        agent_service.get_runs(&agent_id, limit.unwrap_or(DEFAULT_RUN_HISTORY_LIMIT))
+    })
+    .await
+}
+
+/// Get whether an agent's process is alive and when it last produced output,
+/// to tell a recorded status that outlived its process
+#[tauri::command]
+pub async fn get_agent_runtime(
+    agent_id: String,
+    state: State<'_, AppState>,
+) -> AppResult<AgentRuntimeInfo> {
+    authorize(&state, "get_agent_runtime", Capability::Read, None)?;
+
+    state
+        .agent_service
+        .get_runtime(&agent_id)
+        .map_err(AppError::from)
+}
+
+/// Get how long an agent spent working, waiting on the user and idle
+#[tauri::command]
+pub async fn get_agent_activity_summary(
+    agent_id: String,
+    state: State<'_, AppState>,
+) -> AppResult<AgentActivitySummary> {
+    authorize(&state, "get_agent_activity_summary", Capability::Read, None)?;
+
+    state
+        .agent_service
+        .get_activity_summary(&agent_id)
+        .map_err(AppError::from)
+}
+
+/// Get a page of an agent's messages, oldest first; pass `before` to page back
+#[tauri::command]
+pub async fn get_agent_messages(
+    id: String,
+    limit: Option<usize>,
+    before: Option<i64>,
+    state: State<'_, AppState>,
+) -> AppResult<MessageListResponse> {
+    authorize(&state, "get_agent_messages", Capability::Read, None)?;
+
+    let agent_service = state.agent_service.clone();
+    run_blocking(move || {
+        agent_service.get_messages(
+            &id,
+            limit.unwrap_or(DEFAULT_MESSAGE_PAGE_LIMIT),
+            before,
+        )
+    })
+    .await
+}
+
+/// Import the Claude CLI transcript of an agent's session into its messages
+#[tauri::command]
+pub async fn import_session_history(
+    agent_id: String,
+    state: State<'_, AppState>,
+) -> AppResult<SessionHistoryImport> {
+    authorize(
+        &state,
+        "import_session_history",
+        Capability::AgentControl,
+        Some(&agent_id),
+    )?;
+
+    // Reads the whole transcript and writes a row per message
+    let agent_service = state.agent_service.clone();
+    run_blocking(move || agent_service.import_session_history(&agent_id)).await
+}
+
+/// Get the regexes hiding secrets in agent output
+#[tauri::command]
+pub async fn get_output_redaction_patterns(state: State<'_, AppState>) -> AppResult<Vec<String>> {
+    authorize(
+        &state,
+        "get_output_redaction_patterns",
+        Capability::Read,
+        None,
+    )?;
+
+    state
+        .agent_service
+        .get_output_redaction_patterns()
+        .map_err(AppError::from)
+}
+
+/// Replace the regexes hiding secrets in agent output; empty restores the defaults
+#[tauri::command]
+pub async fn set_output_redaction_patterns(
+    patterns: Vec<String>,
+    state: State<'_, AppState>,
+) -> AppResult<Vec<String>> {
+    authorize(
+        &state,
+        "set_output_redaction_patterns",
+        Capability::AgentControl,
+        None,
+    )?;
+
+    state
+        .agent_service
+        .set_output_redaction_patterns(patterns)
+        .map_err(AppError::from)
+}
+
+/// Whether secrets are hidden in an agent's output
+#[tauri::command]
+pub async fn get_agent_output_redaction(
+    agent_id: String,
+    state: State<'_, AppState>,
+) -> AppResult<bool> {
+    authorize(&state, "get_agent_output_redaction", Capability::Read, None)?;
+
+    state
+        .agent_service
+        .get_output_redaction(&agent_id)
+        .map_err(AppError::from)
+}
+
+/// Turn output redaction on or off for an agent
+#[tauri::command]
+pub async fn set_agent_output_redaction(
+    agent_id: String,
+    enabled: bool,
+    state: State<'_, AppState>,
+) -> AppResult<bool> {
+    authorize(
+        &state,
+        "set_agent_output_redaction",
+        Capability::AgentControl,
+        Some(&agent_id),
+    )?;
+
+    state
+        .agent_service
+        .set_output_redaction(&agent_id, enabled)
+        .map_err(AppError::from)
+}
+
+/// Get the unsent message saved for an agent
+#[tauri::command]
+pub async fn get_draft(
+    agent_id: String,
+    state: State<'_, AppState>,
+) -> AppResult<Option<MessageDraft>> {
+    authorize(&state, "get_draft", Capability::Read, None)?;
+
+    state
+        .agent_service
+        .get_draft(&agent_id)
+        .map_err(AppError::from)
+}
+
+/// Save the message being composed for an agent; empty content clears it
+#[tauri::command]
+pub async fn save_draft(
+    agent_id: String,
+    content: String,
+    state: State<'_, AppState>,
+) -> AppResult<()> {
+    authorize(
+        &state,
+        "save_draft",
+        Capability::AgentControl,
+        Some(&agent_id),
+    )?;
+
+    state
+        .agent_service
+        .save_draft(&agent_id, &content)
+        .map_err(AppError::from)
 }
 
 /// Create a new agent
@@ -39,16 +317,44 @@ pub async fn get_agent(
 pub async fn create_agent(
     input: CreateAgentInput,
     state: State<'_, AppState>,
-) -> Result<Agent, String> {
+) -> AppResult<Agent> {
+    authorize(
+        &state,
+        "create_agent",
+        Capability::AgentControl,
+        Some(&input.worktree_id),
+    )?;
+
+    let mut agent = state.agent_service.create_agent(
+        &input.worktree_id,
+        input.name,
+        input.mode.unwrap_or(AgentMode::Regular),
+        input.permissions.unwrap_or_else(|| vec![Permission::Read]),
+    )?;
+    let has_launch_settings = input.system_prompt.is_some()
+        || input.append_system_prompt.is_some()
+        || input.allowed_tools.is_some()
+        || input.disallowed_tools.is_some();
+    if has_launch_settings {
+        agent = state.agent_service.update_agent(
+            &agent.id,
+            UpdateAgentInput {
+                name: None,
+                mode: None,
+                permissions: None,
+                display_order: None,
+                system_prompt: input.system_prompt,
+                append_system_prompt: input.append_system_prompt,
+                allowed_tools: input.allowed_tools,
+                disallowed_tools: input.disallowed_tools,
+                expected_updated_at: None,
+            },
+        )?;
+    }
     state
-        .agent_service
-        .create_agent(
-            &input.worktree_id,
-            input.name,
-            input.mode.unwrap_or(AgentMode::Regular),
-            input.permissions.unwrap_or_else(|| vec![Permission::Read]),
-        )
-        .map_err(|e| e.to_string())
+        .change_feed
+        .created(EntityKind::Agent, &agent.id, &agent);
+    Ok(agent)
 }
 
 /// Update an agent
@@ -57,11 +363,14 @@ pub async fn update_agent(
     id: String,
     input: UpdateAgentInput,
     state: State<'_, AppState>,
-) -> Result<Agent, String> {
+) -> AppResult<Agent> {
+    authorize(&state, "update_agent", Capability::AgentControl, Some(&id))?;
+
+    let agent = state.agent_service.update_agent(&id, input)?;
     state
-        .agent_service
-        .update_agent(&id, input)
-        .map_err(|e| e.to_string())
+        .change_feed
+        .updated(EntityKind::Agent, &agent.id, &agent);
+    Ok(agent)
 }
 
 /// Delete an agent
@@ -70,11 +379,14 @@ pub async fn delete_agent(
     id: String,
     archive: Option<bool>,
     state: State<'_, AppState>,
-) -> Result<(), String> {
+) -> AppResult<()> {
+    authorize(&state, "delete_agent", Capability::AgentControl, Some(&id))?;
+
     state
         .agent_service
-        .delete_agent(&id, archive.unwrap_or(true))
-        .map_err(|e| e.to_string())
+        .delete_agent(&id, archive.unwrap_or(true))?;
+    state.change_feed.deleted(EntityKind::Agent, &id);
+    Ok(())
 }
 
 /// Start an agent
@@ -83,13 +395,75 @@ pub async fn start_agent(
     id: String,
     initial_prompt: Option<String>,
     state: State<'_, AppState>,
-) -> Result<Agent, String> {
-    let agent = state.agent_service.get_agent(&id).map_err(|e| e.to_string())?;
-    let worktree = state.worktree_service.get_worktree(&agent.worktree_id).map_err(|e| e.to_string())?;
+) -> AppResult<Agent> {
+    authorize(&state, "start_agent", Capability::AgentControl, Some(&id))?;
+
+    let agent = state.agent_service.get_agent(&id)?;
+    let worktree = state.worktree_service.get_worktree(&agent.worktree_id)?;
+    let agent = state
+        .agent_service
+        .start_agent(&id, &worktree.path, initial_prompt.as_deref())?;
     state
+        .change_feed
+        .updated(EntityKind::Agent, &agent.id, &agent);
+    Ok(agent)
+}
+
+/// List Claude sessions run in managed worktrees outside the manager
+#[tauri::command]
+pub async fn discover_sessions(
+    worktree_id: Option<String>,
+    state: State<'_, AppState>,
+) -> AppResult<Vec<ExternalSession>> {
+    authorize(&state, "discover_sessions", Capability::Read, None)?;
+
+    state
+        .agent_service
+        .discover_sessions(worktree_id.as_deref())
+        .map_err(AppError::from)
+}
+
+/// Create an agent for a session run outside the manager, to follow and resume it
+#[tauri::command]
+pub async fn adopt_session(
+    worktree_id: String,
+    session_id: String,
+    state: State<'_, AppState>,
+) -> AppResult<Agent> {
+    authorize(
+        &state,
+        "adopt_session",
+        Capability::AgentControl,
+        Some(&worktree_id),
+    )?;
+
+    let agent = state
         .agent_service
-        .start_agent(&id, &worktree.path, initial_prompt.as_deref())
-        .map_err(|e| e.to_string())
+        .adopt_session(&worktree_id, &session_id)?;
+    state
+        .change_feed
+        .created(EntityKind::Agent, &agent.id, &agent);
+    Ok(agent)
+}
+
+/// Start a stopped agent again with its last prompt, e.g. after an error,
+/// recording the run as another attempt
+#[tauri::command]
+pub async fn retry_agent(agent_id: String, state: State<'_, AppState>) -> AppResult<Agent> {
+    authorize(
+        &state,
+        "retry_agent",
+        Capability::AgentControl,
+        Some(&agent_id),
+    )?;
+
+    let agent = state.agent_service.get_agent(&agent_id)?;
+    let worktree = state.worktree_service.get_worktree(&agent.worktree_id)?;
+    let agent = state.agent_service.retry_agent(&agent_id, &worktree.path)?;
+    state
+        .change_feed
+        .updated(EntityKind::Agent, &agent.id, &agent);
+    Ok(agent)
 }
 
 /// Stop an agent
@@ -98,24 +472,109 @@ pub async fn stop_agent(
     id: String,
     force: Option<bool>,
     state: State<'_, AppState>,
-) -> Result<Agent, String> {
+) -> AppResult<Agent> {
+    authorize(&state, "stop_agent", Capability::AgentControl, Some(&id))?;
+
+    let agent = state
+        .agent_service
+        .stop_agent(&id, force.unwrap_or(false))?;
+    state
+        .change_feed
+        .updated(EntityKind::Agent, &agent.id, &agent);
+    Ok(agent)
+}
+
+/// Stop every running agent in a workspace
+#[tauri::command]
+pub async fn stop_workspace_agents(
+    workspace_id: String,
+    force: Option<bool>,
+    state: State<'_, AppState>,
+) -> AppResult<StopAgentsResponse> {
+    authorize(
+        &state,
+        "stop_workspace_agents",
+        Capability::AgentControl,
+        Some(&workspace_id),
+    )?;
+
+    state
+        .agent_service
+        .stop_workspace_agents(&workspace_id, force.unwrap_or(false))
+        .map_err(AppError::from)
+}
+
+/// Stop every running agent in a worktree
+#[tauri::command]
+pub async fn stop_worktree_agents(
+    worktree_id: String,
+    force: Option<bool>,
+    state: State<'_, AppState>,
+) -> AppResult<StopAgentsResponse> {
+    authorize(
+        &state,
+        "stop_worktree_agents",
+        Capability::AgentControl,
+        Some(&worktree_id),
+    )?;
+
+    state
+        .agent_service
+        .stop_worktree_agents(&worktree_id, force.unwrap_or(false))
+        .map_err(AppError::from)
+}
+
+/// Get a workspace's concurrent agent limit and how many agents are running
+#[tauri::command]
+pub async fn get_workspace_agent_limit(
+    workspace_id: String,
+    state: State<'_, AppState>,
+) -> AppResult<WorkspaceAgentLimit> {
+    authorize(&state, "get_workspace_agent_limit", Capability::Read, None)?;
+
     state
         .agent_service
-        .stop_agent(&id, force.unwrap_or(false))
-        .map_err(|e| e.to_string())
+        .get_agent_limit(&workspace_id)
+        .map_err(AppError::from)
 }
 
-/// Fork an agent
+/// Set how many agents of a workspace may run at once (None or 0 for no limit)
+#[tauri::command]
+pub async fn set_workspace_agent_limit(
+    workspace_id: String,
+    max_concurrent_agents: Option<usize>,
+    state: State<'_, AppState>,
+) -> AppResult<WorkspaceAgentLimit> {
+    authorize(
+        &state,
+        "set_workspace_agent_limit",
+        Capability::AgentControl,
+        Some(&workspace_id),
+    )?;
+
+    state
+        .agent_service
+        .set_agent_limit(&workspace_id, max_concurrent_agents)
+        .map_err(AppError::from)
+}
+
+/// Fork an agent, optionally branching its Claude session
 #[tauri::command]
 pub async fn fork_agent(
     id: String,
     name: Option<String>,
+    fork_session: Option<bool>,
     state: State<'_, AppState>,
-) -> Result<Agent, String> {
-    state
+) -> AppResult<Agent> {
+    authorize(&state, "fork_agent", Capability::AgentControl, Some(&id))?;
+
+    let agent = state
         .agent_service
-        .fork_agent(&id, name)
-        .map_err(|e| e.to_string())
+        .fork_agent(&id, name, fork_session.unwrap_or(false))?;
+    state
+        .change_feed
+        .created(EntityKind::Agent, &agent.id, &agent);
+    Ok(agent)
 }
 
 /// Restore a deleted agent
@@ -123,11 +582,37 @@ pub async fn fork_agent(
 pub async fn restore_agent(
     id: String,
     state: State<'_, AppState>,
-) -> Result<Agent, String> {
+) -> AppResult<Agent> {
+    authorize(&state, "restore_agent", Capability::AgentControl, Some(&id))?;
+
+    let agent = state.agent_service.restore_agent(&id)?;
     state
+        .change_feed
+        .updated(EntityKind::Agent, &agent.id, &agent);
+    Ok(agent)
+}
+
+/// Move an agent to another worktree
+#[tauri::command]
+pub async fn move_agent(
+    agent_id: String,
+    target_worktree_id: String,
+    state: State<'_, AppState>,
+) -> AppResult<Agent> {
+    authorize(
+        &state,
+        "move_agent",
+        Capability::AgentControl,
+        Some(&agent_id),
+    )?;
+
+    let agent = state
         .agent_service
-        .restore_agent(&id)
-        .map_err(|e| e.to_string())
+        .move_agent(&agent_id, &target_worktree_id)?;
+    state
+        .change_feed
+        .updated(EntityKind::Agent, &agent.id, &agent);
+    Ok(agent)
 }
 
 /// Reorder agents
@@ -136,9 +621,21 @@ pub async fn reorder_agents(
     worktree_id: String,
     input: ReorderAgentsInput,
     state: State<'_, AppState>,
-) -> Result<Vec<Agent>, String> {
-    state
+) -> AppResult<Vec<Agent>> {
+    authorize(
+        &state,
+        "reorder_agents",
+        Capability::AgentControl,
+        Some(&worktree_id),
+    )?;
+
+    let agents = state
         .agent_service
-        .reorder_agents(&worktree_id, &input.agent_ids)
-        .map_err(|e| e.to_string())
+        .reorder_agents(&worktree_id, &input.agent_ids)?;
+    for agent in &agents {
+        state
+            .change_feed
+            .updated(EntityKind::Agent, &agent.id, agent);
+    }
+    Ok(agents)
 }
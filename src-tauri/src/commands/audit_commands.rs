@@ -0,0 +1,41 @@
+//! Command authorization and audit log Tauri commands
+
+use tauri::State;
+
+use super::run_blocking;
+use crate::error::{AppError, AppResult};
+use crate::services::CallerContext;
+use crate::types::{AuditEntry, Capability};
+use crate::AppState;
+
+/// Audit entries returned when no limit is given
+const DEFAULT_AUDIT_LOG_LIMIT: usize = 100;
+
+/// List the latest privileged commands, allowed or refused, most recent first
+#[tauri::command]
+pub async fn get_audit_log(
+    limit: Option<usize>,
+    state: State<'_, AppState>,
+) -> AppResult<Vec<AuditEntry>> {
+    authorize(&state, "get_audit_log", Capability::Read, None)?;
+
+    let authorization_service = state.authorization_service.clone();
+    run_blocking(move || {
+        authorization_service.get_audit_log(limit.unwrap_or(DEFAULT_AUDIT_LOG_LIMIT))
+    })
+    .await
+}
+
+/// Check the app window may run a command, auditing privileged ones.
+/// Every command calls this first with the capability it needs.
+pub(crate) fn authorize(
+    state: &AppState,
+    command: &str,
+    capability: Capability,
+    target_id: Option<&str>,
+) -> AppResult<()> {
+    state
+        .authorization_service
+        .authorize(&CallerContext::desktop(), command, capability, target_id)
+        .map_err(AppError::from)
+}
@@ -0,0 +1,94 @@
+//! Database backup and migration Tauri commands
+
+use std::path::PathBuf;
+
+use tauri::State;
+
+use super::audit_commands::authorize;
+use super::run_blocking;
+use crate::db::default_nodejs_db_path;
+use crate::error::{AppError, AppResult};
+use crate::services::BackupError;
+use crate::types::{BackupInfo, Capability, Job, RestoreResult};
+use crate::AppState;
+
+/// Create a backup of the application database
+#[tauri::command]
+pub async fn backup_app_database(state: State<'_, AppState>) -> AppResult<BackupInfo> {
+    authorize(&state, "backup_app_database", Capability::Settings, None)?;
+
+    state
+        .backup_service
+        .create_backup()
+        .map_err(AppError::from)
+}
+
+/// Restore the application database from a backup file
+///
+/// Stops all running agents and keeps a snapshot of the current data first.
+#[tauri::command]
+pub async fn restore_app_database(
+    path: String,
+    state: State<'_, AppState>,
+) -> AppResult<RestoreResult> {
+    authorize(&state, "restore_app_database", Capability::Settings, None)?;
+
+    state
+        .backup_service
+        .restore_backup(&PathBuf::from(path))
+        .map_err(AppError::from)
+}
+
+/// Import the Node.js backend's database as a job whose result is a
+/// `LegacyMigrationResult`. Reads from where that backend kept its database
+/// unless `source_path` is given; a dry run only reports what it would import.
+#[tauri::command]
+pub async fn run_legacy_migration(
+    source_path: Option<String>,
+    dry_run: Option<bool>,
+    state: State<'_, AppState>,
+) -> AppResult<Job> {
+    authorize(&state, "run_legacy_migration", Capability::Settings, None)?;
+
+    let source_path = legacy_source_path(source_path)?;
+    let dry_run = dry_run.unwrap_or(false);
+    let backup_service = state.backup_service.clone();
+    let job = state
+        .job_service
+        .spawn("legacy_migration", None, move |job| {
+            let result = backup_service
+                .migrate_legacy(&source_path, dry_run, |percent, message| {
+                    job.progress(percent, message)
+                })
+                .map_err(|e| e.to_string())?;
+            serde_json::to_value(&result).map_err(|e| e.to_string())
+        })?;
+    Ok(job)
+}
+
+/// Check the application database against the Node.js backend's, returning
+/// the problems found
+#[tauri::command]
+pub async fn verify_legacy_migration(
+    source_path: Option<String>,
+    state: State<'_, AppState>,
+) -> AppResult<Vec<String>> {
+    authorize(&state, "verify_legacy_migration", Capability::Read, None)?;
+
+    let source_path = legacy_source_path(source_path)?;
+    let backup_service = state.backup_service.clone();
+    run_blocking(move || backup_service.verify_legacy(&source_path)).await
+}
+
+/// The Node.js database to migrate from, failing before any job is queued
+/// when it doesn't exist
+fn legacy_source_path(source_path: Option<String>) -> AppResult<PathBuf> {
+    let path = source_path
+        .filter(|path| !path.trim().is_empty())
+        .map(PathBuf::from)
+        .unwrap_or_else(default_nodejs_db_path);
+    if !path.exists() {
+        return Err(BackupError::SourceNotFound(path.display().to_string()).into());
+    }
+    Ok(path)
+}
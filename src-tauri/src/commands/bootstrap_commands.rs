@@ -0,0 +1,73 @@
+//! Worktree bootstrap Tauri commands
+
+use tauri::State;
+
+use super::audit_commands::authorize;
+use crate::error::{AppError, AppResult};
+use crate::types::{BootstrapConfig, Capability, WorktreeBootstrap};
+use crate::AppState;
+
+/// Get the commands a workspace runs in each new worktree
+#[tauri::command]
+pub async fn get_bootstrap_config(
+    workspace_id: String,
+    state: State<'_, AppState>,
+) -> AppResult<BootstrapConfig> {
+    authorize(&state, "get_bootstrap_config", Capability::Read, None)?;
+
+    state
+        .bootstrap_service
+        .get_config(&workspace_id)
+        .map_err(AppError::from)
+}
+
+/// Replace the commands a workspace runs in each new worktree
+#[tauri::command]
+pub async fn set_bootstrap_config(
+    workspace_id: String,
+    config: BootstrapConfig,
+    state: State<'_, AppState>,
+) -> AppResult<BootstrapConfig> {
+    authorize(
+        &state,
+        "set_bootstrap_config",
+        Capability::GitWrite,
+        Some(&workspace_id),
+    )?;
+
+    state
+        .bootstrap_service
+        .set_config(&workspace_id, config)
+        .map_err(AppError::from)
+}
+
+/// Get the latest bootstrap run of a worktree
+#[tauri::command]
+pub async fn get_worktree_bootstrap(
+    worktree_id: String,
+    state: State<'_, AppState>,
+) -> AppResult<Option<WorktreeBootstrap>> {
+    authorize(&state, "get_worktree_bootstrap", Capability::Read, None)?;
+
+    Ok(state.bootstrap_service.get_status(&worktree_id))
+}
+
+/// Run the workspace's bootstrap commands again in a worktree
+#[tauri::command]
+pub async fn run_worktree_bootstrap(
+    worktree_id: String,
+    state: State<'_, AppState>,
+) -> AppResult<Option<WorktreeBootstrap>> {
+    authorize(
+        &state,
+        "run_worktree_bootstrap",
+        Capability::GitWrite,
+        Some(&worktree_id),
+    )?;
+
+    let worktree = state.worktree_service.get_worktree(&worktree_id)?;
+    state
+        .bootstrap_service
+        .run(&worktree)
+        .map_err(AppError::from)
+}
@@ -0,0 +1,51 @@
+//! CLAUDE.md memory file Tauri commands
+
+use tauri::State;
+
+use super::audit_commands::authorize;
+use crate::error::{AppError, AppResult};
+use crate::types::{Capability, ClaudeMdFile, ClaudeMdResponse, ClaudeMdScope};
+use crate::AppState;
+
+/// Read a worktree's CLAUDE.md and .claude/CLAUDE.local.md
+#[tauri::command]
+pub async fn get_claude_md(
+    worktree_id: String,
+    state: State<'_, AppState>,
+) -> AppResult<ClaudeMdResponse> {
+    authorize(&state, "get_claude_md", Capability::Read, None)?;
+
+    state
+        .claude_md_service
+        .get_files(&worktree_id)
+        .map(|files| ClaudeMdResponse { files })
+        .map_err(AppError::from)
+}
+
+/// Write one of a worktree's memory files, failing with CONFLICT if it
+/// changed after `modified_at` was read
+#[tauri::command]
+pub async fn update_claude_md(
+    worktree_id: String,
+    content: String,
+    scope: Option<ClaudeMdScope>,
+    modified_at: Option<i64>,
+    state: State<'_, AppState>,
+) -> AppResult<ClaudeMdFile> {
+    authorize(
+        &state,
+        "update_claude_md",
+        Capability::GitWrite,
+        Some(&worktree_id),
+    )?;
+
+    state
+        .claude_md_service
+        .update_file(
+            &worktree_id,
+            scope.unwrap_or_default(),
+            &content,
+            modified_at,
+        )
+        .map_err(AppError::from)
+}
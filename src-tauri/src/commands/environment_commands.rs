@@ -0,0 +1,128 @@
+//! Environment check Tauri commands
+
+use tauri::State;
+
+use super::audit_commands::authorize;
+use crate::error::{AppError, AppResult};
+use crate::services::ClaudeApiService;
+use crate::types::{Capability, ClaudeAuthStatus, ClaudeCliStatus, ClaudeLogin, NetworkStatus};
+use crate::AppState;
+
+/// Check that the Claude CLI is installed, supported and logged in
+#[tauri::command]
+pub async fn check_claude_cli(state: State<'_, AppState>) -> AppResult<ClaudeCliStatus> {
+    authorize(&state, "check_claude_cli", Capability::Read, None)?;
+
+    Ok(state.environment_service.check_claude_cli().await)
+}
+
+/// Check the login of the default account, or of a profile's
+#[tauri::command]
+pub async fn check_claude_auth(
+    profile_id: Option<String>,
+    state: State<'_, AppState>,
+) -> AppResult<ClaudeAuthStatus> {
+    authorize(&state, "check_claude_auth", Capability::Read, None)?;
+
+    let config_dir = state
+        .profile_service
+        .config_dir(profile_id.as_deref())
+        .map_err(AppError::from)?;
+    let service = match config_dir {
+        Some(dir) => ClaudeApiService::new().with_config_dir(dir),
+        None => ClaudeApiService::new(),
+    };
+    if !state.network_status_service.is_online() {
+        return Ok(service.check_auth_offline());
+    }
+    Ok(service.check_auth().await)
+}
+
+/// Start `claude login` in a managed terminal for the default account, or a profile's
+#[tauri::command]
+pub async fn start_claude_login(
+    profile_id: Option<String>,
+    state: State<'_, AppState>,
+) -> AppResult<ClaudeLogin> {
+    authorize(
+        &state,
+        "start_claude_login",
+        Capability::Settings,
+        profile_id.as_deref(),
+    )?;
+
+    let profile = profile_id
+        .map(|id| state.profile_service.get_profile(&id))
+        .transpose()
+        .map_err(AppError::from)?;
+    state
+        .claude_login_service
+        .start_login(profile.as_ref())
+        .map_err(AppError::from)
+}
+
+/// Get the latest login of an account with its output
+#[tauri::command]
+pub async fn get_claude_login(
+    profile_id: Option<String>,
+    state: State<'_, AppState>,
+) -> AppResult<Option<ClaudeLogin>> {
+    authorize(&state, "get_claude_login", Capability::Read, None)?;
+
+    Ok(state.claude_login_service.get_login(profile_id.as_deref()))
+}
+
+/// Type an answer into a running login, e.g. the code from the browser
+#[tauri::command]
+pub async fn send_claude_login_input(
+    profile_id: Option<String>,
+    input: String,
+    state: State<'_, AppState>,
+) -> AppResult<()> {
+    authorize(
+        &state,
+        "send_claude_login_input",
+        Capability::Settings,
+        profile_id.as_deref(),
+    )?;
+
+    state
+        .claude_login_service
+        .send_input(profile_id.as_deref(), &input)
+        .map_err(AppError::from)
+}
+
+/// Stop a running login
+#[tauri::command]
+pub async fn cancel_claude_login(
+    profile_id: Option<String>,
+    state: State<'_, AppState>,
+) -> AppResult<ClaudeLogin> {
+    authorize(
+        &state,
+        "cancel_claude_login",
+        Capability::Settings,
+        profile_id.as_deref(),
+    )?;
+
+    state
+        .claude_login_service
+        .cancel_login(profile_id.as_deref())
+        .map_err(AppError::from)
+}
+
+/// Whether the app can reach the network, as of the last probe
+#[tauri::command]
+pub async fn get_network_status(state: State<'_, AppState>) -> AppResult<NetworkStatus> {
+    authorize(&state, "get_network_status", Capability::Read, None)?;
+
+    Ok(state.network_status_service.status())
+}
+
+/// Probe the network now, e.g. when the user asks to retry
+#[tauri::command]
+pub async fn check_network_status(state: State<'_, AppState>) -> AppResult<NetworkStatus> {
+    authorize(&state, "check_network_status", Capability::Read, None)?;
+
+    Ok(state.network_status_service.check().await)
+}
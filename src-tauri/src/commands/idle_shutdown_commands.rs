@@ -0,0 +1,88 @@
+//! Idle auto-shutdown Tauri commands
+
+use tauri::State;
+
+use super::audit_commands::authorize;
+use crate::error::{AppError, AppResult};
+use crate::types::{AgentIdlePolicy, Capability, IdleShutdown, IdleShutdownPolicy};
+use crate::AppState;
+
+/// Get the idle shutdown policy of agents without one of their own
+#[tauri::command]
+pub async fn get_idle_shutdown_policy(state: State<'_, AppState>) -> AppResult<IdleShutdownPolicy> {
+    authorize(&state, "get_idle_shutdown_policy", Capability::Read, None)?;
+
+    state
+        .idle_shutdown_service
+        .get_policy()
+        .map_err(AppError::from)
+}
+
+/// Set the idle shutdown policy of agents without one of their own
+#[tauri::command]
+pub async fn set_idle_shutdown_policy(
+    policy: IdleShutdownPolicy,
+    state: State<'_, AppState>,
+) -> AppResult<IdleShutdownPolicy> {
+    authorize(
+        &state,
+        "set_idle_shutdown_policy",
+        Capability::Settings,
+        None,
+    )?;
+
+    state
+        .idle_shutdown_service
+        .set_policy(policy)
+        .map_err(AppError::from)
+}
+
+/// Get an agent's idle shutdown policy and the one it runs with
+#[tauri::command]
+pub async fn get_agent_idle_shutdown_policy(
+    agent_id: String,
+    state: State<'_, AppState>,
+) -> AppResult<AgentIdlePolicy> {
+    authorize(
+        &state,
+        "get_agent_idle_shutdown_policy",
+        Capability::Read,
+        None,
+    )?;
+
+    state
+        .idle_shutdown_service
+        .get_agent_policy(&agent_id)
+        .map_err(AppError::from)
+}
+
+/// Give an agent its own idle shutdown policy, or None to follow the global one
+#[tauri::command]
+pub async fn set_agent_idle_shutdown_policy(
+    agent_id: String,
+    policy: Option<IdleShutdownPolicy>,
+    state: State<'_, AppState>,
+) -> AppResult<AgentIdlePolicy> {
+    authorize(
+        &state,
+        "set_agent_idle_shutdown_policy",
+        Capability::AgentControl,
+        Some(&agent_id),
+    )?;
+
+    state
+        .idle_shutdown_service
+        .set_agent_policy(&agent_id, policy)
+        .map_err(AppError::from)
+}
+
+/// List agents stopped for sitting idle; starting one resumes its session
+#[tauri::command]
+pub async fn list_idle_shutdowns(state: State<'_, AppState>) -> AppResult<Vec<IdleShutdown>> {
+    authorize(&state, "list_idle_shutdowns", Capability::Read, None)?;
+
+    state
+        .idle_shutdown_service
+        .list_shutdowns()
+        .map_err(AppError::from)
+}
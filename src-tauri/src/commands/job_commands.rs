@@ -0,0 +1,24 @@
+//! Background job Tauri commands
+
+use tauri::State;
+
+use super::audit_commands::authorize;
+use crate::error::{AppError, AppResult};
+use crate::types::{Capability, Job};
+use crate::AppState;
+
+/// Get a job started by a long-running command
+#[tauri::command]
+pub async fn get_job(id: String, state: State<'_, AppState>) -> AppResult<Job> {
+    authorize(&state, "get_job", Capability::Read, None)?;
+
+    state.job_service.get_job(&id).map_err(AppError::from)
+}
+
+/// Ask a job to stop; it ends cancelled once its work notices
+#[tauri::command]
+pub async fn cancel_job(id: String, state: State<'_, AppState>) -> AppResult<Job> {
+    authorize(&state, "cancel_job", Capability::GitWrite, Some(&id))?;
+
+    state.job_service.cancel_job(&id).map_err(AppError::from)
+}
@@ -0,0 +1,27 @@
+//! App log Tauri commands
+
+use tauri::State;
+
+use super::audit_commands::authorize;
+use crate::error::AppResult;
+use crate::types::{Capability, LogLevel, LogListResponse};
+use crate::AppState;
+
+/// Records returned when no limit is given
+const DEFAULT_LOG_LIMIT: usize = 500;
+
+/// Recent backend log records at `level` or more severe, oldest first
+#[tauri::command]
+pub async fn get_app_logs(
+    level: Option<LogLevel>,
+    limit: Option<usize>,
+    state: State<'_, AppState>,
+) -> AppResult<LogListResponse> {
+    authorize(&state, "get_app_logs", Capability::Read, None)?;
+
+    let records = state.log_service.recent(
+        level.unwrap_or(LogLevel::Trace),
+        limit.unwrap_or(DEFAULT_LOG_LIMIT),
+    );
+    Ok(LogListResponse { records })
+}
@@ -0,0 +1,62 @@
+//! MCP server configuration Tauri commands
+
+use std::collections::BTreeMap;
+
+use tauri::State;
+
+use super::audit_commands::authorize;
+use crate::error::{AppError, AppResult};
+use crate::types::{Capability, McpServerCheckResponse, McpServerConfig, McpServerListResponse};
+use crate::AppState;
+
+/// List the MCP servers configured in a worktree's `.mcp.json`
+#[tauri::command]
+pub async fn list_mcp_servers(
+    worktree_id: String,
+    state: State<'_, AppState>,
+) -> AppResult<McpServerListResponse> {
+    authorize(&state, "list_mcp_servers", Capability::Read, None)?;
+
+    state
+        .mcp_service
+        .list_servers(&worktree_id)
+        .map(|servers| McpServerListResponse { servers })
+        .map_err(AppError::from)
+}
+
+/// Replace a worktree's MCP servers, keyed by server name
+#[tauri::command]
+pub async fn set_mcp_servers(
+    worktree_id: String,
+    config: BTreeMap<String, McpServerConfig>,
+    state: State<'_, AppState>,
+) -> AppResult<McpServerListResponse> {
+    authorize(
+        &state,
+        "set_mcp_servers",
+        Capability::GitWrite,
+        Some(&worktree_id),
+    )?;
+
+    state
+        .mcp_service
+        .set_servers(&worktree_id, config)
+        .map(|servers| McpServerListResponse { servers })
+        .map_err(AppError::from)
+}
+
+/// Check that each of a worktree's MCP servers launches
+#[tauri::command]
+pub async fn check_mcp_servers(
+    worktree_id: String,
+    state: State<'_, AppState>,
+) -> AppResult<McpServerCheckResponse> {
+    authorize(&state, "check_mcp_servers", Capability::Read, None)?;
+
+    state
+        .mcp_service
+        .check_servers(&worktree_id)
+        .await
+        .map(|results| McpServerCheckResponse { results })
+        .map_err(AppError::from)
+}
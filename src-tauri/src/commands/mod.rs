@@ -3,11 +3,75 @@
 //! This module contains all the IPC command handlers that are called from the frontend.
 
 pub mod agent_commands;
+pub mod audit_commands;
+pub mod backup_commands;
+pub mod bootstrap_commands;
+pub mod claude_md_commands;
+pub mod environment_commands;
+pub mod idle_shutdown_commands;
+pub mod job_commands;
+pub mod log_commands;
+pub mod mcp_commands;
+pub mod onboarding_commands;
+pub mod profile_commands;
+pub mod resource_commands;
+pub mod schedule_commands;
+pub mod session_commands;
+pub mod settings_commands;
+pub mod settings_sync_commands;
+pub mod snippet_commands;
+pub mod task_group_commands;
 pub mod usage_commands;
 pub mod workspace_commands;
 pub mod worktree_commands;
 
 pub use agent_commands::*;
+pub use audit_commands::*;
+pub use backup_commands::*;
+pub use bootstrap_commands::*;
+pub use claude_md_commands::*;
+pub use environment_commands::*;
+pub use idle_shutdown_commands::*;
+pub use job_commands::*;
+pub use log_commands::*;
+pub use mcp_commands::*;
+pub use onboarding_commands::*;
+pub use profile_commands::*;
+pub use resource_commands::*;
+pub use schedule_commands::*;
+pub use session_commands::*;
+pub use settings_commands::*;
+pub use settings_sync_commands::*;
+pub use snippet_commands::*;
+pub use task_group_commands::*;
 pub use usage_commands::*;
 pub use workspace_commands::*;
 pub use worktree_commands::*;
+
+use crate::error::{AppError, AppResult};
+use crate::AppState;
+
+/// Run blocking work, such as a heavy SQLite query or a git fetch, on the
+/// blocking thread pool. Commands run on the async runtime that also carries
+/// events, so a slow query called directly would hold those up.
+pub(crate) async fn run_blocking<T, E, F>(work: F) -> AppResult<T>
+where
+    F: FnOnce() -> Result<T, E> + Send + 'static,
+    T: Send + 'static,
+    E: Into<AppError> + Send + 'static,
+{
+    tokio::task::spawn_blocking(work)
+        .await
+        .map_err(|e| AppError::Internal(e.to_string()))?
+        .map_err(Into::into)
+}
+
+/// Refuse `action` while the network is down, so it fails at once instead of
+/// after a connection timeout
+pub(crate) fn require_online(state: &AppState, action: &str) -> AppResult<()> {
+    if state.network_status_service.is_online() {
+        Ok(())
+    } else {
+        Err(AppError::Offline(format!("Can't {} while offline", action)))
+    }
+}
@@ -0,0 +1,40 @@
+//! First-run setup Tauri commands
+
+use tauri::State;
+
+use super::audit_commands::authorize;
+use crate::error::{AppError, AppResult};
+use crate::types::{Capability, OnboardingState, OnboardingStep};
+use crate::AppState;
+
+/// Check setup prerequisites and saved wizard progress
+#[tauri::command]
+pub async fn get_onboarding_state(state: State<'_, AppState>) -> AppResult<OnboardingState> {
+    authorize(&state, "get_onboarding_state", Capability::Read, None)?;
+
+    state
+        .onboarding_service
+        .get_state()
+        .await
+        .map_err(AppError::from)
+}
+
+/// Mark a setup step finished or skipped
+#[tauri::command]
+pub async fn complete_onboarding_step(
+    step: OnboardingStep,
+    state: State<'_, AppState>,
+) -> AppResult<OnboardingState> {
+    authorize(
+        &state,
+        "complete_onboarding_step",
+        Capability::Settings,
+        None,
+    )?;
+
+    state
+        .onboarding_service
+        .complete_step(step)
+        .await
+        .map_err(AppError::from)
+}
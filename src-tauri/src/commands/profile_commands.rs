@@ -0,0 +1,127 @@
+//! Claude account profile Tauri commands
+
+use tauri::State;
+
+use super::audit_commands::authorize;
+use crate::error::{AppError, AppResult};
+use crate::types::{Capability, ClaudeProfile, ProfileSelection, SaveProfileInput};
+use crate::AppState;
+
+/// List Claude account profiles
+#[tauri::command]
+pub async fn list_claude_profiles(state: State<'_, AppState>) -> AppResult<Vec<ClaudeProfile>> {
+    authorize(&state, "list_claude_profiles", Capability::Read, None)?;
+
+    state
+        .profile_service
+        .list_profiles()
+        .map_err(AppError::from)
+}
+
+/// Create a profile, or replace the one with the input's ID
+#[tauri::command]
+pub async fn save_claude_profile(
+    input: SaveProfileInput,
+    state: State<'_, AppState>,
+) -> AppResult<ClaudeProfile> {
+    authorize(
+        &state,
+        "save_claude_profile",
+        Capability::Settings,
+        input.id.as_deref(),
+    )?;
+
+    state
+        .profile_service
+        .save_profile(input)
+        .map_err(AppError::from)
+}
+
+/// Delete a profile; workspaces and agents using it fall back to the default account
+#[tauri::command]
+pub async fn delete_claude_profile(id: String, state: State<'_, AppState>) -> AppResult<()> {
+    authorize(
+        &state,
+        "delete_claude_profile",
+        Capability::Settings,
+        Some(&id),
+    )?;
+
+    state
+        .profile_service
+        .delete_profile(&id)
+        .map_err(AppError::from)
+}
+
+/// Get the profile a workspace's agents run under
+#[tauri::command]
+pub async fn get_workspace_claude_profile(
+    workspace_id: String,
+    state: State<'_, AppState>,
+) -> AppResult<ProfileSelection> {
+    authorize(
+        &state,
+        "get_workspace_claude_profile",
+        Capability::Read,
+        None,
+    )?;
+
+    state
+        .profile_service
+        .get_workspace_profile(&workspace_id)
+        .map_err(AppError::from)
+}
+
+/// Choose the profile a workspace's agents run under; omit it for the default account
+#[tauri::command]
+pub async fn set_workspace_claude_profile(
+    workspace_id: String,
+    profile_id: Option<String>,
+    state: State<'_, AppState>,
+) -> AppResult<ProfileSelection> {
+    authorize(
+        &state,
+        "set_workspace_claude_profile",
+        Capability::Settings,
+        Some(&workspace_id),
+    )?;
+
+    state
+        .profile_service
+        .set_workspace_profile(&workspace_id, profile_id.as_deref())
+        .map_err(AppError::from)
+}
+
+/// Get the profile an agent runs under
+#[tauri::command]
+pub async fn get_agent_claude_profile(
+    agent_id: String,
+    state: State<'_, AppState>,
+) -> AppResult<ProfileSelection> {
+    authorize(&state, "get_agent_claude_profile", Capability::Read, None)?;
+
+    state
+        .profile_service
+        .get_agent_profile(&agent_id)
+        .map_err(AppError::from)
+}
+
+/// Choose the profile an agent runs under from its next start; omit it to use its workspace's
+#[tauri::command]
+pub async fn set_agent_claude_profile(
+    agent_id: String,
+    profile_id: Option<String>,
+    state: State<'_, AppState>,
+) -> AppResult<ProfileSelection> {
+    authorize(
+        &state,
+        "set_agent_claude_profile",
+        Capability::AgentControl,
+        Some(&agent_id),
+    )?;
+
+    state
+        .profile_service
+        .set_agent_profile(&agent_id, profile_id.as_deref())
+        .map_err(AppError::from)
+}
@@ -0,0 +1,44 @@
+//! Agent process resource Tauri commands
+
+use tauri::State;
+
+use super::audit_commands::authorize;
+use crate::error::{AppError, AppResult};
+use crate::types::{AgentResources, Capability, ResourceLimits};
+use crate::AppState;
+
+/// Get the latest memory and CPU sample of an agent's process, if it runs
+#[tauri::command]
+pub async fn get_agent_resources(
+    agent_id: String,
+    state: State<'_, AppState>,
+) -> AppResult<Option<AgentResources>> {
+    authorize(&state, "get_agent_resources", Capability::Read, None)?;
+
+    Ok(state.resource_monitor_service.get_resources(&agent_id))
+}
+
+/// Get the memory and CPU limits agents are warned about
+#[tauri::command]
+pub async fn get_resource_limits(state: State<'_, AppState>) -> AppResult<ResourceLimits> {
+    authorize(&state, "get_resource_limits", Capability::Read, None)?;
+
+    state
+        .resource_monitor_service
+        .get_limits()
+        .map_err(AppError::from)
+}
+
+/// Set the memory and CPU limits agents are warned about
+#[tauri::command]
+pub async fn set_resource_limits(
+    limits: ResourceLimits,
+    state: State<'_, AppState>,
+) -> AppResult<ResourceLimits> {
+    authorize(&state, "set_resource_limits", Capability::Settings, None)?;
+
+    state
+        .resource_monitor_service
+        .set_limits(limits)
+        .map_err(AppError::from)
+}
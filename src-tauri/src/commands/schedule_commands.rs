@@ -0,0 +1,124 @@
+//! Schedule Tauri commands
+
+use tauri::State;
+
+use super::audit_commands::authorize;
+use crate::error::{AppError, AppResult};
+use crate::types::{
+    Capability, CreateScheduleInput, QuietHours, Schedule, ScheduleListResponse, SchedulerStatus,
+};
+use crate::AppState;
+
+/// List all schedules, or those of one worktree
+#[tauri::command]
+pub async fn list_schedules(
+    worktree_id: Option<String>,
+    state: State<'_, AppState>,
+) -> AppResult<ScheduleListResponse> {
+    authorize(&state, "list_schedules", Capability::Read, None)?;
+
+    state
+        .schedule_service
+        .list_schedules(worktree_id.as_deref())
+        .map(|schedules| ScheduleListResponse { schedules })
+        .map_err(AppError::from)
+}
+
+/// Create a schedule that starts a new agent with a prompt whenever its cron expression matches
+#[tauri::command]
+pub async fn create_schedule(
+    input: CreateScheduleInput,
+    state: State<'_, AppState>,
+) -> AppResult<Schedule> {
+    authorize(
+        &state,
+        "create_schedule",
+        Capability::AgentControl,
+        Some(&input.worktree_id),
+    )?;
+
+    state
+        .schedule_service
+        .create_schedule(input)
+        .map_err(AppError::from)
+}
+
+/// Pause a schedule, or resume it from its next matching time
+#[tauri::command]
+pub async fn set_schedule_paused(
+    id: String,
+    paused: bool,
+    state: State<'_, AppState>,
+) -> AppResult<Schedule> {
+    authorize(
+        &state,
+        "set_schedule_paused",
+        Capability::AgentControl,
+        Some(&id),
+    )?;
+
+    state
+        .schedule_service
+        .set_paused(&id, paused)
+        .map_err(AppError::from)
+}
+
+/// Get the quiet hours policy and whether due schedules are being held back
+#[tauri::command]
+pub async fn get_scheduler_status(state: State<'_, AppState>) -> AppResult<SchedulerStatus> {
+    authorize(&state, "get_scheduler_status", Capability::Read, None)?;
+
+    state
+        .schedule_service
+        .get_status(chrono::Utc::now())
+        .map_err(AppError::from)
+}
+
+/// Set the daily window in which scheduled agents are not started, or clear it
+#[tauri::command]
+pub async fn set_quiet_hours(
+    quiet_hours: Option<QuietHours>,
+    state: State<'_, AppState>,
+) -> AppResult<SchedulerStatus> {
+    authorize(&state, "set_quiet_hours", Capability::Settings, None)?;
+
+    state
+        .schedule_service
+        .set_quiet_hours(quiet_hours)
+        .map_err(AppError::from)
+}
+
+/// Start scheduled agents during quiet hours anyway
+#[tauri::command]
+pub async fn set_quiet_hours_override(
+    enabled: bool,
+    state: State<'_, AppState>,
+) -> AppResult<SchedulerStatus> {
+    authorize(
+        &state,
+        "set_quiet_hours_override",
+        Capability::Settings,
+        None,
+    )?;
+
+    state
+        .schedule_service
+        .set_quiet_hours_override(enabled)
+        .map_err(AppError::from)
+}
+
+/// Delete a schedule; agents it started are kept
+#[tauri::command]
+pub async fn delete_schedule(id: String, state: State<'_, AppState>) -> AppResult<()> {
+    authorize(
+        &state,
+        "delete_schedule",
+        Capability::AgentControl,
+        Some(&id),
+    )?;
+
+    state
+        .schedule_service
+        .delete_schedule(&id)
+        .map_err(AppError::from)
+}
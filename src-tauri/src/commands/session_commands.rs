@@ -0,0 +1,65 @@
+//! Agent session snapshot Tauri commands
+
+use tauri::State;
+
+use super::audit_commands::authorize;
+use crate::error::{AppError, AppResult};
+use crate::types::{Agent, AgentSnapshot, AgentSnapshotListResponse, Capability, EntityKind};
+use crate::AppState;
+
+/// Snapshot an agent's session now; returns None when nothing changed since the last one
+#[tauri::command]
+pub async fn snapshot_agent_session(
+    agent_id: String,
+    state: State<'_, AppState>,
+) -> AppResult<Option<AgentSnapshot>> {
+    authorize(
+        &state,
+        "snapshot_agent_session",
+        Capability::AgentControl,
+        Some(&agent_id),
+    )?;
+
+    state
+        .snapshot_service
+        .snapshot_agent(&agent_id)
+        .map_err(AppError::from)
+}
+
+/// List an agent's session snapshots, newest first
+#[tauri::command]
+pub async fn list_agent_snapshots(
+    agent_id: String,
+    state: State<'_, AppState>,
+) -> AppResult<AgentSnapshotListResponse> {
+    authorize(&state, "list_agent_snapshots", Capability::Read, None)?;
+
+    state
+        .snapshot_service
+        .list_snapshots(&agent_id)
+        .map(|snapshots| AgentSnapshotListResponse { snapshots })
+        .map_err(AppError::from)
+}
+
+/// Fork a new agent that resumes the session captured in a snapshot
+#[tauri::command]
+pub async fn restore_agent_from_snapshot(
+    snapshot_id: String,
+    name: Option<String>,
+    state: State<'_, AppState>,
+) -> AppResult<Agent> {
+    authorize(
+        &state,
+        "restore_agent_from_snapshot",
+        Capability::AgentControl,
+        Some(&snapshot_id),
+    )?;
+
+    let agent = state
+        .snapshot_service
+        .restore_from_snapshot(&snapshot_id, name)?;
+    state
+        .change_feed
+        .created(EntityKind::Agent, &agent.id, &agent);
+    Ok(agent)
+}
@@ -0,0 +1,74 @@
+//! Settings-related Tauri commands
+
+use tauri::State;
+
+use super::audit_commands::authorize;
+use crate::error::{AppError, AppResult};
+use crate::types::{Capability, RemoteAccessSettings, UpdateRemoteAccessInput};
+use crate::AppState;
+
+/// Get remote access settings, including the access token to share with clients
+#[tauri::command]
+pub async fn get_remote_access_settings(
+    state: State<'_, AppState>,
+) -> AppResult<RemoteAccessSettings> {
+    authorize(&state, "get_remote_access_settings", Capability::Read, None)?;
+
+    state
+        .remote_access_service
+        .get_settings()
+        .map_err(AppError::from)
+}
+
+/// Update remote access settings (applied on next launch)
+#[tauri::command]
+pub async fn update_remote_access_settings(
+    input: UpdateRemoteAccessInput,
+    state: State<'_, AppState>,
+) -> AppResult<RemoteAccessSettings> {
+    authorize(
+        &state,
+        "update_remote_access_settings",
+        Capability::Settings,
+        None,
+    )?;
+
+    state
+        .remote_access_service
+        .update_settings(input)
+        .map_err(AppError::from)
+}
+
+/// Store the GitHub token used to open pull requests (empty to use the gh CLI)
+#[tauri::command]
+pub async fn set_github_token(token: String, state: State<'_, AppState>) -> AppResult<()> {
+    authorize(&state, "set_github_token", Capability::Settings, None)?;
+
+    state
+        .pull_request_service
+        .set_github_token(&token)
+        .map_err(AppError::from)
+}
+
+/// Whether observer mode is on
+#[tauri::command]
+pub async fn get_observer_mode(state: State<'_, AppState>) -> AppResult<bool> {
+    authorize(&state, "get_observer_mode", Capability::Read, None)?;
+
+    state
+        .agent_service
+        .get_observer_mode()
+        .map_err(AppError::from)
+}
+
+/// Turn observer mode on or off. While on, agent output keeps streaming but
+/// terminals take no input and mutating commands are refused.
+#[tauri::command]
+pub async fn set_observer_mode(enabled: bool, state: State<'_, AppState>) -> AppResult<bool> {
+    authorize(&state, "set_observer_mode", Capability::Settings, None)?;
+
+    state
+        .agent_service
+        .set_observer_mode(enabled)
+        .map_err(AppError::from)
+}
@@ -0,0 +1,60 @@
+//! Commands for syncing manager hooks into worktree Claude settings
+
+use tauri::State;
+
+use super::audit_commands::authorize;
+use crate::error::{AppError, AppResult};
+use crate::types::{Capability, SettingsSyncResult, SharedHook};
+use crate::AppState;
+
+/// Get the hooks shared across all worktrees of a workspace
+#[tauri::command]
+pub async fn get_workspace_hooks(
+    workspace_id: String,
+    state: State<'_, AppState>,
+) -> AppResult<Vec<SharedHook>> {
+    authorize(
+        &state,
+        "get_workspace_hooks",
+        Capability::Read,
+        Some(&workspace_id),
+    )?;
+
+    state
+        .settings_sync_service
+        .get_shared_hooks(&workspace_id)
+        .map_err(AppError::from)
+}
+
+/// Replace a workspace's shared hooks and sync them into every worktree
+#[tauri::command]
+pub async fn set_workspace_hooks(
+    workspace_id: String,
+    hooks: Vec<SharedHook>,
+    state: State<'_, AppState>,
+) -> AppResult<Vec<SettingsSyncResult>> {
+    authorize(
+        &state,
+        "set_workspace_hooks",
+        Capability::Settings,
+        Some(&workspace_id),
+    )?;
+
+    state
+        .settings_sync_service
+        .set_shared_hooks(&workspace_id, hooks)
+        .map_err(AppError::from)
+}
+
+/// Remove every hook the manager installed, leaving user hooks untouched
+#[tauri::command]
+pub async fn remove_manager_hooks(
+    state: State<'_, AppState>,
+) -> AppResult<Vec<SettingsSyncResult>> {
+    authorize(&state, "remove_manager_hooks", Capability::Settings, None)?;
+
+    state
+        .settings_sync_service
+        .remove_all()
+        .map_err(AppError::from)
+}
@@ -0,0 +1,100 @@
+//! Snippet Tauri commands
+
+use std::collections::HashMap;
+
+use tauri::State;
+
+use super::audit_commands::authorize;
+use crate::error::{AppError, AppResult};
+use crate::types::{
+    Capability, CreateSnippetInput, Snippet, SnippetListResponse, UpdateSnippetInput,
+};
+use crate::AppState;
+
+/// List global snippets plus those scoped to a workspace or worktree
+#[tauri::command]
+pub async fn list_snippets(
+    workspace_id: Option<String>,
+    worktree_id: Option<String>,
+    state: State<'_, AppState>,
+) -> AppResult<SnippetListResponse> {
+    authorize(&state, "list_snippets", Capability::Read, None)?;
+
+    state
+        .snippet_service
+        .list_snippets(workspace_id.as_deref(), worktree_id.as_deref())
+        .map(|snippets| SnippetListResponse { snippets })
+        .map_err(AppError::from)
+}
+
+/// Create a new snippet
+#[tauri::command]
+pub async fn create_snippet(
+    input: CreateSnippetInput,
+    state: State<'_, AppState>,
+) -> AppResult<Snippet> {
+    authorize(&state, "create_snippet", Capability::AgentControl, None)?;
+
+    state
+        .snippet_service
+        .create_snippet(input)
+        .map_err(AppError::from)
+}
+
+/// Update a snippet's name or body
+#[tauri::command]
+pub async fn update_snippet(
+    id: String,
+    input: UpdateSnippetInput,
+    state: State<'_, AppState>,
+) -> AppResult<Snippet> {
+    authorize(
+        &state,
+        "update_snippet",
+        Capability::AgentControl,
+        Some(&id),
+    )?;
+
+    state
+        .snippet_service
+        .update_snippet(&id, input)
+        .map_err(AppError::from)
+}
+
+/// Delete a snippet
+#[tauri::command]
+pub async fn delete_snippet(id: String, state: State<'_, AppState>) -> AppResult<()> {
+    authorize(
+        &state,
+        "delete_snippet",
+        Capability::AgentControl,
+        Some(&id),
+    )?;
+
+    state
+        .snippet_service
+        .delete_snippet(&id)
+        .map_err(AppError::from)
+}
+
+/// Fill in a snippet's placeholders and type it into a running agent's
+/// terminal, returning the text that was sent
+#[tauri::command]
+pub async fn send_snippet_to_agent(
+    agent_id: String,
+    snippet_id: String,
+    vars: Option<HashMap<String, String>>,
+    state: State<'_, AppState>,
+) -> AppResult<String> {
+    authorize(
+        &state,
+        "send_snippet_to_agent",
+        Capability::AgentControl,
+        Some(&agent_id),
+    )?;
+
+    state
+        .snippet_service
+        .send_snippet_to_agent(&agent_id, &snippet_id, &vars.unwrap_or_default())
+        .map_err(AppError::from)
+}
@@ -0,0 +1,86 @@
+//! Task group Tauri commands
+
+use tauri::State;
+
+use crate::commands::audit_commands::authorize;
+use crate::commands::worktree_commands::{publish_workspace_details, sync_git_watchers};
+use crate::error::{AppError, AppResult};
+use crate::types::{
+    Capability, FanOutTaskInput, ResolveTaskGroupInput, TaskGroupComparison, TaskGroupDetails,
+};
+use crate::AppState;
+
+/// Launch the same prompt on several agents, each in its own worktree
+#[tauri::command]
+pub async fn fan_out_task(
+    input: FanOutTaskInput,
+    state: State<'_, AppState>,
+) -> AppResult<TaskGroupDetails> {
+    authorize(
+        &state,
+        "fan_out_task",
+        Capability::AgentControl,
+        Some(&input.workspace_id),
+    )?;
+
+    let result =
+        state
+            .task_group_service
+            .fan_out_task(&input.workspace_id, &input.prompt, input.count);
+    // Worktrees created before a failed attempt still need watching
+    sync_git_watchers(&state);
+    publish_workspace_details(&state, &input.workspace_id);
+    result.map_err(AppError::from)
+}
+
+/// Get a task group with the status of each attempt
+#[tauri::command]
+pub async fn get_task_group(id: String, state: State<'_, AppState>) -> AppResult<TaskGroupDetails> {
+    authorize(&state, "get_task_group", Capability::Read, None)?;
+
+    state
+        .task_group_service
+        .get_task_group(&id)
+        .map_err(AppError::from)
+}
+
+/// Compare the changes each attempt of a task group made
+#[tauri::command]
+pub async fn compare_task_group(
+    id: String,
+    state: State<'_, AppState>,
+) -> AppResult<TaskGroupComparison> {
+    authorize(&state, "compare_task_group", Capability::Read, None)?;
+
+    state
+        .task_group_service
+        .compare_task_group(&id)
+        .map_err(AppError::from)
+}
+
+/// Keep one attempt of a task group and delete the others' worktrees
+#[tauri::command]
+pub async fn resolve_task_group(
+    id: String,
+    input: ResolveTaskGroupInput,
+    state: State<'_, AppState>,
+) -> AppResult<TaskGroupDetails> {
+    authorize(
+        &state,
+        "resolve_task_group",
+        Capability::GitWrite,
+        Some(&id),
+    )?;
+
+    let result = state.task_group_service.resolve_task_group(
+        &id,
+        &input.winner_agent_id,
+        input.merge.unwrap_or(false),
+        input.force.unwrap_or(false),
+    );
+    sync_git_watchers(&state);
+    if let Ok(details) = &result {
+        publish_workspace_details(&state, &details.group.workspace_id);
+    }
+    result.map_err(AppError::from)
+}
@@ -2,9 +2,13 @@
 
 use tauri::State;
 
+use super::audit_commands::authorize;
+use super::{require_online, run_blocking};
+use crate::error::{AppError, AppResult};
 use crate::services::ClaudeApiService;
 use crate::types::{
-    ClaudeUsageSummary, UsageHistoryResponse, UsageLimits, UsagePeriod, UsageStats, UsageSummary,
+    BudgetStatus, BudgetThresholds, Capability, ClaudeUsageSummary, UsageEnforcement,
+    UsageHistoryResponse, UsageLimits, UsagePeriod, UsageStats, UsageSummary,
 };
 use crate::AppState;
 
@@ -12,11 +16,13 @@ use crate::AppState;
 #[tauri::command]
 pub async fn get_usage(
     state: State<'_, AppState>,
-) -> Result<UsageSummary, String> {
+) -> AppResult<UsageSummary> {
+    authorize(&state, "get_usage", Capability::Read, None)?;
+
     state
         .usage_service
         .get_usage_summary()
-        .map_err(|e| e.to_string())
+        .map_err(AppError::from)
 }
 
 /// Get usage history
@@ -25,43 +31,121 @@ pub async fn get_usage_history(
     period: Option<String>,
     limit: Option<usize>,
     state: State<'_, AppState>,
-) -> Result<UsageHistoryResponse, String> {
+) -> AppResult<UsageHistoryResponse> {
+    authorize(&state, "get_usage_history", Capability::Read, None)?;
+
     let period = period
         .map(|p| UsagePeriod::parse(&p))
         .unwrap_or(UsagePeriod::Daily);
 
-    state
-        .usage_service
-        .get_usage_history(period, limit.unwrap_or(30))
-        .map(|history| UsageHistoryResponse { history, period })
-        .map_err(|e| e.to_string())
+    let usage_service = state.usage_service.clone();
+    let history =
+        run_blocking(move || usage_service.get_usage_history(period, limit.unwrap_or(30))).await?;
+    Ok(UsageHistoryResponse { history, period })
 }
 
 /// Get today's usage
 #[tauri::command]
 pub async fn get_usage_today(
     state: State<'_, AppState>,
-) -> Result<UsageStats, String> {
+) -> AppResult<UsageStats> {
+    authorize(&state, "get_usage_today", Capability::Read, None)?;
+
     state
         .usage_service
         .get_today_usage()
-        .map_err(|e| e.to_string())
+        .map_err(AppError::from)
 }
 
 /// Get usage limits
 #[tauri::command]
 pub async fn get_usage_limits(
     state: State<'_, AppState>,
-) -> Result<UsageLimits, String> {
+) -> AppResult<UsageLimits> {
+    authorize(&state, "get_usage_limits", Capability::Read, None)?;
+
     state
         .usage_service
         .get_usage_limits()
-        .map_err(|e| e.to_string())
+        .map_err(AppError::from)
+}
+
+/// Get Claude API usage (fetches from Anthropic API), of a profile's account
+/// when one is given
+#[tauri::command]
+pub async fn get_claude_usage(
+    profile_id: Option<String>,
+    state: State<'_, AppState>,
+) -> AppResult<ClaudeUsageSummary> {
+    authorize(&state, "get_claude_usage", Capability::Read, None)?;
+    require_online(&state, "fetch Claude usage")?;
+
+    let config_dir = state
+        .profile_service
+        .config_dir(profile_id.as_deref())
+        .map_err(AppError::from)?;
+    let service = match config_dir {
+        Some(dir) => ClaudeApiService::new().with_config_dir(dir),
+        None => ClaudeApiService::new(),
+    };
+    let summary = service.fetch_usage().await.map_err(AppError::from)?;
+    // Budgets and the start cutoff track the default account
+    if profile_id.is_none() {
+        state
+            .usage_service
+            .record_claude_usage(summary.clone(), chrono::Utc::now());
+    }
+    Ok(summary)
+}
+
+/// Get current usage against the budget thresholds
+#[tauri::command]
+pub async fn get_budget_status(state: State<'_, AppState>) -> AppResult<BudgetStatus> {
+    authorize(&state, "get_budget_status", Capability::Read, None)?;
+
+    state
+        .usage_service
+        .get_budget_status()
+        .await
+        .map_err(AppError::from)
 }
 
-/// Get Claude API usage (fetches from Anthropic API)
+/// Set the budget thresholds; thresholds left out are not checked
 #[tauri::command]
-pub async fn get_claude_usage() -> Result<ClaudeUsageSummary, String> {
-    let service = ClaudeApiService::new();
-    service.fetch_usage().await.map_err(|e| e.to_string())
+pub async fn set_budget_thresholds(
+    thresholds: BudgetThresholds,
+    state: State<'_, AppState>,
+) -> AppResult<BudgetThresholds> {
+    authorize(&state, "set_budget_thresholds", Capability::Settings, None)?;
+
+    state
+        .usage_service
+        .set_budget_thresholds(thresholds)
+        .map_err(AppError::from)
+}
+
+/// Get whether agent starts are refused past a Claude usage cutoff
+#[tauri::command]
+pub async fn get_usage_enforcement(state: State<'_, AppState>) -> AppResult<UsageEnforcement> {
+    authorize(&state, "get_usage_enforcement", Capability::Read, None)?;
+
+    state
+        .usage_service
+        .get_usage_enforcement()
+        .map_err(AppError::from)
+}
+
+/// Refuse agent starts while Claude's five-hour or seven-day usage is past
+/// the cutoff
+#[tauri::command]
+pub async fn set_usage_enforcement(
+    enforcement: UsageEnforcement,
+    state: State<'_, AppState>,
+) -> AppResult<UsageEnforcement> {
+    authorize(&state, "set_usage_enforcement", Capability::Settings, None)?;
+
+    state
+        .usage_service
+        .set_usage_enforcement(enforcement)
+        .map_err(AppError::from)
 }
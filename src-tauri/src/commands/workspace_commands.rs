@@ -2,19 +2,27 @@
 
 use tauri::State;
 
-use crate::types::{CreateWorkspaceInput, Workspace, WorkspaceListResponse, WorkspaceWithDetails};
+use super::audit_commands::authorize;
+use super::worktree_commands::sync_git_watchers;
+use crate::error::{AppError, AppResult};
+use crate::types::{
+    Agent, Capability, CreateWorkspaceInput, EntityKind, Workspace, WorkspaceListResponse,
+    WorkspaceRefreshJob, WorkspaceWithDetails,
+};
 use crate::AppState;
 
 /// List all workspaces
 #[tauri::command]
 pub async fn list_workspaces(
     state: State<'_, AppState>,
-) -> Result<WorkspaceListResponse, String> {
+) -> AppResult<WorkspaceListResponse> {
+    authorize(&state, "list_workspaces", Capability::Read, None)?;
+
     state
         .workspace_service
         .list_workspaces()
         .map(|workspaces| WorkspaceListResponse { workspaces })
-        .map_err(|e| e.to_string())
+        .map_err(AppError::from)
 }
 
 /// Get a single workspace by ID
@@ -22,11 +30,13 @@ pub async fn list_workspaces(
 pub async fn get_workspace(
     id: String,
     state: State<'_, AppState>,
-) -> Result<WorkspaceWithDetails, String> {
+) -> AppResult<WorkspaceWithDetails> {
+    authorize(&state, "get_workspace", Capability::Read, None)?;
+
     state
         .workspace_service
         .get_workspace_with_details(&id)
-        .map_err(|e| e.to_string())
+        .map_err(AppError::from)
 }
 
 /// Create a new workspace
@@ -34,11 +44,18 @@ pub async fn get_workspace(
 pub async fn create_workspace(
     input: CreateWorkspaceInput,
     state: State<'_, AppState>,
-) -> Result<Workspace, String> {
-    state
+) -> AppResult<Workspace> {
+    authorize(&state, "create_workspace", Capability::GitWrite, None)?;
+
+    let workspace = state
         .workspace_service
         .create_workspace(&input.path, input.name.as_deref())
-        .map_err(|e| e.to_string())
+        ?;
+    sync_git_watchers(&state);
+    state
+        .change_feed
+        .created(EntityKind::Workspace, &workspace.id, &workspace);
+    Ok(workspace)
 }
 
 /// Delete a workspace
@@ -46,21 +63,82 @@ pub async fn create_workspace(
 pub async fn delete_workspace(
     id: String,
     state: State<'_, AppState>,
-) -> Result<(), String> {
+) -> AppResult<()> {
+    authorize(&state, "delete_workspace", Capability::GitWrite, Some(&id))?;
+
     state
         .workspace_service
         .delete_workspace(&id)
-        .map_err(|e| e.to_string())
+        ?;
+    sync_git_watchers(&state);
+    state.change_feed.deleted(EntityKind::Workspace, &id);
+    Ok(())
+}
+
+/// Point an existing workspace at a new repository path
+#[tauri::command]
+pub async fn relink_workspace(
+    id: String,
+    path: String,
+    state: State<'_, AppState>,
+) -> AppResult<WorkspaceWithDetails> {
+    authorize(&state, "relink_workspace", Capability::GitWrite, Some(&id))?;
+
+    let workspace = state
+        .workspace_service
+        .relink_workspace(&id, &path)
+        ?;
+    sync_git_watchers(&state);
+    state
+        .change_feed
+        .updated(EntityKind::Workspace, &id, &workspace);
+    Ok(workspace)
 }
 
-/// Refresh workspace data (re-scan worktrees)
+/// Queue a refresh of workspace data (re-scan worktrees); the result is
+/// pushed to the workspace's WebSocket subscribers when the job finishes
 #[tauri::command]
 pub async fn refresh_workspace(
     id: String,
     state: State<'_, AppState>,
-) -> Result<WorkspaceWithDetails, String> {
+) -> AppResult<WorkspaceRefreshJob> {
+    authorize(&state, "refresh_workspace", Capability::GitWrite, Some(&id))?;
+
+    state
+        .workspace_refresh_service
+        .enqueue(&id)
+        .map_err(AppError::from)
+}
+
+/// Get a workspace refresh job queued by `refresh_workspace`
+#[tauri::command]
+pub async fn get_workspace_refresh_job(
+    job_id: String,
+    state: State<'_, AppState>,
+) -> AppResult<WorkspaceRefreshJob> {
+    authorize(&state, "get_workspace_refresh_job", Capability::Read, None)?;
+
+    state
+        .workspace_refresh_service
+        .get_job(&job_id)
+        .ok_or_else(|| AppError::NotFound(format!("Refresh job not found: {}", job_id)))
+}
+
+/// List agents kept from worktrees that were removed outside the app
+#[tauri::command]
+pub async fn list_orphaned_agents(
+    workspace_id: String,
+    state: State<'_, AppState>,
+) -> AppResult<Vec<Agent>> {
+    authorize(
+        &state,
+        "list_orphaned_agents",
+        Capability::Read,
+        Some(&workspace_id),
+    )?;
+
     state
         .workspace_service
-        .refresh_workspace(&id)
-        .map_err(|e| e.to_string())
+        .list_orphaned_agents(&workspace_id)
+        .map_err(AppError::from)
 }
@@ -2,23 +2,33 @@
 
 use tauri::State;
 
+use super::audit_commands::authorize;
+use super::{require_online, run_blocking};
+use crate::error::{AppError, AppResult};
 use crate::types::{
-    BranchInfo, CheckoutBranchInput, CreateWorktreeInput, GitStatusInfo, ReorderWorktreesInput,
-    UpdateWorktreeInput, Worktree, WorktreeListResponse,
+    BranchInfo, BranchTemplate, Capability, CheckoutBranchInput, CommitInfo, ConflictReport,
+    CreateWorktreeInput, EntityKind, GitStatusInfo, GitStatusOptions, Job, PullRequestInfo,
+    ReorderWorktreesInput, SharedFilesConfig, SharedFilesPreview, SortMode, StashEntry,
+    StashSaveInput, UpdateWorktreeInput, Worktree, WorktreeHealth, WorktreeListResponse,
 };
 use crate::AppState;
 
+/// Commits returned per page when the caller doesn't say
+const DEFAULT_COMMIT_LOG_LIMIT: usize = 50;
+
 /// List all worktrees for a workspace
 #[tauri::command]
 pub async fn list_worktrees(
     workspace_id: String,
     state: State<'_, AppState>,
-) -> Result<WorktreeListResponse, String> {
+) -> AppResult<WorktreeListResponse> {
+    authorize(&state, "list_worktrees", Capability::Read, None)?;
+
     state
         .worktree_service
         .list_worktrees(&workspace_id)
         .map(|worktrees| WorktreeListResponse { worktrees })
-        .map_err(|e| e.to_string())
+        .map_err(AppError::from)
 }
 
 /// Get a single worktree by ID
@@ -26,29 +36,66 @@ pub async fn list_worktrees(
 pub async fn get_worktree(
     id: String,
     state: State<'_, AppState>,
-) -> Result<Worktree, String> {
+) -> AppResult<Worktree> {
+    authorize(&state, "get_worktree", Capability::Read, None)?;
+
     state
         .worktree_service
         .get_worktree(&id)
-        .map_err(|e| e.to_string())
+        .map_err(AppError::from)
 }
 
-/// Create a new worktree
+/// Create a new worktree in a background job; the job's result is the worktree
 #[tauri::command]
 pub async fn create_worktree(
     input: CreateWorktreeInput,
     state: State<'_, AppState>,
-) -> Result<Worktree, String> {
-    state
-        .worktree_service
-        .create_worktree(
-            &input.workspace_id,
-            &input.name,
-            &input.branch,
-            input.path.as_deref(),
-            input.create_branch.unwrap_or(false),
-        )
-        .map_err(|e| e.to_string())
+) -> AppResult<Job> {
+    authorize(
+        &state,
+        "create_worktree",
+        Capability::GitWrite,
+        Some(&input.workspace_id),
+    )?;
+
+    let (branch, create_branch) = match input.branch.filter(|b| !b.trim().is_empty()) {
+        Some(branch) => (branch, input.create_branch.unwrap_or(false)),
+        None => (
+            state
+                .worktree_service
+                .generate_branch_name(&input.workspace_id, &input.name)?,
+            true,
+        ),
+    };
+
+    let worktree_service = state.worktree_service.clone();
+    let git_watch_service = state.git_watch_service.clone();
+    let change_feed = state.change_feed.clone();
+    let workspace_id = input.workspace_id.clone();
+    let job = state
+        .job_service
+        .spawn("create_worktree", Some(&workspace_id), move |job| {
+            job.progress(0, "Creating worktree");
+            // A cancelled creation undoes its own partial work
+            let worktree = worktree_service
+                .create_worktree_cancellable(
+                    &input.workspace_id,
+                    &input.name,
+                    &branch,
+                    input.path.as_deref(),
+                    create_branch,
+                    job.token(),
+                )
+                .map_err(|e| e.to_string())?;
+
+            job.progress(90, "Watching worktree");
+            if let Err(e) = git_watch_service.sync() {
+                tracing::warn!("Failed to sync git watchers: {}", e);
+            }
+            change_feed.created(EntityKind::Worktree, &worktree.id, &worktree);
+            serde_json::to_value(&worktree).map_err(|e| e.to_string())
+        })?;
+    Ok(job)
 }
 
 /// Update a worktree
@@ -57,36 +104,202 @@ pub async fn update_worktree(
     id: String,
     input: UpdateWorktreeInput,
     state: State<'_, AppState>,
-) -> Result<Worktree, String> {
+) -> AppResult<Worktree> {
+    authorize(&state, "update_worktree", Capability::GitWrite, Some(&id))?;
+
+    let worktree = state.worktree_service.update_worktree(&id, input)?;
     state
-        .worktree_service
-        .update_worktree(&id, input)
-        .map_err(|e| e.to_string())
+        .change_feed
+        .updated(EntityKind::Worktree, &worktree.id, &worktree);
+    Ok(worktree)
 }
 
-/// Delete a worktree
+/// Delete a worktree; refused while agents are running in it unless forced
 #[tauri::command]
 pub async fn delete_worktree(
     id: String,
+    force: Option<bool>,
+    state: State<'_, AppState>,
+) -> AppResult<()> {
+    authorize(&state, "delete_worktree", Capability::GitWrite, Some(&id))?;
+
+    state
+        .worktree_service
+        .delete_worktree(&id, force.unwrap_or(false))?;
+    sync_git_watchers(&state);
+    state.change_feed.deleted(EntityKind::Worktree, &id);
+    Ok(())
+}
+
+/// Archive a worktree without removing it from git; refused while agents are
+/// running in it unless forced
+#[tauri::command]
+pub async fn archive_worktree(
+    id: String,
+    force: Option<bool>,
+    state: State<'_, AppState>,
+) -> AppResult<Worktree> {
+    authorize(&state, "archive_worktree", Capability::GitWrite, Some(&id))?;
+
+    let worktree = state
+        .worktree_service
+        .archive_worktree(&id, force.unwrap_or(false))?;
+    sync_git_watchers(&state);
+    state
+        .change_feed
+        .updated(EntityKind::Worktree, &worktree.id, &worktree);
+    Ok(worktree)
+}
+
+/// Bring an archived worktree back into its workspace
+#[tauri::command]
+pub async fn restore_worktree(id: String, state: State<'_, AppState>) -> AppResult<Worktree> {
+    authorize(&state, "restore_worktree", Capability::GitWrite, Some(&id))?;
+
+    let worktree = state.worktree_service.restore_worktree(&id)?;
+    sync_git_watchers(&state);
+    state
+        .change_feed
+        .updated(EntityKind::Worktree, &worktree.id, &worktree);
+    Ok(worktree)
+}
+
+/// Pin or unpin a worktree; pinned ones survive scans that can't find them
+#[tauri::command]
+pub async fn set_worktree_pinned(
+    id: String,
+    pinned: bool,
+    state: State<'_, AppState>,
+) -> AppResult<Worktree> {
+    authorize(
+        &state,
+        "set_worktree_pinned",
+        Capability::GitWrite,
+        Some(&id),
+    )?;
+
+    let worktree = state.worktree_service.set_pinned(&id, pinned)?;
+    state
+        .change_feed
+        .updated(EntityKind::Worktree, &worktree.id, &worktree);
+    Ok(worktree)
+}
+
+/// Check whether each of a workspace's worktrees is on disk, known to git,
+/// locked, prunable or dirty
+#[tauri::command]
+pub async fn get_worktree_health(
+    workspace_id: String,
     state: State<'_, AppState>,
-) -> Result<(), String> {
+) -> AppResult<Vec<WorktreeHealth>> {
+    authorize(&state, "get_worktree_health", Capability::Read, None)?;
+
+    // Reads every worktree's git status, so keep it off the async runtime
+    let worktree_service = state.worktree_service.clone();
+    run_blocking(move || worktree_service.get_worktree_health(&workspace_id)).await
+}
+
+/// List a workspace's archived worktrees
+#[tauri::command]
+pub async fn list_archived_worktrees(
+    workspace_id: String,
+    state: State<'_, AppState>,
+) -> AppResult<WorktreeListResponse> {
+    authorize(&state, "list_archived_worktrees", Capability::Read, None)?;
+
     state
         .worktree_service
-        .delete_worktree(&id)
-        .map_err(|e| e.to_string())
+        .list_archived_worktrees(&workspace_id)
+        .map(|worktrees| WorktreeListResponse { worktrees })
+        .map_err(AppError::from)
 }
 
-/// Checkout a branch in a worktree
+/// Checkout a branch in a worktree; refused while agents are running in it unless forced
 #[tauri::command]
 pub async fn checkout_branch(
     id: String,
     input: CheckoutBranchInput,
     state: State<'_, AppState>,
-) -> Result<Worktree, String> {
+) -> AppResult<Worktree> {
+    authorize(&state, "checkout_branch", Capability::GitWrite, Some(&id))?;
+
+    let worktree = state.worktree_service.checkout_branch(
+        &id,
+        &input.branch,
+        input.create.unwrap_or(false),
+        input.stash.unwrap_or(false),
+        input.force.unwrap_or(false),
+    )?;
+    state
+        .change_feed
+        .updated(EntityKind::Worktree, &worktree.id, &worktree);
+    Ok(worktree)
+}
+
+/// List the commits on a worktree's branch, newest first
+#[tauri::command]
+pub async fn get_commit_log(
+    worktree_id: String,
+    limit: Option<usize>,
+    skip: Option<usize>,
+    state: State<'_, AppState>,
+) -> AppResult<Vec<CommitInfo>> {
+    authorize(&state, "get_commit_log", Capability::Read, None)?;
+
+    state
+        .worktree_service
+        .get_commit_log(
+            &worktree_id,
+            limit.unwrap_or(DEFAULT_COMMIT_LOG_LIMIT),
+            skip.unwrap_or(0),
+        )
+        .map_err(AppError::from)
+}
+
+/// Stash a worktree's uncommitted changes
+#[tauri::command]
+pub async fn stash_save(
+    id: String,
+    input: StashSaveInput,
+    state: State<'_, AppState>,
+) -> AppResult<Option<StashEntry>> {
+    authorize(&state, "stash_save", Capability::GitWrite, Some(&id))?;
+
+    state
+        .worktree_service
+        .stash_save(
+            &id,
+            input.message.as_deref(),
+            input.include_untracked.unwrap_or(true),
+        )
+        .map_err(AppError::from)
+}
+
+/// List the stashes of a worktree's repository
+#[tauri::command]
+pub async fn stash_list(id: String, state: State<'_, AppState>) -> AppResult<Vec<StashEntry>> {
+    authorize(&state, "stash_list", Capability::Read, None)?;
+
+    state
+        .worktree_service
+        .stash_list(&id)
+        .map_err(AppError::from)
+}
+
+/// Apply a stash to a worktree and drop it
+#[tauri::command]
+pub async fn stash_pop(
+    id: String,
+    index: Option<usize>,
+    state: State<'_, AppState>,
+) -> AppResult<GitStatusInfo> {
+    authorize(&state, "stash_pop", Capability::GitWrite, Some(&id))?;
+
+    state.worktree_service.stash_pop(&id, index.unwrap_or(0))?;
     state
         .worktree_service
-        .checkout_branch(&id, &input.branch, input.create.unwrap_or(false))
-        .map_err(|e| e.to_string())
+        .get_git_status(&id)
+        .map_err(AppError::from)
 }
 
 /// Reorder worktrees
@@ -95,33 +308,337 @@ pub async fn reorder_worktrees(
     workspace_id: String,
     input: ReorderWorktreesInput,
     state: State<'_, AppState>,
-) -> Result<Vec<Worktree>, String> {
+) -> AppResult<Vec<Worktree>> {
+    authorize(
+        &state,
+        "reorder_worktrees",
+        Capability::GitWrite,
+        Some(&workspace_id),
+    )?;
+
+    let worktrees = state
+        .worktree_service
+        .reorder_worktrees(&workspace_id, &input.worktree_ids)?;
+    for worktree in &worktrees {
+        state
+            .change_feed
+            .updated(EntityKind::Worktree, &worktree.id, worktree);
+    }
+    Ok(worktrees)
+}
+
+/// Get the template for branches generated for a workspace's worktrees
+#[tauri::command]
+pub async fn get_branch_template(
+    workspace_id: String,
+    state: State<'_, AppState>,
+) -> AppResult<BranchTemplate> {
+    authorize(&state, "get_branch_template", Capability::Read, None)?;
+
+    state
+        .worktree_service
+        .get_branch_template(&workspace_id)
+        .map_err(AppError::from)
+}
+
+/// Set a workspace's branch template; an empty template restores the default
+#[tauri::command]
+pub async fn set_branch_template(
+    workspace_id: String,
+    template: String,
+    state: State<'_, AppState>,
+) -> AppResult<BranchTemplate> {
+    authorize(
+        &state,
+        "set_branch_template",
+        Capability::GitWrite,
+        Some(&workspace_id),
+    )?;
+
     state
         .worktree_service
-        .reorder_worktrees(&workspace_id, &input.worktree_ids)
-        .map_err(|e| e.to_string())
+        .set_branch_template(&workspace_id, &template)
+        .map_err(AppError::from)
 }
 
-/// Get git status for a worktree
+/// Branch a new worktree with this name would get
+#[tauri::command]
+pub async fn suggest_branch_name(
+    workspace_id: String,
+    name: String,
+    state: State<'_, AppState>,
+) -> AppResult<String> {
+    authorize(&state, "suggest_branch_name", Capability::Read, None)?;
+
+    state
+        .worktree_service
+        .generate_branch_name(&workspace_id, &name)
+        .map_err(AppError::from)
+}
+
+/// Get the untracked files a workspace copies into new worktrees
+#[tauri::command]
+pub async fn get_shared_files(
+    workspace_id: String,
+    state: State<'_, AppState>,
+) -> AppResult<SharedFilesConfig> {
+    authorize(&state, "get_shared_files", Capability::Read, None)?;
+
+    state
+        .worktree_service
+        .get_shared_files(&workspace_id)
+        .map_err(AppError::from)
+}
+
+/// Set the files a workspace copies into new worktrees; returns a dry run
+/// of what the next worktree would get
+#[tauri::command]
+pub async fn set_shared_files(
+    workspace_id: String,
+    config: SharedFilesConfig,
+    state: State<'_, AppState>,
+) -> AppResult<SharedFilesPreview> {
+    authorize(
+        &state,
+        "set_shared_files",
+        Capability::GitWrite,
+        Some(&workspace_id),
+    )?;
+
+    state
+        .worktree_service
+        .set_shared_files(&workspace_id, config)
+        .map_err(AppError::from)
+}
+
+/// List what creating a worktree would copy, without copying anything
+#[tauri::command]
+pub async fn preview_shared_files(
+    workspace_id: String,
+    state: State<'_, AppState>,
+) -> AppResult<SharedFilesPreview> {
+    authorize(&state, "preview_shared_files", Capability::Read, None)?;
+
+    state
+        .worktree_service
+        .preview_shared_files(&workspace_id)
+        .map_err(AppError::from)
+}
+
+/// Get git status for a worktree, optionally limited and paginated
 #[tauri::command]
 pub async fn get_git_status(
     id: String,
+    options: Option<GitStatusOptions>,
     state: State<'_, AppState>,
-) -> Result<GitStatusInfo, String> {
+) -> AppResult<GitStatusInfo> {
+    authorize(&state, "get_git_status", Capability::Read, None)?;
+
     state
         .worktree_service
-        .get_git_status(&id)
-        .map_err(|e| e.to_string())
+        .get_git_status_with(&id, &options.unwrap_or_default())
+        .map_err(AppError::from)
+}
+
+/// Get the directories new worktrees of a workspace check out
+#[tauri::command]
+pub async fn get_sparse_checkout(
+    workspace_id: String,
+    state: State<'_, AppState>,
+) -> AppResult<Vec<String>> {
+    authorize(&state, "get_sparse_checkout", Capability::Read, None)?;
+
+    state
+        .worktree_service
+        .get_sparse_checkout(&workspace_id)
+        .map_err(AppError::from)
+}
+
+/// Set the directories new worktrees of a workspace check out; empty for full checkouts
+#[tauri::command]
+pub async fn set_sparse_checkout(
+    workspace_id: String,
+    dirs: Vec<String>,
+    state: State<'_, AppState>,
+) -> AppResult<Vec<String>> {
+    authorize(
+        &state,
+        "set_sparse_checkout",
+        Capability::GitWrite,
+        Some(&workspace_id),
+    )?;
+
+    state
+        .worktree_service
+        .set_sparse_checkout(&workspace_id, dirs)
+        .map_err(AppError::from)
 }
 
-/// List branches for a worktree
+/// Get the branch a workspace's worktrees are compared with
+#[tauri::command]
+pub async fn get_base_branch(
+    workspace_id: String,
+    state: State<'_, AppState>,
+) -> AppResult<String> {
+    authorize(&state, "get_base_branch", Capability::Read, None)?;
+
+    state
+        .worktree_service
+        .get_base_branch(&workspace_id)
+        .map_err(AppError::from)
+}
+
+/// Set the branch a workspace's worktrees are compared with
+#[tauri::command]
+pub async fn set_base_branch(
+    workspace_id: String,
+    branch: String,
+    state: State<'_, AppState>,
+) -> AppResult<String> {
+    authorize(
+        &state,
+        "set_base_branch",
+        Capability::GitWrite,
+        Some(&workspace_id),
+    )?;
+
+    state
+        .worktree_service
+        .set_base_branch(&workspace_id, &branch)
+        .map_err(AppError::from)
+}
+
+/// Get how a workspace's worktrees are ordered
+#[tauri::command]
+pub async fn get_worktree_sort_mode(
+    workspace_id: String,
+    state: State<'_, AppState>,
+) -> AppResult<SortMode> {
+    authorize(&state, "get_worktree_sort_mode", Capability::Read, None)?;
+
+    state
+        .worktree_service
+        .get_sort_mode(&workspace_id)
+        .map_err(AppError::from)
+}
+
+/// Set how a workspace's worktrees are ordered
+#[tauri::command]
+pub async fn set_worktree_sort_mode(
+    workspace_id: String,
+    sort_mode: SortMode,
+    state: State<'_, AppState>,
+) -> AppResult<SortMode> {
+    authorize(
+        &state,
+        "set_worktree_sort_mode",
+        Capability::Settings,
+        Some(&workspace_id),
+    )?;
+
+    state
+        .worktree_service
+        .set_sort_mode(&workspace_id, sort_mode)
+        .map_err(AppError::from)
+}
+
+/// List branches for a worktree, fetching the remotes first when asked
 #[tauri::command]
 pub async fn list_branches(
     id: String,
+    fetch: Option<bool>,
     state: State<'_, AppState>,
-) -> Result<BranchInfo, String> {
+) -> AppResult<BranchInfo> {
+    authorize(&state, "list_branches", Capability::Read, None)?;
+
+    // Offline, the branches known locally are still worth listing
+    if fetch.unwrap_or(false) && state.network_status_service.is_online() {
+        fetch_remotes(&state, &id, true).await?;
+    }
     state
         .worktree_service
         .list_branches(&id)
-        .map_err(|e| e.to_string())
+        .map_err(AppError::from)
+}
+
+/// Fetch a worktree's remotes and list its branches afterwards
+#[tauri::command]
+pub async fn git_fetch(
+    id: String,
+    prune: Option<bool>,
+    state: State<'_, AppState>,
+) -> AppResult<BranchInfo> {
+    authorize(&state, "git_fetch", Capability::GitWrite, Some(&id))?;
+    require_online(&state, "fetch remotes")?;
+
+    fetch_remotes(&state, &id, prune.unwrap_or(true)).await?;
+    state
+        .worktree_service
+        .list_branches(&id)
+        .map_err(AppError::from)
+}
+
+async fn fetch_remotes(state: &AppState, id: &str, prune: bool) -> AppResult<()> {
+    // Fetching talks to the network, so keep it off the async runtime
+    let worktree_service = state.worktree_service.clone();
+    let id = id.to_string();
+    run_blocking(move || worktree_service.fetch(&id, prune)).await
+}
+
+/// Push a worktree's branch and open a pull request for it
+#[tauri::command]
+pub async fn create_pull_request(
+    worktree_id: String,
+    title: String,
+    body: String,
+    base: Option<String>,
+    state: State<'_, AppState>,
+) -> AppResult<PullRequestInfo> {
+    authorize(
+        &state,
+        "create_pull_request",
+        Capability::GitWrite,
+        Some(&worktree_id),
+    )?;
+    require_online(&state, "open a pull request")?;
+
+    state
+        .pull_request_service
+        .create_pull_request(&worktree_id, &title, &body, base.as_deref())
+        .await
+        .map_err(AppError::from)
+}
+
+/// Find files edited in more than one worktree of a workspace
+#[tauri::command]
+pub async fn detect_conflicts(
+    workspace_id: String,
+    state: State<'_, AppState>,
+) -> AppResult<ConflictReport> {
+    authorize(&state, "detect_conflicts", Capability::Read, None)?;
+
+    state
+        .worktree_service
+        .detect_conflicts(&workspace_id)
+        .map_err(AppError::from)
+}
+
+/// Start or stop git watchers after worktrees were added or removed
+pub(crate) fn sync_git_watchers(state: &AppState) {
+    if let Err(e) = state.git_watch_service.sync() {
+        tracing::warn!("Failed to sync git watchers: {}", e);
+    }
+}
+
+/// Tell every window about a workspace whose worktrees or agents changed in bulk
+pub(crate) fn publish_workspace_details(state: &AppState, workspace_id: &str) {
+    match state
+        .workspace_service
+        .get_workspace_with_details(workspace_id)
+    {
+        Ok(details) => state
+            .change_feed
+            .updated(EntityKind::Workspace, workspace_id, &details),
+        Err(e) => tracing::warn!("Failed to publish workspace {}: {}", workspace_id, e),
+    }
 }
@@ -2,7 +2,7 @@
 
 use r2d2::Pool;
 use r2d2_sqlite::SqliteConnectionManager;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -13,8 +13,13 @@ pub enum DbError {
     Pool(#[from] r2d2::Error),
     #[error("Migration error: {0}")]
     Migration(String),
+    #[error("Encryption error: {0}")]
+    Crypto(#[from] super::crypto::CryptoError),
     #[error("Not found")]
     NotFound,
+    /// The row was changed after it was read; carries its current `updated_at`
+    #[error("Changed since it was read, at {0}")]
+    Conflict(String),
 }
 
 pub type DbPool = Pool<SqliteConnectionManager>;
@@ -52,3 +57,33 @@ pub fn init_database(data_dir: PathBuf) -> DbResult<DbPool> {
 
     Ok(pool)
 }
+
+/// Open an existing database without migrating it
+///
+/// For tools sharing the database with the app: migrating would change the
+/// schema under a running app, or revert migrations a newer app applied, so
+/// any schema other than the one this build expects is refused instead.
+pub fn open_database(data_dir: &Path) -> DbResult<DbPool> {
+    let db_path = data_dir.join("claude-manager.db");
+    if !db_path.exists() {
+        return Err(DbError::NotFound);
+    }
+
+    let manager = SqliteConnectionManager::file(&db_path)
+        .with_init(|conn| conn.execute_batch("PRAGMA foreign_keys = ON;"));
+    let pool = Pool::builder().max_size(2).build(manager)?;
+
+    let version = {
+        let conn = pool.get()?;
+        super::migrations::current_version(&conn)?
+    };
+    let expected = super::migrations::latest_version();
+    if version != expected {
+        return Err(DbError::Migration(format!(
+            "Database is at schema version {} but this build expects {}",
+            version, expected
+        )));
+    }
+
+    Ok(pool)
+}
@@ -0,0 +1,340 @@
+//! Encryption of sensitive values stored in the database
+//!
+//! Values are sealed with AES-256-GCM under a key derived with HKDF-SHA256
+//! from a master key, one key per field, and the field name is authenticated
+//! with the value so it can't be moved to another field. The master key lives
+//! in the OS keychain. Where there is none, e.g. Linux without a secret
+//! service, it is kept in a file next to the database that only the user can
+//! read, which still keeps the values out of database copies and backups.
+//!
+//! The cipher is installed once at startup. Repositories then encrypt and
+//! decrypt their designated fields through `cipher()`; without one installed
+//! those fields can't be stored or read back.
+
+use std::path::Path;
+use std::sync::OnceLock;
+
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use ring::hkdf::{Salt, HKDF_SHA256};
+use ring::rand::{SecureRandom, SystemRandom};
+use thiserror::Error;
+
+/// Prefix marking an encrypted value; anything else is read as plaintext
+const ENCRYPTED_PREFIX: &str = "enc:v1:";
+const KEYCHAIN_SERVICE: &str = "com.claude-manager.app";
+const KEY_FILE_NAME: &str = "database-key";
+const HKDF_SALT: &[u8] = b"claude-manager field encryption";
+const MASTER_KEY_LEN: usize = 32;
+
+static CIPHER: OnceLock<FieldCipher> = OnceLock::new();
+
+#[derive(Error, Debug)]
+pub enum CryptoError {
+    #[error("Invalid key: {0}")]
+    InvalidKey(String),
+    #[error("Could not decrypt {0}")]
+    Decrypt(String),
+    #[error("Database key unavailable: {0}")]
+    KeyUnavailable(String),
+    #[error("Random number generator failed")]
+    Random,
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+/// Encrypts and decrypts the values of sensitive fields
+pub struct FieldCipher {
+    master: [u8; MASTER_KEY_LEN],
+    rng: SystemRandom,
+}
+
+impl FieldCipher {
+    pub fn new(master: [u8; MASTER_KEY_LEN]) -> Self {
+        Self {
+            master,
+            rng: SystemRandom::new(),
+        }
+    }
+
+    /// Load the master key for the database in `data_dir`, creating one on
+    /// first use. `sealed_values` says whether the database already holds
+    /// encrypted values; their key then has to come from the keychain.
+    pub fn load_or_create(data_dir: &Path, sealed_values: bool) -> Result<Self, CryptoError> {
+        Self::load_or_create_with(data_dir, sealed_values, |fresh| {
+            keychain_key(data_dir, fresh)
+        })
+    }
+
+    /// `keychain` returns the stored key, storing the one it is given when
+    /// there is none
+    fn load_or_create_with(
+        data_dir: &Path,
+        sealed_values: bool,
+        keychain: impl FnOnce(Option<&[u8]>) -> Result<String, keyring::Error>,
+    ) -> Result<Self, CryptoError> {
+        // A key file once written stays in use, or the values sealed with it
+        // could no longer be read
+        let key_file = data_dir.join(KEY_FILE_NAME);
+        if key_file.exists() {
+            let hex = std::fs::read_to_string(&key_file)?;
+            return Ok(Self::new(decode_key(hex.trim())?));
+        }
+
+        let fresh = random_key()?;
+        // A new key would leave the values sealed with the keychain's
+        // unreadable for good, even when its entry is merely missing
+        match keychain((!sealed_values).then_some(&fresh[..])) {
+            Ok(hex) => Ok(Self::new(decode_key(&hex)?)),
+            Err(e) if sealed_values => Err(CryptoError::KeyUnavailable(format!(
+                "No key in the OS keychain for the encrypted values in the database: {}",
+                e
+            ))),
+            Err(e) => {
+                tracing::warn!(
+                    "OS keychain unavailable, keeping database key in a file: {}",
+                    e
+                );
+                std::fs::create_dir_all(data_dir)?;
+                std::fs::write(&key_file, encode_hex(&fresh))?;
+                #[cfg(unix)]
+                {
+                    use std::os::unix::fs::PermissionsExt;
+                    std::fs::set_permissions(&key_file, std::fs::Permissions::from_mode(0o600))?;
+                }
+                Ok(Self::new(fresh))
+            }
+        }
+    }
+
+    /// Seal the value of `field`
+    pub fn encrypt(&self, field: &str, plaintext: &str) -> Result<String, CryptoError> {
+        let mut nonce = [0u8; NONCE_LEN];
+        self.rng.fill(&mut nonce).map_err(|_| CryptoError::Random)?;
+
+        let mut sealed = plaintext.as_bytes().to_vec();
+        self.key(field)?
+            .seal_in_place_append_tag(
+                Nonce::assume_unique_for_key(nonce),
+                Aad::from(field.as_bytes()),
+                &mut sealed,
+            )
+            .map_err(|_| CryptoError::InvalidKey(field.to_string()))?;
+
+        Ok(format!(
+            "{}{}{}",
+            ENCRYPTED_PREFIX,
+            encode_hex(&nonce),
+            encode_hex(&sealed)
+        ))
+    }
+
+    /// Open a value of `field` read from the database; plaintext written
+    /// before the field was encrypted is returned as it is
+    pub fn decrypt(&self, field: &str, stored: &str) -> Result<String, CryptoError> {
+        let Some(hex) = stored.strip_prefix(ENCRYPTED_PREFIX) else {
+            return Ok(stored.to_string());
+        };
+        let bytes = decode_hex(hex).ok_or_else(|| CryptoError::Decrypt(field.to_string()))?;
+        if bytes.len() < NONCE_LEN {
+            return Err(CryptoError::Decrypt(field.to_string()));
+        }
+        let (nonce, sealed) = bytes.split_at(NONCE_LEN);
+        let nonce = Nonce::try_assume_unique_for_key(nonce)
+            .map_err(|_| CryptoError::Decrypt(field.to_string()))?;
+
+        let mut sealed = sealed.to_vec();
+        let plaintext = self
+            .key(field)?
+            .open_in_place(nonce, Aad::from(field.as_bytes()), &mut sealed)
+            .map_err(|_| CryptoError::Decrypt(field.to_string()))?;
+        String::from_utf8(plaintext.to_vec()).map_err(|_| CryptoError::Decrypt(field.to_string()))
+    }
+
+    fn key(&self, field: &str) -> Result<LessSafeKey, CryptoError> {
+        let info = [field.as_bytes()];
+        let prk = Salt::new(HKDF_SHA256, HKDF_SALT).extract(&self.master);
+        let okm = prk
+            .expand(&info, &AES_256_GCM)
+            .map_err(|_| CryptoError::InvalidKey(field.to_string()))?;
+        Ok(LessSafeKey::new(UnboundKey::from(okm)))
+    }
+}
+
+/// Whether a stored value is encrypted
+pub fn is_encrypted(stored: &str) -> bool {
+    stored.starts_with(ENCRYPTED_PREFIX)
+}
+
+/// Use `cipher` for every repository from now on. Returns false when one
+/// was installed already.
+pub fn install(cipher: FieldCipher) -> bool {
+    CIPHER.set(cipher).is_ok()
+}
+
+/// The installed cipher, if any
+pub(crate) fn cipher() -> Option<&'static FieldCipher> {
+    CIPHER.get()
+}
+
+/// The master key kept in the keychain for `data_dir`, storing `fresh`, if
+/// given, when there is none yet. Each data directory gets its own key.
+fn keychain_key(data_dir: &Path, fresh: Option<&[u8]>) -> Result<String, keyring::Error> {
+    let entry = keyring::Entry::new(KEYCHAIN_SERVICE, &data_dir.to_string_lossy())?;
+    match (entry.get_password(), fresh) {
+        (Err(keyring::Error::NoEntry), Some(fresh)) => {
+            let hex = encode_hex(fresh);
+            entry.set_password(&hex)?;
+            Ok(hex)
+        }
+        (result, _) => result,
+    }
+}
+
+fn random_key() -> Result<[u8; MASTER_KEY_LEN], CryptoError> {
+    let mut key = [0u8; MASTER_KEY_LEN];
+    SystemRandom::new()
+        .fill(&mut key)
+        .map_err(|_| CryptoError::Random)?;
+    Ok(key)
+}
+
+fn decode_key(hex: &str) -> Result<[u8; MASTER_KEY_LEN], CryptoError> {
+    decode_hex(hex)
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| CryptoError::InvalidKey("Malformed database key".to_string()))
+}
+
+fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if hex.len() % 2 != 0 {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_values_round_trip_and_stay_bound_to_their_field() {
+        let cipher = FieldCipher::new([7; MASTER_KEY_LEN]);
+
+        let sealed = cipher.encrypt("github_token", "ghp_secret").unwrap();
+        assert!(is_encrypted(&sealed));
+        assert!(!sealed.contains("ghp_secret"));
+        assert_ne!(
+            sealed,
+            cipher.encrypt("github_token", "ghp_secret").unwrap()
+        );
+        assert_eq!(
+            cipher.decrypt("github_token", &sealed).unwrap(),
+            "ghp_secret"
+        );
+
+        assert!(matches!(
+            cipher.decrypt("other_field", &sealed),
+            Err(CryptoError::Decrypt(_))
+        ));
+        assert!(matches!(
+            FieldCipher::new([8; MASTER_KEY_LEN]).decrypt("github_token", &sealed),
+            Err(CryptoError::Decrypt(_))
+        ));
+        assert!(matches!(
+            cipher.decrypt("github_token", "enc:v1:zz"),
+            Err(CryptoError::Decrypt(_))
+        ));
+
+        // Written before encryption was turned on
+        assert_eq!(
+            cipher.decrypt("github_token", "ghp_plain").unwrap(),
+            "ghp_plain"
+        );
+    }
+
+    #[test]
+    fn test_key_file_is_reused() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join(KEY_FILE_NAME), encode_hex(&[3; 32])).unwrap();
+
+        let sealed = FieldCipher::new([3; MASTER_KEY_LEN])
+            .encrypt("github_token", "ghp_secret")
+            .unwrap();
+        let cipher = FieldCipher::load_or_create(dir.path(), true).unwrap();
+        assert_eq!(
+            cipher.decrypt("github_token", &sealed).unwrap(),
+            "ghp_secret"
+        );
+
+        std::fs::write(dir.path().join(KEY_FILE_NAME), "abc").unwrap();
+        assert!(matches!(
+            FieldCipher::load_or_create(dir.path(), true),
+            Err(CryptoError::InvalidKey(_))
+        ));
+    }
+
+    #[test]
+    fn test_key_file_only_replaces_an_unreachable_keychain_before_first_use() {
+        let dir = tempfile::tempdir().unwrap();
+        let unavailable = |_: Option<&[u8]>| Err(keyring::Error::PlatformFailure("locked".into()));
+
+        assert!(matches!(
+            FieldCipher::load_or_create_with(dir.path(), true, unavailable),
+            Err(CryptoError::KeyUnavailable(_))
+        ));
+        assert!(!dir.path().join(KEY_FILE_NAME).exists());
+
+        let sealed = FieldCipher::load_or_create_with(dir.path(), false, unavailable)
+            .unwrap()
+            .encrypt("github_token", "ghp_secret")
+            .unwrap();
+        assert!(dir.path().join(KEY_FILE_NAME).exists());
+        let reloaded = FieldCipher::load_or_create_with(dir.path(), true, unavailable).unwrap();
+        assert_eq!(
+            reloaded.decrypt("github_token", &sealed).unwrap(),
+            "ghp_secret"
+        );
+    }
+
+    #[test]
+    fn test_missing_keychain_entry_is_only_filled_before_first_use() {
+        let dir = tempfile::tempdir().unwrap();
+        // An empty keychain that stores the key it is given
+        let stored = std::cell::RefCell::new(None);
+        let keychain = |fresh: Option<&[u8]>| {
+            let current = stored.borrow().clone();
+            match (current, fresh) {
+                (Some(hex), _) => Ok(hex),
+                (None, Some(fresh)) => {
+                    stored.replace(Some(encode_hex(fresh)));
+                    Ok(encode_hex(fresh))
+                }
+                (None, None) => Err(keyring::Error::NoEntry),
+            }
+        };
+
+        assert!(matches!(
+            FieldCipher::load_or_create_with(dir.path(), true, keychain),
+            Err(CryptoError::KeyUnavailable(_))
+        ));
+        assert!(stored.borrow().is_none());
+        assert!(!dir.path().join(KEY_FILE_NAME).exists());
+
+        let sealed = FieldCipher::load_or_create_with(dir.path(), false, keychain)
+            .unwrap()
+            .encrypt("github_token", "ghp_secret")
+            .unwrap();
+        assert!(stored.borrow().is_some());
+        let reloaded = FieldCipher::load_or_create_with(dir.path(), true, keychain).unwrap();
+        assert_eq!(
+            reloaded.decrypt("github_token", &sealed).unwrap(),
+            "ghp_secret"
+        );
+    }
+}
@@ -3,14 +3,18 @@
 //! This module provides utilities for:
 //! - Backing up the existing database
 //! - Importing data from the Node.js backend database
+//! - Importing sessions from other agent managers (claude-squad, tmux scripts)
 //! - Verifying data integrity after migration
 
 use std::fs;
 use std::path::{Path, PathBuf};
 
 use rusqlite::Connection;
+use serde::Serialize;
 use thiserror::Error;
 
+use crate::util::id::new_id;
+
 #[derive(Error, Debug)]
 pub enum MigrationError {
     #[error("Source database not found: {0}")]
@@ -34,15 +38,26 @@ pub enum MigrationError {
 
 pub type MigrationResult<T> = Result<T, MigrationError>;
 
+/// Tables `migrate_from_nodejs` copies, one step each
+pub const MIGRATION_STEPS: usize = 6;
+
 /// Statistics about the migration
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
 pub struct MigrationStats {
+    /// Nothing was written; the counts are what a migration would copy
+    pub dry_run: bool,
     pub workspaces_migrated: usize,
     pub worktrees_migrated: usize,
     pub agents_migrated: usize,
     pub messages_migrated: usize,
     pub sessions_migrated: usize,
     pub usage_stats_migrated: usize,
+    /// Agents whose 'finished' status became 'idle'
+    pub statuses_converted: usize,
+    /// Tables and columns the copy needs that either database lacks, which
+    /// would fail a migration. Only checked on a dry run.
+    pub schema_mismatches: Vec<String>,
 }
 
 impl MigrationStats {
@@ -82,32 +97,83 @@ pub fn backup_database(db_path: &Path) -> MigrationResult<PathBuf> {
 /// - Both databases have the same schema (as designed in the migration plan)
 /// - The destination database already has migrations run
 ///
-/// Returns statistics about what was migrated.
+/// Returns statistics about what was migrated. A dry run writes nothing and
+/// reports what would be migrated instead.
 pub fn migrate_from_nodejs(
     source_path: &Path,
     dest_conn: &Connection,
+    dry_run: bool,
+) -> MigrationResult<MigrationStats> {
+    migrate_from_nodejs_with_progress(source_path, dest_conn, dry_run, |_, _| {})
+}
+
+/// Like `migrate_from_nodejs`, calling `progress` before each table with how
+/// many of the `MIGRATION_STEPS` are done and the table about to be copied
+pub fn migrate_from_nodejs_with_progress(
+    source_path: &Path,
+    dest_conn: &Connection,
+    dry_run: bool,
+    mut progress: impl FnMut(usize, &str),
 ) -> MigrationResult<MigrationStats> {
     if !source_path.exists() {
         return Err(MigrationError::SourceNotFound(source_path.to_path_buf()));
     }
 
     let source_conn = Connection::open(source_path)?;
-    let mut stats = MigrationStats::default();
 
-    // Disable foreign keys temporarily for import
+    if dry_run {
+        let stats = migrate_tables(&source_conn, dest_conn, true, &mut progress)?;
+        tracing::info!(
+            "Migration dry run: {} records would be migrated, {} schema mismatches",
+            stats.total(),
+            stats.schema_mismatches.len()
+        );
+        return Ok(stats);
+    }
+
+    // Disable foreign keys temporarily for import. The connection may go back
+    // to a pool, so they are re-enabled even when a table fails.
     dest_conn.execute("PRAGMA foreign_keys = OFF", [])?;
+    let stats = migrate_tables(&source_conn, dest_conn, false, &mut progress);
+    dest_conn.execute("PRAGMA foreign_keys = ON", [])?;
+    let stats = stats?;
+
+    tracing::info!(
+        "Migration complete: {} total records migrated",
+        stats.total()
+    );
+
+    Ok(stats)
+}
+
+/// Copy each table in turn, or only count it on a dry run, reporting every step
+fn migrate_tables(
+    source_conn: &Connection,
+    dest_conn: &Connection,
+    dry_run: bool,
+    progress: &mut impl FnMut(usize, &str),
+) -> MigrationResult<MigrationStats> {
+    let mut stats = MigrationStats {
+        dry_run,
+        ..Default::default()
+    };
+    let mismatches = &mut stats.schema_mismatches;
 
     // Migrate workspaces
+    progress(0, "workspaces");
     stats.workspaces_migrated = migrate_table(
-        &source_conn,
+        source_conn,
         dest_conn,
         "workspaces",
         &["id", "name", "path", "created_at", "updated_at"],
+        dry_run,
+        mismatches,
     )?;
 
     // Migrate worktrees
+    progress(1, "worktrees");
     stats.worktrees_migrated = migrate_table(
-        &source_conn,
+        source_conn,
         dest_conn,
         "worktrees",
         &[
@@ -122,14 +188,19 @@ pub fn migrate_from_nodejs(
             "created_at",
             "updated_at",
         ],
+        dry_run,
+        mismatches,
     )?;
 
     // Migrate agents (with status 'finished' → 'idle' conversion)
-    stats.agents_migrated = migrate_agents(&source_conn, dest_conn)?;
+    progress(2, "agents");
+    (stats.agents_migrated, stats.statuses_converted) =
+        migrate_agents(source_conn, dest_conn, dry_run, mismatches)?;
 
     // Migrate messages
+    progress(3, "messages");
     stats.messages_migrated = migrate_table(
-        &source_conn,
+        source_conn,
         dest_conn,
         "messages",
         &[
@@ -144,11 +215,14 @@ pub fn migrate_from_nodejs(
             "is_complete",
             "created_at",
         ],
+        dry_run,
+        mismatches,
     )?;
 
     // Migrate agent sessions
+    progress(4, "agent_sessions");
     stats.sessions_migrated = migrate_table(
-        &source_conn,
+        source_conn,
         dest_conn,
         "agent_sessions",
         &[
@@ -158,11 +232,14 @@ pub fn migrate_from_nodejs(
             "context_snapshot",
             "created_at",
         ],
+        dry_run,
+        mismatches,
     )?;
 
     // Migrate usage stats
+    progress(5, "usage_stats");
     stats.usage_stats_migrated = migrate_table_optional(
-        &source_conn,
+        source_conn,
         dest_conn,
         "usage_stats",
         &[
@@ -178,29 +255,38 @@ pub fn migrate_from_nodejs(
             "created_at",
             "updated_at",
         ],
+        dry_run,
+        mismatches,
     )?;
 
-    // Re-enable foreign keys
-    dest_conn.execute("PRAGMA foreign_keys = ON", [])?;
-
-    tracing::info!(
-        "Migration complete: {} total records migrated",
-        stats.total()
-    );
-
     Ok(stats)
 }
 
-/// Migrate agents table with 'finished' → 'idle' status conversion
+/// Migrate agents table with 'finished' → 'idle' status conversion, returning
+/// how many agents were copied and how many of them were converted
 fn migrate_agents(
     source_conn: &Connection,
     dest_conn: &Connection,
-) -> MigrationResult<usize> {
+    dry_run: bool,
+    mismatches: &mut Vec<String>,
+) -> MigrationResult<(usize, usize)> {
     let columns = &[
         "id", "worktree_id", "name", "status", "context_level", "mode",
         "permissions", "display_order", "pid", "session_id", "parent_agent_id",
         "created_at", "updated_at", "started_at", "stopped_at", "deleted_at",
     ];
+    if dry_run {
+        let count = preview_table(source_conn, dest_conn, "agents", columns, mismatches)?;
+        let finished: i64 = source_conn
+            .query_row(
+                "SELECT COUNT(*) FROM agents WHERE status = 'finished'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap_or(0);
+        return Ok((count, finished as usize));
+    }
+
     let columns_str = columns.join(", ");
     let placeholders = columns.iter().map(|_| "?").collect::<Vec<_>>().join(", ");
 
@@ -215,6 +301,7 @@ fn migrate_agents(
 
     let status_idx = columns.iter().position(|&c| c == "status").unwrap();
     let mut count = 0;
+    let mut converted = 0;
     let mut rows = select_stmt.query([])?;
 
     while let Some(row) = rows.next()? {
@@ -226,6 +313,7 @@ fn migrate_agents(
         if let rusqlite::types::Value::Text(ref s) = values[status_idx] {
             if s == "finished" {
                 values[status_idx] = rusqlite::types::Value::Text("idle".to_string());
+                converted += 1;
             }
         }
 
@@ -233,17 +321,28 @@ fn migrate_agents(
         count += 1;
     }
 
-    tracing::info!("Migrated {} agent records (finished → idle)", count);
-    Ok(count)
+    tracing::info!(
+        "Migrated {} agent records ({} finished → idle)",
+        count,
+        converted
+    );
+    Ok((count, converted))
 }
 
-/// Migrate a single table from source to destination
+/// Migrate a single table from source to destination, or only count its
+/// rows on a dry run
 fn migrate_table(
     source_conn: &Connection,
     dest_conn: &Connection,
     table_name: &str,
     columns: &[&str],
+    dry_run: bool,
+    mismatches: &mut Vec<String>,
 ) -> MigrationResult<usize> {
+    if dry_run {
+        return preview_table(source_conn, dest_conn, table_name, columns, mismatches);
+    }
+
     let columns_str = columns.join(", ");
     let placeholders = columns.iter().map(|_| "?").collect::<Vec<_>>().join(", ");
 
@@ -272,12 +371,58 @@ fn migrate_table(
     Ok(count)
 }
 
+/// Count the rows `migrate_table` would copy, noting the tables and columns
+/// it needs that either database lacks
+fn preview_table(
+    source_conn: &Connection,
+    dest_conn: &Connection,
+    table_name: &str,
+    columns: &[&str],
+    mismatches: &mut Vec<String>,
+) -> MigrationResult<usize> {
+    let source_columns = table_columns(source_conn, table_name)?;
+    let dest_columns = table_columns(dest_conn, table_name)?;
+
+    for (side, present) in [("source", &source_columns), ("destination", &dest_columns)] {
+        if present.is_empty() {
+            mismatches.push(format!("Table {} is missing from the {}", table_name, side));
+            continue;
+        }
+        for column in columns.iter().filter(|c| !present.iter().any(|p| p == *c)) {
+            mismatches.push(format!(
+                "Column {}.{} is missing from the {}",
+                table_name, column, side
+            ));
+        }
+    }
+
+    if source_columns.is_empty() {
+        return Ok(0);
+    }
+    let count: i64 =
+        source_conn.query_row(&format!("SELECT COUNT(*) FROM {}", table_name), [], |row| {
+            row.get(0)
+        })?;
+    Ok(count as usize)
+}
+
+/// Column names of a table; empty when the table doesn't exist
+fn table_columns(conn: &Connection, table_name: &str) -> MigrationResult<Vec<String>> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", table_name))?;
+    let columns = stmt
+        .query_map([], |row| row.get::<_, String>(1))?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(columns)
+}
+
 /// Migrate a table that may or may not exist in the source database
 fn migrate_table_optional(
     source_conn: &Connection,
     dest_conn: &Connection,
     table_name: &str,
     columns: &[&str],
+    dry_run: bool,
+    mismatches: &mut Vec<String>,
 ) -> MigrationResult<usize> {
     // Check if table exists in source
     let table_exists: bool = source_conn
@@ -293,7 +438,14 @@ fn migrate_table_optional(
         return Ok(0);
     }
 
-    migrate_table(source_conn, dest_conn, table_name, columns)
+    migrate_table(
+        source_conn,
+        dest_conn,
+        table_name,
+        columns,
+        dry_run,
+        mismatches,
+    )
 }
 
 /// Verify data integrity after migration
@@ -306,6 +458,10 @@ pub fn verify_migration(
     source_path: &Path,
     dest_conn: &Connection,
 ) -> MigrationResult<Vec<String>> {
+    if !source_path.exists() {
+        return Err(MigrationError::SourceNotFound(source_path.to_path_buf()));
+    }
+
     let source_conn = Connection::open(source_path)?;
     let mut warnings = Vec::new();
 
@@ -397,6 +553,363 @@ pub fn default_rust_db_path() -> PathBuf {
     home.join(".claude-manager").join("claude-manager.db")
 }
 
+/// Agent-manager layouts that can be imported besides the Node.js database
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportFormat {
+    /// claude-squad `state.json` (instances backed by git worktrees)
+    ClaudeSquad,
+    /// Directory of tmux session scripts using `new-session`/`new-window` with `-c <dir>`
+    TmuxSessions,
+}
+
+/// A session found in a foreign layout, resolved to repository paths
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImportedSession {
+    pub name: String,
+    pub workspace_path: String,
+    pub worktree_path: String,
+    pub branch: String,
+}
+
+/// What an import would write (dry run) or has written
+#[derive(Debug, Clone, Default)]
+pub struct ImportReport {
+    pub dry_run: bool,
+    pub sessions_found: usize,
+    /// Paths of workspaces that are new to the database
+    pub workspaces_created: Vec<String>,
+    /// Paths of worktrees that are new to the database
+    pub worktrees_created: Vec<String>,
+    /// Names of agents that are new to the database
+    pub agents_created: Vec<String>,
+    /// Sessions that could not be mapped, with the reason
+    pub skipped: Vec<String>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(untagged)]
+enum ClaudeSquadState {
+    Wrapped { instances: Vec<ClaudeSquadInstance> },
+    Bare(Vec<ClaudeSquadInstance>),
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct ClaudeSquadInstance {
+    title: String,
+    #[serde(default)]
+    path: String,
+    #[serde(default)]
+    branch: String,
+    #[serde(default)]
+    worktree: Option<ClaudeSquadWorktree>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct ClaudeSquadWorktree {
+    repo_path: String,
+    worktree_path: String,
+    #[serde(default)]
+    branch_name: String,
+}
+
+/// Import sessions from another agent manager into the Rust backend database
+///
+/// Existing workspaces and worktrees are matched by path and reused; agents are
+/// skipped if their worktree already has an active agent with the same name.
+/// With `dry_run` set, nothing is written and the report describes the plan.
+pub fn import_sessions(
+    format: ImportFormat,
+    source_path: &Path,
+    dest_conn: &Connection,
+    dry_run: bool,
+) -> MigrationResult<ImportReport> {
+    if !source_path.exists() {
+        return Err(MigrationError::SourceNotFound(source_path.to_path_buf()));
+    }
+
+    let mut report = ImportReport {
+        dry_run,
+        ..Default::default()
+    };
+
+    let sessions = match format {
+        ImportFormat::ClaudeSquad => parse_claude_squad(source_path)?,
+        ImportFormat::TmuxSessions => parse_tmux_sessions(source_path, &mut report.skipped)?,
+    };
+    report.sessions_found = sessions.len() + report.skipped.len();
+
+    let tx = dest_conn.unchecked_transaction()?;
+    let mut workspace_ids: std::collections::HashMap<String, String> = Default::default();
+    let mut worktree_ids: std::collections::HashMap<String, String> = Default::default();
+
+    for session in &sessions {
+        let workspace_id = match workspace_ids.get(&session.workspace_path) {
+            Some(id) => id.clone(),
+            None => {
+                let id = match find_id_by_path(&tx, "workspaces", &session.workspace_path)? {
+                    Some(id) => id,
+                    None => {
+                        let id = new_id("ws");
+                        tx.execute(
+                            "INSERT INTO workspaces (id, name, path) VALUES (?, ?, ?)",
+                            rusqlite::params![
+                                id,
+                                path_name(&session.workspace_path),
+                                session.workspace_path
+                            ],
+                        )?;
+                        report
+                            .workspaces_created
+                            .push(session.workspace_path.clone());
+                        id
+                    }
+                };
+                workspace_ids.insert(session.workspace_path.clone(), id.clone());
+                id
+            }
+        };
+
+        let worktree_id = match worktree_ids.get(&session.worktree_path) {
+            Some(id) => id.clone(),
+            None => {
+                let id = match find_id_by_path(&tx, "worktrees", &session.worktree_path)? {
+                    Some(id) => id,
+                    None => {
+                        let id = new_id("wt");
+                        tx.execute(
+                            r#"
+                            INSERT INTO worktrees (id, workspace_id, name, branch, path, is_main, display_order)
+                            VALUES (?, ?, ?, ?, ?, ?,
+                                (SELECT COALESCE(MAX(display_order) + 1, 0) FROM worktrees WHERE workspace_id = ?))
+                        "#,
+                            rusqlite::params![
+                                id,
+                                workspace_id,
+                                path_name(&session.worktree_path),
+                                session.branch,
+                                session.worktree_path,
+                                session.worktree_path == session.workspace_path,
+                                workspace_id,
+                            ],
+                        )?;
+                        report.worktrees_created.push(session.worktree_path.clone());
+                        id
+                    }
+                };
+                worktree_ids.insert(session.worktree_path.clone(), id.clone());
+                id
+            }
+        };
+
+        let exists: bool = tx.query_row(
+            "SELECT COUNT(*) > 0 FROM agents WHERE worktree_id = ? AND name = ? AND deleted_at IS NULL",
+            rusqlite::params![worktree_id, session.name],
+            |row| row.get(0),
+        )?;
+        if exists {
+            report
+                .skipped
+                .push(format!("{}: agent already exists", session.name));
+            continue;
+        }
+
+        tx.execute(
+            r#"
+            INSERT INTO agents (id, worktree_id, name, display_order)
+            VALUES (?, ?, ?,
+                (SELECT COALESCE(MAX(display_order) + 1, 0) FROM agents WHERE worktree_id = ?))
+        "#,
+            rusqlite::params![new_id("ag"), worktree_id, session.name, worktree_id],
+        )?;
+        report.agents_created.push(session.name.clone());
+    }
+
+    for workspace_id in workspace_ids.values() {
+        tx.execute(
+            r#"
+            UPDATE workspaces SET
+                worktree_count = (SELECT COUNT(*) FROM worktrees WHERE workspace_id = ?1),
+                agent_count = (
+                    SELECT COUNT(*) FROM agents a
+                    JOIN worktrees w ON a.worktree_id = w.id
+                    WHERE w.workspace_id = ?1 AND a.deleted_at IS NULL
+                ),
+                updated_at = datetime('now')
+            WHERE id = ?1
+        "#,
+            [workspace_id],
+        )?;
+    }
+
+    if dry_run {
+        tx.rollback()?;
+    } else {
+        tx.commit()?;
+        tracing::info!(
+            "Imported {} workspaces, {} worktrees, {} agents from {}",
+            report.workspaces_created.len(),
+            report.worktrees_created.len(),
+            report.agents_created.len(),
+            source_path.display()
+        );
+    }
+
+    Ok(report)
+}
+
+/// Parse a claude-squad `state.json`
+fn parse_claude_squad(path: &Path) -> MigrationResult<Vec<ImportedSession>> {
+    let content = fs::read_to_string(path)?;
+    let state: ClaudeSquadState = serde_json::from_str(&content)
+        .map_err(|e| MigrationError::Validation(format!("Invalid claude-squad state: {}", e)))?;
+
+    let instances = match state {
+        ClaudeSquadState::Wrapped { instances } => instances,
+        ClaudeSquadState::Bare(instances) => instances,
+    };
+
+    Ok(instances
+        .into_iter()
+        .map(|instance| match instance.worktree {
+            Some(worktree) => ImportedSession {
+                name: instance.title,
+                workspace_path: trim_path(&worktree.repo_path),
+                worktree_path: trim_path(&worktree.worktree_path),
+                branch: if worktree.branch_name.is_empty() {
+                    instance.branch
+                } else {
+                    worktree.branch_name
+                },
+            },
+            None => ImportedSession {
+                name: instance.title,
+                workspace_path: trim_path(&instance.path),
+                worktree_path: trim_path(&instance.path),
+                branch: instance.branch,
+            },
+        })
+        .collect())
+}
+
+/// Parse every tmux script in a directory
+///
+/// Each `new-session`/`new-window` line with a `-c <dir>` becomes a session; the
+/// directory is resolved to its git repository and worktree.
+fn parse_tmux_sessions(
+    dir: &Path,
+    skipped: &mut Vec<String>,
+) -> MigrationResult<Vec<ImportedSession>> {
+    let mut files: Vec<PathBuf> = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.is_file())
+        .collect();
+    files.sort();
+
+    let mut sessions = Vec::new();
+    for file in files {
+        let content = fs::read_to_string(&file)?;
+        let default_name = file
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or("tmux")
+            .to_string();
+
+        for line in content.lines() {
+            let tokens: Vec<&str> = line
+                .split_whitespace()
+                .map(|t| t.trim_matches(|c| c == '"' || c == '\''))
+                .collect();
+            let name_flag = if tokens.contains(&"new-session") {
+                "-s"
+            } else if tokens.contains(&"new-window") {
+                "-n"
+            } else {
+                continue;
+            };
+
+            let flag_value = |flag: &str| {
+                tokens
+                    .iter()
+                    .position(|t| *t == flag)
+                    .and_then(|i| tokens.get(i + 1))
+                    .map(|v| v.to_string())
+            };
+            let Some(dir) = flag_value("-c") else {
+                continue;
+            };
+            let name = flag_value(name_flag).unwrap_or_else(|| default_name.clone());
+
+            match resolve_git_paths(&expand_home(&dir)) {
+                Some((workspace_path, worktree_path, branch)) => sessions.push(ImportedSession {
+                    name,
+                    workspace_path,
+                    worktree_path,
+                    branch,
+                }),
+                None => skipped.push(format!("{}: {} is not a git repository", name, dir)),
+            }
+        }
+    }
+
+    Ok(sessions)
+}
+
+/// Resolve a directory to (main repository path, worktree path, branch)
+fn resolve_git_paths(dir: &Path) -> Option<(String, String, String)> {
+    let repo = git2::Repository::discover(dir).ok()?;
+    let worktree_path = trim_path(&repo.workdir()?.to_string_lossy());
+
+    let workspace_path = if repo.is_worktree() {
+        // Linked worktrees keep their git dir at <main>/.git/worktrees/<name>
+        trim_path(&repo.path().ancestors().nth(3)?.to_string_lossy())
+    } else {
+        worktree_path.clone()
+    };
+
+    let branch = repo
+        .head()
+        .ok()
+        .and_then(|head| head.shorthand().map(|s| s.to_string()))
+        .unwrap_or_else(|| "HEAD".to_string());
+
+    Some((workspace_path, worktree_path, branch))
+}
+
+fn find_id_by_path(conn: &Connection, table: &str, path: &str) -> MigrationResult<Option<String>> {
+    let sql = format!("SELECT id FROM {} WHERE path = ?", table);
+    match conn.query_row(&sql, [path], |row| row.get(0)) {
+        Ok(id) => Ok(Some(id)),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn path_name(path: &str) -> String {
+    Path::new(path)
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or(path)
+        .to_string()
+}
+
+fn trim_path(path: &str) -> String {
+    let trimmed = path.trim_end_matches('/');
+    if trimmed.is_empty() {
+        path.to_string()
+    } else {
+        trimmed.to_string()
+    }
+}
+
+fn expand_home(path: &str) -> PathBuf {
+    match path.strip_prefix("~/") {
+        Some(rest) => dirs::home_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join(rest),
+        None => PathBuf::from(path),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -521,7 +1034,7 @@ mod tests {
         let _source_conn = setup_source_db(&source_path);
         let dest_conn = setup_dest_db(&dest_path);
 
-        let stats = migrate_from_nodejs(&source_path, &dest_conn).unwrap();
+        let stats = migrate_from_nodejs(&source_path, &dest_conn, false).unwrap();
 
         assert_eq!(stats.workspaces_migrated, 1);
         assert_eq!(stats.worktrees_migrated, 1);
@@ -529,6 +1042,115 @@ mod tests {
         assert_eq!(stats.messages_migrated, 1);
     }
 
+    #[test]
+    fn test_migrate_dry_run_writes_nothing() {
+        let temp_dir = tempdir().unwrap();
+        let source_path = temp_dir.path().join("source.db");
+        let dest_path = temp_dir.path().join("dest.db");
+
+        let _source_conn = setup_source_db(&source_path);
+        let dest_conn = setup_dest_db(&dest_path);
+
+        let preview = migrate_from_nodejs(&source_path, &dest_conn, true).unwrap();
+        assert!(preview.dry_run);
+        assert_eq!(preview.workspaces_migrated, 1);
+        assert_eq!(preview.agents_migrated, 1);
+        assert_eq!(preview.messages_migrated, 1);
+        assert_eq!(preview.statuses_converted, 1);
+        assert!(
+            preview.schema_mismatches.is_empty(),
+            "{:?}",
+            preview.schema_mismatches
+        );
+
+        let workspaces: i64 = dest_conn
+            .query_row("SELECT COUNT(*) FROM workspaces", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(workspaces, 0);
+
+        // The preview matches what the migration then does
+        let stats = migrate_from_nodejs(&source_path, &dest_conn, false).unwrap();
+        assert!(!stats.dry_run);
+        assert_eq!(stats.total(), preview.total());
+        assert_eq!(stats.statuses_converted, preview.statuses_converted);
+    }
+
+    #[test]
+    fn test_migrate_dry_run_reports_schema_mismatches() {
+        let temp_dir = tempdir().unwrap();
+        let source_path = temp_dir.path().join("source.db");
+        let dest_path = temp_dir.path().join("dest.db");
+
+        let source_conn = setup_source_db(&source_path);
+        source_conn
+            .execute_batch("DROP TABLE agent_sessions; ALTER TABLE messages DROP COLUMN tool_name;")
+            .unwrap();
+        let dest_conn = setup_dest_db(&dest_path);
+
+        let preview = migrate_from_nodejs(&source_path, &dest_conn, true).unwrap();
+        assert_eq!(
+            preview.schema_mismatches,
+            vec![
+                "Column messages.tool_name is missing from the source",
+                "Table agent_sessions is missing from the source",
+            ]
+        );
+        assert_eq!(preview.messages_migrated, 1);
+        assert_eq!(preview.sessions_migrated, 0);
+
+        // The migration itself fails on them
+        assert!(migrate_from_nodejs(&source_path, &dest_conn, false).is_err());
+    }
+
+    #[test]
+    fn test_migrate_reports_each_step() {
+        let temp_dir = tempdir().unwrap();
+        let source_path = temp_dir.path().join("source.db");
+        let dest_path = temp_dir.path().join("dest.db");
+
+        let _source_conn = setup_source_db(&source_path);
+        let dest_conn = setup_dest_db(&dest_path);
+
+        let mut steps = Vec::new();
+        migrate_from_nodejs_with_progress(&source_path, &dest_conn, false, |done, table| {
+            steps.push((done, table.to_string()))
+        })
+        .unwrap();
+
+        assert_eq!(steps.len(), MIGRATION_STEPS);
+        assert_eq!(steps[0], (0, "workspaces".to_string()));
+        assert_eq!(steps[5], (5, "usage_stats".to_string()));
+    }
+
+    #[test]
+    fn test_failed_migration_restores_foreign_keys() {
+        let temp_dir = tempdir().unwrap();
+        let source_path = temp_dir.path().join("source.db");
+        let dest_path = temp_dir.path().join("dest.db");
+
+        // A source without the tables the migration copies
+        Connection::open(&source_path).unwrap();
+        let dest_conn = setup_dest_db(&dest_path);
+        dest_conn.execute("PRAGMA foreign_keys = ON", []).unwrap();
+
+        assert!(migrate_from_nodejs(&source_path, &dest_conn, false).is_err());
+        let foreign_keys: bool = dest_conn
+            .query_row("PRAGMA foreign_keys", [], |row| row.get(0))
+            .unwrap();
+        assert!(foreign_keys);
+    }
+
+    #[test]
+    fn test_verify_missing_source() {
+        let temp_dir = tempdir().unwrap();
+        let source_path = temp_dir.path().join("missing.db");
+        let dest_conn = setup_dest_db(&temp_dir.path().join("dest.db"));
+
+        let result = verify_migration(&source_path, &dest_conn);
+        assert!(matches!(result, Err(MigrationError::SourceNotFound(_))));
+        assert!(!source_path.exists());
+    }
+
     #[test]
     fn test_verify_migration() {
         let temp_dir = tempdir().unwrap();
@@ -538,7 +1160,7 @@ mod tests {
         let _source_conn = setup_source_db(&source_path);
         let dest_conn = setup_dest_db(&dest_path);
 
-        migrate_from_nodejs(&source_path, &dest_conn).unwrap();
+        migrate_from_nodejs(&source_path, &dest_conn, false).unwrap();
 
         let warnings = verify_migration(&source_path, &dest_conn).unwrap();
         assert!(
@@ -557,8 +1179,120 @@ mod tests {
             messages_migrated: 10,
             sessions_migrated: 1,
             usage_stats_migrated: 5,
+            ..Default::default()
         };
 
         assert_eq!(stats.total(), 22);
     }
+
+    #[test]
+    fn test_import_claude_squad_dry_run() {
+        let temp_dir = tempdir().unwrap();
+        let state_path = temp_dir.path().join("state.json");
+        let dest_conn = setup_dest_db(&temp_dir.path().join("dest.db"));
+
+        fs::write(
+            &state_path,
+            r#"{"instances": [
+                {"title": "fix-login", "path": "/repos/app", "branch": "fix-login",
+                 "worktree": {"repo_path": "/repos/app", "worktree_path": "/worktrees/fix-login", "branch_name": "fix-login"}},
+                {"title": "main-agent", "path": "/repos/app/", "branch": "main"}
+            ]}"#,
+        )
+        .unwrap();
+
+        let report =
+            import_sessions(ImportFormat::ClaudeSquad, &state_path, &dest_conn, true).unwrap();
+
+        assert!(report.dry_run);
+        assert_eq!(report.sessions_found, 2);
+        assert_eq!(report.workspaces_created, vec!["/repos/app"]);
+        assert_eq!(report.worktrees_created.len(), 2);
+        assert_eq!(report.agents_created, vec!["fix-login", "main-agent"]);
+
+        let count: i64 = dest_conn
+            .query_row("SELECT COUNT(*) FROM workspaces", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(count, 0, "Dry run should not write anything");
+    }
+
+    #[test]
+    fn test_import_claude_squad_is_idempotent() {
+        let temp_dir = tempdir().unwrap();
+        let state_path = temp_dir.path().join("state.json");
+        let dest_conn = setup_dest_db(&temp_dir.path().join("dest.db"));
+
+        fs::write(
+            &state_path,
+            r#"[{"title": "feature", "branch": "feature",
+                 "worktree": {"repo_path": "/repos/app", "worktree_path": "/worktrees/feature", "branch_name": "feature"}}]"#,
+        )
+        .unwrap();
+
+        import_sessions(ImportFormat::ClaudeSquad, &state_path, &dest_conn, false).unwrap();
+        let report =
+            import_sessions(ImportFormat::ClaudeSquad, &state_path, &dest_conn, false).unwrap();
+
+        assert!(report.workspaces_created.is_empty());
+        assert!(report.worktrees_created.is_empty());
+        assert!(report.agents_created.is_empty());
+        assert_eq!(report.skipped.len(), 1);
+
+        let (worktree_count, agent_count): (i64, i64) = dest_conn
+            .query_row(
+                "SELECT worktree_count, agent_count FROM workspaces WHERE path = '/repos/app'",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!(worktree_count, 1);
+        assert_eq!(agent_count, 1);
+    }
+
+    #[test]
+    fn test_import_tmux_sessions() {
+        let temp_dir = tempdir().unwrap();
+        let repo_dir = temp_dir.path().join("repo");
+        let scripts_dir = temp_dir.path().join("tmux");
+        fs::create_dir_all(&scripts_dir).unwrap();
+        git2::Repository::init(&repo_dir).unwrap();
+        let dest_conn = setup_dest_db(&temp_dir.path().join("dest.db"));
+
+        fs::write(
+            scripts_dir.join("work.sh"),
+            format!(
+                "tmux new-session -d -s backend -c {repo}\ntmux new-window -n tests -c \"{repo}\"\ntmux new-window -n scratch -c /nonexistent/dir\n",
+                repo = repo_dir.display()
+            ),
+        )
+        .unwrap();
+
+        let report =
+            import_sessions(ImportFormat::TmuxSessions, &scripts_dir, &dest_conn, false).unwrap();
+
+        assert_eq!(report.sessions_found, 3);
+        assert_eq!(report.workspaces_created.len(), 1);
+        assert_eq!(report.worktrees_created.len(), 1);
+        assert_eq!(report.agents_created, vec!["backend", "tests"]);
+        assert_eq!(report.skipped.len(), 1);
+
+        let is_main: bool = dest_conn
+            .query_row("SELECT is_main FROM worktrees", [], |row| row.get(0))
+            .unwrap();
+        assert!(is_main);
+    }
+
+    #[test]
+    fn test_import_missing_source() {
+        let temp_dir = tempdir().unwrap();
+        let dest_conn = setup_dest_db(&temp_dir.path().join("dest.db"));
+
+        let result = import_sessions(
+            ImportFormat::ClaudeSquad,
+            &temp_dir.path().join("missing.json"),
+            &dest_conn,
+            true,
+        );
+        assert!(matches!(result, Err(MigrationError::SourceNotFound(_))));
+    }
 }
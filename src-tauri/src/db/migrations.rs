@@ -1,11 +1,212 @@
 //! Database migrations
+//!
+//! Each migration carries an `up` script and a `down` script. Applied versions are
+//! recorded in `schema_migrations`, so a database can be moved to any known version
+//! one step at a time in either direction.
 
 use rusqlite::Connection;
 
-use super::DbResult;
+use super::{DbError, DbResult};
+
+/// A single versioned schema change
+#[derive(Debug, Clone, Copy)]
+pub struct Migration {
+    pub version: i64,
+    pub name: &'static str,
+    pub up: &'static str,
+    pub down: &'static str,
+}
+
+impl Migration {
+    /// Checksum of the up script, stored alongside the applied version
+    pub fn checksum(&self) -> String {
+        // FNV-1a: stable across builds and platforms, unlike std's hasher
+        let hash = self.up.bytes().fold(0xcbf29ce484222325u64, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+        });
+        format!("{:016x}", hash)
+    }
+}
+
+/// All known migrations in version order
+pub fn migrations() -> Vec<Migration> {
+    vec![
+        Migration {
+            version: 1,
+            name: "initial_schema",
+            up: include_str!("migrations/001_initial_schema.sql"),
+            down: include_str!("migrations/001_initial_schema.down.sql"),
+        },
+        Migration {
+            version: 2,
+            name: "rename_finished_to_idle",
+            up: include_str!("migrations/002_rename_finished_to_idle.sql"),
+            down: include_str!("migrations/002_rename_finished_to_idle.down.sql"),
+        },
+        Migration {
+            version: 3,
+            name: "remote_access_settings",
+            up: include_str!("migrations/003_remote_access_settings.sql"),
+            down: include_str!("migrations/003_remote_access_settings.down.sql"),
+        },
+        Migration {
+            version: 4,
+            name: "process_event_journal",
+            up: include_str!("migrations/004_process_event_journal.sql"),
+            down: include_str!("migrations/004_process_event_journal.down.sql"),
+        },
+        Migration {
+            version: 5,
+            name: "agent_status_reason",
+            up: include_str!("migrations/005_agent_status_reason.sql"),
+            down: include_str!("migrations/005_agent_status_reason.down.sql"),
+        },
+        Migration {
+            version: 6,
+            name: "worktree_pr_url",
+            up: include_str!("migrations/006_worktree_pr_url.sql"),
+            down: include_str!("migrations/006_worktree_pr_url.down.sql"),
+        },
+        Migration {
+            version: 7,
+            name: "task_groups",
+            up: include_str!("migrations/007_task_groups.sql"),
+            down: include_str!("migrations/007_task_groups.down.sql"),
+        },
+        Migration {
+            version: 8,
+            name: "task_group_resolution",
+            up: include_str!("migrations/008_task_group_resolution.sql"),
+            down: include_str!("migrations/008_task_group_resolution.down.sql"),
+        },
+        Migration {
+            version: 9,
+            name: "agent_tags",
+            up: include_str!("migrations/009_agent_tags.sql"),
+            down: include_str!("migrations/009_agent_tags.down.sql"),
+        },
+        Migration {
+            version: 10,
+            name: "snippets",
+            up: include_str!("migrations/010_snippets.sql"),
+            down: include_str!("migrations/010_snippets.down.sql"),
+        },
+        Migration {
+            version: 11,
+            name: "agent_fork_session",
+            up: include_str!("migrations/011_agent_fork_session.sql"),
+            down: include_str!("migrations/011_agent_fork_session.down.sql"),
+        },
+        Migration {
+            version: 12,
+            name: "drafts",
+            up: include_str!("migrations/012_drafts.sql"),
+            down: include_str!("migrations/012_drafts.down.sql"),
+        },
+        Migration {
+            version: 13,
+            name: "worktree_sparse_patterns",
+            up: include_str!("migrations/013_worktree_sparse_patterns.sql"),
+            down: include_str!("migrations/013_worktree_sparse_patterns.down.sql"),
+        },
+        Migration {
+            version: 14,
+            name: "agent_runs",
+            up: include_str!("migrations/014_agent_runs.sql"),
+            down: include_str!("migrations/014_agent_runs.down.sql"),
+        },
+        Migration {
+            version: 15,
+            name: "audit_log",
+            up: include_str!("migrations/015_audit_log.sql"),
+            down: include_str!("migrations/015_audit_log.down.sql"),
+        },
+        Migration {
+            version: 16,
+            name: "agent_run_retries",
+            up: include_str!("migrations/016_agent_run_retries.sql"),
+            down: include_str!("migrations/016_agent_run_retries.down.sql"),
+        },
+        Migration {
+            version: 17,
+            name: "schedules",
+            up: include_str!("migrations/017_schedules.sql"),
+            down: include_str!("migrations/017_schedules.down.sql"),
+        },
+        Migration {
+            version: 18,
+            name: "agent_system_prompts",
+            up: include_str!("migrations/018_agent_system_prompts.sql"),
+            down: include_str!("migrations/018_agent_system_prompts.down.sql"),
+        },
+        Migration {
+            version: 19,
+            name: "agent_tool_rules",
+            up: include_str!("migrations/019_agent_tool_rules.sql"),
+            down: include_str!("migrations/019_agent_tool_rules.down.sql"),
+        },
+        Migration {
+            version: 20,
+            name: "agent_run_activity",
+            up: include_str!("migrations/020_agent_run_activity.sql"),
+            down: include_str!("migrations/020_agent_run_activity.down.sql"),
+        },
+        Migration {
+            version: 21,
+            name: "worktree_archive",
+            up: include_str!("migrations/021_worktree_archive.sql"),
+            down: include_str!("migrations/021_worktree_archive.down.sql"),
+        },
+        Migration {
+            version: 22,
+            name: "jobs",
+            up: include_str!("migrations/022_jobs.sql"),
+            down: include_str!("migrations/022_jobs.down.sql"),
+        },
+        Migration {
+            version: 23,
+            name: "agent_run_resources",
+            up: include_str!("migrations/023_agent_run_resources.sql"),
+            down: include_str!("migrations/023_agent_run_resources.down.sql"),
+        },
+        Migration {
+            version: 24,
+            name: "worktree_pin_missing",
+            up: include_str!("migrations/024_worktree_pin_missing.sql"),
+            down: include_str!("migrations/024_worktree_pin_missing.down.sql"),
+        },
+    ]
+}
+
+/// Highest schema version this build knows how to migrate to
+pub fn latest_version() -> i64 {
+    migrations().last().map(|m| m.version).unwrap_or(0)
+}
+
+/// Highest schema version recorded in a database (0 if none applied)
+pub fn current_version(conn: &Connection) -> DbResult<i64> {
+    let version: Option<i64> =
+        conn.query_row("SELECT MAX(version) FROM schema_migrations", [], |row| {
+            row.get(0)
+        })?;
+    Ok(version.unwrap_or(0))
+}
 
 /// Run all pending migrations
 pub fn run_migrations(conn: &Connection) -> DbResult<()> {
+    migrate_to(conn, latest_version())
+}
+
+/// Upgrade or downgrade the schema to `target`, one migration at a time
+pub fn migrate_to(conn: &Connection, target: i64) -> DbResult<()> {
+    if target < 0 || target > latest_version() {
+        return Err(DbError::Migration(format!(
+            "Unknown schema version {} (latest is {})",
+            target,
+            latest_version()
+        )));
+    }
+
     // Create migrations table
     conn.execute(
         r#"
@@ -19,38 +220,111 @@ pub fn run_migrations(conn: &Connection) -> DbResult<()> {
         [],
     )?;
 
-    let migrations = vec![
-        (
-            1,
-            "initial_schema",
-            include_str!("migrations/001_initial_schema.sql"),
-        ),
-        (
-            2,
-            "rename_finished_to_idle",
-            include_str!("migrations/002_rename_finished_to_idle.sql"),
-        ),
-    ];
-
-    for (version, name, sql) in migrations {
-        let applied: bool = conn
+    // Table rebuilds drop and recreate referenced tables, which would fire
+    // ON DELETE actions. Foreign keys can only be toggled outside a transaction.
+    let foreign_keys: bool = conn.query_row("PRAGMA foreign_keys", [], |row| row.get(0))?;
+    conn.execute_batch("PRAGMA foreign_keys = OFF;")?;
+
+    let result = apply_migrations(conn, target);
+
+    if foreign_keys {
+        conn.execute_batch("PRAGMA foreign_keys = ON;")?;
+    }
+
+    result
+}
+
+fn apply_migrations(conn: &Connection, target: i64) -> DbResult<()> {
+    let all = migrations();
+
+    for migration in all.iter().filter(|m| m.version <= target) {
+        let recorded: Option<Option<String>> = conn
             .query_row(
-                "SELECT COUNT(*) > 0 FROM schema_migrations WHERE version = ?",
-                [version],
+                "SELECT checksum FROM schema_migrations WHERE version = ?",
+                [migration.version],
                 |row| row.get(0),
             )
-            .unwrap_or(false);
-
-        if !applied {
-            tracing::info!("Running migration {}: {}", version, name);
-            conn.execute_batch(sql)?;
-            conn.execute(
-                "INSERT INTO schema_migrations (version, name) VALUES (?, ?)",
-                rusqlite::params![version, name],
+            .map(Some)
+            .or_else(|e| match e {
+                rusqlite::Error::QueryReturnedNoRows => Ok(None),
+                e => Err(e),
+            })?;
+
+        match recorded {
+            Some(Some(checksum)) if checksum != migration.checksum() => {
+                tracing::warn!(
+                    "Migration {} ({}) was applied with a different script",
+                    migration.version,
+                    migration.name
+                );
+            }
+            Some(_) => {}
+            None => {
+                tracing::info!(
+                    "Running migration {}: {}",
+                    migration.version,
+                    migration.name
+                );
+                let tx = conn.unchecked_transaction()?;
+                tx.execute_batch(migration.up)?;
+                check_foreign_keys(&tx, migration)?;
+                tx.execute(
+                    "INSERT INTO schema_migrations (version, name, checksum) VALUES (?, ?, ?)",
+                    rusqlite::params![migration.version, migration.name, migration.checksum()],
+                )?;
+                tx.commit()?;
+                tracing::info!(
+                    "Applied migration {}: {}",
+                    migration.version,
+                    migration.name
+                );
+            }
+        }
+    }
+
+    for migration in all.iter().rev().filter(|m| m.version > target) {
+        let applied: bool = conn.query_row(
+            "SELECT COUNT(*) > 0 FROM schema_migrations WHERE version = ?",
+            [migration.version],
+            |row| row.get(0),
+        )?;
+
+        if applied {
+            tracing::info!(
+                "Reverting migration {}: {}",
+                migration.version,
+                migration.name
+            );
+            let tx = conn.unchecked_transaction()?;
+            tx.execute_batch(migration.down)?;
+            check_foreign_keys(&tx, migration)?;
+            tx.execute(
+                "DELETE FROM schema_migrations WHERE version = ?",
+                [migration.version],
             )?;
-            tracing::info!("Applied migration {}: {}", version, name);
+            tx.commit()?;
+            tracing::info!(
+                "Reverted migration {}: {}",
+                migration.version,
+                migration.name
+            );
         }
     }
 
     Ok(())
 }
+
+/// Fail the migration if it left dangling references behind
+fn check_foreign_keys(conn: &Connection, migration: &Migration) -> DbResult<()> {
+    let mut stmt = conn.prepare("PRAGMA foreign_key_check")?;
+    let violations = stmt.query_map([], |row| row.get::<_, String>(0))?.count();
+
+    if violations > 0 {
+        return Err(DbError::Migration(format!(
+            "Migration {} ({}) left {} foreign key violation(s)",
+            migration.version, migration.name, violations
+        )));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,8 @@
+-- Revert the initial schema by dropping every table it created
+DROP TABLE IF EXISTS agent_sessions;
+DROP TABLE IF EXISTS settings;
+DROP TABLE IF EXISTS usage_stats;
+DROP TABLE IF EXISTS messages;
+DROP TABLE IF EXISTS agents;
+DROP TABLE IF EXISTS worktrees;
+DROP TABLE IF EXISTS workspaces;
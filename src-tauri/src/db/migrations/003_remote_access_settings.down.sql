@@ -0,0 +1,2 @@
+DELETE FROM settings
+WHERE key IN ('remote_access_enabled', 'remote_bind_address', 'remote_allow_control');
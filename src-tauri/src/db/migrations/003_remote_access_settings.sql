@@ -0,0 +1,5 @@
+-- Remote access is opt-in; PTY input from remote clients needs a second opt-in
+INSERT OR IGNORE INTO settings (key, value, type, description) VALUES
+    ('remote_access_enabled', 'false', 'boolean', 'Serve the API to other machines over TLS'),
+    ('remote_bind_address', '0.0.0.0:3443', 'string', 'Interface and port for remote access'),
+    ('remote_allow_control', 'false', 'boolean', 'Allow remote clients to start/stop agents and type into terminals');
@@ -0,0 +1,17 @@
+-- Process events that must reach the database even if the app crashes or the
+-- broadcast receiver lags. Rows are written before the event is broadcast and
+-- marked processed once the status sync has applied them.
+CREATE TABLE process_event_journal (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    agent_id TEXT NOT NULL,
+    event_type TEXT NOT NULL CHECK (event_type IN ('status', 'error', 'exit')),
+    status TEXT CHECK (status IN ('running', 'waiting', 'error', 'idle')),
+    message TEXT,
+    exit_code INTEGER,
+    signal TEXT,
+    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+    processed_at TEXT
+);
+
+CREATE INDEX idx_process_event_journal_unprocessed
+    ON process_event_journal(id) WHERE processed_at IS NULL;
@@ -0,0 +1,2 @@
+ALTER TABLE process_event_journal DROP COLUMN stopped_by_user;
+ALTER TABLE agents DROP COLUMN status_reason;
@@ -0,0 +1,5 @@
+-- Why an agent last stopped or failed, shown next to its status
+ALTER TABLE agents ADD COLUMN status_reason TEXT;
+
+-- Exits the user asked for are not failures, whatever the exit code
+ALTER TABLE process_event_journal ADD COLUMN stopped_by_user INTEGER NOT NULL DEFAULT 0;
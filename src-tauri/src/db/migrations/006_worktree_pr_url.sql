@@ -0,0 +1,2 @@
+-- Pull request opened from the worktree's branch
+ALTER TABLE worktrees ADD COLUMN pr_url TEXT;
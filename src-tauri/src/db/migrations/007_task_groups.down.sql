@@ -0,0 +1,3 @@
+DROP INDEX idx_agents_task_group;
+ALTER TABLE agents DROP COLUMN task_group_id;
+DROP TABLE task_groups;
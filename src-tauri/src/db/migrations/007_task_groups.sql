@@ -0,0 +1,14 @@
+-- Agents launched on the same prompt in separate worktrees, so their
+-- attempts can be compared
+CREATE TABLE task_groups (
+    id TEXT PRIMARY KEY,
+    workspace_id TEXT NOT NULL REFERENCES workspaces(id) ON DELETE CASCADE,
+    prompt TEXT NOT NULL,
+    base_branch TEXT NOT NULL,
+    created_at TEXT NOT NULL DEFAULT (datetime('now'))
+);
+
+-- No foreign key: SQLite cannot drop a column that has one
+ALTER TABLE agents ADD COLUMN task_group_id TEXT;
+
+CREATE INDEX idx_agents_task_group ON agents(task_group_id) WHERE task_group_id IS NOT NULL;
@@ -0,0 +1,2 @@
+ALTER TABLE task_groups DROP COLUMN resolved_at;
+ALTER TABLE task_groups DROP COLUMN winner_agent_id;
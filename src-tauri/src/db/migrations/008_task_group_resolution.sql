@@ -0,0 +1,3 @@
+-- Record which attempt of a task group was kept
+ALTER TABLE task_groups ADD COLUMN winner_agent_id TEXT;
+ALTER TABLE task_groups ADD COLUMN resolved_at TEXT;
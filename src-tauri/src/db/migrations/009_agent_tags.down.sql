@@ -0,0 +1,2 @@
+DROP INDEX idx_agent_tags_tag;
+DROP TABLE agent_tags;
@@ -0,0 +1,9 @@
+-- Free-form labels for grouping agents by task, independent of worktrees
+CREATE TABLE agent_tags (
+    agent_id TEXT NOT NULL REFERENCES agents(id) ON DELETE CASCADE,
+    tag TEXT NOT NULL,
+    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+    PRIMARY KEY (agent_id, tag)
+);
+
+CREATE INDEX idx_agent_tags_tag ON agent_tags(tag);
@@ -0,0 +1,3 @@
+DROP INDEX idx_snippets_worktree;
+DROP INDEX idx_snippets_workspace;
+DROP TABLE snippets;
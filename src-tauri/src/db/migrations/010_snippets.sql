@@ -0,0 +1,15 @@
+-- Canned prompts that can be sent to an agent's terminal. Global snippets
+-- have no workspace or worktree; scoped ones disappear with their owner.
+CREATE TABLE snippets (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    body TEXT NOT NULL,
+    scope TEXT NOT NULL DEFAULT 'global' CHECK (scope IN ('global', 'workspace', 'worktree')),
+    workspace_id TEXT REFERENCES workspaces(id) ON DELETE CASCADE,
+    worktree_id TEXT REFERENCES worktrees(id) ON DELETE CASCADE,
+    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+    updated_at TEXT NOT NULL DEFAULT (datetime('now'))
+);
+
+CREATE INDEX idx_snippets_workspace ON snippets(workspace_id) WHERE workspace_id IS NOT NULL;
+CREATE INDEX idx_snippets_worktree ON snippets(worktree_id) WHERE worktree_id IS NOT NULL;
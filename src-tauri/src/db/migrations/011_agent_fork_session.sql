@@ -0,0 +1,2 @@
+-- Session a forked agent branches its conversation from on first start
+ALTER TABLE agents ADD COLUMN fork_session_id TEXT;
@@ -0,0 +1,6 @@
+-- Message the user was composing for an agent, kept across restarts
+CREATE TABLE drafts (
+    agent_id TEXT PRIMARY KEY REFERENCES agents(id) ON DELETE CASCADE,
+    content TEXT NOT NULL,
+    updated_at TEXT NOT NULL DEFAULT (datetime('now'))
+);
@@ -0,0 +1,2 @@
+-- Directories checked out in a sparse (cone mode) worktree, as a JSON array
+ALTER TABLE worktrees ADD COLUMN sparse_patterns TEXT;
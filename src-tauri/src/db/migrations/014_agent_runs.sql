@@ -0,0 +1,13 @@
+-- One row per agent process, from spawn until it exits
+CREATE TABLE agent_runs (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    agent_id TEXT NOT NULL REFERENCES agents(id) ON DELETE CASCADE,
+    session_id TEXT,
+    started_at TEXT NOT NULL DEFAULT (datetime('now')),
+    ended_at TEXT,
+    exit_code INTEGER,
+    signal TEXT,
+    stopped_by_user INTEGER NOT NULL DEFAULT 0
+);
+
+CREATE INDEX idx_agent_runs_agent ON agent_runs(agent_id, id);
@@ -0,0 +1,10 @@
+-- Privileged commands, allowed or refused, newest last
+CREATE TABLE audit_log (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    command TEXT NOT NULL,
+    capability TEXT NOT NULL,
+    origin TEXT NOT NULL,
+    target_id TEXT,
+    allowed INTEGER NOT NULL,
+    created_at TEXT NOT NULL DEFAULT (datetime('now'))
+);
@@ -0,0 +1,2 @@
+ALTER TABLE agent_runs DROP COLUMN attempt;
+ALTER TABLE agent_runs DROP COLUMN retry_of;
@@ -0,0 +1,3 @@
+-- Runs started by retrying an earlier run, and which attempt at the task each run is
+ALTER TABLE agent_runs ADD COLUMN retry_of INTEGER;
+ALTER TABLE agent_runs ADD COLUMN attempt INTEGER NOT NULL DEFAULT 1;
@@ -0,0 +1,21 @@
+-- Recurring agent runs. Each time the cron expression matches, a new agent
+-- with the schedule's mode and permissions is started in the worktree.
+CREATE TABLE schedules (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    cron TEXT NOT NULL,
+    worktree_id TEXT NOT NULL REFERENCES worktrees(id) ON DELETE CASCADE,
+    mode TEXT NOT NULL DEFAULT 'regular' CHECK (mode IN ('auto', 'plan', 'regular')),
+    permissions TEXT NOT NULL DEFAULT '["read"]',
+    prompt TEXT NOT NULL,
+    paused INTEGER NOT NULL DEFAULT 0,
+    next_run_at TEXT,
+    last_run_at TEXT,
+    last_agent_id TEXT REFERENCES agents(id) ON DELETE SET NULL,
+    last_error TEXT,
+    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+    updated_at TEXT NOT NULL DEFAULT (datetime('now'))
+);
+
+CREATE INDEX idx_schedules_worktree ON schedules(worktree_id);
+CREATE INDEX idx_schedules_due ON schedules(next_run_at) WHERE paused = 0;
@@ -0,0 +1,2 @@
+ALTER TABLE agents DROP COLUMN append_system_prompt;
+ALTER TABLE agents DROP COLUMN system_prompt;
@@ -0,0 +1,3 @@
+-- Instructions passed to the Claude CLI in place of, or after, its system prompt
+ALTER TABLE agents ADD COLUMN system_prompt TEXT;
+ALTER TABLE agents ADD COLUMN append_system_prompt TEXT;
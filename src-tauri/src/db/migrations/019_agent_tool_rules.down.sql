@@ -0,0 +1,2 @@
+ALTER TABLE agents DROP COLUMN disallowed_tools;
+ALTER TABLE agents DROP COLUMN allowed_tools;
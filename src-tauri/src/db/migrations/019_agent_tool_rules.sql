@@ -0,0 +1,3 @@
+-- Tool patterns an agent may use, or must not use, on top of its permission presets
+ALTER TABLE agents ADD COLUMN allowed_tools TEXT NOT NULL DEFAULT '[]';
+ALTER TABLE agents ADD COLUMN disallowed_tools TEXT NOT NULL DEFAULT '[]';
@@ -0,0 +1,5 @@
+ALTER TABLE agent_runs DROP COLUMN status_since;
+ALTER TABLE agent_runs DROP COLUMN activity_status;
+ALTER TABLE agent_runs DROP COLUMN idle_ms;
+ALTER TABLE agent_runs DROP COLUMN waiting_ms;
+ALTER TABLE agent_runs DROP COLUMN active_ms;
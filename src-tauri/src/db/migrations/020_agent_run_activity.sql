@@ -0,0 +1,8 @@
+-- Time each run spent working, waiting on the user and idle at the prompt,
+-- split at status changes. The current stretch runs from status_since (or
+-- started_at) in activity_status (or 'running') and is added on the next change.
+ALTER TABLE agent_runs ADD COLUMN active_ms INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE agent_runs ADD COLUMN waiting_ms INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE agent_runs ADD COLUMN idle_ms INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE agent_runs ADD COLUMN activity_status TEXT;
+ALTER TABLE agent_runs ADD COLUMN status_since TEXT;
@@ -0,0 +1 @@
+ALTER TABLE worktrees DROP COLUMN archived_at;
@@ -0,0 +1,3 @@
+-- When a worktree was archived; archived worktrees keep their checkout and
+-- agents but are hidden from the workspace until restored or purged
+ALTER TABLE worktrees ADD COLUMN archived_at TEXT;
@@ -0,0 +1,2 @@
+ALTER TABLE agent_runs DROP COLUMN peak_cpu_percent;
+ALTER TABLE agent_runs DROP COLUMN peak_memory_bytes;
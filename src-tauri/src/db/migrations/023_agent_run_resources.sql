@@ -0,0 +1,4 @@
+-- Highest memory (resident set, in bytes) and CPU (percent of one core)
+-- sampled from each run's process; NULL until the first sample
+ALTER TABLE agent_runs ADD COLUMN peak_memory_bytes INTEGER;
+ALTER TABLE agent_runs ADD COLUMN peak_cpu_percent REAL;
@@ -0,0 +1,2 @@
+ALTER TABLE worktrees DROP COLUMN missing_since;
+ALTER TABLE worktrees DROP COLUMN pinned;
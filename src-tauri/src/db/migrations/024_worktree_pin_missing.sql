@@ -0,0 +1,4 @@
+-- Pinned worktrees are never pruned by a workspace scan
+ALTER TABLE worktrees ADD COLUMN pinned INTEGER NOT NULL DEFAULT 0;
+-- When a scan first found the worktree's checkout unavailable; NULL while it is there
+ALTER TABLE worktrees ADD COLUMN missing_since TEXT;
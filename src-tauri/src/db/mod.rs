@@ -4,15 +4,32 @@
 //! and repository implementations for all data access.
 
 pub mod connection;
+pub mod crypto;
 pub mod migration_tool;
 pub mod migrations;
 pub mod repositories;
 
-pub use connection::{init_database, DbError, DbPool, DbResult};
+pub use connection::{init_database, open_database, DbError, DbPool, DbResult};
 pub use migration_tool::{
-    backup_database, migrate_from_nodejs, verify_migration, MigrationError, MigrationResult,
-    MigrationStats,
+    backup_database, default_nodejs_db_path, import_sessions, migrate_from_nodejs,
+    migrate_from_nodejs_with_progress, verify_migration, ImportFormat, ImportReport,
+    MigrationError, MigrationResult, MigrationStats, MIGRATION_STEPS,
 };
 pub use repositories::{
-    AgentRepository, UsageRepository, WorkspaceRepository, WorktreeRepository,
+    AgentRepository, AgentRunRepository, AgentSessionRepository, AuditRepository, DraftRepository,
+    EventJournalRepository, JobRepository, MessageRepository, ScheduleRepository,
+    SettingsRepository, SnippetRepository, TaskGroupRepository, UsageRepository,
+    WorkspaceRepository, WorktreeRepository,
 };
+
+/// A migrated database in a temporary directory, for tests. The database
+/// goes with the directory, so keep it for as long as the pool.
+#[cfg(test)]
+pub(crate) fn test_pool() -> (DbPool, tempfile::TempDir) {
+    let dir = tempfile::tempdir().unwrap();
+    let manager = r2d2_sqlite::SqliteConnectionManager::file(dir.path().join("app.db"))
+        .with_init(|conn| conn.execute_batch("PRAGMA foreign_keys = ON;"));
+    let pool = r2d2::Pool::builder().max_size(5).build(manager).unwrap();
+    migrations::run_migrations(&pool.get().unwrap()).unwrap();
+    (pool, dir)
+}
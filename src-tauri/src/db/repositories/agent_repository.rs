@@ -2,7 +2,7 @@
 
 use rusqlite::params;
 
-use crate::db::{DbPool, DbResult};
+use crate::db::{DbError, DbPool, DbResult};
 use crate::types::{Agent, AgentRow, AgentStatus};
 
 pub struct AgentRepository {
@@ -20,7 +20,10 @@ impl AgentRepository {
             r#"
             SELECT id, worktree_id, name, status, context_level, mode, permissions,
                    display_order, pid, session_id, created_at, updated_at,
-                   started_at, stopped_at, deleted_at, parent_agent_id
+                   started_at, stopped_at, deleted_at, parent_agent_id, status_reason,
+                   task_group_id, fork_session_id, system_prompt, append_system_prompt,
+                   allowed_tools, disallowed_tools,
+                   (SELECT json_group_array(tag) FROM agent_tags WHERE agent_id = agents.id) AS tags
             FROM agents WHERE id = ?
         "#,
         )?;
@@ -44,6 +47,14 @@ impl AgentRepository {
                     stopped_at: row.get(13)?,
                     deleted_at: row.get(14)?,
                     parent_agent_id: row.get(15)?,
+                    status_reason: row.get(16)?,
+                    task_group_id: row.get(17)?,
+                    fork_session_id: row.get(18)?,
+                    system_prompt: row.get(19)?,
+                    append_system_prompt: row.get(20)?,
+                    allowed_tools: row.get(21)?,
+                    disallowed_tools: row.get(22)?,
+                    tags: row.get(23)?,
                 })
             })
             .optional()?;
@@ -61,14 +72,20 @@ impl AgentRepository {
             r#"
                 SELECT id, worktree_id, name, status, context_level, mode, permissions,
                        display_order, pid, session_id, created_at, updated_at,
-                       started_at, stopped_at, deleted_at, parent_agent_id
+                       started_at, stopped_at, deleted_at, parent_agent_id, status_reason,
+                       task_group_id, fork_session_id, system_prompt, append_system_prompt,
+                       allowed_tools, disallowed_tools,
+                       (SELECT json_group_array(tag) FROM agent_tags WHERE agent_id = agents.id) AS tags
                 FROM agents WHERE worktree_id = ? ORDER BY display_order
             "#
         } else {
             r#"
                 SELECT id, worktree_id, name, status, context_level, mode, permissions,
                        display_order, pid, session_id, created_at, updated_at,
-                       started_at, stopped_at, deleted_at, parent_agent_id
+                       started_at, stopped_at, deleted_at, parent_agent_id, status_reason,
+                       task_group_id, fork_session_id, system_prompt, append_system_prompt,
+                       allowed_tools, disallowed_tools,
+                       (SELECT json_group_array(tag) FROM agent_tags WHERE agent_id = agents.id) AS tags
                 FROM agents WHERE worktree_id = ? AND deleted_at IS NULL ORDER BY display_order
             "#
         };
@@ -92,6 +109,14 @@ impl AgentRepository {
                 stopped_at: row.get(13)?,
                 deleted_at: row.get(14)?,
                 parent_agent_id: row.get(15)?,
+                status_reason: row.get(16)?,
+                task_group_id: row.get(17)?,
+                fork_session_id: row.get(18)?,
+                system_prompt: row.get(19)?,
+                append_system_prompt: row.get(20)?,
+                allowed_tools: row.get(21)?,
+                disallowed_tools: row.get(22)?,
+                tags: row.get(23)?,
             })
         })?;
 
@@ -106,7 +131,10 @@ impl AgentRepository {
             r#"
             SELECT id, worktree_id, name, status, context_level, mode, permissions,
                    display_order, pid, session_id, created_at, updated_at,
-                   started_at, stopped_at, deleted_at, parent_agent_id
+                   started_at, stopped_at, deleted_at, parent_agent_id, status_reason,
+                   task_group_id, fork_session_id, system_prompt, append_system_prompt,
+                   allowed_tools, disallowed_tools,
+                   (SELECT json_group_array(tag) FROM agent_tags WHERE agent_id = agents.id) AS tags
             FROM agents WHERE worktree_id = ? AND deleted_at IS NOT NULL ORDER BY deleted_at DESC
         "#,
         )?;
@@ -129,6 +157,126 @@ impl AgentRepository {
                 stopped_at: row.get(13)?,
                 deleted_at: row.get(14)?,
                 parent_agent_id: row.get(15)?,
+                status_reason: row.get(16)?,
+                task_group_id: row.get(17)?,
+                fork_session_id: row.get(18)?,
+                system_prompt: row.get(19)?,
+                append_system_prompt: row.get(20)?,
+                allowed_tools: row.get(21)?,
+                disallowed_tools: row.get(22)?,
+                tags: row.get(23)?,
+            })
+        })?;
+
+        let agents: Vec<Agent> = rows.filter_map(|r| r.ok()).map(Agent::from).collect();
+
+        Ok(agents)
+    }
+
+    /// All agents of a fan-out task, archived ones included, oldest first
+    pub fn find_by_task_group_id(&self, task_group_id: &str) -> DbResult<Vec<Agent>> {
+        let conn = self.pool.get()?;
+        let mut stmt = conn.prepare(
+            r#"
+            SELECT id, worktree_id, name, status, context_level, mode, permissions,
+                   display_order, pid, session_id, created_at, updated_at,
+                   started_at, stopped_at, deleted_at, parent_agent_id, status_reason,
+                   task_group_id, fork_session_id, system_prompt, append_system_prompt,
+                   allowed_tools, disallowed_tools,
+                   (SELECT json_group_array(tag) FROM agent_tags WHERE agent_id = agents.id) AS tags
+            FROM agents WHERE task_group_id = ? ORDER BY created_at, id
+        "#,
+        )?;
+
+        let rows = stmt.query_map([task_group_id], |row| {
+            Ok(AgentRow {
+                id: row.get(0)?,
+                worktree_id: row.get(1)?,
+                name: row.get(2)?,
+                status: row.get(3)?,
+                context_level: row.get(4)?,
+                mode: row.get(5)?,
+                permissions: row.get(6)?,
+                display_order: row.get(7)?,
+                pid: row.get(8)?,
+                session_id: row.get(9)?,
+                created_at: row.get(10)?,
+                updated_at: row.get(11)?,
+                started_at: row.get(12)?,
+                stopped_at: row.get(13)?,
+                deleted_at: row.get(14)?,
+                parent_agent_id: row.get(15)?,
+                status_reason: row.get(16)?,
+                task_group_id: row.get(17)?,
+                fork_session_id: row.get(18)?,
+                system_prompt: row.get(19)?,
+                append_system_prompt: row.get(20)?,
+                allowed_tools: row.get(21)?,
+                disallowed_tools: row.get(22)?,
+                tags: row.get(23)?,
+            })
+        })?;
+
+        let agents: Vec<Agent> = rows.filter_map(|r| r.ok()).map(Agent::from).collect();
+
+        Ok(agents)
+    }
+
+    /// Agents carrying `tag`, ordered by worktree
+    pub fn find_by_tag(&self, tag: &str, include_deleted: bool) -> DbResult<Vec<Agent>> {
+        let conn = self.pool.get()?;
+        let sql = if include_deleted {
+            r#"
+                SELECT id, worktree_id, name, status, context_level, mode, permissions,
+                       display_order, pid, session_id, created_at, updated_at,
+                       started_at, stopped_at, deleted_at, parent_agent_id, status_reason,
+                       task_group_id, fork_session_id, system_prompt, append_system_prompt,
+                       allowed_tools, disallowed_tools,
+                       (SELECT json_group_array(tag) FROM agent_tags WHERE agent_id = agents.id) AS tags
+                FROM agents WHERE id IN (SELECT agent_id FROM agent_tags WHERE tag = ?)
+                ORDER BY worktree_id, display_order
+            "#
+        } else {
+            r#"
+                SELECT id, worktree_id, name, status, context_level, mode, permissions,
+                       display_order, pid, session_id, created_at, updated_at,
+                       started_at, stopped_at, deleted_at, parent_agent_id, status_reason,
+                       task_group_id, fork_session_id, system_prompt, append_system_prompt,
+                       allowed_tools, disallowed_tools,
+                       (SELECT json_group_array(tag) FROM agent_tags WHERE agent_id = agents.id) AS tags
+                FROM agents WHERE id IN (SELECT agent_id FROM agent_tags WHERE tag = ?)
+                  AND deleted_at IS NULL
+                ORDER BY worktree_id, display_order
+            "#
+        };
+
+        let mut stmt = conn.prepare(sql)?;
+        let rows = stmt.query_map([tag], |row| {
+            Ok(AgentRow {
+                id: row.get(0)?,
+                worktree_id: row.get(1)?,
+                name: row.get(2)?,
+                status: row.get(3)?,
+                context_level: row.get(4)?,
+                mode: row.get(5)?,
+                permissions: row.get(6)?,
+                display_order: row.get(7)?,
+                pid: row.get(8)?,
+                session_id: row.get(9)?,
+                created_at: row.get(10)?,
+                updated_at: row.get(11)?,
+                started_at: row.get(12)?,
+                stopped_at: row.get(13)?,
+                deleted_at: row.get(14)?,
+                parent_agent_id: row.get(15)?,
+                status_reason: row.get(16)?,
+                task_group_id: row.get(17)?,
+                fork_session_id: row.get(18)?,
+                system_prompt: row.get(19)?,
+                append_system_prompt: row.get(20)?,
+                allowed_tools: row.get(21)?,
+                disallowed_tools: row.get(22)?,
+                tags: row.get(23)?,
             })
         })?;
 
@@ -141,13 +289,19 @@ impl AgentRepository {
         let conn = self.pool.get()?;
         let permissions_json =
             serde_json::to_string(&agent.permissions).unwrap_or_else(|_| "[\"read\"]".to_string());
+        let allowed_tools_json =
+            serde_json::to_string(&agent.allowed_tools).unwrap_or_else(|_| "[]".to_string());
+        let disallowed_tools_json =
+            serde_json::to_string(&agent.disallowed_tools).unwrap_or_else(|_| "[]".to_string());
 
         conn.execute(
             r#"
             INSERT INTO agents (id, worktree_id, name, status, context_level, mode,
                                permissions, display_order, pid, session_id, parent_agent_id,
-                               created_at, updated_at)
-            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+                               created_at, updated_at, task_group_id, fork_session_id,
+                               system_prompt, append_system_prompt, allowed_tools,
+                               disallowed_tools)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
         "#,
             params![
                 agent.id,
@@ -163,6 +317,12 @@ impl AgentRepository {
                 agent.parent_agent_id,
                 agent.created_at,
                 agent.updated_at,
+                agent.task_group_id,
+                agent.fork_session_id,
+                agent.system_prompt,
+                agent.append_system_prompt,
+                allowed_tools_json,
+                disallowed_tools_json,
             ],
         )?;
 
@@ -170,12 +330,18 @@ impl AgentRepository {
             .ok_or_else(|| rusqlite::Error::QueryReturnedNoRows.into())
     }
 
+    /// Save an agent unless its row changed since `agent.updated_at`, in
+    /// which case nothing is written and `DbError::Conflict` is returned
     pub fn update(&self, agent: &Agent) -> DbResult<Agent> {
         let conn = self.pool.get()?;
         let permissions_json =
             serde_json::to_string(&agent.permissions).unwrap_or_else(|_| "[\"read\"]".to_string());
+        let allowed_tools_json =
+            serde_json::to_string(&agent.allowed_tools).unwrap_or_else(|_| "[]".to_string());
+        let disallowed_tools_json =
+            serde_json::to_string(&agent.disallowed_tools).unwrap_or_else(|_| "[]".to_string());
 
-        conn.execute(
+        let changed = conn.execute(
             r#"
             UPDATE agents SET
                 name = ?,
@@ -186,8 +352,13 @@ impl AgentRepository {
                 display_order = ?,
                 pid = ?,
                 session_id = ?,
-                updated_at = datetime('now')
-            WHERE id = ?
+                fork_session_id = ?,
+                system_prompt = ?,
+                append_system_prompt = ?,
+                allowed_tools = ?,
+                disallowed_tools = ?,
+                updated_at = ?
+            WHERE id = ? AND updated_at = ?
         "#,
             params![
                 agent.name,
@@ -198,12 +369,24 @@ impl AgentRepository {
                 agent.display_order,
                 agent.pid,
                 agent.session_id,
+                agent.fork_session_id,
+                agent.system_prompt,
+                agent.append_system_prompt,
+                allowed_tools_json,
+                disallowed_tools_json,
+                chrono::Utc::now().to_rfc3339(),
                 agent.id,
+                agent.updated_at,
             ],
         )?;
 
-        self.find_by_id(&agent.id)?
-            .ok_or_else(|| rusqlite::Error::QueryReturnedNoRows.into())
+        let updated = self
+            .find_by_id(&agent.id)?
+            .ok_or(rusqlite::Error::QueryReturnedNoRows)?;
+        if changed == 0 {
+            return Err(DbError::Conflict(updated.updated_at));
+        }
+        Ok(updated)
     }
 
     pub fn update_status(
@@ -226,6 +409,87 @@ impl AgentRepository {
         Ok(())
     }
 
+    /// Update the status and its reason without touching the PID. `at` is
+    /// when the status changed; `updated_at` never moves backwards. Rows
+    /// hold both RFC 3339 and SQLite timestamps, so these updates compare
+    /// them with `julianday` rather than as text.
+    pub fn set_status(
+        &self,
+        id: &str,
+        status: AgentStatus,
+        reason: Option<&str>,
+        at: &str,
+    ) -> DbResult<()> {
+        let conn = self.pool.get()?;
+        conn.execute(
+            r#"
+            UPDATE agents
+            SET status = ?1, status_reason = ?2,
+                updated_at = CASE WHEN julianday(updated_at) > julianday(?3)
+                    THEN updated_at ELSE ?3 END
+            WHERE id = ?4
+        "#,
+            params![status.as_str(), reason, at, id],
+        )?;
+        Ok(())
+    }
+
+    /// Update how full the agent's context window is, as a percentage
+    pub fn update_context_level(&self, id: &str, level: i32, at: &str) -> DbResult<()> {
+        let conn = self.pool.get()?;
+        conn.execute(
+            r#"
+            UPDATE agents
+            SET context_level = ?1,
+                updated_at = CASE WHEN julianday(updated_at) > julianday(?2)
+                    THEN updated_at ELSE ?2 END
+            WHERE id = ?3
+        "#,
+            params![level, at, id],
+        )?;
+        Ok(())
+    }
+
+    /// Record that an agent's process started at `at`, clearing when its
+    /// previous one stopped
+    pub fn record_start(&self, id: &str, pid: i32, at: &str) -> DbResult<()> {
+        let conn = self.pool.get()?;
+        conn.execute(
+            r#"
+            UPDATE agents
+            SET status = ?1, pid = ?2, started_at = ?3, stopped_at = NULL,
+                updated_at = CASE WHEN julianday(updated_at) > julianday(?3)
+                    THEN updated_at ELSE ?3 END
+            WHERE id = ?4
+        "#,
+            params![AgentStatus::Running.as_str(), pid, at, id],
+        )?;
+        Ok(())
+    }
+
+    /// Record that an agent's process ended at `at`
+    pub fn record_stop(
+        &self,
+        id: &str,
+        status: AgentStatus,
+        reason: Option<&str>,
+        at: &str,
+    ) -> DbResult<()> {
+        let conn = self.pool.get()?;
+        conn.execute(
+            r#"
+            UPDATE agents
+            SET status = ?1, status_reason = ?2, pid = NULL,
+                stopped_at = ?3,
+                updated_at = CASE WHEN julianday(updated_at) > julianday(?3)
+                    THEN updated_at ELSE ?3 END
+            WHERE id = ?4
+        "#,
+            params![status.as_str(), reason, at, id],
+        )?;
+        Ok(())
+    }
+
     pub fn soft_delete(&self, id: &str) -> DbResult<()> {
         let conn = self.pool.get()?;
         conn.execute(
@@ -258,12 +522,13 @@ impl AgentRepository {
         Ok(())
     }
 
+    /// Record the agent's own session, dropping any session it was forked from
     pub fn update_session_id(&self, id: &str, session_id: &str) -> DbResult<()> {
         let conn = self.pool.get()?;
         conn.execute(
             r#"
             UPDATE agents
-            SET session_id = ?, updated_at = datetime('now')
+            SET session_id = ?, fork_session_id = NULL, updated_at = datetime('now')
             WHERE id = ?
         "#,
             params![session_id, id],
@@ -271,6 +536,19 @@ impl AgentRepository {
         Ok(())
     }
 
+    /// The agent, deleted or not, whose own session this is
+    pub fn find_id_by_session_id(&self, session_id: &str) -> DbResult<Option<String>> {
+        let conn = self.pool.get()?;
+        let id = conn
+            .query_row(
+                "SELECT id FROM agents WHERE session_id = ? LIMIT 1",
+                [session_id],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(id)
+    }
+
     /// Find all agents with non-NULL PIDs (orphaned from previous run)
     pub fn find_with_pids(&self) -> DbResult<Vec<(String, i32)>> {
         let conn = self.pool.get()?;
@@ -284,6 +562,22 @@ impl AgentRepository {
         Ok(results)
     }
 
+    /// Count active (non-deleted) agents per status
+    pub fn count_by_status(&self) -> DbResult<Vec<(AgentStatus, i64)>> {
+        let conn = self.pool.get()?;
+        let mut stmt = conn.prepare(
+            "SELECT status, COUNT(*) FROM agents WHERE deleted_at IS NULL GROUP BY status",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok((
+                AgentStatus::parse(&row.get::<_, String>(0)?),
+                row.get::<_, i64>(1)?,
+            ))
+        })?;
+        let results: Vec<(AgentStatus, i64)> = rows.filter_map(|r| r.ok()).collect();
+        Ok(results)
+    }
+
     pub fn clear_running_pids(&self) -> DbResult<()> {
         let conn = self.pool.get()?;
         conn.execute(
@@ -312,6 +606,73 @@ impl AgentRepository {
 
         Ok(())
     }
+
+    /// Replace an agent's tags
+    pub fn set_tags(&self, id: &str, tags: &[String]) -> DbResult<()> {
+        let conn = self.pool.get()?;
+        let tx = conn.unchecked_transaction()?;
+
+        tx.execute("DELETE FROM agent_tags WHERE agent_id = ?", [id])?;
+        for tag in tags {
+            tx.execute(
+                "INSERT OR IGNORE INTO agent_tags (agent_id, tag) VALUES (?, ?)",
+                params![id, tag],
+            )?;
+        }
+        tx.execute(
+            "UPDATE agents SET updated_at = datetime('now') WHERE id = ?",
+            [id],
+        )?;
+
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Move an agent to the end of another worktree's list and close the gap it leaves
+    pub fn move_to_worktree(&self, id: &str, worktree_id: &str) -> DbResult<()> {
+        let conn = self.pool.get()?;
+        let tx = conn.unchecked_transaction()?;
+
+        let source_worktree_id: String =
+            tx.query_row("SELECT worktree_id FROM agents WHERE id = ?", [id], |row| {
+                row.get(0)
+            })?;
+
+        tx.execute(
+            r#"
+            UPDATE agents SET
+                worktree_id = ?1,
+                display_order = (
+                    SELECT COALESCE(MAX(display_order) + 1, 0) FROM agents
+                    WHERE worktree_id = ?1 AND deleted_at IS NULL
+                ),
+                updated_at = datetime('now')
+            WHERE id = ?2
+        "#,
+            params![worktree_id, id],
+        )?;
+
+        let remaining: Vec<String> = {
+            let mut stmt = tx.prepare(
+                r#"
+                SELECT id FROM agents
+                WHERE worktree_id = ? AND deleted_at IS NULL
+                ORDER BY display_order, created_at
+            "#,
+            )?;
+            let rows = stmt.query_map([&source_worktree_id], |row| row.get(0))?;
+            rows.collect::<Result<_, _>>()?
+        };
+        for (index, agent_id) in remaining.iter().enumerate() {
+            tx.execute(
+                "UPDATE agents SET display_order = ? WHERE id = ?",
+                params![index as i32, agent_id],
+            )?;
+        }
+
+        tx.commit()?;
+        Ok(())
+    }
 }
 
 // Helper trait for optional query results
@@ -411,6 +772,11 @@ mod tests {
             is_main: true,
             created_at: now.clone(),
             updated_at: now,
+            pr_url: None,
+            sparse_patterns: None,
+            archived_at: None,
+            pinned: false,
+            missing_since: None,
         };
 
         let conn = pool.get().unwrap();
@@ -452,8 +818,17 @@ mod tests {
             updated_at: now,
             started_at: None,
             stopped_at: None,
+            run_duration_ms: None,
             deleted_at: None,
             parent_agent_id: None,
+            status_reason: None,
+            task_group_id: None,
+            fork_session_id: None,
+            system_prompt: None,
+            append_system_prompt: None,
+            allowed_tools: Vec::new(),
+            disallowed_tools: Vec::new(),
+            tags: Vec::new(),
         }
     }
 
@@ -473,6 +848,45 @@ mod tests {
         assert_eq!(created.mode, AgentMode::Regular);
     }
 
+    #[test]
+    fn test_create_refuses_duplicate_id() {
+        let pool = create_test_pool();
+        let workspace = create_test_workspace(&pool);
+        let worktree = create_test_worktree(&pool, &workspace.id);
+        let repo = AgentRepository::new(pool);
+
+        let agent = create_test_agent(&worktree.id);
+        repo.create(&agent).unwrap();
+        let mut duplicate = create_test_agent(&worktree.id);
+        duplicate.id = agent.id.clone();
+        duplicate.name = "Duplicate".to_string();
+
+        assert!(repo.create(&duplicate).is_err());
+        assert_eq!(
+            repo.find_by_id(&agent.id).unwrap().unwrap().name,
+            "Test Agent"
+        );
+    }
+
+    #[test]
+    fn test_generated_ids_never_collide() {
+        let pool = create_test_pool();
+        let workspace = create_test_workspace(&pool);
+        let worktree = create_test_worktree(&pool, &workspace.id);
+        let repo = AgentRepository::new(pool);
+
+        // Far more than can be made in one millisecond
+        for _ in 0..500 {
+            let mut agent = create_test_agent(&worktree.id);
+            agent.id = crate::util::id::new_id("ag");
+            repo.create(&agent).unwrap();
+        }
+        assert_eq!(
+            repo.find_by_worktree_id(&worktree.id, false).unwrap().len(),
+            500
+        );
+    }
+
     #[test]
     fn test_find_by_id() {
         let pool = create_test_pool();
@@ -517,6 +931,31 @@ mod tests {
         assert_eq!(agents.len(), 2);
     }
 
+    #[test]
+    fn test_update_refuses_stale_copy() {
+        let pool = create_test_pool();
+        let workspace = create_test_workspace(&pool);
+        let worktree = create_test_worktree(&pool, &workspace.id);
+        let repo = AgentRepository::new(pool);
+
+        let agent = create_test_agent(&worktree.id);
+        repo.create(&agent).unwrap();
+
+        let mut first = agent.clone();
+        first.name = "First".to_string();
+        let saved = repo.update(&first).unwrap();
+        assert_ne!(saved.updated_at, agent.updated_at);
+
+        // A copy read before the first update is stale now
+        let mut second = agent.clone();
+        second.name = "Second".to_string();
+        match repo.update(&second) {
+            Err(DbError::Conflict(updated_at)) => assert_eq!(updated_at, saved.updated_at),
+            other => panic!("Expected a conflict, got {:?}", other.map(|a| a.name)),
+        }
+        assert_eq!(repo.find_by_id(&agent.id).unwrap().unwrap().name, "First");
+    }
+
     #[test]
     fn test_update_status() {
         let pool = create_test_pool();
@@ -535,6 +974,67 @@ mod tests {
         assert_eq!(updated.pid, Some(12345));
     }
 
+    #[test]
+    fn test_set_status_moves_updated_at_forward_across_formats() {
+        let pool = create_test_pool();
+        let workspace = create_test_workspace(&pool);
+        let worktree = create_test_worktree(&pool, &workspace.id);
+        let repo = AgentRepository::new(pool);
+
+        // Created with an RFC 3339 stamp, as the service does
+        let mut agent = create_test_agent(&worktree.id);
+        agent.updated_at = "2026-10-15T10:00:00+00:00".to_string();
+        repo.create(&agent).unwrap();
+
+        repo.set_status(
+            &agent.id,
+            AgentStatus::Running,
+            None,
+            "2026-10-15 11:00:00.000",
+        )
+        .unwrap();
+        let updated = repo.find_by_id(&agent.id).unwrap().unwrap();
+        assert_eq!(updated.status, AgentStatus::Running);
+        assert_eq!(updated.updated_at, "2026-10-15 11:00:00.000");
+
+        // An older status change still lands but leaves updated_at alone
+        repo.set_status(
+            &agent.id,
+            AgentStatus::Idle,
+            None,
+            "2026-10-15T10:30:00+00:00",
+        )
+        .unwrap();
+        let updated = repo.find_by_id(&agent.id).unwrap().unwrap();
+        assert_eq!(updated.status, AgentStatus::Idle);
+        assert_eq!(updated.updated_at, "2026-10-15 11:00:00.000");
+    }
+
+    #[test]
+    fn test_count_by_status() {
+        let pool = create_test_pool();
+        let workspace = create_test_workspace(&pool);
+        let worktree = create_test_worktree(&pool, &workspace.id);
+        let repo = AgentRepository::new(pool);
+
+        let running = create_test_agent(&worktree.id);
+        let idle = create_test_agent(&worktree.id);
+        let deleted = create_test_agent(&worktree.id);
+        repo.create(&running).unwrap();
+        repo.create(&idle).unwrap();
+        repo.create(&deleted).unwrap();
+        repo.update_status(&running.id, AgentStatus::Running, Some(1))
+            .unwrap();
+        repo.soft_delete(&deleted.id).unwrap();
+
+        let mut counts = repo.count_by_status().unwrap();
+        counts.sort_by_key(|(status, _)| status.as_str());
+        assert_eq!(
+            counts,
+            vec![(AgentStatus::Idle, 1), (AgentStatus::Running, 1)]
+        );
+    }
+
     #[test]
     fn test_soft_delete() {
         let pool = create_test_pool();
@@ -630,4 +1130,36 @@ mod tests {
         assert_eq!(updated.status, AgentStatus::Idle);
         assert!(updated.pid.is_none());
     }
+
+    #[test]
+    fn test_set_tags_and_find_by_tag() {
+        let pool = create_test_pool();
+        let workspace = create_test_workspace(&pool);
+        let worktree = create_test_worktree(&pool, &workspace.id);
+        let repo = AgentRepository::new(pool);
+
+        let agent1 = repo.create(&create_test_agent(&worktree.id)).unwrap();
+        let agent2 = repo.create(&create_test_agent(&worktree.id)).unwrap();
+        assert!(agent1.tags.is_empty());
+
+        repo.set_tags(&agent1.id, &["refactor".to_string(), "bugfix".to_string()])
+            .unwrap();
+        repo.set_tags(&agent2.id, &["bugfix".to_string()]).unwrap();
+
+        let found = repo.find_by_id(&agent1.id).unwrap().unwrap();
+        assert_eq!(found.tags, vec!["bugfix", "refactor"]);
+
+        let bugfix = repo.find_by_tag("bugfix", false).unwrap();
+        assert_eq!(bugfix.len(), 2);
+        let refactor = repo.find_by_tag("refactor", false).unwrap();
+        assert_eq!(refactor.len(), 1);
+        assert_eq!(refactor[0].id, agent1.id);
+
+        repo.soft_delete(&agent1.id).unwrap();
+        assert!(repo.find_by_tag("refactor", false).unwrap().is_empty());
+        assert_eq!(repo.find_by_tag("refactor", true).unwrap().len(), 1);
+
+        repo.set_tags(&agent1.id, &[]).unwrap();
+        assert!(repo.find_by_tag("refactor", true).unwrap().is_empty());
+    }
 }
@@ -0,0 +1,381 @@
+//! Agent run history repository for database operations
+
+use rusqlite::{params, OptionalExtension};
+
+use crate::db::{DbPool, DbResult};
+use crate::types::{AgentActivitySummary, AgentRun, AgentStatus};
+
+/// Milliseconds from the start of a run's current stretch to the first parameter
+const STRETCH_MS: &str = "MAX(0, CAST(ROUND((julianday(?) - julianday(COALESCE(status_since, started_at))) * 86400000) AS INTEGER))";
+
+pub struct AgentRunRepository {
+    pool: DbPool,
+}
+
+impl AgentRunRepository {
+    pub fn new(pool: DbPool) -> Self {
+        Self { pool }
+    }
+
+    /// Record that an agent's process started, returning the run id
+    pub fn start(&self, agent_id: &str, session_id: Option<&str>) -> DbResult<i64> {
+        let conn = self.pool.get()?;
+        conn.execute(
+            "INSERT INTO agent_runs (agent_id, session_id) VALUES (?, ?)",
+            params![agent_id, session_id],
+        )?;
+        Ok(conn.last_insert_rowid())
+    }
+
+    /// Record that an agent's process started again to retry run `retry_of`,
+    /// counting it as the next attempt. Returns the run id.
+    pub fn start_retry(
+        &self,
+        agent_id: &str,
+        session_id: Option<&str>,
+        retry_of: i64,
+    ) -> DbResult<i64> {
+        let conn = self.pool.get()?;
+        conn.execute(
+            r#"
+            INSERT INTO agent_runs (agent_id, session_id, retry_of, attempt)
+            SELECT ?, ?, id, attempt + 1 FROM agent_runs WHERE id = ?
+        "#,
+            params![agent_id, session_id, retry_of],
+        )?;
+        Ok(conn.last_insert_rowid())
+    }
+
+    /// Record how an agent's latest unfinished run ended. Returns false when
+    /// there was no unfinished run, e.g. for an exit that was already applied.
+    pub fn finish_latest(
+        &self,
+        agent_id: &str,
+        exit_code: Option<i32>,
+        signal: Option<&str>,
+        stopped_by_user: bool,
+        ended_at: &str,
+    ) -> DbResult<bool> {
+        let conn = self.pool.get()?;
+        let updated = conn.execute(
+            r#"
+            UPDATE agent_runs SET
+                ended_at = ?,
+                exit_code = ?,
+                signal = ?,
+                stopped_by_user = ?
+            WHERE id = (
+                SELECT id FROM agent_runs
+                WHERE agent_id = ? AND ended_at IS NULL
+                ORDER BY id DESC LIMIT 1
+            )
+        "#,
+            params![ended_at, exit_code, signal, stopped_by_user, agent_id],
+        )?;
+        Ok(updated > 0)
+    }
+
+    /// Add the current stretch of an agent's latest unfinished run to the time
+    /// for the status it was in, and start a new stretch in `status` at `at`.
+    /// Returns false when there was no unfinished run.
+    pub fn record_status(&self, agent_id: &str, status: AgentStatus, at: &str) -> DbResult<bool> {
+        let conn = self.pool.get()?;
+        let Some((id, previous, elapsed)) = conn
+            .query_row(
+                &format!(
+                    r#"
+                    SELECT id, COALESCE(activity_status, 'running'), {STRETCH_MS}
+                    FROM agent_runs
+                    WHERE agent_id = ? AND ended_at IS NULL
+                    ORDER BY id DESC LIMIT 1
+                "#
+                ),
+                params![at, agent_id],
+                |row| {
+                    Ok((
+                        row.get::<_, i64>(0)?,
+                        row.get::<_, String>(1)?,
+                        row.get::<_, i64>(2)?,
+                    ))
+                },
+            )
+            .optional()?
+        else {
+            return Ok(false);
+        };
+
+        conn.execute(
+            r#"
+            UPDATE agent_runs SET
+                active_ms = active_ms + CASE WHEN ?1 = 'running' THEN ?2 ELSE 0 END,
+                waiting_ms = waiting_ms + CASE WHEN ?1 = 'waiting' THEN ?2 ELSE 0 END,
+                idle_ms = idle_ms + CASE WHEN ?1 = 'idle' THEN ?2 ELSE 0 END,
+                activity_status = ?3,
+                status_since = ?4
+            WHERE id = ?5
+        "#,
+            params![previous, elapsed, status.as_str(), at, id],
+        )?;
+        Ok(true)
+    }
+
+    /// Raise the peak memory and CPU of an agent's latest unfinished run to a
+    /// new sample. Returns false when there was no unfinished run.
+    pub fn record_resources(
+        &self,
+        agent_id: &str,
+        memory_bytes: u64,
+        cpu_percent: f64,
+    ) -> DbResult<bool> {
+        let conn = self.pool.get()?;
+        let updated = conn.execute(
+            r#"
+            UPDATE agent_runs SET
+                peak_memory_bytes = MAX(COALESCE(peak_memory_bytes, 0), ?1),
+                peak_cpu_percent = MAX(COALESCE(peak_cpu_percent, 0), ?2)
+            WHERE id = (
+                SELECT id FROM agent_runs
+                WHERE agent_id = ?3 AND ended_at IS NULL
+                ORDER BY id DESC LIMIT 1
+            )
+        "#,
+            params![memory_bytes as i64, cpu_percent, agent_id],
+        )?;
+        Ok(updated > 0)
+    }
+
+    /// Total time an agent spent in each status across its runs, counting the
+    /// open stretch of an unfinished run up to `now`
+    pub fn activity_summary(&self, agent_id: &str, now: &str) -> DbResult<AgentActivitySummary> {
+        let conn = self.pool.get()?;
+        let (run_count, active_ms, waiting_ms, idle_ms) = conn.query_row(
+            &format!(
+                r#"
+                SELECT COUNT(*),
+                       COALESCE(SUM(active_ms + CASE WHEN status = 'running' THEN open_ms ELSE 0 END), 0),
+                       COALESCE(SUM(waiting_ms + CASE WHEN status = 'waiting' THEN open_ms ELSE 0 END), 0),
+                       COALESCE(SUM(idle_ms + CASE WHEN status = 'idle' THEN open_ms ELSE 0 END), 0)
+                FROM (
+                    SELECT active_ms, waiting_ms, idle_ms,
+                           COALESCE(activity_status, 'running') AS status,
+                           CASE WHEN ended_at IS NULL THEN {STRETCH_MS} ELSE 0 END AS open_ms
+                    FROM agent_runs WHERE agent_id = ?
+                )
+            "#
+            ),
+            params![now, agent_id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+        )?;
+
+        let total = active_ms + waiting_ms + idle_ms;
+        Ok(AgentActivitySummary {
+            agent_id: agent_id.to_string(),
+            run_count,
+            active_ms,
+            waiting_ms,
+            idle_ms,
+            waiting_percent: if total > 0 {
+                waiting_ms as f64 * 100.0 / total as f64
+            } else {
+                0.0
+            },
+        })
+    }
+
+    /// End every unfinished run without an exit status; for runs whose
+    /// process died with a previous instance of the app
+    pub fn close_unfinished(&self) -> DbResult<usize> {
+        let conn = self.pool.get()?;
+        let closed = conn.execute(
+            "UPDATE agent_runs SET ended_at = datetime('now') WHERE ended_at IS NULL",
+            [],
+        )?;
+        Ok(closed)
+    }
+
+    /// An agent's runs, most recent first
+    pub fn find_by_agent_id(&self, agent_id: &str, limit: usize) -> DbResult<Vec<AgentRun>> {
+        let conn = self.pool.get()?;
+        let mut stmt = conn.prepare(
+            r#"
+            SELECT id, agent_id, session_id, started_at, ended_at, exit_code, signal,
+                   stopped_by_user, retry_of, attempt, active_ms, waiting_ms, idle_ms,
+                   peak_memory_bytes, peak_cpu_percent
+            FROM agent_runs WHERE agent_id = ?
+            ORDER BY id DESC LIMIT ?
+        "#,
+        )?;
+
+        let rows = stmt.query_map(params![agent_id, limit as i64], |row| {
+            Ok(AgentRun {
+                id: row.get(0)?,
+                agent_id: row.get(1)?,
+                session_id: row.get(2)?,
+                started_at: row.get(3)?,
+                ended_at: row.get(4)?,
+                exit_code: row.get(5)?,
+                signal: row.get(6)?,
+                stopped_by_user: row.get(7)?,
+                retry_of: row.get(8)?,
+                attempt: row.get(9)?,
+                active_ms: row.get(10)?,
+                waiting_ms: row.get(11)?,
+                idle_ms: row.get(12)?,
+                peak_memory_bytes: row.get(13)?,
+                peak_cpu_percent: row.get(14)?,
+            })
+        })?;
+
+        Ok(rows.filter_map(|r| r.ok()).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_test_pool() -> (DbPool, tempfile::TempDir) {
+        let (pool, dir) = crate::db::test_pool();
+        let conn = pool.get().unwrap();
+        conn.execute_batch(
+            r#"
+            INSERT INTO workspaces (id, name, path) VALUES ('ws_1', 'Test', '/tmp/test');
+            INSERT INTO worktrees (id, workspace_id, name, branch, path, is_main)
+                VALUES ('wt_1', 'ws_1', 'main', 'main', '/tmp/test', 1);
+            INSERT INTO agents (id, worktree_id, name) VALUES ('ag_1', 'wt_1', 'Agent');
+        "#,
+        )
+        .unwrap();
+
+        (pool, dir)
+    }
+
+    const ENDED_AT: &str = "2026-01-01 12:00:00.000";
+
+    #[test]
+    fn test_runs_are_finished_in_order() {
+        let (pool, _dir) = create_test_pool();
+        let repo = AgentRunRepository::new(pool);
+
+        repo.start("ag_1", Some("session-1")).unwrap();
+        assert!(repo
+            .finish_latest("ag_1", Some(2), None, false, ENDED_AT)
+            .unwrap());
+        // Already finished, e.g. an exit replayed from the journal
+        assert!(!repo
+            .finish_latest("ag_1", Some(0), None, false, ENDED_AT)
+            .unwrap());
+
+        repo.start("ag_1", Some("session-1")).unwrap();
+        assert!(repo
+            .finish_latest("ag_1", None, Some("SIGKILL"), true, ENDED_AT)
+            .unwrap());
+        repo.start("ag_1", None).unwrap();
+
+        let runs = repo.find_by_agent_id("ag_1", 10).unwrap();
+        assert_eq!(runs.len(), 3);
+        assert!(runs[0].ended_at.is_none());
+        assert_eq!(runs[1].signal.as_deref(), Some("SIGKILL"));
+        assert!(runs[1].stopped_by_user);
+        assert_eq!(runs[2].exit_code, Some(2));
+        assert_eq!(runs[2].ended_at.as_deref(), Some(ENDED_AT));
+        assert_eq!(runs[2].session_id.as_deref(), Some("session-1"));
+
+        assert_eq!(repo.close_unfinished().unwrap(), 1);
+        let latest = &repo.find_by_agent_id("ag_1", 1).unwrap()[0];
+        assert!(latest.ended_at.is_some());
+        assert!(latest.exit_code.is_none());
+    }
+    #[test]
+    fn test_retries_count_attempts() {
+        let (pool, _dir) = create_test_pool();
+        let repo = AgentRunRepository::new(pool);
+
+        let first = repo.start("ag_1", Some("session-1")).unwrap();
+        repo.finish_latest("ag_1", Some(1), None, false, ENDED_AT)
+            .unwrap();
+        let second = repo.start_retry("ag_1", Some("session-1"), first).unwrap();
+        repo.finish_latest("ag_1", Some(1), None, false, ENDED_AT)
+            .unwrap();
+        repo.start_retry("ag_1", Some("session-1"), second).unwrap();
+
+        let runs = repo.find_by_agent_id("ag_1", 10).unwrap();
+        assert_eq!(runs[0].attempt, 3);
+        assert_eq!(runs[0].retry_of, Some(second));
+        assert_eq!(runs[1].retry_of, Some(first));
+        assert_eq!(runs[2].attempt, 1);
+        assert!(runs[2].retry_of.is_none());
+    }
+
+    #[test]
+    fn test_activity_is_split_at_status_changes() {
+        let (pool, _dir) = create_test_pool();
+        let repo = AgentRunRepository::new(pool.clone());
+
+        let run = repo.start("ag_1", None).unwrap();
+        pool.get()
+            .unwrap()
+            .execute(
+                "UPDATE agent_runs SET started_at = '2026-01-01 12:00:00' WHERE id = ?",
+                [run],
+            )
+            .unwrap();
+        // 10s working, 5s at a permission prompt, 2s working, then idle
+        for (status, at) in [
+            (AgentStatus::Waiting, "2026-01-01 12:00:10.000"),
+            (AgentStatus::Running, "2026-01-01 12:00:15.000"),
+            (AgentStatus::Idle, "2026-01-01 12:00:17.000"),
+        ] {
+            assert!(repo.record_status("ag_1", status, at).unwrap());
+        }
+
+        // The open idle stretch counts up to now
+        let summary = repo
+            .activity_summary("ag_1", "2026-01-01 12:00:20.000")
+            .unwrap();
+        assert_eq!(summary.run_count, 1);
+        assert_eq!(summary.active_ms, 12_000);
+        assert_eq!(summary.waiting_ms, 5_000);
+        assert_eq!(summary.idle_ms, 3_000);
+        assert_eq!(summary.waiting_percent, 25.0);
+
+        // Once finished, the last stretch is closed at the exit
+        repo.record_status("ag_1", AgentStatus::Idle, "2026-01-01 12:00:18.000")
+            .unwrap();
+        repo.finish_latest("ag_1", Some(0), None, false, "2026-01-01 12:00:18.000")
+            .unwrap();
+        assert!(!repo
+            .record_status("ag_1", AgentStatus::Running, "2026-01-01 12:01:00.000")
+            .unwrap());
+        let run = &repo.find_by_agent_id("ag_1", 1).unwrap()[0];
+        assert_eq!(
+            (run.active_ms, run.waiting_ms, run.idle_ms),
+            (12_000, 5_000, 1_000)
+        );
+        let summary = repo
+            .activity_summary("ag_1", "2026-01-01 13:00:00.000")
+            .unwrap();
+        assert_eq!(summary.idle_ms, 1_000);
+    }
+
+    #[test]
+    fn test_resources_keep_the_peak_of_the_open_run() {
+        let (pool, _dir) = create_test_pool();
+        let repo = AgentRunRepository::new(pool);
+        assert!(!repo.record_resources("ag_1", 1_000, 5.0).unwrap());
+
+        repo.start("ag_1", None).unwrap();
+        let run = &repo.find_by_agent_id("ag_1", 1).unwrap()[0];
+        assert_eq!((run.peak_memory_bytes, run.peak_cpu_percent), (None, None));
+
+        assert!(repo.record_resources("ag_1", 200_000_000, 12.5).unwrap());
+        assert!(repo.record_resources("ag_1", 150_000_000, 80.0).unwrap());
+        let run = &repo.find_by_agent_id("ag_1", 1).unwrap()[0];
+        assert_eq!(run.peak_memory_bytes, Some(200_000_000));
+        assert_eq!(run.peak_cpu_percent, Some(80.0));
+
+        repo.finish_latest("ag_1", Some(0), None, false, ENDED_AT)
+            .unwrap();
+        assert!(!repo.record_resources("ag_1", 300_000_000, 1.0).unwrap());
+    }
+}
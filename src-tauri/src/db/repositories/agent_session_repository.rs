@@ -0,0 +1,155 @@
+//! Agent session repository for snapshot storage in `agent_sessions`
+
+use rusqlite::params;
+
+use crate::db::{DbPool, DbResult};
+use crate::types::{AgentSessionRow, AgentSnapshot, SnapshotMessage};
+
+pub struct AgentSessionRepository {
+    pool: DbPool,
+}
+
+impl AgentSessionRepository {
+    pub fn new(pool: DbPool) -> Self {
+        Self { pool }
+    }
+
+    pub fn find_by_id(&self, id: &str) -> DbResult<Option<AgentSnapshot>> {
+        let conn = self.pool.get()?;
+        let result = conn.query_row(
+            r#"
+            SELECT id, agent_id, session_data, context_snapshot, created_at, updated_at
+            FROM agent_sessions WHERE id = ?
+        "#,
+            [id],
+            |row| {
+                Ok(AgentSessionRow {
+                    id: row.get(0)?,
+                    agent_id: row.get(1)?,
+                    session_data: row.get(2)?,
+                    context_snapshot: row.get(3)?,
+                    created_at: row.get(4)?,
+                    updated_at: row.get(5)?,
+                })
+            },
+        );
+
+        match result {
+            Ok(row) => Ok(Some(AgentSnapshot::from(row))),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Snapshots of an agent, newest first
+    pub fn find_by_agent_id(&self, agent_id: &str) -> DbResult<Vec<AgentSnapshot>> {
+        let conn = self.pool.get()?;
+        let mut stmt = conn.prepare(
+            r#"
+            SELECT id, agent_id, session_data, context_snapshot, created_at, updated_at
+            FROM agent_sessions WHERE agent_id = ?
+            ORDER BY created_at DESC, rowid DESC
+        "#,
+        )?;
+
+        let rows = stmt.query_map([agent_id], |row| {
+            Ok(AgentSessionRow {
+                id: row.get(0)?,
+                agent_id: row.get(1)?,
+                session_data: row.get(2)?,
+                context_snapshot: row.get(3)?,
+                created_at: row.get(4)?,
+                updated_at: row.get(5)?,
+            })
+        })?;
+
+        let snapshots: Vec<AgentSnapshot> = rows
+            .filter_map(|r| r.ok())
+            .map(AgentSnapshot::from)
+            .collect();
+
+        Ok(snapshots)
+    }
+
+    /// Raw session data and context of an agent's newest snapshot
+    pub fn find_latest_data(&self, agent_id: &str) -> DbResult<Option<(String, Option<String>)>> {
+        let conn = self.pool.get()?;
+        let result = conn.query_row(
+            r#"
+            SELECT session_data, context_snapshot FROM agent_sessions
+            WHERE agent_id = ?
+            ORDER BY created_at DESC, rowid DESC LIMIT 1
+        "#,
+            [agent_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        );
+
+        match result {
+            Ok(data) => Ok(Some(data)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn create(
+        &self,
+        id: &str,
+        agent_id: &str,
+        session_data: &str,
+        context_snapshot: &str,
+    ) -> DbResult<AgentSnapshot> {
+        let conn = self.pool.get()?;
+        conn.execute(
+            r#"
+            INSERT INTO agent_sessions (id, agent_id, session_data, context_snapshot)
+            VALUES (?, ?, ?, ?)
+        "#,
+            params![id, agent_id, session_data, context_snapshot],
+        )?;
+
+        self.find_by_id(id)?
+            .ok_or_else(|| rusqlite::Error::QueryReturnedNoRows.into())
+    }
+
+    /// Delete all but the newest `keep` snapshots of an agent
+    pub fn prune(&self, agent_id: &str, keep: usize) -> DbResult<usize> {
+        let conn = self.pool.get()?;
+        let deleted = conn.execute(
+            r#"
+            DELETE FROM agent_sessions
+            WHERE agent_id = ?1 AND id NOT IN (
+                SELECT id FROM agent_sessions WHERE agent_id = ?1
+                ORDER BY created_at DESC, rowid DESC LIMIT ?2
+            )
+        "#,
+            params![agent_id, keep as i64],
+        )?;
+        Ok(deleted)
+    }
+
+    /// An agent's most recent messages, oldest first
+    pub fn recent_messages(&self, agent_id: &str, limit: usize) -> DbResult<Vec<SnapshotMessage>> {
+        let conn = self.pool.get()?;
+        let mut stmt = conn.prepare(
+            r#"
+            SELECT role, content, created_at FROM (
+                SELECT role, content, created_at, rowid FROM messages
+                WHERE agent_id = ?
+                ORDER BY created_at DESC, rowid DESC LIMIT ?
+            ) ORDER BY created_at, rowid
+        "#,
+        )?;
+
+        let rows = stmt.query_map(params![agent_id, limit as i64], |row| {
+            Ok(SnapshotMessage {
+                role: row.get(0)?,
+                content: row.get(1)?,
+                created_at: row.get(2)?,
+            })
+        })?;
+
+        let messages: Vec<SnapshotMessage> = rows.filter_map(|r| r.ok()).collect();
+
+        Ok(messages)
+    }
+}
@@ -0,0 +1,107 @@
+//! Audit log repository for database operations
+
+use rusqlite::params;
+
+use crate::db::{DbPool, DbResult};
+use crate::types::{AuditEntry, CallerOrigin, Capability};
+
+pub struct AuditRepository {
+    pool: DbPool,
+}
+
+impl AuditRepository {
+    pub fn new(pool: DbPool) -> Self {
+        Self { pool }
+    }
+
+    /// Record a privileged command, whether or not it was allowed
+    pub fn record(
+        &self,
+        command: &str,
+        capability: Capability,
+        origin: CallerOrigin,
+        target_id: Option<&str>,
+        allowed: bool,
+    ) -> DbResult<i64> {
+        let conn = self.pool.get()?;
+        conn.execute(
+            r#"
+            INSERT INTO audit_log (command, capability, origin, target_id, allowed)
+            VALUES (?, ?, ?, ?, ?)
+        "#,
+            params![
+                command,
+                capability.as_str(),
+                origin.as_str(),
+                target_id,
+                allowed
+            ],
+        )?;
+        Ok(conn.last_insert_rowid())
+    }
+
+    /// The latest entries, most recent first
+    pub fn find_recent(&self, limit: usize) -> DbResult<Vec<AuditEntry>> {
+        let conn = self.pool.get()?;
+        let mut stmt = conn.prepare(
+            r#"
+            SELECT id, command, capability, origin, target_id, allowed, created_at
+            FROM audit_log
+            ORDER BY id DESC LIMIT ?
+        "#,
+        )?;
+
+        let rows = stmt.query_map([limit as i64], |row| {
+            Ok(AuditEntry {
+                id: row.get(0)?,
+                command: row.get(1)?,
+                capability: Capability::parse(&row.get::<_, String>(2)?),
+                origin: CallerOrigin::parse(&row.get::<_, String>(3)?),
+                target_id: row.get(4)?,
+                allowed: row.get(5)?,
+                created_at: row.get(6)?,
+            })
+        })?;
+
+        Ok(rows.filter_map(|r| r.ok()).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_entries_are_listed_newest_first() {
+        let (pool, _dir) = crate::db::test_pool();
+        let repo = AuditRepository::new(pool);
+
+        repo.record(
+            "start_agent",
+            Capability::AgentControl,
+            CallerOrigin::Desktop,
+            Some("ag_1"),
+            true,
+        )
+        .unwrap();
+        repo.record(
+            "git_fetch",
+            Capability::GitWrite,
+            CallerOrigin::Remote,
+            None,
+            false,
+        )
+        .unwrap();
+
+        let entries = repo.find_recent(10).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].command, "git_fetch");
+        assert_eq!(entries[0].capability, Capability::GitWrite);
+        assert_eq!(entries[0].origin, CallerOrigin::Remote);
+        assert!(!entries[0].allowed);
+        assert_eq!(entries[1].target_id.as_deref(), Some("ag_1"));
+        assert!(entries[1].allowed);
+
+        assert_eq!(repo.find_recent(1).unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,60 @@
+//! Draft repository for database operations
+
+use rusqlite::{params, OptionalExtension};
+
+use crate::db::{DbPool, DbResult};
+use crate::types::MessageDraft;
+
+pub struct DraftRepository {
+    pool: DbPool,
+}
+
+impl DraftRepository {
+    pub fn new(pool: DbPool) -> Self {
+        Self { pool }
+    }
+
+    pub fn find_by_agent_id(&self, agent_id: &str) -> DbResult<Option<MessageDraft>> {
+        let conn = self.pool.get()?;
+        let mut stmt = conn.prepare(
+            r#"
+            SELECT agent_id, content, updated_at
+            FROM drafts WHERE agent_id = ?
+        "#,
+        )?;
+
+        let draft = stmt
+            .query_row([agent_id], |row| {
+                Ok(MessageDraft {
+                    agent_id: row.get(0)?,
+                    content: row.get(1)?,
+                    updated_at: row.get(2)?,
+                })
+            })
+            .optional()?;
+
+        Ok(draft)
+    }
+
+    /// Insert or replace an agent's draft
+    pub fn save(&self, agent_id: &str, content: &str) -> DbResult<()> {
+        let conn = self.pool.get()?;
+        conn.execute(
+            r#"
+            INSERT INTO drafts (agent_id, content, updated_at)
+            VALUES (?, ?, datetime('now'))
+            ON CONFLICT(agent_id) DO UPDATE SET
+                content = excluded.content,
+                updated_at = excluded.updated_at
+        "#,
+            params![agent_id, content],
+        )?;
+        Ok(())
+    }
+
+    pub fn delete(&self, agent_id: &str) -> DbResult<()> {
+        let conn = self.pool.get()?;
+        conn.execute("DELETE FROM drafts WHERE agent_id = ?", [agent_id])?;
+        Ok(())
+    }
+}
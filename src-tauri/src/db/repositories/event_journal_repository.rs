@@ -0,0 +1,187 @@
+//! Process event journal repository for database operations
+
+use rusqlite::params;
+
+use crate::db::{DbPool, DbResult};
+use crate::types::{AgentStatus, JournalEntry, JournalEventType};
+
+#[derive(Clone)]
+pub struct EventJournalRepository {
+    pool: DbPool,
+}
+
+impl EventJournalRepository {
+    pub fn new(pool: DbPool) -> Self {
+        Self { pool }
+    }
+
+    /// Append an event, returning its journal id
+    pub fn append(&self, entry: &JournalEntry) -> DbResult<i64> {
+        let conn = self.pool.get()?;
+        conn.execute(
+            r#"
+            INSERT INTO process_event_journal
+                (agent_id, event_type, status, message, exit_code, signal, stopped_by_user,
+                 created_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+        "#,
+            params![
+                entry.agent_id,
+                entry.event_type.as_str(),
+                entry.status.map(|s| s.as_str()),
+                entry.message,
+                entry.exit_code,
+                entry.signal,
+                entry.stopped_by_user,
+                entry.created_at,
+            ],
+        )?;
+
+        Ok(conn.last_insert_rowid())
+    }
+
+    /// Events not yet applied by the status sync, oldest first
+    pub fn find_unprocessed(&self) -> DbResult<Vec<JournalEntry>> {
+        let conn = self.pool.get()?;
+        let mut stmt = conn.prepare(
+            r#"
+            SELECT id, agent_id, event_type, status, message, exit_code, signal,
+                   stopped_by_user, created_at, processed_at
+            FROM process_event_journal
+            WHERE processed_at IS NULL
+            ORDER BY id
+        "#,
+        )?;
+
+        let rows = stmt.query_map([], |row| {
+            Ok(JournalEntry {
+                id: row.get(0)?,
+                agent_id: row.get(1)?,
+                event_type: JournalEventType::parse(&row.get::<_, String>(2)?),
+                status: row
+                    .get::<_, Option<String>>(3)?
+                    .map(|s| AgentStatus::parse(&s)),
+                message: row.get(4)?,
+                exit_code: row.get(5)?,
+                signal: row.get(6)?,
+                stopped_by_user: row.get(7)?,
+                created_at: row.get(8)?,
+                processed_at: row.get(9)?,
+            })
+        })?;
+
+        let results: Vec<JournalEntry> = rows.filter_map(|r| r.ok()).collect();
+        Ok(results)
+    }
+
+    /// Whether an exit of the agent is journaled but not yet applied
+    pub fn has_unprocessed_exit(&self, agent_id: &str) -> DbResult<bool> {
+        let conn = self.pool.get()?;
+        let pending = conn.query_row(
+            r#"
+            SELECT EXISTS(
+                SELECT 1 FROM process_event_journal
+                WHERE agent_id = ? AND event_type = ? AND processed_at IS NULL
+            )
+        "#,
+            params![agent_id, JournalEventType::Exit.as_str()],
+            |row| row.get(0),
+        )?;
+        Ok(pending)
+    }
+
+    pub fn mark_processed(&self, id: i64) -> DbResult<()> {
+        let conn = self.pool.get()?;
+        conn.execute(
+            "UPDATE process_event_journal SET processed_at = datetime('now') WHERE id = ?",
+            [id],
+        )?;
+        Ok(())
+    }
+
+    /// Delete processed events older than the given number of days
+    pub fn prune_processed(&self, older_than_days: i64) -> DbResult<usize> {
+        let conn = self.pool.get()?;
+        let deleted = conn.execute(
+            r#"
+            DELETE FROM process_event_journal
+            WHERE processed_at IS NOT NULL
+              AND processed_at < datetime('now', '-' || ? || ' days')
+        "#,
+            [older_than_days],
+        )?;
+        Ok(deleted)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn exit_entry(agent_id: &str, code: i32) -> JournalEntry {
+        JournalEntry {
+            id: 0,
+            agent_id: agent_id.to_string(),
+            event_type: JournalEventType::Exit,
+            status: None,
+            message: None,
+            exit_code: Some(code),
+            signal: None,
+            stopped_by_user: false,
+            created_at: "2026-01-01 12:00:00.000".to_string(),
+            processed_at: None,
+        }
+    }
+
+    #[test]
+    fn test_append_and_find_unprocessed() {
+        let (pool, _dir) = crate::db::test_pool();
+        let repo = EventJournalRepository::new(pool);
+
+        let first = repo.append(&exit_entry("ag_1", 0)).unwrap();
+        let second = repo
+            .append(&JournalEntry {
+                event_type: JournalEventType::Status,
+                status: Some(AgentStatus::Waiting),
+                message: Some("Permission prompt".to_string()),
+                exit_code: None,
+                ..exit_entry("ag_2", 0)
+            })
+            .unwrap();
+
+        let entries = repo.find_unprocessed().unwrap();
+        assert_eq!(
+            entries.iter().map(|e| e.id).collect::<Vec<_>>(),
+            vec![first, second]
+        );
+        assert_eq!(entries[0].exit_code, Some(0));
+        assert_eq!(entries[0].created_at, "2026-01-01 12:00:00.000");
+        assert_eq!(entries[1].status, Some(AgentStatus::Waiting));
+        assert_eq!(entries[1].message.as_deref(), Some("Permission prompt"));
+    }
+
+    #[test]
+    fn test_mark_processed_and_prune() {
+        let (pool, _dir) = crate::db::test_pool();
+        let repo = EventJournalRepository::new(pool.clone());
+
+        let id = repo.append(&exit_entry("ag_1", 1)).unwrap();
+        assert!(repo.has_unprocessed_exit("ag_1").unwrap());
+        assert!(!repo.has_unprocessed_exit("ag_2").unwrap());
+        repo.mark_processed(id).unwrap();
+        assert!(repo.find_unprocessed().unwrap().is_empty());
+        assert!(!repo.has_unprocessed_exit("ag_1").unwrap());
+
+        // Recently processed entries are kept
+        assert_eq!(repo.prune_processed(7).unwrap(), 0);
+
+        pool.get()
+            .unwrap()
+            .execute(
+                "UPDATE process_event_journal SET processed_at = datetime('now', '-8 days')",
+                [],
+            )
+            .unwrap();
+        assert_eq!(repo.prune_processed(7).unwrap(), 1);
+    }
+}
@@ -0,0 +1,136 @@
+//! Background job repository for database operations
+
+use rusqlite::{params, OptionalExtension, Row};
+
+use crate::db::{DbPool, DbResult};
+use crate::types::{Job, JobRow, JobStatus};
+
+pub struct JobRepository {
+    pool: DbPool,
+}
+
+impl JobRepository {
+    pub fn new(pool: DbPool) -> Self {
+        Self { pool }
+    }
+
+    pub fn find_by_id(&self, id: &str) -> DbResult<Option<Job>> {
+        let conn = self.pool.get()?;
+        let mut stmt = conn.prepare(
+            r#"
+            SELECT id, kind, target_id, status, progress, message, result, error,
+                   created_at, updated_at, finished_at
+            FROM jobs WHERE id = ?
+        "#,
+        )?;
+
+        let row = stmt.query_row([id], Self::map_row).optional()?;
+
+        Ok(row.map(Job::from))
+    }
+
+    pub fn create(&self, job: &Job) -> DbResult<Job> {
+        let conn = self.pool.get()?;
+        conn.execute(
+            r#"
+            INSERT INTO jobs (id, kind, target_id, status, progress, message, created_at, updated_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+        "#,
+            params![
+                job.id,
+                job.kind,
+                job.target_id,
+                job.status.as_str(),
+                job.progress,
+                job.message,
+                job.created_at,
+                job.updated_at,
+            ],
+        )?;
+
+        self.find_by_id(&job.id)?
+            .ok_or_else(|| rusqlite::Error::QueryReturnedNoRows.into())
+    }
+
+    /// Mark a job running and record how far along it is
+    pub fn update_progress(
+        &self,
+        id: &str,
+        progress: u8,
+        message: Option<&str>,
+        updated_at: &str,
+    ) -> DbResult<()> {
+        let conn = self.pool.get()?;
+        conn.execute(
+            r#"
+            UPDATE jobs SET status = 'running', progress = ?, message = ?, updated_at = ?
+            WHERE id = ? AND finished_at IS NULL
+        "#,
+            params![progress, message, updated_at, id],
+        )?;
+        Ok(())
+    }
+
+    /// Record how a job ended; a job only ends once
+    pub fn finish(
+        &self,
+        id: &str,
+        status: JobStatus,
+        result: Option<&str>,
+        error: Option<&str>,
+        finished_at: &str,
+    ) -> DbResult<()> {
+        let conn = self.pool.get()?;
+        conn.execute(
+            r#"
+            UPDATE jobs SET
+                status = ?,
+                progress = COALESCE(?, progress),
+                result = ?,
+                error = ?,
+                updated_at = ?,
+                finished_at = ?
+            WHERE id = ? AND finished_at IS NULL
+        "#,
+            params![
+                status.as_str(),
+                (status == JobStatus::Completed).then_some(100),
+                result,
+                error,
+                finished_at,
+                finished_at,
+                id
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Fail every job that is still queued or running; returns how many
+    pub fn fail_unfinished(&self, error: &str, finished_at: &str) -> DbResult<usize> {
+        let conn = self.pool.get()?;
+        let count = conn.execute(
+            r#"
+            UPDATE jobs SET status = 'failed', error = ?, updated_at = ?, finished_at = ?
+            WHERE finished_at IS NULL
+        "#,
+            params![error, finished_at, finished_at],
+        )?;
+        Ok(count)
+    }
+
+    fn map_row(row: &Row) -> rusqlite::Result<JobRow> {
+        Ok(JobRow {
+            id: row.get(0)?,
+            kind: row.get(1)?,
+            target_id: row.get(2)?,
+            status: row.get(3)?,
+            progress: row.get(4)?,
+            message: row.get(5)?,
+            result: row.get(6)?,
+            error: row.get(7)?,
+            created_at: row.get(8)?,
+            updated_at: row.get(9)?,
+            finished_at: row.get(10)?,
+        })
+    }
+}
@@ -0,0 +1,315 @@
+//! Agent message repository for database operations
+
+use rusqlite::{params, OptionalExtension, Row};
+
+use crate::db::{DbPool, DbResult};
+use crate::types::{Message, MessageRole};
+
+pub struct MessageRepository {
+    pool: DbPool,
+}
+
+impl MessageRepository {
+    pub fn new(pool: DbPool) -> Self {
+        Self { pool }
+    }
+
+    /// Insert messages whose id is not stored yet, returning how many were new
+    pub fn insert_missing(&self, messages: &[Message]) -> DbResult<usize> {
+        let conn = self.pool.get()?;
+        let tx = conn.unchecked_transaction()?;
+
+        let mut inserted = 0;
+        for message in messages {
+            inserted += tx.execute(
+                r#"
+                INSERT OR IGNORE INTO messages
+                    (id, agent_id, role, content, token_count, tool_name, tool_input,
+                     tool_output, created_at, is_complete)
+                VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            "#,
+                params![
+                    message.id,
+                    message.agent_id,
+                    message.role.as_str(),
+                    message.content,
+                    message.token_count,
+                    message.tool_name,
+                    message.tool_input.as_ref().map(|v| v.to_string()),
+                    message.tool_output.as_ref().map(|v| v.to_string()),
+                    message.created_at,
+                    message.is_complete,
+                ],
+            )?;
+        }
+
+        tx.commit()?;
+        Ok(inserted)
+    }
+
+    /// Replace the content of a message that is still streaming in
+    pub fn update_content(&self, id: &str, content: &str, is_complete: bool) -> DbResult<()> {
+        let conn = self.pool.get()?;
+        conn.execute(
+            "UPDATE messages SET content = ?, is_complete = ? WHERE id = ?",
+            params![content, is_complete, id],
+        )?;
+        Ok(())
+    }
+
+    /// Mark every incomplete message complete; returns how many there were
+    pub fn complete_all(&self) -> DbResult<usize> {
+        let conn = self.pool.get()?;
+        let count = conn.execute(
+            "UPDATE messages SET is_complete = 1 WHERE is_complete = 0",
+            [],
+        )?;
+        Ok(count)
+    }
+
+    /// Up to `limit` of an agent's messages, oldest first, optionally only those
+    /// from before the message whose `seq` is `before`. Also says whether
+    /// older ones remain.
+    ///
+    /// Messages are ordered by time, then by `seq` for messages from the same
+    /// moment. `seq` alone is no order: imported transcripts are stored after
+    /// the messages that came after them.
+    pub fn find_page(
+        &self,
+        agent_id: &str,
+        limit: usize,
+        before: Option<i64>,
+    ) -> DbResult<(Vec<Message>, bool)> {
+        let conn = self.pool.get()?;
+        let mut stmt = conn.prepare(
+            r#"
+            SELECT id, agent_id, role, content, token_count, tool_name, tool_input,
+                   tool_output, created_at, is_complete, rowid
+            FROM messages
+            WHERE agent_id = ?1
+              AND (?2 IS NULL
+                   OR (created_at, rowid) < (SELECT created_at, rowid FROM messages WHERE rowid = ?2))
+            ORDER BY created_at DESC, rowid DESC LIMIT ?3
+        "#,
+        )?;
+
+        let rows = stmt.query_map(
+            params![agent_id, before, limit as i64 + 1],
+            Self::row_to_message,
+        )?;
+
+        let mut messages: Vec<Message> = rows.filter_map(|r| r.ok()).collect();
+        let has_more = messages.len() > limit;
+        messages.truncate(limit);
+        messages.reverse();
+
+        Ok((messages, has_more))
+    }
+
+    /// An agent's most recent message from `role`
+    pub fn find_last_by_role(
+        &self,
+        agent_id: &str,
+        role: MessageRole,
+    ) -> DbResult<Option<Message>> {
+        let conn = self.pool.get()?;
+        let mut stmt = conn.prepare(
+            r#"
+            SELECT id, agent_id, role, content, token_count, tool_name, tool_input,
+                   tool_output, created_at, is_complete, rowid
+            FROM messages WHERE agent_id = ? AND role = ?
+            ORDER BY created_at DESC, rowid DESC LIMIT 1
+        "#,
+        )?;
+
+        let message = stmt
+            .query_row(params![agent_id, role.as_str()], Self::row_to_message)
+            .optional()?;
+        Ok(message)
+    }
+
+    fn row_to_message(row: &Row) -> rusqlite::Result<Message> {
+        let json = |value: Option<String>| value.and_then(|v| serde_json::from_str(&v).ok());
+        Ok(Message {
+            id: row.get(0)?,
+            agent_id: row.get(1)?,
+            role: MessageRole::parse(&row.get::<_, String>(2)?),
+            content: row.get(3)?,
+            token_count: row.get(4)?,
+            tool_name: row.get(5)?,
+            tool_input: json(row.get(6)?),
+            tool_output: json(row.get(7)?),
+            created_at: row.get(8)?,
+            is_complete: row.get(9)?,
+            seq: row.get(10)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_test_pool() -> (DbPool, tempfile::TempDir) {
+        let (pool, dir) = crate::db::test_pool();
+        let conn = pool.get().unwrap();
+        conn.execute_batch(
+            r#"
+            INSERT INTO workspaces (id, name, path) VALUES ('ws_1', 'Test', '/tmp/test');
+            INSERT INTO worktrees (id, workspace_id, name, branch, path, is_main)
+                VALUES ('wt_1', 'ws_1', 'main', 'main', '/tmp/test', 1);
+            INSERT INTO agents (id, worktree_id, name) VALUES ('ag_1', 'wt_1', 'Agent');
+        "#,
+        )
+        .unwrap();
+
+        (pool, dir)
+    }
+
+    fn message(id: &str, role: MessageRole, created_at: &str) -> Message {
+        Message {
+            id: id.to_string(),
+            agent_id: "ag_1".to_string(),
+            role,
+            content: format!("content of {}", id),
+            token_count: None,
+            tool_name: None,
+            tool_input: None,
+            tool_output: None,
+            created_at: created_at.to_string(),
+            is_complete: true,
+            seq: 0,
+        }
+    }
+
+    #[test]
+    fn test_insert_missing_skips_known_ids() {
+        let (pool, _dir) = create_test_pool();
+        let repo = MessageRepository::new(pool);
+        let mut tool = message("msg_2", MessageRole::Tool, "2026-01-01 12:00:01.000");
+        tool.tool_name = Some("Bash".to_string());
+        tool.tool_input = Some(serde_json::json!({ "command": "ls" }));
+        let messages = vec![
+            message("msg_1", MessageRole::User, "2026-01-01 12:00:00.000"),
+            tool,
+        ];
+
+        assert_eq!(repo.insert_missing(&messages).unwrap(), 2);
+        assert_eq!(repo.insert_missing(&messages).unwrap(), 0);
+
+        let (stored, has_more) = repo.find_page("ag_1", 10, None).unwrap();
+        assert!(!has_more);
+        let last_user = repo.find_last_by_role("ag_1", MessageRole::User).unwrap();
+        assert_eq!(last_user.unwrap().id, "msg_1");
+        assert!(repo
+            .find_last_by_role("ag_1", MessageRole::Assistant)
+            .unwrap()
+            .is_none());
+        assert_eq!(stored[1].role, MessageRole::Tool);
+        assert_eq!(
+            stored[1].tool_input,
+            Some(serde_json::json!({ "command": "ls" }))
+        );
+    }
+
+    #[test]
+    fn test_update_content_until_complete() {
+        let (pool, _dir) = create_test_pool();
+        let repo = MessageRepository::new(pool);
+        let mut streaming = message("msg_1", MessageRole::Assistant, "2026-01-01 12:00:00");
+        streaming.is_complete = false;
+        repo.insert_missing(&[streaming]).unwrap();
+
+        repo.update_content("msg_1", "Hello, wor", false).unwrap();
+        let (page, _) = repo.find_page("ag_1", 10, None).unwrap();
+        assert_eq!(page[0].content, "Hello, wor");
+        assert!(!page[0].is_complete);
+
+        assert_eq!(repo.complete_all().unwrap(), 1);
+        assert_eq!(repo.complete_all().unwrap(), 0);
+        let (page, _) = repo.find_page("ag_1", 10, None).unwrap();
+        assert!(page[0].is_complete);
+    }
+
+    #[test]
+    fn test_find_page_walks_back_from_cursor() {
+        let (pool, _dir) = create_test_pool();
+        let repo = MessageRepository::new(pool);
+        let messages: Vec<Message> = (0..5)
+            .map(|i| {
+                message(
+                    &format!("msg_{}", i),
+                    MessageRole::User,
+                    &format!("2026-01-01 12:00:0{}.000", i),
+                )
+            })
+            .collect();
+        repo.insert_missing(&messages).unwrap();
+
+        let (page, has_more) = repo.find_page("ag_1", 2, None).unwrap();
+        let ids: Vec<&str> = page.iter().map(|m| m.id.as_str()).collect();
+        assert_eq!(ids, ["msg_3", "msg_4"]);
+        assert!(has_more);
+
+        let (page, has_more) = repo.find_page("ag_1", 2, Some(page[0].seq)).unwrap();
+        let ids: Vec<&str> = page.iter().map(|m| m.id.as_str()).collect();
+        assert_eq!(ids, ["msg_1", "msg_2"]);
+        assert!(has_more);
+
+        let (page, has_more) = repo.find_page("ag_1", 2, Some(page[0].seq)).unwrap();
+        let ids: Vec<&str> = page.iter().map(|m| m.id.as_str()).collect();
+        assert_eq!(ids, ["msg_0"]);
+        assert!(!has_more);
+    }
+
+    #[test]
+    fn test_find_page_keeps_messages_from_the_same_second() {
+        let (pool, _dir) = create_test_pool();
+        let repo = MessageRepository::new(pool);
+        // Streamed chunks share a timestamp, and their ids don't sort in order
+        let messages: Vec<Message> = ["msg_c", "msg_a", "msg_d", "msg_b", "msg_e"]
+            .iter()
+            .map(|id| message(id, MessageRole::Assistant, "2026-01-01 12:00:00"))
+            .collect();
+        repo.insert_missing(&messages).unwrap();
+
+        let mut seen = Vec::new();
+        let mut before = None;
+        loop {
+            let (page, has_more) = repo.find_page("ag_1", 2, before).unwrap();
+            before = page.first().map(|m| m.seq);
+            seen.splice(0..0, page.into_iter().map(|m| m.id));
+            if !has_more {
+                break;
+            }
+        }
+        assert_eq!(seen, ["msg_c", "msg_a", "msg_d", "msg_b", "msg_e"]);
+    }
+
+    #[test]
+    fn test_find_page_orders_imported_messages_by_time() {
+        let (pool, _dir) = create_test_pool();
+        let repo = MessageRepository::new(pool);
+        repo.insert_missing(&[
+            message("msg_3", MessageRole::User, "2026-01-01 12:00:03.000"),
+            message("msg_4", MessageRole::Assistant, "2026-01-01 12:00:04.000"),
+        ])
+        .unwrap();
+        // A transcript from earlier in the session is imported afterwards
+        repo.insert_missing(&[
+            message("msg_1", MessageRole::User, "2026-01-01 12:00:01.000"),
+            message("msg_2", MessageRole::Assistant, "2026-01-01 12:00:02.000"),
+        ])
+        .unwrap();
+
+        let (page, has_more) = repo.find_page("ag_1", 3, None).unwrap();
+        let ids: Vec<&str> = page.iter().map(|m| m.id.as_str()).collect();
+        assert_eq!(ids, ["msg_2", "msg_3", "msg_4"]);
+        assert!(has_more);
+
+        let (page, has_more) = repo.find_page("ag_1", 3, Some(page[0].seq)).unwrap();
+        let ids: Vec<&str> = page.iter().map(|m| m.id.as_str()).collect();
+        assert_eq!(ids, ["msg_1"]);
+        assert!(!has_more);
+    }
+}
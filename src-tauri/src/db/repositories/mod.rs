@@ -1,11 +1,33 @@
 //! Repository implementations for data access
 
 pub mod agent_repository;
+pub mod agent_run_repository;
+pub mod agent_session_repository;
+pub mod audit_repository;
+pub mod draft_repository;
+pub mod event_journal_repository;
+pub mod job_repository;
+pub mod message_repository;
+pub mod schedule_repository;
+pub mod settings_repository;
+pub mod snippet_repository;
+pub mod task_group_repository;
 pub mod usage_repository;
 pub mod workspace_repository;
 pub mod worktree_repository;
 
 pub use agent_repository::AgentRepository;
+pub use agent_run_repository::AgentRunRepository;
+pub use agent_session_repository::AgentSessionRepository;
+pub use audit_repository::AuditRepository;
+pub use draft_repository::DraftRepository;
+pub use event_journal_repository::EventJournalRepository;
+pub use job_repository::JobRepository;
+pub use message_repository::MessageRepository;
+pub use schedule_repository::ScheduleRepository;
+pub use settings_repository::SettingsRepository;
+pub use snippet_repository::SnippetRepository;
+pub use task_group_repository::TaskGroupRepository;
 pub use usage_repository::UsageRepository;
 pub use workspace_repository::WorkspaceRepository;
 pub use worktree_repository::WorktreeRepository;
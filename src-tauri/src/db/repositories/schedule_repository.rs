@@ -0,0 +1,168 @@
+//! Agent schedule repository for database operations
+
+use rusqlite::{params, OptionalExtension, Row};
+
+use crate::db::{DbPool, DbResult};
+use crate::types::{Schedule, ScheduleRow};
+
+pub struct ScheduleRepository {
+    pool: DbPool,
+}
+
+impl ScheduleRepository {
+    pub fn new(pool: DbPool) -> Self {
+        Self { pool }
+    }
+
+    pub fn find_by_id(&self, id: &str) -> DbResult<Option<Schedule>> {
+        let conn = self.pool.get()?;
+        let mut stmt = conn.prepare(
+            r#"
+            SELECT id, name, cron, worktree_id, mode, permissions, prompt, paused, next_run_at,
+                   last_run_at, last_agent_id, last_error, created_at, updated_at
+            FROM schedules WHERE id = ?
+        "#,
+        )?;
+
+        let row = stmt.query_row([id], Self::map_row).optional()?;
+
+        Ok(row.map(Schedule::from))
+    }
+
+    /// All schedules, or those of one worktree, by name
+    pub fn find_all(&self, worktree_id: Option<&str>) -> DbResult<Vec<Schedule>> {
+        let conn = self.pool.get()?;
+        let mut stmt = conn.prepare(
+            r#"
+            SELECT id, name, cron, worktree_id, mode, permissions, prompt, paused, next_run_at,
+                   last_run_at, last_agent_id, last_error, created_at, updated_at
+            FROM schedules
+            WHERE ?1 IS NULL OR worktree_id = ?1
+            ORDER BY name COLLATE NOCASE, created_at
+        "#,
+        )?;
+
+        let rows = stmt.query_map([worktree_id], Self::map_row)?;
+
+        Ok(rows.filter_map(|r| r.ok()).map(Schedule::from).collect())
+    }
+
+    /// Unpaused schedules whose next run is at or before `now`
+    pub fn find_due(&self, now: &str) -> DbResult<Vec<Schedule>> {
+        let conn = self.pool.get()?;
+        let mut stmt = conn.prepare(
+            r#"
+            SELECT id, name, cron, worktree_id, mode, permissions, prompt, paused, next_run_at,
+                   last_run_at, last_agent_id, last_error, created_at, updated_at
+            FROM schedules
+            WHERE paused = 0 AND next_run_at IS NOT NULL AND next_run_at <= ?
+            ORDER BY next_run_at
+        "#,
+        )?;
+
+        let rows = stmt.query_map([now], Self::map_row)?;
+
+        Ok(rows.filter_map(|r| r.ok()).map(Schedule::from).collect())
+    }
+
+    /// The earliest next run of an unpaused schedule
+    pub fn find_next_run_at(&self) -> DbResult<Option<String>> {
+        let conn = self.pool.get()?;
+        let next_run_at = conn.query_row(
+            "SELECT MIN(next_run_at) FROM schedules WHERE paused = 0",
+            [],
+            |row| row.get(0),
+        )?;
+        Ok(next_run_at)
+    }
+
+    pub fn create(&self, schedule: &Schedule) -> DbResult<Schedule> {
+        let conn = self.pool.get()?;
+        let permissions = serde_json::to_string(&schedule.permissions)
+            .unwrap_or_else(|_| "[\"read\"]".to_string());
+        conn.execute(
+            r#"
+            INSERT INTO schedules (id, name, cron, worktree_id, mode, permissions, prompt,
+                                   paused, next_run_at, created_at, updated_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+        "#,
+            params![
+                schedule.id,
+                schedule.name,
+                schedule.cron,
+                schedule.worktree_id,
+                schedule.mode.as_str(),
+                permissions,
+                schedule.prompt,
+                schedule.paused,
+                schedule.next_run_at,
+                schedule.created_at,
+                schedule.updated_at,
+            ],
+        )?;
+
+        self.find_by_id(&schedule.id)?
+            .ok_or_else(|| rusqlite::Error::QueryReturnedNoRows.into())
+    }
+
+    pub fn set_paused(&self, id: &str, paused: bool, next_run_at: Option<&str>) -> DbResult<()> {
+        let conn = self.pool.get()?;
+        conn.execute(
+            r#"
+            UPDATE schedules SET paused = ?, next_run_at = ?, updated_at = datetime('now')
+            WHERE id = ?
+        "#,
+            params![paused, next_run_at, id],
+        )?;
+        Ok(())
+    }
+
+    /// Record a run and when the next one is due
+    pub fn record_run(
+        &self,
+        id: &str,
+        ran_at: &str,
+        agent_id: Option<&str>,
+        error: Option<&str>,
+        next_run_at: Option<&str>,
+    ) -> DbResult<()> {
+        let conn = self.pool.get()?;
+        conn.execute(
+            r#"
+            UPDATE schedules SET
+                last_run_at = ?,
+                last_agent_id = ?,
+                last_error = ?,
+                next_run_at = ?
+            WHERE id = ?
+        "#,
+            params![ran_at, agent_id, error, next_run_at, id],
+        )?;
+        Ok(())
+    }
+
+    pub fn delete(&self, id: &str) -> DbResult<()> {
+        let conn = self.pool.get()?;
+        conn.execute("DELETE FROM schedules WHERE id = ?", [id])?;
+        Ok(())
+    }
+
+    fn map_row(row: &Row) -> rusqlite::Result<ScheduleRow> {
+        Ok(ScheduleRow {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            cron: row.get(2)?,
+            worktree_id: row.get(3)?,
+            mode: row.get(4)?,
+            permissions: row.get(5)?,
+            prompt: row.get(6)?,
+            paused: row.get(7)?,
+            next_run_at: row.get(8)?,
+            last_run_at: row.get(9)?,
+            last_agent_id: row.get(10)?,
+            last_error: row.get(11)?,
+            created_at: row.get(12)?,
+            updated_at: row.get(13)?,
+        })
+    }
+}
@@ -0,0 +1,245 @@
+//! Settings repository for database operations
+
+use rusqlite::{params, OptionalExtension};
+
+use crate::db::crypto::{self, CryptoError, FieldCipher};
+use crate::db::{DbPool, DbResult};
+
+/// Settings holding secrets, stored encrypted when a cipher is installed
+const ENCRYPTED_KEYS: &[&str] = &["github_token"];
+
+pub struct SettingsRepository {
+    pool: DbPool,
+    cipher: Option<&'static FieldCipher>,
+}
+
+impl SettingsRepository {
+    pub fn new(pool: DbPool) -> Self {
+        Self {
+            pool,
+            cipher: crypto::cipher(),
+        }
+    }
+
+    /// Encrypt secrets with `cipher` rather than the installed one
+    pub fn with_cipher(mut self, cipher: &'static FieldCipher) -> Self {
+        self.cipher = Some(cipher);
+        self
+    }
+
+    pub fn get(&self, key: &str) -> DbResult<Option<String>> {
+        let conn = self.pool.get()?;
+        let result: Option<String> = conn
+            .query_row("SELECT value FROM settings WHERE key = ?", [key], |row| {
+                row.get(0)
+            })
+            .optional()?;
+
+        match (result, self.encryption(key)) {
+            (Some(value), Some(cipher)) => Ok(Some(cipher.decrypt(key, &value)?)),
+            // The ciphertext is of no use to callers
+            (Some(value), None)
+                if ENCRYPTED_KEYS.contains(&key) && crypto::is_encrypted(&value) =>
+            {
+                Err(CryptoError::KeyUnavailable(format!("{} is encrypted", key)).into())
+            }
+            (result, _) => Ok(result),
+        }
+    }
+
+    pub fn get_bool(&self, key: &str, default: bool) -> DbResult<bool> {
+        Ok(self.get(key)?.map(|v| v == "true").unwrap_or(default))
+    }
+
+    pub fn get_string(&self, key: &str, default: &str) -> DbResult<String> {
+        Ok(self.get(key)?.unwrap_or_else(|| default.to_string()))
+    }
+
+    /// Insert or update a setting, keeping its description if it already exists
+    pub fn set(&self, key: &str, value: &str, value_type: &str) -> DbResult<()> {
+        let sealed;
+        let value = match self.encryption(key) {
+            // An empty value means "not set" and gives nothing away
+            _ if value.is_empty() => value,
+            Some(cipher) => {
+                sealed = cipher.encrypt(key, value)?;
+                sealed.as_str()
+            }
+            None if ENCRYPTED_KEYS.contains(&key) => {
+                return Err(CryptoError::KeyUnavailable(format!(
+                    "{} can't be stored unencrypted",
+                    key
+                ))
+                .into());
+            }
+            None => value,
+        };
+
+        let conn = self.pool.get()?;
+        conn.execute(
+            r#"
+            INSERT INTO settings (key, value, type, updated_at)
+            VALUES (?, ?, ?, datetime('now'))
+            ON CONFLICT(key) DO UPDATE SET
+                value = excluded.value,
+                type = excluded.type,
+                updated_at = excluded.updated_at
+        "#,
+            params![key, value, value_type],
+        )?;
+
+        Ok(())
+    }
+
+    pub fn set_bool(&self, key: &str, value: bool) -> DbResult<()> {
+        self.set(key, if value { "true" } else { "false" }, "boolean")
+    }
+
+    pub fn delete(&self, key: &str) -> DbResult<()> {
+        let conn = self.pool.get()?;
+        conn.execute("DELETE FROM settings WHERE key = ?", [key])?;
+        Ok(())
+    }
+
+    /// Encrypt secrets stored before encryption was turned on; returns how
+    /// many there were
+    pub fn encrypt_plaintext(&self) -> DbResult<usize> {
+        if self.cipher.is_none() {
+            return Ok(0);
+        }
+
+        let conn = self.pool.get()?;
+        let mut count = 0;
+        for key in ENCRYPTED_KEYS {
+            let stored: Option<(String, String)> = conn
+                .query_row(
+                    "SELECT value, type FROM settings WHERE key = ?",
+                    [key],
+                    |row| Ok((row.get(0)?, row.get(1)?)),
+                )
+                .optional()?;
+            if let Some((value, value_type)) = stored {
+                if !value.is_empty() && !crypto::is_encrypted(&value) {
+                    self.set(key, &value, &value_type)?;
+                    count += 1;
+                }
+            }
+        }
+        Ok(count)
+    }
+
+    /// Whether any secret is stored encrypted, whatever the cipher
+    pub fn has_encrypted(&self) -> DbResult<bool> {
+        let conn = self.pool.get()?;
+        for key in ENCRYPTED_KEYS {
+            let stored: Option<String> = conn
+                .query_row("SELECT value FROM settings WHERE key = ?", [key], |row| {
+                    row.get(0)
+                })
+                .optional()?;
+            if stored.is_some_and(|value| crypto::is_encrypted(&value)) {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    fn encryption(&self, key: &str) -> Option<&'static FieldCipher> {
+        self.cipher.filter(|_| ENCRYPTED_KEYS.contains(&key))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::DbError;
+
+    #[test]
+    fn test_get_default_setting() {
+        let (pool, _dir) = crate::db::test_pool();
+        let repo = SettingsRepository::new(pool);
+
+        assert_eq!(repo.get("theme").unwrap().as_deref(), Some("system"));
+        assert!(repo.get("missing").unwrap().is_none());
+        assert_eq!(repo.get_string("missing", "fallback").unwrap(), "fallback");
+    }
+
+    #[test]
+    fn test_secrets_are_encrypted_at_rest() {
+        let (pool, _dir) = crate::db::test_pool();
+        let cipher: &'static FieldCipher = Box::leak(Box::new(FieldCipher::new([9; 32])));
+        let stored = |key: &str| -> String {
+            pool.get()
+                .unwrap()
+                .query_row("SELECT value FROM settings WHERE key = ?", [key], |row| {
+                    row.get(0)
+                })
+                .unwrap()
+        };
+
+        // Saved before encryption was turned on
+        pool.get()
+            .unwrap()
+            .execute(
+                "INSERT INTO settings (key, value, type) VALUES ('github_token', 'ghp_old', 'string')",
+                [],
+            )
+            .unwrap();
+        let repo = SettingsRepository::new(pool.clone()).with_cipher(cipher);
+        assert!(!repo.has_encrypted().unwrap());
+        assert_eq!(
+            repo.get("github_token").unwrap().as_deref(),
+            Some("ghp_old")
+        );
+        assert_eq!(repo.encrypt_plaintext().unwrap(), 1);
+        assert_eq!(repo.encrypt_plaintext().unwrap(), 0);
+        assert!(crypto::is_encrypted(&stored("github_token")));
+        assert!(repo.has_encrypted().unwrap());
+        assert_eq!(
+            repo.get("github_token").unwrap().as_deref(),
+            Some("ghp_old")
+        );
+
+        repo.set("github_token", "ghp_new", "string").unwrap();
+        assert!(!stored("github_token").contains("ghp_new"));
+        assert_eq!(
+            repo.get("github_token").unwrap().as_deref(),
+            Some("ghp_new")
+        );
+
+        // Without the key, secrets are neither handed out sealed nor stored
+        // in the clear
+        let keyless = SettingsRepository {
+            pool: pool.clone(),
+            cipher: None,
+        };
+        assert!(matches!(
+            keyless.get("github_token"),
+            Err(DbError::Crypto(CryptoError::KeyUnavailable(_)))
+        ));
+        assert!(matches!(
+            keyless.set("github_token", "ghp_plain", "string"),
+            Err(DbError::Crypto(CryptoError::KeyUnavailable(_)))
+        ));
+        assert!(!stored("github_token").contains("ghp_plain"));
+
+        repo.set("github_token", "", "string").unwrap();
+        assert_eq!(repo.get("github_token").unwrap().as_deref(), Some(""));
+
+        // Other settings stay readable
+        repo.set("theme", "dark", "string").unwrap();
+        assert_eq!(stored("theme"), "dark");
+    }
+
+    #[test]
+    fn test_set_and_get_bool() {
+        let (pool, _dir) = crate::db::test_pool();
+        let repo = SettingsRepository::new(pool);
+
+        assert!(!repo.get_bool("custom_flag", false).unwrap());
+        repo.set_bool("custom_flag", true).unwrap();
+        assert!(repo.get_bool("custom_flag", false).unwrap());
+        repo.set_bool("custom_flag", false).unwrap();
+        assert!(!repo.get_bool("custom_flag", true).unwrap());
+    }
+}
@@ -0,0 +1,118 @@
+//! Snippet repository for database operations
+
+use rusqlite::{params, OptionalExtension};
+
+use crate::db::{DbPool, DbResult};
+use crate::types::{Snippet, SnippetRow};
+
+pub struct SnippetRepository {
+    pool: DbPool,
+}
+
+impl SnippetRepository {
+    pub fn new(pool: DbPool) -> Self {
+        Self { pool }
+    }
+
+    pub fn find_by_id(&self, id: &str) -> DbResult<Option<Snippet>> {
+        let conn = self.pool.get()?;
+        let mut stmt = conn.prepare(
+            r#"
+            SELECT id, name, body, scope, workspace_id, worktree_id, created_at, updated_at
+            FROM snippets WHERE id = ?
+        "#,
+        )?;
+
+        let row = stmt
+            .query_row([id], |row| {
+                Ok(SnippetRow {
+                    id: row.get(0)?,
+                    name: row.get(1)?,
+                    body: row.get(2)?,
+                    scope: row.get(3)?,
+                    workspace_id: row.get(4)?,
+                    worktree_id: row.get(5)?,
+                    created_at: row.get(6)?,
+                    updated_at: row.get(7)?,
+                })
+            })
+            .optional()?;
+
+        Ok(row.map(Snippet::from))
+    }
+
+    /// Global snippets plus those scoped to the given workspace or worktree, by name
+    pub fn find_available(
+        &self,
+        workspace_id: Option<&str>,
+        worktree_id: Option<&str>,
+    ) -> DbResult<Vec<Snippet>> {
+        let conn = self.pool.get()?;
+        let mut stmt = conn.prepare(
+            r#"
+            SELECT id, name, body, scope, workspace_id, worktree_id, created_at, updated_at
+            FROM snippets
+            WHERE scope = 'global'
+               OR (scope = 'workspace' AND workspace_id = ?1)
+               OR (scope = 'worktree' AND worktree_id = ?2)
+            ORDER BY name COLLATE NOCASE, created_at
+        "#,
+        )?;
+
+        let rows = stmt.query_map(params![workspace_id, worktree_id], |row| {
+            Ok(SnippetRow {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                body: row.get(2)?,
+                scope: row.get(3)?,
+                workspace_id: row.get(4)?,
+                worktree_id: row.get(5)?,
+                created_at: row.get(6)?,
+                updated_at: row.get(7)?,
+            })
+        })?;
+
+        let snippets: Vec<Snippet> = rows.filter_map(|r| r.ok()).map(Snippet::from).collect();
+
+        Ok(snippets)
+    }
+
+    pub fn create(&self, snippet: &Snippet) -> DbResult<Snippet> {
+        let conn = self.pool.get()?;
+        conn.execute(
+            r#"
+            INSERT INTO snippets (id, name, body, scope, workspace_id, worktree_id,
+                                  created_at, updated_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+        "#,
+            params![
+                snippet.id,
+                snippet.name,
+                snippet.body,
+                snippet.scope.as_str(),
+                snippet.workspace_id,
+                snippet.worktree_id,
+                snippet.created_at,
+                snippet.updated_at,
+            ],
+        )?;
+
+        self.find_by_id(&snippet.id)?
+            .ok_or_else(|| rusqlite::Error::QueryReturnedNoRows.into())
+    }
+
+    pub fn update(&self, id: &str, name: &str, body: &str) -> DbResult<()> {
+        let conn = self.pool.get()?;
+        conn.execute(
+            "UPDATE snippets SET name = ?, body = ?, updated_at = datetime('now') WHERE id = ?",
+            params![name, body, id],
+        )?;
+        Ok(())
+    }
+
+    pub fn delete(&self, id: &str) -> DbResult<()> {
+        let conn = self.pool.get()?;
+        conn.execute("DELETE FROM snippets WHERE id = ?", [id])?;
+        Ok(())
+    }
+}
@@ -0,0 +1,89 @@
+//! Task group repository for database operations
+
+use rusqlite::params;
+
+use crate::db::{DbPool, DbResult};
+use crate::types::TaskGroup;
+
+pub struct TaskGroupRepository {
+    pool: DbPool,
+}
+
+impl TaskGroupRepository {
+    pub fn new(pool: DbPool) -> Self {
+        Self { pool }
+    }
+
+    pub fn find_by_id(&self, id: &str) -> DbResult<Option<TaskGroup>> {
+        let conn = self.pool.get()?;
+        let result = conn.query_row(
+            r#"
+            SELECT id, workspace_id, prompt, base_branch, winner_agent_id, resolved_at,
+                   created_at
+            FROM task_groups WHERE id = ?
+        "#,
+            [id],
+            |row| {
+                Ok(TaskGroup {
+                    id: row.get(0)?,
+                    workspace_id: row.get(1)?,
+                    prompt: row.get(2)?,
+                    base_branch: row.get(3)?,
+                    winner_agent_id: row.get(4)?,
+                    resolved_at: row.get(5)?,
+                    created_at: row.get(6)?,
+                })
+            },
+        );
+
+        match result {
+            Ok(group) => Ok(Some(group)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn create(&self, group: &TaskGroup) -> DbResult<TaskGroup> {
+        let conn = self.pool.get()?;
+        conn.execute(
+            r#"
+            INSERT INTO task_groups (id, workspace_id, prompt, base_branch, created_at)
+            VALUES (?, ?, ?, ?, ?)
+        "#,
+            params![
+                group.id,
+                group.workspace_id,
+                group.prompt,
+                group.base_branch,
+                group.created_at,
+            ],
+        )?;
+
+        self.find_by_id(&group.id)?
+            .ok_or_else(|| rusqlite::Error::QueryReturnedNoRows.into())
+    }
+
+    /// Record the attempt that was kept
+    pub fn mark_resolved(&self, id: &str, winner_agent_id: &str) -> DbResult<()> {
+        let conn = self.pool.get()?;
+        conn.execute(
+            r#"
+            UPDATE task_groups
+            SET winner_agent_id = ?, resolved_at = datetime('now')
+            WHERE id = ?
+        "#,
+            params![winner_agent_id, id],
+        )?;
+        Ok(())
+    }
+
+    /// Tag an agent as one attempt of a task group
+    pub fn add_agent(&self, id: &str, agent_id: &str) -> DbResult<()> {
+        let conn = self.pool.get()?;
+        conn.execute(
+            "UPDATE agents SET task_group_id = ?, updated_at = datetime('now') WHERE id = ?",
+            params![id, agent_id],
+        )?;
+        Ok(())
+    }
+}
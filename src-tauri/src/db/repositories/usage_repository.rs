@@ -134,6 +134,64 @@ impl UsageRepository {
         Ok(stats)
     }
 
+    /// Tokens recorded this week (from Monday, UTC); usage is only recorded
+    /// on daily rows, so they are summed
+    pub fn get_week_total_tokens(&self) -> DbResult<i64> {
+        let conn = self.pool.get()?;
+        let now = chrono::Utc::now();
+        let week_start = now - chrono::Duration::days(now.weekday().num_days_from_monday() as i64);
+
+        let total = conn.query_row(
+            r#"
+            SELECT COALESCE(SUM(total_tokens), 0) FROM usage_stats
+            WHERE period = 'daily' AND date >= ?
+        "#,
+            [week_start.format("%Y-%m-%d").to_string()],
+            |row| row.get(0),
+        )?;
+
+        Ok(total)
+    }
+
+    /// Sum daily rows into the weekly or monthly rows they fall in, returning
+    /// how many rows changed.
+    ///
+    /// Only periods from the one holding `since` (YYYY-MM-DD) onwards are
+    /// summed; without it all history is. Daily rows are left as they are.
+    pub fn rollup(&self, period: UsagePeriod, since: Option<&str>) -> DbResult<usize> {
+        let (Some(key), Some(since_key)) = (period_key(period, "date"), period_key(period, "?2"))
+        else {
+            return Ok(0);
+        };
+
+        let conn = self.pool.get()?;
+        let changed = conn.execute(
+            &format!(
+                r#"
+            INSERT INTO usage_stats (date, period, input_tokens, output_tokens, total_tokens, request_count, error_count)
+            SELECT {key}, ?1, SUM(input_tokens), SUM(output_tokens), SUM(total_tokens), SUM(request_count), SUM(error_count)
+            FROM usage_stats
+            WHERE period = 'daily' AND (?2 IS NULL OR {key} >= {since_key})
+            GROUP BY {key}
+            ON CONFLICT (date, period) DO UPDATE SET
+                input_tokens = excluded.input_tokens,
+                output_tokens = excluded.output_tokens,
+                total_tokens = excluded.total_tokens,
+                request_count = excluded.request_count,
+                error_count = excluded.error_count,
+                updated_at = datetime('now')
+            WHERE usage_stats.total_tokens != excluded.total_tokens
+                OR usage_stats.input_tokens != excluded.input_tokens
+                OR usage_stats.request_count != excluded.request_count
+                OR usage_stats.error_count != excluded.error_count
+        "#
+            ),
+            params![period.as_str(), since],
+        )?;
+
+        Ok(changed)
+    }
+
     pub fn increment_usage(
         &self,
         input_tokens: i64,
@@ -166,6 +224,20 @@ impl UsageRepository {
     }
 }
 
+/// SQL for the key of the weekly or monthly row that the day `date` falls in,
+/// matching the keys `get_current_period` writes; None for daily rows
+fn period_key(period: UsagePeriod, date: &str) -> Option<String> {
+    match period {
+        UsagePeriod::Daily => None,
+        // The Monday starting the week
+        UsagePeriod::Weekly => Some(format!(
+            "date({0}, '-' || ((strftime('%w', {0}) + 6) % 7) || ' days')",
+            date
+        )),
+        UsagePeriod::Monthly => Some(format!("strftime('%Y-%m', {})", date)),
+    }
+}
+
 // Helper trait for optional query results
 trait OptionalExt<T> {
     fn optional(self) -> Result<Option<T>, rusqlite::Error>;
@@ -89,6 +89,15 @@ impl WorkspaceRepository {
             .ok_or_else(|| rusqlite::Error::QueryReturnedNoRows.into())
     }
 
+    pub fn update_path(&self, id: &str, path: &str) -> DbResult<()> {
+        let conn = self.pool.get()?;
+        conn.execute(
+            "UPDATE workspaces SET path = ?, updated_at = datetime('now') WHERE id = ?",
+            params![path, id],
+        )?;
+        Ok(())
+    }
+
     pub fn delete(&self, id: &str) -> DbResult<()> {
         let conn = self.pool.get()?;
         conn.execute("DELETE FROM workspaces WHERE id = ?", [id])?;
@@ -101,11 +110,14 @@ impl WorkspaceRepository {
         conn.execute(
             r#"
             UPDATE workspaces SET
-                worktree_count = (SELECT COUNT(*) FROM worktrees WHERE workspace_id = ?),
+                worktree_count = (
+                    SELECT COUNT(*) FROM worktrees
+                    WHERE workspace_id = ? AND archived_at IS NULL
+                ),
                 agent_count = (
                     SELECT COUNT(*) FROM agents a
                     JOIN worktrees w ON a.worktree_id = w.id
-                    WHERE w.workspace_id = ? AND a.deleted_at IS NULL
+                    WHERE w.workspace_id = ? AND w.archived_at IS NULL AND a.deleted_at IS NULL
                 ),
                 updated_at = datetime('now')
             WHERE id = ?
@@ -199,6 +211,21 @@ mod tests {
         assert_eq!(created.path, workspace.path);
     }
 
+    #[test]
+    fn test_create_refuses_duplicate_id() {
+        let pool = create_test_pool();
+        let repo = WorkspaceRepository::new(pool);
+
+        let workspace = create_test_workspace();
+        repo.create(&workspace).unwrap();
+        let mut duplicate = create_test_workspace();
+        duplicate.id = workspace.id.clone();
+        duplicate.name = "Duplicate".to_string();
+
+        assert!(repo.create(&duplicate).is_err());
+        assert_eq!(repo.find_all().unwrap().len(), 1);
+    }
+
     #[test]
     fn test_find_by_id() {
         let pool = create_test_pool();
@@ -2,7 +2,7 @@
 
 use rusqlite::params;
 
-use crate::db::{DbPool, DbResult};
+use crate::db::{DbError, DbPool, DbResult};
 use crate::types::{Worktree, WorktreeRow};
 
 pub struct WorktreeRepository {
@@ -18,7 +18,7 @@ impl WorktreeRepository {
         let conn = self.pool.get()?;
         let mut stmt = conn.prepare(
             r#"
-            SELECT id, workspace_id, name, branch, path, sort_mode, display_order, is_main, created_at, updated_at
+            SELECT id, workspace_id, name, branch, path, sort_mode, display_order, is_main, created_at, updated_at, pr_url, sparse_patterns, archived_at, pinned, missing_since
             FROM worktrees WHERE id = ?
         "#,
         )?;
@@ -36,6 +36,11 @@ impl WorktreeRepository {
                     is_main: row.get::<_, i32>(7)? != 0,
                     created_at: row.get(8)?,
                     updated_at: row.get(9)?,
+                    pr_url: row.get(10)?,
+                    sparse_patterns: row.get(11)?,
+                    archived_at: row.get(12)?,
+                    pinned: row.get::<_, i32>(13)? != 0,
+                    missing_since: row.get(14)?,
                 })
             })
             .optional()?;
@@ -47,7 +52,7 @@ impl WorktreeRepository {
         let conn = self.pool.get()?;
         let mut stmt = conn.prepare(
             r#"
-            SELECT id, workspace_id, name, branch, path, sort_mode, display_order, is_main, created_at, updated_at
+            SELECT id, workspace_id, name, branch, path, sort_mode, display_order, is_main, created_at, updated_at, pr_url, sparse_patterns, archived_at, pinned, missing_since
             FROM worktrees WHERE path = ?
         "#,
         )?;
@@ -65,6 +70,11 @@ impl WorktreeRepository {
                     is_main: row.get::<_, i32>(7)? != 0,
                     created_at: row.get(8)?,
                     updated_at: row.get(9)?,
+                    pr_url: row.get(10)?,
+                    sparse_patterns: row.get(11)?,
+                    archived_at: row.get(12)?,
+                    pinned: row.get::<_, i32>(13)? != 0,
+                    missing_since: row.get(14)?,
                 })
             })
             .optional()?;
@@ -76,8 +86,9 @@ impl WorktreeRepository {
         let conn = self.pool.get()?;
         let mut stmt = conn.prepare(
             r#"
-            SELECT id, workspace_id, name, branch, path, sort_mode, display_order, is_main, created_at, updated_at
-            FROM worktrees WHERE workspace_id = ? ORDER BY display_order, created_at
+            SELECT id, workspace_id, name, branch, path, sort_mode, display_order, is_main, created_at, updated_at, pr_url, sparse_patterns, archived_at, pinned, missing_since
+            FROM worktrees WHERE workspace_id = ? AND archived_at IS NULL
+            ORDER BY display_order, created_at
         "#,
         )?;
 
@@ -93,6 +104,82 @@ impl WorktreeRepository {
                 is_main: row.get::<_, i32>(7)? != 0,
                 created_at: row.get(8)?,
                 updated_at: row.get(9)?,
+                pr_url: row.get(10)?,
+                sparse_patterns: row.get(11)?,
+                archived_at: row.get(12)?,
+                pinned: row.get::<_, i32>(13)? != 0,
+                missing_since: row.get(14)?,
+            })
+        })?;
+
+        let worktrees: Vec<Worktree> = rows.filter_map(|r| r.ok()).map(Worktree::from).collect();
+
+        Ok(worktrees)
+    }
+
+    /// Archived worktrees of a workspace, most recently archived first
+    pub fn find_archived_by_workspace_id(&self, workspace_id: &str) -> DbResult<Vec<Worktree>> {
+        let conn = self.pool.get()?;
+        let mut stmt = conn.prepare(
+            r#"
+            SELECT id, workspace_id, name, branch, path, sort_mode, display_order, is_main, created_at, updated_at, pr_url, sparse_patterns, archived_at, pinned, missing_since
+            FROM worktrees WHERE workspace_id = ? AND archived_at IS NOT NULL
+            ORDER BY archived_at DESC
+        "#,
+        )?;
+
+        let rows = stmt.query_map([workspace_id], |row| {
+            Ok(WorktreeRow {
+                id: row.get(0)?,
+                workspace_id: row.get(1)?,
+                name: row.get(2)?,
+                branch: row.get(3)?,
+                path: row.get(4)?,
+                sort_mode: row.get(5)?,
+                display_order: row.get(6)?,
+                is_main: row.get::<_, i32>(7)? != 0,
+                created_at: row.get(8)?,
+                updated_at: row.get(9)?,
+                pr_url: row.get(10)?,
+                sparse_patterns: row.get(11)?,
+                archived_at: row.get(12)?,
+                pinned: row.get::<_, i32>(13)? != 0,
+                missing_since: row.get(14)?,
+            })
+        })?;
+
+        let worktrees: Vec<Worktree> = rows.filter_map(|r| r.ok()).map(Worktree::from).collect();
+
+        Ok(worktrees)
+    }
+
+    pub fn find_all(&self) -> DbResult<Vec<Worktree>> {
+        let conn = self.pool.get()?;
+        let mut stmt = conn.prepare(
+            r#"
+            SELECT id, workspace_id, name, branch, path, sort_mode, display_order, is_main, created_at, updated_at, pr_url, sparse_patterns, archived_at, pinned, missing_since
+            FROM worktrees WHERE archived_at IS NULL
+            ORDER BY workspace_id, display_order, created_at
+        "#,
+        )?;
+
+        let rows = stmt.query_map([], |row| {
+            Ok(WorktreeRow {
+                id: row.get(0)?,
+                workspace_id: row.get(1)?,
+                name: row.get(2)?,
+                branch: row.get(3)?,
+                path: row.get(4)?,
+                sort_mode: row.get(5)?,
+                display_order: row.get(6)?,
+                is_main: row.get::<_, i32>(7)? != 0,
+                created_at: row.get(8)?,
+                updated_at: row.get(9)?,
+                pr_url: row.get(10)?,
+                sparse_patterns: row.get(11)?,
+                archived_at: row.get(12)?,
+                pinned: row.get::<_, i32>(13)? != 0,
+                missing_since: row.get(14)?,
             })
         })?;
 
@@ -106,8 +193,8 @@ impl WorktreeRepository {
 
         conn.execute(
             r#"
-            INSERT INTO worktrees (id, workspace_id, name, branch, path, sort_mode, display_order, is_main, created_at, updated_at)
-            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            INSERT INTO worktrees (id, workspace_id, name, branch, path, sort_mode, display_order, is_main, created_at, updated_at, sparse_patterns)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
         "#,
             params![
                 worktree.id,
@@ -120,6 +207,10 @@ impl WorktreeRepository {
                 worktree.is_main as i32,
                 worktree.created_at,
                 worktree.updated_at,
+                worktree
+                    .sparse_patterns
+                    .as_ref()
+                    .map(|patterns| serde_json::to_string(patterns).unwrap_or_default()),
             ],
         )?;
 
@@ -127,30 +218,102 @@ impl WorktreeRepository {
             .ok_or_else(|| rusqlite::Error::QueryReturnedNoRows.into())
     }
 
+    /// Save a worktree unless its row changed since `worktree.updated_at`, in
+    /// which case nothing is written and `DbError::Conflict` is returned
     pub fn update(&self, worktree: &Worktree) -> DbResult<Worktree> {
         let conn = self.pool.get()?;
 
-        conn.execute(
+        let changed = conn.execute(
             r#"
             UPDATE worktrees SET
                 name = ?,
                 branch = ?,
                 sort_mode = ?,
                 display_order = ?,
-                updated_at = datetime('now')
-            WHERE id = ?
+                updated_at = ?
+            WHERE id = ? AND updated_at = ?
         "#,
             params![
                 worktree.name,
                 worktree.branch,
                 worktree.sort_mode.as_str(),
                 worktree.display_order,
+                chrono::Utc::now().to_rfc3339(),
                 worktree.id,
+                worktree.updated_at,
             ],
         )?;
 
-        self.find_by_id(&worktree.id)?
-            .ok_or_else(|| rusqlite::Error::QueryReturnedNoRows.into())
+        let updated = self
+            .find_by_id(&worktree.id)?
+            .ok_or(rusqlite::Error::QueryReturnedNoRows)?;
+        if changed == 0 {
+            return Err(DbError::Conflict(updated.updated_at));
+        }
+        Ok(updated)
+    }
+
+    pub fn update_path(&self, id: &str, path: &str) -> DbResult<()> {
+        let conn = self.pool.get()?;
+        conn.execute(
+            "UPDATE worktrees SET path = ?, updated_at = datetime('now') WHERE id = ?",
+            params![path, id],
+        )?;
+        Ok(())
+    }
+
+    pub fn set_pr_url(&self, id: &str, pr_url: &str) -> DbResult<()> {
+        let conn = self.pool.get()?;
+        conn.execute(
+            "UPDATE worktrees SET pr_url = ?, updated_at = datetime('now') WHERE id = ?",
+            params![pr_url, id],
+        )?;
+        Ok(())
+    }
+
+    /// Archive a worktree (Some) or bring it back (None)
+    pub fn set_archived(&self, id: &str, archived_at: Option<&str>) -> DbResult<()> {
+        let conn = self.pool.get()?;
+        conn.execute(
+            "UPDATE worktrees SET archived_at = ?, updated_at = datetime('now') WHERE id = ?",
+            params![archived_at, id],
+        )?;
+        Ok(())
+    }
+
+    /// Keep a worktree through scans that no longer find it, or stop keeping it
+    pub fn set_pinned(&self, id: &str, pinned: bool) -> DbResult<()> {
+        let conn = self.pool.get()?;
+        conn.execute(
+            "UPDATE worktrees SET pinned = ?, updated_at = datetime('now') WHERE id = ?",
+            params![pinned as i32, id],
+        )?;
+        Ok(())
+    }
+
+    /// Mark a worktree's checkout unavailable since a time (Some) or back (None)
+    pub fn set_missing(&self, id: &str, missing_since: Option<&str>) -> DbResult<()> {
+        let conn = self.pool.get()?;
+        conn.execute(
+            "UPDATE worktrees SET missing_since = ?, updated_at = datetime('now') WHERE id = ?",
+            params![missing_since, id],
+        )?;
+        Ok(())
+    }
+
+    /// Whether schedules or snippets belong to a worktree, which deleting it
+    /// would delete with it
+    pub fn has_dependents(&self, id: &str) -> DbResult<bool> {
+        let conn = self.pool.get()?;
+        let found = conn.query_row(
+            r#"
+            SELECT EXISTS(SELECT 1 FROM schedules WHERE worktree_id = ?1)
+                OR EXISTS(SELECT 1 FROM snippets WHERE worktree_id = ?1)
+        "#,
+            [id],
+            |row| row.get(0),
+        )?;
+        Ok(found)
     }
 
     pub fn delete(&self, id: &str) -> DbResult<()> {
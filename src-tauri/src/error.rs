@@ -1,9 +1,19 @@
 //! Error types and result aliases for Claude Manager
 
-use serde::Serialize;
+use serde::{Serialize, Serializer};
 use thiserror::Error;
 
+use crate::services::{
+    AgentError, AuthorizationError, BootstrapError, ClaudeApiError, ClaudeMdError, GitError,
+    IdleShutdownError, JobError, McpError, ProcessError, ProfileError, ResourceMonitorError,
+    ScheduleError, SettingsSyncError, SnapshotError, SnippetError, TaskGroupError, WorkspaceError,
+    WorktreeError,
+};
+
 /// Main application error type
+///
+/// Commands return this to the frontend, where it arrives as an
+/// [`ErrorResponse`] object so the UI can branch on `code`.
 #[derive(Error, Debug)]
 pub enum AppError {
     #[error("Database error: {0}")]
@@ -24,12 +34,74 @@ pub enum AppError {
     #[error("Worktree error: {0}")]
     Worktree(#[from] crate::services::WorktreeError),
 
+    #[error("Backup error: {0}")]
+    Backup(#[from] crate::services::BackupError),
+
+    #[error("Remote access error: {0}")]
+    RemoteAccess(#[from] crate::services::RemoteAccessError),
+
+    #[error("Pull request error: {0}")]
+    PullRequest(#[from] crate::services::PullRequestError),
+
+    #[error("Task group error: {0}")]
+    TaskGroup(#[from] crate::services::TaskGroupError),
+
+    #[error("Snippet error: {0}")]
+    Snippet(#[from] crate::services::SnippetError),
+
+    #[error("Snapshot error: {0}")]
+    Snapshot(#[from] crate::services::SnapshotError),
+
+    #[error("Schedule error: {0}")]
+    Schedule(#[from] crate::services::ScheduleError),
+    #[error("Settings sync error: {0}")]
+    SettingsSync(#[from] crate::services::SettingsSyncError),
+
+    #[error("MCP error: {0}")]
+    Mcp(#[from] crate::services::McpError),
+
+    #[error("CLAUDE.md error: {0}")]
+    ClaudeMd(#[from] crate::services::ClaudeMdError),
+
+    #[error("Onboarding error: {0}")]
+    Onboarding(#[from] crate::services::OnboardingError),
+
+    #[error("Bootstrap error: {0}")]
+    Bootstrap(#[from] crate::services::BootstrapError),
+
+    #[error("Job error: {0}")]
+    Job(#[from] crate::services::JobError),
+
+    #[error("Usage error: {0}")]
+    Usage(#[from] crate::services::UsageError),
+
+    #[error("Claude API error: {0}")]
+    ClaudeApi(#[from] crate::services::ClaudeApiError),
+
+    #[error("Profile error: {0}")]
+    Profile(#[from] ProfileError),
+
+    #[error("Idle shutdown error: {0}")]
+    IdleShutdown(#[from] IdleShutdownError),
+
+    #[error("Resource monitor error: {0}")]
+    ResourceMonitor(#[from] ResourceMonitorError),
+
+    #[error("Login error: {0}")]
+    Login(#[from] crate::services::LoginError),
+
+    #[error("Authorization error: {0}")]
+    Authorization(#[from] AuthorizationError),
+
     #[error("Validation error: {0}")]
     Validation(String),
 
     #[error("Not found: {0}")]
     NotFound(String),
 
+    #[error("Offline: {0}")]
+    Offline(String),
+
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
 
@@ -53,34 +125,237 @@ pub struct ErrorResponse {
     pub details: Option<serde_json::Value>,
 }
 
-impl From<AppError> for ErrorResponse {
-    fn from(err: AppError) -> Self {
-        let (code, message) = match &err {
-            AppError::Database(e) => ("DATABASE_ERROR", e.to_string()),
-            AppError::Agent(e) => ("AGENT_ERROR", e.to_string()),
-            AppError::Process(e) => ("PROCESS_ERROR", e.to_string()),
-            AppError::Git(e) => ("GIT_ERROR", e.to_string()),
-            AppError::Workspace(e) => ("WORKSPACE_ERROR", e.to_string()),
-            AppError::Worktree(e) => ("WORKTREE_ERROR", e.to_string()),
-            AppError::Validation(msg) => ("VALIDATION_ERROR", msg.clone()),
-            AppError::NotFound(msg) => ("NOT_FOUND", msg.clone()),
-            AppError::Io(e) => ("IO_ERROR", e.to_string()),
-            AppError::Json(e) => ("JSON_ERROR", e.to_string()),
-            AppError::Internal(msg) => ("INTERNAL_ERROR", msg.clone()),
+impl AppError {
+    /// Stable machine-readable code; specific failures get their own code
+    /// instead of the one for the service they came from
+    pub fn code(&self) -> &'static str {
+        match self {
+            AppError::Agent(AgentError::NotFound(_))
+            | AppError::Workspace(WorkspaceError::NotFound(_))
+            | AppError::Worktree(WorktreeError::NotFound(_))
+            | AppError::Worktree(WorktreeError::WorkspaceNotFound(_))
+            | AppError::PullRequest(crate::services::PullRequestError::NotFound(_))
+            | AppError::TaskGroup(TaskGroupError::NotFound(_))
+            | AppError::Snippet(SnippetError::NotFound(_))
+            | AppError::Snippet(SnippetError::AgentNotFound(_))
+            | AppError::Snapshot(SnapshotError::NotFound(_))
+            | AppError::Snapshot(SnapshotError::Agent(AgentError::NotFound(_)))
+            | AppError::Schedule(ScheduleError::NotFound(_))
+            | AppError::SettingsSync(SettingsSyncError::WorkspaceNotFound(_))
+            | AppError::Mcp(McpError::WorktreeNotFound(_))
+            | AppError::Mcp(McpError::ServerNotFound(_))
+            | AppError::ClaudeMd(ClaudeMdError::WorktreeNotFound(_))
+            | AppError::Bootstrap(BootstrapError::WorkspaceNotFound(_))
+            | AppError::Job(JobError::NotFound(_))
+            | AppError::Profile(ProfileError::NotFound(_))
+            | AppError::Profile(ProfileError::WorkspaceNotFound(_))
+            | AppError::Profile(ProfileError::AgentNotFound(_))
+            | AppError::IdleShutdown(IdleShutdownError::AgentNotFound(_))
+            | AppError::Agent(AgentError::TranscriptNotFound(_))
+            | AppError::NotFound(_) => "NOT_FOUND",
+            AppError::Agent(AgentError::Validation(_))
+            | AppError::Workspace(WorkspaceError::InvalidPath(_))
+            | AppError::Worktree(WorktreeError::CannotDeleteMain)
+            | AppError::Worktree(WorktreeError::Validation(_))
+            | AppError::TaskGroup(TaskGroupError::Validation(_))
+            | AppError::Snippet(SnippetError::Validation(_))
+            | AppError::Schedule(ScheduleError::Validation(_))
+            | AppError::SettingsSync(SettingsSyncError::Validation(_))
+            | AppError::Mcp(McpError::Validation(_))
+            | AppError::Job(JobError::Finished(_))
+            | AppError::Profile(ProfileError::Validation(_))
+            | AppError::IdleShutdown(IdleShutdownError::Validation(_))
+            | AppError::ResourceMonitor(ResourceMonitorError::Validation(_))
+            | AppError::Validation(_) => "VALIDATION_ERROR",
+            AppError::Workspace(WorkspaceError::AlreadyExists(_)) => "ALREADY_EXISTS",
+            AppError::Agent(AgentError::LimitReached { .. }) => "AGENT_LIMIT_REACHED",
+            AppError::Agent(AgentError::UsageLimitExceeded { .. }) => "USAGE_LIMIT_EXCEEDED",
+            AppError::Worktree(WorktreeError::Busy { .. })
+            | AppError::TaskGroup(TaskGroupError::Worktree(WorktreeError::Busy { .. })) => {
+                "WORKTREE_BUSY"
+            }
+            AppError::Agent(AgentError::Bootstrapping(_))
+            | AppError::TaskGroup(TaskGroupError::Agent(AgentError::Bootstrapping(_))) => {
+                "WORKTREE_BOOTSTRAPPING"
+            }
+            AppError::ClaudeMd(ClaudeMdError::Conflict { .. })
+            | AppError::Agent(AgentError::Conflict { .. })
+            | AppError::Worktree(WorktreeError::Conflict { .. }) => "CONFLICT",
+            AppError::Worktree(WorktreeError::Cancelled) | AppError::Git(GitError::Cancelled) => {
+                "CANCELLED"
+            }
+            AppError::Git(GitError::Git(e)) if e.code() == git2::ErrorCode::Conflict => {
+                "GIT_CONFLICT"
+            }
+            AppError::Workspace(WorkspaceError::Git(_))
+            | AppError::Worktree(WorktreeError::Git(_))
+            | AppError::Git(_) => "GIT_ERROR",
+            AppError::Agent(AgentError::Process(ProcessError::MessageTooLarge { .. }))
+            | AppError::Snippet(SnippetError::Process(ProcessError::MessageTooLarge { .. }))
+            | AppError::Process(ProcessError::MessageTooLarge { .. }) => "MESSAGE_TOO_LARGE",
+            AppError::Agent(AgentError::Process(ProcessError::ReadOnly))
+            | AppError::Snippet(SnippetError::Process(ProcessError::ReadOnly))
+            | AppError::Process(ProcessError::ReadOnly)
+            | AppError::Authorization(AuthorizationError::ReadOnly(_)) => "READ_ONLY",
+            AppError::Authorization(AuthorizationError::Forbidden { .. }) => "FORBIDDEN",
+            AppError::Agent(AgentError::Process(_))
+            | AppError::Snippet(SnippetError::Process(_))
+            | AppError::Process(_) => "PROCESS_ERROR",
+            AppError::Database(_)
+            | AppError::Agent(AgentError::Database(_))
+            | AppError::Workspace(WorkspaceError::Database(_))
+            | AppError::Worktree(WorktreeError::Database(_))
+            | AppError::Snippet(SnippetError::Database(_))
+            | AppError::Schedule(ScheduleError::Database(_))
+            | AppError::SettingsSync(SettingsSyncError::Database(_))
+            | AppError::Mcp(McpError::Database(_))
+            | AppError::ClaudeMd(ClaudeMdError::Database(_))
+            | AppError::Bootstrap(BootstrapError::Database(_))
+            | AppError::Job(JobError::Database(_))
+            | AppError::Profile(ProfileError::Database(_))
+            | AppError::IdleShutdown(IdleShutdownError::Database(_))
+            | AppError::ResourceMonitor(ResourceMonitorError::Database(_))
+            | AppError::Authorization(AuthorizationError::Database(_))
+            | AppError::Onboarding(_) => "DATABASE_ERROR",
+            AppError::Backup(_) => "BACKUP_ERROR",
+            AppError::RemoteAccess(_) => "REMOTE_ACCESS_ERROR",
+            AppError::PullRequest(_) => "PULL_REQUEST_ERROR",
+            AppError::TaskGroup(_) => "TASK_GROUP_ERROR",
+            AppError::Snapshot(_) => "SNAPSHOT_ERROR",
+            AppError::Schedule(_) => "SCHEDULE_ERROR",
+            AppError::SettingsSync(_) => "SETTINGS_SYNC_ERROR",
+            AppError::Mcp(_) => "MCP_ERROR",
+            AppError::ClaudeMd(_) => "CLAUDE_MD_ERROR",
+            AppError::Bootstrap(_) => "BOOTSTRAP_ERROR",
+            AppError::Usage(_) => "USAGE_ERROR",
+            AppError::ClaudeApi(ClaudeApiError::RateLimited { .. }) => "CLAUDE_RATE_LIMITED",
+            AppError::ClaudeApi(ClaudeApiError::Unauthorized(_)) => "CLAUDE_UNAUTHORIZED",
+            AppError::ClaudeApi(_) => "CLAUDE_API_ERROR",
+            AppError::Login(_) => "LOGIN_ERROR",
+            AppError::Agent(AgentError::Transcript(_)) | AppError::Io(_) => "IO_ERROR",
+            AppError::Json(_) => "JSON_ERROR",
+            AppError::Offline(_) => "OFFLINE",
+            AppError::Internal(_) => "INTERNAL_ERROR",
+        }
+    }
+
+    /// Structured data the UI needs to act on the error
+    pub fn details(&self) -> Option<serde_json::Value> {
+        match self {
+            AppError::Workspace(WorkspaceError::AlreadyExists(id)) => {
+                Some(serde_json::json!({ "existingWorkspaceId": id }))
+            }
+            AppError::Agent(AgentError::LimitReached {
+                workspace_id,
+                running,
+                limit,
+            }) => Some(serde_json::json!({
+                "workspaceId": workspace_id,
+                "running": running,
+                "limit": limit,
+            })),
+            AppError::Agent(AgentError::UsageLimitExceeded {
+                window,
+                utilization,
+                cutoff,
+            }) => Some(serde_json::json!({
+                "window": window,
+                "utilization": utilization,
+                "cutoff": cutoff,
+            })),
+            AppError::ClaudeApi(ClaudeApiError::RateLimited {
+                retry_after: Some(wait),
+            }) => Some(serde_json::json!({ "retryAfterSecs": wait.as_secs() })),
+            AppError::ClaudeMd(ClaudeMdError::Conflict { path, modified_at }) => {
+                Some(serde_json::json!({ "path": path, "modifiedAt": modified_at }))
+            }
+            AppError::Agent(AgentError::Conflict { id, updated_at })
+            | AppError::Worktree(WorktreeError::Conflict { id, updated_at }) => {
+                Some(serde_json::json!({ "id": id, "updatedAt": updated_at }))
+            }
+            AppError::Agent(AgentError::Bootstrapping(worktree_id)) => {
+                Some(serde_json::json!({ "worktreeId": worktree_id }))
+            }
+            AppError::Authorization(AuthorizationError::Forbidden {
+                command,
+                capability,
+            }) => Some(serde_json::json!({ "command": command, "capability": capability })),
+            AppError::Worktree(WorktreeError::Busy {
+                worktree_id,
+                agents,
+            })
+            | AppError::TaskGroup(TaskGroupError::Worktree(WorktreeError::Busy {
+                worktree_id,
+                agents,
+            })) => Some(serde_json::json!({ "worktreeId": worktree_id, "agents": agents })),
+            AppError::Agent(AgentError::Process(ProcessError::MessageTooLarge { size, max }))
+            | AppError::Snippet(SnippetError::Process(ProcessError::MessageTooLarge {
+                size,
+                max,
+            }))
+            | AppError::Process(ProcessError::MessageTooLarge { size, max }) => {
+                Some(serde_json::json!({ "size": size, "maxSize": max }))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl From<&AppError> for ErrorResponse {
+    fn from(err: &AppError) -> Self {
+        let message = match err {
+            AppError::Validation(msg)
+            | AppError::NotFound(msg)
+            | AppError::Offline(msg)
+            | AppError::Internal(msg) => msg.clone(),
+            AppError::Database(e) => e.to_string(),
+            AppError::Agent(e) => e.to_string(),
+            AppError::Process(e) => e.to_string(),
+            AppError::Git(e) => e.to_string(),
+            AppError::Workspace(e) => e.to_string(),
+            AppError::Worktree(e) => e.to_string(),
+            AppError::Backup(e) => e.to_string(),
+            AppError::RemoteAccess(e) => e.to_string(),
+            AppError::PullRequest(e) => e.to_string(),
+            AppError::TaskGroup(e) => e.to_string(),
+            AppError::Snippet(e) => e.to_string(),
+            AppError::Snapshot(e) => e.to_string(),
+            AppError::Schedule(e) => e.to_string(),
+            AppError::SettingsSync(e) => e.to_string(),
+            AppError::Mcp(e) => e.to_string(),
+            AppError::ClaudeMd(e) => e.to_string(),
+            AppError::Onboarding(e) => e.to_string(),
+            AppError::Bootstrap(e) => e.to_string(),
+            AppError::Job(e) => e.to_string(),
+            AppError::Usage(e) => e.to_string(),
+            AppError::ClaudeApi(e) => e.to_string(),
+            AppError::Profile(e) => e.to_string(),
+            AppError::IdleShutdown(e) => e.to_string(),
+            AppError::ResourceMonitor(e) => e.to_string(),
+            AppError::Login(e) => e.to_string(),
+            AppError::Authorization(e) => e.to_string(),
+            AppError::Io(e) => e.to_string(),
+            AppError::Json(e) => e.to_string(),
         };
 
         ErrorResponse {
-            code: code.to_string(),
+            code: err.code().to_string(),
             message,
-            details: None,
+            details: err.details(),
         }
     }
 }
 
-// Implement conversion to Tauri's invoke error
-impl From<AppError> for tauri::ipc::InvokeError {
+impl From<AppError> for ErrorResponse {
     fn from(err: AppError) -> Self {
-        tauri::ipc::InvokeError::from(err.to_string())
+        ErrorResponse::from(&err)
+    }
+}
+
+// Serialized as an ErrorResponse, which also makes it a Tauri invoke error
+impl Serialize for AppError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        ErrorResponse::from(self).serialize(serializer)
     }
 }
 
@@ -104,3 +379,98 @@ impl<T, E: Into<AppError>> ResultExt<T> for Result<T, E> {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_serializes_code_message_and_details() {
+        let err = AppError::from(WorkspaceError::AlreadyExists("ws_1".to_string()));
+
+        let json = serde_json::to_value(&err).unwrap();
+
+        assert_eq!(json["code"], "ALREADY_EXISTS");
+        assert_eq!(
+            json["message"],
+            "Workspace already exists for this repository: ws_1"
+        );
+        assert_eq!(json["details"]["existingWorkspaceId"], "ws_1");
+    }
+
+    #[test]
+    fn test_stale_updates_are_conflicts() {
+        let err = AppError::from(AgentError::Conflict {
+            id: "ag_1".to_string(),
+            updated_at: "2026-01-01T00:00:00+00:00".to_string(),
+        });
+
+        let response = ErrorResponse::from(err);
+
+        assert_eq!(response.code, "CONFLICT");
+        let details = response.details.unwrap();
+        assert_eq!(details["id"], "ag_1");
+        assert_eq!(details["updatedAt"], "2026-01-01T00:00:00+00:00");
+    }
+
+    #[test]
+    fn test_not_found_code_across_services() {
+        let errors = [
+            AppError::from(AgentError::NotFound("ag_1".to_string())),
+            AppError::from(WorktreeError::NotFound("wt_1".to_string())),
+            AppError::NotFound("thing".to_string()),
+        ];
+
+        for err in errors {
+            let response = ErrorResponse::from(err);
+            assert_eq!(response.code, "NOT_FOUND");
+            assert!(response.details.is_none());
+        }
+    }
+
+    #[test]
+    fn test_message_too_large_has_its_own_code() {
+        let err = AppError::from(AgentError::Process(ProcessError::MessageTooLarge {
+            size: 300_000,
+            max: 262_144,
+        }));
+
+        let response = ErrorResponse::from(err);
+
+        assert_eq!(response.code, "MESSAGE_TOO_LARGE");
+        let details = response.details.unwrap();
+        assert_eq!(details["size"], 300_000);
+        assert_eq!(details["maxSize"], 262_144);
+    }
+
+    #[test]
+    fn test_observer_mode_refusals_are_read_only() {
+        for err in [
+            AppError::from(ProcessError::ReadOnly),
+            AppError::from(AgentError::Process(ProcessError::ReadOnly)),
+            AppError::from(AuthorizationError::ReadOnly("start_agent".to_string())),
+        ] {
+            assert_eq!(err.code(), "READ_ONLY");
+        }
+    }
+
+    #[test]
+    fn test_usage_limit_exceeded_has_its_own_code() {
+        let err = AppError::from(AgentError::UsageLimitExceeded {
+            window: "seven-day".to_string(),
+            utilization: 97.0,
+            cutoff: 95.0,
+        });
+
+        let response = ErrorResponse::from(err);
+
+        assert_eq!(response.code, "USAGE_LIMIT_EXCEEDED");
+        assert_eq!(
+            response.message,
+            "Claude seven-day usage is at 97%, past the 95% cutoff for starting agents"
+        );
+        let details = response.details.unwrap();
+        assert_eq!(details["window"], "seven-day");
+        assert_eq!(details["cutoff"], 95.0);
+    }
+}
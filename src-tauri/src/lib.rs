@@ -8,11 +8,19 @@ pub mod db;
 pub mod error;
 pub mod services;
 pub mod types;
+pub mod util;
 
 use std::sync::Arc;
 
 use db::DbPool;
-use services::{AgentService, ProcessManager, UsageService, WorkspaceService, WorktreeService};
+use services::{
+    AgentService, AuthorizationService, BackupService, BootstrapService, ChangeFeedService,
+    ClaudeLoginService, ClaudeMdService, EnvironmentService, GitWatchService, IdleShutdownService,
+    JobService, LogService, McpService, NetworkStatusService, OnboardingService, ProcessManager,
+    ProfileService, PullRequestService, RemoteAccessService, ResourceMonitorService,
+    ScheduleService, SessionSnapshotService, SettingsSyncService, SnippetService, TaskGroupService,
+    UsageService, WorkspaceRefreshService, WorkspaceService, WorktreeService,
+};
 
 /// Application state shared across all Tauri commands
 pub struct AppState {
@@ -24,10 +32,58 @@ pub struct AppState {
     pub agent_service: Arc<AgentService>,
     /// Workspace service for workspace-related operations
     pub workspace_service: Arc<WorkspaceService>,
+    /// Workspace refresh queue running git rescans in the background
+    pub workspace_refresh_service: Arc<WorkspaceRefreshService>,
     /// Worktree service for worktree-related operations
     pub worktree_service: Arc<WorktreeService>,
+    /// Bootstrap service for commands run in new worktrees
+    pub bootstrap_service: Arc<BootstrapService>,
     /// Usage service for tracking API usage
     pub usage_service: Arc<UsageService>,
+    /// Profile service for the Claude accounts agents run under
+    pub profile_service: Arc<ProfileService>,
+    /// Idle shutdown service stopping agents left idle at the prompt
+    pub idle_shutdown_service: Arc<IdleShutdownService>,
+    /// Resource monitor sampling the memory and CPU of agent processes
+    pub resource_monitor_service: Arc<ResourceMonitorService>,
+    /// Backup service for snapshotting and restoring the database
+    pub backup_service: Arc<BackupService>,
+    /// Remote access service for the opt-in TLS listener
+    pub remote_access_service: Arc<RemoteAccessService>,
+    /// Pull request service for opening PRs from worktree branches
+    pub pull_request_service: Arc<PullRequestService>,
+    /// Git watch service for live worktree status
+    pub git_watch_service: Arc<GitWatchService>,
+    /// Task group service for fanning a prompt out to several agents
+    pub task_group_service: Arc<TaskGroupService>,
+    /// Snippet service for canned prompts sent to agents
+    pub snippet_service: Arc<SnippetService>,
+    /// Session snapshot service for saving and restoring agent sessions
+    pub snapshot_service: Arc<SessionSnapshotService>,
+    /// Schedule service for recurring agent runs
+    pub schedule_service: Arc<ScheduleService>,
+    /// Settings sync service for the hooks in worktrees' Claude settings
+    pub settings_sync_service: Arc<SettingsSyncService>,
+    /// MCP service for worktree MCP server configuration
+    pub mcp_service: Arc<McpService>,
+    /// CLAUDE.md service for worktree memory files
+    pub claude_md_service: Arc<ClaudeMdService>,
+    /// Environment service for Claude CLI checks
+    pub environment_service: Arc<EnvironmentService>,
+    /// Login service for re-authenticating the Claude CLI
+    pub claude_login_service: Arc<ClaudeLoginService>,
+    /// Network status service noticing when the app is offline
+    pub network_status_service: Arc<NetworkStatusService>,
+    /// Onboarding service for the first-run setup wizard
+    pub onboarding_service: Arc<OnboardingService>,
+    /// Log service holding recent backend log records
+    pub log_service: Arc<LogService>,
+    /// Job service running long operations in the background
+    pub job_service: Arc<JobService>,
+    /// Change feed broadcasting entity mutations to every window
+    pub change_feed: Arc<ChangeFeedService>,
+    /// Authorization service checking and auditing commands
+    pub authorization_service: Arc<AuthorizationService>,
 }
 
 // Re-export commonly used types
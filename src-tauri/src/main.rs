@@ -1,16 +1,28 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-use claude_manager_lib::{commands, db, services, AppState};
+use claude_manager_lib::{commands, db, services, types, AppState};
 use std::sync::Arc;
 use tauri::Manager;
+use tauri_plugin_notification::NotificationExt;
+use tokio::sync::broadcast;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
 
 fn main() {
-    // Initialize logging
-    tracing_subscriber::fmt()
-        .with_env_filter(
+    // Initialize logging: stdout, a rolling file and an in-memory ring for the UI
+    let log_service = Arc::new(services::LogService::new());
+    tracing_subscriber::registry()
+        .with(
             tracing_subscriber::EnvFilter::from_default_env()
                 .add_directive(tracing::Level::INFO.into()),
         )
+        .with(tracing_subscriber::fmt::layer())
+        .with(
+            tracing_subscriber::fmt::layer()
+                .with_ansi(false)
+                .with_writer(log_service.file_writer()),
+        )
+        .with(log_service.capture_layer())
         .init();
 
     tracing::info!("Starting Claude Manager");
@@ -21,7 +33,7 @@ fn main() {
         .plugin(tauri_plugin_process::init())
         .plugin(tauri_plugin_notification::init())
         .plugin(tauri_plugin_dialog::init())
-        .setup(|app| {
+        .setup(move |app| {
             // Get data directory
             let data_dir = app
                 .path()
@@ -29,6 +41,9 @@ fn main() {
                 .expect("Failed to get app data directory");
 
             tracing::info!("Data directory: {:?}", data_dir);
+            if let Err(e) = log_service.set_log_dir(data_dir.join("logs")) {
+                tracing::warn!("Failed to open log file: {}", e);
+            }
 
             // Initialize database
             let pool = db::init_database(data_dir.clone())
@@ -36,6 +51,26 @@ fn main() {
 
             tracing::info!("Database initialized");
 
+            // Encrypt secrets stored in the database from here on
+            // When unsure, assume there are sealed values rather than risk a new key
+            let sealed_values = db::SettingsRepository::new(pool.clone())
+                .has_encrypted()
+                .unwrap_or(true);
+            match db::crypto::FieldCipher::load_or_create(&data_dir, sealed_values) {
+                Ok(cipher) => {
+                    db::crypto::install(cipher);
+                    match db::SettingsRepository::new(pool.clone()).encrypt_plaintext() {
+                        Ok(0) => {}
+                        Ok(count) => tracing::info!("Encrypted {} stored secrets", count),
+                        Err(e) => tracing::warn!("Failed to encrypt stored secrets: {}", e),
+                    }
+                }
+                Err(e @ db::crypto::CryptoError::KeyUnavailable(_)) => {
+                    tracing::error!("Stored secrets can't be read until the key is back: {}", e)
+                }
+                Err(e) => tracing::error!("Secrets will be stored unencrypted: {}", e),
+            }
+
             // Kill orphaned processes from previous run, then clear PIDs in DB
             let agent_repo = db::repositories::AgentRepository::new(pool.clone());
             if let Ok(orphans) = agent_repo.find_with_pids() {
@@ -51,6 +86,11 @@ fn main() {
                     std::thread::sleep(std::time::Duration::from_millis(500));
                 }
             }
+            // Apply exits recorded by a previous run that never reached the agents table
+            let status_sync = services::StatusSyncService::new(pool.clone());
+            if let Err(e) = status_sync.replay_on_startup() {
+                tracing::warn!("Failed to replay event journal: {}", e);
+            }
             if let Err(e) = agent_repo.clear_running_pids() {
                 tracing::warn!("Failed to clear orphaned PIDs: {}", e);
             }
@@ -60,72 +100,274 @@ fn main() {
                 .unwrap_or_else(|_| "claude".to_string());
             tracing::info!("Claude CLI path: {}", claude_cli_path);
 
-            let process_manager = Arc::new(services::ProcessManager::new(claude_cli_path));
+            let environment_service =
+                Arc::new(services::EnvironmentService::new(claude_cli_path.clone()));
+            let claude_login_service =
+                Arc::new(services::ClaudeLoginService::new(claude_cli_path.clone()));
+            let process_manager = Arc::new(
+                services::ProcessManager::new(claude_cli_path)
+                    .with_event_journal(db::EventJournalRepository::new(pool.clone())),
+            );
+
+            // Take out hooks left in worktrees by a previous run; no agent
+            // is running yet
+            let settings_sync_service = Arc::new(
+                services::SettingsSyncService::new(pool.clone())
+                    .with_process_manager(process_manager.clone()),
+            );
+            match settings_sync_service.release_tracked() {
+                Ok(0) => {}
+                Ok(count) => tracing::info!("Removed stale hook settings from {} worktrees", count),
+                Err(e) => tracing::warn!("Failed to remove stale hook settings: {}", e),
+            }
 
             // Initialize services
-            let agent_service =
-                Arc::new(services::AgentService::new(pool.clone(), process_manager.clone()));
+            let bootstrap_service = Arc::new(services::BootstrapService::new(pool.clone()));
+            let network_status_service = Arc::new(services::NetworkStatusService::new());
+            let usage_service = Arc::new(
+                services::UsageService::new(pool.clone())
+                    .with_network(network_status_service.clone()),
+            );
+            let profile_service = Arc::new(services::ProfileService::new(pool.clone()));
+            let idle_shutdown_service = Arc::new(services::IdleShutdownService::new(
+                pool.clone(),
+                process_manager.clone(),
+            ));
+            let resource_monitor_service = Arc::new(services::ResourceMonitorService::new(
+                pool.clone(),
+                process_manager.clone(),
+            ));
+            let agent_service = Arc::new(
+                services::AgentService::new(pool.clone(), process_manager.clone())
+                    .with_bootstrap(bootstrap_service.clone())
+                    .with_usage(usage_service.clone())
+                    .with_profiles(profile_service.clone())
+                    .with_idle_shutdown(idle_shutdown_service.clone()),
+            );
+            match agent_service.restore_observer_mode() {
+                Ok(true) => tracing::info!("Observer mode is on; agent terminals are read-only"),
+                Ok(false) => {}
+                Err(e) => tracing::warn!("Failed to load observer mode: {}", e),
+            }
             let workspace_service = Arc::new(services::WorkspaceService::new(pool.clone()));
-            let worktree_service = Arc::new(services::WorktreeService::new(pool.clone()));
-            let usage_service = Arc::new(services::UsageService::new(pool.clone()));
+            let git_watch_service = Arc::new(services::GitWatchService::new(pool.clone()));
+            let worktree_service = Arc::new(
+                services::WorktreeService::new(pool.clone())
+                    .with_bootstrap(bootstrap_service.clone())
+                    .with_git_watch(git_watch_service.clone())
+                    .with_process_manager(process_manager.clone())
+                    .with_settings_sync(settings_sync_service.clone()),
+            );
+            let backup_service = Arc::new(services::BackupService::new(
+                pool.clone(),
+                data_dir.clone(),
+                process_manager.clone(),
+            ));
+            let remote_access_service = Arc::new(services::RemoteAccessService::new(
+                pool.clone(),
+                data_dir.clone(),
+            ));
 
-            // Create DB sync repo before pool moves into app state
-            let db_sync_repo = db::repositories::AgentRepository::new(pool.clone());
+            let pull_request_service = Arc::new(services::PullRequestService::new(pool.clone()));
+            let task_group_service = Arc::new(services::TaskGroupService::new(
+                pool.clone(),
+                worktree_service.clone(),
+                agent_service.clone(),
+            ));
+            let snippet_service = Arc::new(services::SnippetService::new(
+                pool.clone(),
+                process_manager.clone(),
+            ));
+            let snapshot_service = Arc::new(services::SessionSnapshotService::new(
+                pool.clone(),
+                agent_service.clone(),
+                process_manager.clone(),
+            ));
+            let message_stream_service =
+                services::MessageStreamService::new(pool.clone(), process_manager.clone());
+            match message_stream_service.complete_interrupted() {
+                Ok(0) => {}
+                Ok(count) => tracing::info!("Completed {} interrupted messages", count),
+                Err(e) => tracing::warn!("Failed to complete interrupted messages: {}", e),
+            }
+            let mcp_service = Arc::new(services::McpService::new(pool.clone()));
+            let claude_md_service = Arc::new(services::ClaudeMdService::new(pool.clone()));
+            let onboarding_service = Arc::new(services::OnboardingService::new(
+                pool.clone(),
+                environment_service.clone(),
+            ));
+            let change_feed = Arc::new(services::ChangeFeedService::new());
+            let job_service = Arc::new(services::JobService::new(pool.clone()));
+            match job_service.fail_interrupted() {
+                Ok(0) => {}
+                Ok(count) => tracing::info!("Marked {} interrupted jobs as failed", count),
+                Err(e) => tracing::warn!("Failed to clean up interrupted jobs: {}", e),
+            }
+            let schedule_service = Arc::new(services::ScheduleService::new(
+                pool.clone(),
+                agent_service.clone(),
+                change_feed.clone(),
+            ));
+            let authorization_service = Arc::new(services::AuthorizationService::new(
+                pool.clone(),
+                process_manager.clone(),
+            ));
+            let workspace_refresh_service = Arc::new(
+                services::WorkspaceRefreshService::new(workspace_service.clone())
+                    .with_git_watch(git_watch_service.clone())
+                    .with_change_feed(change_feed.clone()),
+            );
+            match git_watch_service.sync() {
+                Ok(count) => tracing::info!("Watching {} worktrees for git changes", count),
+                Err(e) => tracing::warn!("Failed to watch worktrees: {}", e),
+            }
 
             // Create app state
             let app_state = AppState {
-                pool,
+                pool: pool.clone(),
                 process_manager: process_manager.clone(),
-                agent_service,
-                workspace_service,
-                worktree_service,
-                usage_service,
+                agent_service: agent_service.clone(),
+                workspace_service: workspace_service.clone(),
+                workspace_refresh_service: workspace_refresh_service.clone(),
+                worktree_service: worktree_service.clone(),
+                bootstrap_service: bootstrap_service.clone(),
+                usage_service: usage_service.clone(),
+                profile_service,
+                idle_shutdown_service: idle_shutdown_service.clone(),
+                resource_monitor_service: resource_monitor_service.clone(),
+                backup_service,
+                remote_access_service: remote_access_service.clone(),
+                pull_request_service,
+                git_watch_service: git_watch_service.clone(),
+                task_group_service,
+                snippet_service,
+                snapshot_service: snapshot_service.clone(),
+                schedule_service: schedule_service.clone(),
+                settings_sync_service: settings_sync_service.clone(),
+                mcp_service,
+                claude_md_service,
+                environment_service: environment_service.clone(),
+                claude_login_service,
+                network_status_service: network_status_service.clone(),
+                onboarding_service,
+                log_service: log_service.clone(),
+                job_service: job_service.clone(),
+                change_feed: change_feed.clone(),
+                authorization_service: authorization_service.clone(),
             };
 
             // Store in app state
             app.manage(app_state);
 
             // Start WebSocket server in background
+            let api_token = services::load_or_create_api_token(&data_dir)
+                .expect("Failed to load API token");
             let ws_rx = process_manager.subscribe();
-            let ws_pm = process_manager.clone();
+            let ws_context = services::ServerContext {
+                pool: pool.clone(),
+                process_manager: process_manager.clone(),
+                agent_service: agent_service.clone(),
+                workspace_service: workspace_service.clone(),
+                worktree_service: worktree_service.clone(),
+                bootstrap_service,
+                usage_service: usage_service.clone(),
+                authorization_service,
+                git_status_rx: git_watch_service.subscribe(),
+                workspace_refresh_rx: workspace_refresh_service.subscribe(),
+                job_rx: job_service.subscribe(),
+                entity_change_rx: change_feed.subscribe(),
+                network_status_rx: network_status_service.subscribe(),
+                resources_rx: resource_monitor_service.subscribe(),
+                api_token,
+                remote: remote_access_service.server_config().unwrap_or_else(|e| {
+                    tracing::error!("Remote access disabled: {}", e);
+                    None
+                }),
+            };
             tauri::async_runtime::spawn(async move {
-                if let Err(e) = services::start_websocket_server(ws_rx, ws_pm).await {
+                if let Err(e) = services::start_websocket_server(ws_rx, ws_context).await {
                     tracing::error!("WebSocket server error: {}", e);
                 }
             });
 
             // Sync process events to database status
             let db_sync_rx = process_manager.subscribe();
+            let status_sync = status_sync
+                .with_process_manager(process_manager.clone())
+                .with_settings_sync(settings_sync_service);
+            tauri::async_runtime::spawn(async move {
+                status_sync.run(db_sync_rx).await;
+            });
+
+            // Assemble agent output into streaming assistant messages
+            let message_stream_rx = process_manager.subscribe();
+            tauri::async_runtime::spawn(async move {
+                message_stream_service.run(message_stream_rx).await;
+            });
+
+            // Stop agents left idle past their idle timeout
+            let idle_shutdown_rx = process_manager.subscribe();
             tauri::async_runtime::spawn(async move {
-                let mut rx = db_sync_rx;
-                while let Ok(event) = rx.recv().await {
-                    match event {
-                        services::ProcessEvent::Exit { ref agent_id, .. } => {
-                            if let Err(e) = db_sync_repo.update_status(
-                                agent_id,
-                                claude_manager_lib::types::AgentStatus::Idle,
-                                None,
-                            ) {
-                                tracing::warn!("Failed to sync exit status for {}: {}", agent_id, e);
-                            }
-                        }
-                        services::ProcessEvent::Status {
-                            ref agent_id,
-                            ref status,
-                            ..
-                        } => {
-                            if let Err(e) = db_sync_repo.update_status(agent_id, status.clone(), None) {
-                                tracing::warn!(
-                                    "Failed to sync status for {}: {}",
-                                    agent_id,
-                                    e
-                                );
-                            }
-                        }
-                        _ => {}
+                idle_shutdown_service.run(idle_shutdown_rx).await;
+            });
+
+            // Sample the memory and CPU of agent processes
+            tauri::async_runtime::spawn(async move {
+                resource_monitor_service.run().await;
+            });
+
+            // Log Claude CLI problems; the UI shows them via check_claude_cli
+            tauri::async_runtime::spawn(async move {
+                let status = environment_service.check_claude_cli().await;
+                for diagnostic in &status.diagnostics {
+                    tracing::warn!("{}", diagnostic.message);
+                }
+            });
+
+            // Notice when the network goes away, so remote calls are skipped
+            tauri::async_runtime::spawn(async move {
+                network_status_service.run().await;
+            });
+
+            // Periodically snapshot running agent sessions
+            tauri::async_runtime::spawn(async move {
+                snapshot_service.run().await;
+            });
+
+            // Check usage against the budget, and raise a desktop notification
+            // for each threshold crossed
+            let mut budget_alert_rx = usage_service.subscribe();
+            let app_handle = app.handle().clone();
+            tauri::async_runtime::spawn(async move {
+                loop {
+                    let alert = match budget_alert_rx.recv().await {
+                        Ok(alert) => alert,
+                        Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                        Err(broadcast::error::RecvError::Closed) => break,
+                    };
+                    let title = match alert.level {
+                        types::BudgetLevel::Exceeded => "Usage budget exceeded",
+                        _ => "Usage budget warning",
+                    };
+                    if let Err(e) = app_handle
+                        .notification()
+                        .builder()
+                        .title(title)
+                        .body(&alert.message)
+                        .show()
+                    {
+                        tracing::warn!("Failed to show budget notification: {}", e);
                     }
                 }
             });
+            tauri::async_runtime::spawn(async move {
+                usage_service.run().await;
+            });
+
+            // Start agents for due schedules
+            tauri::async_runtime::spawn(async move {
+                schedule_service.run().await;
+            });
 
             tracing::info!("Claude Manager setup complete");
             Ok(())
@@ -137,33 +379,164 @@ fn main() {
             commands::create_workspace,
             commands::delete_workspace,
             commands::refresh_workspace,
+            commands::get_workspace_refresh_job,
+            commands::list_orphaned_agents,
+            commands::get_job,
+            commands::cancel_job,
+            commands::relink_workspace,
             // Worktree commands
             commands::list_worktrees,
             commands::get_worktree,
             commands::create_worktree,
             commands::update_worktree,
             commands::delete_worktree,
+            commands::archive_worktree,
+            commands::restore_worktree,
+            commands::set_worktree_pinned,
+            commands::get_worktree_health,
+            commands::list_archived_worktrees,
             commands::checkout_branch,
+            commands::get_commit_log,
+            commands::stash_save,
+            commands::stash_list,
+            commands::stash_pop,
             commands::reorder_worktrees,
             commands::get_git_status,
             commands::list_branches,
+            commands::get_sparse_checkout,
+            commands::set_sparse_checkout,
+            commands::get_base_branch,
+            commands::set_base_branch,
+            commands::get_worktree_sort_mode,
+            commands::set_worktree_sort_mode,
+            commands::git_fetch,
+            commands::create_pull_request,
+            commands::detect_conflicts,
+            commands::get_branch_template,
+            commands::set_branch_template,
+            commands::suggest_branch_name,
+            commands::get_shared_files,
+            commands::set_shared_files,
+            commands::preview_shared_files,
+            commands::get_bootstrap_config,
+            commands::set_bootstrap_config,
+            commands::get_worktree_bootstrap,
+            commands::run_worktree_bootstrap,
             // Agent commands
             commands::list_agents,
+            commands::list_agents_by_tag,
+            commands::set_agent_tags,
             commands::get_agent,
+            commands::get_agent_terminal_text,
+            commands::send_message,
+            commands::get_agent_runs,
+            commands::get_agent_runtime,
+            commands::get_agent_activity_summary,
+            commands::get_agent_messages,
+            commands::import_session_history,
+            commands::discover_sessions,
+            commands::adopt_session,
+            commands::get_output_redaction_patterns,
+            commands::set_output_redaction_patterns,
+            commands::get_agent_output_redaction,
+            commands::set_agent_output_redaction,
+            commands::get_draft,
+            commands::save_draft,
             commands::create_agent,
             commands::update_agent,
             commands::delete_agent,
             commands::start_agent,
+            commands::retry_agent,
             commands::stop_agent,
+            commands::stop_workspace_agents,
+            commands::stop_worktree_agents,
+            commands::get_workspace_agent_limit,
+            commands::set_workspace_agent_limit,
             commands::fork_agent,
             commands::restore_agent,
             commands::reorder_agents,
+            commands::move_agent,
+            // Task group commands
+            commands::fan_out_task,
+            commands::get_task_group,
+            commands::compare_task_group,
+            commands::resolve_task_group,
+            commands::list_snippets,
+            commands::create_snippet,
+            commands::update_snippet,
+            commands::delete_snippet,
+            commands::send_snippet_to_agent,
+            commands::snapshot_agent_session,
+            commands::list_agent_snapshots,
+            commands::restore_agent_from_snapshot,
+            // Schedule commands
+            commands::list_schedules,
+            commands::create_schedule,
+            commands::set_schedule_paused,
+            commands::delete_schedule,
+            commands::get_scheduler_status,
+            commands::set_quiet_hours,
+            commands::set_quiet_hours_override,
+            // Settings sync commands
+            commands::get_workspace_hooks,
+            commands::set_workspace_hooks,
+            commands::remove_manager_hooks,
+            commands::list_mcp_servers,
+            commands::set_mcp_servers,
+            commands::check_mcp_servers,
+            commands::get_claude_md,
+            commands::update_claude_md,
+            commands::check_claude_cli,
+            commands::check_claude_auth,
+            commands::start_claude_login,
+            commands::get_claude_login,
+            commands::send_claude_login_input,
+            commands::cancel_claude_login,
+            commands::get_network_status,
+            commands::check_network_status,
+            commands::get_onboarding_state,
+            commands::complete_onboarding_step,
+            commands::get_app_logs,
             // Usage commands
             commands::get_usage,
             commands::get_usage_history,
             commands::get_usage_today,
             commands::get_usage_limits,
             commands::get_claude_usage,
+            commands::get_budget_status,
+            commands::set_budget_thresholds,
+            commands::get_usage_enforcement,
+            commands::set_usage_enforcement,
+            // Claude profile commands
+            commands::list_claude_profiles,
+            commands::save_claude_profile,
+            commands::delete_claude_profile,
+            commands::get_workspace_claude_profile,
+            commands::set_workspace_claude_profile,
+            commands::get_agent_claude_profile,
+            commands::set_agent_claude_profile,
+            // Idle shutdown commands
+            commands::get_idle_shutdown_policy,
+            commands::set_idle_shutdown_policy,
+            commands::get_agent_idle_shutdown_policy,
+            commands::set_agent_idle_shutdown_policy,
+            commands::list_idle_shutdowns,
+            // Resource monitor commands
+            commands::get_agent_resources,
+            commands::get_resource_limits,
+            commands::set_resource_limits,
+            // Backup commands
+            commands::backup_app_database,
+            commands::restore_app_database,
+            commands::run_legacy_migration,
+            commands::verify_legacy_migration,
+            // Settings commands
+            commands::get_remote_access_settings,
+            commands::update_remote_access_settings,
+            commands::set_github_token,
+            commands::get_observer_mode,
+            commands::set_observer_mode,
+            commands::get_audit_log,
         ])
         .on_window_event(|window, event| {
             if let tauri::WindowEvent::CloseRequested { .. } = event {
@@ -171,6 +544,10 @@ fn main() {
                 if let Some(state) = window.try_state::<AppState>() {
                     tracing::info!("Shutting down - stopping all agents");
                     state.process_manager.stop_all();
+                    // The hooks would post to a port nothing listens on
+                    if let Err(e) = state.settings_sync_service.release_tracked() {
+                        tracing::warn!("Failed to remove hook settings: {}", e);
+                    }
                 }
             }
         })
@@ -1,13 +1,44 @@
 //! Agent service for managing Claude Code agents
 
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::{Duration, SystemTime};
 
 use thiserror::Error;
-use uuid::Uuid;
 
-use crate::db::{AgentRepository, DbPool};
-use crate::services::{ProcessError, ProcessManager};
-use crate::types::{Agent, AgentMode, AgentStatus, Permission, UpdateAgentInput};
+use crate::db::{
+    AgentRepository, AgentRunRepository, DbError, DbPool, DraftRepository, EventJournalRepository,
+    MessageRepository, SettingsRepository, WorkspaceRepository, WorktreeRepository,
+};
+use crate::services::process_service::DB_TIMESTAMP_FORMAT;
+use crate::services::status_sync_service::LOST_EXIT_REASON;
+use crate::services::{
+    BootstrapService, IdleShutdownService, LaunchPrompts, ProcessControl, ProcessError,
+    ProfileService, SessionLaunch, SettingsSyncService, ToolAccess, UsageService,
+};
+use crate::types::{
+    Agent, AgentActivitySummary, AgentMode, AgentRun, AgentRuntimeInfo, AgentStatus,
+    AgentStopResult, ClaudeProfile, ExternalSession, Message, MessageDraft, MessageListResponse,
+    MessageRole, Permission, SessionHistoryImport, StopAgentsResponse, UpdateAgentInput,
+    WorkspaceAgentLimit, Worktree,
+};
+use crate::util::id::new_id;
+use crate::util::redact::{Redactor, DEFAULT_REDACTION_PATTERNS};
+use crate::util::{ansi, transcript};
+
+const MAX_TAG_LENGTH: usize = 50;
+/// Settings key prefix for a workspace's concurrent agent limit
+const MAX_CONCURRENT_AGENTS_KEY: &str = "max_concurrent_agents";
+/// Settings key for the regexes hiding secrets in agent output
+const OUTPUT_REDACTION_PATTERNS_KEY: &str = "output_redaction_patterns";
+/// Settings key prefix for turning output redaction off for one agent
+const OUTPUT_REDACTION_KEY: &str = "output_redaction";
+/// Settings key for read-only observer mode
+const OBSERVER_MODE_KEY: &str = "observer_mode";
+/// Sessions run outside the manager are offered for adoption for this long
+const EXTERNAL_SESSION_WINDOW: Duration = Duration::from_secs(24 * 60 * 60);
+/// An external session written to this recently is probably still running
+const ACTIVE_SESSION_WINDOW: Duration = Duration::from_secs(5 * 60);
 
 #[derive(Error, Debug)]
 pub enum AgentError {
@@ -19,21 +50,99 @@ pub enum AgentError {
     Process(#[from] ProcessError),
     #[error("Validation error: {0}")]
     Validation(String),
+    #[error("Workspace {workspace_id} already has {running} of {limit} agents running")]
+    LimitReached {
+        workspace_id: String,
+        running: usize,
+        limit: usize,
+    },
+    #[error("Worktree {0} is still running its bootstrap commands")]
+    Bootstrapping(String),
+    #[error("Claude {window} usage is at {utilization:.0}%, past the {cutoff:.0}% cutoff for starting agents")]
+    UsageLimitExceeded {
+        window: String,
+        utilization: f64,
+        cutoff: f64,
+    },
+    #[error("No transcript found for session {0}")]
+    TranscriptNotFound(String),
+    #[error("Failed to read transcript: {0}")]
+    Transcript(String),
+    #[error("Agent {id} was changed since it was loaded")]
+    Conflict { id: String, updated_at: String },
 }
 
 pub struct AgentService {
     agent_repo: AgentRepository,
-    process_manager: Arc<ProcessManager>,
+    worktree_repo: WorktreeRepository,
+    workspace_repo: WorkspaceRepository,
+    settings_repo: SettingsRepository,
+    draft_repo: DraftRepository,
+    run_repo: AgentRunRepository,
+    message_repo: MessageRepository,
+    journal: EventJournalRepository,
+    process_manager: Arc<dyn ProcessControl>,
+    settings_sync: SettingsSyncService,
+    bootstrap_service: Option<Arc<BootstrapService>>,
+    usage_service: Option<Arc<UsageService>>,
+    profile_service: Option<Arc<ProfileService>>,
+    idle_shutdown_service: Option<Arc<IdleShutdownService>>,
+    /// Where the Claude CLI keeps session transcripts
+    claude_projects_dir: Option<PathBuf>,
 }
 
 impl AgentService {
-    pub fn new(pool: DbPool, process_manager: Arc<ProcessManager>) -> Self {
+    pub fn new(pool: DbPool, process_manager: Arc<dyn ProcessControl>) -> Self {
         Self {
-            agent_repo: AgentRepository::new(pool),
+            agent_repo: AgentRepository::new(pool.clone()),
+            worktree_repo: WorktreeRepository::new(pool.clone()),
+            workspace_repo: WorkspaceRepository::new(pool.clone()),
+            settings_repo: SettingsRepository::new(pool.clone()),
+            draft_repo: DraftRepository::new(pool.clone()),
+            run_repo: AgentRunRepository::new(pool.clone()),
+            message_repo: MessageRepository::new(pool.clone()),
+            journal: EventJournalRepository::new(pool.clone()),
+            settings_sync: SettingsSyncService::new(pool)
+                .with_process_manager(process_manager.clone()),
             process_manager,
+            bootstrap_service: None,
+            usage_service: None,
+            profile_service: None,
+            idle_shutdown_service: None,
+            claude_projects_dir: dirs::home_dir().map(|h| h.join(".claude").join("projects")),
         }
     }
 
+    /// Hold agent starts while a blocking worktree bootstrap runs
+    pub fn with_bootstrap(mut self, bootstrap_service: Arc<BootstrapService>) -> Self {
+        self.bootstrap_service = Some(bootstrap_service);
+        self
+    }
+
+    /// Refuse agent starts while Claude's usage is past the enforcement cutoff
+    pub fn with_usage(mut self, usage_service: Arc<UsageService>) -> Self {
+        self.usage_service = Some(usage_service);
+        self
+    }
+
+    /// Run agents under the Claude account profile chosen for them
+    pub fn with_profiles(mut self, profile_service: Arc<ProfileService>) -> Self {
+        self.profile_service = Some(profile_service);
+        self
+    }
+
+    /// Stop agents left idle at the prompt as their idle policy says
+    pub fn with_idle_shutdown(mut self, idle_shutdown_service: Arc<IdleShutdownService>) -> Self {
+        self.idle_shutdown_service = Some(idle_shutdown_service);
+        self
+    }
+
+    /// Read session transcripts from a directory other than `~/.claude/projects`
+    pub fn with_claude_projects_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.claude_projects_dir = Some(dir.into());
+        self
+    }
+
     /// Create a new agent
     pub fn create_agent(
         &self,
@@ -47,11 +156,7 @@ impl AgentService {
 
         let now = chrono::Utc::now().to_rfc3339();
         let agent = Agent {
-            id: format!(
-                "ag_{}{}",
-                chrono::Utc::now().timestamp_millis(),
-                &Uuid::new_v4().to_string()[..8]
-            ),
+            id: new_id("ag"),
             worktree_id: worktree_id.to_string(),
             name: agent_name,
             status: AgentStatus::Idle,
@@ -65,8 +170,17 @@ impl AgentService {
             updated_at: now,
             started_at: None,
             stopped_at: None,
+            run_duration_ms: None,
             deleted_at: None,
             parent_agent_id: None,
+            status_reason: None,
+            task_group_id: None,
+            fork_session_id: None,
+            system_prompt: None,
+            append_system_prompt: None,
+            allowed_tools: Vec::new(),
+            disallowed_tools: Vec::new(),
+            tags: Vec::new(),
         };
 
         self.agent_repo
@@ -82,17 +196,142 @@ impl AgentService {
             .ok_or_else(|| AgentError::NotFound(id.to_string()))
     }
 
-    /// List agents for a worktree
+    /// The last `lines` lines of an agent's terminal as plain text
+    pub fn get_terminal_text(&self, id: &str, lines: usize) -> Result<String, AgentError> {
+        self.get_agent(id)?;
+        let buffer = self.process_manager.get_pty_buffer(id).unwrap_or_default();
+        Ok(ansi::tail_lines(&buffer, lines).join("\n"))
+    }
+
+    /// Count active agents per persisted status
+    pub fn count_by_status(&self) -> Result<Vec<(AgentStatus, i64)>, AgentError> {
+        self.agent_repo
+            .count_by_status()
+            .map_err(|e| AgentError::Database(e.to_string()))
+    }
+
+    /// List agents for a worktree, keeping only agents that carry every tag in `tags`
     pub fn list_agents(
         &self,
         worktree_id: &str,
         include_deleted: bool,
+        tags: &[String],
     ) -> Result<Vec<Agent>, AgentError> {
-        self.agent_repo
+        let agents = self
+            .agent_repo
             .find_by_worktree_id(worktree_id, include_deleted)
+            .map_err(|e| AgentError::Database(e.to_string()))?;
+
+        agents
+            .into_iter()
+            .filter(|agent| tags.iter().all(|tag| agent.tags.contains(tag)))
+            .map(|agent| self.reconcile_status(agent))
+            .collect()
+    }
+
+    /// Mark an agent stopped when it is recorded as working but the process
+    /// manager no longer runs its process, because the exit event was dropped.
+    /// An exit still waiting in the journal is left to the status sync.
+    fn reconcile_status(&self, agent: Agent) -> Result<Agent, AgentError> {
+        if !matches!(agent.status, AgentStatus::Running | AgentStatus::Waiting)
+            || self.process_manager.is_running(&agent.id)
+        {
+            return Ok(agent);
+        }
+        if self
+            .journal
+            .has_unprocessed_exit(&agent.id)
+            .map_err(|e| AgentError::Database(e.to_string()))?
+        {
+            return Ok(agent);
+        }
+
+        tracing::warn!(
+            "Agent {} is recorded as {:?} but its process is gone; marking it stopped",
+            agent.id,
+            agent.status
+        );
+        let at = chrono::Utc::now().format(DB_TIMESTAMP_FORMAT).to_string();
+        self.run_repo
+            .finish_latest(&agent.id, None, None, false, &at)
+            .and_then(|_| {
+                self.agent_repo.record_stop(
+                    &agent.id,
+                    AgentStatus::Error,
+                    Some(LOST_EXIT_REASON),
+                    &at,
+                )
+            })
+            .map_err(|e| AgentError::Database(e.to_string()))?;
+        self.get_agent(&agent.id)
+    }
+
+    /// List agents carrying a tag across all worktrees
+    pub fn list_agents_by_tag(
+        &self,
+        tag: &str,
+        include_deleted: bool,
+    ) -> Result<Vec<Agent>, AgentError> {
+        self.agent_repo
+            .find_by_tag(tag.trim(), include_deleted)
             .map_err(|e| AgentError::Database(e.to_string()))
     }
 
+    /// Replace an agent's tags. Tags are trimmed, deduplicated and sorted.
+    pub fn set_agent_tags(&self, id: &str, tags: &[String]) -> Result<Agent, AgentError> {
+        self.get_agent(id)?;
+
+        let mut tags: Vec<String> = tags
+            .iter()
+            .map(|tag| tag.trim().to_string())
+            .filter(|tag| !tag.is_empty())
+            .collect();
+        tags.sort();
+        tags.dedup();
+
+        if let Some(tag) = tags.iter().find(|tag| tag.chars().count() > MAX_TAG_LENGTH) {
+            return Err(AgentError::Validation(format!(
+                "Tag is longer than {} characters: {}",
+                MAX_TAG_LENGTH, tag
+            )));
+        }
+
+        self.agent_repo
+            .set_tags(id, &tags)
+            .map_err(|e| AgentError::Database(e.to_string()))?;
+
+        self.get_agent(id)
+    }
+
+    /// Type a message into a running agent's terminal and submit it
+    pub fn send_message(&self, id: &str, message: &str) -> Result<(), AgentError> {
+        self.get_agent(id)?;
+        self.process_manager.send_message(id, message)?;
+        // The draft has been sent
+        self.draft_repo
+            .delete(id)
+            .map_err(|e| AgentError::Database(e.to_string()))
+    }
+
+    /// Get the unsent message saved for an agent
+    pub fn get_draft(&self, agent_id: &str) -> Result<Option<MessageDraft>, AgentError> {
+        self.get_agent(agent_id)?;
+        self.draft_repo
+            .find_by_agent_id(agent_id)
+            .map_err(|e| AgentError::Database(e.to_string()))
+    }
+
+    /// Save the message being composed for an agent; empty content clears it
+    pub fn save_draft(&self, agent_id: &str, content: &str) -> Result<(), AgentError> {
+        self.get_agent(agent_id)?;
+        let result = if content.is_empty() {
+            self.draft_repo.delete(agent_id)
+        } else {
+            self.draft_repo.save(agent_id, content)
+        };
+        result.map_err(|e| AgentError::Database(e.to_string()))
+    }
+
     /// Update an agent
     pub fn update_agent(&self, id: &str, input: UpdateAgentInput) -> Result<Agent, AgentError> {
         let mut agent = self.get_agent(id)?;
@@ -109,12 +348,31 @@ impl AgentService {
         if let Some(display_order) = input.display_order {
             agent.display_order = display_order;
         }
+        if let Some(system_prompt) = input.system_prompt {
+            agent.system_prompt = Some(system_prompt).filter(|p| !p.trim().is_empty());
+        }
+        if let Some(append_system_prompt) = input.append_system_prompt {
+            agent.append_system_prompt =
+                Some(append_system_prompt).filter(|p| !p.trim().is_empty());
+        }
+        if let Some(allowed_tools) = input.allowed_tools {
+            agent.allowed_tools = normalize_tool_patterns(&allowed_tools)?;
+        }
+        if let Some(disallowed_tools) = input.disallowed_tools {
+            agent.disallowed_tools = normalize_tool_patterns(&disallowed_tools)?;
+        }
+        // Don't overwrite what was changed after the caller loaded the agent
+        if let Some(expected_updated_at) = input.expected_updated_at {
+            agent.updated_at = expected_updated_at;
+        }
 
-        agent.updated_at = chrono::Utc::now().to_rfc3339();
-
-        self.agent_repo
-            .update(&agent)
-            .map_err(|e| AgentError::Database(e.to_string()))
+        self.agent_repo.update(&agent).map_err(|e| match e {
+            DbError::Conflict(updated_at) => AgentError::Conflict {
+                id: id.to_string(),
+                updated_at,
+            },
+            e => AgentError::Database(e.to_string()),
+        })
     }
 
     /// Start an agent
@@ -125,18 +383,121 @@ impl AgentService {
         initial_prompt: Option<&str>,
     ) -> Result<Agent, AgentError> {
         let agent = self.get_agent(id)?;
+        let session = match (&agent.session_id, &agent.fork_session_id) {
+            (Some(sid), _) => SessionLaunch::Resume(sid),
+            (None, Some(parent_sid)) => SessionLaunch::Fork(parent_sid),
+            (None, None) => SessionLaunch::New,
+        };
+        self.launch(&agent, worktree_path, initial_prompt, session, None)
+    }
+
+    /// Start a stopped agent again with its last prompt, resuming its session
+    /// when the transcript is still there. The new run is recorded as a retry
+    /// of the previous one.
+    pub fn retry_agent(&self, id: &str, worktree_path: &str) -> Result<Agent, AgentError> {
+        let agent = self.get_agent(id)?;
+        if self.process_manager.is_running(id) {
+            return Err(AgentError::Validation(
+                "Stop the agent before retrying it".to_string(),
+            ));
+        }
+
+        // Bring the messages up to date so the last prompt is the one Claude saw
+        let resumable = match self.import_session_history(id) {
+            Ok(_) => true,
+            Err(AgentError::Validation(_)) | Err(AgentError::TranscriptNotFound(_)) => false,
+            Err(e) => return Err(e),
+        };
+        let prompt = self
+            .message_repo
+            .find_last_by_role(id, MessageRole::User)
+            .map_err(|e| AgentError::Database(e.to_string()))?
+            .ok_or_else(|| AgentError::Validation("Agent has no prompt to retry".to_string()))?;
+        let retry_of = self
+            .run_repo
+            .find_by_agent_id(id, 1)
+            .map_err(|e| AgentError::Database(e.to_string()))?
+            .first()
+            .map(|run| run.id);
+
+        let session = match (&agent.session_id, &agent.fork_session_id) {
+            (Some(sid), _) if resumable => SessionLaunch::Resume(sid),
+            (None, Some(parent_sid)) => SessionLaunch::Fork(parent_sid),
+            _ => SessionLaunch::New,
+        };
+        self.launch(
+            &agent,
+            worktree_path,
+            Some(&prompt.content),
+            session,
+            retry_of,
+        )
+    }
+
+    /// Spawn an agent's process and record the run
+    fn launch(
+        &self,
+        agent: &Agent,
+        worktree_path: &str,
+        initial_prompt: Option<&str>,
+        session: SessionLaunch,
+        retry_of: Option<i64>,
+    ) -> Result<Agent, AgentError> {
+        let id = agent.id.as_str();
+        if self
+            .bootstrap_service
+            .as_ref()
+            .is_some_and(|b| b.blocks_agent_start(&agent.worktree_id))
+        {
+            return Err(AgentError::Bootstrapping(agent.worktree_id.clone()));
+        }
+        if !self.process_manager.is_running(id) {
+            self.check_concurrency_limit(&agent.worktree_id)?;
+            self.check_usage_limit()?;
+        }
+
+        let redactor = if self.get_output_redaction(id)? {
+            Some(Arc::new(self.output_redactor()?))
+        } else {
+            None
+        };
+        self.process_manager.set_output_redactor(id, redactor);
+        self.process_manager.set_account(id, self.agent_profile(agent)?);
+        if let Some(idle_shutdown) = &self.idle_shutdown_service {
+            idle_shutdown
+                .apply(id)
+                .map_err(|e| AgentError::Database(e.to_string()))?;
+        }
+
+        // Hooks give deterministic status detection; without them the idle
+        // heuristic still works
+        if let Err(e) = self
+            .settings_sync
+            .prepare_worktree(&agent.worktree_id, worktree_path)
+        {
+            tracing::warn!("Failed to write hook settings for agent {}: {}", id, e);
+        }
 
         let (pid, session_id) = self.process_manager.spawn_agent(
             id,
             worktree_path,
             agent.mode,
-            &agent.permissions,
-            initial_prompt,
-            agent.session_id.as_deref(),
+            ToolAccess {
+                permissions: &agent.permissions,
+                allowed: &agent.allowed_tools,
+                disallowed: &agent.disallowed_tools,
+            },
+            LaunchPrompts {
+                initial: initial_prompt,
+                system: agent.system_prompt.as_deref(),
+                append_system: agent.append_system_prompt.as_deref(),
+            },
+            session,
         )?;
 
+        let started_at = chrono::Utc::now().format(DB_TIMESTAMP_FORMAT).to_string();
         self.agent_repo
-            .update_status(id, AgentStatus::Running, Some(pid as i32))
+            .record_start(id, pid as i32, &started_at)
             .map_err(|e| AgentError::Database(e.to_string()))?;
 
         // Persist session_id for future resume and hook matching
@@ -144,9 +505,252 @@ impl AgentService {
             .update_session_id(id, &session_id)
             .map_err(|e| AgentError::Database(e.to_string()))?;
 
+        match retry_of {
+            Some(run_id) => self.run_repo.start_retry(id, Some(&session_id), run_id),
+            None => self.run_repo.start(id, Some(&session_id)),
+        }
+        .map_err(|e| AgentError::Database(e.to_string()))?;
+
+        // Running again, so there is no idle shutdown left to resume from
+        if let Some(idle_shutdown) = &self.idle_shutdown_service {
+            if let Err(e) = idle_shutdown.clear_shutdown(id) {
+                tracing::warn!("Failed to clear idle shutdown of {}: {}", id, e);
+            }
+        }
+
         self.get_agent(id)
     }
 
+    /// An agent's process runs with how each ended, most recent first
+    pub fn get_runs(&self, agent_id: &str, limit: usize) -> Result<Vec<AgentRun>, AgentError> {
+        self.get_agent(agent_id)?;
+        self.run_repo
+            .find_by_agent_id(agent_id, limit)
+            .map_err(|e| AgentError::Database(e.to_string()))
+    }
+
+    /// An agent's process as the process manager sees it, to tell when the
+    /// recorded status has outlived the process
+    pub fn get_runtime(&self, agent_id: &str) -> Result<AgentRuntimeInfo, AgentError> {
+        let agent = self.get_agent(agent_id)?;
+        let process = self.process_manager.snapshot(agent_id);
+        let now = chrono::Utc::now();
+        Ok(AgentRuntimeInfo {
+            agent_id: agent.id,
+            status: agent.status,
+            is_running: process.is_running,
+            stale: !process.is_running
+                && matches!(agent.status, AgentStatus::Running | AgentStatus::Waiting),
+            pid: process.pid,
+            last_output_at: process.last_activity.map(|at| at.to_rfc3339()),
+            last_output_age_ms: process
+                .last_activity
+                .map(|at| (now - at).num_milliseconds().max(0)),
+            buffer_bytes: process.buffer_bytes,
+            subscribers: process.subscribers,
+        })
+    }
+
+    /// How long an agent spent working, waiting on the user and idle across
+    /// its runs
+    pub fn get_activity_summary(&self, agent_id: &str) -> Result<AgentActivitySummary, AgentError> {
+        self.get_agent(agent_id)?;
+        let now = chrono::Utc::now().format(DB_TIMESTAMP_FORMAT).to_string();
+        self.run_repo
+            .activity_summary(agent_id, &now)
+            .map_err(|e| AgentError::Database(e.to_string()))
+    }
+
+    /// A page of an agent's messages, oldest first. Pass the previous page's
+    /// `next_cursor` as `before` to walk back through the conversation.
+    pub fn get_messages(
+        &self,
+        agent_id: &str,
+        limit: usize,
+        before: Option<i64>,
+    ) -> Result<MessageListResponse, AgentError> {
+        self.get_agent(agent_id)?;
+        let (messages, has_more) = self
+            .message_repo
+            .find_page(agent_id, limit, before)
+            .map_err(|e| AgentError::Database(e.to_string()))?;
+
+        let next_cursor = if has_more {
+            messages.first().map(|m| m.seq)
+        } else {
+            None
+        };
+        Ok(MessageListResponse {
+            messages,
+            has_more,
+            next_cursor,
+        })
+    }
+
+    /// Copy the Claude CLI transcript of the agent's session into its
+    /// messages. Messages imported before are skipped, so this can run again
+    /// after the session continues.
+    pub fn import_session_history(
+        &self,
+        agent_id: &str,
+    ) -> Result<SessionHistoryImport, AgentError> {
+        let agent = self.get_agent(agent_id)?;
+        let session_id = agent.session_id.clone().ok_or_else(|| {
+            AgentError::Validation("Agent has no Claude session to import".to_string())
+        })?;
+
+        let path = self
+            .agent_projects_dir(&agent)?
+            .and_then(|dir| transcript::find_transcript(&dir, &session_id))
+            .ok_or_else(|| AgentError::TranscriptNotFound(session_id.clone()))?;
+        let contents =
+            std::fs::read_to_string(&path).map_err(|e| AgentError::Transcript(e.to_string()))?;
+
+        let messages: Vec<Message> = transcript::parse_transcript(&contents)
+            .into_iter()
+            .map(|m| Message {
+                id: m.id,
+                agent_id: agent_id.to_string(),
+                role: m.role,
+                content: m.content,
+                token_count: None,
+                tool_name: m.tool_name,
+                tool_input: m.tool_input,
+                tool_output: None,
+                created_at: m.timestamp.format(DB_TIMESTAMP_FORMAT).to_string(),
+                is_complete: true,
+                seq: 0,
+            })
+            .collect();
+        let imported = self
+            .message_repo
+            .insert_missing(&messages)
+            .map_err(|e| AgentError::Database(e.to_string()))?;
+
+        Ok(SessionHistoryImport {
+            agent_id: agent_id.to_string(),
+            session_id,
+            found: messages.len(),
+            imported,
+        })
+    }
+
+    /// Claude sessions from the last day that ran in a managed worktree but
+    /// belong to no agent, most recently active first
+    pub fn discover_sessions(
+        &self,
+        worktree_id: Option<&str>,
+    ) -> Result<Vec<ExternalSession>, AgentError> {
+        let Some(projects_dir) = self.claude_projects_dir.as_deref() else {
+            return Ok(Vec::new());
+        };
+        let worktrees = match worktree_id {
+            Some(worktree_id) => vec![self.find_worktree(worktree_id)?],
+            None => self
+                .worktree_repo
+                .find_all()
+                .map_err(|e| AgentError::Database(e.to_string()))?,
+        };
+
+        let now = SystemTime::now();
+        let mut sessions = Vec::new();
+        for worktree in worktrees {
+            let project_dir = projects_dir.join(transcript::project_dir_name(&worktree.path));
+            let Ok(entries) = std::fs::read_dir(&project_dir) else {
+                continue;
+            };
+            for entry in entries.filter_map(|entry| entry.ok()) {
+                let path = entry.path();
+                let Some(session_id) = path
+                    .file_name()
+                    .and_then(|name| name.to_str())
+                    .and_then(|name| name.strip_suffix(".jsonl"))
+                else {
+                    continue;
+                };
+                let Ok(modified) = entry.metadata().and_then(|m| m.modified()) else {
+                    continue;
+                };
+                let age = now.duration_since(modified).unwrap_or_default();
+                if age > EXTERNAL_SESSION_WINDOW || self.session_owner(session_id)?.is_some() {
+                    continue;
+                }
+                // Directory names are lossy, so check where the session really ran
+                let Some(cwd) = transcript::read_cwd(&path)
+                    .filter(|cwd| Path::new(cwd) == Path::new(&worktree.path))
+                else {
+                    continue;
+                };
+
+                sessions.push(ExternalSession {
+                    session_id: session_id.to_string(),
+                    worktree_id: worktree.id.clone(),
+                    cwd,
+                    last_activity_at: chrono::DateTime::<chrono::Utc>::from(modified)
+                        .format(DB_TIMESTAMP_FORMAT)
+                        .to_string(),
+                    active: age <= ACTIVE_SESSION_WINDOW,
+                });
+            }
+        }
+
+        sessions.sort_by(|a, b| b.last_activity_at.cmp(&a.last_activity_at));
+        Ok(sessions)
+    }
+
+    /// Create an agent for a session run outside the manager in a worktree,
+    /// with its conversation imported. Starting the agent resumes the session.
+    pub fn adopt_session(&self, worktree_id: &str, session_id: &str) -> Result<Agent, AgentError> {
+        let worktree = self.find_worktree(worktree_id)?;
+        if let Some(agent_id) = self.session_owner(session_id)? {
+            return Err(AgentError::Validation(format!(
+                "Session {} already belongs to agent {}",
+                session_id, agent_id
+            )));
+        }
+        let path = self
+            .claude_projects_dir
+            .as_deref()
+            .and_then(|dir| transcript::find_transcript(dir, session_id))
+            .ok_or_else(|| AgentError::TranscriptNotFound(session_id.to_string()))?;
+        let ran_in_worktree = transcript::read_cwd(&path)
+            .is_some_and(|cwd| Path::new(&cwd) == Path::new(&worktree.path));
+        if !ran_in_worktree {
+            return Err(AgentError::Validation(format!(
+                "Session {} did not run in worktree {}",
+                session_id, worktree.name
+            )));
+        }
+
+        let name = format!("Session {}", session_id.chars().take(8).collect::<String>());
+        let agent = self.create_agent(
+            &worktree.id,
+            Some(name),
+            AgentMode::Regular,
+            vec![Permission::Read],
+        )?;
+        self.agent_repo
+            .update_session_id(&agent.id, session_id)
+            .map_err(|e| AgentError::Database(e.to_string()))?;
+        self.import_session_history(&agent.id)?;
+
+        self.get_agent(&agent.id)
+    }
+
+    /// The agent a session belongs to, whether it was started by the manager or adopted
+    fn session_owner(&self, session_id: &str) -> Result<Option<String>, AgentError> {
+        self.agent_repo
+            .find_id_by_session_id(session_id)
+            .map_err(|e| AgentError::Database(e.to_string()))
+    }
+
+    fn find_worktree(&self, worktree_id: &str) -> Result<Worktree, AgentError> {
+        self.worktree_repo
+            .find_by_id(worktree_id)
+            .map_err(|e| AgentError::Database(e.to_string()))?
+            .ok_or_else(|| AgentError::Validation(format!("Worktree not found: {}", worktree_id)))
+    }
+
     /// Stop an agent
     pub fn stop_agent(&self, id: &str, force: bool) -> Result<Agent, AgentError> {
         self.process_manager.stop_agent(id, force)?;
@@ -157,8 +761,8 @@ impl AgentService {
                 .update_status(id, AgentStatus::Idle, None)
                 .map_err(|e| AgentError::Database(e.to_string()))?;
         }
-        // For graceful stop (SIGINT), the DB status sync task in main.rs
-        // will update when the process actually exits
+        // For graceful stop (SIGINT), StatusSyncService will update
+        // when the process actually exits
 
         self.get_agent(id)
     }
@@ -169,31 +773,144 @@ impl AgentService {
         if self.process_manager.is_running(id) {
             self.process_manager.stop_agent(id, true)?;
         }
+        let worktree_id = self
+            .agent_repo
+            .find_by_id(id)
+            .map_err(|e| AgentError::Database(e.to_string()))?
+            .map(|agent| agent.worktree_id);
 
         if archive {
             self.agent_repo.soft_delete(id)
         } else {
             self.agent_repo.hard_delete(id)
         }
-        .map_err(|e| AgentError::Database(e.to_string()))
+        .map_err(|e| AgentError::Database(e.to_string()))?;
+        if let Some(idle_shutdown) = &self.idle_shutdown_service {
+            if let Err(e) = idle_shutdown.clear_shutdown(id) {
+                tracing::warn!("Failed to clear idle shutdown of {}: {}", id, e);
+            }
+        }
+
+        // Take the manager's hooks out once no agent is left running there
+        if let Some(worktree_id) = worktree_id {
+            if let Err(e) = self.settings_sync.release_if_idle(&worktree_id) {
+                tracing::warn!("Failed to remove hook settings for {}: {}", worktree_id, e);
+            }
+        }
+        Ok(())
     }
 
-    /// Fork an agent
-    pub fn fork_agent(&self, id: &str, name: Option<String>) -> Result<Agent, AgentError> {
+    /// Stop every running agent in a worktree, continuing past failures
+    pub fn stop_worktree_agents(
+        &self,
+        worktree_id: &str,
+        force: bool,
+    ) -> Result<StopAgentsResponse, AgentError> {
+        self.worktree_repo
+            .find_by_id(worktree_id)
+            .map_err(|e| AgentError::Database(e.to_string()))?
+            .ok_or_else(|| {
+                AgentError::Validation(format!("Worktree not found: {}", worktree_id))
+            })?;
+
+        let agents = self
+            .agent_repo
+            .find_by_worktree_id(worktree_id, false)
+            .map_err(|e| AgentError::Database(e.to_string()))?;
+
+        Ok(self.stop_running(agents, force))
+    }
+
+    /// Stop every running agent in all worktrees of a workspace, continuing past failures
+    pub fn stop_workspace_agents(
+        &self,
+        workspace_id: &str,
+        force: bool,
+    ) -> Result<StopAgentsResponse, AgentError> {
+        self.workspace_repo
+            .find_by_id(workspace_id)
+            .map_err(|e| AgentError::Database(e.to_string()))?
+            .ok_or_else(|| {
+                AgentError::Validation(format!("Workspace not found: {}", workspace_id))
+            })?;
+
+        let worktrees = self
+            .worktree_repo
+            .find_by_workspace_id(workspace_id)
+            .map_err(|e| AgentError::Database(e.to_string()))?;
+        let mut agents = Vec::new();
+        for worktree in worktrees {
+            agents.extend(
+                self.agent_repo
+                    .find_by_worktree_id(&worktree.id, false)
+                    .map_err(|e| AgentError::Database(e.to_string()))?,
+            );
+        }
+
+        Ok(self.stop_running(agents, force))
+    }
+
+    fn stop_running(&self, agents: Vec<Agent>, force: bool) -> StopAgentsResponse {
+        let mut response = StopAgentsResponse::default();
+
+        for agent in agents {
+            if !self.process_manager.is_running(&agent.id) {
+                continue;
+            }
+            let error = self.stop_agent(&agent.id, force).err();
+            match &error {
+                Some(e) => {
+                    tracing::warn!("Failed to stop agent {}: {}", agent.id, e);
+                    response.failed += 1;
+                }
+                None => response.stopped += 1,
+            }
+            response.results.push(AgentStopResult {
+                agent_id: agent.id,
+                agent_name: agent.name,
+                stopped: error.is_none(),
+                error: error.map(|e| e.to_string()),
+            });
+        }
+
+        response
+    }
+
+    /// Fork an agent. With `fork_session`, the fork branches the parent's
+    /// Claude session on first start instead of sharing it.
+    pub fn fork_agent(
+        &self,
+        id: &str,
+        name: Option<String>,
+        fork_session: bool,
+    ) -> Result<Agent, AgentError> {
         let parent = self.get_agent(id)?;
         let now = chrono::Utc::now().to_rfc3339();
 
+        let (session_id, fork_session_id) = if fork_session {
+            let parent_sid = parent.session_id.clone().ok_or_else(|| {
+                AgentError::Validation("Agent has no session to fork".to_string())
+            })?;
+            (None, Some(parent_sid))
+        } else {
+            (parent.session_id.clone(), None)
+        };
+
         let forked = Agent {
-            id: format!(
-                "ag_{}{}",
-                chrono::Utc::now().timestamp_millis(),
-                &Uuid::new_v4().to_string()[..8]
-            ),
+            id: new_id("ag"),
             name: name.unwrap_or_else(|| format!("{} (fork)", parent.name)),
             parent_agent_id: Some(parent.id.clone()),
+            status_reason: None,
+            task_group_id: None,
+            fork_session_id,
+            system_prompt: parent.system_prompt,
+            append_system_prompt: parent.append_system_prompt,
+            allowed_tools: parent.allowed_tools,
+            disallowed_tools: parent.disallowed_tools,
+            tags: Vec::new(),
             status: AgentStatus::Idle,
             pid: None,
-            session_id: parent.session_id.clone(),
+            session_id,
             worktree_id: parent.worktree_id,
             context_level: parent.context_level,
             mode: parent.mode,
@@ -203,6 +920,7 @@ impl AgentService {
             updated_at: now,
             started_at: None,
             stopped_at: None,
+            run_duration_ms: None,
             deleted_at: None,
         };
 
@@ -220,6 +938,234 @@ impl AgentService {
         self.get_agent(id)
     }
 
+    /// Get the regexes hiding secrets in agent output; the defaults until set
+    pub fn get_output_redaction_patterns(&self) -> Result<Vec<String>, AgentError> {
+        let patterns = self
+            .settings_repo
+            .get(OUTPUT_REDACTION_PATTERNS_KEY)
+            .map_err(|e| AgentError::Database(e.to_string()))?
+            .and_then(|patterns| serde_json::from_str::<Vec<String>>(&patterns).ok())
+            .filter(|patterns| !patterns.is_empty());
+        Ok(patterns.unwrap_or_else(|| {
+            DEFAULT_REDACTION_PATTERNS
+                .iter()
+                .map(|p| p.to_string())
+                .collect()
+        }))
+    }
+
+    /// Replace the regexes hiding secrets in agent output; an empty list
+    /// restores the defaults. Running agents with redaction on switch over
+    /// immediately.
+    pub fn set_output_redaction_patterns(
+        &self,
+        patterns: Vec<String>,
+    ) -> Result<Vec<String>, AgentError> {
+        let patterns: Vec<String> = patterns
+            .into_iter()
+            .map(|p| p.trim().to_string())
+            .filter(|p| !p.is_empty())
+            .collect();
+        Redactor::new(&patterns)
+            .map_err(|e| AgentError::Validation(format!("Invalid redaction pattern: {}", e)))?;
+
+        let result = if patterns.is_empty() {
+            self.settings_repo.delete(OUTPUT_REDACTION_PATTERNS_KEY)
+        } else {
+            let value = serde_json::to_string(&patterns).unwrap_or_default();
+            self.settings_repo
+                .set(OUTPUT_REDACTION_PATTERNS_KEY, &value, "json")
+        };
+        result.map_err(|e| AgentError::Database(e.to_string()))?;
+
+        self.process_manager
+            .replace_output_redactors(Arc::new(self.output_redactor()?));
+        self.get_output_redaction_patterns()
+    }
+
+    /// Whether secrets are hidden in an agent's output; on unless turned off
+    pub fn get_output_redaction(&self, agent_id: &str) -> Result<bool, AgentError> {
+        self.settings_repo
+            .get_bool(&format!("{}:{}", OUTPUT_REDACTION_KEY, agent_id), true)
+            .map_err(|e| AgentError::Database(e.to_string()))
+    }
+
+    /// Turn output redaction on or off for an agent, including its running process
+    pub fn set_output_redaction(&self, agent_id: &str, enabled: bool) -> Result<bool, AgentError> {
+        self.get_agent(agent_id)?;
+
+        let key = format!("{}:{}", OUTPUT_REDACTION_KEY, agent_id);
+        let (result, redactor) = if enabled {
+            (
+                self.settings_repo.delete(&key),
+                Some(Arc::new(self.output_redactor()?)),
+            )
+        } else {
+            (self.settings_repo.set_bool(&key, false), None)
+        };
+        result.map_err(|e| AgentError::Database(e.to_string()))?;
+        self.process_manager.set_output_redactor(agent_id, redactor);
+
+        Ok(enabled)
+    }
+
+    fn output_redactor(&self) -> Result<Redactor, AgentError> {
+        Redactor::new(&self.get_output_redaction_patterns()?)
+            .map_err(|e| AgentError::Validation(format!("Invalid redaction pattern: {}", e)))
+    }
+
+    /// Whether observer mode is on: agent output streams, but terminals take
+    /// no input and mutating commands are refused
+    pub fn get_observer_mode(&self) -> Result<bool, AgentError> {
+        self.settings_repo
+            .get_bool(OBSERVER_MODE_KEY, false)
+            .map_err(|e| AgentError::Database(e.to_string()))
+    }
+
+    /// Turn observer mode on or off, e.g. while sharing the screen
+    pub fn set_observer_mode(&self, enabled: bool) -> Result<bool, AgentError> {
+        self.settings_repo
+            .set_bool(OBSERVER_MODE_KEY, enabled)
+            .map_err(|e| AgentError::Database(e.to_string()))?;
+        self.process_manager.set_read_only(enabled);
+        Ok(enabled)
+    }
+
+    /// Apply the saved observer mode to the process manager, on startup
+    pub fn restore_observer_mode(&self) -> Result<bool, AgentError> {
+        let enabled = self.get_observer_mode()?;
+        self.process_manager.set_read_only(enabled);
+        Ok(enabled)
+    }
+
+    /// Get a workspace's concurrent agent limit and how many of its agents are running
+    pub fn get_agent_limit(&self, workspace_id: &str) -> Result<WorkspaceAgentLimit, AgentError> {
+        self.workspace_repo
+            .find_by_id(workspace_id)
+            .map_err(|e| AgentError::Database(e.to_string()))?
+            .ok_or_else(|| {
+                AgentError::Validation(format!("Workspace not found: {}", workspace_id))
+            })?;
+
+        Ok(WorkspaceAgentLimit {
+            workspace_id: workspace_id.to_string(),
+            max_concurrent_agents: self.max_concurrent_agents(workspace_id)?,
+            running: self.running_in_workspace(workspace_id)?,
+        })
+    }
+
+    /// Limit how many agents of a workspace may run at once; None or 0 removes the limit.
+    ///
+    /// Agents that are already running are not stopped.
+    pub fn set_agent_limit(
+        &self,
+        workspace_id: &str,
+        max_concurrent_agents: Option<usize>,
+    ) -> Result<WorkspaceAgentLimit, AgentError> {
+        self.get_agent_limit(workspace_id)?;
+
+        let key = format!("{}:{}", MAX_CONCURRENT_AGENTS_KEY, workspace_id);
+        let result = match max_concurrent_agents.filter(|&limit| limit > 0) {
+            Some(limit) => self.settings_repo.set(&key, &limit.to_string(), "number"),
+            None => self.settings_repo.delete(&key),
+        };
+        result.map_err(|e| AgentError::Database(e.to_string()))?;
+
+        self.get_agent_limit(workspace_id)
+    }
+
+    fn max_concurrent_agents(&self, workspace_id: &str) -> Result<Option<usize>, AgentError> {
+        let value = self
+            .settings_repo
+            .get(&format!("{}:{}", MAX_CONCURRENT_AGENTS_KEY, workspace_id))
+            .map_err(|e| AgentError::Database(e.to_string()))?;
+        Ok(value
+            .and_then(|v| v.parse().ok())
+            .filter(|&limit| limit > 0))
+    }
+
+    /// Count agents in the workspace's worktrees that have a live process
+    fn running_in_workspace(&self, workspace_id: &str) -> Result<usize, AgentError> {
+        let worktrees = self
+            .worktree_repo
+            .find_by_workspace_id(workspace_id)
+            .map_err(|e| AgentError::Database(e.to_string()))?;
+
+        let mut running = 0;
+        for worktree in worktrees {
+            let agents = self
+                .agent_repo
+                .find_by_worktree_id(&worktree.id, false)
+                .map_err(|e| AgentError::Database(e.to_string()))?;
+            running += agents
+                .iter()
+                .filter(|agent| self.process_manager.is_running(&agent.id))
+                .count();
+        }
+        Ok(running)
+    }
+
+    fn check_concurrency_limit(&self, worktree_id: &str) -> Result<(), AgentError> {
+        let Some(worktree) = self
+            .worktree_repo
+            .find_by_id(worktree_id)
+            .map_err(|e| AgentError::Database(e.to_string()))?
+        else {
+            return Ok(());
+        };
+        let Some(limit) = self.max_concurrent_agents(&worktree.workspace_id)? else {
+            return Ok(());
+        };
+
+        let running = self.running_in_workspace(&worktree.workspace_id)?;
+        if running >= limit {
+            return Err(AgentError::LimitReached {
+                workspace_id: worktree.workspace_id,
+                running,
+                limit,
+            });
+        }
+        Ok(())
+    }
+
+    fn check_usage_limit(&self) -> Result<(), AgentError> {
+        let Some(usage_service) = &self.usage_service else {
+            return Ok(());
+        };
+        let exceeded = usage_service
+            .exceeded_usage_limit(chrono::Utc::now())
+            .map_err(|e| AgentError::Database(e.to_string()))?;
+        if let Some((window, utilization, cutoff)) = exceeded {
+            return Err(AgentError::UsageLimitExceeded {
+                window,
+                utilization,
+                cutoff,
+            });
+        }
+        Ok(())
+    }
+
+    /// The Claude account profile an agent runs under; None for the default
+    /// account
+    fn agent_profile(&self, agent: &Agent) -> Result<Option<ClaudeProfile>, AgentError> {
+        let Some(profile_service) = &self.profile_service else {
+            return Ok(None);
+        };
+        profile_service
+            .resolve_for_agent(agent)
+            .map_err(|e| AgentError::Database(e.to_string()))
+    }
+
+    /// Where the CLI keeps an agent's transcripts, which is under the config
+    /// directory of the account it runs under
+    fn agent_projects_dir(&self, agent: &Agent) -> Result<Option<PathBuf>, AgentError> {
+        let config_dir = self.agent_profile(agent)?.and_then(|p| p.config_dir);
+        Ok(match config_dir {
+            Some(config_dir) => Some(PathBuf::from(config_dir).join("projects")),
+            None => self.claude_projects_dir.clone(),
+        })
+    }
+
     /// Reorder agents
     pub fn reorder_agents(
         &self,
@@ -230,18 +1176,86 @@ impl AgentService {
             .reorder(worktree_id, agent_ids)
             .map_err(|e| AgentError::Database(e.to_string()))?;
 
-        self.list_agents(worktree_id, false)
+        self.list_agents(worktree_id, false, &[])
+    }
+
+    /// Move a stopped agent to another worktree, appending it to that worktree's list
+    pub fn move_agent(&self, id: &str, target_worktree_id: &str) -> Result<Agent, AgentError> {
+        let agent = self.get_agent(id)?;
+        if agent.worktree_id == target_worktree_id {
+            return Ok(agent);
+        }
+        if self.process_manager.is_running(id) {
+            return Err(AgentError::Validation(
+                "Stop the agent before moving it to another worktree".to_string(),
+            ));
+        }
+        if agent.deleted_at.is_some() {
+            return Err(AgentError::Validation(
+                "Restore the agent before moving it".to_string(),
+            ));
+        }
+
+        let find_worktree = |worktree_id: &str| {
+            self.worktree_repo
+                .find_by_id(worktree_id)
+                .map_err(|e| AgentError::Database(e.to_string()))
+        };
+        let source = find_worktree(&agent.worktree_id)?;
+        let target = find_worktree(target_worktree_id)?.ok_or_else(|| {
+            AgentError::Validation(format!("Worktree not found: {}", target_worktree_id))
+        })?;
+
+        self.agent_repo
+            .move_to_worktree(id, &target.id)
+            .map_err(|e| AgentError::Database(e.to_string()))?;
+
+        let mut workspace_ids = vec![target.workspace_id];
+        if let Some(source) = source {
+            workspace_ids.push(source.workspace_id);
+        }
+        workspace_ids.dedup();
+        for workspace_id in &workspace_ids {
+            self.workspace_repo
+                .update_counts(workspace_id)
+                .map_err(|e| AgentError::Database(e.to_string()))?;
+        }
+
+        self.get_agent(id)
     }
 }
 
+/// Trim and deduplicate tool patterns, keeping their order. A pattern is a
+/// tool name, optionally followed by a specifier in parentheses, e.g.
+/// `Bash(npm test:*)`.
+fn normalize_tool_patterns(patterns: &[String]) -> Result<Vec<String>, AgentError> {
+    let mut normalized: Vec<String> = Vec::new();
+    for pattern in patterns.iter().map(|p| p.trim()).filter(|p| !p.is_empty()) {
+        let name = pattern.split('(').next().unwrap_or_default();
+        let valid_specifier = !pattern.contains('(') || pattern.ends_with(')');
+        if name.is_empty() || name.contains(char::is_whitespace) || !valid_specifier {
+            return Err(AgentError::Validation(format!(
+                "Invalid tool pattern: {}",
+                pattern
+            )));
+        }
+        if !normalized.iter().any(|p| p == pattern) {
+            normalized.push(pattern.to_string());
+        }
+    }
+    Ok(normalized)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::db::DbPool;
+    use crate::services::ProcessManager;
     use crate::types::{SortMode, Workspace, Worktree};
     use r2d2::Pool;
     use r2d2_sqlite::SqliteConnectionManager;
     use std::sync::atomic::{AtomicUsize, Ordering};
+    use uuid::Uuid;
 
     static DB_COUNTER: AtomicUsize = AtomicUsize::new(0);
 
@@ -289,6 +1303,11 @@ mod tests {
             is_main: true,
             created_at: now.clone(),
             updated_at: now,
+            pr_url: None,
+            sparse_patterns: None,
+            archived_at: None,
+            pinned: false,
+            missing_since: None,
         };
 
         let conn = pool.get().unwrap();
@@ -348,62 +1367,244 @@ mod tests {
     }
 
     #[test]
-    fn test_get_agent() {
+    fn test_get_agent() {
+        let pool = create_test_pool();
+        let (_, worktree) = setup_test_data(&pool);
+        let process_manager = Arc::new(ProcessManager::new("claude".to_string()));
+        let service = AgentService::new(pool, process_manager);
+
+        let created = service
+            .create_agent(
+                &worktree.id,
+                Some("Test Agent".to_string()),
+                AgentMode::Regular,
+                vec![Permission::Read],
+            )
+            .unwrap();
+
+        let found = service.get_agent(&created.id).unwrap();
+        assert_eq!(found.id, created.id);
+        assert_eq!(found.name, "Test Agent");
+    }
+
+    #[test]
+    fn test_get_agent_not_found() {
+        let pool = create_test_pool();
+        let process_manager = Arc::new(ProcessManager::new("claude".to_string()));
+        let service = AgentService::new(pool, process_manager);
+
+        let result = service.get_agent("nonexistent");
+        assert!(matches!(result, Err(AgentError::NotFound(_))));
+    }
+
+    #[test]
+    fn test_get_terminal_text_without_output() {
+        let pool = create_test_pool();
+        let (_, worktree) = setup_test_data(&pool);
+        let process_manager = Arc::new(ProcessManager::new("claude".to_string()));
+        let service = AgentService::new(pool, process_manager);
+        let agent = service
+            .create_agent(
+                &worktree.id,
+                None,
+                AgentMode::Regular,
+                vec![Permission::Read],
+            )
+            .unwrap();
+
+        assert_eq!(service.get_terminal_text(&agent.id, 50).unwrap(), "");
+        assert!(matches!(
+            service.get_terminal_text("nonexistent", 50),
+            Err(AgentError::NotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_list_agents() {
+        let pool = create_test_pool();
+        let (_, worktree) = setup_test_data(&pool);
+        let process_manager = Arc::new(ProcessManager::new("claude".to_string()));
+        let service = AgentService::new(pool, process_manager);
+
+        service
+            .create_agent(
+                &worktree.id,
+                Some("Agent 1".to_string()),
+                AgentMode::Regular,
+                vec![Permission::Read],
+            )
+            .unwrap();
+        service
+            .create_agent(
+                &worktree.id,
+                Some("Agent 2".to_string()),
+                AgentMode::Auto,
+                vec![Permission::Read, Permission::Write],
+            )
+            .unwrap();
+
+        let agents = service.list_agents(&worktree.id, false, &[]).unwrap();
+        assert_eq!(agents.len(), 2);
+    }
+
+    #[test]
+    fn test_set_agent_tags_and_filter() {
         let pool = create_test_pool();
         let (_, worktree) = setup_test_data(&pool);
         let process_manager = Arc::new(ProcessManager::new("claude".to_string()));
         let service = AgentService::new(pool, process_manager);
 
-        let created = service
-            .create_agent(
-                &worktree.id,
-                Some("Test Agent".to_string()),
-                AgentMode::Regular,
-                vec![Permission::Read],
+        let agent1 = service
+            .create_agent(&worktree.id, None, AgentMode::Regular, vec![])
+            .unwrap();
+        let agent2 = service
+            .create_agent(&worktree.id, None, AgentMode::Regular, vec![])
+            .unwrap();
+
+        let tagged = service
+            .set_agent_tags(
+                &agent1.id,
+                &[
+                    " ui ".to_string(),
+                    "bugfix".to_string(),
+                    "ui".to_string(),
+                    "".to_string(),
+                ],
             )
             .unwrap();
+        assert_eq!(tagged.tags, vec!["bugfix", "ui"]);
+        service
+            .set_agent_tags(&agent2.id, &["bugfix".to_string()])
+            .unwrap();
 
-        let found = service.get_agent(&created.id).unwrap();
-        assert_eq!(found.id, created.id);
-        assert_eq!(found.name, "Test Agent");
+        let bugfix = service
+            .list_agents(&worktree.id, false, &["bugfix".to_string()])
+            .unwrap();
+        assert_eq!(bugfix.len(), 2);
+        let both = service
+            .list_agents(
+                &worktree.id,
+                false,
+                &["bugfix".to_string(), "ui".to_string()],
+            )
+            .unwrap();
+        assert_eq!(both.len(), 1);
+        assert_eq!(both[0].id, agent1.id);
+
+        assert_eq!(service.list_agents_by_tag("ui", false).unwrap().len(), 1);
+
+        assert!(matches!(
+            service.set_agent_tags(&agent1.id, &["x".repeat(MAX_TAG_LENGTH + 1)]),
+            Err(AgentError::Validation(_))
+        ));
+        assert!(matches!(
+            service.set_agent_tags("nonexistent", &[]),
+            Err(AgentError::NotFound(_))
+        ));
     }
 
     #[test]
-    fn test_get_agent_not_found() {
+    fn test_stop_workspace_and_worktree_agents() {
         let pool = create_test_pool();
+        let (workspace, worktree) = setup_test_data(&pool);
         let process_manager = Arc::new(ProcessManager::new("claude".to_string()));
         let service = AgentService::new(pool, process_manager);
 
-        let result = service.get_agent("nonexistent");
-        assert!(matches!(result, Err(AgentError::NotFound(_))));
+        service
+            .create_agent(&worktree.id, None, AgentMode::Regular, vec![])
+            .unwrap();
+
+        // Agents that are not running are skipped
+        let response = service.stop_workspace_agents(&workspace.id, false).unwrap();
+        assert!(response.results.is_empty());
+        assert_eq!((response.stopped, response.failed), (0, 0));
+        let response = service.stop_worktree_agents(&worktree.id, true).unwrap();
+        assert!(response.results.is_empty());
+
+        assert!(matches!(
+            service.stop_workspace_agents("ws_missing", false),
+            Err(AgentError::Validation(_))
+        ));
+        assert!(matches!(
+            service.stop_worktree_agents("wt_missing", false),
+            Err(AgentError::Validation(_))
+        ));
     }
 
-    #[test]
-    fn test_list_agents() {
+    #[tokio::test]
+    async fn test_start_agent_enforces_workspace_limit() {
         let pool = create_test_pool();
-        let (_, worktree) = setup_test_data(&pool);
-        let process_manager = Arc::new(ProcessManager::new("claude".to_string()));
+        let (workspace, worktree) = setup_test_data(&pool);
+        std::fs::create_dir_all(&worktree.path).unwrap();
+        // Stand-in CLI that ignores its arguments and keeps running
+        let cli = std::path::Path::new(&worktree.path).join("fake-claude");
+        std::fs::write(&cli, "#!/bin/sh\nexec sleep 30\n").unwrap();
+        std::fs::set_permissions(&cli, std::os::unix::fs::PermissionsExt::from_mode(0o755))
+            .unwrap();
+        let process_manager = Arc::new(ProcessManager::new(cli.to_string_lossy().into_owned()));
         let service = AgentService::new(pool, process_manager);
 
+        let limit = service.set_agent_limit(&workspace.id, Some(1)).unwrap();
+        assert_eq!(limit.max_concurrent_agents, Some(1));
+        assert_eq!(limit.running, 0);
+
+        let agent1 = service
+            .create_agent(&worktree.id, None, AgentMode::Regular, vec![])
+            .unwrap();
+        let agent2 = service
+            .create_agent(&worktree.id, None, AgentMode::Regular, vec![])
+            .unwrap();
         service
-            .create_agent(
-                &worktree.id,
-                Some("Agent 1".to_string()),
-                AgentMode::Regular,
-                vec![Permission::Read],
-            )
+            .start_agent(&agent1.id, &worktree.path, None)
             .unwrap();
+
+        match service.start_agent(&agent2.id, &worktree.path, None) {
+            Err(AgentError::LimitReached { running, limit, .. }) => {
+                assert_eq!((running, limit), (1, 1));
+            }
+            other => panic!("Expected LimitReached, got {:?}", other.map(|a| a.id)),
+        }
+
+        let limit = service.set_agent_limit(&workspace.id, None).unwrap();
+        assert_eq!(limit.max_concurrent_agents, None);
+        assert_eq!(limit.running, 1);
         service
-            .create_agent(
-                &worktree.id,
-                Some("Agent 2".to_string()),
-                AgentMode::Auto,
-                vec![Permission::Read, Permission::Write],
+            .start_agent(&agent2.id, &worktree.path, None)
+            .unwrap();
+
+        service.stop_workspace_agents(&workspace.id, true).unwrap();
+        let _ = std::fs::remove_dir_all(&worktree.path);
+    }
+
+    #[test]
+    fn test_start_agent_waits_for_blocking_bootstrap() {
+        let pool = create_test_pool();
+        let (workspace, worktree) = setup_test_data(&pool);
+        std::fs::create_dir_all(&worktree.path).unwrap();
+        let bootstrap_service = Arc::new(BootstrapService::new(pool.clone()));
+        bootstrap_service
+            .set_config(
+                &workspace.id,
+                crate::types::BootstrapConfig {
+                    commands: vec!["sleep 2".to_string()],
+                    block_agent_start: true,
+                },
             )
             .unwrap();
+        let process_manager = Arc::new(ProcessManager::new("claude".to_string()));
+        let service =
+            AgentService::new(pool, process_manager).with_bootstrap(bootstrap_service.clone());
 
-        let agents = service.list_agents(&worktree.id, false).unwrap();
-        assert_eq!(agents.len(), 2);
+        let agent = service
+            .create_agent(&worktree.id, None, AgentMode::Regular, vec![])
+            .unwrap();
+        bootstrap_service.run(&worktree).unwrap();
+
+        match service.start_agent(&agent.id, &worktree.path, None) {
+            Err(AgentError::Bootstrapping(worktree_id)) => assert_eq!(worktree_id, worktree.id),
+            other => panic!("Expected Bootstrapping, got {:?}", other.map(|a| a.id)),
+        }
+        let _ = std::fs::remove_dir_all(&worktree.path);
     }
 
     #[test]
@@ -430,6 +1631,11 @@ mod tests {
                     mode: Some(AgentMode::Auto),
                     permissions: None,
                     display_order: None,
+                    system_prompt: None,
+                    append_system_prompt: None,
+                    allowed_tools: None,
+                    disallowed_tools: None,
+                    expected_updated_at: None,
                 },
             )
             .unwrap();
@@ -457,11 +1663,11 @@ mod tests {
         service.delete_agent(&created.id, true).unwrap();
 
         // Should not appear in normal list
-        let agents = service.list_agents(&worktree.id, false).unwrap();
+        let agents = service.list_agents(&worktree.id, false, &[]).unwrap();
         assert_eq!(agents.len(), 0);
 
         // Should appear with include_deleted
-        let agents = service.list_agents(&worktree.id, true).unwrap();
+        let agents = service.list_agents(&worktree.id, true, &[]).unwrap();
         assert_eq!(agents.len(), 1);
     }
 
@@ -484,10 +1690,36 @@ mod tests {
         service.delete_agent(&created.id, false).unwrap();
 
         // Should not appear even with include_deleted
-        let agents = service.list_agents(&worktree.id, true).unwrap();
+        let agents = service.list_agents(&worktree.id, true, &[]).unwrap();
         assert_eq!(agents.len(), 0);
     }
 
+    #[test]
+    fn test_save_and_clear_draft() {
+        let pool = create_test_pool();
+        let (_, worktree) = setup_test_data(&pool);
+        let process_manager = Arc::new(ProcessManager::new("claude".to_string()));
+        let service = AgentService::new(pool, process_manager);
+        let agent = service
+            .create_agent(&worktree.id, None, AgentMode::Regular, vec![])
+            .unwrap();
+
+        assert!(service.get_draft(&agent.id).unwrap().is_none());
+
+        service.save_draft(&agent.id, "Fix the").unwrap();
+        service.save_draft(&agent.id, "Fix the login bug").unwrap();
+        let draft = service.get_draft(&agent.id).unwrap().unwrap();
+        assert_eq!(draft.content, "Fix the login bug");
+
+        service.save_draft(&agent.id, "").unwrap();
+        assert!(service.get_draft(&agent.id).unwrap().is_none());
+
+        assert!(matches!(
+            service.save_draft("ag_missing", "hello"),
+            Err(AgentError::NotFound(_))
+        ));
+    }
+
     #[test]
     fn test_fork_agent() {
         let pool = create_test_pool();
@@ -504,7 +1736,7 @@ mod tests {
             )
             .unwrap();
 
-        let forked = service.fork_agent(&parent.id, None).unwrap();
+        let forked = service.fork_agent(&parent.id, None, false).unwrap();
 
         assert_eq!(forked.name, "Parent Agent (fork)");
         assert_eq!(forked.mode, AgentMode::Auto);
@@ -512,6 +1744,39 @@ mod tests {
         assert_eq!(forked.parent_agent_id, Some(parent.id));
     }
 
+    #[test]
+    fn test_fork_agent_session() {
+        let pool = create_test_pool();
+        let (_, worktree) = setup_test_data(&pool);
+        let process_manager = Arc::new(ProcessManager::new("claude".to_string()));
+        let service = AgentService::new(pool.clone(), process_manager);
+
+        let parent = service
+            .create_agent(&worktree.id, None, AgentMode::Regular, vec![])
+            .unwrap();
+        assert!(matches!(
+            service.fork_agent(&parent.id, None, true),
+            Err(AgentError::Validation(_))
+        ));
+
+        let repo = AgentRepository::new(pool);
+        repo.update_session_id(&parent.id, "session-1").unwrap();
+
+        let shared = service.fork_agent(&parent.id, None, false).unwrap();
+        assert_eq!(shared.session_id.as_deref(), Some("session-1"));
+        assert!(shared.fork_session_id.is_none());
+
+        let branched = service.fork_agent(&parent.id, None, true).unwrap();
+        assert!(branched.session_id.is_none());
+        assert_eq!(branched.fork_session_id.as_deref(), Some("session-1"));
+
+        // Once the fork has its own session it stops branching from the parent
+        repo.update_session_id(&branched.id, "session-2").unwrap();
+        let branched = service.get_agent(&branched.id).unwrap();
+        assert_eq!(branched.session_id.as_deref(), Some("session-2"));
+        assert!(branched.fork_session_id.is_none());
+    }
+
     #[test]
     fn test_restore_agent() {
         let pool = create_test_pool();
@@ -533,7 +1798,7 @@ mod tests {
 
         assert!(restored.deleted_at.is_none());
 
-        let agents = service.list_agents(&worktree.id, false).unwrap();
+        let agents = service.list_agents(&worktree.id, false, &[]).unwrap();
         assert_eq!(agents.len(), 1);
     }
 
@@ -569,4 +1834,509 @@ mod tests {
         assert_eq!(reordered[0].display_order, 0);
         assert_eq!(reordered[1].display_order, 1);
     }
+
+    #[test]
+    fn test_move_agent() {
+        let pool = create_test_pool();
+        let (workspace, worktree) = setup_test_data(&pool);
+        let target_id = format!("wt_{}", Uuid::new_v4());
+        pool.get()
+            .unwrap()
+            .execute(
+                r#"INSERT INTO worktrees (id, workspace_id, name, branch, path)
+                   VALUES (?, ?, 'feature', 'feature', '/tmp/feature')"#,
+                rusqlite::params![target_id, workspace.id],
+            )
+            .unwrap();
+        let process_manager = Arc::new(ProcessManager::new("claude".to_string()));
+        let service = AgentService::new(pool.clone(), process_manager);
+
+        let create = |worktree_id: &str, name: &str| {
+            service
+                .create_agent(
+                    worktree_id,
+                    Some(name.to_string()),
+                    AgentMode::Regular,
+                    vec![Permission::Read],
+                )
+                .unwrap()
+        };
+        let agent1 = create(&worktree.id, "Agent 1");
+        let agent2 = create(&worktree.id, "Agent 2");
+        service
+            .reorder_agents(&worktree.id, &[agent1.id.clone(), agent2.id.clone()])
+            .unwrap();
+        create(&target_id, "Existing");
+
+        let moved = service.move_agent(&agent1.id, &target_id).unwrap();
+        assert_eq!(moved.worktree_id, target_id);
+        assert_eq!(moved.display_order, 1);
+
+        let source_agents = service.list_agents(&worktree.id, false, &[]).unwrap();
+        assert_eq!(source_agents.len(), 1);
+        assert_eq!(source_agents[0].id, agent2.id);
+        assert_eq!(source_agents[0].display_order, 0);
+
+        let agent_count: i32 = pool
+            .get()
+            .unwrap()
+            .query_row(
+                "SELECT agent_count FROM workspaces WHERE id = ?",
+                [&workspace.id],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(agent_count, 3);
+
+        let result = service.move_agent(&agent2.id, "wt_missing");
+        assert!(matches!(result, Err(AgentError::Validation(_))));
+    }
+
+    #[test]
+    fn test_output_redaction_settings() {
+        let pool = create_test_pool();
+        let (_, worktree) = setup_test_data(&pool);
+        let process_manager = Arc::new(ProcessManager::new("claude".to_string()));
+        let service = AgentService::new(pool, process_manager);
+        let agent = service
+            .create_agent(
+                &worktree.id,
+                None,
+                AgentMode::Regular,
+                vec![Permission::Read],
+            )
+            .unwrap();
+
+        assert_eq!(
+            service.get_output_redaction_patterns().unwrap().len(),
+            DEFAULT_REDACTION_PATTERNS.len()
+        );
+        let patterns = service
+            .set_output_redaction_patterns(vec![" internal-[0-9]+ ".to_string(), String::new()])
+            .unwrap();
+        assert_eq!(patterns, vec!["internal-[0-9]+".to_string()]);
+
+        let result = service.set_output_redaction_patterns(vec!["(unclosed".to_string()]);
+        assert!(matches!(result, Err(AgentError::Validation(_))));
+        assert_eq!(service.get_output_redaction_patterns().unwrap(), patterns);
+
+        service.set_output_redaction_patterns(vec![]).unwrap();
+        assert_eq!(
+            service.get_output_redaction_patterns().unwrap().len(),
+            DEFAULT_REDACTION_PATTERNS.len()
+        );
+
+        assert!(service.get_output_redaction(&agent.id).unwrap());
+        assert!(!service.set_output_redaction(&agent.id, false).unwrap());
+        assert!(!service.get_output_redaction(&agent.id).unwrap());
+        assert!(service.set_output_redaction(&agent.id, true).unwrap());
+        assert!(service.get_output_redaction(&agent.id).unwrap());
+
+        let result = service.set_output_redaction("ag_missing", false);
+        assert!(matches!(result, Err(AgentError::NotFound(_))));
+    }
+
+    #[test]
+    fn test_observer_mode_is_saved_and_applied() {
+        let pool = create_test_pool();
+        let process_manager = Arc::new(ProcessManager::new("claude".to_string()));
+        let service = AgentService::new(pool.clone(), process_manager.clone());
+
+        assert!(!service.get_observer_mode().unwrap());
+        assert!(service.set_observer_mode(true).unwrap());
+        assert!(process_manager.is_read_only());
+
+        // A fresh process manager picks the saved mode up on startup
+        let process_manager = Arc::new(ProcessManager::new("claude".to_string()));
+        let service = AgentService::new(pool, process_manager.clone());
+        assert!(!process_manager.is_read_only());
+        assert!(service.restore_observer_mode().unwrap());
+        assert!(process_manager.is_read_only());
+
+        service.set_observer_mode(false).unwrap();
+        assert!(!process_manager.is_read_only());
+    }
+
+    #[tokio::test]
+    async fn test_retry_agent_resends_last_prompt() {
+        let pool = create_test_pool();
+        let (workspace, worktree) = setup_test_data(&pool);
+        std::fs::create_dir_all(&worktree.path).unwrap();
+        // Stand-in CLI that records its arguments and keeps running
+        let cli = std::path::Path::new(&worktree.path).join("fake-claude");
+        let args_file = std::path::Path::new(&worktree.path).join("args");
+        std::fs::write(
+            &cli,
+            format!(
+                "#!/bin/sh\nprintf '%s\\n' \"$@\" > {}\nexec sleep 30\n",
+                args_file.display()
+            ),
+        )
+        .unwrap();
+        std::fs::set_permissions(&cli, std::os::unix::fs::PermissionsExt::from_mode(0o755))
+            .unwrap();
+
+        let projects = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(projects.path().join("project")).unwrap();
+        std::fs::write(
+            projects.path().join("project").join("sess-1.jsonl"),
+            r#"{"type":"user","uuid":"u1","timestamp":"2026-01-01T12:00:00.000Z","message":{"role":"user","content":"First try"}}
+{"type":"user","uuid":"u2","timestamp":"2026-01-01T12:01:00.000Z","message":{"role":"user","content":"Fix the build"}}
+{"type":"assistant","uuid":"u3","timestamp":"2026-01-01T12:01:05.000Z","message":{"role":"assistant","content":"API Error: overloaded"}}
+"#,
+        )
+        .unwrap();
+
+        let process_manager = Arc::new(ProcessManager::new(cli.to_string_lossy().into_owned()));
+        let service =
+            AgentService::new(pool, process_manager).with_claude_projects_dir(projects.path());
+        let agent = service
+            .create_agent(&worktree.id, None, AgentMode::Regular, vec![])
+            .unwrap();
+
+        let result = service.retry_agent(&agent.id, &worktree.path);
+        assert!(matches!(result, Err(AgentError::Validation(_))));
+
+        service
+            .agent_repo
+            .update_session_id(&agent.id, "sess-1")
+            .unwrap();
+        let failed_run = service.run_repo.start(&agent.id, Some("sess-1")).unwrap();
+        service
+            .run_repo
+            .finish_latest(&agent.id, Some(1), None, false, "2026-01-01 12:01:06.000")
+            .unwrap();
+
+        let retried = service.retry_agent(&agent.id, &worktree.path).unwrap();
+        assert_eq!(retried.status, AgentStatus::Running);
+        let result = service.retry_agent(&agent.id, &worktree.path);
+        assert!(matches!(result, Err(AgentError::Validation(_))));
+
+        let runs = service.get_runs(&agent.id, 10).unwrap();
+        assert_eq!(runs[0].retry_of, Some(failed_run));
+        assert_eq!(runs[0].attempt, 2);
+
+        let mut args = String::new();
+        for _ in 0..50 {
+            args = std::fs::read_to_string(&args_file).unwrap_or_default();
+            if !args.is_empty() {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+        let args: Vec<&str> = args.lines().collect();
+        assert!(args.windows(2).any(|w| w == ["--resume", "sess-1"]));
+        assert_eq!(args.last(), Some(&"Fix the build"));
+
+        service.stop_workspace_agents(&workspace.id, true).unwrap();
+        let _ = std::fs::remove_dir_all(&worktree.path);
+    }
+
+    #[test]
+    fn test_import_session_history() {
+        let pool = create_test_pool();
+        let (_, worktree) = setup_test_data(&pool);
+        let projects = tempfile::tempdir().unwrap();
+        let project = projects.path().join("-tmp-test-workspace");
+        std::fs::create_dir_all(&project).unwrap();
+        std::fs::write(
+            project.join("sess-1.jsonl"),
+            r#"{"type":"user","uuid":"u1","timestamp":"2026-01-01T12:00:00.000Z","message":{"role":"user","content":"Fix the build"}}
+{"type":"assistant","uuid":"u2","timestamp":"2026-01-01T12:00:05.000Z","message":{"role":"assistant","content":[{"type":"text","text":"On it."},{"type":"tool_use","id":"toolu_1","name":"Bash","input":{"command":"cargo build"}}]}}
+"#,
+        )
+        .unwrap();
+
+        let process_manager = Arc::new(ProcessManager::new("claude".to_string()));
+        let service =
+            AgentService::new(pool, process_manager).with_claude_projects_dir(projects.path());
+        let agent = service
+            .create_agent(
+                &worktree.id,
+                None,
+                AgentMode::Regular,
+                vec![Permission::Read],
+            )
+            .unwrap();
+
+        let result = service.import_session_history(&agent.id);
+        assert!(matches!(result, Err(AgentError::Validation(_))));
+
+        service
+            .agent_repo
+            .update_session_id(&agent.id, "sess-missing")
+            .unwrap();
+        let result = service.import_session_history(&agent.id);
+        assert!(matches!(result, Err(AgentError::TranscriptNotFound(_))));
+
+        service
+            .agent_repo
+            .update_session_id(&agent.id, "sess-1")
+            .unwrap();
+        let import = service.import_session_history(&agent.id).unwrap();
+        assert_eq!((import.found, import.imported), (3, 3));
+        let import = service.import_session_history(&agent.id).unwrap();
+        assert_eq!((import.found, import.imported), (3, 0));
+
+        let page = service.get_messages(&agent.id, 2, None).unwrap();
+        assert!(page.has_more);
+        assert_eq!(page.messages[0].content, "On it.");
+        assert_eq!(page.messages[1].tool_name.as_deref(), Some("Bash"));
+        assert_eq!(page.messages[0].created_at, "2026-01-01 12:00:05.000");
+
+        let page = service
+            .get_messages(&agent.id, 2, page.next_cursor)
+            .unwrap();
+        assert!(!page.has_more);
+        assert_eq!(page.next_cursor, None);
+        assert_eq!(page.messages.len(), 1);
+        assert_eq!(page.messages[0].content, "Fix the build");
+    }
+
+    #[test]
+    fn test_discover_and_adopt_external_session() {
+        let pool = create_test_pool();
+        let (_, worktree) = setup_test_data(&pool);
+        let projects = tempfile::tempdir().unwrap();
+        let project = projects
+            .path()
+            .join(transcript::project_dir_name(&worktree.path));
+        std::fs::create_dir_all(&project).unwrap();
+        let line = |uuid: &str, cwd: &str| {
+            format!(
+                r#"{{"type":"user","uuid":"{}","cwd":"{}","timestamp":"2026-01-01T12:00:00.000Z","message":{{"role":"user","content":"Fix the build"}}}}"#,
+                uuid, cwd
+            )
+        };
+        std::fs::write(project.join("sess-ext.jsonl"), line("u1", &worktree.path)).unwrap();
+        // Same directory name, but a different working directory
+        let elsewhere = worktree.path.replace('-', "_");
+        std::fs::write(project.join("sess-other.jsonl"), line("u2", &elsewhere)).unwrap();
+
+        let process_manager = Arc::new(ProcessManager::new("claude".to_string()));
+        let service =
+            AgentService::new(pool, process_manager).with_claude_projects_dir(projects.path());
+
+        let sessions = service.discover_sessions(None).unwrap();
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].session_id, "sess-ext");
+        assert_eq!(sessions[0].worktree_id, worktree.id);
+        assert!(sessions[0].active);
+
+        let result = service.adopt_session(&worktree.id, "sess-other");
+        assert!(matches!(result, Err(AgentError::Validation(_))));
+        let result = service.adopt_session(&worktree.id, "sess-missing");
+        assert!(matches!(result, Err(AgentError::TranscriptNotFound(_))));
+
+        let agent = service.adopt_session(&worktree.id, "sess-ext").unwrap();
+        assert_eq!(agent.session_id.as_deref(), Some("sess-ext"));
+        assert_eq!(agent.worktree_id, worktree.id);
+        let page = service.get_messages(&agent.id, 10, None).unwrap();
+        assert_eq!(page.messages[0].content, "Fix the build");
+
+        assert!(service
+            .discover_sessions(Some(&worktree.id))
+            .unwrap()
+            .is_empty());
+        let result = service.adopt_session(&worktree.id, "sess-ext");
+        assert!(matches!(result, Err(AgentError::Validation(_))));
+    }
+
+    #[test]
+    fn test_start_agent_refused_past_usage_cutoff() {
+        let pool = create_test_pool();
+        let (_, worktree) = setup_test_data(&pool);
+        let usage_service = Arc::new(UsageService::new(pool.clone()));
+        let process_manager = Arc::new(ProcessManager::new("/nonexistent/claude".to_string()));
+        let service = AgentService::new(pool, process_manager).with_usage(usage_service.clone());
+        let agent = service
+            .create_agent(&worktree.id, None, AgentMode::Regular, vec![])
+            .unwrap();
+
+        let entry = |used| crate::types::UsageLimitEntry {
+            used,
+            limit: 100.0,
+            reset_time: String::new(),
+        };
+        usage_service.record_claude_usage(
+            crate::types::ClaudeUsageSummary {
+                daily: entry(92.0),
+                weekly: entry(40.0),
+                sonnet_only: entry(0.0),
+            },
+            chrono::Utc::now(),
+        );
+        usage_service
+            .set_usage_enforcement(crate::types::UsageEnforcement {
+                enabled: true,
+                cutoff: 90.0,
+            })
+            .unwrap();
+
+        match service.start_agent(&agent.id, &worktree.path, None) {
+            Err(AgentError::UsageLimitExceeded {
+                window,
+                utilization,
+                cutoff,
+            }) => {
+                assert_eq!(window, "five-hour");
+                assert_eq!(utilization, 92.0);
+                assert_eq!(cutoff, 90.0);
+            }
+            other => panic!("Expected UsageLimitExceeded, got {:?}", other.map(|a| a.id)),
+        }
+
+        // With enforcement off the start goes ahead, and fails on the missing CLI
+        usage_service
+            .set_usage_enforcement(crate::types::UsageEnforcement {
+                enabled: false,
+                cutoff: 90.0,
+            })
+            .unwrap();
+        assert!(matches!(
+            service.start_agent(&agent.id, &worktree.path, None),
+            Err(AgentError::Process(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_system_prompts_passed_on_next_start() {
+        let pool = create_test_pool();
+        let (_, worktree) = setup_test_data(&pool);
+        std::fs::create_dir_all(&worktree.path).unwrap();
+        // Stand-in CLI that records its arguments and keeps running
+        let cli = std::path::Path::new(&worktree.path).join("fake-claude");
+        let args_file = std::path::Path::new(&worktree.path).join("args");
+        std::fs::write(
+            &cli,
+            format!(
+                "#!/bin/sh\nprintf '%s\\n' \"$@\" > {}\nexec sleep 30\n",
+                args_file.display()
+            ),
+        )
+        .unwrap();
+        std::fs::set_permissions(&cli, std::os::unix::fs::PermissionsExt::from_mode(0o755))
+            .unwrap();
+        let process_manager = Arc::new(ProcessManager::new(cli.to_string_lossy().into_owned()));
+        let service = AgentService::new(pool, process_manager);
+
+        let agent = service
+            .create_agent(&worktree.id, None, AgentMode::Regular, vec![])
+            .unwrap();
+        let agent = service
+            .update_agent(
+                &agent.id,
+                UpdateAgentInput {
+                    name: None,
+                    mode: None,
+                    permissions: None,
+                    display_order: None,
+                    system_prompt: Some("You are a reviewer".to_string()),
+                    append_system_prompt: Some("Only comment, never edit".to_string()),
+                    allowed_tools: None,
+                    disallowed_tools: None,
+                    expected_updated_at: None,
+                },
+            )
+            .unwrap();
+        assert_eq!(agent.system_prompt.as_deref(), Some("You are a reviewer"));
+
+        let fork = service.fork_agent(&agent.id, None, false).unwrap();
+        assert_eq!(
+            fork.append_system_prompt.as_deref(),
+            Some("Only comment, never edit")
+        );
+
+        service
+            .start_agent(&agent.id, &worktree.path, Some("Review the diff"))
+            .unwrap();
+        let mut args = String::new();
+        for _ in 0..50 {
+            args = std::fs::read_to_string(&args_file).unwrap_or_default();
+            if !args.is_empty() {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+        let args: Vec<&str> = args.lines().collect();
+        assert!(args
+            .windows(2)
+            .any(|w| w == ["--system-prompt", "You are a reviewer"]));
+        assert!(args
+            .windows(2)
+            .any(|w| w == ["--append-system-prompt", "Only comment, never edit"]));
+        assert_eq!(args.last(), Some(&"Review the diff"));
+        service.stop_agent(&agent.id, true).unwrap();
+
+        // An empty string clears a prompt
+        let cleared = service
+            .update_agent(
+                &agent.id,
+                UpdateAgentInput {
+                    name: None,
+                    mode: None,
+                    permissions: None,
+                    display_order: None,
+                    system_prompt: Some(String::new()),
+                    append_system_prompt: None,
+                    allowed_tools: None,
+                    disallowed_tools: None,
+                    expected_updated_at: None,
+                },
+            )
+            .unwrap();
+        assert!(cleared.system_prompt.is_none());
+        assert!(cleared.append_system_prompt.is_some());
+        let _ = std::fs::remove_dir_all(&worktree.path);
+    }
+
+    #[test]
+    fn test_update_agent_tool_patterns() {
+        let pool = create_test_pool();
+        let (_, worktree) = setup_test_data(&pool);
+        let process_manager = Arc::new(ProcessManager::new("claude".to_string()));
+        let service = AgentService::new(pool, process_manager);
+        let agent = service
+            .create_agent(
+                &worktree.id,
+                None,
+                AgentMode::Regular,
+                vec![Permission::Read],
+            )
+            .unwrap();
+        let input = |allowed: &[&str], disallowed: &[&str]| UpdateAgentInput {
+            name: None,
+            mode: None,
+            permissions: None,
+            display_order: None,
+            system_prompt: None,
+            append_system_prompt: None,
+            allowed_tools: Some(allowed.iter().map(|p| p.to_string()).collect()),
+            disallowed_tools: Some(disallowed.iter().map(|p| p.to_string()).collect()),
+            expected_updated_at: None,
+        };
+
+        let updated = service
+            .update_agent(
+                &agent.id,
+                input(
+                    &[" Edit ", "mcp__github__create_issue", "Edit", ""],
+                    &["Bash(rm:*)"],
+                ),
+            )
+            .unwrap();
+        assert_eq!(
+            updated.allowed_tools,
+            vec!["Edit", "mcp__github__create_issue"]
+        );
+        assert_eq!(updated.disallowed_tools, vec!["Bash(rm:*)"]);
+        assert_eq!(service.get_agent(&agent.id).unwrap().allowed_tools.len(), 2);
+
+        for invalid in ["Bash(rm:*", "(rm)", "Read File"] {
+            assert!(matches!(
+                service.update_agent(&agent.id, input(&[], &[invalid])),
+                Err(AgentError::Validation(_))
+            ));
+        }
+    }
 }
@@ -0,0 +1,196 @@
+//! Authorization service deciding which callers may run which commands
+//!
+//! Every command declares the capability it needs. Reads are always allowed.
+//! Anything else is checked against the caller and written to the audit log,
+//! whether it was allowed or not.
+
+use std::sync::Arc;
+
+use thiserror::Error;
+
+use crate::db::{AuditRepository, DbPool};
+use crate::services::ProcessControl;
+use crate::types::{AuditEntry, CallerOrigin, Capability};
+
+#[derive(Error, Debug)]
+pub enum AuthorizationError {
+    #[error("Observer mode is on; {0} is not allowed")]
+    ReadOnly(String),
+    #[error("{command} needs {} access, which this caller does not have", .capability.as_str())]
+    Forbidden {
+        command: String,
+        capability: Capability,
+    },
+    #[error("Database error: {0}")]
+    Database(String),
+}
+
+/// Who is running a command
+#[derive(Debug, Clone, Copy)]
+pub struct CallerContext {
+    pub origin: CallerOrigin,
+    /// False for observers and remote clients without control
+    pub allow_control: bool,
+}
+
+impl CallerContext {
+    /// The app's own window
+    pub fn desktop() -> Self {
+        Self {
+            origin: CallerOrigin::Desktop,
+            allow_control: true,
+        }
+    }
+}
+
+pub struct AuthorizationService {
+    audit_repo: AuditRepository,
+    process_manager: Arc<dyn ProcessControl>,
+}
+
+impl AuthorizationService {
+    pub fn new(pool: DbPool, process_manager: Arc<dyn ProcessControl>) -> Self {
+        Self {
+            audit_repo: AuditRepository::new(pool),
+            process_manager,
+        }
+    }
+
+    /// Check that a caller may run a command, auditing every privileged one.
+    ///
+    /// Observer mode refuses agent control and git writes from everyone.
+    /// Settings stay with the desktop window, so it can turn observer mode
+    /// back off.
+    pub fn authorize(
+        &self,
+        caller: &CallerContext,
+        command: &str,
+        capability: Capability,
+        target_id: Option<&str>,
+    ) -> Result<(), AuthorizationError> {
+        if capability == Capability::Read {
+            return Ok(());
+        }
+
+        let result = self.check(caller, command, capability);
+        self.audit_repo
+            .record(
+                command,
+                capability,
+                caller.origin,
+                target_id,
+                result.is_ok(),
+            )
+            .map_err(|e| AuthorizationError::Database(e.to_string()))?;
+        result
+    }
+
+    /// The latest privileged commands, most recent first
+    pub fn get_audit_log(&self, limit: usize) -> Result<Vec<AuditEntry>, AuthorizationError> {
+        self.audit_repo
+            .find_recent(limit)
+            .map_err(|e| AuthorizationError::Database(e.to_string()))
+    }
+
+    fn check(
+        &self,
+        caller: &CallerContext,
+        command: &str,
+        capability: Capability,
+    ) -> Result<(), AuthorizationError> {
+        let forbidden = || AuthorizationError::Forbidden {
+            command: command.to_string(),
+            capability,
+        };
+        if !caller.allow_control {
+            return Err(forbidden());
+        }
+        match capability {
+            Capability::Settings if caller.origin != CallerOrigin::Desktop => Err(forbidden()),
+            Capability::AgentControl | Capability::GitWrite
+                if self.process_manager.is_read_only() =>
+            {
+                Err(AuthorizationError::ReadOnly(command.to_string()))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::ProcessManager;
+
+    fn create_service() -> (AuthorizationService, Arc<ProcessManager>, tempfile::TempDir) {
+        let (pool, dir) = crate::db::test_pool();
+
+        let process_manager = Arc::new(ProcessManager::new("claude".to_string()));
+        let service = AuthorizationService::new(pool, process_manager.clone());
+        (service, process_manager, dir)
+    }
+
+    #[test]
+    fn test_privileged_commands_are_audited() {
+        let (service, _pm, _dir) = create_service();
+        let desktop = CallerContext::desktop();
+
+        service
+            .authorize(&desktop, "list_agents", Capability::Read, None)
+            .unwrap();
+        service
+            .authorize(
+                &desktop,
+                "start_agent",
+                Capability::AgentControl,
+                Some("ag_1"),
+            )
+            .unwrap();
+
+        let log = service.get_audit_log(10).unwrap();
+        assert_eq!(log.len(), 1);
+        assert_eq!(log[0].command, "start_agent");
+        assert_eq!(log[0].origin, CallerOrigin::Desktop);
+        assert_eq!(log[0].target_id.as_deref(), Some("ag_1"));
+        assert!(log[0].allowed);
+    }
+
+    #[test]
+    fn test_remote_and_observer_callers_are_refused() {
+        let (service, pm, _dir) = create_service();
+        let remote = CallerContext {
+            origin: CallerOrigin::Remote,
+            allow_control: true,
+        };
+        let observer = CallerContext {
+            origin: CallerOrigin::Local,
+            allow_control: false,
+        };
+
+        assert!(service
+            .authorize(&remote, "stop_agent", Capability::AgentControl, None)
+            .is_ok());
+        assert!(matches!(
+            service.authorize(&remote, "set_github_token", Capability::Settings, None),
+            Err(AuthorizationError::Forbidden { .. })
+        ));
+        assert!(matches!(
+            service.authorize(&observer, "stop_agent", Capability::AgentControl, None),
+            Err(AuthorizationError::Forbidden { .. })
+        ));
+
+        pm.set_read_only(true);
+        let desktop = CallerContext::desktop();
+        assert!(matches!(
+            service.authorize(&desktop, "git_fetch", Capability::GitWrite, None),
+            Err(AuthorizationError::ReadOnly(_))
+        ));
+        assert!(service
+            .authorize(&desktop, "set_observer_mode", Capability::Settings, None)
+            .is_ok());
+
+        let log = service.get_audit_log(10).unwrap();
+        assert_eq!(log.len(), 5);
+        assert_eq!(log.iter().filter(|entry| !entry.allowed).count(), 3);
+    }
+}
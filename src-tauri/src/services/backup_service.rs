@@ -0,0 +1,361 @@
+//! Backup service for snapshotting and restoring the application database
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use rusqlite::{backup::Backup, Connection, OpenFlags};
+use thiserror::Error;
+
+use crate::db::{
+    migrate_from_nodejs_with_progress, migrations, verify_migration, AgentRepository, DbPool,
+    MigrationError, MIGRATION_STEPS,
+};
+use crate::services::ProcessControl;
+use crate::types::{BackupInfo, LegacyMigrationResult, RestoreResult};
+
+#[derive(Error, Debug)]
+pub enum BackupError {
+    #[error("Backup file not found: {0}")]
+    SourceNotFound(String),
+    #[error("Invalid backup: {0}")]
+    InvalidBackup(String),
+    #[error("Unsupported backup schema version {found} (latest is {supported})")]
+    IncompatibleSchema { found: i64, supported: i64 },
+    #[error("Database error: {0}")]
+    Database(String),
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Migration error: {0}")]
+    Migration(#[from] MigrationError),
+}
+
+pub struct BackupService {
+    pool: DbPool,
+    backup_dir: PathBuf,
+    process_manager: Arc<dyn ProcessControl>,
+}
+
+impl BackupService {
+    pub fn new(pool: DbPool, data_dir: PathBuf, process_manager: Arc<dyn ProcessControl>) -> Self {
+        Self {
+            pool,
+            backup_dir: data_dir.join("backups"),
+            process_manager,
+        }
+    }
+
+    /// Snapshot the live database into the backups directory
+    pub fn create_backup(&self) -> Result<BackupInfo, BackupError> {
+        self.snapshot("backup")
+    }
+
+    /// Restore the live database from a backup file.
+    ///
+    /// All running agents are stopped and the current data is snapshotted first,
+    /// so a bad restore can itself be rolled back.
+    pub fn restore_backup(&self, path: &Path) -> Result<RestoreResult, BackupError> {
+        if !path.exists() {
+            return Err(BackupError::SourceNotFound(path.display().to_string()));
+        }
+
+        let source = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+            .map_err(|e| BackupError::InvalidBackup(e.to_string()))?;
+
+        let integrity: String = source
+            .query_row("PRAGMA quick_check", [], |row| row.get(0))
+            .map_err(|e| BackupError::InvalidBackup(e.to_string()))?;
+        if integrity != "ok" {
+            return Err(BackupError::InvalidBackup(integrity));
+        }
+
+        let schema_version = migrations::current_version(&source)
+            .map_err(|e| BackupError::InvalidBackup(format!("No schema version: {}", e)))?;
+        let supported = migrations::latest_version();
+        if schema_version == 0 || schema_version > supported {
+            return Err(BackupError::IncompatibleSchema {
+                found: schema_version,
+                supported,
+            });
+        }
+
+        // Agents hold PIDs and session state that the restored data knows nothing about
+        let agents_stopped = self.process_manager.get_running_count();
+        self.process_manager.stop_all();
+
+        let pre_restore_backup = self.snapshot("pre-restore")?;
+
+        {
+            let mut conn = self
+                .pool
+                .get()
+                .map_err(|e| BackupError::Database(e.to_string()))?;
+            Backup::new(&source, &mut conn)
+                .and_then(|backup| backup.run_to_completion(256, Duration::ZERO, None))
+                .map_err(|e| BackupError::Database(e.to_string()))?;
+
+            // Bring older backups up to the current schema
+            migrations::run_migrations(&conn).map_err(|e| BackupError::Database(e.to_string()))?;
+        }
+
+        AgentRepository::new(self.pool.clone())
+            .clear_running_pids()
+            .map_err(|e| BackupError::Database(e.to_string()))?;
+
+        tracing::info!(
+            "Restored database from {} (schema v{}), previous data saved to {}",
+            path.display(),
+            schema_version,
+            pre_restore_backup.path
+        );
+
+        Ok(RestoreResult {
+            restored_from: path.display().to_string(),
+            pre_restore_backup,
+            schema_version,
+            agents_stopped,
+        })
+    }
+
+    /// Import the Node.js backend's database into the live one and verify it.
+    ///
+    /// The live data is snapshotted first. A dry run writes nothing and only
+    /// reports what would be imported. `progress` is called with how far along
+    /// the import is (0 to 100) and what it is doing.
+    pub fn migrate_legacy(
+        &self,
+        source_path: &Path,
+        dry_run: bool,
+        mut progress: impl FnMut(u8, &str),
+    ) -> Result<LegacyMigrationResult, BackupError> {
+        if !source_path.exists() {
+            return Err(BackupError::SourceNotFound(
+                source_path.display().to_string(),
+            ));
+        }
+
+        let backup = if dry_run {
+            None
+        } else {
+            progress(0, "Backing up database");
+            Some(self.snapshot("pre-migration")?)
+        };
+
+        let conn = self
+            .pool
+            .get()
+            .map_err(|e| BackupError::Database(e.to_string()))?;
+        let action = if dry_run { "Checking" } else { "Importing" };
+        let stats =
+            migrate_from_nodejs_with_progress(source_path, &conn, dry_run, |done, table| {
+                let percent = 10 + done * 80 / MIGRATION_STEPS;
+                progress(percent as u8, &format!("{} {}", action, table));
+            })?;
+        if dry_run {
+            return Ok(LegacyMigrationResult {
+                source_path: source_path.display().to_string(),
+                backup,
+                stats,
+                warnings: Vec::new(),
+            });
+        }
+
+        progress(90, "Verifying import");
+        let warnings = verify_migration(source_path, &conn)?;
+        for warning in &warnings {
+            tracing::warn!("Legacy migration: {}", warning);
+        }
+
+        Ok(LegacyMigrationResult {
+            source_path: source_path.display().to_string(),
+            backup,
+            stats,
+            warnings,
+        })
+    }
+
+    /// Compare the live database with the Node.js backend's, returning the
+    /// problems found
+    pub fn verify_legacy(&self, source_path: &Path) -> Result<Vec<String>, BackupError> {
+        if !source_path.exists() {
+            return Err(BackupError::SourceNotFound(
+                source_path.display().to_string(),
+            ));
+        }
+
+        let conn = self
+            .pool
+            .get()
+            .map_err(|e| BackupError::Database(e.to_string()))?;
+        Ok(verify_migration(source_path, &conn)?)
+    }
+
+    /// Write a consistent copy of the database using `VACUUM INTO`
+    fn snapshot(&self, label: &str) -> Result<BackupInfo, BackupError> {
+        std::fs::create_dir_all(&self.backup_dir)?;
+
+        let now = chrono::Utc::now();
+        let path = self.backup_dir.join(format!(
+            "claude-manager-{}-{}.db",
+            label,
+            now.format("%Y%m%d_%H%M%S_%3f")
+        ));
+
+        let conn = self
+            .pool
+            .get()
+            .map_err(|e| BackupError::Database(e.to_string()))?;
+        conn.execute("VACUUM INTO ?", [path.to_string_lossy()])
+            .map_err(|e| BackupError::Database(e.to_string()))?;
+        let schema_version =
+            migrations::current_version(&conn).map_err(|e| BackupError::Database(e.to_string()))?;
+
+        Ok(BackupInfo {
+            path: path.display().to_string(),
+            size_bytes: std::fs::metadata(&path)?.len(),
+            schema_version,
+            created_at: now.to_rfc3339(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::ProcessManager;
+    use tempfile::TempDir;
+
+    fn create_test_service() -> (BackupService, DbPool, TempDir) {
+        let (pool, dir) = crate::db::test_pool();
+
+        let pm = Arc::new(ProcessManager::new("echo".to_string()));
+        let service = BackupService::new(pool.clone(), dir.path().to_path_buf(), pm);
+        (service, pool, dir)
+    }
+
+    fn workspace_count(pool: &DbPool) -> i64 {
+        pool.get()
+            .unwrap()
+            .query_row("SELECT COUNT(*) FROM workspaces", [], |row| row.get(0))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_create_backup() {
+        let (service, _pool, _dir) = create_test_service();
+
+        let info = service.create_backup().unwrap();
+
+        assert!(Path::new(&info.path).exists());
+        assert!(info.size_bytes > 0);
+        assert_eq!(info.schema_version, migrations::latest_version());
+    }
+
+    #[test]
+    fn test_restore_backup_rolls_back_data() {
+        let (service, pool, _dir) = create_test_service();
+        let backup = service.create_backup().unwrap();
+
+        pool.get()
+            .unwrap()
+            .execute(
+                "INSERT INTO workspaces (id, name, path) VALUES ('ws_1', 'Later', '/tmp/later')",
+                [],
+            )
+            .unwrap();
+        assert_eq!(workspace_count(&pool), 1);
+
+        let result = service.restore_backup(Path::new(&backup.path)).unwrap();
+
+        assert_eq!(workspace_count(&pool), 0);
+        assert!(Path::new(&result.pre_restore_backup.path).exists());
+        assert_eq!(result.agents_stopped, 0);
+    }
+
+    #[test]
+    fn test_restore_missing_file() {
+        let (service, _pool, dir) = create_test_service();
+
+        let result = service.restore_backup(&dir.path().join("missing.db"));
+        assert!(matches!(result, Err(BackupError::SourceNotFound(_))));
+    }
+
+    #[test]
+    fn test_restore_rejects_unknown_schema() {
+        let (service, _pool, dir) = create_test_service();
+        let foreign = dir.path().join("foreign.db");
+        Connection::open(&foreign)
+            .unwrap()
+            .execute_batch("CREATE TABLE notes (id INTEGER PRIMARY KEY);")
+            .unwrap();
+
+        let result = service.restore_backup(&foreign);
+        assert!(matches!(result, Err(BackupError::InvalidBackup(_))));
+    }
+
+    #[test]
+    fn test_restore_rejects_newer_schema() {
+        let (service, pool, _dir) = create_test_service();
+        let backup = service.create_backup().unwrap();
+        Connection::open(&backup.path)
+            .unwrap()
+            .execute(
+                "INSERT INTO schema_migrations (version, name) VALUES (999, 'from_the_future')",
+                [],
+            )
+            .unwrap();
+
+        let result = service.restore_backup(Path::new(&backup.path));
+        assert!(matches!(
+            result,
+            Err(BackupError::IncompatibleSchema { found: 999, .. })
+        ));
+        drop(pool);
+    }
+
+    #[test]
+    fn test_migrate_legacy_imports_and_verifies() {
+        let (service, pool, dir) = create_test_service();
+        let legacy = dir.path().join("database.db");
+        let conn = Connection::open(&legacy).unwrap();
+        migrations::run_migrations(&conn).unwrap();
+        conn.execute(
+            "INSERT INTO workspaces (id, name, path) VALUES ('ws_1', 'Old', '/tmp/old')",
+            [],
+        )
+        .unwrap();
+
+        let preview = service.migrate_legacy(&legacy, true, |_, _| {}).unwrap();
+        assert!(preview.stats.dry_run && preview.backup.is_none());
+        assert_eq!(preview.stats.workspaces_migrated, 1);
+        assert_eq!(workspace_count(&pool), 0);
+
+        let mut steps = Vec::new();
+        let result = service
+            .migrate_legacy(&legacy, false, |percent, message| {
+                steps.push((percent, message.to_string()))
+            })
+            .unwrap();
+
+        assert_eq!(workspace_count(&pool), 1);
+        assert_eq!(result.stats.workspaces_migrated, 1);
+        assert!(result.warnings.is_empty(), "{:?}", result.warnings);
+        assert!(Path::new(&result.backup.unwrap().path).exists());
+        assert!(steps.windows(2).all(|w| w[0].0 <= w[1].0));
+        assert_eq!(steps.first().unwrap().0, 0);
+        assert!(steps.iter().any(|(_, m)| m == "Importing agents"));
+        assert_eq!(service.verify_legacy(&legacy).unwrap(), result.warnings);
+    }
+
+    #[test]
+    fn test_migrate_legacy_missing_source() {
+        let (service, _pool, dir) = create_test_service();
+        let missing = dir.path().join("missing.db");
+
+        let result = service.migrate_legacy(&missing, false, |_, _| {});
+        assert!(matches!(result, Err(BackupError::SourceNotFound(_))));
+        let result = service.verify_legacy(&missing);
+        assert!(matches!(result, Err(BackupError::SourceNotFound(_))));
+        assert!(!missing.exists());
+    }
+}
@@ -0,0 +1,407 @@
+//! Bootstrap service for preparing new worktrees
+//!
+//! A workspace can list commands such as `npm install` or `cargo build` to
+//! run in every worktree created for it. They run in a PTY so their output
+//! can be streamed to a terminal like an agent's; optionally, agents in the
+//! worktree cannot start until the run finishes.
+
+use std::collections::HashMap;
+use std::io::Read;
+use std::sync::Arc;
+
+use parking_lot::Mutex;
+use portable_pty::{native_pty_system, CommandBuilder, PtySize};
+use thiserror::Error;
+use tokio::sync::broadcast;
+
+use crate::db::{DbPool, SettingsRepository, WorkspaceRepository};
+use crate::types::{BootstrapConfig, BootstrapStatus, Worktree, WorktreeBootstrap};
+use crate::util::ansi;
+
+/// Settings key prefix for a workspace's bootstrap config
+const BOOTSTRAP_CONFIG_KEY: &str = "worktree_bootstrap";
+/// Raw output kept per run for replay
+const OUTPUT_BUFFER_MAX_BYTES: usize = 256 * 1_024;
+/// Lines of plain-text output returned with the run status
+const OUTPUT_TAIL_LINES: usize = 200;
+
+#[derive(Error, Debug)]
+pub enum BootstrapError {
+    #[error("Workspace not found: {0}")]
+    WorkspaceNotFound(String),
+    #[error("Bootstrap already running for worktree {0}")]
+    AlreadyRunning(String),
+    #[error("Failed to start bootstrap: {0}")]
+    SpawnFailed(String),
+    #[error("Database error: {0}")]
+    Database(String),
+}
+
+/// A bootstrap run and its output
+struct BootstrapRun {
+    info: WorktreeBootstrap,
+    output: Vec<u8>,
+    /// Dropped when the run finishes, which ends output streams
+    output_tx: Option<broadcast::Sender<Vec<u8>>>,
+}
+
+pub struct BootstrapService {
+    settings_repo: SettingsRepository,
+    workspace_repo: WorkspaceRepository,
+    runs: Arc<Mutex<HashMap<String, BootstrapRun>>>,
+}
+
+impl BootstrapService {
+    pub fn new(pool: DbPool) -> Self {
+        Self {
+            settings_repo: SettingsRepository::new(pool.clone()),
+            workspace_repo: WorkspaceRepository::new(pool),
+            runs: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Get a workspace's bootstrap commands
+    pub fn get_config(&self, workspace_id: &str) -> Result<BootstrapConfig, BootstrapError> {
+        self.ensure_workspace(workspace_id)?;
+        let value = self
+            .settings_repo
+            .get(&format!("{}:{}", BOOTSTRAP_CONFIG_KEY, workspace_id))
+            .map_err(|e| BootstrapError::Database(e.to_string()))?;
+        Ok(value
+            .and_then(|v| serde_json::from_str(&v).ok())
+            .unwrap_or_default())
+    }
+
+    /// Replace a workspace's bootstrap commands; blank commands are dropped
+    pub fn set_config(
+        &self,
+        workspace_id: &str,
+        config: BootstrapConfig,
+    ) -> Result<BootstrapConfig, BootstrapError> {
+        self.ensure_workspace(workspace_id)?;
+        let config = BootstrapConfig {
+            commands: config
+                .commands
+                .iter()
+                .map(|c| c.trim().to_string())
+                .filter(|c| !c.is_empty())
+                .collect(),
+            block_agent_start: config.block_agent_start,
+        };
+
+        let key = format!("{}:{}", BOOTSTRAP_CONFIG_KEY, workspace_id);
+        let result = if config.commands.is_empty() {
+            self.settings_repo.delete(&key)
+        } else {
+            let value = serde_json::to_string(&config).unwrap_or_default();
+            self.settings_repo.set(&key, &value, "json")
+        };
+        result.map_err(|e| BootstrapError::Database(e.to_string()))?;
+
+        Ok(config)
+    }
+
+    /// Run the workspace's bootstrap commands in a worktree.
+    ///
+    /// Returns None when the workspace has no commands. The run continues in
+    /// the background; poll `get_status` or stream its output.
+    pub fn run(&self, worktree: &Worktree) -> Result<Option<WorktreeBootstrap>, BootstrapError> {
+        let config = self.get_config(&worktree.workspace_id)?;
+        if config.commands.is_empty() {
+            return Ok(None);
+        }
+        if self.is_running(&worktree.id) {
+            return Err(BootstrapError::AlreadyRunning(worktree.id.clone()));
+        }
+
+        let pair = native_pty_system()
+            .openpty(PtySize {
+                rows: 24,
+                cols: 120,
+                pixel_width: 0,
+                pixel_height: 0,
+            })
+            .map_err(|e| BootstrapError::SpawnFailed(e.to_string()))?;
+
+        let mut cmd = shell_command(&config.commands.join(" && "));
+        cmd.cwd(&worktree.path);
+        cmd.env("TERM", "xterm-256color");
+        let mut child = pair
+            .slave
+            .spawn_command(cmd)
+            .map_err(|e| BootstrapError::SpawnFailed(e.to_string()))?;
+        drop(pair.slave);
+        let mut reader = pair
+            .master
+            .try_clone_reader()
+            .map_err(|e| BootstrapError::SpawnFailed(e.to_string()))?;
+
+        let info = WorktreeBootstrap {
+            worktree_id: worktree.id.clone(),
+            status: BootstrapStatus::Running,
+            commands: config.commands,
+            block_agent_start: config.block_agent_start,
+            exit_code: None,
+            output: String::new(),
+            started_at: chrono::Utc::now().to_rfc3339(),
+            finished_at: None,
+        };
+        let (output_tx, _) = broadcast::channel::<Vec<u8>>(1000);
+        self.runs.lock().insert(
+            worktree.id.clone(),
+            BootstrapRun {
+                info: info.clone(),
+                output: Vec::new(),
+                output_tx: Some(output_tx.clone()),
+            },
+        );
+        tracing::info!(
+            "Bootstrapping worktree {} in {}",
+            worktree.id,
+            worktree.path
+        );
+
+        let runs = self.runs.clone();
+        let worktree_id = worktree.id.clone();
+        let master = pair.master;
+        std::thread::spawn(move || {
+            let mut buf = [0u8; 4096];
+            loop {
+                match reader.read(&mut buf) {
+                    Ok(0) | Err(_) => break,
+                    Ok(n) => {
+                        let bytes = buf[..n].to_vec();
+                        if let Some(run) = runs.lock().get_mut(&worktree_id) {
+                            run.output.extend_from_slice(&bytes);
+                            if run.output.len() > OUTPUT_BUFFER_MAX_BYTES {
+                                let excess = run.output.len() - OUTPUT_BUFFER_MAX_BYTES;
+                                run.output.drain(..excess);
+                            }
+                        }
+                        let _ = output_tx.send(bytes);
+                    }
+                }
+            }
+
+            let exit_code = child.wait().ok().map(|status| status.exit_code() as i32);
+            drop(master);
+
+            let mut runs = runs.lock();
+            if let Some(run) = runs.get_mut(&worktree_id) {
+                run.info.status = match exit_code {
+                    Some(0) => BootstrapStatus::Succeeded,
+                    _ => BootstrapStatus::Failed,
+                };
+                run.info.exit_code = exit_code;
+                run.info.finished_at = Some(chrono::Utc::now().to_rfc3339());
+                run.output_tx = None;
+                tracing::info!(
+                    "Bootstrap of worktree {} finished with {:?}",
+                    worktree_id,
+                    exit_code
+                );
+            }
+        });
+
+        Ok(Some(info))
+    }
+
+    /// The latest bootstrap run of a worktree, if any ran since the app started
+    pub fn get_status(&self, worktree_id: &str) -> Option<WorktreeBootstrap> {
+        self.runs
+            .lock()
+            .get(worktree_id)
+            .map(|run| WorktreeBootstrap {
+                output: ansi::tail_lines(&run.output, OUTPUT_TAIL_LINES).join("\n"),
+                ..run.info.clone()
+            })
+    }
+
+    /// Subscribe to a running bootstrap's output, along with what it printed so far
+    pub fn subscribe_output(
+        &self,
+        worktree_id: &str,
+    ) -> Option<(broadcast::Receiver<Vec<u8>>, Vec<u8>)> {
+        let runs = self.runs.lock();
+        let run = runs.get(worktree_id)?;
+        let rx = run.output_tx.as_ref()?.subscribe();
+        Some((rx, run.output.clone()))
+    }
+
+    /// Whether agents in the worktree must wait for a running bootstrap
+    pub fn blocks_agent_start(&self, worktree_id: &str) -> bool {
+        self.runs.lock().get(worktree_id).is_some_and(|run| {
+            run.info.block_agent_start && run.info.status == BootstrapStatus::Running
+        })
+    }
+
+    fn is_running(&self, worktree_id: &str) -> bool {
+        self.runs
+            .lock()
+            .get(worktree_id)
+            .is_some_and(|run| run.info.status == BootstrapStatus::Running)
+    }
+
+    fn ensure_workspace(&self, workspace_id: &str) -> Result<(), BootstrapError> {
+        self.workspace_repo
+            .find_by_id(workspace_id)
+            .map_err(|e| BootstrapError::Database(e.to_string()))?
+            .ok_or_else(|| BootstrapError::WorkspaceNotFound(workspace_id.to_string()))?;
+        Ok(())
+    }
+}
+
+#[cfg(unix)]
+fn shell_command(script: &str) -> CommandBuilder {
+    let mut cmd = CommandBuilder::new("sh");
+    cmd.args(["-c", script]);
+    cmd
+}
+
+#[cfg(not(unix))]
+fn shell_command(script: &str) -> CommandBuilder {
+    let mut cmd = CommandBuilder::new("cmd");
+    cmd.args(["/C", script]);
+    cmd
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn create_test_service() -> (BootstrapService, Worktree, TempDir) {
+        let (pool, dir) = crate::db::test_pool();
+        pool.get()
+            .unwrap()
+            .execute(
+                "INSERT INTO workspaces (id, name, path) VALUES ('ws_1', 'Test', '/tmp/test')",
+                [],
+            )
+            .unwrap();
+
+        let now = chrono::Utc::now().to_rfc3339();
+        let worktree = Worktree {
+            id: "wt_1".to_string(),
+            workspace_id: "ws_1".to_string(),
+            name: "feature".to_string(),
+            branch: "feature".to_string(),
+            path: dir.path().to_string_lossy().to_string(),
+            sort_mode: crate::types::SortMode::Free,
+            display_order: 0,
+            is_main: false,
+            created_at: now.clone(),
+            updated_at: now,
+            pr_url: None,
+            sparse_patterns: None,
+            archived_at: None,
+            pinned: false,
+            missing_since: None,
+        };
+        (BootstrapService::new(pool), worktree, dir)
+    }
+
+    fn wait_until_finished(service: &BootstrapService, worktree_id: &str) -> WorktreeBootstrap {
+        for _ in 0..200 {
+            let status = service.get_status(worktree_id).unwrap();
+            if status.status != BootstrapStatus::Running {
+                return status;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(25));
+        }
+        panic!("Bootstrap did not finish");
+    }
+
+    #[test]
+    fn test_config_round_trip() {
+        let (service, _worktree, _dir) = create_test_service();
+        assert_eq!(
+            service.get_config("ws_1").unwrap(),
+            BootstrapConfig::default()
+        );
+
+        let config = service
+            .set_config(
+                "ws_1",
+                BootstrapConfig {
+                    commands: vec![" npm install ".to_string(), "  ".to_string()],
+                    block_agent_start: true,
+                },
+            )
+            .unwrap();
+        assert_eq!(config.commands, ["npm install"]);
+        assert_eq!(service.get_config("ws_1").unwrap(), config);
+
+        assert!(matches!(
+            service.get_config("ws_missing"),
+            Err(BootstrapError::WorkspaceNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_run_reports_output_and_exit_code() {
+        let (service, worktree, _dir) = create_test_service();
+        assert!(service.run(&worktree).unwrap().is_none());
+
+        service
+            .set_config(
+                "ws_1",
+                BootstrapConfig {
+                    commands: vec![
+                        "echo installed > marker".to_string(),
+                        "cat marker".to_string(),
+                    ],
+                    block_agent_start: false,
+                },
+            )
+            .unwrap();
+        let started = service.run(&worktree).unwrap().unwrap();
+        assert_eq!(started.status, BootstrapStatus::Running);
+
+        let finished = wait_until_finished(&service, &worktree.id);
+        assert_eq!(finished.status, BootstrapStatus::Succeeded);
+        assert_eq!(finished.exit_code, Some(0));
+        assert!(finished.output.contains("installed"));
+        assert!(finished.finished_at.is_some());
+        assert!(service.subscribe_output(&worktree.id).is_none());
+
+        service
+            .set_config(
+                "ws_1",
+                BootstrapConfig {
+                    commands: vec!["exit 3".to_string(), "echo skipped".to_string()],
+                    block_agent_start: false,
+                },
+            )
+            .unwrap();
+        service.run(&worktree).unwrap();
+        let failed = wait_until_finished(&service, &worktree.id);
+        assert_eq!(failed.status, BootstrapStatus::Failed);
+        assert_eq!(failed.exit_code, Some(3));
+        assert!(!failed.output.contains("skipped"));
+    }
+
+    #[test]
+    fn test_blocking_run_holds_agent_start() {
+        let (service, worktree, _dir) = create_test_service();
+        service
+            .set_config(
+                "ws_1",
+                BootstrapConfig {
+                    commands: vec!["sleep 0.3".to_string()],
+                    block_agent_start: true,
+                },
+            )
+            .unwrap();
+
+        service.run(&worktree).unwrap();
+        assert!(service.blocks_agent_start(&worktree.id));
+        assert!(matches!(
+            service.run(&worktree),
+            Err(BootstrapError::AlreadyRunning(_))
+        ));
+
+        wait_until_finished(&service, &worktree.id);
+        assert!(!service.blocks_agent_start(&worktree.id));
+    }
+}
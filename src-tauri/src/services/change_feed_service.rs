@@ -0,0 +1,114 @@
+//! Change feed service for keeping every open window in sync
+//!
+//! Commands that create, update or delete workspaces, worktrees or agents
+//! publish the entity they changed. The WebSocket server forwards each
+//! change to every connected client, so a second window (or a remote
+//! client) sees mutations without refreshing.
+
+use serde::Serialize;
+use tokio::sync::broadcast;
+
+use crate::types::{ChangeAction, EntityKind};
+
+/// A workspace, worktree or agent that was created, updated or deleted
+#[derive(Debug, Clone)]
+pub struct EntityChange {
+    pub entity: EntityKind,
+    pub action: ChangeAction,
+    pub id: String,
+    /// The entity after the change; None for deletions
+    pub data: Option<serde_json::Value>,
+}
+
+pub struct ChangeFeedService {
+    event_tx: broadcast::Sender<EntityChange>,
+}
+
+impl ChangeFeedService {
+    pub fn new() -> Self {
+        let (event_tx, _) = broadcast::channel(256);
+        Self { event_tx }
+    }
+
+    /// Subscribe to entity changes
+    pub fn subscribe(&self) -> broadcast::Receiver<EntityChange> {
+        self.event_tx.subscribe()
+    }
+
+    pub fn created<T: Serialize>(&self, entity: EntityKind, id: &str, data: &T) {
+        self.publish(
+            entity,
+            ChangeAction::Created,
+            id,
+            serde_json::to_value(data).ok(),
+        );
+    }
+
+    pub fn updated<T: Serialize>(&self, entity: EntityKind, id: &str, data: &T) {
+        self.publish(
+            entity,
+            ChangeAction::Updated,
+            id,
+            serde_json::to_value(data).ok(),
+        );
+    }
+
+    pub fn deleted(&self, entity: EntityKind, id: &str) {
+        self.publish(entity, ChangeAction::Deleted, id, None);
+    }
+
+    fn publish(
+        &self,
+        entity: EntityKind,
+        action: ChangeAction,
+        id: &str,
+        data: Option<serde_json::Value>,
+    ) {
+        // No subscribers just means no window is connected yet
+        let _ = self.event_tx.send(EntityChange {
+            entity,
+            action,
+            id: id.to_string(),
+            data,
+        });
+    }
+}
+
+impl Default for ChangeFeedService {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_changes_reach_every_subscriber() {
+        let feed = ChangeFeedService::new();
+        // Publishing without subscribers is not an error
+        feed.deleted(EntityKind::Agent, "ag_0");
+
+        let mut first = feed.subscribe();
+        let mut second = feed.subscribe();
+        feed.updated(
+            EntityKind::Worktree,
+            "wt_1",
+            &serde_json::json!({ "id": "wt_1", "name": "feature" }),
+        );
+        feed.deleted(EntityKind::Agent, "ag_1");
+
+        for rx in [&mut first, &mut second] {
+            let change = rx.recv().await.unwrap();
+            assert_eq!(change.entity, EntityKind::Worktree);
+            assert_eq!(change.action, ChangeAction::Updated);
+            assert_eq!(change.id, "wt_1");
+            assert_eq!(change.data.unwrap()["name"], "feature");
+
+            let change = rx.recv().await.unwrap();
+            assert_eq!(change.action, ChangeAction::Deleted);
+            assert!(change.data.is_none());
+        }
+    }
+}
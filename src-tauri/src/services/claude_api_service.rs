@@ -1,14 +1,28 @@
 //! Claude API service for fetching usage data from Anthropic's API
 
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
 use std::path::PathBuf;
+use std::time::Duration;
+
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use reqwest::StatusCode;
 use thiserror::Error;
 
 use crate::types::{
-    ClaudeApiUsageResponse, ClaudeCredentials, ClaudeUsageSummary, UsageLimitEntry,
+    AuthState, ClaudeApiUsageResponse, ClaudeAuthStatus, ClaudeCredentials, ClaudeOAuthCredentials,
+    ClaudeUsageSummary, UsageLimitEntry,
 };
 
 const CLAUDE_USAGE_API: &str = "https://api.anthropic.com/api/oauth/usage";
 const CLAUDE_CODE_VERSION: &str = "2.1.29";
+/// Requests made for one call before a transient failure is returned
+const MAX_ATTEMPTS: u32 = 3;
+/// Wait before the first retry; it doubles for each one after
+const RETRY_BASE_DELAY: Duration = Duration::from_millis(500);
+/// A Retry-After longer than this is returned to the caller instead of
+/// being waited out
+const MAX_RETRY_WAIT: Duration = Duration::from_secs(10);
 
 #[derive(Error, Debug)]
 pub enum ClaudeApiError {
@@ -16,33 +30,69 @@ pub enum ClaudeApiError {
     CredentialsNotFound(String),
     #[error("Invalid credentials format: {0}")]
     InvalidCredentials(String),
+    #[error("Rate limited by the Claude API{}", retry_after_hint(.retry_after))]
+    RateLimited { retry_after: Option<Duration> },
+    #[error("Claude API rejected the credentials: {0}")]
+    Unauthorized(String),
     #[error("API request failed: {0}")]
     RequestFailed(String),
     #[error("Failed to parse response: {0}")]
     ParseError(String),
 }
 
+fn retry_after_hint(retry_after: &Option<Duration>) -> String {
+    retry_after
+        .map(|d| format!("; retry after {}s", d.as_secs()))
+        .unwrap_or_default()
+}
+
 pub struct ClaudeApiService {
     client: reqwest::Client,
+    usage_url: String,
+    /// Config directory of the account to query; `~/.claude` when unset
+    config_dir: Option<PathBuf>,
 }
 
 impl ClaudeApiService {
     pub fn new() -> Self {
         Self {
             client: reqwest::Client::new(),
+            usage_url: CLAUDE_USAGE_API.to_string(),
+            config_dir: None,
         }
     }
 
+    /// Fetch usage from another endpoint, e.g. a local stand-in
+    pub fn with_usage_url(mut self, url: impl Into<String>) -> Self {
+        self.usage_url = url.into();
+        self
+    }
+
+    /// Query the account whose credentials are in `config_dir`, as set by a
+    /// profile's `CLAUDE_CONFIG_DIR`
+    pub fn with_config_dir(mut self, config_dir: impl Into<PathBuf>) -> Self {
+        self.config_dir = Some(config_dir.into());
+        self
+    }
+
     /// Get the path to Claude credentials file
-    fn credentials_path() -> Result<PathBuf, ClaudeApiError> {
+    pub(crate) fn credentials_path() -> Result<PathBuf, ClaudeApiError> {
         dirs::home_dir()
             .map(|h| h.join(".claude").join(".credentials.json"))
             .ok_or_else(|| ClaudeApiError::CredentialsNotFound("Cannot find home directory".into()))
     }
 
+    /// The credentials file of the account this service queries
+    fn account_credentials_path(&self) -> Result<PathBuf, ClaudeApiError> {
+        match &self.config_dir {
+            Some(dir) => Ok(dir.join(".credentials.json")),
+            None => Self::credentials_path(),
+        }
+    }
+
     /// Read and parse Claude credentials
-    fn read_credentials() -> Result<ClaudeCredentials, ClaudeApiError> {
-        let path = Self::credentials_path()?;
+    fn read_credentials(&self) -> Result<ClaudeCredentials, ClaudeApiError> {
+        let path = self.account_credentials_path()?;
 
         if !path.exists() {
             return Err(ClaudeApiError::CredentialsNotFound(format!(
@@ -61,8 +111,8 @@ impl ClaudeApiService {
     }
 
     /// Get access token from credentials
-    fn get_access_token() -> Result<String, ClaudeApiError> {
-        let creds = Self::read_credentials()?;
+    fn get_access_token(&self) -> Result<String, ClaudeApiError> {
+        let creds = self.read_credentials()?;
 
         creds
             .claude_ai_oauth
@@ -72,13 +122,137 @@ impl ClaudeApiService {
             })
     }
 
-    /// Fetch usage data from Claude API
+    /// Check that the stored login is present, unexpired and accepted by the
+    /// API, which is asked for usage as the cheapest authenticated call
+    pub async fn check_auth(&self) -> ClaudeAuthStatus {
+        let oauth = match self.stored_login() {
+            Ok(oauth) => oauth,
+            Err(status) => return status,
+        };
+
+        match self.usage_request(&oauth.access_token).await {
+            Ok(response) if response.status().is_success() => {
+                auth_status(AuthState::Valid, Some(&oauth), None)
+            }
+            Ok(response)
+                if matches!(
+                    response.status(),
+                    StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN
+                ) =>
+            {
+                auth_status(
+                    AuthState::Rejected,
+                    Some(&oauth),
+                    Some(format!("API returned {}; log in again", response.status())),
+                )
+            }
+            Ok(response) => auth_status(
+                AuthState::Unverified,
+                Some(&oauth),
+                Some(format!("API returned {}", response.status())),
+            ),
+            Err(e) => auth_status(AuthState::Unverified, Some(&oauth), Some(e.to_string())),
+        }
+    }
+
+    /// Check the stored login without asking the API, for when the app is
+    /// offline; a login that looks fine is reported as unverified
+    pub fn check_auth_offline(&self) -> ClaudeAuthStatus {
+        match self.stored_login() {
+            Ok(oauth) => auth_status(
+                AuthState::Unverified,
+                Some(&oauth),
+                Some("Offline; the login is checked with the API once back online".to_string()),
+            ),
+            Err(status) => status,
+        }
+    }
+
+    /// The stored OAuth login, or the status saying why it can't be used
+    fn stored_login(&self) -> Result<ClaudeOAuthCredentials, ClaudeAuthStatus> {
+        let oauth = match self.read_credentials() {
+            Ok(creds) => creds.claude_ai_oauth,
+            Err(ClaudeApiError::CredentialsNotFound(e)) => {
+                return Err(auth_status(AuthState::Missing, None, Some(e)))
+            }
+            Err(e) => return Err(auth_status(AuthState::Invalid, None, Some(e.to_string()))),
+        };
+        let Some(oauth) = oauth else {
+            return Err(auth_status(
+                AuthState::Missing,
+                None,
+                Some("No OAuth credentials found".to_string()),
+            ));
+        };
+        match check_expiry(&oauth, chrono::Utc::now()) {
+            Some(status) => Err(status),
+            None => Ok(oauth),
+        }
+    }
+
+    /// Fetch usage data from Claude API.
+    ///
+    /// Rate limits, server errors and dropped connections are retried with
+    /// exponential backoff, or after the server's Retry-After when it gives
+    /// one. A rate limit that outlasts the retries is returned as
+    /// `RateLimited` so pollers can slow down.
     pub async fn fetch_usage(&self) -> Result<ClaudeUsageSummary, ClaudeApiError> {
-        let token = Self::get_access_token()?;
+        let token = self.get_access_token()?;
+
+        let mut attempt = 1;
+        loop {
+            let (error, retry_after) = match self.usage_request(&token).await {
+                Ok(response) if response.status().is_success() => {
+                    let api_response: ClaudeApiUsageResponse = response
+                        .json()
+                        .await
+                        .map_err(|e| ClaudeApiError::ParseError(e.to_string()))?;
+                    return Ok(self.convert_to_summary(api_response));
+                }
+                Ok(response) => {
+                    let status = response.status();
+                    let retry_after = parse_retry_after(response.headers(), chrono::Utc::now());
+                    let body = response.text().await.unwrap_or_default();
+                    let message = format!("API returned {}: {}", status, body);
+                    match status {
+                        StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => {
+                            return Err(ClaudeApiError::Unauthorized(message))
+                        }
+                        StatusCode::TOO_MANY_REQUESTS => {
+                            (ClaudeApiError::RateLimited { retry_after }, retry_after)
+                        }
+                        status if status.is_server_error() => {
+                            (ClaudeApiError::RequestFailed(message), retry_after)
+                        }
+                        _ => return Err(ClaudeApiError::RequestFailed(message)),
+                    }
+                }
+                // The request never got an answer, which is as likely to
+                // pass as a server error
+                Err(e) => (e, None),
+            };
 
-        let response = self
-            .client
-            .get(CLAUDE_USAGE_API)
+            if attempt >= MAX_ATTEMPTS {
+                return Err(error);
+            }
+            let delay = match retry_after {
+                Some(wait) if wait > MAX_RETRY_WAIT => return Err(error),
+                Some(wait) => wait,
+                None => backoff_delay(attempt),
+            };
+            tracing::debug!(
+                "Claude usage request failed ({}), retrying in {:?}",
+                error,
+                delay
+            );
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+
+    async fn usage_request(&self, token: &str) -> Result<reqwest::Response, ClaudeApiError> {
+        self.client
+            .get(&self.usage_url)
             .header("Accept", "application/json")
             .header("Content-Type", "application/json")
             .header("User-Agent", format!("claude-code/{}", CLAUDE_CODE_VERSION))
@@ -86,23 +260,7 @@ impl ClaudeApiService {
             .header("anthropic-beta", "oauth-2025-04-20")
             .send()
             .await
-            .map_err(|e| ClaudeApiError::RequestFailed(e.to_string()))?;
-
-        if !response.status().is_success() {
-            let status = response.status();
-            let body = response.text().await.unwrap_or_default();
-            return Err(ClaudeApiError::RequestFailed(format!(
-                "API returned {}: {}",
-                status, body
-            )));
-        }
-
-        let api_response: ClaudeApiUsageResponse = response
-            .json()
-            .await
-            .map_err(|e| ClaudeApiError::ParseError(e.to_string()))?;
-
-        Ok(self.convert_to_summary(api_response))
+            .map_err(|e| ClaudeApiError::RequestFailed(e.to_string()))
     }
 
     /// Convert Claude API response to frontend-expected format
@@ -164,3 +322,235 @@ impl Default for ClaudeApiService {
         Self::new()
     }
 }
+
+/// Backoff before retry `attempt`: the base delay doubled per earlier retry,
+/// plus up to half again at random so clients don't retry in step
+fn backoff_delay(attempt: u32) -> Duration {
+    let delay = RETRY_BASE_DELAY * 2u32.pow(attempt.saturating_sub(1));
+    let jitter = RandomState::new().build_hasher().finish() % 1_000;
+    delay + delay.mul_f64(jitter as f64 / 2_000.0)
+}
+
+/// The wait a Retry-After header asks for, given in seconds or as an HTTP date
+fn parse_retry_after(headers: &HeaderMap, now: chrono::DateTime<chrono::Utc>) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+    let at = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    Some(
+        (at.with_timezone(&chrono::Utc) - now)
+            .to_std()
+            .unwrap_or_default(),
+    )
+}
+
+fn auth_status(
+    state: AuthState,
+    oauth: Option<&ClaudeOAuthCredentials>,
+    message: Option<String>,
+) -> ClaudeAuthStatus {
+    ClaudeAuthStatus {
+        state,
+        expires_at: oauth
+            .and_then(|o| o.expires_at)
+            .and_then(chrono::DateTime::from_timestamp_millis)
+            .map(|t| t.to_rfc3339()),
+        subscription_type: oauth.and_then(|o| o.subscription_type.clone()),
+        message,
+    }
+}
+
+/// An expired status when the token's expiry time has passed
+fn check_expiry(
+    oauth: &ClaudeOAuthCredentials,
+    now: chrono::DateTime<chrono::Utc>,
+) -> Option<ClaudeAuthStatus> {
+    let expires_at = oauth.expires_at?;
+    (expires_at <= now.timestamp_millis()).then(|| {
+        auth_status(
+            AuthState::Expired,
+            Some(oauth),
+            Some("Access token has expired; run Claude to refresh it or log in again".to_string()),
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_reads_credentials_from_config_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let service = ClaudeApiService::new().with_config_dir(dir.path());
+        assert!(matches!(
+            service.get_access_token(),
+            Err(ClaudeApiError::CredentialsNotFound(_))
+        ));
+
+        std::fs::write(
+            dir.path().join(".credentials.json"),
+            r#"{"claudeAiOauth":{"accessToken":"sk-ant-oat-work"}}"#,
+        )
+        .unwrap();
+        assert_eq!(service.get_access_token().unwrap(), "sk-ant-oat-work");
+    }
+
+    #[tokio::test]
+    async fn test_check_auth_without_network() {
+        let dir = tempfile::tempdir().unwrap();
+        let service = ClaudeApiService::new().with_config_dir(dir.path());
+        assert_eq!(service.check_auth().await.state, AuthState::Missing);
+
+        std::fs::write(dir.path().join(".credentials.json"), "not json").unwrap();
+        assert_eq!(service.check_auth().await.state, AuthState::Invalid);
+
+        std::fs::write(
+            dir.path().join(".credentials.json"),
+            r#"{"claudeAiOauth":{"accessToken":"t","expiresAt":1700000000000,"subscriptionType":"max"}}"#,
+        )
+        .unwrap();
+        let status = service.check_auth().await;
+        assert_eq!(status.state, AuthState::Expired);
+        assert_eq!(status.subscription_type.as_deref(), Some("max"));
+        assert_eq!(
+            status.expires_at.as_deref(),
+            Some("2023-11-14T22:13:20+00:00")
+        );
+        assert_eq!(service.check_auth_offline().state, AuthState::Expired);
+
+        std::fs::write(
+            dir.path().join(".credentials.json"),
+            r#"{"claudeAiOauth":{"accessToken":"t"}}"#,
+        )
+        .unwrap();
+        assert_eq!(service.check_auth_offline().state, AuthState::Unverified);
+    }
+
+    /// Status, headers and body of a response to a usage request
+    type CannedResponse = (u16, Vec<(&'static str, &'static str)>, &'static str);
+
+    /// Serve the given responses to usage requests in turn, counting requests
+    async fn usage_server(responses: Vec<CannedResponse>) -> (String, Arc<AtomicUsize>) {
+        let requests = Arc::new(AtomicUsize::new(0));
+        let counter = requests.clone();
+        let responses = Arc::new(responses);
+        let app = axum::Router::new().route(
+            "/usage",
+            axum::routing::get(move || {
+                let counter = counter.clone();
+                let responses = responses.clone();
+                async move {
+                    let n = counter
+                        .fetch_add(1, Ordering::SeqCst)
+                        .min(responses.len() - 1);
+                    let (status, headers, body) = &responses[n];
+                    let mut response = axum::response::Response::builder().status(*status);
+                    for (name, value) in headers {
+                        response = response.header(*name, *value);
+                    }
+                    response.body(axum::body::Body::from(*body)).unwrap()
+                }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        (format!("http://{}/usage", addr), requests)
+    }
+
+    fn logged_in_service(dir: &std::path::Path, url: String) -> ClaudeApiService {
+        std::fs::write(
+            dir.join(".credentials.json"),
+            r#"{"claudeAiOauth":{"accessToken":"t"}}"#,
+        )
+        .unwrap();
+        ClaudeApiService::new()
+            .with_config_dir(dir)
+            .with_usage_url(url)
+    }
+
+    #[tokio::test]
+    async fn test_fetch_usage_retries_transient_failures() {
+        let dir = tempfile::tempdir().unwrap();
+        let (url, requests) = usage_server(vec![
+            (503, vec![], "overloaded"),
+            (429, vec![("retry-after", "0")], ""),
+            (200, vec![("content-type", "application/json")], "{}"),
+        ])
+        .await;
+
+        let service = logged_in_service(dir.path(), url);
+        let summary = service.fetch_usage().await.unwrap();
+        assert_eq!(summary.daily.used, 0.0);
+        assert_eq!(requests.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_usage_returns_long_rate_limits_and_rejections() {
+        let dir = tempfile::tempdir().unwrap();
+        let (url, requests) = usage_server(vec![(429, vec![("retry-after", "120")], "")]).await;
+        let service = logged_in_service(dir.path(), url);
+        let error = service.fetch_usage().await.unwrap_err();
+        assert!(matches!(
+            error,
+            ClaudeApiError::RateLimited {
+                retry_after: Some(wait)
+            } if wait == Duration::from_secs(120)
+        ));
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+
+        let (url, requests) = usage_server(vec![(401, vec![], "expired")]).await;
+        let service = logged_in_service(dir.path(), url);
+        assert!(matches!(
+            service.fetch_usage().await,
+            Err(ClaudeApiError::Unauthorized(_))
+        ));
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_retry_after_and_backoff() {
+        let now = chrono::DateTime::parse_from_rfc3339("2026-01-01T00:00:00Z")
+            .unwrap()
+            .with_timezone(&chrono::Utc);
+        let headers = |value: &'static str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(RETRY_AFTER, value.parse().unwrap());
+            headers
+        };
+        assert_eq!(
+            parse_retry_after(&headers("30"), now),
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(
+            parse_retry_after(&headers("Thu, 01 Jan 2026 00:01:00 GMT"), now),
+            Some(Duration::from_secs(60))
+        );
+        assert_eq!(parse_retry_after(&headers("soon"), now), None);
+        assert_eq!(parse_retry_after(&HeaderMap::new(), now), None);
+
+        for attempt in 1..=3 {
+            let base = RETRY_BASE_DELAY * 2u32.pow(attempt - 1);
+            let delay = backoff_delay(attempt);
+            assert!(delay >= base && delay <= base.mul_f64(1.5));
+        }
+    }
+
+    #[test]
+    fn test_unexpired_token_passes_expiry_check() {
+        let now = chrono::Utc::now();
+        let oauth = |expires_at| ClaudeOAuthCredentials {
+            access_token: "t".to_string(),
+            refresh_token: None,
+            expires_at,
+            subscription_type: None,
+        };
+        assert!(check_expiry(&oauth(None), now).is_none());
+        assert!(check_expiry(&oauth(Some(now.timestamp_millis() + 60_000)), now).is_none());
+        assert!(check_expiry(&oauth(Some(now.timestamp_millis())), now).is_some());
+    }
+}
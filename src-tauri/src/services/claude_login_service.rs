@@ -0,0 +1,325 @@
+//! Login service for re-authenticating the Claude CLI from inside the app
+//!
+//! Runs `claude login` in a PTY, for the default account or a profile's,
+//! so the UI can show the sign-in URL and type the code the browser hands
+//! back. One login runs per account at a time.
+
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use parking_lot::Mutex;
+use portable_pty::{native_pty_system, ChildKiller, CommandBuilder, PtySize};
+use thiserror::Error;
+
+use crate::types::{ClaudeLogin, ClaudeProfile, LoginStatus};
+use crate::util::ansi;
+
+/// Key of the default account's login
+const DEFAULT_ACCOUNT: &str = "default";
+/// Raw output kept per login
+const OUTPUT_BUFFER_MAX_BYTES: usize = 64 * 1_024;
+/// Lines of plain-text output returned with the login status
+const OUTPUT_TAIL_LINES: usize = 50;
+
+#[derive(Error, Debug)]
+pub enum LoginError {
+    #[error("A login is already running for this account")]
+    AlreadyRunning,
+    #[error("No login is running for this account")]
+    NotRunning,
+    #[error("Failed to start login: {0}")]
+    SpawnFailed(String),
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+/// A login run and the handles to drive it
+struct LoginSession {
+    /// Tells this run apart from a later one for the same account, which a
+    /// cancelled run's reader must leave alone
+    run: u64,
+    info: ClaudeLogin,
+    output: Vec<u8>,
+    /// Dropped when the run finishes
+    writer: Option<Box<dyn Write + Send>>,
+    killer: Option<Box<dyn ChildKiller + Send + Sync>>,
+}
+
+pub struct ClaudeLoginService {
+    claude_cli_path: String,
+    sessions: Arc<Mutex<HashMap<String, LoginSession>>>,
+    next_run: AtomicU64,
+}
+
+impl ClaudeLoginService {
+    pub fn new(claude_cli_path: String) -> Self {
+        Self {
+            claude_cli_path,
+            sessions: Arc::new(Mutex::new(HashMap::new())),
+            next_run: AtomicU64::new(0),
+        }
+    }
+
+    /// Start `claude login` for a profile's account, or the default one.
+    ///
+    /// The run continues in the background; poll `get_login` for its output
+    /// and answer prompts with `send_input`.
+    pub fn start_login(&self, profile: Option<&ClaudeProfile>) -> Result<ClaudeLogin, LoginError> {
+        let key = account_key(profile.map(|p| p.id.as_str()));
+        if self.is_running(&key) {
+            return Err(LoginError::AlreadyRunning);
+        }
+
+        let pair = native_pty_system()
+            .openpty(PtySize {
+                rows: 24,
+                cols: 120,
+                pixel_width: 0,
+                pixel_height: 0,
+            })
+            .map_err(|e| LoginError::SpawnFailed(e.to_string()))?;
+
+        let cli_path = profile
+            .and_then(|p| p.cli_path.as_deref())
+            .unwrap_or(&self.claude_cli_path);
+        let mut cmd = CommandBuilder::new(cli_path);
+        cmd.arg("login");
+        cmd.env("TERM", "xterm-256color");
+        if let Some(config_dir) = profile.and_then(|p| p.config_dir.as_deref()) {
+            cmd.env("CLAUDE_CONFIG_DIR", config_dir);
+        }
+        if let Some(home) = dirs::home_dir() {
+            cmd.cwd(home);
+        }
+
+        let mut child = pair
+            .slave
+            .spawn_command(cmd)
+            .map_err(|e| LoginError::SpawnFailed(e.to_string()))?;
+        drop(pair.slave);
+        let mut reader = pair
+            .master
+            .try_clone_reader()
+            .map_err(|e| LoginError::SpawnFailed(e.to_string()))?;
+        let writer = pair
+            .master
+            .take_writer()
+            .map_err(|e| LoginError::SpawnFailed(e.to_string()))?;
+
+        let info = ClaudeLogin {
+            profile_id: profile.map(|p| p.id.clone()),
+            status: LoginStatus::Running,
+            exit_code: None,
+            output: String::new(),
+            started_at: chrono::Utc::now().to_rfc3339(),
+            finished_at: None,
+        };
+        let run = self.next_run.fetch_add(1, Ordering::Relaxed);
+        self.sessions.lock().insert(
+            key.clone(),
+            LoginSession {
+                run,
+                info: info.clone(),
+                output: Vec::new(),
+                writer: Some(writer),
+                killer: Some(child.clone_killer()),
+            },
+        );
+        tracing::info!("Started Claude login for the {} account", key);
+
+        let sessions = self.sessions.clone();
+        let master = pair.master;
+        std::thread::spawn(move || {
+            let mut buf = [0u8; 4096];
+            loop {
+                match reader.read(&mut buf) {
+                    Ok(0) | Err(_) => break,
+                    Ok(n) => {
+                        let mut sessions = sessions.lock();
+                        if let Some(session) = sessions.get_mut(&key).filter(|s| s.run == run) {
+                            session.output.extend_from_slice(&buf[..n]);
+                            if session.output.len() > OUTPUT_BUFFER_MAX_BYTES {
+                                let excess = session.output.len() - OUTPUT_BUFFER_MAX_BYTES;
+                                session.output.drain(..excess);
+                            }
+                        }
+                    }
+                }
+            }
+
+            let exit_code = child.wait().ok().map(|status| status.exit_code() as i32);
+            drop(master);
+
+            let mut sessions = sessions.lock();
+            if let Some(session) = sessions.get_mut(&key).filter(|s| s.run == run) {
+                if session.info.status == LoginStatus::Running {
+                    session.info.status = match exit_code {
+                        Some(0) => LoginStatus::Succeeded,
+                        _ => LoginStatus::Failed,
+                    };
+                    session.info.finished_at = Some(chrono::Utc::now().to_rfc3339());
+                }
+                session.info.exit_code = exit_code;
+                session.writer = None;
+                session.killer = None;
+                tracing::info!(
+                    "Claude login for the {} account finished with {:?}",
+                    key,
+                    exit_code
+                );
+            }
+        });
+
+        Ok(info)
+    }
+
+    /// The latest login of an account, if one ran since the app started
+    pub fn get_login(&self, profile_id: Option<&str>) -> Option<ClaudeLogin> {
+        self.sessions
+            .lock()
+            .get(&account_key(profile_id))
+            .map(|session| ClaudeLogin {
+                output: ansi::tail_lines(&session.output, OUTPUT_TAIL_LINES).join("\n"),
+                ..session.info.clone()
+            })
+    }
+
+    /// Answer a prompt of a running login, e.g. with the code from the browser
+    pub fn send_input(&self, profile_id: Option<&str>, input: &str) -> Result<(), LoginError> {
+        let mut sessions = self.sessions.lock();
+        let writer = sessions
+            .get_mut(&account_key(profile_id))
+            .and_then(|session| session.writer.as_mut())
+            .ok_or(LoginError::NotRunning)?;
+        writer.write_all(input.trim_end().as_bytes())?;
+        writer.write_all(b"\r")?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Stop a running login
+    pub fn cancel_login(&self, profile_id: Option<&str>) -> Result<ClaudeLogin, LoginError> {
+        {
+            let mut sessions = self.sessions.lock();
+            let session = sessions
+                .get_mut(&account_key(profile_id))
+                .filter(|session| session.info.status == LoginStatus::Running)
+                .ok_or(LoginError::NotRunning)?;
+            if let Some(mut killer) = session.killer.take() {
+                killer.kill()?;
+            }
+            session.writer = None;
+            session.info.status = LoginStatus::Cancelled;
+            session.info.finished_at = Some(chrono::Utc::now().to_rfc3339());
+        }
+        self.get_login(profile_id).ok_or(LoginError::NotRunning)
+    }
+
+    fn is_running(&self, key: &str) -> bool {
+        self.sessions
+            .lock()
+            .get(key)
+            .is_some_and(|session| session.info.status == LoginStatus::Running)
+    }
+}
+
+fn account_key(profile_id: Option<&str>) -> String {
+    profile_id.unwrap_or(DEFAULT_ACCOUNT).to_string()
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+    use tempfile::TempDir;
+
+    /// A stand-in CLI that asks for a code and echoes what it got
+    fn fake_cli(script: &str) -> (TempDir, String) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("claude");
+        std::fs::write(&path, format!("#!/bin/sh\n{}\n", script)).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let path = path.to_string_lossy().to_string();
+        (dir, path)
+    }
+
+    fn wait_until_finished(service: &ClaudeLoginService, profile_id: Option<&str>) -> ClaudeLogin {
+        for _ in 0..200 {
+            let login = service.get_login(profile_id).unwrap();
+            if login.status != LoginStatus::Running {
+                return login;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(25));
+        }
+        panic!("login did not finish");
+    }
+
+    fn wait_for_output(service: &ClaudeLoginService, profile_id: Option<&str>, text: &str) {
+        for _ in 0..200 {
+            if service.get_login(profile_id).unwrap().output.contains(text) {
+                return;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(25));
+        }
+        panic!("login never printed {}", text);
+    }
+
+    #[test]
+    fn test_login_takes_input_under_profile_config_dir() {
+        let (_dir, cli) = fake_cli(
+            r#"echo "$1 in $CLAUDE_CONFIG_DIR"; printf 'Paste code: '; read code; echo "got $code""#,
+        );
+        let service = ClaudeLoginService::new("claude-missing".to_string());
+        let profile = ClaudeProfile {
+            id: "prof_work".to_string(),
+            name: "Work".to_string(),
+            config_dir: Some("/tmp/claude-work".to_string()),
+            cli_path: Some(cli),
+            model: None,
+        };
+
+        let login = service.start_login(Some(&profile)).unwrap();
+        assert_eq!(login.status, LoginStatus::Running);
+        assert!(matches!(
+            service.start_login(Some(&profile)),
+            Err(LoginError::AlreadyRunning)
+        ));
+        assert!(service.get_login(None).is_none());
+
+        wait_for_output(&service, Some("prof_work"), "Paste code");
+        service.send_input(Some("prof_work"), "abc123\n").unwrap();
+
+        let login = wait_until_finished(&service, Some("prof_work"));
+        assert_eq!(login.status, LoginStatus::Succeeded);
+        assert_eq!(login.exit_code, Some(0));
+        assert!(login.output.contains("login in /tmp/claude-work"));
+        assert!(login.output.contains("got abc123"));
+        assert!(matches!(
+            service.send_input(Some("prof_work"), "again"),
+            Err(LoginError::NotRunning)
+        ));
+    }
+
+    #[test]
+    fn test_cancel_login() {
+        let (_dir, cli) = fake_cli("sleep 30");
+        let service = ClaudeLoginService::new(cli);
+
+        service.start_login(None).unwrap();
+        let login = service.cancel_login(None).unwrap();
+        assert_eq!(login.status, LoginStatus::Cancelled);
+
+        // The exit of the killed process doesn't turn it into a failure
+        std::thread::sleep(std::time::Duration::from_millis(200));
+        assert_eq!(
+            service.get_login(None).unwrap().status,
+            LoginStatus::Cancelled
+        );
+        assert!(matches!(
+            service.cancel_login(None),
+            Err(LoginError::NotRunning)
+        ));
+    }
+}
@@ -0,0 +1,199 @@
+//! CLAUDE.md service for editing a worktree's memory files
+//!
+//! The Claude CLI loads `CLAUDE.md` and `.claude/CLAUDE.local.md` from the
+//! worktree as standing instructions. Updates carry the modification time
+//! the editor last saw, and are refused if the file has changed since.
+
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use thiserror::Error;
+
+use crate::db::{DbPool, WorktreeRepository};
+use crate::types::{ClaudeMdFile, ClaudeMdScope};
+
+#[derive(Error, Debug)]
+pub enum ClaudeMdError {
+    #[error("Worktree not found: {0}")]
+    WorktreeNotFound(String),
+    #[error("{path} was changed since it was loaded")]
+    Conflict {
+        path: String,
+        modified_at: Option<i64>,
+    },
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Database error: {0}")]
+    Database(String),
+}
+
+pub struct ClaudeMdService {
+    worktree_repo: WorktreeRepository,
+}
+
+impl ClaudeMdService {
+    pub fn new(pool: DbPool) -> Self {
+        Self {
+            worktree_repo: WorktreeRepository::new(pool),
+        }
+    }
+
+    /// Both memory files of a worktree, project first
+    pub fn get_files(&self, worktree_id: &str) -> Result<Vec<ClaudeMdFile>, ClaudeMdError> {
+        let root = self.worktree_path(worktree_id)?;
+        [ClaudeMdScope::Project, ClaudeMdScope::Local]
+            .into_iter()
+            .map(|scope| read_file(&root, scope))
+            .collect()
+    }
+
+    /// Write a memory file, creating it if needed.
+    ///
+    /// `expected_modified_at` is the modification time from the last read,
+    /// or None if the file did not exist then.
+    pub fn update_file(
+        &self,
+        worktree_id: &str,
+        scope: ClaudeMdScope,
+        content: &str,
+        expected_modified_at: Option<i64>,
+    ) -> Result<ClaudeMdFile, ClaudeMdError> {
+        let root = self.worktree_path(worktree_id)?;
+        let current = read_file(&root, scope)?;
+        if current.modified_at != expected_modified_at {
+            return Err(ClaudeMdError::Conflict {
+                path: current.path,
+                modified_at: current.modified_at,
+            });
+        }
+
+        let path = root.join(scope.relative_path());
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&path, content)?;
+
+        read_file(&root, scope)
+    }
+
+    fn worktree_path(&self, worktree_id: &str) -> Result<PathBuf, ClaudeMdError> {
+        self.worktree_repo
+            .find_by_id(worktree_id)
+            .map_err(|e| ClaudeMdError::Database(e.to_string()))?
+            .map(|worktree| PathBuf::from(worktree.path))
+            .ok_or_else(|| ClaudeMdError::WorktreeNotFound(worktree_id.to_string()))
+    }
+}
+
+fn read_file(root: &Path, scope: ClaudeMdScope) -> Result<ClaudeMdFile, ClaudeMdError> {
+    let path = root.join(scope.relative_path());
+    let (exists, content, modified_at) = match std::fs::metadata(&path) {
+        Ok(metadata) => {
+            let modified_at = metadata
+                .modified()?
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_millis() as i64)
+                .unwrap_or(0);
+            (true, std::fs::read_to_string(&path)?, Some(modified_at))
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => (false, String::new(), None),
+        Err(e) => return Err(e.into()),
+    };
+
+    Ok(ClaudeMdFile {
+        scope,
+        path: path.to_string_lossy().to_string(),
+        exists,
+        content,
+        modified_at,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn create_test_service() -> (ClaudeMdService, PathBuf, TempDir) {
+        let (pool, dir) = crate::db::test_pool();
+        let worktree_path = dir.path().join("worktree");
+        std::fs::create_dir(&worktree_path).unwrap();
+
+        let conn = pool.get().unwrap();
+        conn.execute(
+            "INSERT INTO workspaces (id, name, path) VALUES ('ws_1', 'Test', '/tmp/test')",
+            [],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO worktrees (id, workspace_id, name, branch, path)
+             VALUES ('wt_1', 'ws_1', 'main', 'main', ?)",
+            [worktree_path.to_string_lossy()],
+        )
+        .unwrap();
+
+        (ClaudeMdService::new(pool), worktree_path, dir)
+    }
+
+    #[test]
+    fn test_get_files_reports_missing_files() {
+        let (service, worktree_path, _dir) = create_test_service();
+        std::fs::write(worktree_path.join("CLAUDE.md"), "# Rules").unwrap();
+
+        let files = service.get_files("wt_1").unwrap();
+
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[0].scope, ClaudeMdScope::Project);
+        assert!(files[0].exists);
+        assert_eq!(files[0].content, "# Rules");
+        assert!(files[0].modified_at.is_some());
+        assert_eq!(files[1].scope, ClaudeMdScope::Local);
+        assert!(!files[1].exists);
+        assert!(files[1].modified_at.is_none());
+
+        assert!(matches!(
+            service.get_files("wt_missing"),
+            Err(ClaudeMdError::WorktreeNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_update_file_detects_conflicts() {
+        let (service, worktree_path, _dir) = create_test_service();
+
+        let created = service
+            .update_file("wt_1", ClaudeMdScope::Local, "Use pnpm", None)
+            .unwrap();
+        assert!(created.exists);
+        assert_eq!(
+            std::fs::read_to_string(worktree_path.join(".claude/CLAUDE.local.md")).unwrap(),
+            "Use pnpm"
+        );
+
+        // Creating again as if the file were still missing is a conflict
+        assert!(matches!(
+            service.update_file("wt_1", ClaudeMdScope::Local, "Use npm", None),
+            Err(ClaudeMdError::Conflict { .. })
+        ));
+        // So is writing over a change made after the last read
+        assert!(matches!(
+            service.update_file(
+                "wt_1",
+                ClaudeMdScope::Local,
+                "Use npm",
+                created.modified_at.map(|t| t - 1_000)
+            ),
+            Err(ClaudeMdError::Conflict { .. })
+        ));
+
+        let updated = service
+            .update_file(
+                "wt_1",
+                ClaudeMdScope::Local,
+                "Use yarn",
+                created.modified_at,
+            )
+            .unwrap();
+        assert_eq!(updated.content, "Use yarn");
+    }
+}
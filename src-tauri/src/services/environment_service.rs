@@ -0,0 +1,224 @@
+//! Environment service for checking the Claude CLI setup
+//!
+//! Agents need the Claude CLI at the configured path, new enough for the
+//! flags we pass, and logged in. Each missing piece is reported as a
+//! diagnostic the UI can show with a fix.
+
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use tokio::process::Command;
+
+use crate::services::ClaudeApiService;
+use crate::types::{ClaudeCliStatus, CliDiagnostic, CliIssue};
+
+/// Oldest CLI with the hook notifications and session flags agents rely on
+const MIN_CLAUDE_CLI_VERSION: &str = "2.0.0";
+/// `claude --version` normally answers at once
+const VERSION_TIMEOUT: Duration = Duration::from_secs(10);
+const API_KEY_VAR: &str = "ANTHROPIC_API_KEY";
+
+pub struct EnvironmentService {
+    claude_cli_path: String,
+    credentials_path: Option<PathBuf>,
+}
+
+impl EnvironmentService {
+    pub fn new(claude_cli_path: String) -> Self {
+        Self {
+            claude_cli_path,
+            credentials_path: ClaudeApiService::credentials_path().ok(),
+        }
+    }
+
+    /// Check the configured Claude CLI and report what needs fixing
+    pub async fn check_claude_cli(&self) -> ClaudeCliStatus {
+        let mut status = ClaudeCliStatus {
+            path: self.claude_cli_path.clone(),
+            resolved_path: None,
+            version: None,
+            min_version: MIN_CLAUDE_CLI_VERSION.to_string(),
+            logged_in: self.is_logged_in(),
+            diagnostics: Vec::new(),
+        };
+
+        match resolve_executable(&self.claude_cli_path) {
+            Some(resolved) => {
+                status.resolved_path = Some(resolved.to_string_lossy().to_string());
+                match cli_version(&resolved).await {
+                    Ok(version) => {
+                        if !version_at_least(&version, MIN_CLAUDE_CLI_VERSION) {
+                            status.diagnostics.push(CliDiagnostic {
+                                issue: CliIssue::Outdated,
+                                message: format!(
+                                    "Claude CLI {} is older than {}; run `claude update`",
+                                    version, MIN_CLAUDE_CLI_VERSION
+                                ),
+                            });
+                        }
+                        status.version = Some(version);
+                    }
+                    Err(e) => status.diagnostics.push(CliDiagnostic {
+                        issue: CliIssue::VersionUnknown,
+                        message: format!("Could not read the Claude CLI version: {}", e),
+                    }),
+                }
+            }
+            None => status.diagnostics.push(CliDiagnostic {
+                issue: CliIssue::NotFound,
+                message: format!(
+                    "Claude CLI not found at {}; install it or set CLAUDE_CLI_PATH",
+                    self.claude_cli_path
+                ),
+            }),
+        }
+
+        if !status.logged_in {
+            status.diagnostics.push(CliDiagnostic {
+                issue: CliIssue::NotLoggedIn,
+                message: format!(
+                    "Claude CLI is not logged in; run `claude` and log in, or set {}",
+                    API_KEY_VAR
+                ),
+            });
+        }
+
+        status
+    }
+
+    /// Stored OAuth credentials or an API key in the environment
+    pub fn is_logged_in(&self) -> bool {
+        let has_api_key = std::env::var(API_KEY_VAR).is_ok_and(|key| !key.is_empty());
+        has_api_key || self.credentials_path.as_deref().is_some_and(Path::exists)
+    }
+}
+
+/// The executable a command name or path refers to, searching PATH for bare names
+fn resolve_executable(command: &str) -> Option<PathBuf> {
+    let path = Path::new(command);
+    if path.components().count() > 1 {
+        return is_executable(path).then(|| path.to_path_buf());
+    }
+    std::env::split_paths(&std::env::var_os("PATH")?)
+        .map(|dir| dir.join(command))
+        .find(|candidate| is_executable(candidate))
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    path.metadata()
+        .is_ok_and(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
+/// Version printed by `--version`, e.g. "2.1.29 (Claude Code)"
+async fn cli_version(path: &Path) -> Result<String, String> {
+    let output = tokio::time::timeout(
+        VERSION_TIMEOUT,
+        Command::new(path)
+            .arg("--version")
+            .kill_on_drop(true)
+            .output(),
+    )
+    .await
+    .map_err(|_| "timed out".to_string())?
+    .map_err(|e| e.to_string())?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(stderr.trim().to_string());
+    }
+    parse_version(&String::from_utf8_lossy(&output.stdout))
+        .ok_or_else(|| "no version in output".to_string())
+}
+
+/// First dotted number in the text
+fn parse_version(text: &str) -> Option<String> {
+    text.split_whitespace()
+        .map(|word| word.trim_start_matches('v'))
+        .find(|word| word.contains('.') && word.split('.').all(|part| part.parse::<u32>().is_ok()))
+        .map(|word| word.to_string())
+}
+
+fn version_at_least(version: &str, min: &str) -> bool {
+    let parts = |v: &str| -> Vec<u32> { v.split('.').filter_map(|p| p.parse().ok()).collect() };
+    parts(version) >= parts(min)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+    use tempfile::TempDir;
+
+    fn fake_cli(dir: &TempDir, script: &str) -> String {
+        let path = dir.path().join("claude");
+        std::fs::write(&path, format!("#!/bin/sh\n{}\n", script)).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        path.to_string_lossy().to_string()
+    }
+
+    fn service(path: String, credentials_path: Option<PathBuf>) -> EnvironmentService {
+        EnvironmentService {
+            claude_cli_path: path,
+            credentials_path,
+        }
+    }
+
+    fn issues(status: &ClaudeCliStatus) -> Vec<CliIssue> {
+        status.diagnostics.iter().map(|d| d.issue).collect()
+    }
+
+    #[test]
+    fn test_parse_and_compare_versions() {
+        assert_eq!(
+            parse_version("2.1.29 (Claude Code)").as_deref(),
+            Some("2.1.29")
+        );
+        assert_eq!(parse_version("claude v1.0.3\n").as_deref(), Some("1.0.3"));
+        assert_eq!(parse_version("Claude Code"), None);
+
+        assert!(version_at_least("2.1.29", "2.0.0"));
+        assert!(!version_at_least("2.0", "2.0.0"));
+        assert!(version_at_least("10.0.0", "2.0.0"));
+        assert!(!version_at_least("1.9.9", "2.0.0"));
+    }
+
+    #[tokio::test]
+    async fn test_check_claude_cli_reports_issues() {
+        let dir = tempfile::tempdir().unwrap();
+        let credentials = dir.path().join(".credentials.json");
+        std::fs::write(&credentials, "{}").unwrap();
+
+        let path = fake_cli(&dir, "echo '2.1.29 (Claude Code)'");
+        let status = service(path, Some(credentials.clone()))
+            .check_claude_cli()
+            .await;
+        assert_eq!(status.version.as_deref(), Some("2.1.29"));
+        assert!(status.logged_in);
+        assert!(status.diagnostics.is_empty());
+        assert!(status.is_installed());
+
+        let path = fake_cli(&dir, "echo '1.0.3 (Claude Code)'");
+        let status = service(path, Some(credentials)).check_claude_cli().await;
+        assert_eq!(issues(&status), [CliIssue::Outdated]);
+        assert!(!status.is_installed());
+
+        let path = fake_cli(&dir, "echo 'broken' >&2; exit 1");
+        let status = service(path, Some(dir.path().join("missing.json")))
+            .check_claude_cli()
+            .await;
+        assert!(status.version.is_none());
+        assert!(issues(&status).contains(&CliIssue::VersionUnknown));
+
+        let missing = dir.path().join("nope").to_string_lossy().to_string();
+        let status = service(missing, None).check_claude_cli().await;
+        assert!(status.resolved_path.is_none());
+        assert!(issues(&status).contains(&CliIssue::NotFound));
+    }
+}
@@ -0,0 +1,177 @@
+//! What workspace and worktree services need from git
+//!
+//! The services hold an `Arc<dyn GitOps>` rather than calling `GitService`
+//! directly, so tests can hand them an in-memory repository instead of a
+//! real one.
+
+use std::path::PathBuf;
+
+use crate::services::git_service::{DivergedFiles, GitError, GitService, WorktreeInfo};
+use crate::types::{BranchInfo, CommitInfo, GitStatusInfo, GitStatusOptions, StashEntry};
+use crate::util::cancel::CancellationToken;
+
+pub trait GitOps: Send + Sync {
+    fn is_valid_repository(&self, path: &str) -> bool;
+
+    /// The git dir shared by a repository and all its worktrees
+    fn common_dir(&self, path: &str) -> Result<PathBuf, GitError>;
+
+    fn get_current_branch(&self, path: &str) -> Result<String, GitError>;
+
+    /// The main worktree of a repository followed by its linked ones
+    fn list_worktrees(&self, path: &str) -> Result<Vec<WorktreeInfo>, GitError>;
+
+    /// Add a worktree, undoing what was done if it is cancelled part way
+    fn add_worktree(
+        &self,
+        repo_path: &str,
+        worktree_path: &str,
+        branch: &str,
+        create_branch: bool,
+        sparse_dirs: &[String],
+        cancel: &CancellationToken,
+    ) -> Result<WorktreeInfo, GitError>;
+
+    fn remove_worktree(&self, repo_path: &str, worktree_path: &str) -> Result<(), GitError>;
+
+    fn checkout_branch(
+        &self,
+        worktree_path: &str,
+        branch: &str,
+        create: bool,
+    ) -> Result<(), GitError>;
+
+    fn list_branches(&self, path: &str) -> Result<BranchInfo, GitError>;
+
+    fn fetch(&self, path: &str, prune: bool, token: Option<&str>) -> Result<(), GitError>;
+
+    /// Stash local changes; None when there was nothing to stash
+    fn stash_save(
+        &self,
+        path: &str,
+        message: Option<&str>,
+        include_untracked: bool,
+    ) -> Result<Option<StashEntry>, GitError>;
+
+    fn stash_list(&self, path: &str) -> Result<Vec<StashEntry>, GitError>;
+
+    fn stash_pop(&self, path: &str, index: usize) -> Result<(), GitError>;
+
+    /// Status, with how far HEAD is ahead of and behind a base branch
+    fn get_status_against(
+        &self,
+        path: &str,
+        base_branch: &str,
+        options: &GitStatusOptions,
+    ) -> Result<GitStatusInfo, GitError>;
+
+    /// Commits reachable from HEAD but not from the base branch, newest first
+    fn commit_log(
+        &self,
+        path: &str,
+        base_branch: Option<&str>,
+        limit: usize,
+        skip: usize,
+    ) -> Result<Vec<CommitInfo>, GitError>;
+
+    /// Files changed on each side since HEAD diverged from a base branch
+    fn diverged_files(&self, path: &str, base_branch: &str) -> Result<DivergedFiles, GitError>;
+}
+
+impl GitOps for GitService {
+    fn is_valid_repository(&self, path: &str) -> bool {
+        GitService::is_valid_repository(path)
+    }
+
+    fn common_dir(&self, path: &str) -> Result<PathBuf, GitError> {
+        GitService::common_dir(path)
+    }
+
+    fn get_current_branch(&self, path: &str) -> Result<String, GitError> {
+        GitService::get_current_branch(path)
+    }
+
+    fn list_worktrees(&self, path: &str) -> Result<Vec<WorktreeInfo>, GitError> {
+        GitService::list_worktrees(path)
+    }
+
+    fn add_worktree(
+        &self,
+        repo_path: &str,
+        worktree_path: &str,
+        branch: &str,
+        create_branch: bool,
+        sparse_dirs: &[String],
+        cancel: &CancellationToken,
+    ) -> Result<WorktreeInfo, GitError> {
+        GitService::add_worktree_cancellable(
+            repo_path,
+            worktree_path,
+            branch,
+            create_branch,
+            sparse_dirs,
+            cancel,
+        )
+    }
+
+    fn remove_worktree(&self, repo_path: &str, worktree_path: &str) -> Result<(), GitError> {
+        GitService::remove_worktree(repo_path, worktree_path)
+    }
+
+    fn checkout_branch(
+        &self,
+        worktree_path: &str,
+        branch: &str,
+        create: bool,
+    ) -> Result<(), GitError> {
+        GitService::checkout_branch(worktree_path, branch, create)
+    }
+
+    fn list_branches(&self, path: &str) -> Result<BranchInfo, GitError> {
+        GitService::list_branches(path)
+    }
+
+    fn fetch(&self, path: &str, prune: bool, token: Option<&str>) -> Result<(), GitError> {
+        GitService::fetch(path, prune, token)
+    }
+
+    fn stash_save(
+        &self,
+        path: &str,
+        message: Option<&str>,
+        include_untracked: bool,
+    ) -> Result<Option<StashEntry>, GitError> {
+        GitService::stash_save(path, message, include_untracked)
+    }
+
+    fn stash_list(&self, path: &str) -> Result<Vec<StashEntry>, GitError> {
+        GitService::stash_list(path)
+    }
+
+    fn stash_pop(&self, path: &str, index: usize) -> Result<(), GitError> {
+        GitService::stash_pop(path, index)
+    }
+
+    fn get_status_against(
+        &self,
+        path: &str,
+        base_branch: &str,
+        options: &GitStatusOptions,
+    ) -> Result<GitStatusInfo, GitError> {
+        GitService::get_status_against(path, base_branch, options)
+    }
+
+    fn commit_log(
+        &self,
+        path: &str,
+        base_branch: Option<&str>,
+        limit: usize,
+        skip: usize,
+    ) -> Result<Vec<CommitInfo>, GitError> {
+        GitService::commit_log(path, base_branch, limit, skip)
+    }
+
+    fn diverged_files(&self, path: &str, base_branch: &str) -> Result<DivergedFiles, GitError> {
+        GitService::diverged_files(path, base_branch)
+    }
+}
@@ -1,10 +1,28 @@
 //! Git service for interacting with git repositories
 
-use git2::{BranchType, Repository, StatusOptions};
-use std::path::Path;
+use git2::build::CheckoutBuilder;
+use git2::{
+    BranchType, Commit, Cred, CredentialType, Delta, Diff, DiffFormat, DiffOptions, ErrorClass,
+    ErrorCode, FetchOptions, FetchPrune, Patch, RemoteCallbacks, Repository, Signature,
+    StashApplyOptions, StashFlags, StatusOptions, Tree, WorktreeLockStatus,
+};
+use std::collections::{BTreeSet, HashSet};
+use std::ffi::OsString;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::Duration;
 use thiserror::Error;
 
-use crate::types::{BranchInfo, GitStatusInfo};
+use crate::types::{
+    BranchDiff, BranchInfo, CommitInfo, FileDiffStat, GitStatusInfo, GitStatusOptions, StashEntry,
+};
+use crate::util::cancel::CancellationToken;
+
+/// Index entry flag for files outside a sparse checkout
+const SKIP_WORKTREE: u16 = 1 << 14;
+/// How often a running git command checks whether it was cancelled
+const CANCEL_POLL_INTERVAL: Duration = Duration::from_millis(50);
 
 #[derive(Error, Debug)]
 pub enum GitError {
@@ -14,6 +32,10 @@ pub enum GitError {
     NotARepo(String),
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
+    #[error("git {0}")]
+    Command(String),
+    #[error("Cancelled")]
+    Cancelled,
 }
 
 /// Information about a worktree from git
@@ -22,6 +44,19 @@ pub struct WorktreeInfo {
     pub path: String,
     pub branch: String,
     pub is_main: bool,
+    /// Locked against pruning, e.g. because it lives on removable media
+    pub locked: bool,
+    /// Git would prune it: its directory is gone and it isn't locked
+    pub prunable: bool,
+}
+
+/// Files changed on each side since a branch diverged from its base
+#[derive(Debug, Clone, Default)]
+pub struct DivergedFiles {
+    /// Changed on the branch, including uncommitted and untracked files
+    pub branch: BTreeSet<String>,
+    /// Changed on the base branch since the merge base
+    pub base: BTreeSet<String>,
 }
 
 pub struct GitService;
@@ -32,6 +67,18 @@ impl GitService {
         Repository::open(path).is_ok()
     }
 
+    /// Get the canonical git dir shared by a repository and all its worktrees
+    pub fn common_dir(path: &str) -> Result<PathBuf, GitError> {
+        let repo = Repository::open(path)?;
+        // Linked worktrees have their git dir at <common dir>/worktrees/<name>
+        let git_dir = if repo.is_worktree() {
+            repo.path().ancestors().nth(2).unwrap_or(repo.path())
+        } else {
+            repo.path()
+        };
+        Ok(git_dir.canonicalize()?)
+    }
+
     /// Get the current branch name
     pub fn get_current_branch(path: &str) -> Result<String, GitError> {
         let repo = Repository::open(path)?;
@@ -39,6 +86,13 @@ impl GitService {
         Ok(head.shorthand().unwrap_or("HEAD").to_string())
     }
 
+    /// Get the commit HEAD points to
+    pub fn head_commit(path: &str) -> Result<String, GitError> {
+        let repo = Repository::open(path)?;
+        let commit = repo.head()?.peel_to_commit()?;
+        Ok(commit.id().to_string())
+    }
+
     /// List all worktrees for a repository
     pub fn list_worktrees(path: &str) -> Result<Vec<WorktreeInfo>, GitError> {
         let repo = Repository::open(path)?;
@@ -55,6 +109,8 @@ impl GitService {
             path: main_path.trim_end_matches('/').to_string(),
             branch: Self::get_current_branch(path)?,
             is_main: true,
+            locked: false,
+            prunable: false,
         });
 
         // Additional worktrees
@@ -67,6 +123,8 @@ impl GitService {
                             path: wt_path.to_string(),
                             branch,
                             is_main: false,
+                            locked: matches!(wt.is_locked(), Ok(WorktreeLockStatus::Locked(_))),
+                            prunable: wt.is_prunable(None).unwrap_or(false),
                         });
                     }
                 }
@@ -76,14 +134,45 @@ impl GitService {
         Ok(worktrees)
     }
 
-    /// Add a new worktree
+    /// Add a new worktree. With `sparse_dirs`, only those directories (and
+    /// files at the root) are checked out, using cone-mode sparse checkout.
     pub fn add_worktree(
         repo_path: &str,
         worktree_path: &str,
         branch: &str,
         create_branch: bool,
+        sparse_dirs: &[String],
     ) -> Result<WorktreeInfo, GitError> {
+        Self::add_worktree_cancellable(
+            repo_path,
+            worktree_path,
+            branch,
+            create_branch,
+            sparse_dirs,
+            &CancellationToken::new(),
+        )
+    }
+
+    /// Add a new worktree, stopping when `cancel` is set.
+    ///
+    /// If the worktree cannot be added or the add is cancelled, whatever was
+    /// done so far is undone: the half-created worktree is removed and a
+    /// branch created for it is deleted. Whatever was at the path before,
+    /// such as another worktree, is left alone.
+    pub fn add_worktree_cancellable(
+        repo_path: &str,
+        worktree_path: &str,
+        branch: &str,
+        create_branch: bool,
+        sparse_dirs: &[String],
+        cancel: &CancellationToken,
+    ) -> Result<WorktreeInfo, GitError> {
+        if cancel.is_cancelled() {
+            return Err(GitError::Cancelled);
+        }
         let repo = Repository::open(repo_path)?;
+        let existed = Path::new(worktree_path).exists();
+        let records = Self::worktree_records(&repo);
 
         if create_branch {
             // Create branch from HEAD
@@ -91,6 +180,54 @@ impl GitService {
             repo.branch(branch, &head, false)?;
         }
 
+        let mut registered = false;
+        let added = if sparse_dirs.is_empty() {
+            Self::add_full_worktree(&repo, worktree_path, branch, &mut registered)
+        } else {
+            Self::add_sparse_worktree(
+                repo_path,
+                worktree_path,
+                branch,
+                sparse_dirs,
+                cancel,
+                &mut registered,
+            )
+        };
+        let result = added.and_then(|()| {
+            // libgit2 checks out in one call, so look again once it is done
+            if cancel.is_cancelled() {
+                return Err(GitError::Cancelled);
+            }
+            Ok(WorktreeInfo {
+                path: worktree_path.to_string(),
+                branch: branch.to_string(),
+                is_main: false,
+                locked: false,
+                prunable: false,
+            })
+        });
+
+        if result.is_err() {
+            if !registered {
+                Self::discard_unfinished_records(&repo, &records, worktree_path);
+            }
+            Self::discard_worktree(repo_path, worktree_path, registered, !existed);
+            if create_branch {
+                if let Err(e) = Self::delete_branch(repo_path, branch) {
+                    tracing::warn!("Failed to delete branch {}: {}", branch, e);
+                }
+            }
+        }
+        result
+    }
+
+    /// Sets `registered` once git has recorded the worktree
+    fn add_full_worktree(
+        repo: &Repository,
+        worktree_path: &str,
+        branch: &str,
+        registered: &mut bool,
+    ) -> Result<(), GitError> {
         // Find the branch reference
         let branch_ref = repo.find_branch(branch, BranchType::Local)?;
         let reference = branch_ref.into_reference();
@@ -106,12 +243,152 @@ impl GitService {
             Path::new(worktree_path),
             Some(git2::WorktreeAddOptions::new().reference(Some(&reference))),
         )?;
+        *registered = true;
 
-        Ok(WorktreeInfo {
-            path: worktree_path.to_string(),
-            branch: branch.to_string(),
-            is_main: false,
-        })
+        Ok(())
+    }
+
+    /// Remove a partly added worktree if the add registered it, its directory
+    /// if the add created it, and git's record of it. A worktree the add
+    /// never registered is not touched, as the path may hold another one.
+    fn discard_worktree(repo_path: &str, worktree_path: &str, registered: bool, created_dir: bool) {
+        if registered {
+            let _ = Self::remove_worktree(repo_path, worktree_path);
+        }
+        if created_dir && Path::new(worktree_path).exists() {
+            if let Err(e) = std::fs::remove_dir_all(worktree_path) {
+                tracing::warn!("Failed to remove {}: {}", worktree_path, e);
+            }
+        }
+        let _ = Self::run_git(repo_path, &["worktree", "prune"]);
+    }
+
+    /// Where git keeps its records of the worktrees. A linked worktree's git
+    /// directory names the main one in its `commondir` file.
+    fn worktree_records_dir(repo: &Repository) -> PathBuf {
+        let git_dir = repo.path();
+        let common = std::fs::read_to_string(git_dir.join("commondir"))
+            .map(|dir| git_dir.join(dir.trim()))
+            .unwrap_or_else(|_| git_dir.to_path_buf());
+        common.join("worktrees")
+    }
+
+    /// Names of the worktrees git keeps a record of
+    fn worktree_records(repo: &Repository) -> HashSet<OsString> {
+        std::fs::read_dir(Self::worktree_records_dir(repo))
+            .map(|entries| entries.flatten().map(|e| e.file_name()).collect())
+            .unwrap_or_default()
+    }
+
+    /// Delete the records an add killed midway left of `worktree_path`. Git
+    /// keeps a record locked until the add finishes, so prune passes it over.
+    /// Only records made since `before` are looked at.
+    fn discard_unfinished_records(
+        repo: &Repository,
+        before: &HashSet<OsString>,
+        worktree_path: &str,
+    ) {
+        let target = Path::new(worktree_path)
+            .canonicalize()
+            .unwrap_or_else(|_| PathBuf::from(worktree_path));
+        for name in Self::worktree_records(repo).difference(before) {
+            let record = Self::worktree_records_dir(repo).join(name);
+            // The record points at the worktree's .git, unless the add was
+            // killed before it got that far
+            let ours = match std::fs::read_to_string(record.join("gitdir")) {
+                Ok(gitdir) => Path::new(gitdir.trim()).parent().is_some_and(|dir| {
+                    dir.canonicalize().unwrap_or_else(|_| dir.to_path_buf()) == target
+                }),
+                Err(_) => true,
+            };
+            if ours {
+                if let Err(e) = std::fs::remove_dir_all(&record) {
+                    tracing::warn!("Failed to remove {}: {}", record.display(), e);
+                }
+            }
+        }
+    }
+
+    /// libgit2 has no sparse checkout, so this goes through the git CLI:
+    /// add the worktree without checking out, narrow it, then check out.
+    /// Sets `registered` once git has recorded the worktree.
+    fn add_sparse_worktree(
+        repo_path: &str,
+        worktree_path: &str,
+        branch: &str,
+        sparse_dirs: &[String],
+        cancel: &CancellationToken,
+        registered: &mut bool,
+    ) -> Result<(), GitError> {
+        Self::run_git_cancellable(
+            repo_path,
+            &["worktree", "add", "--no-checkout", worktree_path, branch],
+            cancel,
+        )?;
+        *registered = true;
+
+        let mut set_args = vec!["sparse-checkout", "set", "--cone", "--"];
+        set_args.extend(sparse_dirs.iter().map(String::as_str));
+        let result = Self::run_git_cancellable(worktree_path, &set_args, cancel)
+            .and_then(|_| Self::run_git_cancellable(worktree_path, &["checkout", branch], cancel));
+        if result.is_err() {
+            let _ = Self::run_git(repo_path, &["worktree", "remove", "--force", worktree_path]);
+        }
+        result
+    }
+
+    fn run_git(dir: &str, args: &[&str]) -> Result<(), GitError> {
+        let output = std::process::Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args(args)
+            .output()?;
+        if !output.status.success() {
+            return Err(GitError::Command(format!(
+                "{} failed: {}",
+                args[0],
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+        Ok(())
+    }
+
+    /// Run git, killing it as soon as `cancel` is set
+    fn run_git_cancellable(
+        dir: &str,
+        args: &[&str],
+        cancel: &CancellationToken,
+    ) -> Result<(), GitError> {
+        let mut child = std::process::Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args(args)
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()?;
+        let status = loop {
+            if let Some(status) = child.try_wait()? {
+                break status;
+            }
+            if cancel.is_cancelled() {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(GitError::Cancelled);
+            }
+            std::thread::sleep(CANCEL_POLL_INTERVAL);
+        };
+        if !status.success() {
+            let mut stderr = String::new();
+            if let Some(mut pipe) = child.stderr.take() {
+                let _ = pipe.read_to_string(&mut stderr);
+            }
+            return Err(GitError::Command(format!(
+                "{} failed: {}",
+                args[0],
+                stderr.trim()
+            )));
+        }
+        Ok(())
     }
 
     /// Remove a worktree
@@ -162,6 +439,66 @@ impl GitService {
         Ok(())
     }
 
+    /// Merge a local branch into the branch checked out at `path`,
+    /// fast-forwarding when possible. A conflicting merge is aborted.
+    pub fn merge_branch(path: &str, branch: &str) -> Result<(), GitError> {
+        let repo = Repository::open(path)?;
+        let reference = repo
+            .find_branch(branch, BranchType::Local)?
+            .into_reference();
+        let theirs = repo.reference_to_annotated_commit(&reference)?;
+        let (analysis, _) = repo.merge_analysis(&[&theirs])?;
+
+        if analysis.is_up_to_date() {
+            return Ok(());
+        }
+        if analysis.is_fast_forward() {
+            let target = repo.find_object(theirs.id(), None)?;
+            repo.checkout_tree(&target, Some(CheckoutBuilder::new().safe()))?;
+            repo.head()?
+                .set_target(theirs.id(), &format!("Fast-forward to {}", branch))?;
+            return Ok(());
+        }
+
+        repo.merge(&[&theirs], None, None)?;
+        let mut index = repo.index()?;
+        if index.has_conflicts() {
+            repo.cleanup_state()?;
+            repo.checkout_head(Some(CheckoutBuilder::new().force()))?;
+            return Err(git2::Error::new(
+                ErrorCode::Conflict,
+                ErrorClass::Merge,
+                format!("Merging {} has conflicts", branch),
+            )
+            .into());
+        }
+
+        let tree = repo.find_tree(index.write_tree()?)?;
+        let head = repo.head()?.peel_to_commit()?;
+        let their_commit = repo.find_commit(theirs.id())?;
+        let sig = repo
+            .signature()
+            .or_else(|_| Signature::now("Claude Manager", "claude-manager@localhost"))?;
+        repo.commit(
+            Some("HEAD"),
+            &sig,
+            &sig,
+            &format!("Merge branch '{}'", branch),
+            &tree,
+            &[&head, &their_commit],
+        )?;
+        repo.cleanup_state()?;
+
+        Ok(())
+    }
+
+    /// Delete a local branch
+    pub fn delete_branch(repo_path: &str, branch: &str) -> Result<(), GitError> {
+        let repo = Repository::open(repo_path)?;
+        repo.find_branch(branch, BranchType::Local)?.delete()?;
+        Ok(())
+    }
+
     /// List branches
     pub fn list_branches(path: &str) -> Result<BranchInfo, GitError> {
         let repo = Repository::open(path)?;
@@ -193,22 +530,157 @@ impl GitService {
         })
     }
 
+    /// Fetch every remote, optionally pruning remote branches deleted upstream.
+    ///
+    /// SSH remotes authenticate through ssh-agent; HTTPS remotes use `token`
+    /// when given, falling back to git's credential helpers otherwise.
+    pub fn fetch(path: &str, prune: bool, token: Option<&str>) -> Result<(), GitError> {
+        let repo = Repository::open(path)?;
+        let config = repo.config()?;
+
+        for name in repo.remotes()?.iter().flatten() {
+            let mut remote = repo.find_remote(name)?;
+            let mut attempts = 0;
+            let mut callbacks = RemoteCallbacks::new();
+            callbacks.credentials(|url, username, allowed| {
+                // libgit2 keeps asking while credentials are rejected
+                attempts += 1;
+                if attempts > 3 {
+                    return Err(git2::Error::from_str("Authentication failed"));
+                }
+                if allowed.contains(CredentialType::SSH_KEY) {
+                    return Cred::ssh_key_from_agent(username.unwrap_or("git"));
+                }
+                if allowed.contains(CredentialType::USER_PASS_PLAINTEXT) {
+                    if let Some(token) = token {
+                        return Cred::userpass_plaintext("x-access-token", token);
+                    }
+                    return Cred::credential_helper(&config, url, username);
+                }
+                Cred::default()
+            });
+
+            let mut options = FetchOptions::new();
+            options.remote_callbacks(callbacks);
+            if prune {
+                options.prune(FetchPrune::On);
+            }
+            let refspecs: [&str; 0] = [];
+            remote.fetch(&refspecs, Some(&mut options), None)?;
+        }
+        Ok(())
+    }
+
+    /// Stash uncommitted changes, returning None when there is nothing to stash.
+    ///
+    /// Stashes live in the repository, so every worktree of it sees the same list.
+    pub fn stash_save(
+        path: &str,
+        message: Option<&str>,
+        include_untracked: bool,
+    ) -> Result<Option<StashEntry>, GitError> {
+        let mut repo = Repository::open(path)?;
+        let sig = repo
+            .signature()
+            .or_else(|_| Signature::now("Claude Manager", "claude-manager@localhost"))?;
+        let flags = if include_untracked {
+            StashFlags::INCLUDE_UNTRACKED
+        } else {
+            StashFlags::DEFAULT
+        };
+
+        match repo.stash_save(&sig, message.unwrap_or("Claude Manager stash"), Some(flags)) {
+            Ok(_) => Ok(Self::stash_list(path)?.into_iter().next()),
+            Err(e) if e.code() == ErrorCode::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// List stashes, most recent first
+    pub fn stash_list(path: &str) -> Result<Vec<StashEntry>, GitError> {
+        let mut repo = Repository::open(path)?;
+        let mut entries = Vec::new();
+        repo.stash_foreach(|index, message, oid| {
+            entries.push(StashEntry {
+                index,
+                message: message.to_string(),
+                oid: oid.to_string(),
+            });
+            true
+        })?;
+        Ok(entries)
+    }
+
+    /// Apply a stash and drop it; a stash that conflicts is left in place
+    pub fn stash_pop(path: &str, index: usize) -> Result<(), GitError> {
+        let mut repo = Repository::open(path)?;
+        let mut options = StashApplyOptions::new();
+        repo.stash_pop(index, Some(&mut options))?;
+        Ok(())
+    }
+
     /// Get repository status
     pub fn get_status(path: &str) -> Result<GitStatusInfo, GitError> {
+        Self::get_status_with(path, &GitStatusOptions::default())
+    }
+
+    /// Get repository status, limited and paginated by `options`.
+    ///
+    /// Ignored files are never scanned. Pages follow path order, and a path
+    /// with both staged and unstaged changes counts once.
+    pub fn get_status_with(
+        path: &str,
+        options: &GitStatusOptions,
+    ) -> Result<GitStatusInfo, GitError> {
         let repo = Repository::open(path)?;
         let mut opts = StatusOptions::new();
-        opts.include_untracked(true);
+        opts.include_untracked(!options.skip_untracked)
+            .include_ignored(false);
 
         let statuses = repo.statuses(Some(&mut opts))?;
 
+        // libgit2 ignores the skip-worktree bit, so files left out of a
+        // sparse checkout would otherwise show up as deleted
+        let skipped: HashSet<Vec<u8>> = repo
+            .index()?
+            .iter()
+            .filter(|entry| entry.flags_extended & SKIP_WORKTREE != 0)
+            .map(|entry| entry.path)
+            .collect();
+
+        let changed: Vec<(String, git2::Status)> = statuses
+            .iter()
+            .filter(|entry| {
+                let status = entry.status();
+                if status == git2::Status::WT_DELETED && skipped.contains(entry.path_bytes()) {
+                    return false;
+                }
+                status.is_wt_modified()
+                    || status.is_wt_deleted()
+                    || status.is_index_new()
+                    || status.is_index_modified()
+                    || status.is_index_deleted()
+                    || status.is_wt_new()
+            })
+            .map(|entry| (entry.path().unwrap_or_default().to_string(), entry.status()))
+            .filter(|(file_path, _)| {
+                !options
+                    .exclude
+                    .iter()
+                    .any(|pattern| wildcard_match(pattern, file_path))
+            })
+            .collect();
+
+        let total_entries = changed.len();
+        let end = options.max_entries.map_or(total_entries, |max| {
+            options.offset.saturating_add(max).min(total_entries)
+        });
+
         let mut modified = Vec::new();
         let mut staged = Vec::new();
         let mut untracked = Vec::new();
 
-        for entry in statuses.iter() {
-            let status = entry.status();
-            let file_path = entry.path().unwrap_or_default().to_string();
-
+        for (file_path, status) in changed.into_iter().take(end).skip(options.offset) {
             if status.is_wt_modified() || status.is_wt_deleted() {
                 modified.push(file_path.clone());
             }
@@ -220,21 +692,195 @@ impl GitService {
             }
         }
 
-        let is_clean = modified.is_empty() && staged.is_empty() && untracked.is_empty();
-
         // Calculate ahead/behind from upstream
         let (ahead, behind) = Self::get_ahead_behind(&repo).unwrap_or((0, 0));
 
         Ok(GitStatusInfo {
-            is_clean,
+            is_clean: total_entries == 0,
             ahead,
             behind,
             modified,
             staged,
             untracked,
+            base_branch: None,
+            ahead_of_base: 0,
+            behind_base: 0,
+            total_entries,
+            has_more: end < total_entries,
         })
     }
 
+    /// Get repository status, including how far HEAD has diverged from
+    /// `base_branch` (or `origin/<base_branch>` when there is no local one)
+    pub fn get_status_against(
+        path: &str,
+        base_branch: &str,
+        options: &GitStatusOptions,
+    ) -> Result<GitStatusInfo, GitError> {
+        let mut status = Self::get_status_with(path, options)?;
+        let repo = Repository::open(path)?;
+        if let Ok((ahead, behind)) = Self::ahead_behind_branch(&repo, base_branch) {
+            status.base_branch = Some(base_branch.to_string());
+            status.ahead_of_base = ahead;
+            status.behind_base = behind;
+        }
+        Ok(status)
+    }
+
+    /// Commits reachable from HEAD, newest first. With a base branch, only
+    /// commits that are not on the base are listed.
+    pub fn commit_log(
+        path: &str,
+        base_branch: Option<&str>,
+        limit: usize,
+        skip: usize,
+    ) -> Result<Vec<CommitInfo>, GitError> {
+        let repo = Repository::open(path)?;
+        let mut revwalk = repo.revwalk()?;
+        revwalk.set_sorting(git2::Sort::TIME)?;
+        revwalk.push_head()?;
+        if let Some(base_branch) = base_branch {
+            let base = repo.find_branch(base_branch, BranchType::Local)?;
+            if let Some(oid) = base.get().target() {
+                revwalk.hide(oid)?;
+            }
+        }
+
+        let mut commits = Vec::new();
+        for oid in revwalk.skip(skip).take(limit) {
+            let commit = repo.find_commit(oid?)?;
+            let parent_tree = match commit.parents().next() {
+                Some(parent) => Some(parent.tree()?),
+                None => None,
+            };
+            let diff = repo.diff_tree_to_tree(parent_tree.as_ref(), Some(&commit.tree()?), None)?;
+            let author = commit.author();
+            commits.push(CommitInfo {
+                hash: commit.id().to_string(),
+                author: author.name().unwrap_or_default().to_string(),
+                email: author.email().unwrap_or_default().to_string(),
+                date: chrono::DateTime::from_timestamp(author.when().seconds(), 0)
+                    .unwrap_or_default()
+                    .to_rfc3339(),
+                message: commit.message().unwrap_or_default().trim_end().to_string(),
+                files_changed: diff.deltas().len(),
+            });
+        }
+        Ok(commits)
+    }
+
+    /// Files changed in a worktree and on `base_branch` since their merge base
+    pub fn diverged_files(path: &str, base_branch: &str) -> Result<DivergedFiles, GitError> {
+        let repo = Repository::open(path)?;
+        let (base, fork_tree) = Self::fork_point(&repo, base_branch)?;
+
+        let mut opts = DiffOptions::new();
+        opts.include_untracked(true).recurse_untracked_dirs(true);
+        let branch_diff =
+            repo.diff_tree_to_workdir_with_index(Some(&fork_tree), Some(&mut opts))?;
+        let base_diff = repo.diff_tree_to_tree(Some(&fork_tree), Some(&base.tree()?), None)?;
+
+        Ok(DivergedFiles {
+            branch: Self::diff_paths(&branch_diff),
+            base: Self::diff_paths(&base_diff),
+        })
+    }
+
+    /// Line-level changes in a worktree since it diverged from `base_branch`,
+    /// including uncommitted and untracked files
+    pub fn diff_against_branch(path: &str, base_branch: &str) -> Result<BranchDiff, GitError> {
+        let repo = Repository::open(path)?;
+        let (_, fork_tree) = Self::fork_point(&repo, base_branch)?;
+
+        let mut opts = DiffOptions::new();
+        opts.include_untracked(true)
+            .recurse_untracked_dirs(true)
+            .show_untracked_content(true);
+        let diff = repo.diff_tree_to_workdir_with_index(Some(&fork_tree), Some(&mut opts))?;
+
+        let mut result = BranchDiff::default();
+        for (idx, delta) in diff.deltas().enumerate() {
+            let (insertions, deletions) = match Patch::from_diff(&diff, idx)? {
+                Some(patch) => {
+                    let (_, insertions, deletions) = patch.line_stats()?;
+                    (insertions, deletions)
+                }
+                // Binary files have no line stats
+                None => (0, 0),
+            };
+            let status = match delta.status() {
+                Delta::Added | Delta::Untracked => "added",
+                Delta::Deleted => "deleted",
+                Delta::Renamed => "renamed",
+                _ => "modified",
+            };
+            let path = delta
+                .new_file()
+                .path()
+                .or_else(|| delta.old_file().path())
+                .map(|p| p.to_string_lossy().to_string())
+                .unwrap_or_default();
+
+            result.insertions += insertions;
+            result.deletions += deletions;
+            result.files.push(FileDiffStat {
+                path,
+                status: status.to_string(),
+                insertions,
+                deletions,
+            });
+        }
+
+        diff.print(DiffFormat::Patch, |_, _, line| {
+            if matches!(line.origin(), '+' | '-' | ' ') {
+                result.patch.push(line.origin());
+            }
+            result
+                .patch
+                .push_str(&String::from_utf8_lossy(line.content()));
+            true
+        })?;
+
+        Ok(result)
+    }
+
+    /// Tip of `base_branch` and the tree of its merge base with HEAD
+    fn fork_point<'r>(
+        repo: &'r Repository,
+        base_branch: &str,
+    ) -> Result<(Commit<'r>, Tree<'r>), GitError> {
+        let head = repo.head()?.peel_to_commit()?;
+        let base = repo
+            .find_branch(base_branch, BranchType::Local)?
+            .get()
+            .peel_to_commit()?;
+        let fork_tree = repo
+            .find_commit(repo.merge_base(head.id(), base.id())?)?
+            .tree()?;
+        Ok((base, fork_tree))
+    }
+
+    /// Paths touched by a diff, counting both sides of renames
+    fn diff_paths(diff: &Diff) -> BTreeSet<String> {
+        diff.deltas()
+            .flat_map(|delta| [delta.old_file().path(), delta.new_file().path()])
+            .flatten()
+            .map(|p| p.to_string_lossy().to_string())
+            .collect()
+    }
+
+    /// Get ahead/behind counts from a local branch, or its origin counterpart
+    fn ahead_behind_branch(repo: &Repository, branch: &str) -> Result<(i32, i32), GitError> {
+        let head = repo.head()?.peel_to_commit()?;
+        let base = repo
+            .find_branch(branch, BranchType::Local)
+            .or_else(|_| repo.find_branch(&format!("origin/{}", branch), BranchType::Remote))?
+            .get()
+            .peel_to_commit()?;
+        let (ahead, behind) = repo.graph_ahead_behind(head.id(), base.id())?;
+        Ok((ahead as i32, behind as i32))
+    }
+
     /// Get ahead/behind counts from upstream
     fn get_ahead_behind(repo: &Repository) -> Result<(i32, i32), GitError> {
         let head = repo.head()?;
@@ -262,3 +908,60 @@ impl GitService {
         }
     }
 }
+
+/// Match `text` against a pattern where `*` stands for any run of characters
+pub(crate) fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let parts: Vec<&str> = pattern.split('*').collect();
+    let (first, last) = (parts[0], parts[parts.len() - 1]);
+    if parts.len() == 1 {
+        return pattern == text;
+    }
+    if text.len() < first.len() + last.len() || !text.starts_with(first) || !text.ends_with(last) {
+        return false;
+    }
+
+    let mut rest = &text[first.len()..text.len() - last.len()];
+    for part in &parts[1..parts.len() - 1] {
+        match rest.find(part) {
+            Some(i) => rest = &rest[i + part.len()..],
+            None => return false,
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_discard_unfinished_records_keeps_other_worktrees() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path().join("repo")).unwrap();
+        let records = GitService::worktree_records_dir(&repo);
+        let record = |name: &str, gitdir: Option<&Path>| {
+            std::fs::create_dir_all(records.join(name)).unwrap();
+            std::fs::write(records.join(name).join("locked"), "initializing").unwrap();
+            if let Some(gitdir) = gitdir {
+                let gitdir = format!("{}\n", gitdir.join(".git").display());
+                std::fs::write(records.join(name).join("gitdir"), gitdir).unwrap();
+            }
+        };
+        let path = dir.path().join("feature");
+        std::fs::create_dir_all(&path).unwrap();
+
+        // Older than the add, even if it is at the same path
+        record("feature", Some(&path));
+        let before = GitService::worktree_records(&repo);
+        // Left by the killed add, one before it wrote where the worktree is
+        record("feature1", Some(&path));
+        record("feature2", None);
+        // Added meanwhile somewhere else
+        record("other", Some(&dir.path().join("other")));
+
+        GitService::discard_unfinished_records(&repo, &before, path.to_str().unwrap());
+        let mut left: Vec<_> = GitService::worktree_records(&repo).into_iter().collect();
+        left.sort();
+        assert_eq!(left, ["feature", "other"]);
+    }
+}
@@ -0,0 +1,392 @@
+//! Git watch service for pushing live worktree status
+//!
+//! Each worktree is watched for filesystem changes. Bursts of changes are
+//! debounced, git status is recomputed, and changed statuses are broadcast
+//! so the UI does not have to poll `get_git_status`.
+
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use git2::Repository;
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use parking_lot::Mutex;
+use thiserror::Error;
+use tokio::sync::broadcast;
+
+use crate::db::{DbPool, SettingsRepository, WorktreeRepository};
+use crate::services::worktree_service::{base_branch_key, DEFAULT_BASE_BRANCH};
+use crate::services::GitService;
+use crate::types::{GitStatusInfo, GitStatusOptions, Worktree};
+
+/// Quiet period after the last change before status is recomputed
+const DEBOUNCE: Duration = Duration::from_millis(300);
+/// Upper bound on how long a continuous stream of changes can delay an update
+const MAX_DELAY: Duration = Duration::from_secs(2);
+/// Files in the git dir whose changes affect status
+const GIT_DIR_FILES: [&str; 2] = ["HEAD", "index"];
+
+#[derive(Error, Debug)]
+pub enum GitWatchError {
+    #[error("Failed to watch worktree: {0}")]
+    Watch(String),
+    #[error("Database error: {0}")]
+    Database(String),
+}
+
+/// Git status of a worktree after a change on disk
+#[derive(Debug, Clone)]
+pub struct GitStatusEvent {
+    pub workspace_id: String,
+    pub worktree_id: String,
+    pub status: GitStatusInfo,
+}
+
+/// A status served for a watched worktree, valid until the worktree changes
+struct CachedStatus {
+    base_branch: String,
+    options: GitStatusOptions,
+    status: GitStatusInfo,
+}
+
+type StatusCache = Arc<Mutex<HashMap<String, CachedStatus>>>;
+
+pub struct GitWatchService {
+    worktree_repo: WorktreeRepository,
+    settings_repo: Arc<SettingsRepository>,
+    /// Dropping a watcher stops its debounce thread
+    watchers: Mutex<HashMap<String, RecommendedWatcher>>,
+    status_cache: StatusCache,
+    event_tx: broadcast::Sender<GitStatusEvent>,
+}
+
+impl GitWatchService {
+    pub fn new(pool: DbPool) -> Self {
+        let (event_tx, _) = broadcast::channel(256);
+        Self {
+            worktree_repo: WorktreeRepository::new(pool.clone()),
+            settings_repo: Arc::new(SettingsRepository::new(pool)),
+            watchers: Mutex::new(HashMap::new()),
+            status_cache: Arc::new(Mutex::new(HashMap::new())),
+            event_tx,
+        }
+    }
+
+    /// Subscribe to status changes of watched worktrees
+    pub fn subscribe(&self) -> broadcast::Receiver<GitStatusEvent> {
+        self.event_tx.subscribe()
+    }
+
+    /// Watch every worktree in the database and stop watching removed ones.
+    ///
+    /// Call after worktrees are added or removed. Returns how many worktrees
+    /// are being watched.
+    pub fn sync(&self) -> Result<usize, GitWatchError> {
+        let worktrees = self
+            .worktree_repo
+            .find_all()
+            .map_err(|e| GitWatchError::Database(e.to_string()))?;
+
+        let mut watchers = self.watchers.lock();
+        watchers.retain(|id, _| worktrees.iter().any(|w| &w.id == id));
+        self.status_cache
+            .lock()
+            .retain(|id, _| watchers.contains_key(id));
+
+        for worktree in &worktrees {
+            if watchers.contains_key(&worktree.id) {
+                continue;
+            }
+            match self.watch(worktree) {
+                Ok(watcher) => {
+                    watchers.insert(worktree.id.clone(), watcher);
+                }
+                Err(e) => tracing::warn!("Not watching worktree {}: {}", worktree.id, e),
+            }
+        }
+
+        Ok(watchers.len())
+    }
+
+    /// Check if a worktree is being watched
+    pub fn is_watching(&self, worktree_id: &str) -> bool {
+        self.watchers.lock().contains_key(worktree_id)
+    }
+
+    /// Status cached for a worktree with the same base branch and options
+    pub fn cached_status(
+        &self,
+        worktree_id: &str,
+        base_branch: &str,
+        options: &GitStatusOptions,
+    ) -> Option<GitStatusInfo> {
+        self.status_cache
+            .lock()
+            .get(worktree_id)
+            .filter(|cached| cached.base_branch == base_branch && &cached.options == options)
+            .map(|cached| cached.status.clone())
+    }
+
+    /// Cache a worktree's status until it changes on disk. Worktrees that
+    /// aren't watched are not cached, since nothing would invalidate them.
+    pub fn cache_status(
+        &self,
+        worktree_id: &str,
+        base_branch: &str,
+        options: &GitStatusOptions,
+        status: GitStatusInfo,
+    ) {
+        if !self.is_watching(worktree_id) {
+            return;
+        }
+        self.status_cache.lock().insert(
+            worktree_id.to_string(),
+            CachedStatus {
+                base_branch: base_branch.to_string(),
+                options: options.clone(),
+                status,
+            },
+        );
+    }
+
+    fn watch(&self, worktree: &Worktree) -> Result<RecommendedWatcher, GitWatchError> {
+        // Linked worktrees keep HEAD and index outside the working directory
+        let git_dir = Repository::open(&worktree.path)
+            .map(|repo| repo.path().to_path_buf())
+            .map_err(|e| GitWatchError::Watch(e.to_string()))?;
+
+        let (raw_tx, raw_rx) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(move |res: notify::Result<notify::Event>| {
+            if let Ok(event) = res {
+                let _ = raw_tx.send(event.paths);
+            }
+        })
+        .map_err(|e| GitWatchError::Watch(e.to_string()))?;
+
+        watcher
+            .watch(Path::new(&worktree.path), RecursiveMode::Recursive)
+            .map_err(|e| GitWatchError::Watch(e.to_string()))?;
+        watcher
+            .watch(&git_dir, RecursiveMode::NonRecursive)
+            .map_err(|e| GitWatchError::Watch(e.to_string()))?;
+
+        let worktree = worktree.clone();
+        let settings_repo = self.settings_repo.clone();
+        let status_cache = self.status_cache.clone();
+        let event_tx = self.event_tx.clone();
+        std::thread::Builder::new()
+            .name(format!("git-watch-{}", worktree.id))
+            .spawn(move || {
+                debounce_changes(
+                    raw_rx,
+                    worktree,
+                    git_dir,
+                    settings_repo,
+                    status_cache,
+                    event_tx,
+                )
+            })
+            .map_err(|e| GitWatchError::Watch(e.to_string()))?;
+
+        Ok(watcher)
+    }
+}
+
+/// Recompute status once changes settle, until the watcher is dropped
+fn debounce_changes(
+    rx: mpsc::Receiver<Vec<PathBuf>>,
+    worktree: Worktree,
+    git_dir: PathBuf,
+    settings_repo: Arc<SettingsRepository>,
+    status_cache: StatusCache,
+    event_tx: broadcast::Sender<GitStatusEvent>,
+) {
+    let repo = Repository::open(&worktree.path).ok();
+    let workdir = Path::new(&worktree.path);
+    let relevant = |paths: &[PathBuf]| {
+        let relevant = paths
+            .iter()
+            .any(|path| affects_status(repo.as_ref(), workdir, &git_dir, path));
+        if relevant {
+            // Right away rather than after the debounce, so no stale status is served
+            status_cache.lock().remove(&worktree.id);
+        }
+        relevant
+    };
+    let mut last_status: Option<GitStatusInfo> = None;
+
+    while let Ok(paths) = rx.recv() {
+        let mut changed = relevant(&paths);
+        let started = Instant::now();
+        loop {
+            match rx.recv_timeout(DEBOUNCE) {
+                Ok(paths) => {
+                    changed |= relevant(&paths);
+                    if started.elapsed() >= MAX_DELAY {
+                        break;
+                    }
+                }
+                Err(RecvTimeoutError::Timeout) => break,
+                Err(RecvTimeoutError::Disconnected) => return,
+            }
+        }
+        if !changed {
+            continue;
+        }
+
+        // Read each time so a changed base branch shows up on the next change
+        let base_branch = settings_repo
+            .get_string(
+                &base_branch_key(&worktree.workspace_id),
+                DEFAULT_BASE_BRANCH,
+            )
+            .unwrap_or_else(|_| DEFAULT_BASE_BRANCH.to_string());
+        match GitService::get_status_against(
+            &worktree.path,
+            &base_branch,
+            &GitStatusOptions::default(),
+        ) {
+            Ok(status) if last_status.as_ref() != Some(&status) => {
+                last_status = Some(status.clone());
+                let _ = event_tx.send(GitStatusEvent {
+                    workspace_id: worktree.workspace_id.clone(),
+                    worktree_id: worktree.id.clone(),
+                    status,
+                });
+            }
+            Ok(_) => {}
+            Err(e) => tracing::debug!("Failed to get git status for {}: {}", worktree.id, e),
+        }
+    }
+}
+
+/// Whether a change to `path` can change the worktree's git status
+fn affects_status(repo: Option<&Repository>, workdir: &Path, git_dir: &Path, path: &Path) -> bool {
+    // git2 reports the git dir with a trailing separator
+    if path.parent() == Some(git_dir.components().as_path()) {
+        return path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| GIT_DIR_FILES.contains(&name));
+    }
+
+    let Ok(relative) = path.strip_prefix(workdir) else {
+        return true;
+    };
+    if relative
+        .components()
+        .any(|c| c == Component::Normal(".git".as_ref()))
+    {
+        return false;
+    }
+    !repo.is_some_and(|repo| repo.is_path_ignored(relative).unwrap_or(false))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn init_repo(path: &Path) -> Repository {
+        let repo = Repository::init(path).unwrap();
+        std::fs::write(path.join(".gitignore"), "target/\n").unwrap();
+        {
+            let mut index = repo.index().unwrap();
+            index.add_path(Path::new(".gitignore")).unwrap();
+            index.write().unwrap();
+            let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+            let sig = git2::Signature::now("Test", "test@example.com").unwrap();
+            repo.commit(Some("HEAD"), &sig, &sig, "Initial commit", &tree, &[])
+                .unwrap();
+        }
+        repo
+    }
+
+    fn create_test_service() -> (GitWatchService, TempDir, TempDir) {
+        let (pool, db_dir) = crate::db::test_pool();
+        let repo_dir = tempfile::tempdir().unwrap();
+        init_repo(repo_dir.path());
+
+        let conn = pool.get().unwrap();
+        let path = repo_dir.path().to_str().unwrap();
+        conn.execute(
+            "INSERT INTO workspaces (id, name, path) VALUES ('ws_1', 'Test', ?1)",
+            [path],
+        )
+        .unwrap();
+        conn.execute(
+            r#"INSERT INTO worktrees (id, workspace_id, name, branch, path, is_main)
+               VALUES ('wt_1', 'ws_1', 'main', 'main', ?1, 1)"#,
+            [path],
+        )
+        .unwrap();
+
+        (GitWatchService::new(pool), db_dir, repo_dir)
+    }
+
+    #[test]
+    fn test_affects_status_filters_git_internals_and_ignored_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = init_repo(dir.path());
+        let workdir = dir.path();
+        let git_dir = repo.path().to_path_buf();
+
+        let affects = |path: PathBuf| affects_status(Some(&repo), workdir, &git_dir, &path);
+
+        assert!(affects(workdir.join("src/main.rs")));
+        assert!(affects(git_dir.join("index")));
+        assert!(affects(git_dir.join("HEAD")));
+        assert!(!affects(git_dir.join("index.lock")));
+        assert!(!affects(git_dir.join("objects/ab/cdef")));
+        assert!(!affects(workdir.join("target/debug/app")));
+    }
+
+    #[tokio::test]
+    async fn test_sync_broadcasts_status_on_change() {
+        let (service, _db_dir, repo_dir) = create_test_service();
+        let mut rx = service.subscribe();
+
+        assert_eq!(service.sync().unwrap(), 1);
+        assert!(service.is_watching("wt_1"));
+
+        std::fs::write(repo_dir.path().join("new.txt"), "hello\n").unwrap();
+
+        let event = tokio::time::timeout(Duration::from_secs(10), rx.recv())
+            .await
+            .expect("Should receive a status update")
+            .unwrap();
+        assert_eq!(event.workspace_id, "ws_1");
+        assert_eq!(event.worktree_id, "wt_1");
+        assert!(!event.status.is_clean);
+        assert_eq!(event.status.untracked, vec!["new.txt".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_cached_status_is_dropped_on_change() {
+        let (service, _db_dir, repo_dir) = create_test_service();
+        let options = GitStatusOptions::default();
+        let status = GitService::get_status(repo_dir.path().to_str().unwrap()).unwrap();
+
+        // Nothing would invalidate an unwatched worktree's status
+        service.cache_status("wt_1", "main", &options, status.clone());
+        assert!(service.cached_status("wt_1", "main", &options).is_none());
+
+        service.sync().unwrap();
+        let mut rx = service.subscribe();
+        service.cache_status("wt_1", "main", &options, status.clone());
+        assert_eq!(
+            service.cached_status("wt_1", "main", &options),
+            Some(status)
+        );
+        assert!(service.cached_status("wt_1", "develop", &options).is_none());
+
+        std::fs::write(repo_dir.path().join("new.txt"), "hello\n").unwrap();
+        tokio::time::timeout(Duration::from_secs(10), rx.recv())
+            .await
+            .expect("Should receive a status update")
+            .unwrap();
+        assert!(service.cached_status("wt_1", "main", &options).is_none());
+    }
+}
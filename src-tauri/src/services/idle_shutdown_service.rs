@@ -0,0 +1,362 @@
+//! Idle shutdown service for stopping agents left idle at the prompt
+//!
+//! Agents that sit at the prompt for hours hold on to a CLI process and its
+//! memory for nothing. With idle shutdown on, globally or for one agent, the
+//! process manager's idle monitor warns the agent's clients shortly before
+//! the timeout and then asks for the agent to be stopped, which this service
+//! does. The shutdown is recorded until the agent is started again, which
+//! resumes its session.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use parking_lot::Mutex;
+use thiserror::Error;
+use tokio::sync::broadcast::{self, error::RecvError};
+
+use crate::db::{AgentRepository, DbPool, SettingsRepository};
+use crate::services::{ProcessControl, ProcessEvent, StampedEvent};
+use crate::types::{AgentIdlePolicy, IdleShutdown, IdleShutdownPolicy};
+
+/// Settings key for the global policy
+const POLICY_KEY: &str = "idle_shutdown";
+/// Settings key prefix for an agent's own policy
+const AGENT_POLICY_KEY: &str = "agent_idle_shutdown";
+/// Settings key for the agents stopped for sitting idle
+const SHUTDOWNS_KEY: &str = "idle_shutdowns";
+/// Longest idle timeout accepted, one day
+const MAX_IDLE_MINUTES: u32 = 24 * 60;
+
+#[derive(Error, Debug)]
+pub enum IdleShutdownError {
+    #[error("Agent not found: {0}")]
+    AgentNotFound(String),
+    #[error("Validation error: {0}")]
+    Validation(String),
+    #[error("Database error: {0}")]
+    Database(String),
+}
+
+pub struct IdleShutdownService {
+    settings_repo: SettingsRepository,
+    agent_repo: AgentRepository,
+    process_manager: Arc<dyn ProcessControl>,
+    /// Serializes updates of the stored shutdowns
+    shutdowns_lock: Mutex<()>,
+}
+
+impl IdleShutdownService {
+    pub fn new(pool: DbPool, process_manager: Arc<dyn ProcessControl>) -> Self {
+        Self {
+            settings_repo: SettingsRepository::new(pool.clone()),
+            agent_repo: AgentRepository::new(pool),
+            process_manager,
+            shutdowns_lock: Mutex::new(()),
+        }
+    }
+
+    /// The policy of agents without one of their own; off until set
+    pub fn get_policy(&self) -> Result<IdleShutdownPolicy, IdleShutdownError> {
+        Ok(self.read_policy(POLICY_KEY)?.unwrap_or_default())
+    }
+
+    /// Change the global policy, including for running agents that follow it
+    pub fn set_policy(
+        &self,
+        policy: IdleShutdownPolicy,
+    ) -> Result<IdleShutdownPolicy, IdleShutdownError> {
+        validate(&policy)?;
+        self.write_policy(POLICY_KEY, Some(&policy))?;
+
+        for agent_id in self.process_manager.running_agent_ids() {
+            self.apply(&agent_id)?;
+        }
+        Ok(policy)
+    }
+
+    /// An agent's own policy and the one it runs with
+    pub fn get_agent_policy(&self, agent_id: &str) -> Result<AgentIdlePolicy, IdleShutdownError> {
+        self.require_agent(agent_id)?;
+        let policy = self.read_policy(&agent_policy_key(agent_id))?;
+        let effective = match policy {
+            Some(policy) => policy,
+            None => self.get_policy()?,
+        };
+        Ok(AgentIdlePolicy {
+            agent_id: agent_id.to_string(),
+            policy,
+            effective,
+        })
+    }
+
+    /// Give an agent its own policy, or make it follow the global one with
+    /// None. A running agent switches over immediately.
+    pub fn set_agent_policy(
+        &self,
+        agent_id: &str,
+        policy: Option<IdleShutdownPolicy>,
+    ) -> Result<AgentIdlePolicy, IdleShutdownError> {
+        self.require_agent(agent_id)?;
+        if let Some(policy) = &policy {
+            validate(policy)?;
+        }
+        self.write_policy(&agent_policy_key(agent_id), policy.as_ref())?;
+
+        if self.process_manager.is_running(agent_id) {
+            self.apply(agent_id)?;
+        }
+        self.get_agent_policy(agent_id)
+    }
+
+    /// Hand the process manager the idle timeout of an agent about to run
+    pub fn apply(&self, agent_id: &str) -> Result<(), IdleShutdownError> {
+        let policy = self.get_agent_policy(agent_id)?.effective;
+        let timeout = policy
+            .enabled
+            .then(|| Duration::from_secs(u64::from(policy.idle_minutes) * 60));
+        self.process_manager.set_idle_timeout(agent_id, timeout);
+        Ok(())
+    }
+
+    /// Agents stopped for sitting idle that weren't started since
+    pub fn list_shutdowns(&self) -> Result<Vec<IdleShutdown>, IdleShutdownError> {
+        let value = self
+            .settings_repo
+            .get(SHUTDOWNS_KEY)
+            .map_err(|e| IdleShutdownError::Database(e.to_string()))?;
+        Ok(value
+            .and_then(|v| serde_json::from_str(&v).ok())
+            .unwrap_or_default())
+    }
+
+    /// Forget an agent's idle shutdown, once it runs again or is deleted
+    pub fn clear_shutdown(&self, agent_id: &str) -> Result<(), IdleShutdownError> {
+        let _guard = self.shutdowns_lock.lock();
+        let mut shutdowns = self.list_shutdowns()?;
+        let before = shutdowns.len();
+        shutdowns.retain(|s| s.agent_id != agent_id);
+        if shutdowns.len() != before {
+            self.store_shutdowns(&shutdowns)?;
+        }
+        Ok(())
+    }
+
+    /// Stop an agent whose idle timeout passed and record why it stopped.
+    /// None when the agent produced output since it had been idle for `idle`,
+    /// and is left running.
+    pub fn shut_down(
+        &self,
+        agent_id: &str,
+        idle: Duration,
+    ) -> Result<Option<IdleShutdown>, IdleShutdownError> {
+        let agent = self
+            .agent_repo
+            .find_by_id(agent_id)
+            .map_err(|e| IdleShutdownError::Database(e.to_string()))?
+            .ok_or_else(|| IdleShutdownError::AgentNotFound(agent_id.to_string()))?;
+        // The timeout may have been sent just before the agent got busy again
+        let active_since = self
+            .process_manager
+            .snapshot(agent_id)
+            .last_activity
+            .is_some_and(|at| {
+                (chrono::Utc::now() - at)
+                    .to_std()
+                    .map_or(true, |quiet| quiet < idle)
+            });
+        if active_since {
+            tracing::debug!("Agent {} is active again; not stopping it", agent_id);
+            return Ok(None);
+        }
+        let shutdown = IdleShutdown {
+            agent_id: agent_id.to_string(),
+            session_id: agent.session_id,
+            idle_minutes: (idle.as_secs() / 60) as u32,
+            stopped_at: chrono::Utc::now().to_rfc3339(),
+        };
+        {
+            let _guard = self.shutdowns_lock.lock();
+            let mut shutdowns = self.list_shutdowns()?;
+            shutdowns.retain(|s| s.agent_id != agent_id);
+            shutdowns.push(shutdown.clone());
+            self.store_shutdowns(&shutdowns)?;
+        }
+
+        // The session is saved at the prompt, so there is nothing to lose by
+        // killing the process rather than waiting for the CLI to exit
+        if let Err(e) = self.process_manager.stop_agent(agent_id, true) {
+            tracing::debug!("Idle agent {} already stopped: {}", agent_id, e);
+        }
+        tracing::info!(
+            "Stopped agent {} after {} minutes idle",
+            agent_id,
+            shutdown.idle_minutes
+        );
+        Ok(Some(shutdown))
+    }
+
+    /// Stop agents as their idle timeouts pass, until the channel closes
+    pub async fn run(&self, mut rx: broadcast::Receiver<StampedEvent>) {
+        loop {
+            match rx.recv().await {
+                Ok(StampedEvent {
+                    event:
+                        ProcessEvent::IdleTimeout {
+                            agent_id,
+                            idle_secs,
+                        },
+                    ..
+                }) => {
+                    if let Err(e) = self.shut_down(&agent_id, Duration::from_secs(idle_secs)) {
+                        tracing::warn!("Failed to stop idle agent {}: {}", agent_id, e);
+                    }
+                }
+                Ok(_) => {}
+                // The idle monitor asks again while the agent keeps running
+                Err(RecvError::Lagged(_)) => {}
+                Err(RecvError::Closed) => break,
+            }
+        }
+    }
+
+    fn require_agent(&self, agent_id: &str) -> Result<(), IdleShutdownError> {
+        self.agent_repo
+            .find_by_id(agent_id)
+            .map_err(|e| IdleShutdownError::Database(e.to_string()))?
+            .map(|_| ())
+            .ok_or_else(|| IdleShutdownError::AgentNotFound(agent_id.to_string()))
+    }
+
+    fn read_policy(&self, key: &str) -> Result<Option<IdleShutdownPolicy>, IdleShutdownError> {
+        let value = self
+            .settings_repo
+            .get(key)
+            .map_err(|e| IdleShutdownError::Database(e.to_string()))?;
+        Ok(value.and_then(|v| serde_json::from_str(&v).ok()))
+    }
+
+    fn write_policy(
+        &self,
+        key: &str,
+        policy: Option<&IdleShutdownPolicy>,
+    ) -> Result<(), IdleShutdownError> {
+        match policy {
+            Some(policy) => {
+                let value = serde_json::to_string(policy).unwrap_or_default();
+                self.settings_repo.set(key, &value, "json")
+            }
+            None => self.settings_repo.delete(key),
+        }
+        .map_err(|e| IdleShutdownError::Database(e.to_string()))
+    }
+
+    fn store_shutdowns(&self, shutdowns: &[IdleShutdown]) -> Result<(), IdleShutdownError> {
+        let value = serde_json::to_string(shutdowns).unwrap_or_default();
+        self.settings_repo
+            .set(SHUTDOWNS_KEY, &value, "json")
+            .map_err(|e| IdleShutdownError::Database(e.to_string()))
+    }
+}
+
+fn agent_policy_key(agent_id: &str) -> String {
+    format!("{}:{}", AGENT_POLICY_KEY, agent_id)
+}
+
+fn validate(policy: &IdleShutdownPolicy) -> Result<(), IdleShutdownError> {
+    if !(1..=MAX_IDLE_MINUTES).contains(&policy.idle_minutes) {
+        return Err(IdleShutdownError::Validation(format!(
+            "Idle timeout must be between 1 and {} minutes",
+            MAX_IDLE_MINUTES
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::ProcessManager;
+    use tempfile::TempDir;
+
+    fn create_test_service() -> (IdleShutdownService, TempDir) {
+        let (pool, dir) = crate::db::test_pool();
+        let conn = pool.get().unwrap();
+        conn.execute_batch(
+            r#"
+            INSERT INTO workspaces (id, name, path) VALUES ('ws_1', 'Test', '/tmp/test');
+            INSERT INTO worktrees (id, workspace_id, name, branch, path)
+                VALUES ('wt_1', 'ws_1', 'main', 'main', '/tmp/test');
+            INSERT INTO agents (id, worktree_id, name, session_id)
+                VALUES ('ag_1', 'wt_1', 'Agent', 'sess-1');
+        "#,
+        )
+        .unwrap();
+
+        let process_manager = Arc::new(ProcessManager::new("claude".to_string()));
+        (IdleShutdownService::new(pool, process_manager), dir)
+    }
+
+    fn policy(enabled: bool, idle_minutes: u32) -> IdleShutdownPolicy {
+        IdleShutdownPolicy {
+            enabled,
+            idle_minutes,
+        }
+    }
+
+    #[test]
+    fn test_agent_policy_overrides_global_one() {
+        let (service, _dir) = create_test_service();
+        assert_eq!(service.get_policy().unwrap(), IdleShutdownPolicy::default());
+
+        service.set_policy(policy(true, 30)).unwrap();
+        let agent = service.get_agent_policy("ag_1").unwrap();
+        assert_eq!(agent.policy, None);
+        assert_eq!(agent.effective, policy(true, 30));
+
+        let agent = service
+            .set_agent_policy("ag_1", Some(policy(false, 30)))
+            .unwrap();
+        assert_eq!(agent.effective, policy(false, 30));
+        let agent = service.set_agent_policy("ag_1", None).unwrap();
+        assert_eq!(agent.effective, policy(true, 30));
+
+        assert!(matches!(
+            service.set_policy(policy(true, 0)),
+            Err(IdleShutdownError::Validation(_))
+        ));
+        assert!(matches!(
+            service.set_agent_policy("ag_1", Some(policy(true, MAX_IDLE_MINUTES + 1))),
+            Err(IdleShutdownError::Validation(_))
+        ));
+        assert!(matches!(
+            service.get_agent_policy("ag_missing"),
+            Err(IdleShutdownError::AgentNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_shutdown_is_kept_until_cleared() {
+        let (service, _dir) = create_test_service();
+
+        let shutdown = service
+            .shut_down("ag_1", Duration::from_secs(45 * 60 + 20))
+            .unwrap()
+            .unwrap();
+        assert_eq!(shutdown.session_id.as_deref(), Some("sess-1"));
+        assert_eq!(shutdown.idle_minutes, 45);
+        // A second timeout replaces the first record
+        service
+            .shut_down("ag_1", Duration::from_secs(3600))
+            .unwrap();
+        let shutdowns = service.list_shutdowns().unwrap();
+        assert_eq!(shutdowns.len(), 1);
+        assert_eq!(shutdowns[0].idle_minutes, 60);
+
+        service.clear_shutdown("ag_1").unwrap();
+        assert!(service.list_shutdowns().unwrap().is_empty());
+        assert!(matches!(
+            service.shut_down("ag_missing", Duration::from_secs(60)),
+            Err(IdleShutdownError::AgentNotFound(_))
+        ));
+    }
+}
@@ -0,0 +1,357 @@
+//! Job service for long-running operations
+//!
+//! Commands that can take many seconds, such as creating a worktree, hand
+//! their work to `spawn` and return the queued job at once. The work runs on
+//! its own thread, reports progress through a `JobHandle` and checks it for
+//! cancellation between steps. Every change is saved to the jobs table and
+//! published, so the WebSocket server can push it to the windows.
+
+use std::collections::HashMap;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::Arc;
+
+use parking_lot::Mutex;
+use thiserror::Error;
+use tokio::sync::broadcast;
+
+use crate::db::{DbPool, JobRepository};
+use crate::types::{Job, JobStatus};
+use crate::util::cancel::CancellationToken;
+use crate::util::id::new_id;
+
+/// Error recorded on jobs a restart cut off
+const INTERRUPTED_ERROR: &str = "Interrupted by restart";
+
+/// Error recorded on jobs whose work panicked
+const PANICKED_ERROR: &str = "Job panicked";
+
+#[derive(Error, Debug)]
+pub enum JobError {
+    #[error("Job not found: {0}")]
+    NotFound(String),
+    #[error("Job already finished: {0}")]
+    Finished(String),
+    #[error("Database error: {0}")]
+    Database(String),
+}
+
+pub struct JobService {
+    job_repo: Arc<JobRepository>,
+    /// Cancellation tokens of jobs whose work is still running
+    cancel_tokens: Arc<Mutex<HashMap<String, CancellationToken>>>,
+    event_tx: broadcast::Sender<Job>,
+}
+
+impl JobService {
+    pub fn new(pool: DbPool) -> Self {
+        let (event_tx, _) = broadcast::channel(256);
+        Self {
+            job_repo: Arc::new(JobRepository::new(pool)),
+            cancel_tokens: Arc::new(Mutex::new(HashMap::new())),
+            event_tx,
+        }
+    }
+
+    /// Subscribe to job changes
+    pub fn subscribe(&self) -> broadcast::Receiver<Job> {
+        self.event_tx.subscribe()
+    }
+
+    /// Fail the jobs a previous run left queued or running. Call once at
+    /// startup, before any job is spawned; returns how many there were.
+    pub fn fail_interrupted(&self) -> Result<usize, JobError> {
+        self.job_repo
+            .fail_unfinished(INTERRUPTED_ERROR, &chrono::Utc::now().to_rfc3339())
+            .map_err(|e| JobError::Database(e.to_string()))
+    }
+
+    /// Get a job by ID
+    pub fn get_job(&self, id: &str) -> Result<Job, JobError> {
+        self.job_repo
+            .find_by_id(id)
+            .map_err(|e| JobError::Database(e.to_string()))?
+            .ok_or_else(|| JobError::NotFound(id.to_string()))
+    }
+
+    /// Run `work` on a background thread and return the queued job.
+    ///
+    /// The job completes with what `work` returns. When `work` fails after
+    /// the job was cancelled, the job ends cancelled rather than failed, so
+    /// work that notices a cancellation should undo what it did and bail out.
+    pub fn spawn<F>(&self, kind: &str, target_id: Option<&str>, work: F) -> Result<Job, JobError>
+    where
+        F: FnOnce(&JobHandle) -> Result<serde_json::Value, String> + Send + 'static,
+    {
+        let now = chrono::Utc::now().to_rfc3339();
+        let job = self
+            .job_repo
+            .create(&Job {
+                id: new_id("job"),
+                kind: kind.to_string(),
+                target_id: target_id.map(str::to_string),
+                status: JobStatus::Queued,
+                progress: 0,
+                message: None,
+                result: None,
+                error: None,
+                created_at: now.clone(),
+                updated_at: now,
+                finished_at: None,
+            })
+            .map_err(|e| JobError::Database(e.to_string()))?;
+        let _ = self.event_tx.send(job.clone());
+
+        let cancel = CancellationToken::new();
+        self.cancel_tokens
+            .lock()
+            .insert(job.id.clone(), cancel.clone());
+        let handle = JobHandle {
+            id: job.id.clone(),
+            cancel,
+            job_repo: self.job_repo.clone(),
+            event_tx: self.event_tx.clone(),
+        };
+        let cancel_tokens = self.cancel_tokens.clone();
+        std::thread::spawn(move || {
+            // A panic would otherwise leave the job running forever
+            let outcome = panic::catch_unwind(AssertUnwindSafe(|| work(&handle)))
+                .unwrap_or_else(|_| Err(PANICKED_ERROR.to_string()));
+            cancel_tokens.lock().remove(&handle.id);
+            handle.finish(outcome);
+        });
+
+        Ok(job)
+    }
+
+    /// Ask a job to stop. Work stops at its next cancellation check, or
+    /// sooner when it passed the job's token down to git, so the job
+    /// returned may still be running.
+    pub fn cancel_job(&self, id: &str) -> Result<Job, JobError> {
+        let job = self.get_job(id)?;
+        if job.status.is_finished() {
+            return Err(JobError::Finished(id.to_string()));
+        }
+
+        match self.cancel_tokens.lock().get(id) {
+            Some(cancel) => cancel.cancel(),
+            // Finished between the lookup and now
+            None => return Err(JobError::Finished(id.to_string())),
+        }
+
+        Ok(job)
+    }
+}
+
+/// Passed to a job's work to report progress and check for cancellation
+pub struct JobHandle {
+    id: String,
+    cancel: CancellationToken,
+    job_repo: Arc<JobRepository>,
+    event_tx: broadcast::Sender<Job>,
+}
+
+impl JobHandle {
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Whether the job was asked to stop
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.is_cancelled()
+    }
+
+    /// The job's cancellation token, for operations that can stop midway
+    pub fn token(&self) -> &CancellationToken {
+        &self.cancel
+    }
+
+    /// Report how far along the job is (0 to 100) and what it is doing
+    pub fn progress(&self, percent: u8, message: &str) {
+        let now = chrono::Utc::now().to_rfc3339();
+        if let Err(e) =
+            self.job_repo
+                .update_progress(&self.id, percent.min(100), Some(message), &now)
+        {
+            tracing::warn!("Failed to record progress of job {}: {}", self.id, e);
+            return;
+        }
+        self.publish();
+    }
+
+    fn finish(&self, outcome: Result<serde_json::Value, String>) {
+        let now = chrono::Utc::now().to_rfc3339();
+        let saved = match &outcome {
+            Ok(result) => self.job_repo.finish(
+                &self.id,
+                JobStatus::Completed,
+                Some(&result.to_string()),
+                None,
+                &now,
+            ),
+            Err(_) if self.is_cancelled() => {
+                self.job_repo
+                    .finish(&self.id, JobStatus::Cancelled, None, None, &now)
+            }
+            Err(error) => {
+                self.job_repo
+                    .finish(&self.id, JobStatus::Failed, None, Some(error), &now)
+            }
+        };
+        if let Err(e) = saved {
+            tracing::warn!("Failed to record end of job {}: {}", self.id, e);
+            return;
+        }
+        self.publish();
+    }
+
+    fn publish(&self) {
+        match self.job_repo.find_by_id(&self.id) {
+            // No subscribers just means no window is connected yet
+            Ok(Some(job)) => {
+                let _ = self.event_tx.send(job);
+            }
+            Ok(None) => {}
+            Err(e) => tracing::warn!("Failed to load job {}: {}", self.id, e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    use tempfile::TempDir;
+
+    fn create_test_service() -> (JobService, TempDir) {
+        let (pool, dir) = crate::db::test_pool();
+        (JobService::new(pool), dir)
+    }
+
+    /// Wait for the job to publish a finished state
+    async fn finished(rx: &mut broadcast::Receiver<Job>, id: &str) -> Job {
+        loop {
+            let job = tokio::time::timeout(Duration::from_secs(10), rx.recv())
+                .await
+                .expect("Should receive a job update")
+                .unwrap();
+            if job.id == id && job.status.is_finished() {
+                return job;
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_job_reports_progress_and_result() {
+        let (service, _dir) = create_test_service();
+        let mut rx = service.subscribe();
+
+        let job = service
+            .spawn("test", Some("ws_1"), |job| {
+                job.progress(50, "Halfway");
+                Ok(serde_json::json!({ "answer": 42 }))
+            })
+            .unwrap();
+        assert_eq!(job.status, JobStatus::Queued);
+        assert_eq!(job.target_id.as_deref(), Some("ws_1"));
+
+        let queued = rx.recv().await.unwrap();
+        assert_eq!(queued.status, JobStatus::Queued);
+        let running = rx.recv().await.unwrap();
+        assert_eq!(running.status, JobStatus::Running);
+        assert_eq!(running.progress, 50);
+        assert_eq!(running.message.as_deref(), Some("Halfway"));
+
+        let done = finished(&mut rx, &job.id).await;
+        assert_eq!(done.status, JobStatus::Completed);
+        assert_eq!(done.progress, 100);
+        assert_eq!(done.result, Some(serde_json::json!({ "answer": 42 })));
+        assert!(done.finished_at.is_some());
+        assert_eq!(
+            service.get_job(&job.id).unwrap().status,
+            JobStatus::Completed
+        );
+        assert!(matches!(
+            service.cancel_job(&job.id),
+            Err(JobError::Finished(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_failed_job_keeps_error() {
+        let (service, _dir) = create_test_service();
+        let mut rx = service.subscribe();
+
+        let job = service
+            .spawn("test", None, |_| Err("Disk full".to_string()))
+            .unwrap();
+
+        let done = finished(&mut rx, &job.id).await;
+        assert_eq!(done.status, JobStatus::Failed);
+        assert_eq!(done.error.as_deref(), Some("Disk full"));
+        assert!(done.result.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_panicking_job_fails() {
+        let (service, _dir) = create_test_service();
+        let mut rx = service.subscribe();
+
+        let job = service.spawn("test", None, |_| panic!("Boom")).unwrap();
+
+        let done = finished(&mut rx, &job.id).await;
+        assert_eq!(done.status, JobStatus::Failed);
+        assert_eq!(done.error.as_deref(), Some(PANICKED_ERROR));
+        assert!(service.cancel_job(&job.id).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_job_stops_at_next_check() {
+        let (service, _dir) = create_test_service();
+        let mut rx = service.subscribe();
+        let (started_tx, started_rx) = mpsc::channel();
+
+        let job = service
+            .spawn("test", None, move |job| {
+                started_tx.send(()).unwrap();
+                while !job.is_cancelled() {
+                    std::thread::sleep(Duration::from_millis(5));
+                }
+                Err("Cancelled".to_string())
+            })
+            .unwrap();
+        started_rx.recv_timeout(Duration::from_secs(10)).unwrap();
+
+        service.cancel_job(&job.id).expect("Should cancel job");
+
+        let done = finished(&mut rx, &job.id).await;
+        assert_eq!(done.status, JobStatus::Cancelled);
+        assert!(done.error.is_none());
+    }
+
+    #[test]
+    fn test_unfinished_jobs_fail_on_restart() {
+        let (service, _dir) = create_test_service();
+        let (release_tx, release_rx) = mpsc::channel::<()>();
+
+        let job = service
+            .spawn("test", None, move |_| {
+                let _ = release_rx.recv();
+                Ok(serde_json::Value::Null)
+            })
+            .unwrap();
+
+        assert_eq!(service.fail_interrupted().unwrap(), 1);
+        let interrupted = service.get_job(&job.id).unwrap();
+        assert_eq!(interrupted.status, JobStatus::Failed);
+        assert_eq!(interrupted.error.as_deref(), Some(INTERRUPTED_ERROR));
+        assert_eq!(service.fail_interrupted().unwrap(), 0);
+        drop(release_tx);
+
+        assert!(matches!(
+            service.get_job("job_missing"),
+            Err(JobError::NotFound(_))
+        ));
+    }
+}
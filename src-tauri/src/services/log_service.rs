@@ -0,0 +1,273 @@
+//! Log service for capturing backend logs
+//!
+//! Besides stdout, tracing events go to a size-rotated file under the data
+//! directory and to an in-memory ring of recent records, which the UI reads
+//! so users can see spawn failures and git errors when reporting problems.
+//! The file is opened once the data directory is known; events logged
+//! before that are only kept in memory.
+
+use std::collections::VecDeque;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use parking_lot::Mutex;
+use tracing::field::{Field, Visit};
+use tracing::{Event, Subscriber};
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::layer::{Context, Layer};
+
+use crate::types::{LogLevel, LogRecord};
+
+/// Records kept in memory
+const LOG_RING_CAPACITY: usize = 2_000;
+/// Log file size that triggers rotation (5 MB)
+const LOG_FILE_MAX_BYTES: u64 = 5 * 1_024 * 1_024;
+/// Rotated files kept besides the current one
+const LOG_FILES_KEPT: usize = 3;
+const LOG_FILE_NAME: &str = "claude-manager.log";
+
+pub struct LogService {
+    records: Arc<Mutex<VecDeque<LogRecord>>>,
+    file: Arc<Mutex<RollingFile>>,
+}
+
+impl LogService {
+    pub fn new() -> Self {
+        Self {
+            records: Arc::new(Mutex::new(VecDeque::new())),
+            file: Arc::new(Mutex::new(RollingFile::new(LOG_FILE_MAX_BYTES))),
+        }
+    }
+
+    /// Layer that copies each event into the in-memory ring
+    pub fn capture_layer(&self) -> LogCaptureLayer {
+        LogCaptureLayer {
+            records: self.records.clone(),
+        }
+    }
+
+    /// Writer for a fmt layer, appending to the rolling log file
+    pub fn file_writer(&self) -> LogFileWriter {
+        LogFileWriter(self.file.clone())
+    }
+
+    /// Start writing the log file in `dir`
+    pub fn set_log_dir(&self, dir: PathBuf) -> std::io::Result<()> {
+        self.file.lock().open(dir)
+    }
+
+    /// Most recent records at `level` or more severe, oldest first
+    pub fn recent(&self, level: LogLevel, limit: usize) -> Vec<LogRecord> {
+        let records = self.records.lock();
+        let mut recent: Vec<LogRecord> = records
+            .iter()
+            .rev()
+            .filter(|r| r.level <= level)
+            .take(limit)
+            .cloned()
+            .collect();
+        recent.reverse();
+        recent
+    }
+}
+
+impl Default for LogService {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub struct LogCaptureLayer {
+    records: Arc<Mutex<VecDeque<LogRecord>>>,
+}
+
+impl<S: Subscriber> Layer<S> for LogCaptureLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let mut visitor = MessageVisitor::default();
+        event.record(&mut visitor);
+
+        let metadata = event.metadata();
+        let record = LogRecord {
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            level: LogLevel::from(*metadata.level()),
+            target: metadata.target().to_string(),
+            message: visitor.finish(),
+        };
+
+        let mut records = self.records.lock();
+        if records.len() == LOG_RING_CAPACITY {
+            records.pop_front();
+        }
+        records.push_back(record);
+    }
+}
+
+/// Collects an event's message and its other fields as `key=value`
+#[derive(Default)]
+struct MessageVisitor {
+    message: String,
+    fields: Vec<String>,
+}
+
+impl MessageVisitor {
+    fn finish(self) -> String {
+        std::iter::once(self.message)
+            .chain(self.fields)
+            .filter(|part| !part.is_empty())
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+impl Visit for MessageVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.message = value.to_string();
+        } else {
+            self.fields.push(format!("{}={}", field.name(), value));
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "message" {
+            self.message = format!("{:?}", value);
+        } else {
+            self.fields.push(format!("{}={:?}", field.name(), value));
+        }
+    }
+}
+
+/// Log file that is renamed to `.1`, `.2`, ... once it grows too large
+struct RollingFile {
+    dir: Option<PathBuf>,
+    file: Option<File>,
+    size: u64,
+    max_bytes: u64,
+}
+
+impl RollingFile {
+    fn new(max_bytes: u64) -> Self {
+        Self {
+            dir: None,
+            file: None,
+            size: 0,
+            max_bytes,
+        }
+    }
+
+    fn open(&mut self, dir: PathBuf) -> std::io::Result<()> {
+        std::fs::create_dir_all(&dir)?;
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(dir.join(LOG_FILE_NAME))?;
+        self.size = file.metadata()?.len();
+        self.file = Some(file);
+        self.dir = Some(dir);
+        Ok(())
+    }
+
+    fn rotate(&mut self) -> std::io::Result<()> {
+        let Some(dir) = self.dir.clone() else {
+            return Ok(());
+        };
+        self.file = None;
+
+        let rotated = |n: usize| dir.join(format!("{}.{}", LOG_FILE_NAME, n));
+        let _ = std::fs::remove_file(rotated(LOG_FILES_KEPT));
+        for n in (1..LOG_FILES_KEPT).rev() {
+            let _ = std::fs::rename(rotated(n), rotated(n + 1));
+        }
+        std::fs::rename(dir.join(LOG_FILE_NAME), rotated(1))?;
+        self.open(dir)
+    }
+
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<()> {
+        if self.file.is_none() {
+            return Ok(());
+        }
+        if self.size > 0 && self.size + buf.len() as u64 > self.max_bytes {
+            self.rotate()?;
+        }
+        if let Some(file) = self.file.as_mut() {
+            file.write_all(buf)?;
+            self.size += buf.len() as u64;
+        }
+        Ok(())
+    }
+}
+
+/// Shared handle to the rolling log file
+#[derive(Clone)]
+pub struct LogFileWriter(Arc<Mutex<RollingFile>>);
+
+impl Write for LogFileWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().write(buf)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self.0.lock().file.as_mut() {
+            Some(file) => file.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<'a> MakeWriter<'a> for LogFileWriter {
+    type Writer = LogFileWriter;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        self.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    #[test]
+    fn test_capture_layer_keeps_recent_records() {
+        let service = LogService::new();
+        let subscriber = tracing_subscriber::registry().with(service.capture_layer());
+
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::info!("Database initialized");
+            tracing::warn!(agent_id = "ag_1", "Failed to spawn agent");
+            tracing::error!("Git error: {}", "conflict");
+        });
+
+        let all = service.recent(LogLevel::Trace, 10);
+        assert_eq!(all.len(), 3);
+        assert_eq!(all[0].message, "Database initialized");
+        assert_eq!(all[1].message, "Failed to spawn agent agent_id=ag_1");
+
+        let problems = service.recent(LogLevel::Warn, 10);
+        let levels: Vec<LogLevel> = problems.iter().map(|r| r.level).collect();
+        assert_eq!(levels, [LogLevel::Warn, LogLevel::Error]);
+
+        let last = service.recent(LogLevel::Trace, 1);
+        assert_eq!(last[0].message, "Git error: conflict");
+    }
+
+    #[test]
+    fn test_rolling_file_rotates_when_full() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut file = RollingFile::new(10);
+        file.write(b"dropped before open\n").unwrap();
+        file.open(dir.path().to_path_buf()).unwrap();
+
+        for line in ["first\n", "second\n", "third\n"] {
+            file.write(line.as_bytes()).unwrap();
+        }
+
+        let read = |name: &str| std::fs::read_to_string(dir.path().join(name)).unwrap();
+        assert_eq!(read(LOG_FILE_NAME), "third\n");
+        assert_eq!(read(&format!("{}.1", LOG_FILE_NAME)), "second\n");
+        assert_eq!(read(&format!("{}.2", LOG_FILE_NAME)), "first\n");
+    }
+}
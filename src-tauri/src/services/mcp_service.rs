@@ -0,0 +1,395 @@
+//! MCP service for managing a worktree's MCP server configuration
+//!
+//! Servers live in `.mcp.json` at the worktree root, which the Claude CLI
+//! reads on start, so every agent in the worktree gets the same tools.
+//! Other top-level keys in the file, and unknown keys in server entries,
+//! are preserved when it is rewritten.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::Duration;
+
+use thiserror::Error;
+use tokio::io::AsyncReadExt;
+use tokio::process::Command;
+
+use crate::db::{DbPool, WorktreeRepository};
+use crate::types::{McpServer, McpServerCheck, McpServerConfig};
+
+const MCP_CONFIG_FILE: &str = ".mcp.json";
+/// A stdio server still running after this long is considered launched
+const LAUNCH_CHECK_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Error, Debug)]
+pub enum McpError {
+    #[error("Worktree not found: {0}")]
+    WorktreeNotFound(String),
+    #[error("MCP server not found: {0}")]
+    ServerNotFound(String),
+    #[error("Validation error: {0}")]
+    Validation(String),
+    #[error("Invalid .mcp.json: {0}")]
+    InvalidConfig(String),
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Database error: {0}")]
+    Database(String),
+}
+
+pub struct McpService {
+    worktree_repo: WorktreeRepository,
+}
+
+impl McpService {
+    pub fn new(pool: DbPool) -> Self {
+        Self {
+            worktree_repo: WorktreeRepository::new(pool),
+        }
+    }
+
+    /// MCP servers configured for a worktree, sorted by name
+    pub fn list_servers(&self, worktree_id: &str) -> Result<Vec<McpServer>, McpError> {
+        let path = self.config_path(worktree_id)?;
+        let config = read_config(&path)?;
+        Ok(to_servers(servers_from(&config)?))
+    }
+
+    /// Replace a worktree's MCP servers
+    pub fn set_servers(
+        &self,
+        worktree_id: &str,
+        servers: BTreeMap<String, McpServerConfig>,
+    ) -> Result<Vec<McpServer>, McpError> {
+        for (name, server) in &servers {
+            validate_server(name, server)?;
+        }
+
+        let path = self.config_path(worktree_id)?;
+        let mut config = read_config(&path)?;
+        config["mcpServers"] =
+            serde_json::to_value(&servers).map_err(|e| McpError::InvalidConfig(e.to_string()))?;
+        write_config(&path, &config)?;
+
+        Ok(to_servers(servers))
+    }
+
+    /// Add a server to a worktree's configuration
+    pub fn add_server(
+        &self,
+        worktree_id: &str,
+        name: &str,
+        server: McpServerConfig,
+    ) -> Result<Vec<McpServer>, McpError> {
+        let mut servers = self.server_map(worktree_id)?;
+        if servers.contains_key(name) {
+            return Err(McpError::Validation(format!(
+                "MCP server already exists: {}",
+                name
+            )));
+        }
+        servers.insert(name.to_string(), server);
+        self.set_servers(worktree_id, servers)
+    }
+
+    /// Remove a server from a worktree's configuration
+    pub fn remove_server(&self, worktree_id: &str, name: &str) -> Result<Vec<McpServer>, McpError> {
+        let mut servers = self.server_map(worktree_id)?;
+        if servers.remove(name).is_none() {
+            return Err(McpError::ServerNotFound(name.to_string()));
+        }
+        self.set_servers(worktree_id, servers)
+    }
+
+    /// Check that each configured server launches.
+    ///
+    /// Stdio servers are started in the worktree and pass if they are still
+    /// running, or exited cleanly, after a short wait. Remote servers are
+    /// only checked for a well-formed URL.
+    pub async fn check_servers(&self, worktree_id: &str) -> Result<Vec<McpServerCheck>, McpError> {
+        let worktree_path = self.worktree_path(worktree_id)?;
+        let servers = self.server_map(worktree_id)?;
+
+        let checks = servers.iter().map(|(name, server)| {
+            let worktree_path = worktree_path.clone();
+            async move {
+                let result = match validate_server(name, server) {
+                    Ok(()) if is_remote(server) => Ok(()),
+                    Ok(()) => check_launch(server, &worktree_path).await,
+                    Err(e) => Err(e.to_string()),
+                };
+                McpServerCheck {
+                    name: name.clone(),
+                    ok: result.is_ok(),
+                    error: result.err(),
+                }
+            }
+        });
+
+        Ok(futures::future::join_all(checks).await)
+    }
+
+    fn server_map(&self, worktree_id: &str) -> Result<BTreeMap<String, McpServerConfig>, McpError> {
+        let path = self.config_path(worktree_id)?;
+        servers_from(&read_config(&path)?)
+    }
+
+    fn worktree_path(&self, worktree_id: &str) -> Result<PathBuf, McpError> {
+        self.worktree_repo
+            .find_by_id(worktree_id)
+            .map_err(|e| McpError::Database(e.to_string()))?
+            .map(|worktree| PathBuf::from(worktree.path))
+            .ok_or_else(|| McpError::WorktreeNotFound(worktree_id.to_string()))
+    }
+
+    fn config_path(&self, worktree_id: &str) -> Result<PathBuf, McpError> {
+        Ok(self.worktree_path(worktree_id)?.join(MCP_CONFIG_FILE))
+    }
+}
+
+/// The whole `.mcp.json`, or an empty object when there is none
+fn read_config(path: &Path) -> Result<serde_json::Value, McpError> {
+    if !path.exists() {
+        return Ok(serde_json::json!({}));
+    }
+    let content = std::fs::read_to_string(path)?;
+    let config: serde_json::Value =
+        serde_json::from_str(&content).map_err(|e| McpError::InvalidConfig(e.to_string()))?;
+    if !config.is_object() {
+        return Err(McpError::InvalidConfig(
+            "expected a JSON object".to_string(),
+        ));
+    }
+    Ok(config)
+}
+
+fn write_config(path: &Path, config: &serde_json::Value) -> Result<(), McpError> {
+    let content =
+        serde_json::to_string_pretty(config).map_err(|e| McpError::InvalidConfig(e.to_string()))?;
+    std::fs::write(path, content + "\n")?;
+    Ok(())
+}
+
+fn servers_from(config: &serde_json::Value) -> Result<BTreeMap<String, McpServerConfig>, McpError> {
+    match config.get("mcpServers") {
+        Some(servers) => serde_json::from_value(servers.clone())
+            .map_err(|e| McpError::InvalidConfig(format!("mcpServers: {}", e))),
+        None => Ok(BTreeMap::new()),
+    }
+}
+
+fn to_servers(servers: BTreeMap<String, McpServerConfig>) -> Vec<McpServer> {
+    servers
+        .into_iter()
+        .map(|(name, config)| McpServer { name, config })
+        .collect()
+}
+
+fn is_remote(server: &McpServerConfig) -> bool {
+    matches!(server.transport.as_deref(), Some("http") | Some("sse"))
+}
+
+fn validate_server(name: &str, server: &McpServerConfig) -> Result<(), McpError> {
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err(McpError::Validation(format!(
+            "MCP server name must be letters, digits, '-' or '_': {:?}",
+            name
+        )));
+    }
+
+    match server.transport.as_deref() {
+        None | Some("stdio") => {
+            if server
+                .command
+                .as_deref()
+                .map_or(true, |c| c.trim().is_empty())
+            {
+                return Err(McpError::Validation(format!(
+                    "MCP server {} needs a command",
+                    name
+                )));
+            }
+        }
+        Some("http") | Some("sse") => {
+            let url = server.url.as_deref().unwrap_or_default();
+            if !url.starts_with("http://") && !url.starts_with("https://") {
+                return Err(McpError::Validation(format!(
+                    "MCP server {} needs an http(s) URL",
+                    name
+                )));
+            }
+        }
+        Some(other) => {
+            return Err(McpError::Validation(format!(
+                "MCP server {} has unknown type: {}",
+                name, other
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Start a stdio server and see whether it stays up
+async fn check_launch(server: &McpServerConfig, worktree_path: &Path) -> Result<(), String> {
+    let command = server.command.as_deref().unwrap_or_default();
+    let mut child = Command::new(command)
+        .args(&server.args)
+        .envs(&server.env)
+        .current_dir(worktree_path)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| format!("Failed to launch {}: {}", command, e))?;
+
+    let status = match tokio::time::timeout(LAUNCH_CHECK_TIMEOUT, child.wait()).await {
+        Ok(status) => status.map_err(|e| e.to_string())?,
+        Err(_) => {
+            let _ = child.kill().await;
+            return Ok(());
+        }
+    };
+    if status.success() {
+        return Ok(());
+    }
+
+    let mut stderr = String::new();
+    if let Some(mut pipe) = child.stderr.take() {
+        let _ = pipe.read_to_string(&mut stderr).await;
+    }
+    let code = status
+        .code()
+        .map_or_else(|| "a signal".to_string(), |c| format!("code {}", c));
+    match stderr.lines().find(|l| !l.trim().is_empty()) {
+        Some(line) => Err(format!("Exited with {}: {}", code, line.trim())),
+        None => Err(format!("Exited with {}", code)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn create_test_service() -> (McpService, TempDir) {
+        let (pool, dir) = crate::db::test_pool();
+        let worktree_path = dir.path().join("worktree");
+        std::fs::create_dir(&worktree_path).unwrap();
+
+        let conn = pool.get().unwrap();
+        conn.execute(
+            "INSERT INTO workspaces (id, name, path) VALUES ('ws_1', 'Test', '/tmp/test')",
+            [],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO worktrees (id, workspace_id, name, branch, path)
+             VALUES ('wt_1', 'ws_1', 'main', 'main', ?)",
+            [worktree_path.to_string_lossy()],
+        )
+        .unwrap();
+
+        (McpService::new(pool), dir)
+    }
+
+    fn stdio(command: &str, args: &[&str]) -> McpServerConfig {
+        McpServerConfig {
+            command: Some(command.to_string()),
+            args: args.iter().map(|a| a.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_add_and_remove_servers_preserves_other_keys() {
+        let (service, dir) = create_test_service();
+        let config_path = dir.path().join("worktree").join(MCP_CONFIG_FILE);
+        std::fs::write(
+            &config_path,
+            r#"{"other": true, "mcpServers": {"docs": {"type": "http", "url": "https://example.com/mcp", "timeout": 30}}}"#,
+        )
+        .unwrap();
+
+        let servers = service
+            .add_server("wt_1", "files", stdio("npx", &["-y", "server-files"]))
+            .unwrap();
+        let names: Vec<&str> = servers.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, ["docs", "files"]);
+        assert!(matches!(
+            service.add_server("wt_1", "files", stdio("npx", &[])),
+            Err(McpError::Validation(_))
+        ));
+
+        service.remove_server("wt_1", "files").unwrap();
+        assert!(matches!(
+            service.remove_server("wt_1", "files"),
+            Err(McpError::ServerNotFound(_))
+        ));
+
+        let config: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&config_path).unwrap()).unwrap();
+        assert_eq!(config["other"], true);
+        assert_eq!(config["mcpServers"]["docs"]["timeout"], 30);
+        assert!(config["mcpServers"].get("files").is_none());
+    }
+
+    #[test]
+    fn test_set_servers_validates_entries() {
+        let (service, _dir) = create_test_service();
+        assert!(service.list_servers("wt_1").unwrap().is_empty());
+
+        let invalid = [
+            ("bad name", stdio("npx", &[])),
+            ("no-command", McpServerConfig::default()),
+            (
+                "no-url",
+                McpServerConfig {
+                    transport: Some("sse".to_string()),
+                    ..Default::default()
+                },
+            ),
+        ];
+        for (name, server) in invalid {
+            let servers = BTreeMap::from([(name.to_string(), server)]);
+            assert!(matches!(
+                service.set_servers("wt_1", servers),
+                Err(McpError::Validation(_))
+            ));
+        }
+
+        assert!(matches!(
+            service.list_servers("wt_missing"),
+            Err(McpError::WorktreeNotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_check_servers_reports_launch_failures() {
+        let (service, _dir) = create_test_service();
+        let servers = BTreeMap::from([
+            ("running".to_string(), stdio("sleep", &["30"])),
+            (
+                "failing".to_string(),
+                stdio("sh", &["-c", "echo 'missing token' >&2; exit 3"]),
+            ),
+            ("missing".to_string(), stdio("no-such-mcp-server", &[])),
+        ]);
+        service.set_servers("wt_1", servers).unwrap();
+
+        let results = service.check_servers("wt_1").await.unwrap();
+        let result = |name: &str| results.iter().find(|r| r.name == name).unwrap();
+
+        assert!(result("running").ok);
+        assert!(!result("failing").ok);
+        assert_eq!(
+            result("failing").error.as_deref(),
+            Some("Exited with code 3: missing token")
+        );
+        assert!(!result("missing").ok);
+    }
+}
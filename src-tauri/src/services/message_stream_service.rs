@@ -0,0 +1,363 @@
+//! Message stream service for assembling agent output into messages
+//!
+//! While an agent writes to its terminal, the text is collected into an
+//! incomplete assistant message. Every flush interval the message is saved
+//! and the text it gained is published as an `agent:output` delta carrying
+//! the message ID. The message is completed once the agent goes idle, waits
+//! for input or exits; output after that starts the next message.
+
+use chrono::Utc;
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use thiserror::Error;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+use crate::db::{DbPool, MessageRepository};
+use crate::services::process_service::DB_TIMESTAMP_FORMAT;
+use crate::services::{ProcessControl, ProcessEvent, StampedEvent};
+use crate::types::{AgentStatus, Message, MessageRole};
+use crate::util::ansi::strip_ansi_escapes;
+use crate::util::id::new_id;
+
+/// How often streaming messages are saved and their new text published
+const FLUSH_INTERVAL: Duration = Duration::from_millis(500);
+/// Raw output a message collects before it is completed and the next begun,
+/// since every flush turns all of it into text again
+const MAX_MESSAGE_BYTES: usize = 256 * 1024;
+
+#[derive(Error, Debug)]
+pub enum MessageStreamError {
+    #[error("Database error: {0}")]
+    Database(String),
+}
+
+/// Output collected for an agent's current message
+#[derive(Default)]
+struct OpenMessage {
+    /// Set once the output held some text and the message was saved
+    message_id: Option<String>,
+    /// Terminal output as read, escape sequences and all
+    raw: Vec<u8>,
+    /// Bytes of text already saved and published
+    sent: usize,
+}
+
+pub struct MessageStreamService {
+    message_repo: MessageRepository,
+    process_manager: Arc<dyn ProcessControl>,
+    /// Current message per agent
+    open: Mutex<HashMap<String, OpenMessage>>,
+}
+
+impl MessageStreamService {
+    pub fn new(pool: DbPool, process_manager: Arc<dyn ProcessControl>) -> Self {
+        Self {
+            message_repo: MessageRepository::new(pool),
+            process_manager,
+            open: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Complete the messages a previous run left streaming. Call once at
+    /// startup; returns how many there were.
+    pub fn complete_interrupted(&self) -> Result<usize, MessageStreamError> {
+        self.message_repo
+            .complete_all()
+            .map_err(|e| MessageStreamError::Database(e.to_string()))
+    }
+
+    /// Add terminal output to the agent's current message
+    pub fn append(&self, agent_id: &str, output: &[u8]) {
+        self.open
+            .lock()
+            .entry(agent_id.to_string())
+            .or_default()
+            .raw
+            .extend_from_slice(output);
+    }
+
+    /// Save and publish the text every current message gained since the
+    /// last flush
+    pub fn flush(&self) -> Result<(), MessageStreamError> {
+        let mut open = self.open.lock();
+        let mut result = Ok(());
+        open.retain(|agent_id, message| {
+            let full = message.raw.len() >= MAX_MESSAGE_BYTES;
+            if let Err(e) = self.flush_message(agent_id, message, full) {
+                result = Err(e);
+            }
+            !full
+        });
+        result
+    }
+
+    /// Save the agent's current message as complete, if it has one
+    pub fn complete(&self, agent_id: &str) -> Result<(), MessageStreamError> {
+        let Some(mut message) = self.open.lock().remove(agent_id) else {
+            return Ok(());
+        };
+        self.flush_message(agent_id, &mut message, true)
+    }
+
+    fn flush_message(
+        &self,
+        agent_id: &str,
+        message: &mut OpenMessage,
+        complete: bool,
+    ) -> Result<(), MessageStreamError> {
+        let text = output_text(&message.raw);
+        let message_id = match &message.message_id {
+            Some(_) if text.len() == message.sent && !complete => return Ok(()),
+            Some(id) => {
+                self.message_repo
+                    .update_content(id, &text, complete)
+                    .map_err(|e| MessageStreamError::Database(e.to_string()))?;
+                id.clone()
+            }
+            // Nothing but escape sequences and blank space so far
+            None if text.trim().is_empty() => return Ok(()),
+            None => {
+                let id = new_id("msg");
+                self.message_repo
+                    .insert_missing(&[Message {
+                        id: id.clone(),
+                        agent_id: agent_id.to_string(),
+                        role: MessageRole::Assistant,
+                        content: text.clone(),
+                        token_count: None,
+                        tool_name: None,
+                        tool_input: None,
+                        tool_output: None,
+                        created_at: Utc::now().format(DB_TIMESTAMP_FORMAT).to_string(),
+                        is_complete: complete,
+                        seq: 0,
+                    }])
+                    .map_err(|e| MessageStreamError::Database(e.to_string()))?;
+                message.message_id = Some(id.clone());
+                id
+            }
+        };
+
+        self.process_manager
+            .emit_output(agent_id, &message_id, &text[message.sent..], complete);
+        message.sent = text.len();
+        Ok(())
+    }
+
+    /// Assemble messages from agent output until the event channel closes
+    pub async fn run(&self, mut rx: broadcast::Receiver<StampedEvent>) {
+        let (chunk_tx, mut chunk_rx) = mpsc::unbounded_channel();
+        let mut readers: HashMap<String, JoinHandle<()>> = HashMap::new();
+        let mut interval = tokio::time::interval(FLUSH_INTERVAL);
+        loop {
+            tokio::select! {
+                event = rx.recv() => match event {
+                    Ok(stamped) => self.apply_event(&stamped.event, &mut readers, &chunk_tx),
+                    Err(RecvError::Lagged(n)) => {
+                        tracing::warn!("Message stream lagged by {} process events", n);
+                    }
+                    Err(RecvError::Closed) => break,
+                },
+                Some((agent_id, chunk)) = chunk_rx.recv() => {
+                    self.append(&agent_id, &chunk);
+                }
+                _ = interval.tick() => {
+                    if let Err(e) = self.flush() {
+                        tracing::warn!("Failed to save streaming messages: {}", e);
+                    }
+                }
+            }
+        }
+    }
+
+    fn apply_event(
+        &self,
+        event: &ProcessEvent,
+        readers: &mut HashMap<String, JoinHandle<()>>,
+        chunk_tx: &mpsc::UnboundedSender<(String, Vec<u8>)>,
+    ) {
+        let agent_id = match event {
+            ProcessEvent::Status {
+                agent_id,
+                status: AgentStatus::Running,
+                ..
+            } => {
+                readers.retain(|_, reader| !reader.is_finished());
+                if !readers.contains_key(agent_id) {
+                    if let Some(reader) = self.read_output(agent_id, chunk_tx.clone()) {
+                        readers.insert(agent_id.clone(), reader);
+                    }
+                }
+                return;
+            }
+            ProcessEvent::Status { agent_id, .. } => agent_id,
+            ProcessEvent::Exit { agent_id, .. } => {
+                // The next run gets a new output channel
+                if let Some(reader) = readers.remove(agent_id) {
+                    reader.abort();
+                }
+                agent_id
+            }
+            _ => return,
+        };
+        if let Err(e) = self.complete(agent_id) {
+            tracing::warn!("Failed to complete message of {}: {}", agent_id, e);
+        }
+    }
+
+    /// Forward an agent's terminal output to the run loop
+    fn read_output(
+        &self,
+        agent_id: &str,
+        chunk_tx: mpsc::UnboundedSender<(String, Vec<u8>)>,
+    ) -> Option<JoinHandle<()>> {
+        let (mut output_rx, _) = self.process_manager.subscribe_pty_output(agent_id)?;
+        let agent_id = agent_id.to_string();
+        Some(tokio::spawn(async move {
+            loop {
+                match output_rx.recv().await {
+                    Ok(chunk) => {
+                        if chunk_tx.send((agent_id.clone(), chunk)).is_err() {
+                            break;
+                        }
+                    }
+                    Err(RecvError::Lagged(n)) => {
+                        tracing::debug!("Message stream skipped {} chunks of {}", n, agent_id);
+                    }
+                    Err(RecvError::Closed) => break,
+                }
+            }
+        }))
+    }
+}
+
+/// Plain text of terminal output. Only grows as output is added: a
+/// character split across reads is left out until its last byte arrives,
+/// and an unfinished escape sequence produces no text.
+fn output_text(raw: &[u8]) -> String {
+    let complete = match std::str::from_utf8(raw) {
+        Ok(text) => text,
+        Err(e) if e.error_len().is_none() => {
+            std::str::from_utf8(&raw[..e.valid_up_to()]).unwrap_or_default()
+        }
+        Err(_) => return strip_ansi_escapes(&String::from_utf8_lossy(raw)).replace('\r', ""),
+    };
+    strip_ansi_escapes(complete).replace('\r', "")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::ProcessManager;
+    use tempfile::TempDir;
+
+    fn create_test_service() -> (MessageStreamService, Arc<ProcessManager>, DbPool, TempDir) {
+        let (pool, dir) = crate::db::test_pool();
+        let conn = pool.get().unwrap();
+        conn.execute_batch(
+            r#"
+            INSERT INTO workspaces (id, name, path) VALUES ('ws_1', 'Test', '/tmp/test');
+            INSERT INTO worktrees (id, workspace_id, name, branch, path, is_main)
+                VALUES ('wt_1', 'ws_1', 'main', 'main', '/tmp/test', 1);
+            INSERT INTO agents (id, worktree_id, name) VALUES ('ag_1', 'wt_1', 'Agent');
+        "#,
+        )
+        .unwrap();
+
+        let process_manager = Arc::new(ProcessManager::new("claude".to_string()));
+        let service = MessageStreamService::new(pool.clone(), process_manager.clone());
+        (service, process_manager, pool, dir)
+    }
+
+    /// (message_id, content, is_complete) of the output deltas received
+    fn deltas(rx: &mut broadcast::Receiver<StampedEvent>) -> Vec<(String, String, bool)> {
+        let mut deltas = Vec::new();
+        while let Ok(stamped) = rx.try_recv() {
+            if let ProcessEvent::Output {
+                message_id,
+                content,
+                is_complete,
+                ..
+            } = stamped.event
+            {
+                deltas.push((message_id, content, is_complete));
+            }
+        }
+        deltas
+    }
+
+    #[test]
+    fn test_output_streams_into_one_message_until_complete() {
+        let (service, process_manager, pool, _dir) = create_test_service();
+        let repo = MessageRepository::new(pool);
+        let mut rx = process_manager.subscribe();
+
+        // Escape sequences alone don't start a message
+        service.append("ag_1", b"\x1b[?25l\x1b[2K");
+        service.flush().unwrap();
+        assert!(deltas(&mut rx).is_empty());
+        assert!(repo.find_page("ag_1", 10, None).unwrap().0.is_empty());
+
+        service.append("ag_1", b"\x1b[1mHello\x1b[0m, w");
+        service.flush().unwrap();
+        service.append("ag_1", "or\u{2014}".as_bytes());
+        service.flush().unwrap();
+        // Nothing new to publish
+        service.flush().unwrap();
+
+        let (page, _) = repo.find_page("ag_1", 10, None).unwrap();
+        assert_eq!(page.len(), 1);
+        assert_eq!(page[0].role, MessageRole::Assistant);
+        assert_eq!(page[0].content, "Hello, wor\u{2014}");
+        assert!(!page[0].is_complete);
+
+        service.append("ag_1", b"ld\r\n");
+        service.complete("ag_1").unwrap();
+        let message_id = page[0].id.clone();
+        assert_eq!(
+            deltas(&mut rx),
+            [
+                (message_id.clone(), "Hello, w".to_string(), false),
+                (message_id.clone(), "or\u{2014}".to_string(), false),
+                (message_id.clone(), "ld\n".to_string(), true),
+            ]
+        );
+        let (page, _) = repo.find_page("ag_1", 10, None).unwrap();
+        assert_eq!(page[0].content, "Hello, wor\u{2014}ld\n");
+        assert!(page[0].is_complete);
+
+        // Output after completion starts the next message
+        service.append("ag_1", b"Next");
+        service.complete("ag_1").unwrap();
+        let (page, _) = repo.find_page("ag_1", 10, None).unwrap();
+        assert_eq!(page.len(), 2);
+        assert_ne!(page[1].id, message_id);
+        assert_eq!(page[1].content, "Next");
+    }
+
+    #[test]
+    fn test_interrupted_messages_are_completed() {
+        let (service, _process_manager, pool, _dir) = create_test_service();
+        service.append("ag_1", b"Half an answer");
+        service.flush().unwrap();
+
+        assert_eq!(service.complete_interrupted().unwrap(), 1);
+        let (page, _) = MessageRepository::new(pool)
+            .find_page("ag_1", 10, None)
+            .unwrap();
+        assert!(page[0].is_complete);
+    }
+
+    #[test]
+    fn test_output_text_holds_back_split_characters() {
+        let dash = "\u{2014}".as_bytes();
+        assert_eq!(output_text(&[b"ab", &dash[..2]].concat()), "ab");
+        assert_eq!(output_text(&[b"ab", dash].concat()), "ab\u{2014}");
+        assert_eq!(output_text(b"ab\x1b[3"), "ab");
+        assert_eq!(output_text(b"ab\x1b[31mc\r\n"), "abc\n");
+    }
+}
@@ -0,0 +1,234 @@
+//! Prometheus metrics for monitoring the agent fleet
+//!
+//! Values are gathered on each scrape; nothing is aggregated in the background.
+
+use std::fmt::Write;
+
+use crate::types::AgentStatus;
+
+/// Point-in-time values exported by the `/metrics` endpoint
+#[derive(Debug, Default)]
+pub struct MetricsSnapshot {
+    /// Agents with a live process
+    pub running_agents: usize,
+    /// Non-deleted agents per persisted status
+    pub agents_by_status: Vec<(AgentStatus, i64)>,
+    /// PTY output bytes per agent since the app started
+    pub pty_output_bytes: Vec<(String, u64)>,
+    /// Connected event WebSocket clients
+    pub ws_event_clients: usize,
+    /// Connected terminal WebSocket clients
+    pub ws_pty_clients: usize,
+    /// Unsent messages per event WebSocket client
+    pub ws_client_queue_depths: Vec<(String, usize)>,
+    /// Event WebSocket clients dropped for not answering pings
+    pub ws_dead_clients: u64,
+    /// Database connections currently checked out of the pool
+    pub db_pool_in_use: u32,
+    /// Maximum number of pooled database connections
+    pub db_pool_max: u32,
+    pub usage_input_tokens_today: i64,
+    pub usage_output_tokens_today: i64,
+}
+
+impl MetricsSnapshot {
+    /// Render in the Prometheus text exposition format (version 0.0.4)
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+
+        metric_header(
+            &mut out,
+            "ccmanager_agents_running",
+            "gauge",
+            "Agents with a running process",
+        );
+        let _ = writeln!(out, "ccmanager_agents_running {}", self.running_agents);
+
+        metric_header(
+            &mut out,
+            "ccmanager_agents",
+            "gauge",
+            "Agents by persisted status",
+        );
+        for status in [
+            AgentStatus::Running,
+            AgentStatus::Waiting,
+            AgentStatus::Error,
+            AgentStatus::Idle,
+        ] {
+            let count: i64 = self
+                .agents_by_status
+                .iter()
+                .filter(|(s, _)| *s == status)
+                .map(|(_, count)| count)
+                .sum();
+            let _ = writeln!(
+                out,
+                "ccmanager_agents{{status=\"{}\"}} {}",
+                status.as_str(),
+                count
+            );
+        }
+
+        metric_header(
+            &mut out,
+            "ccmanager_pty_output_bytes_total",
+            "counter",
+            "Terminal output bytes read per agent",
+        );
+        for (agent_id, bytes) in &self.pty_output_bytes {
+            let _ = writeln!(
+                out,
+                "ccmanager_pty_output_bytes_total{{agent_id=\"{}\"}} {}",
+                escape_label(agent_id),
+                bytes
+            );
+        }
+
+        metric_header(
+            &mut out,
+            "ccmanager_ws_clients",
+            "gauge",
+            "Connected WebSocket clients",
+        );
+        let _ = writeln!(
+            out,
+            "ccmanager_ws_clients{{kind=\"events\"}} {}",
+            self.ws_event_clients
+        );
+        let _ = writeln!(
+            out,
+            "ccmanager_ws_clients{{kind=\"pty\"}} {}",
+            self.ws_pty_clients
+        );
+
+        metric_header(
+            &mut out,
+            "ccmanager_ws_client_queue_depth",
+            "gauge",
+            "Messages queued for an event WebSocket client but not yet sent",
+        );
+        for (client_id, depth) in &self.ws_client_queue_depths {
+            let _ = writeln!(
+                out,
+                "ccmanager_ws_client_queue_depth{{client_id=\"{}\"}} {}",
+                escape_label(client_id),
+                depth
+            );
+        }
+        metric_header(
+            &mut out,
+            "ccmanager_ws_dead_clients_total",
+            "counter",
+            "Event WebSocket clients dropped for not answering pings",
+        );
+        let _ = writeln!(
+            out,
+            "ccmanager_ws_dead_clients_total {}",
+            self.ws_dead_clients
+        );
+
+        metric_header(
+            &mut out,
+            "ccmanager_db_pool_connections_in_use",
+            "gauge",
+            "Database connections checked out of the pool",
+        );
+        let _ = writeln!(
+            out,
+            "ccmanager_db_pool_connections_in_use {}",
+            self.db_pool_in_use
+        );
+        metric_header(
+            &mut out,
+            "ccmanager_db_pool_connections_max",
+            "gauge",
+            "Maximum database pool size",
+        );
+        let _ = writeln!(
+            out,
+            "ccmanager_db_pool_connections_max {}",
+            self.db_pool_max
+        );
+
+        metric_header(
+            &mut out,
+            "ccmanager_usage_tokens_today",
+            "gauge",
+            "Tokens used today",
+        );
+        let _ = writeln!(
+            out,
+            "ccmanager_usage_tokens_today{{direction=\"input\"}} {}",
+            self.usage_input_tokens_today
+        );
+        let _ = writeln!(
+            out,
+            "ccmanager_usage_tokens_today{{direction=\"output\"}} {}",
+            self.usage_output_tokens_today
+        );
+
+        out
+    }
+}
+
+fn metric_header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+/// Escape a label value per the exposition format
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_includes_all_statuses() {
+        let snapshot = MetricsSnapshot {
+            running_agents: 2,
+            agents_by_status: vec![(AgentStatus::Running, 2), (AgentStatus::Idle, 3)],
+            ..Default::default()
+        };
+
+        let text = snapshot.render();
+
+        assert!(text.contains("ccmanager_agents_running 2\n"));
+        assert!(text.contains("ccmanager_agents{status=\"running\"} 2\n"));
+        assert!(text.contains("ccmanager_agents{status=\"waiting\"} 0\n"));
+        assert!(text.contains("ccmanager_agents{status=\"idle\"} 3\n"));
+        assert!(text.contains("# TYPE ccmanager_pty_output_bytes_total counter\n"));
+    }
+
+    #[test]
+    fn test_render_includes_websocket_client_health() {
+        let snapshot = MetricsSnapshot {
+            ws_client_queue_depths: vec![("client-1".to_string(), 7)],
+            ws_dead_clients: 3,
+            ..Default::default()
+        };
+
+        let text = snapshot.render();
+
+        assert!(text.contains("ccmanager_ws_client_queue_depth{client_id=\"client-1\"} 7\n"));
+        assert!(text.contains("ccmanager_ws_dead_clients_total 3\n"));
+    }
+
+    #[test]
+    fn test_render_escapes_labels() {
+        let snapshot = MetricsSnapshot {
+            pty_output_bytes: vec![("agent_\"1\"".to_string(), 42)],
+            ..Default::default()
+        };
+
+        assert!(snapshot
+            .render()
+            .contains("ccmanager_pty_output_bytes_total{agent_id=\"agent_\\\"1\\\"\"} 42\n"));
+    }
+}
@@ -4,19 +4,80 @@
 //! between the command layer and the database/process layers.
 
 pub mod agent_service;
+pub mod authorization_service;
+pub mod backup_service;
+pub mod bootstrap_service;
+pub mod change_feed_service;
 pub mod claude_api_service;
+pub mod claude_login_service;
+pub mod claude_md_service;
+pub mod environment_service;
+pub mod git_ops;
 pub mod git_service;
+pub mod git_watch_service;
+pub mod idle_shutdown_service;
+pub mod job_service;
+pub mod log_service;
+pub mod mcp_service;
+pub mod message_stream_service;
+pub mod metrics;
+pub mod network_status_service;
+pub mod onboarding_service;
+pub mod process_control;
 pub mod process_service;
+pub mod profile_service;
+pub mod pull_request_service;
+pub mod remote_access_service;
+pub mod resource_monitor_service;
+pub mod schedule_service;
+pub mod session_snapshot_service;
+pub mod settings_sync_service;
+pub mod snippet_service;
+pub mod status_sync_service;
+pub mod task_group_service;
 pub mod usage_service;
 pub mod websocket_server;
+pub mod workspace_refresh_service;
 pub mod workspace_service;
 pub mod worktree_service;
 
 pub use agent_service::{AgentError, AgentService};
+pub use authorization_service::{AuthorizationError, AuthorizationService, CallerContext};
+pub use backup_service::{BackupError, BackupService};
+pub use bootstrap_service::{BootstrapError, BootstrapService};
+pub use change_feed_service::{ChangeFeedService, EntityChange};
 pub use claude_api_service::{ClaudeApiError, ClaudeApiService};
-pub use git_service::{GitError, GitService};
-pub use process_service::{ProcessError, ProcessEvent, ProcessManager};
+pub use claude_login_service::{ClaudeLoginService, LoginError};
+pub use claude_md_service::{ClaudeMdError, ClaudeMdService};
+pub use environment_service::EnvironmentService;
+pub use git_ops::GitOps;
+pub use git_service::{DivergedFiles, GitError, GitService, WorktreeInfo};
+pub use git_watch_service::{GitStatusEvent, GitWatchError, GitWatchService};
+pub use idle_shutdown_service::{IdleShutdownError, IdleShutdownService};
+pub use job_service::{JobError, JobHandle, JobService};
+pub use log_service::LogService;
+pub use mcp_service::{McpError, McpService};
+pub use message_stream_service::{MessageStreamError, MessageStreamService};
+pub use metrics::MetricsSnapshot;
+pub use network_status_service::NetworkStatusService;
+pub use onboarding_service::{OnboardingError, OnboardingService};
+pub use process_control::ProcessControl;
+pub use process_service::{
+    LaunchPrompts, ProcessError, ProcessEvent, ProcessManager, ProcessSnapshot, SessionLaunch,
+    StampedEvent, ToolAccess,
+};
+pub use profile_service::{ProfileError, ProfileService};
+pub use pull_request_service::{PullRequestError, PullRequestService};
+pub use remote_access_service::{RemoteAccessError, RemoteAccessService, RemoteServerConfig};
+pub use resource_monitor_service::{ResourceMonitorError, ResourceMonitorService};
+pub use schedule_service::{ScheduleError, ScheduleService};
+pub use session_snapshot_service::{SessionSnapshotService, SnapshotError};
+pub use settings_sync_service::{SettingsSyncError, SettingsSyncService};
+pub use snippet_service::{SnippetError, SnippetService};
+pub use status_sync_service::{StatusSyncError, StatusSyncService};
+pub use task_group_service::{TaskGroupError, TaskGroupService};
 pub use usage_service::{UsageError, UsageService};
-pub use websocket_server::start_websocket_server;
+pub use websocket_server::{load_or_create_api_token, start_websocket_server, ServerContext};
+pub use workspace_refresh_service::{WorkspaceRefreshEvent, WorkspaceRefreshService};
 pub use workspace_service::{WorkspaceError, WorkspaceService};
 pub use worktree_service::{WorktreeError, WorktreeService};
@@ -0,0 +1,164 @@
+//! Network status service for noticing when the app is offline
+//!
+//! A cheap probe, resolving the Claude API host and opening a TCP
+//! connection to it, runs every minute, or more often while offline so the
+//! app notices the connection coming back. Background checks that call
+//! remote services skip their calls while offline instead of failing over
+//! and over; everything local keeps working.
+
+use std::time::Duration;
+
+use parking_lot::Mutex;
+use tokio::net::{lookup_host, TcpStream};
+use tokio::sync::broadcast;
+
+use crate::types::NetworkStatus;
+
+/// Host probed for connectivity
+const PROBE_HOST: &str = "api.anthropic.com:443";
+/// Longest each probe step may take before the network counts as down
+const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+/// How often the connection is probed while online
+const ONLINE_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+/// How often the connection is probed while offline
+const OFFLINE_CHECK_INTERVAL: Duration = Duration::from_secs(15);
+
+pub struct NetworkStatusService {
+    probe_host: String,
+    status: Mutex<NetworkStatus>,
+    status_tx: broadcast::Sender<NetworkStatus>,
+}
+
+impl NetworkStatusService {
+    /// Starts out online, so nothing is skipped before the first probe
+    pub fn new() -> Self {
+        let (status_tx, _) = broadcast::channel(16);
+        Self {
+            probe_host: PROBE_HOST.to_string(),
+            status: Mutex::new(NetworkStatus {
+                online: true,
+                since: chrono::Utc::now().to_rfc3339(),
+                checked_at: None,
+                error: None,
+            }),
+            status_tx,
+        }
+    }
+
+    /// Probe another `host:port`, e.g. a local listener
+    pub fn with_probe_host(mut self, host: impl Into<String>) -> Self {
+        self.probe_host = host.into();
+        self
+    }
+
+    /// Subscribe to changes between online and offline
+    pub fn subscribe(&self) -> broadcast::Receiver<NetworkStatus> {
+        self.status_tx.subscribe()
+    }
+
+    /// Whether remote services were reachable at the last probe
+    pub fn is_online(&self) -> bool {
+        self.status.lock().online
+    }
+
+    /// The status as of the last probe
+    pub fn status(&self) -> NetworkStatus {
+        self.status.lock().clone()
+    }
+
+    /// Probe the connection now
+    pub async fn check(&self) -> NetworkStatus {
+        let result = probe(&self.probe_host).await;
+        self.record(result)
+    }
+
+    /// Probe the connection for as long as the app runs
+    pub async fn run(&self) {
+        loop {
+            let status = self.check().await;
+            let interval = if status.online {
+                ONLINE_CHECK_INTERVAL
+            } else {
+                OFFLINE_CHECK_INTERVAL
+            };
+            tokio::time::sleep(interval).await;
+        }
+    }
+
+    /// Store a probe's result, announcing a change between online and offline
+    fn record(&self, result: Result<(), String>) -> NetworkStatus {
+        let now = chrono::Utc::now().to_rfc3339();
+        let mut status = self.status.lock();
+        let online = result.is_ok();
+        let changed = status.online != online;
+        if changed {
+            status.online = online;
+            status.since = now.clone();
+        }
+        status.checked_at = Some(now);
+        status.error = result.err();
+
+        if changed {
+            match &status.error {
+                Some(e) => tracing::warn!("Network is unreachable, skipping remote calls: {}", e),
+                None => tracing::info!("Network is reachable again"),
+            }
+            // No subscribers is fine
+            let _ = self.status_tx.send(status.clone());
+        }
+        status.clone()
+    }
+}
+
+impl Default for NetworkStatusService {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Resolve `host` and open a TCP connection to it
+async fn probe(host: &str) -> Result<(), String> {
+    let mut addrs = tokio::time::timeout(PROBE_TIMEOUT, lookup_host(host))
+        .await
+        .map_err(|_| format!("DNS lookup of {} timed out", host))?
+        .map_err(|e| format!("DNS lookup of {} failed: {}", host, e))?;
+    let addr = addrs
+        .next()
+        .ok_or_else(|| format!("{} has no addresses", host))?;
+    tokio::time::timeout(PROBE_TIMEOUT, TcpStream::connect(addr))
+        .await
+        .map_err(|_| format!("Connecting to {} timed out", host))?
+        .map_err(|e| format!("Connecting to {} failed: {}", host, e))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_status_follows_probes_and_announces_changes() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let service = NetworkStatusService::new().with_probe_host(addr.to_string());
+        let mut changes = service.subscribe();
+
+        let status = service.check().await;
+        assert!(status.online);
+        assert!(status.checked_at.is_some());
+        // Still online, so nothing to announce
+        assert!(changes.try_recv().is_err());
+
+        drop(listener);
+        let status = service.check().await;
+        assert!(!status.online);
+        assert!(status.error.is_some());
+        assert!(!service.is_online());
+        assert_eq!(changes.try_recv().unwrap(), status);
+
+        let unresolvable = NetworkStatusService::new().with_probe_host("host.invalid:443");
+        let status = unresolvable.check().await;
+        assert!(!status.online);
+        assert!(status.error.unwrap().contains("DNS lookup"));
+    }
+}
@@ -0,0 +1,167 @@
+//! Onboarding service backing the first-run setup wizard
+//!
+//! Each step has a prerequisite checked live (CLI installed, credentials
+//! present, a workspace added). Steps the user finishes or skips are
+//! remembered in settings, so a prerequisite we cannot detect, such as
+//! credentials kept in the system keychain, does not block setup.
+
+use std::sync::Arc;
+
+use thiserror::Error;
+
+use crate::db::{DbPool, SettingsRepository, WorkspaceRepository};
+use crate::services::EnvironmentService;
+use crate::types::{CliIssue, OnboardingState, OnboardingStep, OnboardingStepState};
+
+const COMPLETED_STEPS_KEY: &str = "onboarding_completed_steps";
+
+#[derive(Error, Debug)]
+pub enum OnboardingError {
+    #[error("Database error: {0}")]
+    Database(String),
+}
+
+pub struct OnboardingService {
+    settings_repo: SettingsRepository,
+    workspace_repo: WorkspaceRepository,
+    environment_service: Arc<EnvironmentService>,
+}
+
+impl OnboardingService {
+    pub fn new(pool: DbPool, environment_service: Arc<EnvironmentService>) -> Self {
+        Self {
+            settings_repo: SettingsRepository::new(pool.clone()),
+            workspace_repo: WorkspaceRepository::new(pool),
+            environment_service,
+        }
+    }
+
+    /// Check every prerequisite and combine it with the saved progress
+    pub async fn get_state(&self) -> Result<OnboardingState, OnboardingError> {
+        let cli = self.environment_service.check_claude_cli().await;
+        let has_workspace = !self
+            .workspace_repo
+            .find_all()
+            .map_err(|e| OnboardingError::Database(e.to_string()))?
+            .is_empty();
+        let completed = self.completed_steps()?;
+
+        let message_for = |issues: &[CliIssue]| {
+            cli.diagnostics
+                .iter()
+                .find(|d| issues.contains(&d.issue))
+                .map(|d| d.message.clone())
+        };
+
+        let steps: Vec<OnboardingStepState> = OnboardingStep::ALL
+            .into_iter()
+            .map(|step| {
+                let message = match step {
+                    OnboardingStep::InstallCli => message_for(&[
+                        CliIssue::NotFound,
+                        CliIssue::VersionUnknown,
+                        CliIssue::Outdated,
+                    ]),
+                    OnboardingStep::LogIn => message_for(&[CliIssue::NotLoggedIn]),
+                    OnboardingStep::AddWorkspace => (!has_workspace)
+                        .then(|| "Add a git repository as your first workspace".to_string()),
+                };
+                OnboardingStepState {
+                    step,
+                    satisfied: message.is_none(),
+                    completed: completed.contains(&step),
+                    message,
+                }
+            })
+            .collect();
+
+        let current_step = steps
+            .iter()
+            .find(|s| !s.satisfied && !s.completed)
+            .map(|s| s.step);
+        Ok(OnboardingState {
+            steps,
+            current_step,
+            finished: current_step.is_none(),
+        })
+    }
+
+    /// Mark a step finished or skipped, returning the updated state
+    pub async fn complete_step(
+        &self,
+        step: OnboardingStep,
+    ) -> Result<OnboardingState, OnboardingError> {
+        let mut completed = self.completed_steps()?;
+        if !completed.contains(&step) {
+            completed.push(step);
+            let value = serde_json::to_string(&completed).unwrap_or_default();
+            self.settings_repo
+                .set(COMPLETED_STEPS_KEY, &value, "json")
+                .map_err(|e| OnboardingError::Database(e.to_string()))?;
+        }
+        self.get_state().await
+    }
+
+    fn completed_steps(&self) -> Result<Vec<OnboardingStep>, OnboardingError> {
+        let value = self
+            .settings_repo
+            .get(COMPLETED_STEPS_KEY)
+            .map_err(|e| OnboardingError::Database(e.to_string()))?;
+        Ok(value
+            .and_then(|v| serde_json::from_str(&v).ok())
+            .unwrap_or_default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn create_test_service() -> (OnboardingService, DbPool, TempDir) {
+        let (pool, dir) = crate::db::test_pool();
+
+        let missing_cli = dir.path().join("no-claude").to_string_lossy().to_string();
+        let environment_service = Arc::new(EnvironmentService::new(missing_cli));
+        let service = OnboardingService::new(pool.clone(), environment_service);
+        (service, pool, dir)
+    }
+
+    fn step(state: &OnboardingState, step: OnboardingStep) -> &OnboardingStepState {
+        state.steps.iter().find(|s| s.step == step).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_state_follows_prerequisites_and_saved_progress() {
+        let (service, pool, _dir) = create_test_service();
+
+        let state = service.get_state().await.unwrap();
+        assert_eq!(state.current_step, Some(OnboardingStep::InstallCli));
+        assert!(!state.finished);
+        let install = step(&state, OnboardingStep::InstallCli);
+        assert!(!install.satisfied);
+        assert!(install.message.as_deref().unwrap().contains("not found"));
+        assert!(!step(&state, OnboardingStep::AddWorkspace).satisfied);
+
+        // Completing steps whose prerequisites cannot be detected moves on
+        service
+            .complete_step(OnboardingStep::InstallCli)
+            .await
+            .unwrap();
+        let state = service.complete_step(OnboardingStep::LogIn).await.unwrap();
+        assert_eq!(state.current_step, Some(OnboardingStep::AddWorkspace));
+        assert!(step(&state, OnboardingStep::InstallCli).completed);
+
+        pool.get()
+            .unwrap()
+            .execute(
+                "INSERT INTO workspaces (id, name, path) VALUES ('ws_1', 'Test', '/tmp/test')",
+                [],
+            )
+            .unwrap();
+        let state = service.get_state().await.unwrap();
+        assert!(step(&state, OnboardingStep::AddWorkspace).satisfied);
+        assert!(state.current_step.is_none());
+        assert!(state.finished);
+    }
+}
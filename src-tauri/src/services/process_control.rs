@@ -0,0 +1,175 @@
+//! What services need from the process manager
+//!
+//! Services that start, stop, watch and talk to agents hold an
+//! `Arc<dyn ProcessControl>` rather than the concrete `ProcessManager`, so
+//! tests can hand them a stand-in that spawns nothing.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::broadcast;
+
+use crate::services::process_service::{
+    LaunchPrompts, ProcessError, ProcessManager, ProcessSnapshot, SessionLaunch, StampedEvent,
+    ToolAccess,
+};
+use crate::types::{AgentMode, ClaudeProfile};
+use crate::util::redact::Redactor;
+
+pub trait ProcessControl: Send + Sync {
+    /// Spawn an agent's process, returning its PID and Claude session ID
+    fn spawn_agent(
+        &self,
+        agent_id: &str,
+        worktree_path: &str,
+        mode: AgentMode,
+        tools: ToolAccess<'_>,
+        prompts: LaunchPrompts<'_>,
+        session: SessionLaunch<'_>,
+    ) -> Result<(u32, String), ProcessError>;
+
+    /// Stop an agent's process; a graceful stop returns before it exits
+    fn stop_agent(&self, agent_id: &str, force: bool) -> Result<(), ProcessError>;
+
+    /// Stop every running agent
+    fn stop_all(&self);
+
+    /// Type a message into a running agent's terminal and submit it
+    fn send_message(&self, agent_id: &str, message: &str) -> Result<(), ProcessError>;
+
+    /// Subscribe to status changes, output and exits of every agent
+    fn subscribe(&self) -> broadcast::Receiver<StampedEvent>;
+
+    /// Subscribe to an agent's raw terminal output, along with what it
+    /// produced so far; None when it has no terminal
+    fn subscribe_pty_output(
+        &self,
+        agent_id: &str,
+    ) -> Option<(broadcast::Receiver<Vec<u8>>, Vec<u8>)>;
+
+    /// Publish a piece of an agent's reply to subscribers
+    fn emit_output(&self, agent_id: &str, message_id: &str, content: &str, is_complete: bool);
+
+    fn is_running(&self, agent_id: &str) -> bool;
+
+    fn get_running_count(&self) -> usize;
+
+    /// IDs of the agents with a running process
+    fn running_agent_ids(&self) -> Vec<String>;
+
+    /// Agents with a running process and the PID of each
+    fn running_pids(&self) -> Vec<(String, u32)>;
+
+    /// How long an agent may sit idle before it is reported, or None to
+    /// never report it
+    fn set_idle_timeout(&self, agent_id: &str, timeout: Option<Duration>);
+
+    /// Live state of an agent's process; the default when it never ran
+    fn snapshot(&self, agent_id: &str) -> ProcessSnapshot;
+
+    /// Terminal output an agent's process produced, kept for replay
+    fn get_pty_buffer(&self, agent_id: &str) -> Option<Vec<u8>>;
+
+    /// Filter secrets out of an agent's output, or stop filtering it
+    fn set_output_redactor(&self, agent_id: &str, redactor: Option<Arc<Redactor>>);
+
+    /// Swap the filter of every agent that has one
+    fn replace_output_redactors(&self, redactor: Arc<Redactor>);
+
+    /// Run an agent under a profile's Claude account on its next spawn
+    fn set_account(&self, agent_id: &str, profile: Option<ClaudeProfile>);
+
+    /// Refuse terminal input while observer mode is on
+    fn set_read_only(&self, read_only: bool);
+
+    fn is_read_only(&self) -> bool;
+}
+
+impl ProcessControl for ProcessManager {
+    fn spawn_agent(
+        &self,
+        agent_id: &str,
+        worktree_path: &str,
+        mode: AgentMode,
+        tools: ToolAccess<'_>,
+        prompts: LaunchPrompts<'_>,
+        session: SessionLaunch<'_>,
+    ) -> Result<(u32, String), ProcessError> {
+        ProcessManager::spawn_agent(self, agent_id, worktree_path, mode, tools, prompts, session)
+    }
+
+    fn stop_agent(&self, agent_id: &str, force: bool) -> Result<(), ProcessError> {
+        ProcessManager::stop_agent(self, agent_id, force)
+    }
+
+    fn stop_all(&self) {
+        ProcessManager::stop_all(self)
+    }
+
+    fn send_message(&self, agent_id: &str, message: &str) -> Result<(), ProcessError> {
+        ProcessManager::send_message(self, agent_id, message)
+    }
+
+    fn subscribe(&self) -> broadcast::Receiver<StampedEvent> {
+        ProcessManager::subscribe(self)
+    }
+
+    fn subscribe_pty_output(
+        &self,
+        agent_id: &str,
+    ) -> Option<(broadcast::Receiver<Vec<u8>>, Vec<u8>)> {
+        ProcessManager::subscribe_pty_output(self, agent_id)
+    }
+
+    fn emit_output(&self, agent_id: &str, message_id: &str, content: &str, is_complete: bool) {
+        ProcessManager::emit_output(self, agent_id, message_id, content, is_complete)
+    }
+
+    fn is_running(&self, agent_id: &str) -> bool {
+        ProcessManager::is_running(self, agent_id)
+    }
+
+    fn get_running_count(&self) -> usize {
+        ProcessManager::get_running_count(self)
+    }
+
+    fn running_agent_ids(&self) -> Vec<String> {
+        ProcessManager::running_agent_ids(self)
+    }
+
+    fn running_pids(&self) -> Vec<(String, u32)> {
+        ProcessManager::running_pids(self)
+    }
+
+    fn set_idle_timeout(&self, agent_id: &str, timeout: Option<Duration>) {
+        ProcessManager::set_idle_timeout(self, agent_id, timeout)
+    }
+
+    fn snapshot(&self, agent_id: &str) -> ProcessSnapshot {
+        ProcessManager::snapshot(self, agent_id)
+    }
+
+    fn get_pty_buffer(&self, agent_id: &str) -> Option<Vec<u8>> {
+        ProcessManager::get_pty_buffer(self, agent_id)
+    }
+
+    fn set_output_redactor(&self, agent_id: &str, redactor: Option<Arc<Redactor>>) {
+        ProcessManager::set_output_redactor(self, agent_id, redactor)
+    }
+
+    fn replace_output_redactors(&self, redactor: Arc<Redactor>) {
+        ProcessManager::replace_output_redactors(self, redactor)
+    }
+
+    fn set_account(&self, agent_id: &str, profile: Option<ClaudeProfile>) {
+        ProcessManager::set_account(self, agent_id, profile)
+    }
+
+    fn set_read_only(&self, read_only: bool) {
+        ProcessManager::set_read_only(self, read_only)
+    }
+
+    fn is_read_only(&self) -> bool {
+        ProcessManager::is_read_only(self)
+    }
+}
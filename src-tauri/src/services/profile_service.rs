@@ -0,0 +1,399 @@
+//! Profile service for running agents under different Claude accounts
+//!
+//! A profile names a Claude config directory, and optionally a CLI and a
+//! model, so agents can run under a work and a personal account side by
+//! side. Profiles are chosen per workspace and may be overridden per agent;
+//! agents with neither run under the default account in `~/.claude`.
+
+use std::path::{Path, PathBuf};
+
+use thiserror::Error;
+
+use crate::db::{
+    AgentRepository, DbPool, SettingsRepository, WorkspaceRepository, WorktreeRepository,
+};
+use crate::types::{Agent, ClaudeProfile, ProfileSelection, SaveProfileInput};
+use crate::util::id::new_id;
+
+/// Settings key for the list of profiles
+const PROFILES_KEY: &str = "claude_profiles";
+/// Settings key prefix for a workspace's profile
+const WORKSPACE_PROFILE_KEY: &str = "claude_profile";
+/// Settings key prefix for an agent's profile
+const AGENT_PROFILE_KEY: &str = "agent_claude_profile";
+
+#[derive(Error, Debug)]
+pub enum ProfileError {
+    #[error("Profile not found: {0}")]
+    NotFound(String),
+    #[error("Workspace not found: {0}")]
+    WorkspaceNotFound(String),
+    #[error("Agent not found: {0}")]
+    AgentNotFound(String),
+    #[error("Validation error: {0}")]
+    Validation(String),
+    #[error("Database error: {0}")]
+    Database(String),
+}
+
+pub struct ProfileService {
+    settings_repo: SettingsRepository,
+    workspace_repo: WorkspaceRepository,
+    worktree_repo: WorktreeRepository,
+    agent_repo: AgentRepository,
+}
+
+impl ProfileService {
+    pub fn new(pool: DbPool) -> Self {
+        Self {
+            settings_repo: SettingsRepository::new(pool.clone()),
+            workspace_repo: WorkspaceRepository::new(pool.clone()),
+            worktree_repo: WorktreeRepository::new(pool.clone()),
+            agent_repo: AgentRepository::new(pool),
+        }
+    }
+
+    /// All profiles in the order they were created
+    pub fn list_profiles(&self) -> Result<Vec<ClaudeProfile>, ProfileError> {
+        let value = self
+            .settings_repo
+            .get(PROFILES_KEY)
+            .map_err(|e| ProfileError::Database(e.to_string()))?;
+        Ok(value
+            .and_then(|v| serde_json::from_str(&v).ok())
+            .unwrap_or_default())
+    }
+
+    /// Get a profile by ID
+    pub fn get_profile(&self, id: &str) -> Result<ClaudeProfile, ProfileError> {
+        self.list_profiles()?
+            .into_iter()
+            .find(|p| p.id == id)
+            .ok_or_else(|| ProfileError::NotFound(id.to_string()))
+    }
+
+    /// Create a profile, or replace the one with the input's ID
+    pub fn save_profile(&self, input: SaveProfileInput) -> Result<ClaudeProfile, ProfileError> {
+        let name = input.name.trim();
+        if name.is_empty() {
+            return Err(ProfileError::Validation(
+                "Profile name cannot be empty".to_string(),
+            ));
+        }
+        let config_dir = non_empty(input.config_dir);
+        if let Some(dir) = &config_dir {
+            if !Path::new(dir).is_absolute() {
+                return Err(ProfileError::Validation(format!(
+                    "Config directory must be an absolute path: {}",
+                    dir
+                )));
+            }
+        }
+        let model = non_empty(input.model);
+        if model
+            .as_deref()
+            .is_some_and(|m| m.contains(char::is_whitespace))
+        {
+            return Err(ProfileError::Validation(
+                "Model name cannot contain spaces".to_string(),
+            ));
+        }
+
+        let mut profiles = self.list_profiles()?;
+        if profiles
+            .iter()
+            .any(|p| p.name.eq_ignore_ascii_case(name) && Some(&p.id) != input.id.as_ref())
+        {
+            return Err(ProfileError::Validation(format!(
+                "A profile named {} already exists",
+                name
+            )));
+        }
+
+        let profile = ClaudeProfile {
+            id: input.id.clone().unwrap_or_else(|| new_id("prof")),
+            name: name.to_string(),
+            config_dir,
+            cli_path: non_empty(input.cli_path),
+            model,
+        };
+        match input.id {
+            Some(id) => {
+                let existing = profiles
+                    .iter_mut()
+                    .find(|p| p.id == id)
+                    .ok_or(ProfileError::NotFound(id))?;
+                *existing = profile.clone();
+            }
+            None => profiles.push(profile.clone()),
+        }
+        self.store(&profiles)?;
+
+        Ok(profile)
+    }
+
+    /// Delete a profile. Workspaces and agents that chose it fall back to
+    /// the default account.
+    pub fn delete_profile(&self, id: &str) -> Result<(), ProfileError> {
+        let mut profiles = self.list_profiles()?;
+        let before = profiles.len();
+        profiles.retain(|p| p.id != id);
+        if profiles.len() == before {
+            return Err(ProfileError::NotFound(id.to_string()));
+        }
+        self.store(&profiles)
+    }
+
+    /// The profile a workspace's agents run under
+    pub fn get_workspace_profile(
+        &self,
+        workspace_id: &str,
+    ) -> Result<ProfileSelection, ProfileError> {
+        self.ensure_workspace(workspace_id)?;
+        let profile_id = self.selection(WORKSPACE_PROFILE_KEY, workspace_id)?;
+        let effective = self.find(profile_id.as_deref())?;
+        Ok(ProfileSelection {
+            profile_id,
+            effective,
+        })
+    }
+
+    /// Choose the profile a workspace's agents run under; None for the
+    /// default account
+    pub fn set_workspace_profile(
+        &self,
+        workspace_id: &str,
+        profile_id: Option<&str>,
+    ) -> Result<ProfileSelection, ProfileError> {
+        self.ensure_workspace(workspace_id)?;
+        self.select(WORKSPACE_PROFILE_KEY, workspace_id, profile_id)?;
+        self.get_workspace_profile(workspace_id)
+    }
+
+    /// The profile an agent runs under, with its own choice if it has one
+    pub fn get_agent_profile(&self, agent_id: &str) -> Result<ProfileSelection, ProfileError> {
+        let agent = self
+            .agent_repo
+            .find_by_id(agent_id)
+            .map_err(|e| ProfileError::Database(e.to_string()))?
+            .ok_or_else(|| ProfileError::AgentNotFound(agent_id.to_string()))?;
+        Ok(ProfileSelection {
+            profile_id: self.selection(AGENT_PROFILE_KEY, agent_id)?,
+            effective: self.resolve_for_agent(&agent)?,
+        })
+    }
+
+    /// Choose the profile an agent runs under from its next start; None to
+    /// use its workspace's
+    pub fn set_agent_profile(
+        &self,
+        agent_id: &str,
+        profile_id: Option<&str>,
+    ) -> Result<ProfileSelection, ProfileError> {
+        self.agent_repo
+            .find_by_id(agent_id)
+            .map_err(|e| ProfileError::Database(e.to_string()))?
+            .ok_or_else(|| ProfileError::AgentNotFound(agent_id.to_string()))?;
+        self.select(AGENT_PROFILE_KEY, agent_id, profile_id)?;
+        self.get_agent_profile(agent_id)
+    }
+
+    /// The profile an agent runs under: its own, else its workspace's.
+    /// None means the default account.
+    pub fn resolve_for_agent(&self, agent: &Agent) -> Result<Option<ClaudeProfile>, ProfileError> {
+        let own = self.selection(AGENT_PROFILE_KEY, &agent.id)?;
+        if let Some(profile) = self.find(own.as_deref())? {
+            return Ok(Some(profile));
+        }
+        let worktree = self
+            .worktree_repo
+            .find_by_id(&agent.worktree_id)
+            .map_err(|e| ProfileError::Database(e.to_string()))?;
+        match worktree {
+            Some(worktree) => {
+                let workspace = self.selection(WORKSPACE_PROFILE_KEY, &worktree.workspace_id)?;
+                self.find(workspace.as_deref())
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// The config directory of a profile's account; None for the default
+    /// account in `~/.claude`
+    pub fn config_dir(&self, profile_id: Option<&str>) -> Result<Option<PathBuf>, ProfileError> {
+        match profile_id {
+            Some(id) => Ok(self.get_profile(id)?.config_dir.map(PathBuf::from)),
+            None => Ok(None),
+        }
+    }
+
+    fn selection(&self, key: &str, id: &str) -> Result<Option<String>, ProfileError> {
+        self.settings_repo
+            .get(&format!("{}:{}", key, id))
+            .map_err(|e| ProfileError::Database(e.to_string()))
+    }
+
+    fn select(&self, key: &str, id: &str, profile_id: Option<&str>) -> Result<(), ProfileError> {
+        let key = format!("{}:{}", key, id);
+        let result = match profile_id {
+            Some(profile_id) => {
+                self.get_profile(profile_id)?;
+                self.settings_repo.set(&key, profile_id, "string")
+            }
+            None => self.settings_repo.delete(&key),
+        };
+        result.map_err(|e| ProfileError::Database(e.to_string()))
+    }
+
+    /// Look up a chosen profile; one deleted since it was chosen counts as
+    /// no choice
+    fn find(&self, profile_id: Option<&str>) -> Result<Option<ClaudeProfile>, ProfileError> {
+        let Some(profile_id) = profile_id else {
+            return Ok(None);
+        };
+        Ok(self
+            .list_profiles()?
+            .into_iter()
+            .find(|p| p.id == profile_id))
+    }
+
+    fn store(&self, profiles: &[ClaudeProfile]) -> Result<(), ProfileError> {
+        let value = serde_json::to_string(profiles).unwrap_or_default();
+        self.settings_repo
+            .set(PROFILES_KEY, &value, "json")
+            .map_err(|e| ProfileError::Database(e.to_string()))
+    }
+
+    fn ensure_workspace(&self, workspace_id: &str) -> Result<(), ProfileError> {
+        self.workspace_repo
+            .find_by_id(workspace_id)
+            .map_err(|e| ProfileError::Database(e.to_string()))?
+            .ok_or_else(|| ProfileError::WorkspaceNotFound(workspace_id.to_string()))?;
+        Ok(())
+    }
+}
+
+fn non_empty(value: Option<String>) -> Option<String> {
+    value
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn create_test_service() -> (ProfileService, TempDir) {
+        let (pool, dir) = crate::db::test_pool();
+        let conn = pool.get().unwrap();
+        conn.execute_batch(
+            r#"
+            INSERT INTO workspaces (id, name, path) VALUES ('ws_1', 'Test', '/tmp/test');
+            INSERT INTO worktrees (id, workspace_id, name, branch, path)
+                VALUES ('wt_1', 'ws_1', 'main', 'main', '/tmp/test');
+            INSERT INTO agents (id, worktree_id, name) VALUES ('ag_1', 'wt_1', 'Agent');
+        "#,
+        )
+        .unwrap();
+
+        (ProfileService::new(pool), dir)
+    }
+
+    fn input(name: &str, config_dir: Option<&str>) -> SaveProfileInput {
+        SaveProfileInput {
+            id: None,
+            name: name.to_string(),
+            config_dir: config_dir.map(|d| d.to_string()),
+            cli_path: None,
+            model: None,
+        }
+    }
+
+    #[test]
+    fn test_save_and_replace_profiles() {
+        let (service, _dir) = create_test_service();
+
+        let work = service
+            .save_profile(SaveProfileInput {
+                model: Some(" opus ".to_string()),
+                cli_path: Some("  ".to_string()),
+                ..input(" Work ", Some("/home/me/.claude-work"))
+            })
+            .unwrap();
+        assert_eq!(work.name, "Work");
+        assert_eq!(work.model.as_deref(), Some("opus"));
+        assert_eq!(work.cli_path, None);
+
+        let renamed = service
+            .save_profile(SaveProfileInput {
+                id: Some(work.id.clone()),
+                ..input("Job", Some("/home/me/.claude-work"))
+            })
+            .unwrap();
+        assert_eq!(renamed.id, work.id);
+        assert_eq!(service.list_profiles().unwrap(), vec![renamed]);
+
+        assert!(matches!(
+            service.save_profile(input("job", None)),
+            Err(ProfileError::Validation(_))
+        ));
+        assert!(matches!(
+            service.save_profile(input("Personal", Some("relative/dir"))),
+            Err(ProfileError::Validation(_))
+        ));
+        assert!(matches!(
+            service.save_profile(SaveProfileInput {
+                id: Some("prof_missing".to_string()),
+                ..input("Personal", None)
+            }),
+            Err(ProfileError::NotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_agent_profile_falls_back_to_workspace() {
+        let (service, _dir) = create_test_service();
+        let work = service
+            .save_profile(input("Work", Some("/home/me/.claude-work")))
+            .unwrap();
+        let personal = service
+            .save_profile(input("Personal", Some("/home/me/.claude-personal")))
+            .unwrap();
+
+        let selection = service.get_agent_profile("ag_1").unwrap();
+        assert_eq!(selection.profile_id, None);
+        assert_eq!(selection.effective, None);
+
+        service
+            .set_workspace_profile("ws_1", Some(&work.id))
+            .unwrap();
+        let selection = service.get_agent_profile("ag_1").unwrap();
+        assert_eq!(selection.profile_id, None);
+        assert_eq!(selection.effective.as_ref(), Some(&work));
+
+        service
+            .set_agent_profile("ag_1", Some(&personal.id))
+            .unwrap();
+        let selection = service.get_agent_profile("ag_1").unwrap();
+        assert_eq!(selection.profile_id.as_deref(), Some(personal.id.as_str()));
+        assert_eq!(selection.effective.as_ref(), Some(&personal));
+
+        // A deleted profile no longer applies
+        service.delete_profile(&personal.id).unwrap();
+        let selection = service.get_agent_profile("ag_1").unwrap();
+        assert_eq!(selection.effective.as_ref(), Some(&work));
+
+        service.set_workspace_profile("ws_1", None).unwrap();
+        assert_eq!(service.get_agent_profile("ag_1").unwrap().effective, None);
+
+        assert!(matches!(
+            service.set_agent_profile("ag_1", Some("prof_missing")),
+            Err(ProfileError::NotFound(_))
+        ));
+        assert!(matches!(
+            service.set_workspace_profile("ws_missing", None),
+            Err(ProfileError::WorkspaceNotFound(_))
+        ));
+    }
+}
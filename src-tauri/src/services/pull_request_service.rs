@@ -0,0 +1,307 @@
+//! Pull request service for opening GitHub PRs from worktree branches
+//!
+//! PRs are created through the GitHub REST API when a token is stored in
+//! settings, and through the `gh` CLI otherwise.
+
+use std::path::Path;
+
+use serde::Deserialize;
+use thiserror::Error;
+use tokio::process::Command;
+
+use crate::db::{DbPool, SettingsRepository, WorktreeRepository};
+use crate::types::PullRequestInfo;
+
+const GITHUB_API: &str = "https://api.github.com";
+const GITHUB_TOKEN_KEY: &str = "github_token";
+
+#[derive(Error, Debug)]
+pub enum PullRequestError {
+    #[error("Worktree not found: {0}")]
+    NotFound(String),
+    #[error("Failed to push branch: {0}")]
+    Push(String),
+    #[error("GitHub error: {0}")]
+    GitHub(String),
+    #[error("Database error: {0}")]
+    Database(String),
+}
+
+#[derive(Deserialize)]
+struct GitHubRepo {
+    default_branch: String,
+}
+
+#[derive(Deserialize)]
+struct GitHubPullRequest {
+    html_url: String,
+}
+
+pub struct PullRequestService {
+    worktree_repo: WorktreeRepository,
+    settings_repo: SettingsRepository,
+    client: reqwest::Client,
+}
+
+impl PullRequestService {
+    pub fn new(pool: DbPool) -> Self {
+        Self {
+            worktree_repo: WorktreeRepository::new(pool.clone()),
+            settings_repo: SettingsRepository::new(pool),
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// Push a worktree's branch and open a pull request for it.
+    ///
+    /// `base` defaults to the repository's default branch.
+    pub async fn create_pull_request(
+        &self,
+        worktree_id: &str,
+        title: &str,
+        body: &str,
+        base: Option<&str>,
+    ) -> Result<PullRequestInfo, PullRequestError> {
+        let worktree = self
+            .worktree_repo
+            .find_by_id(worktree_id)
+            .map_err(|e| PullRequestError::Database(e.to_string()))?
+            .ok_or_else(|| PullRequestError::NotFound(worktree_id.to_string()))?;
+
+        push_branch(&worktree.path, &worktree.branch).await?;
+
+        let url = match self.github_token()? {
+            Some(token) => {
+                self.create_with_api(&token, &worktree.path, &worktree.branch, title, body, base)
+                    .await?
+            }
+            None => create_with_gh(&worktree.path, &worktree.branch, title, body, base).await?,
+        };
+
+        self.worktree_repo
+            .set_pr_url(worktree_id, &url)
+            .map_err(|e| PullRequestError::Database(e.to_string()))?;
+
+        tracing::info!("Opened pull request {} for worktree {}", url, worktree_id);
+
+        Ok(PullRequestInfo {
+            worktree_id: worktree_id.to_string(),
+            url,
+            head_branch: worktree.branch,
+            base_branch: base.map(|b| b.to_string()),
+        })
+    }
+
+    /// Store the token used for the GitHub REST API; an empty token removes it
+    pub fn set_github_token(&self, token: &str) -> Result<(), PullRequestError> {
+        self.settings_repo
+            .set(GITHUB_TOKEN_KEY, token.trim(), "string")
+            .map_err(|e| PullRequestError::Database(e.to_string()))
+    }
+
+    fn github_token(&self) -> Result<Option<String>, PullRequestError> {
+        let token = self
+            .settings_repo
+            .get(GITHUB_TOKEN_KEY)
+            .map_err(|e| PullRequestError::Database(e.to_string()))?;
+        Ok(token.filter(|t| !t.is_empty()))
+    }
+
+    async fn create_with_api(
+        &self,
+        token: &str,
+        worktree_path: &str,
+        head: &str,
+        title: &str,
+        body: &str,
+        base: Option<&str>,
+    ) -> Result<String, PullRequestError> {
+        let remote_url = git2::Repository::open(worktree_path)
+            .and_then(|repo| {
+                repo.find_remote("origin")
+                    .map(|remote| remote.url().unwrap_or_default().to_string())
+            })
+            .map_err(|e| PullRequestError::Push(e.to_string()))?;
+        let (owner, repo) = parse_github_remote(&remote_url).ok_or_else(|| {
+            PullRequestError::GitHub(format!("Not a GitHub remote: {}", remote_url))
+        })?;
+
+        let base = match base {
+            Some(base) => base.to_string(),
+            None => {
+                let url = format!("{}/repos/{}/{}", GITHUB_API, owner, repo);
+                let info: GitHubRepo = self.send(self.client.get(url), token).await?;
+                info.default_branch
+            }
+        };
+
+        let url = format!("{}/repos/{}/{}/pulls", GITHUB_API, owner, repo);
+        let request = self.client.post(url).json(&serde_json::json!({
+            "title": title,
+            "body": body,
+            "head": head,
+            "base": base,
+        }));
+        let pr: GitHubPullRequest = self.send(request, token).await?;
+
+        Ok(pr.html_url)
+    }
+
+    async fn send<T: serde::de::DeserializeOwned>(
+        &self,
+        request: reqwest::RequestBuilder,
+        token: &str,
+    ) -> Result<T, PullRequestError> {
+        let response = request
+            .bearer_auth(token)
+            .header("Accept", "application/vnd.github+json")
+            .header("User-Agent", "claude-manager")
+            .send()
+            .await
+            .map_err(|e| PullRequestError::GitHub(e.to_string()))?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let text = response.text().await.unwrap_or_default();
+            return Err(PullRequestError::GitHub(format!("{}: {}", status, text)));
+        }
+
+        response
+            .json()
+            .await
+            .map_err(|e| PullRequestError::GitHub(e.to_string()))
+    }
+}
+
+async fn push_branch(worktree_path: &str, branch: &str) -> Result<(), PullRequestError> {
+    let output = Command::new("git")
+        .args(["push", "--set-upstream", "origin", branch])
+        .current_dir(Path::new(worktree_path))
+        .output()
+        .await
+        .map_err(|e| PullRequestError::Push(e.to_string()))?;
+
+    if !output.status.success() {
+        return Err(PullRequestError::Push(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+    Ok(())
+}
+
+async fn create_with_gh(
+    worktree_path: &str,
+    head: &str,
+    title: &str,
+    body: &str,
+    base: Option<&str>,
+) -> Result<String, PullRequestError> {
+    let mut command = Command::new("gh");
+    command
+        .args([
+            "pr", "create", "--title", title, "--body", body, "--head", head,
+        ])
+        .current_dir(Path::new(worktree_path));
+    if let Some(base) = base {
+        command.args(["--base", base]);
+    }
+
+    let output = command.output().await.map_err(|e| {
+        if e.kind() == std::io::ErrorKind::NotFound {
+            PullRequestError::GitHub(
+                "GitHub CLI (gh) not found; install it or store a GitHub token".to_string(),
+            )
+        } else {
+            PullRequestError::GitHub(e.to_string())
+        }
+    })?;
+
+    if !output.status.success() {
+        return Err(PullRequestError::GitHub(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+
+    pr_url_from_gh_output(&String::from_utf8_lossy(&output.stdout))
+        .ok_or_else(|| PullRequestError::GitHub("gh did not print a PR URL".to_string()))
+}
+
+/// `gh pr create` prints progress lines followed by the PR URL
+fn pr_url_from_gh_output(stdout: &str) -> Option<String> {
+    stdout
+        .lines()
+        .rev()
+        .map(str::trim)
+        .find(|line| line.starts_with("https://"))
+        .map(|line| line.to_string())
+}
+
+/// Extract `(owner, repo)` from an HTTPS or SSH GitHub remote URL
+fn parse_github_remote(url: &str) -> Option<(String, String)> {
+    let path = url
+        .strip_prefix("https://github.com/")
+        .or_else(|| url.strip_prefix("http://github.com/"))
+        .or_else(|| url.strip_prefix("git@github.com:"))
+        .or_else(|| url.strip_prefix("ssh://git@github.com/"))?;
+    let path = path.trim_end_matches('/');
+    let path = path.strip_suffix(".git").unwrap_or(path);
+
+    let (owner, repo) = path.split_once('/')?;
+    if owner.is_empty() || repo.is_empty() || repo.contains('/') {
+        return None;
+    }
+    Some((owner.to_string(), repo.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_github_remote() {
+        let expected = Some(("octo".to_string(), "widgets".to_string()));
+        assert_eq!(
+            parse_github_remote("https://github.com/octo/widgets.git"),
+            expected
+        );
+        assert_eq!(
+            parse_github_remote("https://github.com/octo/widgets"),
+            expected
+        );
+        assert_eq!(
+            parse_github_remote("git@github.com:octo/widgets.git"),
+            expected
+        );
+        assert_eq!(
+            parse_github_remote("ssh://git@github.com/octo/widgets.git"),
+            expected
+        );
+        assert_eq!(
+            parse_github_remote("https://gitlab.com/octo/widgets.git"),
+            None
+        );
+        assert_eq!(parse_github_remote("https://github.com/octo"), None);
+    }
+
+    #[test]
+    fn test_pr_url_from_gh_output() {
+        let stdout = "\nCreating pull request for feature into main in octo/widgets\n\nhttps://github.com/octo/widgets/pull/42\n";
+        assert_eq!(
+            pr_url_from_gh_output(stdout).as_deref(),
+            Some("https://github.com/octo/widgets/pull/42")
+        );
+        assert_eq!(pr_url_from_gh_output("warning: nothing\n"), None);
+    }
+
+    #[tokio::test]
+    async fn test_create_pull_request_unknown_worktree() {
+        let (pool, _dir) = crate::db::test_pool();
+        let service = PullRequestService::new(pool);
+
+        let result = service
+            .create_pull_request("wt_missing", "Title", "Body", None)
+            .await;
+
+        assert!(matches!(result, Err(PullRequestError::NotFound(_))));
+    }
+}
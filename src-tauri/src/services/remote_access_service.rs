@@ -0,0 +1,270 @@
+//! Remote access service for serving the API to other machines
+//!
+//! Remote access is opt-in. When enabled, the same routes as the local server
+//! are served over TLS on a configurable interface, every request must carry
+//! the access token, and terminal input is only forwarded when remote control
+//! is allowed separately.
+
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use axum_server::tls_rustls::RustlsConfig;
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use thiserror::Error;
+
+use crate::db::{DbPool, SettingsRepository};
+use crate::services::load_or_create_api_token;
+use crate::types::{RemoteAccessSettings, UpdateRemoteAccessInput};
+
+const ENABLED_KEY: &str = "remote_access_enabled";
+const BIND_ADDRESS_KEY: &str = "remote_bind_address";
+const ALLOW_CONTROL_KEY: &str = "remote_allow_control";
+const DEFAULT_BIND_ADDRESS: &str = "0.0.0.0:3443";
+
+#[derive(Error, Debug)]
+pub enum RemoteAccessError {
+    #[error("Invalid bind address: {0}")]
+    InvalidAddress(String),
+    #[error("TLS error: {0}")]
+    Tls(String),
+    #[error("Database error: {0}")]
+    Database(String),
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+/// Everything the server needs to start the remote listener
+#[derive(Clone)]
+pub struct RemoteServerConfig {
+    pub bind_address: SocketAddr,
+    pub tls: RustlsConfig,
+    pub allow_control: bool,
+}
+
+pub struct RemoteAccessService {
+    settings_repo: SettingsRepository,
+    data_dir: PathBuf,
+}
+
+impl RemoteAccessService {
+    pub fn new(pool: DbPool, data_dir: PathBuf) -> Self {
+        Self {
+            settings_repo: SettingsRepository::new(pool),
+            data_dir,
+        }
+    }
+
+    /// Get the current remote access settings
+    pub fn get_settings(&self) -> Result<RemoteAccessSettings, RemoteAccessError> {
+        let db_err = |e: crate::db::DbError| RemoteAccessError::Database(e.to_string());
+        let cert_path = self.cert_path();
+
+        Ok(RemoteAccessSettings {
+            enabled: self
+                .settings_repo
+                .get_bool(ENABLED_KEY, false)
+                .map_err(db_err)?,
+            bind_address: self
+                .settings_repo
+                .get_string(BIND_ADDRESS_KEY, DEFAULT_BIND_ADDRESS)
+                .map_err(db_err)?,
+            allow_remote_control: self
+                .settings_repo
+                .get_bool(ALLOW_CONTROL_KEY, false)
+                .map_err(db_err)?,
+            access_token: load_or_create_api_token(&self.data_dir)?,
+            certificate_path: cert_path.exists().then(|| cert_path.display().to_string()),
+        })
+    }
+
+    /// Update remote access settings
+    pub fn update_settings(
+        &self,
+        input: UpdateRemoteAccessInput,
+    ) -> Result<RemoteAccessSettings, RemoteAccessError> {
+        let db_err = |e: crate::db::DbError| RemoteAccessError::Database(e.to_string());
+
+        if let Some(address) = &input.bind_address {
+            address
+                .parse::<SocketAddr>()
+                .map_err(|_| RemoteAccessError::InvalidAddress(address.clone()))?;
+            self.settings_repo
+                .set(BIND_ADDRESS_KEY, address, "string")
+                .map_err(db_err)?;
+        }
+        if let Some(enabled) = input.enabled {
+            self.settings_repo
+                .set_bool(ENABLED_KEY, enabled)
+                .map_err(db_err)?;
+            if enabled {
+                ensure_self_signed_certificate(&self.data_dir)?;
+            }
+        }
+        if let Some(allow) = input.allow_remote_control {
+            self.settings_repo
+                .set_bool(ALLOW_CONTROL_KEY, allow)
+                .map_err(db_err)?;
+        }
+
+        self.get_settings()
+    }
+
+    /// Build the listener configuration, or `None` when remote access is disabled
+    pub fn server_config(&self) -> Result<Option<RemoteServerConfig>, RemoteAccessError> {
+        let settings = self.get_settings()?;
+        if !settings.enabled {
+            return Ok(None);
+        }
+
+        let bind_address = settings
+            .bind_address
+            .parse::<SocketAddr>()
+            .map_err(|_| RemoteAccessError::InvalidAddress(settings.bind_address.clone()))?;
+        let (cert_path, key_path) = ensure_self_signed_certificate(&self.data_dir)?;
+
+        Ok(Some(RemoteServerConfig {
+            bind_address,
+            tls: load_tls_config(&cert_path, &key_path)?,
+            allow_control: settings.allow_remote_control,
+        }))
+    }
+
+    fn cert_path(&self) -> PathBuf {
+        self.data_dir.join("tls").join("cert.pem")
+    }
+}
+
+/// Generate a self-signed certificate in `<data_dir>/tls` unless one exists
+///
+/// Returns the certificate and private key paths.
+pub fn ensure_self_signed_certificate(
+    data_dir: &Path,
+) -> Result<(PathBuf, PathBuf), RemoteAccessError> {
+    let tls_dir = data_dir.join("tls");
+    let cert_path = tls_dir.join("cert.pem");
+    let key_path = tls_dir.join("key.pem");
+
+    if cert_path.exists() && key_path.exists() {
+        return Ok((cert_path, key_path));
+    }
+
+    let mut names = vec!["localhost".to_string(), "127.0.0.1".to_string()];
+    if let Some(hostname) = hostname() {
+        names.push(hostname);
+    }
+
+    let certified = rcgen::generate_simple_self_signed(names)
+        .map_err(|e| RemoteAccessError::Tls(e.to_string()))?;
+
+    std::fs::create_dir_all(&tls_dir)?;
+    std::fs::write(&cert_path, certified.cert.pem())?;
+    std::fs::write(&key_path, certified.key_pair.serialize_pem())?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&key_path, std::fs::Permissions::from_mode(0o600))?;
+    }
+
+    tracing::info!(
+        "Generated self-signed certificate at {}",
+        cert_path.display()
+    );
+    Ok((cert_path, key_path))
+}
+
+/// Load a PEM certificate chain and key into a rustls server config
+fn load_tls_config(cert_path: &Path, key_path: &Path) -> Result<RustlsConfig, RemoteAccessError> {
+    let tls_err = |e: &dyn std::fmt::Display| RemoteAccessError::Tls(e.to_string());
+
+    let certs = CertificateDer::pem_file_iter(cert_path)
+        .map_err(|e| tls_err(&e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| tls_err(&e))?;
+    let key = PrivateKeyDer::from_pem_file(key_path).map_err(|e| tls_err(&e))?;
+
+    let config = rustls::ServerConfig::builder_with_provider(Arc::new(
+        rustls::crypto::ring::default_provider(),
+    ))
+    .with_safe_default_protocol_versions()
+    .map_err(|e| tls_err(&e))?
+    .with_no_client_auth()
+    .with_single_cert(certs, key)
+    .map_err(|e| tls_err(&e))?;
+
+    Ok(RustlsConfig::from_config(Arc::new(config)))
+}
+
+#[cfg(unix)]
+fn hostname() -> Option<String> {
+    let mut buf = [0u8; 256];
+    let result = unsafe { libc::gethostname(buf.as_mut_ptr() as *mut libc::c_char, buf.len()) };
+    if result != 0 {
+        return None;
+    }
+    let len = buf.iter().position(|&b| b == 0).unwrap_or(buf.len());
+    String::from_utf8(buf[..len].to_vec())
+        .ok()
+        .filter(|name| !name.is_empty())
+}
+
+#[cfg(not(unix))]
+fn hostname() -> Option<String> {
+    std::env::var("COMPUTERNAME").ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn create_test_service() -> (RemoteAccessService, TempDir) {
+        let (pool, dir) = crate::db::test_pool();
+
+        let service = RemoteAccessService::new(pool, dir.path().to_path_buf());
+        (service, dir)
+    }
+
+    #[test]
+    fn test_remote_access_disabled_by_default() {
+        let (service, _dir) = create_test_service();
+
+        let settings = service.get_settings().unwrap();
+        assert!(!settings.enabled);
+        assert!(!settings.allow_remote_control);
+        assert_eq!(settings.bind_address, DEFAULT_BIND_ADDRESS);
+        assert!(service.server_config().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_enable_remote_access_generates_certificate() {
+        let (service, _dir) = create_test_service();
+
+        let settings = service
+            .update_settings(UpdateRemoteAccessInput {
+                enabled: Some(true),
+                bind_address: Some("127.0.0.1:0".to_string()),
+                ..Default::default()
+            })
+            .unwrap();
+
+        assert!(settings.enabled);
+        assert!(settings.certificate_path.is_some());
+
+        let config = service.server_config().unwrap().unwrap();
+        assert_eq!(config.bind_address.to_string(), "127.0.0.1:0");
+        assert!(!config.allow_control);
+    }
+
+    #[test]
+    fn test_invalid_bind_address_rejected() {
+        let (service, _dir) = create_test_service();
+
+        let result = service.update_settings(UpdateRemoteAccessInput {
+            bind_address: Some("not-an-address".to_string()),
+            ..Default::default()
+        });
+        assert!(matches!(result, Err(RemoteAccessError::InvalidAddress(_))));
+    }
+}
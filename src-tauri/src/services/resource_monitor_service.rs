@@ -0,0 +1,307 @@
+//! Resource monitor for the memory and CPU of agent processes
+//!
+//! Every few seconds each running agent's process is sampled for its
+//! resident memory and CPU use. Samples go to the agent's subscribers, the
+//! peaks to the agent's run history, and crossing a configured limit logs a
+//! warning and flags the sample. A CPU limit only counts once it was exceeded
+//! for several samples in a row, so short bursts, e.g. while a tool runs,
+//! don't trip it.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use parking_lot::Mutex;
+use sysinfo::{Pid, ProcessRefreshKind, ProcessesToUpdate, System};
+use thiserror::Error;
+use tokio::sync::broadcast;
+
+use crate::db::{AgentRunRepository, DbPool, SettingsRepository};
+use crate::services::ProcessControl;
+use crate::types::{AgentResources, ResourceLimits};
+
+/// Settings key for the resource limits
+const LIMITS_KEY: &str = "resource_limits";
+/// How often agent processes are sampled
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(5);
+/// Samples in a row CPU use has to stay above its limit to count
+const CPU_SUSTAINED_SAMPLES: u32 = 3;
+
+#[derive(Error, Debug)]
+pub enum ResourceMonitorError {
+    #[error("Validation error: {0}")]
+    Validation(String),
+    #[error("Database error: {0}")]
+    Database(String),
+}
+
+/// What is remembered about an agent between samples
+struct AgentSample {
+    resources: AgentResources,
+    /// Samples in a row with CPU use above the limit
+    cpu_over: u32,
+}
+
+pub struct ResourceMonitorService {
+    process_manager: Arc<dyn ProcessControl>,
+    run_repo: AgentRunRepository,
+    settings_repo: SettingsRepository,
+    system: Mutex<System>,
+    samples: Mutex<HashMap<String, AgentSample>>,
+    resources_tx: broadcast::Sender<AgentResources>,
+}
+
+impl ResourceMonitorService {
+    pub fn new(pool: DbPool, process_manager: Arc<dyn ProcessControl>) -> Self {
+        let (resources_tx, _) = broadcast::channel(256);
+        Self {
+            process_manager,
+            run_repo: AgentRunRepository::new(pool.clone()),
+            settings_repo: SettingsRepository::new(pool),
+            system: Mutex::new(System::new()),
+            samples: Mutex::new(HashMap::new()),
+            resources_tx,
+        }
+    }
+
+    /// Subscribe to every sample of every agent
+    pub fn subscribe(&self) -> broadcast::Receiver<AgentResources> {
+        self.resources_tx.subscribe()
+    }
+
+    /// The limits agents are warned about; none until set
+    pub fn get_limits(&self) -> Result<ResourceLimits, ResourceMonitorError> {
+        let value = self
+            .settings_repo
+            .get(LIMITS_KEY)
+            .map_err(|e| ResourceMonitorError::Database(e.to_string()))?;
+        Ok(value
+            .and_then(|v| serde_json::from_str(&v).ok())
+            .unwrap_or_default())
+    }
+
+    /// Change the limits agents are warned about
+    pub fn set_limits(
+        &self,
+        limits: ResourceLimits,
+    ) -> Result<ResourceLimits, ResourceMonitorError> {
+        if limits.memory_mb == Some(0) {
+            return Err(ResourceMonitorError::Validation(
+                "Memory limit must be at least 1 MB".to_string(),
+            ));
+        }
+        if limits
+            .cpu_percent
+            .is_some_and(|cpu| cpu.is_nan() || cpu <= 0.0)
+        {
+            return Err(ResourceMonitorError::Validation(
+                "CPU limit must be above 0 percent".to_string(),
+            ));
+        }
+
+        let value = serde_json::to_string(&limits).unwrap_or_default();
+        self.settings_repo
+            .set(LIMITS_KEY, &value, "json")
+            .map_err(|e| ResourceMonitorError::Database(e.to_string()))?;
+        Ok(limits)
+    }
+
+    /// An agent's latest sample, while it runs
+    pub fn get_resources(&self, agent_id: &str) -> Option<AgentResources> {
+        self.samples
+            .lock()
+            .get(agent_id)
+            .map(|sample| sample.resources.clone())
+    }
+
+    /// Sample every running agent's process
+    pub fn sample(&self) -> Result<Vec<AgentResources>, ResourceMonitorError> {
+        let limits = self.get_limits()?;
+        let running = self.process_manager.running_pids();
+        let pids: Vec<Pid> = running.iter().map(|(_, pid)| Pid::from_u32(*pid)).collect();
+
+        let measured: Vec<(String, u32, u64, f64)> = {
+            let mut system = self.system.lock();
+            system.refresh_processes_specifics(
+                ProcessesToUpdate::Some(&pids),
+                true,
+                ProcessRefreshKind::nothing().with_memory().with_cpu(),
+            );
+            running
+                .into_iter()
+                .filter_map(|(agent_id, pid)| {
+                    let process = system.process(Pid::from_u32(pid))?;
+                    Some((
+                        agent_id,
+                        pid,
+                        process.memory(),
+                        f64::from(process.cpu_usage()),
+                    ))
+                })
+                .collect()
+        };
+
+        let now = chrono::Utc::now().to_rfc3339();
+        let mut samples = self.samples.lock();
+        samples.retain(|agent_id, _| measured.iter().any(|(id, ..)| id == agent_id));
+        let resources: Vec<AgentResources> = measured
+            .into_iter()
+            .map(|(agent_id, pid, memory_bytes, cpu_percent)| {
+                let previous = samples.remove(&agent_id).filter(|s| s.resources.pid == pid);
+                let sample = evaluate(
+                    previous.as_ref(),
+                    &limits,
+                    AgentResources {
+                        agent_id: agent_id.clone(),
+                        pid,
+                        memory_bytes,
+                        cpu_percent,
+                        memory_exceeded: false,
+                        cpu_exceeded: false,
+                        sampled_at: now.clone(),
+                    },
+                );
+                let resources = sample.resources.clone();
+                samples.insert(agent_id, sample);
+                resources
+            })
+            .collect();
+        drop(samples);
+
+        for resources in &resources {
+            if let Err(e) = self.run_repo.record_resources(
+                &resources.agent_id,
+                resources.memory_bytes,
+                resources.cpu_percent,
+            ) {
+                tracing::warn!(
+                    "Failed to record resources of {}: {}",
+                    resources.agent_id,
+                    e
+                );
+            }
+            // No subscribers is fine
+            let _ = self.resources_tx.send(resources.clone());
+        }
+        Ok(resources)
+    }
+
+    /// Sample agent processes for as long as the app runs
+    pub async fn run(&self) {
+        let mut interval = tokio::time::interval(SAMPLE_INTERVAL);
+        loop {
+            interval.tick().await;
+            if let Err(e) = self.sample() {
+                tracing::warn!("Failed to sample agent resources: {}", e);
+            }
+        }
+    }
+}
+
+/// Flag a new sample against the limits, warning when an agent crosses one
+fn evaluate(
+    previous: Option<&AgentSample>,
+    limits: &ResourceLimits,
+    mut resources: AgentResources,
+) -> AgentSample {
+    resources.memory_exceeded = limits
+        .memory_mb
+        .is_some_and(|mb| resources.memory_bytes > mb * 1024 * 1024);
+    let cpu_over = match limits.cpu_percent {
+        Some(limit) if resources.cpu_percent > limit => previous.map_or(0, |p| p.cpu_over) + 1,
+        _ => 0,
+    };
+    resources.cpu_exceeded = cpu_over >= CPU_SUSTAINED_SAMPLES;
+
+    let was = previous.map(|p| &p.resources);
+    if resources.memory_exceeded && !was.is_some_and(|r| r.memory_exceeded) {
+        tracing::warn!(
+            "Agent {} uses {} MB of memory, above the {} MB limit",
+            resources.agent_id,
+            resources.memory_bytes / (1024 * 1024),
+            limits.memory_mb.unwrap_or_default()
+        );
+    }
+    if resources.cpu_exceeded && !was.is_some_and(|r| r.cpu_exceeded) {
+        tracing::warn!(
+            "Agent {} uses {:.0}% CPU, above the {:.0}% limit",
+            resources.agent_id,
+            resources.cpu_percent,
+            limits.cpu_percent.unwrap_or_default()
+        );
+    }
+
+    AgentSample {
+        resources,
+        cpu_over,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::ProcessManager;
+
+    fn resources(memory_mb: u64, cpu_percent: f64) -> AgentResources {
+        AgentResources {
+            agent_id: "ag_1".to_string(),
+            pid: 42,
+            memory_bytes: memory_mb * 1024 * 1024,
+            cpu_percent,
+            memory_exceeded: false,
+            cpu_exceeded: false,
+            sampled_at: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_cpu_limit_needs_sustained_use() {
+        let limits = ResourceLimits {
+            memory_mb: Some(500),
+            cpu_percent: Some(90.0),
+        };
+
+        let sample = evaluate(None, &limits, resources(600, 150.0));
+        assert!(sample.resources.memory_exceeded);
+        assert!(!sample.resources.cpu_exceeded);
+        let sample = evaluate(Some(&sample), &limits, resources(400, 150.0));
+        assert!(!sample.resources.memory_exceeded);
+        assert!(!sample.resources.cpu_exceeded);
+        let sample = evaluate(Some(&sample), &limits, resources(400, 150.0));
+        assert!(sample.resources.cpu_exceeded);
+
+        // One quiet sample starts the count over
+        let sample = evaluate(Some(&sample), &limits, resources(400, 10.0));
+        let sample = evaluate(Some(&sample), &limits, resources(400, 150.0));
+        assert!(!sample.resources.cpu_exceeded);
+
+        let sample = evaluate(None, &ResourceLimits::default(), resources(10_000, 800.0));
+        assert!(!sample.resources.memory_exceeded && !sample.resources.cpu_exceeded);
+    }
+
+    #[test]
+    fn test_limits_are_validated_and_stored() {
+        let (pool, _dir) = crate::db::test_pool();
+        let service =
+            ResourceMonitorService::new(pool, Arc::new(ProcessManager::new("claude".to_string())));
+
+        assert_eq!(service.get_limits().unwrap(), ResourceLimits::default());
+        let limits = ResourceLimits {
+            memory_mb: Some(2048),
+            cpu_percent: None,
+        };
+        service.set_limits(limits).unwrap();
+        assert_eq!(service.get_limits().unwrap(), limits);
+        assert!(matches!(
+            service.set_limits(ResourceLimits {
+                memory_mb: Some(0),
+                cpu_percent: None,
+            }),
+            Err(ResourceMonitorError::Validation(_))
+        ));
+
+        // Nothing running, nothing sampled
+        assert!(service.sample().unwrap().is_empty());
+        assert!(service.get_resources("ag_1").is_none());
+    }
+}
@@ -0,0 +1,503 @@
+//! Schedule service for recurring agent runs
+//!
+//! A schedule names a worktree, a cron expression and a prompt. A background
+//! loop looks for due schedules every half minute and starts a fresh agent
+//! for each, so chores like nightly dependency updates run unattended.
+//! Runs missed while the app was closed are made up once on the next tick.
+//!
+//! During the optional quiet hours set in settings, due schedules are held
+//! back and run once the window ends, unless the override is on.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Local, NaiveTime, Utc};
+use croner::Cron;
+use thiserror::Error;
+
+use crate::db::{DbPool, ScheduleRepository, SettingsRepository, WorktreeRepository};
+use crate::services::process_service::DB_TIMESTAMP_FORMAT;
+use crate::services::{AgentError, AgentService, ChangeFeedService};
+use crate::types::{
+    CreateScheduleInput, EntityKind, Permission, QuietHours, Schedule, SchedulerStatus,
+};
+use crate::util::id::new_id;
+
+/// How often due schedules are looked for
+const TICK_INTERVAL: Duration = Duration::from_secs(30);
+/// Settings key for the daily window in which agents are not started automatically
+const QUIET_HOURS_KEY: &str = "quiet_hours";
+/// Settings key for starting agents during quiet hours anyway
+const QUIET_HOURS_OVERRIDE_KEY: &str = "quiet_hours_override";
+const QUIET_HOURS_FORMAT: &str = "%H:%M";
+
+#[derive(Error, Debug)]
+pub enum ScheduleError {
+    #[error("Schedule not found: {0}")]
+    NotFound(String),
+    #[error("Validation error: {0}")]
+    Validation(String),
+    #[error("Database error: {0}")]
+    Database(String),
+    #[error("Agent error: {0}")]
+    Agent(#[from] AgentError),
+}
+
+pub struct ScheduleService {
+    schedule_repo: ScheduleRepository,
+    worktree_repo: WorktreeRepository,
+    settings_repo: SettingsRepository,
+    agent_service: Arc<AgentService>,
+    change_feed: Arc<ChangeFeedService>,
+}
+
+impl ScheduleService {
+    pub fn new(
+        pool: DbPool,
+        agent_service: Arc<AgentService>,
+        change_feed: Arc<ChangeFeedService>,
+    ) -> Self {
+        Self {
+            schedule_repo: ScheduleRepository::new(pool.clone()),
+            worktree_repo: WorktreeRepository::new(pool.clone()),
+            settings_repo: SettingsRepository::new(pool),
+            agent_service,
+            change_feed,
+        }
+    }
+
+    /// All schedules, or those of one worktree
+    pub fn list_schedules(
+        &self,
+        worktree_id: Option<&str>,
+    ) -> Result<Vec<Schedule>, ScheduleError> {
+        self.schedule_repo
+            .find_all(worktree_id)
+            .map_err(|e| ScheduleError::Database(e.to_string()))
+    }
+
+    /// Get a schedule by ID
+    pub fn get_schedule(&self, id: &str) -> Result<Schedule, ScheduleError> {
+        self.schedule_repo
+            .find_by_id(id)
+            .map_err(|e| ScheduleError::Database(e.to_string()))?
+            .ok_or_else(|| ScheduleError::NotFound(id.to_string()))
+    }
+
+    /// Create a schedule; its first run is the next time the cron expression matches
+    pub fn create_schedule(&self, input: CreateScheduleInput) -> Result<Schedule, ScheduleError> {
+        let name = input.name.trim();
+        if name.is_empty() {
+            return Err(ScheduleError::Validation(
+                "Schedule name must not be empty".to_string(),
+            ));
+        }
+        if input.prompt.trim().is_empty() {
+            return Err(ScheduleError::Validation(
+                "Schedule prompt must not be empty".to_string(),
+            ));
+        }
+        let cron = input.cron.trim();
+        let next_run_at = next_run(cron, Utc::now())?;
+        self.worktree_repo
+            .find_by_id(&input.worktree_id)
+            .map_err(|e| ScheduleError::Database(e.to_string()))?
+            .ok_or_else(|| {
+                ScheduleError::Validation(format!("Worktree not found: {}", input.worktree_id))
+            })?;
+
+        let now = Utc::now().to_rfc3339();
+        let schedule = Schedule {
+            id: new_id("sc"),
+            name: name.to_string(),
+            cron: cron.to_string(),
+            worktree_id: input.worktree_id,
+            mode: input.mode.unwrap_or_default(),
+            permissions: input.permissions.unwrap_or_else(|| vec![Permission::Read]),
+            prompt: input.prompt,
+            paused: false,
+            next_run_at: Some(next_run_at),
+            last_run_at: None,
+            last_agent_id: None,
+            last_error: None,
+            created_at: now.clone(),
+            updated_at: now,
+        };
+
+        self.schedule_repo
+            .create(&schedule)
+            .map_err(|e| ScheduleError::Database(e.to_string()))
+    }
+
+    /// Pause or resume a schedule. Resuming skips the runs missed while paused.
+    pub fn set_paused(&self, id: &str, paused: bool) -> Result<Schedule, ScheduleError> {
+        let schedule = self.get_schedule(id)?;
+        let next_run_at = if paused {
+            None
+        } else {
+            Some(next_run(&schedule.cron, Utc::now())?)
+        };
+
+        self.schedule_repo
+            .set_paused(id, paused, next_run_at.as_deref())
+            .map_err(|e| ScheduleError::Database(e.to_string()))?;
+
+        self.get_schedule(id)
+    }
+
+    /// Delete a schedule; agents it already started are kept
+    pub fn delete_schedule(&self, id: &str) -> Result<(), ScheduleError> {
+        self.get_schedule(id)?;
+        self.schedule_repo
+            .delete(id)
+            .map_err(|e| ScheduleError::Database(e.to_string()))
+    }
+
+    /// The quiet hours window and whether due schedules are held back at `now`
+    pub fn get_status(&self, now: DateTime<Utc>) -> Result<SchedulerStatus, ScheduleError> {
+        let quiet_hours = self.get_quiet_hours()?;
+        let quiet_hours_override = self
+            .settings_repo
+            .get_bool(QUIET_HOURS_OVERRIDE_KEY, false)
+            .map_err(|e| ScheduleError::Database(e.to_string()))?;
+        let in_quiet_hours = quiet_hours
+            .as_ref()
+            .is_some_and(|window| in_window(window, now.with_timezone(&Local).time()));
+        let next_run_at = self
+            .schedule_repo
+            .find_next_run_at()
+            .map_err(|e| ScheduleError::Database(e.to_string()))?;
+
+        Ok(SchedulerStatus {
+            quiet_hours,
+            quiet_hours_override,
+            in_quiet_hours,
+            deferring: in_quiet_hours && !quiet_hours_override,
+            next_run_at,
+        })
+    }
+
+    /// Set the daily quiet hours window, or clear it with None
+    pub fn set_quiet_hours(
+        &self,
+        quiet_hours: Option<QuietHours>,
+    ) -> Result<SchedulerStatus, ScheduleError> {
+        let result = match quiet_hours {
+            Some(window) => {
+                let start = parse_time(&window.start)?;
+                let end = parse_time(&window.end)?;
+                if start == end {
+                    return Err(ScheduleError::Validation(
+                        "Quiet hours must end at a different time than they start".to_string(),
+                    ));
+                }
+                let window = QuietHours {
+                    start: start.format(QUIET_HOURS_FORMAT).to_string(),
+                    end: end.format(QUIET_HOURS_FORMAT).to_string(),
+                };
+                let value = serde_json::to_string(&window).unwrap_or_default();
+                self.settings_repo.set(QUIET_HOURS_KEY, &value, "json")
+            }
+            None => self.settings_repo.delete(QUIET_HOURS_KEY),
+        };
+        result.map_err(|e| ScheduleError::Database(e.to_string()))?;
+
+        self.get_status(Utc::now())
+    }
+
+    /// Start agents during quiet hours anyway, e.g. to let a run go ahead tonight
+    pub fn set_quiet_hours_override(
+        &self,
+        enabled: bool,
+    ) -> Result<SchedulerStatus, ScheduleError> {
+        self.settings_repo
+            .set_bool(QUIET_HOURS_OVERRIDE_KEY, enabled)
+            .map_err(|e| ScheduleError::Database(e.to_string()))?;
+
+        self.get_status(Utc::now())
+    }
+
+    fn get_quiet_hours(&self) -> Result<Option<QuietHours>, ScheduleError> {
+        Ok(self
+            .settings_repo
+            .get(QUIET_HOURS_KEY)
+            .map_err(|e| ScheduleError::Database(e.to_string()))?
+            .and_then(|value| serde_json::from_str(&value).ok()))
+    }
+
+    /// Start an agent for every schedule due at `now`, returning how many
+    /// schedules ran. A schedule whose agent fails to start keeps the error
+    /// and still moves on to its next run. Nothing runs during quiet hours;
+    /// due schedules stay due until they end.
+    pub fn run_due(&self, now: DateTime<Utc>) -> Result<usize, ScheduleError> {
+        if self.get_status(now)?.deferring {
+            return Ok(0);
+        }
+
+        let ran_at = now.format(DB_TIMESTAMP_FORMAT).to_string();
+        let due = self
+            .schedule_repo
+            .find_due(&ran_at)
+            .map_err(|e| ScheduleError::Database(e.to_string()))?;
+
+        for schedule in &due {
+            let (agent_id, result) = self.start_run(schedule);
+            let error = result.err().map(|e| e.to_string());
+            if let Some(error) = &error {
+                tracing::warn!(
+                    "Schedule {} failed to start its agent: {}",
+                    schedule.id,
+                    error
+                );
+            }
+
+            // The expression was checked when the schedule was created
+            let next_run_at = next_run(&schedule.cron, now).ok();
+            self.schedule_repo
+                .record_run(
+                    &schedule.id,
+                    &ran_at,
+                    agent_id.as_deref(),
+                    error.as_deref(),
+                    next_run_at.as_deref(),
+                )
+                .map_err(|e| ScheduleError::Database(e.to_string()))?;
+        }
+
+        Ok(due.len())
+    }
+
+    /// Create and start a schedule's agent. The agent's ID is returned even
+    /// when it fails to start, so the failed run can be looked at.
+    fn start_run(&self, schedule: &Schedule) -> (Option<String>, Result<(), ScheduleError>) {
+        let worktree = match self.worktree_repo.find_by_id(&schedule.worktree_id) {
+            Ok(Some(worktree)) => worktree,
+            Ok(None) => {
+                let error = format!("Worktree not found: {}", schedule.worktree_id);
+                return (None, Err(ScheduleError::Validation(error)));
+            }
+            Err(e) => return (None, Err(ScheduleError::Database(e.to_string()))),
+        };
+
+        let agent = match self.agent_service.create_agent(
+            &worktree.id,
+            Some(schedule.name.clone()),
+            schedule.mode,
+            schedule.permissions.clone(),
+        ) {
+            Ok(agent) => agent,
+            Err(e) => return (None, Err(e.into())),
+        };
+        self.change_feed
+            .created(EntityKind::Agent, &agent.id, &agent);
+
+        let result = self
+            .agent_service
+            .start_agent(&agent.id, &worktree.path, Some(&schedule.prompt))
+            .map(|started| {
+                self.change_feed
+                    .updated(EntityKind::Agent, &started.id, &started)
+            })
+            .map_err(ScheduleError::from);
+        (Some(agent.id), result)
+    }
+
+    /// Run due schedules every half minute, for as long as the app runs
+    pub async fn run(&self) {
+        let mut interval = tokio::time::interval(TICK_INTERVAL);
+        loop {
+            interval.tick().await;
+            match self.run_due(Utc::now()) {
+                Ok(0) => {}
+                Ok(count) => tracing::info!("Started agents for {} schedules", count),
+                Err(e) => tracing::warn!("Failed to run schedules: {}", e),
+            }
+        }
+    }
+}
+
+fn parse_time(time: &str) -> Result<NaiveTime, ScheduleError> {
+    NaiveTime::parse_from_str(time.trim(), QUIET_HOURS_FORMAT).map_err(|_| {
+        ScheduleError::Validation(format!("Quiet hours times must be HH:MM, got: {}", time))
+    })
+}
+
+/// Whether a local time of day falls in the window; a window whose end is
+/// before its start runs past midnight
+fn in_window(window: &QuietHours, time: NaiveTime) -> bool {
+    let (Ok(start), Ok(end)) = (parse_time(&window.start), parse_time(&window.end)) else {
+        return false;
+    };
+    if start <= end {
+        start <= time && time < end
+    } else {
+        time >= start || time < end
+    }
+}
+
+/// The first time after `after` that a five-field cron expression matches in
+/// local time, in the database's UTC format
+fn next_run(cron: &str, after: DateTime<Utc>) -> Result<String, ScheduleError> {
+    let schedule = Cron::new(cron)
+        .parse()
+        .map_err(|e| ScheduleError::Validation(format!("Invalid cron expression: {}", e)))?;
+    let next = schedule
+        .find_next_occurrence(&after.with_timezone(&Local), false)
+        .map_err(|e| ScheduleError::Validation(format!("Invalid cron expression: {}", e)))?;
+
+    Ok(next
+        .with_timezone(&Utc)
+        .format(DB_TIMESTAMP_FORMAT)
+        .to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::ProcessManager;
+    use tempfile::TempDir;
+
+    fn create_test_service() -> (ScheduleService, DbPool, TempDir) {
+        let (pool, dir) = crate::db::test_pool();
+        let conn = pool.get().unwrap();
+        conn.execute_batch(
+            r#"
+            INSERT INTO workspaces (id, name, path) VALUES ('ws_1', 'Test', '/tmp/test');
+            INSERT INTO worktrees (id, workspace_id, name, branch, path)
+                VALUES ('wt_1', 'ws_1', 'main', 'main', '/tmp/nonexistent-worktree');
+        "#,
+        )
+        .unwrap();
+
+        // A CLI that cannot be spawned, so scheduled agents fail to start
+        let process_manager = Arc::new(ProcessManager::new("/nonexistent/claude".to_string()));
+        let agent_service = Arc::new(AgentService::new(pool.clone(), process_manager));
+        let service = ScheduleService::new(
+            pool.clone(),
+            agent_service,
+            Arc::new(ChangeFeedService::new()),
+        );
+        (service, pool, dir)
+    }
+
+    fn input(cron: &str) -> CreateScheduleInput {
+        CreateScheduleInput {
+            name: " Nightly deps ".to_string(),
+            cron: cron.to_string(),
+            worktree_id: "wt_1".to_string(),
+            mode: None,
+            permissions: None,
+            prompt: "Update the dependencies".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_create_schedule_validates() {
+        let (service, _pool, _dir) = create_test_service();
+
+        for bad in [input("0 3 * *"), input("0 0 3 * * *")] {
+            let result = service.create_schedule(bad);
+            assert!(matches!(result, Err(ScheduleError::Validation(_))));
+        }
+        let mut missing = input("0 3 * * *");
+        missing.worktree_id = "wt_missing".to_string();
+        let result = service.create_schedule(missing);
+        assert!(matches!(result, Err(ScheduleError::Validation(_))));
+
+        let schedule = service.create_schedule(input("0 3 * * *")).unwrap();
+        assert_eq!(schedule.name, "Nightly deps");
+        assert_eq!(schedule.permissions, vec![Permission::Read]);
+        let now = Utc::now().format(DB_TIMESTAMP_FORMAT).to_string();
+        assert!(schedule.next_run_at.unwrap() > now);
+    }
+
+    #[test]
+    fn test_due_schedules_start_agents() {
+        let (service, pool, _dir) = create_test_service();
+        let schedule = service.create_schedule(input("*/5 * * * *")).unwrap();
+        let paused = service.create_schedule(input("*/5 * * * *")).unwrap();
+        service.set_paused(&paused.id, true).unwrap();
+
+        // Nothing is due before the first run
+        assert_eq!(service.run_due(Utc::now()).unwrap(), 0);
+
+        let later = Utc::now() + chrono::Duration::minutes(6);
+        assert_eq!(service.run_due(later).unwrap(), 1);
+        assert_eq!(service.run_due(later).unwrap(), 0);
+
+        let schedule = service.get_schedule(&schedule.id).unwrap();
+        let agent_id = schedule.last_agent_id.unwrap();
+        assert!(schedule.last_error.is_some());
+        assert!(schedule.next_run_at.unwrap() > schedule.last_run_at.unwrap());
+
+        let agent = AgentService::new(pool, Arc::new(ProcessManager::new("claude".to_string())))
+            .get_agent(&agent_id)
+            .unwrap();
+        assert_eq!(agent.name, "Nightly deps");
+        assert_eq!(agent.worktree_id, "wt_1");
+
+        let paused = service.get_schedule(&paused.id).unwrap();
+        assert!(paused.next_run_at.is_none());
+        assert!(paused.last_run_at.is_none());
+        let resumed = service.set_paused(&paused.id, false).unwrap();
+        assert!(!resumed.paused);
+        assert!(resumed.next_run_at.is_some());
+    }
+
+    #[test]
+    fn test_in_window_wraps_past_midnight() {
+        let window = |start: &str, end: &str| QuietHours {
+            start: start.to_string(),
+            end: end.to_string(),
+        };
+        let time = |t: &str| NaiveTime::parse_from_str(t, "%H:%M").unwrap();
+
+        assert!(in_window(&window("09:00", "17:00"), time("09:00")));
+        assert!(!in_window(&window("09:00", "17:00"), time("17:00")));
+        assert!(in_window(&window("22:00", "07:00"), time("23:30")));
+        assert!(in_window(&window("22:00", "07:00"), time("06:59")));
+        assert!(!in_window(&window("22:00", "07:00"), time("12:00")));
+    }
+
+    #[test]
+    fn test_quiet_hours_defer_due_schedules() {
+        let (service, _pool, _dir) = create_test_service();
+        let schedule = service.create_schedule(input("*/5 * * * *")).unwrap();
+        let later = Utc::now() + chrono::Duration::minutes(6);
+
+        let result = service.set_quiet_hours(Some(QuietHours {
+            start: "25:00".to_string(),
+            end: "07:00".to_string(),
+        }));
+        assert!(matches!(result, Err(ScheduleError::Validation(_))));
+
+        // A window covering `later` in local time
+        let local = later.with_timezone(&Local).time();
+        let status = service
+            .set_quiet_hours(Some(QuietHours {
+                start: (local - chrono::Duration::hours(1))
+                    .format(QUIET_HOURS_FORMAT)
+                    .to_string(),
+                end: (local + chrono::Duration::hours(1))
+                    .format(QUIET_HOURS_FORMAT)
+                    .to_string(),
+            }))
+            .unwrap();
+        assert!(status.quiet_hours.is_some());
+        assert_eq!(status.next_run_at, schedule.next_run_at);
+
+        assert!(service.get_status(later).unwrap().deferring);
+        assert_eq!(service.run_due(later).unwrap(), 0);
+
+        let status = service.set_quiet_hours_override(true).unwrap();
+        assert!(status.quiet_hours_override);
+        let status = service.get_status(later).unwrap();
+        assert!(status.in_quiet_hours);
+        assert!(!status.deferring);
+        assert_eq!(service.run_due(later).unwrap(), 1);
+
+        let status = service.set_quiet_hours(None).unwrap();
+        assert!(status.quiet_hours.is_none());
+        assert!(!service.get_status(later).unwrap().in_quiet_hours);
+    }
+}
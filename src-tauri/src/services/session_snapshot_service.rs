@@ -0,0 +1,274 @@
+//! Session snapshot service for saving and restoring agent sessions
+//!
+//! Running agents are periodically snapshotted into `agent_sessions`: the
+//! Claude session ID, context level, recent messages and the worktree's
+//! HEAD commit. A snapshot can later be forked into a new agent that
+//! resumes that session.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use thiserror::Error;
+
+use crate::db::{AgentRepository, AgentSessionRepository, DbPool, WorktreeRepository};
+use crate::services::{AgentError, AgentService, GitService, ProcessControl};
+use crate::types::{Agent, AgentSnapshot, SessionData};
+use crate::util::id::new_id;
+
+/// How often running agents are snapshotted
+const SNAPSHOT_INTERVAL: Duration = Duration::from_secs(5 * 60);
+/// Messages kept in each snapshot
+const SNAPSHOT_MESSAGES: usize = 20;
+/// Snapshots kept per agent; older ones are pruned
+const SNAPSHOTS_PER_AGENT: usize = 20;
+
+#[derive(Error, Debug)]
+pub enum SnapshotError {
+    #[error("Snapshot not found: {0}")]
+    NotFound(String),
+    #[error("Agent error: {0}")]
+    Agent(#[from] AgentError),
+    #[error("Database error: {0}")]
+    Database(String),
+}
+
+pub struct SessionSnapshotService {
+    session_repo: AgentSessionRepository,
+    agent_repo: AgentRepository,
+    worktree_repo: WorktreeRepository,
+    agent_service: Arc<AgentService>,
+    process_manager: Arc<dyn ProcessControl>,
+}
+
+impl SessionSnapshotService {
+    pub fn new(
+        pool: DbPool,
+        agent_service: Arc<AgentService>,
+        process_manager: Arc<dyn ProcessControl>,
+    ) -> Self {
+        Self {
+            session_repo: AgentSessionRepository::new(pool.clone()),
+            agent_repo: AgentRepository::new(pool.clone()),
+            worktree_repo: WorktreeRepository::new(pool),
+            agent_service,
+            process_manager,
+        }
+    }
+
+    /// Snapshot an agent's session, returning None when nothing changed
+    /// since its last snapshot
+    pub fn snapshot_agent(&self, agent_id: &str) -> Result<Option<AgentSnapshot>, SnapshotError> {
+        let agent = self.agent_service.get_agent(agent_id)?;
+        let worktree = self
+            .worktree_repo
+            .find_by_id(&agent.worktree_id)
+            .map_err(|e| SnapshotError::Database(e.to_string()))?;
+
+        let (branch, head_commit) = match &worktree {
+            Some(worktree) => (
+                GitService::get_current_branch(&worktree.path).ok(),
+                GitService::head_commit(&worktree.path).ok(),
+            ),
+            None => (None, None),
+        };
+        let data = SessionData {
+            session_id: agent.session_id.clone(),
+            context_level: agent.context_level,
+            worktree_id: agent.worktree_id.clone(),
+            branch,
+            head_commit,
+        };
+        let messages = self
+            .session_repo
+            .recent_messages(agent_id, SNAPSHOT_MESSAGES)
+            .map_err(|e| SnapshotError::Database(e.to_string()))?;
+
+        let session_data = serde_json::to_string(&data).unwrap_or_default();
+        let context_snapshot = serde_json::to_string(&messages).unwrap_or_default();
+
+        let latest = self
+            .session_repo
+            .find_latest_data(agent_id)
+            .map_err(|e| SnapshotError::Database(e.to_string()))?;
+        if latest == Some((session_data.clone(), Some(context_snapshot.clone()))) {
+            return Ok(None);
+        }
+
+        let id = new_id("snap");
+        let snapshot = self
+            .session_repo
+            .create(&id, agent_id, &session_data, &context_snapshot)
+            .map_err(|e| SnapshotError::Database(e.to_string()))?;
+        self.session_repo
+            .prune(agent_id, SNAPSHOTS_PER_AGENT)
+            .map_err(|e| SnapshotError::Database(e.to_string()))?;
+
+        Ok(Some(snapshot))
+    }
+
+    /// Snapshot every running agent, returning how many snapshots were taken
+    pub fn snapshot_running(&self) -> Result<usize, SnapshotError> {
+        let agents = self
+            .agent_repo
+            .find_with_pids()
+            .map_err(|e| SnapshotError::Database(e.to_string()))?;
+
+        let mut taken = 0;
+        for (agent_id, _) in agents {
+            if !self.process_manager.is_running(&agent_id) {
+                continue;
+            }
+            match self.snapshot_agent(&agent_id) {
+                Ok(Some(_)) => taken += 1,
+                Ok(None) => {}
+                Err(e) => tracing::warn!("Failed to snapshot agent {}: {}", agent_id, e),
+            }
+        }
+        Ok(taken)
+    }
+
+    /// List an agent's snapshots, newest first
+    pub fn list_snapshots(&self, agent_id: &str) -> Result<Vec<AgentSnapshot>, SnapshotError> {
+        self.agent_service.get_agent(agent_id)?;
+
+        self.session_repo
+            .find_by_agent_id(agent_id)
+            .map_err(|e| SnapshotError::Database(e.to_string()))
+    }
+
+    /// Fork a new agent from a snapshot, branching the snapshotted session.
+    ///
+    /// The worktree is left as it is; compare its HEAD with the snapshot's
+    /// `head_commit` to see whether the code has moved on since.
+    pub fn restore_from_snapshot(
+        &self,
+        snapshot_id: &str,
+        name: Option<String>,
+    ) -> Result<Agent, SnapshotError> {
+        let snapshot = self
+            .session_repo
+            .find_by_id(snapshot_id)
+            .map_err(|e| SnapshotError::Database(e.to_string()))?
+            .ok_or_else(|| SnapshotError::NotFound(snapshot_id.to_string()))?;
+
+        let mut agent = self
+            .agent_service
+            .fork_agent(&snapshot.agent_id, name, false)?;
+        agent.session_id = None;
+        agent.fork_session_id = snapshot.session_id;
+        agent.context_level = snapshot.context_level;
+
+        self.agent_repo
+            .update(&agent)
+            .map_err(|e| SnapshotError::Database(e.to_string()))
+    }
+
+    /// Snapshot running agents every few minutes, for as long as the app runs
+    pub async fn run(&self) {
+        let mut interval = tokio::time::interval(SNAPSHOT_INTERVAL);
+        // The first tick completes immediately; agents have not started yet
+        interval.tick().await;
+        loop {
+            interval.tick().await;
+            match self.snapshot_running() {
+                Ok(0) => {}
+                Ok(count) => tracing::debug!("Snapshotted {} agent sessions", count),
+                Err(e) => tracing::warn!("Failed to snapshot agent sessions: {}", e),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::ProcessManager;
+    use tempfile::TempDir;
+
+    fn create_test_service() -> (SessionSnapshotService, DbPool, TempDir) {
+        let (pool, dir) = crate::db::test_pool();
+        let conn = pool.get().unwrap();
+        conn.execute_batch(
+            r#"
+            INSERT INTO workspaces (id, name, path) VALUES ('ws_1', 'Test', '/tmp/test');
+            INSERT INTO worktrees (id, workspace_id, name, branch, path)
+                VALUES ('wt_1', 'ws_1', 'main', 'main', '/tmp/nonexistent-worktree');
+            INSERT INTO agents (id, worktree_id, name, context_level, session_id)
+                VALUES ('ag_1', 'wt_1', 'Agent', 42, 'session-1');
+            INSERT INTO messages (id, agent_id, role, content, created_at)
+                VALUES ('msg_1', 'ag_1', 'user', 'Fix the bug', '2024-01-01 00:00:00');
+            INSERT INTO messages (id, agent_id, role, content, created_at)
+                VALUES ('msg_2', 'ag_1', 'assistant', 'Done', '2024-01-01 00:01:00');
+        "#,
+        )
+        .unwrap();
+
+        let process_manager = Arc::new(ProcessManager::new("claude".to_string()));
+        let agent_service = Arc::new(AgentService::new(pool.clone(), process_manager.clone()));
+        let service = SessionSnapshotService::new(pool.clone(), agent_service, process_manager);
+        (service, pool, dir)
+    }
+
+    #[test]
+    fn test_snapshot_agent_skips_unchanged_sessions() {
+        let (service, pool, _dir) = create_test_service();
+
+        let snapshot = service.snapshot_agent("ag_1").unwrap().unwrap();
+        assert_eq!(snapshot.session_id.as_deref(), Some("session-1"));
+        assert_eq!(snapshot.context_level, 42);
+        assert_eq!(snapshot.worktree_id, "wt_1");
+        assert!(snapshot.head_commit.is_none());
+        let contents: Vec<&str> = snapshot
+            .messages
+            .iter()
+            .map(|m| m.content.as_str())
+            .collect();
+        assert_eq!(contents, ["Fix the bug", "Done"]);
+
+        assert!(service.snapshot_agent("ag_1").unwrap().is_none());
+
+        pool.get()
+            .unwrap()
+            .execute("UPDATE agents SET context_level = 60 WHERE id = 'ag_1'", [])
+            .unwrap();
+        assert!(service.snapshot_agent("ag_1").unwrap().is_some());
+
+        let snapshots = service.list_snapshots("ag_1").unwrap();
+        assert_eq!(snapshots.len(), 2);
+        assert_eq!(snapshots[0].context_level, 60);
+
+        assert!(matches!(
+            service.snapshot_agent("ag_missing"),
+            Err(SnapshotError::Agent(AgentError::NotFound(_)))
+        ));
+    }
+
+    #[test]
+    fn test_restore_agent_from_snapshot() {
+        let (service, pool, _dir) = create_test_service();
+        let snapshot = service.snapshot_agent("ag_1").unwrap().unwrap();
+        pool.get()
+            .unwrap()
+            .execute(
+                "UPDATE agents SET context_level = 90, session_id = 'session-2' WHERE id = 'ag_1'",
+                [],
+            )
+            .unwrap();
+
+        let agent = service
+            .restore_from_snapshot(&snapshot.id, Some("Restored".to_string()))
+            .unwrap();
+
+        assert_ne!(agent.id, "ag_1");
+        assert_eq!(agent.name, "Restored");
+        assert_eq!(agent.parent_agent_id.as_deref(), Some("ag_1"));
+        assert!(agent.session_id.is_none());
+        assert_eq!(agent.fork_session_id.as_deref(), Some("session-1"));
+        assert_eq!(agent.context_level, 42);
+
+        assert!(matches!(
+            service.restore_from_snapshot("snap_missing", None),
+            Err(SnapshotError::NotFound(_))
+        ));
+    }
+}
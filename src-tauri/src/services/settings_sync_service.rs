@@ -0,0 +1,701 @@
+//! Settings sync service for the hooks the manager adds to Claude settings
+//!
+//! Agents report their status through hooks in each worktree's
+//! `.claude/settings.local.json`. The file is shared with the user and other
+//! tools, so the manager only ever touches its own hook entries: each carries
+//! a marker comment at the end of its command, and merging first drops every
+//! marked entry and then adds the current set, which makes it idempotent.
+//! Removing the marked entries gives back the file as it was, and deletes it
+//! when nothing else was in it.
+//!
+//! Hooks shared by a workspace are kept in settings and written to every
+//! worktree of the workspace along with the manager's own.
+//!
+//! The hooks post to the running app, so they are taken out again once no
+//! agent in the worktree is running. The worktrees holding them are tracked
+//! in settings, so hooks left behind by a crash are removed on next launch.
+
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use serde_json::{json, Map, Value};
+use thiserror::Error;
+
+use crate::db::{
+    AgentRepository, DbPool, SettingsRepository, WorkspaceRepository, WorktreeRepository,
+};
+use crate::services::ProcessControl;
+use crate::types::{AgentStatus, SettingsSyncResult, SharedHook};
+
+const SETTINGS_FILE: &str = "settings.local.json";
+/// Settings key prefix for a workspace's shared hooks
+const SHARED_HOOKS_KEY: &str = "shared_hooks";
+/// Settings key for the paths of worktrees holding the manager's hooks
+const HOOKED_WORKTREES_KEY: &str = "hooked_worktrees";
+/// Port of the manager's /hooks endpoint
+const HOOK_PORT: u16 = 3001;
+/// Ends every hook command the manager owns. A shell comment, because the
+/// Claude CLI validates hook entries and would reject an extra key.
+const HOOK_MARKER: &str = "# claude-manager";
+/// Notification types the manager listens for to track agent status
+const STATUS_NOTIFICATIONS: [&str; 3] = ["permission_prompt", "idle_prompt", "elicitation_dialog"];
+/// Lifecycle events the manager listens for: a session starting, and the
+/// agent or one of its subagents finishing
+const STATUS_EVENTS: [&str; 3] = ["SessionStart", "Stop", "SubagentStop"];
+/// Hook events the Claude CLI fires
+const HOOK_EVENTS: [&str; 9] = [
+    "PreToolUse",
+    "PostToolUse",
+    "Notification",
+    "UserPromptSubmit",
+    "Stop",
+    "SubagentStop",
+    "PreCompact",
+    "SessionStart",
+    "SessionEnd",
+];
+
+/// Serializes edits to settings files, which agents in one worktree share
+static SETTINGS_LOCK: Mutex<()> = Mutex::new(());
+/// Serializes updates to the tracked worktrees
+static TRACKING_LOCK: Mutex<()> = Mutex::new(());
+
+#[derive(Error, Debug)]
+pub enum SettingsSyncError {
+    #[error("Workspace not found: {0}")]
+    WorkspaceNotFound(String),
+    #[error("Validation error: {0}")]
+    Validation(String),
+    #[error("Invalid settings file {0}: {1}")]
+    InvalidSettings(String, String),
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Database error: {0}")]
+    Database(String),
+}
+
+pub struct SettingsSyncService {
+    worktree_repo: WorktreeRepository,
+    workspace_repo: WorkspaceRepository,
+    settings_repo: SettingsRepository,
+    agent_repo: AgentRepository,
+    process_manager: Option<Arc<dyn ProcessControl>>,
+}
+
+impl SettingsSyncService {
+    pub fn new(pool: DbPool) -> Self {
+        Self {
+            worktree_repo: WorktreeRepository::new(pool.clone()),
+            workspace_repo: WorkspaceRepository::new(pool.clone()),
+            settings_repo: SettingsRepository::new(pool.clone()),
+            agent_repo: AgentRepository::new(pool),
+            process_manager: None,
+        }
+    }
+
+    /// Tell running agents by their live process rather than their status
+    pub fn with_process_manager(mut self, process_manager: Arc<dyn ProcessControl>) -> Self {
+        self.process_manager = Some(process_manager);
+        self
+    }
+
+    /// Write the manager's hooks, and the workspace's shared hooks, into a
+    /// worktree before an agent starts there. Returns whether the file changed.
+    pub fn prepare_worktree(
+        &self,
+        worktree_id: &str,
+        worktree_path: &str,
+    ) -> Result<bool, SettingsSyncError> {
+        let worktree = self
+            .worktree_repo
+            .find_by_id(worktree_id)
+            .map_err(|e| SettingsSyncError::Database(e.to_string()))?;
+        let shared = match worktree {
+            Some(worktree) => self.get_shared_hooks(&worktree.workspace_id)?,
+            None => Vec::new(),
+        };
+
+        let changed = merge_hooks(&settings_path(worktree_path), &shared)?;
+        self.track(worktree_path, true)?;
+        Ok(changed)
+    }
+
+    /// Take the manager's hooks out of a worktree, e.g. when it is deleted.
+    /// Returns whether the file changed.
+    pub fn release_worktree(&self, worktree_path: &str) -> Result<bool, SettingsSyncError> {
+        let changed = remove_hooks(&settings_path(worktree_path))?;
+        self.track(worktree_path, false)?;
+        Ok(changed)
+    }
+
+    /// Take the manager's hooks out of a worktree once none of its agents is
+    /// running. Returns whether the file changed.
+    pub fn release_if_idle(&self, worktree_id: &str) -> Result<bool, SettingsSyncError> {
+        let agents = self
+            .agent_repo
+            .find_by_worktree_id(worktree_id, false)
+            .map_err(|e| SettingsSyncError::Database(e.to_string()))?;
+        let running = agents.iter().any(|agent| match &self.process_manager {
+            Some(pm) => pm.is_running(&agent.id),
+            None => matches!(agent.status, AgentStatus::Running | AgentStatus::Waiting),
+        });
+        if running {
+            return Ok(false);
+        }
+        let Some(worktree) = self
+            .worktree_repo
+            .find_by_id(worktree_id)
+            .map_err(|e| SettingsSyncError::Database(e.to_string()))?
+        else {
+            return Ok(false);
+        };
+
+        self.release_worktree(&worktree.path)
+    }
+
+    /// Take the manager's hooks out of every worktree known to hold them,
+    /// e.g. on shutdown or after a crash. Returns how many files changed.
+    pub fn release_tracked(&self) -> Result<usize, SettingsSyncError> {
+        let mut released = 0;
+        for path in self.tracked()? {
+            match self.release_worktree(&path) {
+                Ok(true) => released += 1,
+                Ok(false) => {}
+                Err(e) => tracing::warn!("Failed to remove hook settings from {}: {}", path, e),
+            }
+        }
+        Ok(released)
+    }
+
+    /// Hooks shared by every worktree in a workspace
+    pub fn get_shared_hooks(
+        &self,
+        workspace_id: &str,
+    ) -> Result<Vec<SharedHook>, SettingsSyncError> {
+        Ok(self
+            .settings_repo
+            .get(&format!("{}:{}", SHARED_HOOKS_KEY, workspace_id))
+            .map_err(|e| SettingsSyncError::Database(e.to_string()))?
+            .and_then(|value| serde_json::from_str(&value).ok())
+            .unwrap_or_default())
+    }
+
+    /// Replace a workspace's shared hooks and write them to each of its
+    /// worktrees holding the manager's hooks; the others get them when an
+    /// agent next starts there. A worktree that can't be updated doesn't stop
+    /// the others.
+    pub fn set_shared_hooks(
+        &self,
+        workspace_id: &str,
+        hooks: Vec<SharedHook>,
+    ) -> Result<Vec<SettingsSyncResult>, SettingsSyncError> {
+        self.workspace_repo
+            .find_by_id(workspace_id)
+            .map_err(|e| SettingsSyncError::Database(e.to_string()))?
+            .ok_or_else(|| SettingsSyncError::WorkspaceNotFound(workspace_id.to_string()))?;
+
+        let hooks = hooks
+            .into_iter()
+            .map(validate_hook)
+            .collect::<Result<Vec<_>, _>>()?;
+        let key = format!("{}:{}", SHARED_HOOKS_KEY, workspace_id);
+        let result = if hooks.is_empty() {
+            self.settings_repo.delete(&key)
+        } else {
+            let value = serde_json::to_string(&hooks).unwrap_or_default();
+            self.settings_repo.set(&key, &value, "json")
+        };
+        result.map_err(|e| SettingsSyncError::Database(e.to_string()))?;
+
+        let tracked = self.tracked()?;
+        let worktrees = self
+            .worktree_repo
+            .find_by_workspace_id(workspace_id)
+            .map_err(|e| SettingsSyncError::Database(e.to_string()))?;
+        Ok(worktrees
+            .into_iter()
+            .filter(|worktree| tracked.contains(&worktree.path))
+            .map(|worktree| {
+                let outcome = merge_hooks(&settings_path(&worktree.path), &hooks);
+                sync_result(worktree.id, worktree.path, outcome)
+            })
+            .collect())
+    }
+
+    /// Take the manager's hooks out of every worktree, leaving the settings
+    /// files as they were before the manager was used
+    pub fn remove_all(&self) -> Result<Vec<SettingsSyncResult>, SettingsSyncError> {
+        let worktrees = self
+            .worktree_repo
+            .find_all()
+            .map_err(|e| SettingsSyncError::Database(e.to_string()))?;
+        Ok(worktrees
+            .into_iter()
+            .map(|worktree| {
+                let outcome = self.release_worktree(&worktree.path);
+                sync_result(worktree.id, worktree.path, outcome)
+            })
+            .collect())
+    }
+
+    fn tracked(&self) -> Result<Vec<String>, SettingsSyncError> {
+        Ok(self
+            .settings_repo
+            .get(HOOKED_WORKTREES_KEY)
+            .map_err(|e| SettingsSyncError::Database(e.to_string()))?
+            .and_then(|value| serde_json::from_str(&value).ok())
+            .unwrap_or_default())
+    }
+
+    /// Add or remove a worktree path from the tracked set
+    fn track(&self, worktree_path: &str, hooked: bool) -> Result<(), SettingsSyncError> {
+        let _guard = TRACKING_LOCK.lock().unwrap();
+        let mut paths = self.tracked()?;
+        let present = paths.iter().any(|p| p == worktree_path);
+        if present == hooked {
+            return Ok(());
+        }
+        if hooked {
+            paths.push(worktree_path.to_string());
+        } else {
+            paths.retain(|p| p != worktree_path);
+        }
+
+        let result = if paths.is_empty() {
+            self.settings_repo.delete(HOOKED_WORKTREES_KEY)
+        } else {
+            let value = serde_json::to_string(&paths).unwrap_or_default();
+            self.settings_repo.set(HOOKED_WORKTREES_KEY, &value, "json")
+        };
+        result.map_err(|e| SettingsSyncError::Database(e.to_string()))
+    }
+}
+
+fn settings_path(worktree_path: &str) -> PathBuf {
+    Path::new(worktree_path).join(".claude").join(SETTINGS_FILE)
+}
+
+fn sync_result(
+    worktree_id: String,
+    worktree_path: String,
+    outcome: Result<bool, SettingsSyncError>,
+) -> SettingsSyncResult {
+    let (changed, error) = match outcome {
+        Ok(changed) => (changed, None),
+        Err(e) => (false, Some(e.to_string())),
+    };
+    SettingsSyncResult {
+        worktree_id,
+        worktree_path,
+        changed,
+        error,
+    }
+}
+
+fn validate_hook(hook: SharedHook) -> Result<SharedHook, SettingsSyncError> {
+    let event = hook.event.trim();
+    if !HOOK_EVENTS.contains(&event) {
+        return Err(SettingsSyncError::Validation(format!(
+            "Unknown hook event: {}",
+            hook.event
+        )));
+    }
+    let command = hook.command.trim();
+    if command.is_empty() || command.contains('\n') {
+        return Err(SettingsSyncError::Validation(
+            "Hook commands must be a single non-empty line".to_string(),
+        ));
+    }
+
+    Ok(SharedHook {
+        event: event.to_string(),
+        matcher: hook
+            .matcher
+            .map(|m| m.trim().to_string())
+            .filter(|m| !m.is_empty()),
+        command: command.to_string(),
+    })
+}
+
+/// The hooks the manager owns: its status notifications and events, then the
+/// shared ones
+fn manager_hooks(shared: &[SharedHook]) -> Vec<(String, Option<String>, String)> {
+    // curl posts stdin (hook JSON) to our /hooks endpoint
+    let curl_cmd = format!(
+        "curl -s -X POST http://127.0.0.1:{HOOK_PORT}/hooks -H 'Content-Type: application/json' -d @-"
+    );
+    let mut hooks: Vec<_> = STATUS_NOTIFICATIONS
+        .iter()
+        .map(|matcher| {
+            (
+                "Notification".to_string(),
+                Some(matcher.to_string()),
+                curl_cmd.clone(),
+            )
+        })
+        .collect();
+    hooks.extend(
+        STATUS_EVENTS
+            .iter()
+            .map(|event| (event.to_string(), None, curl_cmd.clone())),
+    );
+    hooks.extend(shared.iter().map(|hook| {
+        (
+            hook.event.clone(),
+            hook.matcher.clone(),
+            hook.command.clone(),
+        )
+    }));
+    hooks
+}
+
+/// Whether a hook command was written by the manager, including the unmarked
+/// status hooks of earlier versions
+fn is_manager_command(command: &str) -> bool {
+    command.ends_with(HOOK_MARKER)
+        || (command.starts_with("curl -s -X POST http://127.0.0.1:") && command.contains("/hooks "))
+}
+
+/// Drop every hook entry the manager owns, and any event or `hooks` object
+/// left empty by that
+fn strip_manager_hooks(settings: &mut Value) {
+    let Some(hooks) = settings.get_mut("hooks").and_then(Value::as_object_mut) else {
+        return;
+    };
+    for entries in hooks.values_mut() {
+        if let Some(entries) = entries.as_array_mut() {
+            entries.retain(|entry| {
+                let commands: Vec<&str> = entry["hooks"]
+                    .as_array()
+                    .map(|h| h.iter().filter_map(|h| h["command"].as_str()).collect())
+                    .unwrap_or_default();
+                commands.is_empty() || !commands.iter().all(|c| is_manager_command(c))
+            });
+        }
+    }
+    hooks.retain(|_, entries| entries.as_array().map_or(true, |e| !e.is_empty()));
+    if hooks.is_empty() {
+        if let Some(settings) = settings.as_object_mut() {
+            settings.remove("hooks");
+        }
+    }
+}
+
+fn read_settings(path: &Path) -> Result<Option<Value>, SettingsSyncError> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let content = std::fs::read_to_string(path)?;
+    if content.trim().is_empty() {
+        return Ok(Some(json!({})));
+    }
+    let settings: Value = serde_json::from_str(&content).map_err(|e| {
+        SettingsSyncError::InvalidSettings(path.display().to_string(), e.to_string())
+    })?;
+    if !settings.is_object() {
+        return Err(SettingsSyncError::InvalidSettings(
+            path.display().to_string(),
+            "expected a JSON object".to_string(),
+        ));
+    }
+    Ok(Some(settings))
+}
+
+fn write_settings(path: &Path, settings: &Value) -> Result<(), SettingsSyncError> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let content = serde_json::to_string_pretty(settings).map_err(|e| {
+        SettingsSyncError::InvalidSettings(path.display().to_string(), e.to_string())
+    })?;
+    std::fs::write(path, content)?;
+    Ok(())
+}
+
+/// Replace the manager's hook entries in a settings file with the current
+/// ones, keeping everything else
+fn merge_hooks(path: &Path, shared: &[SharedHook]) -> Result<bool, SettingsSyncError> {
+    let _guard = SETTINGS_LOCK.lock().unwrap();
+    let original = read_settings(path)?;
+    let mut settings = original.clone().unwrap_or_else(|| json!({}));
+    strip_manager_hooks(&mut settings);
+
+    if !settings["hooks"].is_object() {
+        settings["hooks"] = Value::Object(Map::new());
+    }
+    for (event, matcher, command) in manager_hooks(shared) {
+        let mut entry = Map::new();
+        if let Some(matcher) = matcher {
+            entry.insert("matcher".to_string(), json!(matcher));
+        }
+        entry.insert(
+            "hooks".to_string(),
+            json!([{ "type": "command", "command": format!("{} {}", command, HOOK_MARKER) }]),
+        );
+
+        let entries = &mut settings["hooks"][event.as_str()];
+        if !entries.is_array() {
+            *entries = json!([]);
+        }
+        if let Some(entries) = entries.as_array_mut() {
+            entries.push(Value::Object(entry));
+        }
+    }
+
+    if original.as_ref() == Some(&settings) {
+        return Ok(false);
+    }
+    write_settings(path, &settings)?;
+    Ok(true)
+}
+
+/// Take the manager's hook entries out of a settings file, deleting the file
+/// when nothing else is left in it
+fn remove_hooks(path: &Path) -> Result<bool, SettingsSyncError> {
+    let _guard = SETTINGS_LOCK.lock().unwrap();
+    let Some(original) = read_settings(path)? else {
+        return Ok(false);
+    };
+    let mut settings = original.clone();
+    strip_manager_hooks(&mut settings);
+
+    if settings == original {
+        return Ok(false);
+    }
+    if settings.as_object().is_some_and(|s| s.is_empty()) {
+        std::fs::remove_file(path)?;
+        // Only removed when empty, i.e. the manager created it
+        if let Some(dir) = path.parent() {
+            let _ = std::fs::remove_dir(dir);
+        }
+    } else {
+        write_settings(path, &settings)?;
+    }
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn create_test_service() -> (SettingsSyncService, DbPool, TempDir) {
+        let (pool, dir) = crate::db::test_pool();
+        let conn = pool.get().unwrap();
+        for name in ["main", "feature"] {
+            let path = dir.path().join(name);
+            std::fs::create_dir_all(&path).unwrap();
+            conn.execute(
+                "INSERT OR IGNORE INTO workspaces (id, name, path) VALUES ('ws_1', 'Test', ?)",
+                [dir.path().to_str().unwrap()],
+            )
+            .unwrap();
+            conn.execute(
+                "INSERT INTO worktrees (id, workspace_id, name, branch, path)
+                 VALUES (?, 'ws_1', ?, ?, ?)",
+                [
+                    format!("wt_{}", name),
+                    name.to_string(),
+                    name.to_string(),
+                    path.to_str().unwrap().to_string(),
+                ],
+            )
+            .unwrap();
+        }
+
+        (SettingsSyncService::new(pool.clone()), pool, dir)
+    }
+
+    fn read(path: &Path) -> Value {
+        serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_prepare_creates_status_hooks_and_release_removes_file() {
+        let (service, _pool, dir) = create_test_service();
+        let worktree = dir.path().join("main");
+        let path = settings_path(worktree.to_str().unwrap());
+
+        assert!(service
+            .prepare_worktree("wt_main", worktree.to_str().unwrap())
+            .unwrap());
+        let settings = read(&path);
+        let notifications = settings["hooks"]["Notification"].as_array().unwrap();
+        let matchers: Vec<&str> = notifications
+            .iter()
+            .map(|n| n["matcher"].as_str().unwrap())
+            .collect();
+        assert_eq!(
+            matchers,
+            ["permission_prompt", "idle_prompt", "elicitation_dialog"]
+        );
+        let command = notifications[0]["hooks"][0]["command"].as_str().unwrap();
+        assert!(command.contains("127.0.0.1:3001/hooks"));
+        assert!(command.ends_with(HOOK_MARKER));
+        for event in ["SessionStart", "Stop", "SubagentStop"] {
+            let entry = &settings["hooks"][event][0];
+            assert!(entry["matcher"].is_null());
+            assert_eq!(entry["hooks"][0]["command"], command);
+        }
+
+        // Merging again changes nothing
+        assert!(!service
+            .prepare_worktree("wt_main", worktree.to_str().unwrap())
+            .unwrap());
+
+        assert!(service
+            .release_worktree(worktree.to_str().unwrap())
+            .unwrap());
+        assert!(!path.exists());
+        assert!(!path.parent().unwrap().exists());
+    }
+
+    #[test]
+    fn test_user_settings_and_hooks_survive_merge_and_release() {
+        let (service, _pool, dir) = create_test_service();
+        let worktree = dir.path().join("main");
+        let path = settings_path(worktree.to_str().unwrap());
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        let original = json!({
+            "someExistingSetting": true,
+            "hooks": {
+                "Notification": [
+                    { "matcher": "idle_prompt", "hooks": [{ "type": "command", "command": "notify-send idle" }] },
+                    // Status hook from an earlier version, without the marker
+                    { "matcher": "idle_prompt", "hooks": [{ "type": "command", "command": "curl -s -X POST http://127.0.0.1:3001/hooks -H 'Content-Type: application/json' -d @-" }] }
+                ],
+                "Stop": [{ "hooks": [{ "type": "command", "command": "say done" }] }]
+            }
+        });
+        std::fs::write(&path, original.to_string()).unwrap();
+
+        service
+            .prepare_worktree("wt_main", worktree.to_str().unwrap())
+            .unwrap();
+        let settings = read(&path);
+        assert_eq!(settings["someExistingSetting"], true);
+        assert_eq!(
+            settings["hooks"]["Notification"].as_array().unwrap().len(),
+            4
+        );
+        assert_eq!(
+            settings["hooks"]["Notification"][0]["hooks"][0]["command"],
+            "notify-send idle"
+        );
+        assert_eq!(
+            settings["hooks"]["Stop"][0]["hooks"][0]["command"],
+            "say done"
+        );
+
+        service
+            .release_worktree(worktree.to_str().unwrap())
+            .unwrap();
+        let settings = read(&path);
+        assert_eq!(settings["someExistingSetting"], true);
+        assert_eq!(
+            settings["hooks"]["Notification"].as_array().unwrap().len(),
+            1
+        );
+        assert_eq!(
+            settings["hooks"]["Stop"][0]["hooks"][0]["command"],
+            "say done"
+        );
+    }
+
+    #[test]
+    fn test_shared_hooks_propagate_to_every_worktree() {
+        let (service, _pool, dir) = create_test_service();
+        let worktree = |name: &str| dir.path().join(name).to_str().unwrap().to_string();
+        service
+            .prepare_worktree("wt_main", &worktree("main"))
+            .unwrap();
+        let hook = SharedHook {
+            event: "PostToolUse".to_string(),
+            matcher: Some(" Edit|Write ".to_string()),
+            command: "cargo fmt".to_string(),
+        };
+
+        // Only the worktree holding the manager's hooks is updated now
+        let results = service.set_shared_hooks("ws_1", vec![hook]).unwrap();
+        assert_eq!(results.len(), 1);
+        assert!(results[0].changed && results[0].error.is_none());
+        assert!(!settings_path(&worktree("feature")).exists());
+        service
+            .prepare_worktree("wt_feature", &worktree("feature"))
+            .unwrap();
+        assert_eq!(
+            service.get_shared_hooks("ws_1").unwrap()[0]
+                .matcher
+                .as_deref(),
+            Some("Edit|Write")
+        );
+        for name in ["main", "feature"] {
+            let settings = read(&settings_path(dir.path().join(name).to_str().unwrap()));
+            let entry = &settings["hooks"]["PostToolUse"][0];
+            assert_eq!(entry["matcher"], "Edit|Write");
+            assert_eq!(
+                entry["hooks"][0]["command"],
+                format!("cargo fmt {}", HOOK_MARKER)
+            );
+        }
+
+        // Clearing them takes them out again, leaving the status hooks
+        service.set_shared_hooks("ws_1", Vec::new()).unwrap();
+        let settings = read(&settings_path(dir.path().join("main").to_str().unwrap()));
+        assert!(settings["hooks"]["PostToolUse"].is_null());
+        assert!(settings["hooks"]["Notification"].is_array());
+
+        let results = service.remove_all().unwrap();
+        assert!(results.iter().all(|r| r.changed));
+        assert!(!dir.path().join("main").join(".claude").exists());
+
+        let invalid = SharedHook {
+            event: "OnSave".to_string(),
+            matcher: None,
+            command: "true".to_string(),
+        };
+        assert!(matches!(
+            service.set_shared_hooks("ws_1", vec![invalid]),
+            Err(SettingsSyncError::Validation(_))
+        ));
+        assert!(matches!(
+            service.set_shared_hooks("ws_missing", Vec::new()),
+            Err(SettingsSyncError::WorkspaceNotFound(_))
+        ));
+    }
+    #[test]
+    fn test_release_if_idle_and_tracked() {
+        let (service, pool, dir) = create_test_service();
+        let worktree = |name: &str| dir.path().join(name).to_str().unwrap().to_string();
+        let conn = pool.get().unwrap();
+        conn.execute(
+            "INSERT INTO agents (id, worktree_id, name, status)
+             VALUES ('ag_1', 'wt_main', 'Agent', 'running')",
+            [],
+        )
+        .unwrap();
+        for name in ["main", "feature"] {
+            service
+                .prepare_worktree(&format!("wt_{}", name), &worktree(name))
+                .unwrap();
+        }
+
+        // An agent is still running in main
+        assert!(!service.release_if_idle("wt_main").unwrap());
+        assert!(settings_path(&worktree("main")).exists());
+        assert!(service.release_if_idle("wt_feature").unwrap());
+        assert!(!settings_path(&worktree("feature")).exists());
+
+        conn.execute("UPDATE agents SET status = 'idle'", [])
+            .unwrap();
+        assert!(service.release_if_idle("wt_main").unwrap());
+
+        // Hooks left behind, e.g. by a crash, go on the next sweep
+        service
+            .prepare_worktree("wt_main", &worktree("main"))
+            .unwrap();
+        assert_eq!(service.release_tracked().unwrap(), 1);
+        assert!(!settings_path(&worktree("main")).exists());
+        assert_eq!(service.release_tracked().unwrap(), 0);
+    }
+}
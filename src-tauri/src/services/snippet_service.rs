@@ -0,0 +1,430 @@
+//! Snippet service for canned prompts sent to agent terminals
+//!
+//! Snippets are global or scoped to a workspace or worktree. Sending one
+//! fills in `{{name}}` placeholders and types the result into the agent's
+//! PTY as if the user had entered it.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use thiserror::Error;
+
+use crate::db::{
+    AgentRepository, DbPool, SnippetRepository, WorkspaceRepository, WorktreeRepository,
+};
+use crate::services::{ProcessControl, ProcessError};
+use crate::types::{CreateSnippetInput, Snippet, SnippetScope, UpdateSnippetInput};
+use crate::util::id::new_id;
+
+#[derive(Error, Debug)]
+pub enum SnippetError {
+    #[error("Snippet not found: {0}")]
+    NotFound(String),
+    #[error("Agent not found: {0}")]
+    AgentNotFound(String),
+    #[error("Validation error: {0}")]
+    Validation(String),
+    #[error("Database error: {0}")]
+    Database(String),
+    #[error("Process error: {0}")]
+    Process(#[from] ProcessError),
+}
+
+pub struct SnippetService {
+    snippet_repo: SnippetRepository,
+    agent_repo: AgentRepository,
+    worktree_repo: WorktreeRepository,
+    workspace_repo: WorkspaceRepository,
+    process_manager: Arc<dyn ProcessControl>,
+}
+
+impl SnippetService {
+    pub fn new(pool: DbPool, process_manager: Arc<dyn ProcessControl>) -> Self {
+        Self {
+            snippet_repo: SnippetRepository::new(pool.clone()),
+            agent_repo: AgentRepository::new(pool.clone()),
+            worktree_repo: WorktreeRepository::new(pool.clone()),
+            workspace_repo: WorkspaceRepository::new(pool),
+            process_manager,
+        }
+    }
+
+    /// Snippets offered for a workspace or worktree, including global ones.
+    ///
+    /// A worktree's workspace snippets are included even when
+    /// `workspace_id` is not given.
+    pub fn list_snippets(
+        &self,
+        workspace_id: Option<&str>,
+        worktree_id: Option<&str>,
+    ) -> Result<Vec<Snippet>, SnippetError> {
+        let mut workspace_id = workspace_id.map(|id| id.to_string());
+        if let (None, Some(worktree_id)) = (&workspace_id, worktree_id) {
+            workspace_id = self
+                .worktree_repo
+                .find_by_id(worktree_id)
+                .map_err(|e| SnippetError::Database(e.to_string()))?
+                .map(|worktree| worktree.workspace_id);
+        }
+
+        self.snippet_repo
+            .find_available(workspace_id.as_deref(), worktree_id)
+            .map_err(|e| SnippetError::Database(e.to_string()))
+    }
+
+    /// Get a snippet by ID
+    pub fn get_snippet(&self, id: &str) -> Result<Snippet, SnippetError> {
+        self.snippet_repo
+            .find_by_id(id)
+            .map_err(|e| SnippetError::Database(e.to_string()))?
+            .ok_or_else(|| SnippetError::NotFound(id.to_string()))
+    }
+
+    /// Create a snippet; scoped snippets must name the workspace or worktree they belong to
+    pub fn create_snippet(&self, input: CreateSnippetInput) -> Result<Snippet, SnippetError> {
+        let name = validate_name(&input.name)?;
+        let scope = input.scope.unwrap_or_default();
+
+        let (workspace_id, worktree_id) = match scope {
+            SnippetScope::Global => (None, None),
+            SnippetScope::Workspace => {
+                let workspace_id = input.workspace_id.ok_or_else(|| {
+                    SnippetError::Validation(
+                        "Workspace snippets require a workspace ID".to_string(),
+                    )
+                })?;
+                self.workspace_repo
+                    .find_by_id(&workspace_id)
+                    .map_err(|e| SnippetError::Database(e.to_string()))?
+                    .ok_or_else(|| {
+                        SnippetError::Validation(format!("Workspace not found: {}", workspace_id))
+                    })?;
+                (Some(workspace_id), None)
+            }
+            SnippetScope::Worktree => {
+                let worktree_id = input.worktree_id.ok_or_else(|| {
+                    SnippetError::Validation("Worktree snippets require a worktree ID".to_string())
+                })?;
+                let worktree = self
+                    .worktree_repo
+                    .find_by_id(&worktree_id)
+                    .map_err(|e| SnippetError::Database(e.to_string()))?
+                    .ok_or_else(|| {
+                        SnippetError::Validation(format!("Worktree not found: {}", worktree_id))
+                    })?;
+                (Some(worktree.workspace_id), Some(worktree_id))
+            }
+        };
+
+        let now = chrono::Utc::now().to_rfc3339();
+        let snippet = Snippet {
+            id: new_id("sn"),
+            name,
+            body: input.body,
+            scope,
+            workspace_id,
+            worktree_id,
+            created_at: now.clone(),
+            updated_at: now,
+        };
+
+        self.snippet_repo
+            .create(&snippet)
+            .map_err(|e| SnippetError::Database(e.to_string()))
+    }
+
+    /// Rename a snippet or replace its body
+    pub fn update_snippet(
+        &self,
+        id: &str,
+        input: UpdateSnippetInput,
+    ) -> Result<Snippet, SnippetError> {
+        let mut snippet = self.get_snippet(id)?;
+
+        if let Some(name) = input.name {
+            snippet.name = validate_name(&name)?;
+        }
+        if let Some(body) = input.body {
+            snippet.body = body;
+        }
+
+        self.snippet_repo
+            .update(id, &snippet.name, &snippet.body)
+            .map_err(|e| SnippetError::Database(e.to_string()))?;
+
+        self.get_snippet(id)
+    }
+
+    /// Delete a snippet
+    pub fn delete_snippet(&self, id: &str) -> Result<(), SnippetError> {
+        self.get_snippet(id)?;
+
+        self.snippet_repo
+            .delete(id)
+            .map_err(|e| SnippetError::Database(e.to_string()))
+    }
+
+    /// Fill in a snippet's placeholders and type it into a running agent's
+    /// terminal, returning the text that was sent.
+    ///
+    /// `agent_name`, `worktree_name`, `branch` and `worktree_path` are
+    /// provided for the agent; entries in `vars` take precedence.
+    pub fn send_snippet_to_agent(
+        &self,
+        agent_id: &str,
+        snippet_id: &str,
+        vars: &HashMap<String, String>,
+    ) -> Result<String, SnippetError> {
+        let agent = self
+            .agent_repo
+            .find_by_id(agent_id)
+            .map_err(|e| SnippetError::Database(e.to_string()))?
+            .ok_or_else(|| SnippetError::AgentNotFound(agent_id.to_string()))?;
+        let worktree = self
+            .worktree_repo
+            .find_by_id(&agent.worktree_id)
+            .map_err(|e| SnippetError::Database(e.to_string()))?
+            .ok_or_else(|| {
+                SnippetError::Database(format!("Worktree not found: {}", agent.worktree_id))
+            })?;
+        let snippet = self.get_snippet(snippet_id)?;
+
+        let applies = match snippet.scope {
+            SnippetScope::Global => true,
+            SnippetScope::Workspace => {
+                snippet.workspace_id.as_deref() == Some(&worktree.workspace_id)
+            }
+            SnippetScope::Worktree => snippet.worktree_id.as_deref() == Some(&worktree.id),
+        };
+        if !applies {
+            return Err(SnippetError::Validation(format!(
+                "Snippet {} is not available to agent {}",
+                snippet_id, agent_id
+            )));
+        }
+
+        let mut values = HashMap::from([
+            ("agent_name".to_string(), agent.name),
+            ("worktree_name".to_string(), worktree.name),
+            ("branch".to_string(), worktree.branch),
+            ("worktree_path".to_string(), worktree.path),
+        ]);
+        values.extend(vars.iter().map(|(k, v)| (k.clone(), v.clone())));
+        let text = render_template(&snippet.body, &values)?;
+
+        self.process_manager.send_message(agent_id, &text)?;
+
+        Ok(text)
+    }
+}
+
+fn validate_name(name: &str) -> Result<String, SnippetError> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(SnippetError::Validation(
+            "Snippet name must not be empty".to_string(),
+        ));
+    }
+    Ok(name.to_string())
+}
+
+/// Replace `{{name}}` placeholders with values from `vars`.
+///
+/// Whitespace inside the braces is ignored. Braces around anything other
+/// than a name made of letters, digits and underscores are left as-is.
+fn render_template(body: &str, vars: &HashMap<String, String>) -> Result<String, SnippetError> {
+    let mut output = String::with_capacity(body.len());
+    let mut rest = body;
+
+    while let Some(start) = rest.find("{{") {
+        output.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let Some(end) = after.find("}}") else {
+            output.push_str(&rest[start..]);
+            return Ok(output);
+        };
+
+        let name = after[..end].trim();
+        let is_name =
+            !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if is_name {
+            let value = vars.get(name).ok_or_else(|| {
+                SnippetError::Validation(format!("No value for template variable: {}", name))
+            })?;
+            output.push_str(value);
+        } else {
+            output.push_str(&rest[start..start + 2 + end + 2]);
+        }
+        rest = &after[end + 2..];
+    }
+
+    output.push_str(rest);
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::ProcessManager;
+    use tempfile::TempDir;
+
+    fn create_test_service() -> (SnippetService, TempDir) {
+        let (pool, dir) = crate::db::test_pool();
+        let conn = pool.get().unwrap();
+        conn.execute_batch(
+            r#"
+            INSERT INTO workspaces (id, name, path) VALUES ('ws_1', 'Test', '/tmp/test');
+            INSERT INTO workspaces (id, name, path) VALUES ('ws_2', 'Other', '/tmp/other');
+            INSERT INTO worktrees (id, workspace_id, name, branch, path)
+                VALUES ('wt_1', 'ws_1', 'main', 'main', '/tmp/test');
+            INSERT INTO worktrees (id, workspace_id, name, branch, path)
+                VALUES ('wt_2', 'ws_1', 'feature', 'feature/x', '/tmp/test-feature');
+            INSERT INTO agents (id, worktree_id, name) VALUES ('ag_1', 'wt_1', 'Agent');
+        "#,
+        )
+        .unwrap();
+
+        let process_manager = Arc::new(ProcessManager::new("claude".to_string()));
+        (SnippetService::new(pool, process_manager), dir)
+    }
+
+    fn input(name: &str, scope: SnippetScope) -> CreateSnippetInput {
+        CreateSnippetInput {
+            name: name.to_string(),
+            body: "Run the tests on {{branch}}".to_string(),
+            scope: Some(scope),
+            workspace_id: None,
+            worktree_id: None,
+        }
+    }
+
+    #[test]
+    fn test_render_template() {
+        let vars = HashMap::from([
+            ("branch".to_string(), "main".to_string()),
+            ("file".to_string(), "src/lib.rs".to_string()),
+        ]);
+
+        assert_eq!(
+            render_template("Review {{file}} on {{ branch }}", &vars).unwrap(),
+            "Review src/lib.rs on main"
+        );
+        assert_eq!(
+            render_template("Keep {{ not a name }} and {{}} and {{branch", &vars).unwrap(),
+            "Keep {{ not a name }} and {{}} and {{branch"
+        );
+        assert!(matches!(
+            render_template("Fix {{issue}}", &vars),
+            Err(SnippetError::Validation(_))
+        ));
+    }
+
+    #[test]
+    fn test_create_and_list_by_scope() {
+        let (service, _dir) = create_test_service();
+
+        service
+            .create_snippet(input("Global", SnippetScope::Global))
+            .unwrap();
+        let workspace = service
+            .create_snippet(CreateSnippetInput {
+                workspace_id: Some("ws_1".to_string()),
+                ..input("Workspace", SnippetScope::Workspace)
+            })
+            .unwrap();
+        let worktree = service
+            .create_snippet(CreateSnippetInput {
+                worktree_id: Some("wt_2".to_string()),
+                ..input("Worktree", SnippetScope::Worktree)
+            })
+            .unwrap();
+        assert_eq!(workspace.workspace_id.as_deref(), Some("ws_1"));
+        assert_eq!(worktree.workspace_id.as_deref(), Some("ws_1"));
+
+        let names = |snippets: Vec<Snippet>| -> Vec<String> {
+            snippets.into_iter().map(|s| s.name).collect()
+        };
+        assert_eq!(
+            names(service.list_snippets(None, None).unwrap()),
+            ["Global"]
+        );
+        assert_eq!(
+            names(service.list_snippets(Some("ws_1"), None).unwrap()),
+            ["Global", "Workspace"]
+        );
+        assert_eq!(
+            names(service.list_snippets(None, Some("wt_2")).unwrap()),
+            ["Global", "Workspace", "Worktree"]
+        );
+        assert_eq!(
+            names(service.list_snippets(Some("ws_2"), None).unwrap()),
+            ["Global"]
+        );
+
+        assert!(matches!(
+            service.create_snippet(input("Missing", SnippetScope::Workspace)),
+            Err(SnippetError::Validation(_))
+        ));
+        assert!(matches!(
+            service.create_snippet(input("  ", SnippetScope::Global)),
+            Err(SnippetError::Validation(_))
+        ));
+    }
+
+    #[test]
+    fn test_update_and_delete_snippet() {
+        let (service, _dir) = create_test_service();
+        let snippet = service
+            .create_snippet(input("Tests", SnippetScope::Global))
+            .unwrap();
+
+        let updated = service
+            .update_snippet(
+                &snippet.id,
+                UpdateSnippetInput {
+                    name: None,
+                    body: Some("Run cargo test".to_string()),
+                },
+            )
+            .unwrap();
+        assert_eq!(updated.name, "Tests");
+        assert_eq!(updated.body, "Run cargo test");
+
+        service.delete_snippet(&snippet.id).unwrap();
+        assert!(matches!(
+            service.get_snippet(&snippet.id),
+            Err(SnippetError::NotFound(_))
+        ));
+        assert!(matches!(
+            service.delete_snippet(&snippet.id),
+            Err(SnippetError::NotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_send_snippet_checks_scope_and_running_agent() {
+        let (service, _dir) = create_test_service();
+        let global = service
+            .create_snippet(input("Global", SnippetScope::Global))
+            .unwrap();
+        let other_worktree = service
+            .create_snippet(CreateSnippetInput {
+                worktree_id: Some("wt_2".to_string()),
+                ..input("Worktree", SnippetScope::Worktree)
+            })
+            .unwrap();
+
+        assert!(matches!(
+            service.send_snippet_to_agent("ag_1", &other_worktree.id, &HashMap::new()),
+            Err(SnippetError::Validation(_))
+        ));
+        assert!(matches!(
+            service.send_snippet_to_agent("ag_1", &global.id, &HashMap::new()),
+            Err(SnippetError::Process(ProcessError::AgentNotFound(_)))
+        ));
+        assert!(matches!(
+            service.send_snippet_to_agent("ag_missing", &global.id, &HashMap::new()),
+            Err(SnippetError::AgentNotFound(_))
+        ));
+    }
+}
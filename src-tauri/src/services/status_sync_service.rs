@@ -0,0 +1,513 @@
+//! Status sync service for persisting process events to the agents table
+//!
+//! Status, error and exit events are journaled by the process manager before
+//! they are broadcast. This service applies them from the journal, so events
+//! missed by a lagging receiver or a crash are still applied, in order.
+//! Context levels are not journaled; each is applied as it arrives, and a
+//! missed one is superseded by the next. When the receiver lags, the agents
+//! table is also reconciled against the live processes.
+
+use chrono::Utc;
+use std::sync::Arc;
+use thiserror::Error;
+use tokio::sync::broadcast::{self, error::RecvError};
+
+use crate::db::{AgentRepository, AgentRunRepository, DbPool, EventJournalRepository};
+use crate::services::process_service::DB_TIMESTAMP_FORMAT;
+use crate::services::{ProcessControl, ProcessEvent, SettingsSyncService, StampedEvent};
+use crate::types::{AgentStatus, JournalEventType};
+
+/// Processed journal entries are kept this long for debugging
+const JOURNAL_RETENTION_DAYS: i64 = 7;
+/// Status reason for an agent whose process ended without a recorded exit
+pub(crate) const LOST_EXIT_REASON: &str = "Process ended while status updates were dropped";
+
+#[derive(Error, Debug)]
+pub enum StatusSyncError {
+    #[error("Database error: {0}")]
+    Database(String),
+}
+
+pub struct StatusSyncService {
+    agent_repo: AgentRepository,
+    run_repo: AgentRunRepository,
+    journal: EventJournalRepository,
+    process_manager: Option<Arc<dyn ProcessControl>>,
+    settings_sync: Option<Arc<SettingsSyncService>>,
+}
+
+impl StatusSyncService {
+    pub fn new(pool: DbPool) -> Self {
+        Self {
+            agent_repo: AgentRepository::new(pool.clone()),
+            run_repo: AgentRunRepository::new(pool.clone()),
+            journal: EventJournalRepository::new(pool),
+            process_manager: None,
+            settings_sync: None,
+        }
+    }
+
+    /// Reconcile against the live processes when events were dropped
+    pub fn with_process_manager(mut self, process_manager: Arc<dyn ProcessControl>) -> Self {
+        self.process_manager = Some(process_manager);
+        self
+    }
+
+    /// Take the manager's hooks out of a worktree when its last agent exits
+    pub fn with_settings_sync(mut self, settings_sync: Arc<SettingsSyncService>) -> Self {
+        self.settings_sync = Some(settings_sync);
+        self
+    }
+
+    /// Apply events a previous run journaled but never synced.
+    ///
+    /// Status changes are skipped: they describe processes that no longer
+    /// exist, while exits still tell us how the previous run ended. Runs
+    /// that never journaled an exit died with the previous run of the app.
+    pub fn replay_on_startup(&self) -> Result<usize, StatusSyncError> {
+        let applied = self.drain_journal(false)?;
+        self.run_repo
+            .close_unfinished()
+            .map_err(|e| StatusSyncError::Database(e.to_string()))?;
+        self.journal
+            .prune_processed(JOURNAL_RETENTION_DAYS)
+            .map_err(|e| StatusSyncError::Database(e.to_string()))?;
+        Ok(applied)
+    }
+
+    /// Apply unprocessed journal entries oldest first, returning how many were applied
+    pub fn drain_journal(&self, apply_status: bool) -> Result<usize, StatusSyncError> {
+        let entries = self
+            .journal
+            .find_unprocessed()
+            .map_err(|e| StatusSyncError::Database(e.to_string()))?;
+
+        let mut applied = 0;
+        for entry in entries {
+            if apply_status || entry.event_type != JournalEventType::Status {
+                self.apply_event(&ProcessEvent::from_journal_entry(&entry), &entry.created_at)?;
+                applied += 1;
+            }
+            self.journal
+                .mark_processed(entry.id)
+                .map_err(|e| StatusSyncError::Database(e.to_string()))?;
+        }
+
+        Ok(applied)
+    }
+
+    /// Bring the agents table in line with the live processes after events
+    /// were dropped, returning how many agents had to be marked stopped.
+    ///
+    /// Journaled events are applied first. An agent still recorded with a
+    /// process the manager no longer runs is marked stopped, and running
+    /// agents take their context level from the live process.
+    pub fn resync(&self) -> Result<usize, StatusSyncError> {
+        self.drain_journal(true)?;
+        let Some(process_manager) = &self.process_manager else {
+            return Ok(0);
+        };
+
+        let at = Utc::now().format(DB_TIMESTAMP_FORMAT).to_string();
+        let agents = self
+            .agent_repo
+            .find_with_pids()
+            .map_err(|e| StatusSyncError::Database(e.to_string()))?;
+        let mut stopped = 0;
+        for (agent_id, _) in agents {
+            let process = process_manager.snapshot(&agent_id);
+            let result = if !process.is_running {
+                stopped += 1;
+                self.run_repo
+                    .finish_latest(&agent_id, None, None, false, &at)
+                    .and_then(|_| {
+                        self.agent_repo.record_stop(
+                            &agent_id,
+                            AgentStatus::Error,
+                            Some(LOST_EXIT_REASON),
+                            &at,
+                        )
+                    })
+            } else if let Some(level) = process.context_level {
+                self.agent_repo.update_context_level(&agent_id, level, &at)
+            } else {
+                Ok(())
+            };
+            result.map_err(|e| StatusSyncError::Database(e.to_string()))?;
+        }
+        Ok(stopped)
+    }
+
+    /// Persist the effect of a single process event emitted at `at`
+    pub fn apply_event(&self, event: &ProcessEvent, at: &str) -> Result<(), StatusSyncError> {
+        let result = match event {
+            ProcessEvent::Status {
+                agent_id,
+                status,
+                reason,
+            } => {
+                self.run_repo
+                    .record_status(agent_id, *status, at)
+                    .map_err(|e| StatusSyncError::Database(e.to_string()))?;
+                self.agent_repo
+                    .set_status(agent_id, *status, reason.as_deref(), at)
+            }
+            ProcessEvent::Error { agent_id, message } => {
+                self.agent_repo
+                    .set_status(agent_id, AgentStatus::Error, Some(message), at)
+            }
+            ProcessEvent::Exit {
+                agent_id,
+                code,
+                signal,
+                stopped_by_user,
+            } => {
+                let (status, reason) = exit_status(*code, signal.as_deref(), *stopped_by_user);
+                // Close the run's last stretch of activity at the exit
+                self.run_repo
+                    .record_status(agent_id, status, at)
+                    .and_then(|_| {
+                        self.run_repo.finish_latest(
+                            agent_id,
+                            *code,
+                            signal.as_deref(),
+                            *stopped_by_user,
+                            at,
+                        )
+                    })
+                    .map_err(|e| StatusSyncError::Database(e.to_string()))?;
+                self.agent_repo
+                    .record_stop(agent_id, status, reason.as_deref(), at)
+                    .map_err(|e| StatusSyncError::Database(e.to_string()))?;
+                self.release_hooks(agent_id);
+                Ok(())
+            }
+            ProcessEvent::Context { agent_id, level } => {
+                self.agent_repo.update_context_level(agent_id, *level, at)
+            }
+            ProcessEvent::Output { .. }
+            | ProcessEvent::IdleWarning { .. }
+            | ProcessEvent::IdleTimeout { .. } => return Ok(()),
+        };
+
+        result.map_err(|e| StatusSyncError::Database(e.to_string()))
+    }
+
+    /// Keep the agents table in sync with process events until the channel closes
+    pub async fn run(&self, mut rx: broadcast::Receiver<StampedEvent>) {
+        loop {
+            match rx.recv().await {
+                Ok(StampedEvent {
+                    event:
+                        ProcessEvent::Status { .. }
+                        | ProcessEvent::Error { .. }
+                        | ProcessEvent::Exit { .. },
+                    ..
+                }) => self.drain_logged(),
+                Ok(stamped) if matches!(stamped.event, ProcessEvent::Context { .. }) => {
+                    if let Err(e) = self.apply_event(&stamped.event, &stamped.db_timestamp()) {
+                        tracing::warn!("Failed to sync agent context level: {}", e);
+                    }
+                }
+                Ok(_) => {}
+                Err(RecvError::Lagged(n)) => {
+                    tracing::warn!("Status sync lagged by {} events, resyncing", n);
+                    match self.resync() {
+                        Ok(0) => {}
+                        Ok(stopped) => {
+                            tracing::warn!("Resync marked {} vanished agents stopped", stopped)
+                        }
+                        Err(e) => tracing::warn!("Failed to resync agent status: {}", e),
+                    }
+                }
+                Err(RecvError::Closed) => break,
+            }
+        }
+    }
+
+    fn release_hooks(&self, agent_id: &str) {
+        let Some(settings_sync) = &self.settings_sync else {
+            return;
+        };
+        let result = self
+            .agent_repo
+            .find_by_id(agent_id)
+            .map_err(|e| e.to_string())
+            .and_then(|agent| match agent {
+                Some(agent) => settings_sync
+                    .release_if_idle(&agent.worktree_id)
+                    .map_err(|e| e.to_string()),
+                None => Ok(false),
+            });
+        if let Err(e) = result {
+            tracing::warn!(
+                "Failed to remove hook settings after {} exited: {}",
+                agent_id,
+                e
+            );
+        }
+    }
+
+    fn drain_logged(&self) {
+        if let Err(e) = self.drain_journal(true) {
+            tracing::warn!("Failed to sync agent status: {}", e);
+        }
+    }
+}
+
+/// Status and reason to record for a process exit
+fn exit_status(
+    code: Option<i32>,
+    signal: Option<&str>,
+    stopped_by_user: bool,
+) -> (AgentStatus, Option<String>) {
+    if stopped_by_user {
+        return (AgentStatus::Idle, None);
+    }
+    match (code, signal) {
+        (_, Some(signal)) => (
+            AgentStatus::Error,
+            Some(format!("Terminated by {}", signal)),
+        ),
+        (Some(0), None) => (AgentStatus::Idle, None),
+        (Some(code), None) => (
+            AgentStatus::Error,
+            Some(format!("Exited with code {}", code)),
+        ),
+        (None, None) => (
+            AgentStatus::Error,
+            Some("Exited with unknown status".to_string()),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::ProcessManager;
+    use crate::types::JournalEntry;
+    use tempfile::TempDir;
+
+    const AT: &str = "2026-01-01 12:00:00.000";
+
+    fn create_test_service() -> (StatusSyncService, DbPool, TempDir) {
+        let (pool, dir) = crate::db::test_pool();
+        let conn = pool.get().unwrap();
+        conn.execute_batch(
+            r#"
+            INSERT INTO workspaces (id, name, path) VALUES ('ws_1', 'Test', '/tmp/test');
+            INSERT INTO worktrees (id, workspace_id, name, branch, path)
+                VALUES ('wt_1', 'ws_1', 'main', 'main', '/tmp/test');
+            INSERT INTO agents (id, worktree_id, name, status, pid)
+                VALUES ('ag_1', 'wt_1', 'Agent', 'running', 4242);
+        "#,
+        )
+        .unwrap();
+
+        (StatusSyncService::new(pool.clone()), pool, dir)
+    }
+
+    fn agent_state(pool: &DbPool) -> (String, Option<String>, Option<i32>, Option<String>) {
+        pool.get()
+            .unwrap()
+            .query_row(
+                "SELECT status, status_reason, pid, stopped_at FROM agents WHERE id = 'ag_1'",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+            )
+            .unwrap()
+    }
+
+    fn exit_event(code: Option<i32>, signal: Option<&str>, stopped_by_user: bool) -> ProcessEvent {
+        ProcessEvent::Exit {
+            agent_id: "ag_1".to_string(),
+            code,
+            signal: signal.map(|s| s.to_string()),
+            stopped_by_user,
+        }
+    }
+
+    fn journal(pool: &DbPool, event: &ProcessEvent) {
+        let entry: JournalEntry = event.to_journal_entry().unwrap();
+        EventJournalRepository::new(pool.clone())
+            .append(&entry)
+            .unwrap();
+    }
+
+    #[test]
+    fn test_clean_exit_is_idle() {
+        let (service, pool, _dir) = create_test_service();
+
+        service
+            .apply_event(&exit_event(Some(0), None, false), AT)
+            .unwrap();
+
+        let (status, reason, pid, stopped_at) = agent_state(&pool);
+        assert_eq!(status, "idle");
+        assert!(reason.is_none());
+        assert!(pid.is_none());
+        // Stamped with the exit event's time, not when it was applied
+        assert_eq!(stopped_at.as_deref(), Some(AT));
+    }
+
+    #[test]
+    fn test_non_zero_exit_is_error() {
+        let (service, pool, _dir) = create_test_service();
+
+        service
+            .apply_event(&exit_event(Some(1), None, false), AT)
+            .unwrap();
+
+        let (status, reason, _, stopped_at) = agent_state(&pool);
+        assert_eq!(status, "error");
+        assert_eq!(reason.as_deref(), Some("Exited with code 1"));
+        assert!(stopped_at.is_some());
+    }
+
+    #[test]
+    fn test_signal_is_error_unless_requested() {
+        let (service, pool, _dir) = create_test_service();
+
+        service
+            .apply_event(&exit_event(None, Some("Killed"), false), AT)
+            .unwrap();
+        let (status, reason, _, _) = agent_state(&pool);
+        assert_eq!(status, "error");
+        assert_eq!(reason.as_deref(), Some("Terminated by Killed"));
+
+        service
+            .apply_event(&exit_event(None, Some("SIGKILL"), true), AT)
+            .unwrap();
+        let (status, reason, _, _) = agent_state(&pool);
+        assert_eq!(status, "idle");
+        assert!(reason.is_none());
+    }
+
+    #[test]
+    fn test_status_event_keeps_pid() {
+        let (service, pool, _dir) = create_test_service();
+
+        service
+            .apply_event(
+                &ProcessEvent::Status {
+                    agent_id: "ag_1".to_string(),
+                    status: AgentStatus::Waiting,
+                    reason: Some("permission_prompt".to_string()),
+                },
+                AT,
+            )
+            .unwrap();
+
+        let (status, reason, pid, _) = agent_state(&pool);
+        assert_eq!(status, "waiting");
+        assert_eq!(reason.as_deref(), Some("permission_prompt"));
+        assert_eq!(pid, Some(4242));
+    }
+
+    #[test]
+    fn test_context_event_updates_context_level() {
+        let (service, pool, _dir) = create_test_service();
+
+        service
+            .apply_event(
+                &ProcessEvent::Context {
+                    agent_id: "ag_1".to_string(),
+                    level: 73,
+                },
+                AT,
+            )
+            .unwrap();
+
+        let level: i32 = pool
+            .get()
+            .unwrap()
+            .query_row(
+                "SELECT context_level FROM agents WHERE id = 'ag_1'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(level, 73);
+    }
+
+    #[test]
+    fn test_drain_journal_applies_in_order() {
+        let (service, pool, _dir) = create_test_service();
+        journal(
+            &pool,
+            &ProcessEvent::Status {
+                agent_id: "ag_1".to_string(),
+                status: AgentStatus::Waiting,
+                reason: None,
+            },
+        );
+        journal(&pool, &exit_event(Some(3), None, false));
+
+        assert_eq!(service.drain_journal(true).unwrap(), 2);
+        assert_eq!(service.drain_journal(true).unwrap(), 0);
+
+        let (status, reason, _, _) = agent_state(&pool);
+        assert_eq!(status, "error");
+        assert_eq!(reason.as_deref(), Some("Exited with code 3"));
+    }
+
+    #[test]
+    fn test_exit_finishes_latest_run() {
+        let (service, pool, _dir) = create_test_service();
+        let runs = AgentRunRepository::new(pool.clone());
+        runs.start("ag_1", Some("session-1")).unwrap();
+
+        service
+            .apply_event(&exit_event(None, Some("Killed"), true), AT)
+            .unwrap();
+
+        let run = &runs.find_by_agent_id("ag_1", 10).unwrap()[0];
+        assert_eq!(run.ended_at.as_deref(), Some(AT));
+        assert_eq!(run.exit_code, None);
+        assert_eq!(run.signal.as_deref(), Some("Killed"));
+        assert!(run.stopped_by_user);
+    }
+
+    #[test]
+    fn test_resync_stops_agents_without_a_process() {
+        let (service, pool, _dir) = create_test_service();
+        AgentRunRepository::new(pool.clone())
+            .start("ag_1", None)
+            .unwrap();
+        // The process manager never saw ag_1, e.g. its exit event was dropped
+        let service =
+            service.with_process_manager(Arc::new(ProcessManager::new("echo".to_string())));
+
+        assert_eq!(service.resync().unwrap(), 1);
+
+        let (status, reason, pid, stopped_at) = agent_state(&pool);
+        assert_eq!(status, "error");
+        assert_eq!(reason.as_deref(), Some(LOST_EXIT_REASON));
+        assert!(pid.is_none());
+        assert!(stopped_at.is_some());
+        // Nothing left to correct
+        assert_eq!(service.resync().unwrap(), 0);
+    }
+
+    #[test]
+    fn test_replay_on_startup_skips_status_events() {
+        let (service, pool, _dir) = create_test_service();
+        journal(
+            &pool,
+            &ProcessEvent::Status {
+                agent_id: "ag_1".to_string(),
+                status: AgentStatus::Waiting,
+                reason: None,
+            },
+        );
+
+        assert_eq!(service.replay_on_startup().unwrap(), 0);
+
+        let (status, _, _, _) = agent_state(&pool);
+        assert_eq!(status, "running");
+        assert!(EventJournalRepository::new(pool)
+            .find_unprocessed()
+            .unwrap()
+            .is_empty());
+    }
+}
@@ -0,0 +1,550 @@
+//! Task group service for fanning a prompt out to several agents
+//!
+//! Each attempt gets its own worktree and branch forked from the main
+//! worktree's branch, so the results can be compared side by side.
+
+use std::sync::Arc;
+
+use thiserror::Error;
+use uuid::Uuid;
+
+use crate::db::{AgentRepository, DbPool, TaskGroupRepository, WorktreeRepository};
+use crate::services::{AgentError, AgentService, GitService, WorktreeError, WorktreeService};
+use crate::types::{
+    AgentMode, AgentStatus, AttemptDiff, GitStatusInfo, Permission, TaskGroup, TaskGroupComparison,
+    TaskGroupDetails, TaskGroupMember, TaskGroupStatusCounts, Worktree,
+};
+use crate::util::id::new_id;
+
+/// Upper bound on attempts per task, each of which runs its own agent
+pub const MAX_FAN_OUT: usize = 8;
+/// Fan-out branches go under `task/` unless the workspace has its own template
+const TASK_BRANCH_TEMPLATE: &str = "task/{slug}";
+
+#[derive(Error, Debug)]
+pub enum TaskGroupError {
+    #[error("Task group not found: {0}")]
+    NotFound(String),
+    #[error("Validation error: {0}")]
+    Validation(String),
+    #[error("Database error: {0}")]
+    Database(String),
+    #[error("Git error: {0}")]
+    Git(String),
+    #[error("Worktree error: {0}")]
+    Worktree(#[from] WorktreeError),
+    #[error("Agent error: {0}")]
+    Agent(#[from] AgentError),
+}
+
+pub struct TaskGroupService {
+    task_group_repo: TaskGroupRepository,
+    agent_repo: AgentRepository,
+    worktree_repo: WorktreeRepository,
+    worktree_service: Arc<WorktreeService>,
+    agent_service: Arc<AgentService>,
+}
+
+impl TaskGroupService {
+    pub fn new(
+        pool: DbPool,
+        worktree_service: Arc<WorktreeService>,
+        agent_service: Arc<AgentService>,
+    ) -> Self {
+        Self {
+            task_group_repo: TaskGroupRepository::new(pool.clone()),
+            agent_repo: AgentRepository::new(pool.clone()),
+            worktree_repo: WorktreeRepository::new(pool),
+            worktree_service,
+            agent_service,
+        }
+    }
+
+    /// Create `count` worktrees off the main branch and start an agent on
+    /// `prompt` in each
+    pub fn fan_out_task(
+        &self,
+        workspace_id: &str,
+        prompt: &str,
+        count: usize,
+    ) -> Result<TaskGroupDetails, TaskGroupError> {
+        if !(1..=MAX_FAN_OUT).contains(&count) {
+            return Err(TaskGroupError::Validation(format!(
+                "Attempt count must be between 1 and {}",
+                MAX_FAN_OUT
+            )));
+        }
+        if prompt.trim().is_empty() {
+            return Err(TaskGroupError::Validation(
+                "Prompt must not be empty".to_string(),
+            ));
+        }
+
+        let main = self.main_worktree(workspace_id)?;
+        let base_branch = GitService::get_current_branch(&main.path)
+            .map_err(|e| TaskGroupError::Git(e.to_string()))?;
+
+        let suffix = Uuid::new_v4().to_string()[..8].to_string();
+        let group = self
+            .task_group_repo
+            .create(&TaskGroup {
+                id: new_id("tg"),
+                workspace_id: workspace_id.to_string(),
+                prompt: prompt.to_string(),
+                base_branch,
+                winner_agent_id: None,
+                resolved_at: None,
+                created_at: chrono::Utc::now().to_rfc3339(),
+            })
+            .map_err(|e| TaskGroupError::Database(e.to_string()))?;
+
+        for attempt in 1..=count {
+            let branch = self.worktree_service.generate_branch_name_or(
+                workspace_id,
+                &format!("{}-{}", suffix, attempt),
+                TASK_BRANCH_TEMPLATE,
+            )?;
+            let worktree = self.worktree_service.create_worktree(
+                workspace_id,
+                &format!("task-{}-{}", suffix, attempt),
+                &branch,
+                None,
+                true,
+            )?;
+
+            let agent = self.agent_service.create_agent(
+                &worktree.id,
+                Some(format!("Attempt {}", attempt)),
+                AgentMode::Regular,
+                vec![Permission::Read, Permission::Write],
+            )?;
+            self.task_group_repo
+                .add_agent(&group.id, &agent.id)
+                .map_err(|e| TaskGroupError::Database(e.to_string()))?;
+
+            match self
+                .agent_service
+                .start_agent(&agent.id, &worktree.path, Some(prompt))
+            {
+                // Started by the user once the worktree is bootstrapped
+                Err(AgentError::Bootstrapping(_)) => {
+                    tracing::info!("Agent {} waits for its worktree bootstrap", agent.id)
+                }
+                result => {
+                    result?;
+                }
+            }
+        }
+
+        tracing::info!("Fanned task group {} out to {} agents", group.id, count);
+
+        self.get_task_group(&group.id)
+    }
+
+    /// Get a task group with its attempts and their aggregate status
+    pub fn get_task_group(&self, id: &str) -> Result<TaskGroupDetails, TaskGroupError> {
+        let group = self
+            .task_group_repo
+            .find_by_id(id)
+            .map_err(|e| TaskGroupError::Database(e.to_string()))?
+            .ok_or_else(|| TaskGroupError::NotFound(id.to_string()))?;
+
+        let agents = self
+            .agent_repo
+            .find_by_task_group_id(id)
+            .map_err(|e| TaskGroupError::Database(e.to_string()))?;
+
+        let mut status_counts = TaskGroupStatusCounts::default();
+        let mut members = Vec::with_capacity(agents.len());
+        for agent in agents {
+            match agent.status {
+                AgentStatus::Running => status_counts.running += 1,
+                AgentStatus::Waiting => status_counts.waiting += 1,
+                AgentStatus::Idle => status_counts.idle += 1,
+                AgentStatus::Error => status_counts.error += 1,
+            }
+            // Archived attempts are parked on the main worktree
+            let worktree = if agent.deleted_at.is_some() {
+                None
+            } else {
+                self.worktree_repo
+                    .find_by_id(&agent.worktree_id)
+                    .map_err(|e| TaskGroupError::Database(e.to_string()))?
+            };
+            members.push(TaskGroupMember { agent, worktree });
+        }
+
+        Ok(TaskGroupDetails {
+            group,
+            members,
+            is_complete: status_counts.running == 0 && status_counts.waiting == 0,
+            status_counts,
+        })
+    }
+
+    /// Diff every attempt's worktree against the group's base branch
+    pub fn compare_task_group(&self, id: &str) -> Result<TaskGroupComparison, TaskGroupError> {
+        let details = self.get_task_group(id)?;
+
+        let attempts = details
+            .members
+            .into_iter()
+            .map(|member| {
+                let diff = member
+                    .worktree
+                    .as_ref()
+                    .map(|w| GitService::diff_against_branch(&w.path, &details.group.base_branch))
+                    .transpose()
+                    .map_err(|e| TaskGroupError::Git(e.to_string()))?;
+                Ok(AttemptDiff {
+                    agent_id: member.agent.id,
+                    agent_name: member.agent.name,
+                    worktree_id: member.agent.worktree_id,
+                    branch: member.worktree.map(|w| w.branch),
+                    diff,
+                })
+            })
+            .collect::<Result<Vec<_>, TaskGroupError>>()?;
+
+        Ok(TaskGroupComparison {
+            task_group_id: details.group.id,
+            base_branch: details.group.base_branch,
+            attempts,
+        })
+    }
+
+    /// Keep the winning attempt and discard the others.
+    ///
+    /// Losing agents are archived onto the main worktree, and their worktrees
+    /// and branches are deleted. With `merge`, the winner's branch is first
+    /// merged into the base branch. Nothing is removed unless every losing
+    /// worktree is clean, or `force` is set.
+    pub fn resolve_task_group(
+        &self,
+        id: &str,
+        winner_agent_id: &str,
+        merge: bool,
+        force: bool,
+    ) -> Result<TaskGroupDetails, TaskGroupError> {
+        let details = self.get_task_group(id)?;
+        if details.group.resolved_at.is_some() {
+            return Err(TaskGroupError::Validation(format!(
+                "Task group {} is already resolved",
+                id
+            )));
+        }
+        let winner = details
+            .members
+            .iter()
+            .find(|m| m.agent.id == winner_agent_id)
+            .ok_or_else(|| {
+                TaskGroupError::Validation(format!(
+                    "Agent {} is not part of task group {}",
+                    winner_agent_id, id
+                ))
+            })?;
+        let main = self.main_worktree(&details.group.workspace_id)?;
+
+        let losers: Vec<&TaskGroupMember> = details
+            .members
+            .iter()
+            .filter(|m| m.agent.id != winner_agent_id)
+            .collect();
+        if !force {
+            for worktree in losers.iter().filter_map(|m| m.worktree.as_ref()) {
+                if !Self::status(&worktree.path)?.is_clean {
+                    return Err(TaskGroupError::Validation(format!(
+                        "Worktree {} has uncommitted changes",
+                        worktree.name
+                    )));
+                }
+            }
+        }
+
+        if merge {
+            let worktree = winner.worktree.as_ref().ok_or_else(|| {
+                TaskGroupError::Validation(format!(
+                    "Worktree of agent {} no longer exists",
+                    winner_agent_id
+                ))
+            })?;
+            if !Self::status(&worktree.path)?.is_clean {
+                return Err(TaskGroupError::Validation(format!(
+                    "Worktree {} has uncommitted changes; commit them before merging",
+                    worktree.name
+                )));
+            }
+            let current = GitService::get_current_branch(&main.path)
+                .map_err(|e| TaskGroupError::Git(e.to_string()))?;
+            if current != details.group.base_branch {
+                return Err(TaskGroupError::Validation(format!(
+                    "Main worktree must be on {} to merge, found {}",
+                    details.group.base_branch, current
+                )));
+            }
+            let status = Self::status(&main.path)?;
+            if !status.modified.is_empty() || !status.staged.is_empty() {
+                return Err(TaskGroupError::Validation(
+                    "Main worktree has uncommitted changes".to_string(),
+                ));
+            }
+
+            GitService::merge_branch(&main.path, &worktree.branch)
+                .map_err(|e| TaskGroupError::Git(e.to_string()))?;
+        }
+
+        for loser in losers {
+            // Deleting the worktree cascades to its agents, so move them off it first
+            self.agent_service.delete_agent(&loser.agent.id, true)?;
+            self.agent_repo
+                .move_to_worktree(&loser.agent.id, &main.id)
+                .map_err(|e| TaskGroupError::Database(e.to_string()))?;
+
+            if let Some(worktree) = &loser.worktree {
+                self.worktree_service.delete_worktree(&worktree.id, false)?;
+                GitService::delete_branch(&main.path, &worktree.branch)
+                    .map_err(|e| TaskGroupError::Git(e.to_string()))?;
+            }
+        }
+
+        self.task_group_repo
+            .mark_resolved(id, winner_agent_id)
+            .map_err(|e| TaskGroupError::Database(e.to_string()))?;
+
+        tracing::info!(
+            "Resolved task group {} in favor of agent {}",
+            id,
+            winner_agent_id
+        );
+
+        self.get_task_group(id)
+    }
+
+    fn main_worktree(&self, workspace_id: &str) -> Result<Worktree, TaskGroupError> {
+        self.worktree_service
+            .list_worktrees(workspace_id)?
+            .into_iter()
+            .find(|w| w.is_main)
+            .ok_or_else(|| {
+                TaskGroupError::Validation(format!(
+                    "Workspace {} has no main worktree",
+                    workspace_id
+                ))
+            })
+    }
+
+    fn status(path: &str) -> Result<GitStatusInfo, TaskGroupError> {
+        GitService::get_status(path).map_err(|e| TaskGroupError::Git(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::ProcessManager;
+    use tempfile::TempDir;
+
+    fn create_test_service() -> (TaskGroupService, String, TempDir) {
+        let (pool, dir) = crate::db::test_pool();
+        let repo_path = dir.path().join("repo");
+        std::fs::create_dir(&repo_path).unwrap();
+        let repo = git2::Repository::init(&repo_path).unwrap();
+        // Agents write their hook settings into the worktree
+        std::fs::write(repo_path.join(".gitignore"), ".claude/\n").unwrap();
+        {
+            let mut index = repo.index().unwrap();
+            index.add_path(std::path::Path::new(".gitignore")).unwrap();
+            index.write().unwrap();
+            let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+            let sig = git2::Signature::now("Test", "test@example.com").unwrap();
+            repo.commit(Some("HEAD"), &sig, &sig, "Initial commit", &tree, &[])
+                .unwrap();
+        }
+
+        let conn = pool.get().unwrap();
+        let path = repo_path.to_str().unwrap();
+        conn.execute(
+            "INSERT INTO workspaces (id, name, path) VALUES ('ws_1', 'Test', ?1)",
+            [path],
+        )
+        .unwrap();
+        conn.execute(
+            r#"INSERT INTO worktrees (id, workspace_id, name, branch, path, is_main)
+               VALUES ('wt_main', 'ws_1', 'repo', 'master', ?1, 1)"#,
+            [path],
+        )
+        .unwrap();
+
+        let process_manager = Arc::new(ProcessManager::new("echo".to_string()));
+        let service = TaskGroupService::new(
+            pool.clone(),
+            Arc::new(WorktreeService::new(pool.clone())),
+            Arc::new(AgentService::new(pool, process_manager)),
+        );
+        (service, "ws_1".to_string(), dir)
+    }
+
+    #[tokio::test]
+    async fn test_fan_out_task() {
+        let (service, workspace_id, _dir) = create_test_service();
+
+        let details = service
+            .fan_out_task(&workspace_id, "Fix the flaky test", 2)
+            .unwrap();
+
+        assert_eq!(details.group.prompt, "Fix the flaky test");
+        assert_eq!(details.members.len(), 2);
+        for member in &details.members {
+            let worktree = member.worktree.as_ref().unwrap();
+            assert!(worktree.branch.starts_with("task/"));
+            assert!(std::path::Path::new(&worktree.path).exists());
+            assert_eq!(
+                member.agent.task_group_id.as_deref(),
+                Some(details.group.id.as_str())
+            );
+        }
+        assert_ne!(
+            details.members[0].agent.worktree_id,
+            details.members[1].agent.worktree_id
+        );
+
+        let fetched = service.get_task_group(&details.group.id).unwrap();
+        assert_eq!(fetched.members.len(), 2);
+        let counts = fetched.status_counts;
+        assert_eq!(
+            counts.running + counts.waiting + counts.idle + counts.error,
+            2
+        );
+    }
+
+    #[tokio::test]
+    async fn test_compare_task_group() {
+        let (service, workspace_id, _dir) = create_test_service();
+        let details = service.fan_out_task(&workspace_id, "Add notes", 2).unwrap();
+        let first = details.members[0].worktree.as_ref().unwrap();
+        std::fs::write(
+            std::path::Path::new(&first.path).join("notes.txt"),
+            "one\ntwo\n",
+        )
+        .unwrap();
+
+        let comparison = service.compare_task_group(&details.group.id).unwrap();
+
+        assert_eq!(comparison.base_branch, details.group.base_branch);
+        assert_eq!(comparison.attempts.len(), 2);
+        let changed = comparison
+            .attempts
+            .iter()
+            .find(|a| a.worktree_id == first.id)
+            .unwrap();
+        let diff = changed.diff.as_ref().unwrap();
+        assert_eq!(diff.files.len(), 1);
+        assert_eq!(diff.files[0].path, "notes.txt");
+        assert_eq!(diff.files[0].status, "added");
+        assert_eq!(diff.insertions, 2);
+        assert_eq!(diff.deletions, 0);
+        assert!(diff.patch.contains("+two"));
+
+        let unchanged = comparison
+            .attempts
+            .iter()
+            .find(|a| a.worktree_id != first.id)
+            .unwrap();
+        assert!(unchanged.diff.as_ref().unwrap().files.is_empty());
+    }
+
+    fn commit_all(path: &str) {
+        let repo = git2::Repository::open(path).unwrap();
+        let mut index = repo.index().unwrap();
+        index
+            .add_all(["*"], git2::IndexAddOption::DEFAULT, None)
+            .unwrap();
+        index.write().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let head = repo.head().unwrap().peel_to_commit().unwrap();
+        let sig = git2::Signature::now("Test", "test@example.com").unwrap();
+        repo.commit(Some("HEAD"), &sig, &sig, "Attempt", &tree, &[&head])
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_resolve_task_group_merges_winner() {
+        let (service, workspace_id, dir) = create_test_service();
+        let details = service.fan_out_task(&workspace_id, "Add notes", 2).unwrap();
+        let winner = &details.members[0];
+        let loser = &details.members[1];
+        let winner_path = winner.worktree.as_ref().unwrap().path.clone();
+        let loser_worktree = loser.worktree.clone().unwrap();
+        std::fs::write(
+            std::path::Path::new(&winner_path).join("notes.txt"),
+            "done\n",
+        )
+        .unwrap();
+        commit_all(&winner_path);
+
+        let resolved = service
+            .resolve_task_group(&details.group.id, &winner.agent.id, true, false)
+            .unwrap();
+
+        assert_eq!(
+            resolved.group.winner_agent_id.as_deref(),
+            Some(winner.agent.id.as_str())
+        );
+        assert!(resolved.group.resolved_at.is_some());
+        assert!(dir.path().join("repo/notes.txt").exists());
+        assert!(!std::path::Path::new(&loser_worktree.path).exists());
+        let repo = git2::Repository::open(dir.path().join("repo")).unwrap();
+        assert!(repo
+            .find_branch(&loser_worktree.branch, git2::BranchType::Local)
+            .is_err());
+
+        let archived = resolved
+            .members
+            .iter()
+            .find(|m| m.agent.id == loser.agent.id)
+            .unwrap();
+        assert!(archived.agent.deleted_at.is_some());
+        assert_eq!(archived.agent.worktree_id, "wt_main");
+        assert!(archived.worktree.is_none());
+
+        let result = service.resolve_task_group(&details.group.id, &winner.agent.id, false, false);
+        assert!(matches!(result, Err(TaskGroupError::Validation(_))));
+    }
+
+    #[tokio::test]
+    async fn test_resolve_task_group_requires_clean_losers() {
+        let (service, workspace_id, _dir) = create_test_service();
+        let details = service.fan_out_task(&workspace_id, "Add notes", 2).unwrap();
+        let loser_path = details.members[1].worktree.as_ref().unwrap().path.clone();
+        std::fs::write(std::path::Path::new(&loser_path).join("wip.txt"), "wip\n").unwrap();
+
+        let winner_id = &details.members[0].agent.id;
+        let result = service.resolve_task_group(&details.group.id, winner_id, false, false);
+        assert!(matches!(result, Err(TaskGroupError::Validation(_))));
+        assert!(std::path::Path::new(&loser_path).exists());
+
+        service
+            .resolve_task_group(&details.group.id, winner_id, false, true)
+            .unwrap();
+        assert!(!std::path::Path::new(&loser_path).exists());
+    }
+
+    #[test]
+    fn test_fan_out_task_validates_count() {
+        let (service, workspace_id, _dir) = create_test_service();
+
+        let result = service.fan_out_task(&workspace_id, "prompt", 0);
+        assert!(matches!(result, Err(TaskGroupError::Validation(_))));
+
+        let result = service.fan_out_task(&workspace_id, "prompt", MAX_FAN_OUT + 1);
+        assert!(matches!(result, Err(TaskGroupError::Validation(_))));
+    }
+
+    #[test]
+    fn test_get_task_group_not_found() {
+        let (service, _, _dir) = create_test_service();
+
+        let result = service.get_task_group("tg_missing");
+        assert!(matches!(result, Err(TaskGroupError::NotFound(_))));
+    }
+}
@@ -1,29 +1,102 @@
 //! Usage service for tracking API usage statistics
+//!
+//! Usage can be checked against a budget of daily and weekly thresholds, on
+//! local token counts and on Claude's own utilization figures. A background
+//! check raises an alert once when usage reaches 80% of a threshold and
+//! again when it reaches 100%.
+//!
+//! With enforcement on, agents are not started while Claude's five-hour or
+//! seven-day utilization is past a cutoff, so unattended starts can't use up
+//! the rest of the limit. The check relies on the utilization last fetched;
+//! when none is recent, starts are allowed.
+//!
+//! Usage is recorded on daily rows. Weekly and monthly rows are sums of
+//! them, rolled up when they are read and by the background check, which
+//! also backfills history once at startup.
+//!
+//! When the API rate-limits the usage fetch or rejects the login, Claude is
+//! not asked again until the wait is over, and the background check slows
+//! down to match. While the app is offline Claude isn't asked at all.
 
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use chrono::{DateTime, NaiveDate, Utc};
 use thiserror::Error;
+use tokio::sync::broadcast;
+
+use crate::db::{DbPool, SettingsRepository, UsageRepository};
+use crate::services::{ClaudeApiError, ClaudeApiService, NetworkStatusService};
+use crate::types::{
+    BudgetAlert, BudgetLevel, BudgetMetric, BudgetMetricStatus, BudgetStatus, BudgetThresholds,
+    ClaudeUsageSummary, UsageEnforcement, UsageLimits, UsagePeriod, UsageStats, UsageSummary,
+};
 
-use crate::db::{DbPool, UsageRepository};
-use crate::types::{UsageLimits, UsagePeriod, UsageStats, UsageSummary};
+/// How often usage is checked against the budget
+const BUDGET_CHECK_INTERVAL: Duration = Duration::from_secs(300);
+/// Settings key for the usage budget
+const BUDGET_KEY: &str = "usage_budget";
+/// Percent of a threshold at which a warning is raised
+const BUDGET_WARNING_PERCENT: f64 = 80.0;
+/// Settings key for refusing agent starts past a utilization cutoff
+const ENFORCEMENT_KEY: &str = "usage_enforcement";
+/// Fetched utilization older than this is not enforced
+const CLAUDE_USAGE_MAX_AGE: chrono::Duration = chrono::Duration::minutes(15);
+/// Wait after a rejected login before asking Claude again; a successful
+/// fetch elsewhere, e.g. after logging in again, ends it early
+const UNAUTHORIZED_BACKOFF: Duration = Duration::from_secs(60 * 60);
 
 #[derive(Error, Debug)]
 pub enum UsageError {
+    #[error("Validation error: {0}")]
+    Validation(String),
     #[error("Database error: {0}")]
     Database(String),
 }
 
 pub struct UsageService {
     usage_repo: UsageRepository,
+    settings_repo: SettingsRepository,
+    alert_tx: broadcast::Sender<BudgetAlert>,
+    /// Last level seen per metric, so each crossing alerts only once
+    budget_levels: Mutex<HashMap<BudgetMetric, BudgetLevel>>,
+    /// Claude's utilization as last fetched, and when
+    claude_usage: Mutex<Option<(DateTime<Utc>, ClaudeUsageSummary)>>,
+    /// Claude is not asked for utilization again before this
+    claude_backoff_until: Mutex<Option<DateTime<Utc>>>,
+    network: Option<Arc<NetworkStatusService>>,
 }
 
 impl UsageService {
     pub fn new(pool: DbPool) -> Self {
+        let (alert_tx, _) = broadcast::channel(16);
         Self {
-            usage_repo: UsageRepository::new(pool),
+            usage_repo: UsageRepository::new(pool.clone()),
+            settings_repo: SettingsRepository::new(pool),
+            alert_tx,
+            budget_levels: Mutex::new(HashMap::new()),
+            claude_usage: Mutex::new(None),
+            claude_backoff_until: Mutex::new(None),
+            network: None,
         }
     }
 
+    /// Skip asking Claude for utilization while the network is down
+    pub fn with_network(mut self, network: Arc<NetworkStatusService>) -> Self {
+        self.network = Some(network);
+        self
+    }
+
+    /// Subscribe to budget alerts
+    pub fn subscribe(&self) -> broadcast::Receiver<BudgetAlert> {
+        self.alert_tx.subscribe()
+    }
+
     /// Get current usage summary
     pub fn get_usage_summary(&self) -> Result<UsageSummary, UsageError> {
+        self.rollup_recent()?;
+
         let today = self
             .usage_repo
             .get_or_create_today()
@@ -52,6 +125,9 @@ impl UsageService {
         period: UsagePeriod,
         limit: usize,
     ) -> Result<Vec<UsageStats>, UsageError> {
+        if period != UsagePeriod::Daily {
+            self.rollup_recent()?;
+        }
         self.usage_repo
             .get_history(period, limit)
             .map_err(|e| UsageError::Database(e.to_string()))
@@ -64,6 +140,26 @@ impl UsageService {
             .map_err(|e| UsageError::Database(e.to_string()))
     }
 
+    /// Sum daily usage into weekly and monthly rows from the periods holding
+    /// `since` onwards, or over all history, returning how many rows changed
+    pub fn rollup_usage(&self, since: Option<NaiveDate>) -> Result<usize, UsageError> {
+        let since = since.map(|date| date.format("%Y-%m-%d").to_string());
+        let mut changed = 0;
+        for period in [UsagePeriod::Weekly, UsagePeriod::Monthly] {
+            changed += self
+                .usage_repo
+                .rollup(period, since.as_deref())
+                .map_err(|e| UsageError::Database(e.to_string()))?;
+        }
+        Ok(changed)
+    }
+
+    /// Roll up the current periods, and the previous ones in case yesterday's
+    /// last usage came in after they were last rolled up
+    fn rollup_recent(&self) -> Result<usize, UsageError> {
+        self.rollup_usage(Some(Utc::now().date_naive() - chrono::Duration::days(1)))
+    }
+
     /// Get usage limits
     pub fn get_usage_limits(&self) -> Result<UsageLimits, UsageError> {
         // For now, return default limits
@@ -87,4 +183,612 @@ impl UsageService {
             .increment_usage(input_tokens, output_tokens, is_error)
             .map_err(|e| UsageError::Database(e.to_string()))
     }
+
+    /// Get the usage budget
+    pub fn get_budget_thresholds(&self) -> Result<BudgetThresholds, UsageError> {
+        Ok(self
+            .settings_repo
+            .get(BUDGET_KEY)
+            .map_err(|e| UsageError::Database(e.to_string()))?
+            .and_then(|value| serde_json::from_str(&value).ok())
+            .unwrap_or_default())
+    }
+
+    /// Replace the usage budget; thresholds left out are not checked
+    pub fn set_budget_thresholds(
+        &self,
+        thresholds: BudgetThresholds,
+    ) -> Result<BudgetThresholds, UsageError> {
+        let token_limits = [thresholds.daily_token_limit, thresholds.weekly_token_limit];
+        if token_limits.iter().flatten().any(|limit| *limit <= 0) {
+            return Err(UsageError::Validation(
+                "Token thresholds must be positive".to_string(),
+            ));
+        }
+        let utilization_limits = [
+            thresholds.daily_utilization_limit,
+            thresholds.weekly_utilization_limit,
+        ];
+        if utilization_limits
+            .iter()
+            .flatten()
+            .any(|limit| !(*limit > 0.0 && *limit <= 100.0))
+        {
+            return Err(UsageError::Validation(
+                "Utilization thresholds must be between 0 and 100 percent".to_string(),
+            ));
+        }
+
+        let value = serde_json::to_string(&thresholds).unwrap_or_default();
+        self.settings_repo
+            .set(BUDGET_KEY, &value, "json")
+            .map_err(|e| UsageError::Database(e.to_string()))?;
+        Ok(thresholds)
+    }
+
+    /// Current usage against the budget, fetching Claude's utilization when
+    /// a utilization threshold is set or enforcement is on
+    pub async fn get_budget_status(&self) -> Result<BudgetStatus, UsageError> {
+        let thresholds = self.get_budget_thresholds()?;
+        let claude_usage = if thresholds.daily_utilization_limit.is_some()
+            || thresholds.weekly_utilization_limit.is_some()
+            || self.get_usage_enforcement()?.enabled
+        {
+            self.fetch_claude_usage().await
+        } else {
+            None
+        };
+
+        self.budget_status(claude_usage.as_ref())
+    }
+
+    /// Current usage against the budget. Utilization thresholds are skipped
+    /// without Claude's usage summary.
+    pub fn budget_status(
+        &self,
+        claude_usage: Option<&ClaudeUsageSummary>,
+    ) -> Result<BudgetStatus, UsageError> {
+        let thresholds = self.get_budget_thresholds()?;
+        let mut metrics = Vec::new();
+
+        if thresholds.daily_token_limit.is_some() || thresholds.weekly_token_limit.is_some() {
+            let today = self.get_today_usage()?;
+            let this_week = self
+                .usage_repo
+                .get_week_total_tokens()
+                .map_err(|e| UsageError::Database(e.to_string()))?;
+            let token_metrics = [
+                (
+                    BudgetMetric::DailyTokens,
+                    thresholds.daily_token_limit,
+                    today.total_tokens,
+                ),
+                (
+                    BudgetMetric::WeeklyTokens,
+                    thresholds.weekly_token_limit,
+                    this_week,
+                ),
+            ];
+            for (metric, limit, used) in token_metrics {
+                if let Some(limit) = limit {
+                    metrics.push(metric_status(metric, used as f64, limit as f64));
+                }
+            }
+        }
+
+        if let Some(claude_usage) = claude_usage {
+            let utilization_metrics = [
+                (
+                    BudgetMetric::DailyUtilization,
+                    thresholds.daily_utilization_limit,
+                    claude_usage.daily.used,
+                ),
+                (
+                    BudgetMetric::WeeklyUtilization,
+                    thresholds.weekly_utilization_limit,
+                    claude_usage.weekly.used,
+                ),
+            ];
+            for (metric, limit, used) in utilization_metrics {
+                if let Some(limit) = limit {
+                    metrics.push(metric_status(metric, used, limit));
+                }
+            }
+        }
+
+        Ok(BudgetStatus {
+            thresholds,
+            metrics,
+            claude_usage_available: claude_usage.is_some(),
+            checked_at: Utc::now().to_rfc3339(),
+        })
+    }
+
+    /// Get the agent start enforcement settings
+    pub fn get_usage_enforcement(&self) -> Result<UsageEnforcement, UsageError> {
+        Ok(self
+            .settings_repo
+            .get(ENFORCEMENT_KEY)
+            .map_err(|e| UsageError::Database(e.to_string()))?
+            .and_then(|value| serde_json::from_str(&value).ok())
+            .unwrap_or_default())
+    }
+
+    /// Turn agent start enforcement on or off, or change its cutoff
+    pub fn set_usage_enforcement(
+        &self,
+        enforcement: UsageEnforcement,
+    ) -> Result<UsageEnforcement, UsageError> {
+        if !(enforcement.cutoff > 0.0 && enforcement.cutoff <= 100.0) {
+            return Err(UsageError::Validation(
+                "Usage cutoff must be between 0 and 100 percent".to_string(),
+            ));
+        }
+
+        let value = serde_json::to_string(&enforcement).unwrap_or_default();
+        self.settings_repo
+            .set(ENFORCEMENT_KEY, &value, "json")
+            .map_err(|e| UsageError::Database(e.to_string()))?;
+        Ok(enforcement)
+    }
+
+    /// Remember Claude's utilization for enforcement
+    pub fn record_claude_usage(&self, summary: ClaudeUsageSummary, fetched_at: DateTime<Utc>) {
+        *self.claude_usage.lock().unwrap() = Some((fetched_at, summary));
+        *self.claude_backoff_until.lock().unwrap() = None;
+    }
+
+    /// Claude's utilization, fetched unless the app is offline or an earlier
+    /// failure asked us to wait; meanwhile the last one fetched is used while
+    /// it is recent
+    async fn fetch_claude_usage(&self) -> Option<ClaudeUsageSummary> {
+        let now = Utc::now();
+        let offline = self.network.as_ref().is_some_and(|n| !n.is_online());
+        if offline || self.claude_backoff(now).is_some() {
+            return self
+                .claude_usage
+                .lock()
+                .unwrap()
+                .as_ref()
+                .filter(|(fetched_at, _)| now - *fetched_at <= CLAUDE_USAGE_MAX_AGE)
+                .map(|(_, summary)| summary.clone());
+        }
+
+        match ClaudeApiService::new().fetch_usage().await {
+            Ok(summary) => {
+                self.record_claude_usage(summary.clone(), Utc::now());
+                Some(summary)
+            }
+            Err(e) => {
+                let wait = match &e {
+                    ClaudeApiError::RateLimited { retry_after } => {
+                        Some(retry_after.unwrap_or(BUDGET_CHECK_INTERVAL))
+                    }
+                    ClaudeApiError::Unauthorized(_) => Some(UNAUTHORIZED_BACKOFF),
+                    _ => None,
+                };
+                match wait {
+                    Some(wait) => {
+                        tracing::info!("{}; not asking Claude for usage for {:?}", e, wait);
+                        self.back_off_claude(now, wait);
+                    }
+                    None => tracing::debug!("Claude usage unavailable for budget check: {}", e),
+                }
+                None
+            }
+        }
+    }
+
+    /// Don't ask Claude for utilization for `wait` from `now`
+    fn back_off_claude(&self, now: DateTime<Utc>, wait: Duration) {
+        let until = now + chrono::Duration::from_std(wait).unwrap_or(chrono::Duration::MAX);
+        *self.claude_backoff_until.lock().unwrap() = Some(until);
+    }
+
+    /// How much longer Claude is not asked for utilization at `now`
+    fn claude_backoff(&self, now: DateTime<Utc>) -> Option<Duration> {
+        self.claude_backoff_until
+            .lock()
+            .unwrap()
+            .and_then(|until| (until - now).to_std().ok())
+            .filter(|remaining| !remaining.is_zero())
+    }
+
+    /// The Claude usage window past the enforcement cutoff at `now`, with its
+    /// utilization and the cutoff, or None when agents may start. A window
+    /// that has reset since the utilization was fetched no longer counts.
+    pub fn exceeded_usage_limit(
+        &self,
+        now: DateTime<Utc>,
+    ) -> Result<Option<(String, f64, f64)>, UsageError> {
+        let enforcement = self.get_usage_enforcement()?;
+        if !enforcement.enabled {
+            return Ok(None);
+        }
+        let claude_usage = self.claude_usage.lock().unwrap();
+        let Some((fetched_at, summary)) = claude_usage.as_ref() else {
+            return Ok(None);
+        };
+        if now - *fetched_at > CLAUDE_USAGE_MAX_AGE {
+            return Ok(None);
+        }
+
+        let windows = [
+            ("five-hour", &summary.daily),
+            ("seven-day", &summary.weekly),
+        ];
+        for (window, entry) in windows {
+            let has_reset = DateTime::parse_from_rfc3339(&entry.reset_time)
+                .is_ok_and(|reset_at| reset_at <= now);
+            if entry.used >= enforcement.cutoff && !has_reset {
+                return Ok(Some((window.to_string(), entry.used, enforcement.cutoff)));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Alert on every metric whose level rose since the last check. A level
+    /// that falls, e.g. when a new day starts, is remembered so the next
+    /// crossing alerts again.
+    pub fn check_budget(&self, status: &BudgetStatus) -> Vec<BudgetAlert> {
+        let mut levels = self.budget_levels.lock().unwrap();
+        let mut alerts = Vec::new();
+
+        for metric in &status.metrics {
+            let previous = levels
+                .insert(metric.metric, metric.level)
+                .unwrap_or(BudgetLevel::Ok);
+            if metric.level <= previous {
+                continue;
+            }
+
+            let message = match metric.level {
+                BudgetLevel::Exceeded => format!(
+                    "{} budget exceeded: {:.0}% of the threshold used",
+                    metric.metric.label(),
+                    metric.percent
+                ),
+                _ => format!(
+                    "{} at {:.0}% of the budget threshold",
+                    metric.metric.label(),
+                    metric.percent
+                ),
+            };
+            let alert = BudgetAlert {
+                metric: metric.metric,
+                level: metric.level,
+                used: metric.used,
+                limit: metric.limit,
+                percent: metric.percent,
+                message,
+            };
+            // No subscribers is fine; the alert is still returned
+            let _ = self.alert_tx.send(alert.clone());
+            alerts.push(alert);
+        }
+
+        alerts
+    }
+
+    /// Check usage against the budget every few minutes, for as long as the
+    /// app runs, rolling up daily usage as it goes
+    pub async fn run(&self) {
+        match self.rollup_usage(None) {
+            Ok(changed) if changed > 0 => tracing::info!("Backfilled {} usage rollups", changed),
+            Ok(_) => {}
+            Err(e) => tracing::warn!("Failed to backfill usage rollups: {}", e),
+        }
+        loop {
+            if let Err(e) = self.rollup_recent() {
+                tracing::warn!("Failed to roll up usage: {}", e);
+            }
+            match self.get_budget_status().await {
+                Ok(status) => {
+                    for alert in self.check_budget(&status) {
+                        tracing::warn!("{}", alert.message);
+                    }
+                }
+                Err(e) => tracing::warn!("Failed to check usage budget: {}", e),
+            }
+            tokio::time::sleep(self.next_check_delay(Utc::now())).await;
+        }
+    }
+
+    /// Wait before the next background check, stretched to the end of a
+    /// Claude backoff
+    fn next_check_delay(&self, now: DateTime<Utc>) -> Duration {
+        self.claude_backoff(now)
+            .map_or(BUDGET_CHECK_INTERVAL, |wait| {
+                wait.max(BUDGET_CHECK_INTERVAL)
+            })
+    }
+}
+
+fn metric_status(metric: BudgetMetric, used: f64, limit: f64) -> BudgetMetricStatus {
+    let percent = used / limit * 100.0;
+    let level = if percent >= 100.0 {
+        BudgetLevel::Exceeded
+    } else if percent >= BUDGET_WARNING_PERCENT {
+        BudgetLevel::Warning
+    } else {
+        BudgetLevel::Ok
+    };
+
+    BudgetMetricStatus {
+        metric,
+        used,
+        limit,
+        percent,
+        level,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::UsageLimitEntry;
+    use tempfile::TempDir;
+
+    fn create_test_service() -> (UsageService, TempDir) {
+        let (pool, dir) = crate::db::test_pool();
+
+        (UsageService::new(pool), dir)
+    }
+
+    fn claude_usage(daily: f64, weekly: f64) -> ClaudeUsageSummary {
+        let entry = |used| UsageLimitEntry {
+            used,
+            limit: 100.0,
+            reset_time: String::new(),
+        };
+        ClaudeUsageSummary {
+            daily: entry(daily),
+            weekly: entry(weekly),
+            sonnet_only: entry(0.0),
+        }
+    }
+
+    #[test]
+    fn test_rollup_sums_daily_usage_into_weeks_and_months() {
+        let (pool, _dir) = crate::db::test_pool();
+        let conn = pool.get().unwrap();
+        // A Sunday, the Monday and Tuesday after it, and a Monday in April
+        for (date, tokens) in [
+            ("2020-03-01", 100),
+            ("2020-03-02", 20),
+            ("2020-03-03", 3),
+            ("2020-04-06", 1000),
+        ] {
+            conn.execute(
+                "INSERT INTO usage_stats (date, period, input_tokens, total_tokens, request_count)
+                 VALUES (?1, 'daily', ?2, ?2, 1)",
+                rusqlite::params![date, tokens],
+            )
+            .unwrap();
+        }
+        let service = UsageService::new(pool);
+
+        assert_eq!(service.rollup_usage(None).unwrap(), 5);
+        let totals = |period| {
+            service
+                .get_usage_history(period, 10)
+                .unwrap()
+                .into_iter()
+                .filter(|stats| stats.date.starts_with("2020"))
+                .map(|stats| (stats.date, stats.total_tokens, stats.request_count))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            totals(UsagePeriod::Weekly),
+            vec![
+                ("2020-04-06".to_string(), 1000, 1),
+                ("2020-03-02".to_string(), 23, 2),
+                ("2020-02-24".to_string(), 100, 1),
+            ]
+        );
+        assert_eq!(
+            totals(UsagePeriod::Monthly),
+            vec![
+                ("2020-04".to_string(), 1000, 1),
+                ("2020-03".to_string(), 123, 3),
+            ]
+        );
+        assert_eq!(service.rollup_usage(None).unwrap(), 0);
+
+        // Only periods from the one holding `since` are summed again
+        conn.execute(
+            "UPDATE usage_stats SET total_tokens = 4 WHERE date = '2020-03-03'",
+            [],
+        )
+        .unwrap();
+        let april = NaiveDate::from_ymd_opt(2020, 4, 1).unwrap();
+        assert_eq!(service.rollup_usage(Some(april)).unwrap(), 0);
+        let march = NaiveDate::from_ymd_opt(2020, 3, 3).unwrap();
+        assert_eq!(service.rollup_usage(Some(march)).unwrap(), 2);
+        assert_eq!(totals(UsagePeriod::Monthly)[1].1, 124);
+
+        // The current week and month include usage as it is recorded
+        service.record_usage(100, 50, false).unwrap();
+        let summary = service.get_usage_summary().unwrap();
+        assert_eq!(summary.this_week.total_tokens, 150);
+        assert_eq!(summary.this_month.total_tokens, 150);
+    }
+
+    #[test]
+    fn test_budget_thresholds_round_trip_and_validate() {
+        let (service, _dir) = create_test_service();
+        assert_eq!(
+            service.get_budget_thresholds().unwrap(),
+            BudgetThresholds::default()
+        );
+
+        let thresholds = BudgetThresholds {
+            daily_token_limit: Some(1000),
+            weekly_utilization_limit: Some(90.0),
+            ..Default::default()
+        };
+        service.set_budget_thresholds(thresholds.clone()).unwrap();
+        assert_eq!(service.get_budget_thresholds().unwrap(), thresholds);
+
+        let negative = BudgetThresholds {
+            weekly_token_limit: Some(0),
+            ..Default::default()
+        };
+        assert!(matches!(
+            service.set_budget_thresholds(negative),
+            Err(UsageError::Validation(_))
+        ));
+        let over_full = BudgetThresholds {
+            daily_utilization_limit: Some(150.0),
+            ..Default::default()
+        };
+        assert!(matches!(
+            service.set_budget_thresholds(over_full),
+            Err(UsageError::Validation(_))
+        ));
+    }
+
+    #[test]
+    fn test_budget_status_covers_local_and_claude_usage() {
+        let (service, _dir) = create_test_service();
+        service
+            .set_budget_thresholds(BudgetThresholds {
+                daily_token_limit: Some(1000),
+                weekly_token_limit: Some(10_000),
+                daily_utilization_limit: Some(50.0),
+                weekly_utilization_limit: None,
+            })
+            .unwrap();
+        service.record_usage(600, 250, false).unwrap();
+
+        let status = service.budget_status(None).unwrap();
+        assert!(!status.claude_usage_available);
+        assert_eq!(status.metrics.len(), 2);
+        assert_eq!(status.metrics[0].metric, BudgetMetric::DailyTokens);
+        assert_eq!(status.metrics[0].level, BudgetLevel::Warning);
+        assert_eq!(status.metrics[1].metric, BudgetMetric::WeeklyTokens);
+        assert_eq!(status.metrics[1].used, 850.0);
+        assert_eq!(status.metrics[1].level, BudgetLevel::Ok);
+
+        let status = service
+            .budget_status(Some(&claude_usage(60.0, 99.0)))
+            .unwrap();
+        assert!(status.claude_usage_available);
+        assert_eq!(status.metrics.len(), 3);
+        assert_eq!(status.metrics[2].metric, BudgetMetric::DailyUtilization);
+        assert_eq!(status.metrics[2].percent, 120.0);
+        assert_eq!(status.metrics[2].level, BudgetLevel::Exceeded);
+    }
+
+    #[test]
+    fn test_exceeded_usage_limit_needs_enforcement_and_fresh_usage() {
+        let (service, _dir) = create_test_service();
+        let now = Utc::now();
+        service.record_claude_usage(claude_usage(20.0, 97.0), now);
+        assert!(service.exceeded_usage_limit(now).unwrap().is_none());
+
+        service
+            .set_usage_enforcement(UsageEnforcement {
+                enabled: true,
+                cutoff: 95.0,
+            })
+            .unwrap();
+        let (window, utilization, cutoff) = service.exceeded_usage_limit(now).unwrap().unwrap();
+        assert_eq!(window, "seven-day");
+        assert_eq!(utilization, 97.0);
+        assert_eq!(cutoff, 95.0);
+
+        // Stale utilization isn't enforced
+        let later = now + chrono::Duration::hours(1);
+        assert!(service.exceeded_usage_limit(later).unwrap().is_none());
+
+        // Nor is a window that has reset since it was fetched
+        let mut reset = claude_usage(99.0, 0.0);
+        reset.daily.reset_time = (now - chrono::Duration::minutes(1)).to_rfc3339();
+        service.record_claude_usage(reset, now);
+        assert!(service.exceeded_usage_limit(now).unwrap().is_none());
+
+        assert!(matches!(
+            service.set_usage_enforcement(UsageEnforcement {
+                enabled: true,
+                cutoff: 0.0,
+            }),
+            Err(UsageError::Validation(_))
+        ));
+    }
+
+    #[test]
+    fn test_claude_backoff_stretches_checks_until_a_fetch_succeeds() {
+        let (service, _dir) = create_test_service();
+        let now = Utc::now();
+        assert_eq!(service.claude_backoff(now), None);
+        assert_eq!(service.next_check_delay(now), BUDGET_CHECK_INTERVAL);
+
+        service.back_off_claude(now, Duration::from_secs(900));
+        assert_eq!(service.claude_backoff(now), Some(Duration::from_secs(900)));
+        assert_eq!(service.next_check_delay(now), Duration::from_secs(900));
+        // Never sooner than the usual interval
+        let later = now + chrono::Duration::seconds(800);
+        assert_eq!(service.next_check_delay(later), BUDGET_CHECK_INTERVAL);
+        assert_eq!(
+            service.claude_backoff(now + chrono::Duration::seconds(900)),
+            None
+        );
+
+        service.back_off_claude(now, Duration::from_secs(900));
+        service.record_claude_usage(claude_usage(10.0, 10.0), now);
+        assert_eq!(service.claude_backoff(now), None);
+    }
+
+    #[tokio::test]
+    async fn test_claude_is_not_asked_while_offline() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let probe_host = listener.local_addr().unwrap().to_string();
+        drop(listener);
+        let network = Arc::new(NetworkStatusService::new().with_probe_host(probe_host));
+        assert!(!network.check().await.online);
+        let (service, _dir) = create_test_service();
+        let service = service.with_network(network);
+
+        assert!(service.fetch_claude_usage().await.is_none());
+
+        // The last utilization fetched stands in while it is recent
+        service.record_claude_usage(claude_usage(40.0, 20.0), Utc::now());
+        let summary = service.fetch_claude_usage().await.unwrap();
+        assert_eq!(summary.daily.used, 40.0);
+    }
+
+    #[test]
+    fn test_check_budget_alerts_once_per_crossing() {
+        let (service, _dir) = create_test_service();
+        service
+            .set_budget_thresholds(BudgetThresholds {
+                daily_utilization_limit: Some(50.0),
+                ..Default::default()
+            })
+            .unwrap();
+        let mut alert_rx = service.subscribe();
+        let check = |used| {
+            let status = service
+                .budget_status(Some(&claude_usage(used, 0.0)))
+                .unwrap();
+            service.check_budget(&status)
+        };
+
+        assert!(check(10.0).is_empty());
+        let alerts = check(42.0);
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].level, BudgetLevel::Warning);
+        assert_eq!(alert_rx.try_recv().unwrap().level, BudgetLevel::Warning);
+        assert!(check(45.0).is_empty());
+
+        let alerts = check(50.0);
+        assert_eq!(alerts[0].level, BudgetLevel::Exceeded);
+        assert!(alerts[0].message.contains("exceeded"));
+        assert!(check(70.0).is_empty());
+
+        // Usage resets with a new window, so the next crossing alerts again
+        assert!(check(5.0).is_empty());
+        assert_eq!(check(45.0)[0].level, BudgetLevel::Warning);
+    }
 }
@@ -1,60 +1,194 @@
 //! WebSocket server for real-time updates
 
 use axum::{
+    body::Body,
     extract::{
         ws::{Message, WebSocket, WebSocketUpgrade},
-        Path, State,
+        Path, Query, RawQuery, Request, State,
     },
-    response::IntoResponse,
+    http::{header, HeaderMap, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
     routing::{get, post},
-    Json, Router,
+    Extension, Json, Router,
 };
 use chrono::Utc;
 use futures::{SinkExt, StreamExt};
 use parking_lot::RwLock;
 use std::collections::{HashMap, HashSet};
+use std::convert::Infallible;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
 use std::sync::Arc;
-use tokio::sync::broadcast;
+use std::time::{Duration, Instant};
+use tokio::sync::{broadcast, oneshot};
 
+use crate::db::DbPool;
+use crate::error::ErrorResponse;
 use crate::services::process_service::ProcessManager;
-use crate::services::ProcessEvent;
+use crate::services::{
+    AgentError, AgentService, AuthorizationService, BootstrapService, CallerContext, EntityChange,
+    GitStatusEvent, MetricsSnapshot, ProcessEvent, RemoteServerConfig, StampedEvent, UsageService,
+    WorkspaceRefreshEvent, WorkspaceService, WorktreeService,
+};
 use crate::types::{
-    AgentContextPayload, AgentErrorPayload, AgentOutputPayload, AgentStatusPayload,
-    AgentTerminatedPayload, AgentStatus, HookNotification, WsClientMessage, WsServerMessage,
+    AgentContextPayload, AgentErrorPayload, AgentIdleShutdownPayload, AgentIdleWarningPayload,
+    AgentListResponse, AgentOutputPayload, AgentResources, AgentResourcesPayload,
+    AgentSnapshotPayload, AgentStatusPayload, AgentTerminatedPayload, CallerOrigin, Capability,
+    EntityChangedPayload, EntityKind, HookNotification, Job, JobUpdatedPayload, NetworkStatus,
+    NetworkStatusPayload, ResyncRequiredPayload, UsageBudgetPayload, WorkspaceListResponse,
+    WorkspaceRefreshedPayload, WorktreeGitStatusPayload, WorktreeListResponse, WsClientMessage,
+    WsServerMessage,
 };
+use crate::util::ansi::{after_carriage_return, strip_ansi_escapes};
+
+/// Event clients are pinged this often; browsers answer pings automatically
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(30);
+/// Clients that sent nothing for this long, not even a pong, are dropped
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(90);
+/// Unsent messages at which a client is logged as falling behind
+const QUEUE_WARN_DEPTH: usize = 1_000;
 
 /// Connected client information
 struct ConnectedClient {
     subscribed_agents: HashSet<String>,
     subscribed_workspaces: HashSet<String>,
     sender: tokio::sync::mpsc::UnboundedSender<String>,
+    /// Messages queued but not yet written to the socket
+    queued: Arc<AtomicUsize>,
+    /// When anything, pongs included, last arrived from the client
+    last_seen: Instant,
+    /// Dropped with the client, which tells its connection to close
+    _removed: oneshot::Sender<()>,
+}
+
+impl ConnectedClient {
+    fn send(&self, message: String) {
+        if self.sender.send(message).is_ok() {
+            self.queued.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+/// The connection's handle on its registered client
+struct ClientConnection {
+    /// Decrement as queued messages are written to the socket
+    queued: Arc<AtomicUsize>,
+    /// Resolves once the client is removed, e.g. by the dead-client sweep
+    removed: oneshot::Receiver<()>,
+}
+
+/// Resolves the workspace an agent belongs to, so workspace subscribers get
+/// the agent's events. Entries are dropped whenever the agent changes, since
+/// agents can move between worktrees.
+struct AgentWorkspaces {
+    agent_service: Arc<AgentService>,
+    worktree_service: Arc<WorktreeService>,
+    cache: RwLock<HashMap<String, String>>,
+}
+
+impl AgentWorkspaces {
+    fn new(agent_service: Arc<AgentService>, worktree_service: Arc<WorktreeService>) -> Self {
+        Self {
+            agent_service,
+            worktree_service,
+            cache: RwLock::new(HashMap::new()),
+        }
+    }
+
+    fn resolve(&self, agent_id: &str) -> Option<String> {
+        if let Some(workspace_id) = self.cache.read().get(agent_id) {
+            return Some(workspace_id.clone());
+        }
+        let agent = self.agent_service.get_agent(agent_id).ok()?;
+        let worktree = self
+            .worktree_service
+            .get_worktree(&agent.worktree_id)
+            .ok()?;
+        self.cache
+            .write()
+            .insert(agent_id.to_string(), worktree.workspace_id.clone());
+        Some(worktree.workspace_id)
+    }
+
+    fn invalidate(&self, agent_id: &str) {
+        self.cache.write().remove(agent_id);
+    }
 }
 
 /// Client manager for tracking WebSocket connections
 struct ClientManager {
     clients: RwLock<HashMap<String, ConnectedClient>>,
+    /// Clients dropped for not answering pings
+    dead_clients: AtomicU64,
 }
 
 impl ClientManager {
     fn new() -> Self {
         Self {
             clients: RwLock::new(HashMap::new()),
+            dead_clients: AtomicU64::new(0),
         }
     }
 
-    fn add_client(&self, id: &str, sender: tokio::sync::mpsc::UnboundedSender<String>) {
+    fn add_client(
+        &self,
+        id: &str,
+        sender: tokio::sync::mpsc::UnboundedSender<String>,
+    ) -> ClientConnection {
+        let queued = Arc::new(AtomicUsize::new(0));
+        let (removed_tx, removed) = oneshot::channel();
         let client = ConnectedClient {
             subscribed_agents: HashSet::new(),
             subscribed_workspaces: HashSet::new(),
             sender,
+            queued: queued.clone(),
+            last_seen: Instant::now(),
+            _removed: removed_tx,
         };
         self.clients.write().insert(id.to_string(), client);
+        ClientConnection { queued, removed }
     }
 
     fn remove_client(&self, id: &str) {
         self.clients.write().remove(id);
     }
 
+    fn client_count(&self) -> usize {
+        self.clients.read().len()
+    }
+
+    /// Record that the client is still there
+    fn touch(&self, client_id: &str) {
+        if let Some(client) = self.clients.write().get_mut(client_id) {
+            client.last_seen = Instant::now();
+        }
+    }
+
+    /// Drop clients not heard from within `timeout`, returning their ids
+    fn sweep(&self, timeout: Duration) -> Vec<String> {
+        let mut clients = self.clients.write();
+        let dead: Vec<String> = clients
+            .iter()
+            .filter(|(_, client)| client.last_seen.elapsed() >= timeout)
+            .map(|(id, _)| id.clone())
+            .collect();
+        for id in &dead {
+            clients.remove(id);
+        }
+        self.dead_clients
+            .fetch_add(dead.len() as u64, Ordering::Relaxed);
+        dead
+    }
+
+    /// Unsent messages per client
+    fn queue_depths(&self) -> Vec<(String, usize)> {
+        self.clients
+            .read()
+            .iter()
+            .map(|(id, client)| (id.clone(), client.queued.load(Ordering::Relaxed)))
+            .collect()
+    }
+
     fn subscribe_to_agent(&self, client_id: &str, agent_id: &str) {
         if let Some(client) = self.clients.write().get_mut(client_id) {
             client.subscribed_agents.insert(agent_id.to_string());
@@ -79,56 +213,248 @@ impl ClientManager {
         }
     }
 
-    fn send_to_agent_subscribers(&self, agent_id: &str, message: &str) {
+    /// Send to clients subscribed to the agent or, if given, to its
+    /// workspace; once per client even when subscribed to both
+    fn send_to_agent_subscribers(&self, agent_id: &str, workspace_id: Option<&str>, message: &str) {
+        let clients = self.clients.read();
+        for client in clients.values() {
+            if client.subscribed_agents.contains(agent_id)
+                || workspace_id.is_some_and(|ws| client.subscribed_workspaces.contains(ws))
+            {
+                client.send(message.to_string());
+            }
+        }
+    }
+
+    fn send_to_workspace_subscribers(&self, workspace_id: &str, message: &str) {
         let clients = self.clients.read();
         for client in clients.values() {
-            if client.subscribed_agents.contains(agent_id) {
-                let _ = client.sender.send(message.to_string());
+            if client.subscribed_workspaces.contains(workspace_id) {
+                client.send(message.to_string());
             }
         }
     }
 
+    fn send_to_all(&self, message: &str) {
+        let clients = self.clients.read();
+        for client in clients.values() {
+            client.send(message.to_string());
+        }
+    }
+
+    /// Tell every client its incremental view is stale
+    fn send_resync_required(&self, skipped: u64) {
+        let msg = WsServerMessage::ResyncRequired(ResyncRequiredPayload {
+            skipped,
+            timestamp: Utc::now().to_rfc3339(),
+        });
+        if let Ok(json) = serde_json::to_string(&msg) {
+            self.send_to_all(&json);
+        }
+    }
+
     fn send_pong(&self, client_id: &str) {
+        self.send_to_client(client_id, &WsServerMessage::Pong);
+    }
+
+    fn send_to_client(&self, client_id: &str, message: &WsServerMessage) {
         let clients = self.clients.read();
         if let Some(client) = clients.get(client_id) {
-            let pong = serde_json::to_string(&WsServerMessage::Pong).unwrap_or_default();
-            let _ = client.sender.send(pong);
+            client.send(serde_json::to_string(message).unwrap_or_default());
+        }
+    }
+}
+
+/// Services the server uses to answer client requests
+pub struct ServerContext {
+    pub pool: DbPool,
+    pub process_manager: Arc<ProcessManager>,
+    pub agent_service: Arc<AgentService>,
+    pub workspace_service: Arc<WorkspaceService>,
+    pub worktree_service: Arc<WorktreeService>,
+    pub bootstrap_service: Arc<BootstrapService>,
+    pub usage_service: Arc<UsageService>,
+    pub authorization_service: Arc<AuthorizationService>,
+    /// Worktree git status changes to push to workspace subscribers
+    pub git_status_rx: broadcast::Receiver<GitStatusEvent>,
+    /// Finished workspace refreshes to push to workspace subscribers
+    pub workspace_refresh_rx: broadcast::Receiver<WorkspaceRefreshEvent>,
+    /// Background job progress to push to every client
+    pub job_rx: broadcast::Receiver<Job>,
+    /// Workspace, worktree and agent mutations to push to every client
+    pub entity_change_rx: broadcast::Receiver<EntityChange>,
+    /// Changes between online and offline to push to every client
+    pub network_status_rx: broadcast::Receiver<NetworkStatus>,
+    /// Agent process resource samples to push to the agents' subscribers
+    pub resources_rx: broadcast::Receiver<AgentResources>,
+    /// Bearer token required by the REST API and by remote clients
+    pub api_token: String,
+    /// Remote listener to start alongside the local one, if enabled
+    pub remote: Option<RemoteServerConfig>,
+}
+
+/// What a connection may do, depending on which listener accepted it
+#[derive(Clone, Copy)]
+struct ClientAccess {
+    origin: CallerOrigin,
+    /// Start/stop agents, resize terminals and send terminal input
+    allow_control: bool,
+    /// Presented the API token, which starting and stopping agents requires
+    authenticated: bool,
+}
+
+impl ClientAccess {
+    /// Drop control for a client that connected as an observer
+    fn for_client(self, params: &ConnectParams) -> Self {
+        Self {
+            allow_control: self.allow_control && !params.observer,
+            ..self
+        }
+    }
+
+    /// Mark a client that presented the API token on connect
+    fn with_token(self, valid: bool) -> Self {
+        Self {
+            authenticated: self.authenticated || valid,
+            ..self
         }
     }
+
+    /// Any local process can reach the local listener, so agents are only
+    /// started and stopped for clients holding the token
+    fn check_token(&self) -> Result<(), String> {
+        if self.authenticated {
+            Ok(())
+        } else {
+            Err("Missing or invalid API token".to_string())
+        }
+    }
+
+    fn caller(&self) -> CallerContext {
+        CallerContext {
+            origin: self.origin,
+            allow_control: self.allow_control,
+        }
+    }
+
+    /// Whether the connection may act right now; observer mode overrides
+    /// every listener
+    fn can_control(&self, process_manager: &ProcessManager) -> bool {
+        self.allow_control && !process_manager.is_read_only()
+    }
+}
+
+/// Query parameters of `/ws` and `/ws/pty/:agent_id`
+#[derive(serde::Deserialize)]
+struct ConnectParams {
+    /// Stream output without being able to act, e.g. on a shared screen
+    #[serde(default)]
+    observer: bool,
 }
 
 /// WebSocket server state
 struct WsState {
     client_manager: Arc<ClientManager>,
+    /// Open terminal sockets, which are not tracked by the client manager
+    pty_clients: AtomicUsize,
+    pool: DbPool,
     process_manager: Arc<ProcessManager>,
+    agent_service: Arc<AgentService>,
+    workspace_service: Arc<WorkspaceService>,
+    worktree_service: Arc<WorktreeService>,
+    bootstrap_service: Arc<BootstrapService>,
+    usage_service: Arc<UsageService>,
+    authorization_service: Arc<AuthorizationService>,
+    api_token: String,
 }
 
 /// Start the WebSocket server
 pub async fn start_websocket_server(
-    mut process_rx: broadcast::Receiver<ProcessEvent>,
-    process_manager: Arc<ProcessManager>,
+    mut process_rx: broadcast::Receiver<StampedEvent>,
+    context: ServerContext,
 ) -> Result<(), std::io::Error> {
     let client_manager = Arc::new(ClientManager::new());
     let state = Arc::new(WsState {
         client_manager: client_manager.clone(),
-        process_manager,
+        pty_clients: AtomicUsize::new(0),
+        pool: context.pool,
+        process_manager: context.process_manager,
+        agent_service: context.agent_service,
+        workspace_service: context.workspace_service,
+        worktree_service: context.worktree_service,
+        bootstrap_service: context.bootstrap_service,
+        usage_service: context.usage_service,
+        authorization_service: context.authorization_service,
+        api_token: context.api_token,
     });
 
-    // Spawn task to broadcast process events
+    // Spawn task to drop clients that vanished without closing, e.g. on sleep
     let cm = client_manager.clone();
     tokio::spawn(async move {
-        while let Ok(event) = process_rx.recv().await {
+        let mut interval = tokio::time::interval(HEARTBEAT_INTERVAL);
+        loop {
+            interval.tick().await;
+            for client_id in cm.sweep(CLIENT_TIMEOUT) {
+                tracing::info!("Dropped unresponsive WebSocket client {}", client_id);
+            }
+            for (client_id, depth) in cm.queue_depths() {
+                if depth >= QUEUE_WARN_DEPTH {
+                    tracing::warn!(
+                        "WebSocket client {} has {} unsent messages",
+                        client_id,
+                        depth
+                    );
+                } else {
+                    tracing::debug!(
+                        "WebSocket client {} has {} unsent messages",
+                        client_id,
+                        depth
+                    );
+                }
+            }
+        }
+    });
+
+    // Spawn task to broadcast process events. Workspace subscribers get
+    // everything but terminal output for the agents in their workspace.
+    let cm = client_manager.clone();
+    let agent_workspaces = Arc::new(AgentWorkspaces::new(
+        state.agent_service.clone(),
+        state.worktree_service.clone(),
+    ));
+    let workspaces = agent_workspaces.clone();
+    tokio::spawn(async move {
+        loop {
+            let StampedEvent {
+                seq,
+                timestamp,
+                event,
+            } = match process_rx.recv().await {
+                Ok(stamped) => stamped,
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    tracing::warn!("Dropped {} process events for WebSocket clients", skipped);
+                    cm.send_resync_required(skipped);
+                    continue;
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            };
+            let timestamp = timestamp.to_rfc3339();
+            let seq = Some(seq);
+            let workspace_wide = !matches!(event, ProcessEvent::Output { .. });
             let message = match event {
                 ProcessEvent::Output {
                     agent_id,
+                    message_id,
                     content,
                     is_complete,
                 } => {
                     let payload = AgentOutputPayload {
                         agent_id: agent_id.clone(),
+                        message_id,
                         content,
                         is_complete,
-                        timestamp: Utc::now().to_rfc3339(),
+                        timestamp,
+                        seq,
                     };
                     let msg = WsServerMessage::AgentOutput(payload);
                     Some((agent_id, serde_json::to_string(&msg).ok()))
@@ -142,7 +468,8 @@ pub async fn start_websocket_server(
                         agent_id: agent_id.clone(),
                         status,
                         reason,
-                        timestamp: Utc::now().to_rfc3339(),
+                        timestamp,
+                        seq,
                     };
                     let msg = WsServerMessage::AgentStatus(payload);
                     Some((agent_id, serde_json::to_string(&msg).ok()))
@@ -151,7 +478,8 @@ pub async fn start_websocket_server(
                     let payload = AgentContextPayload {
                         agent_id: agent_id.clone(),
                         level,
-                        timestamp: Utc::now().to_rfc3339(),
+                        timestamp,
+                        seq,
                     };
                     let msg = WsServerMessage::AgentContext(payload);
                     Some((agent_id, serde_json::to_string(&msg).ok()))
@@ -160,7 +488,8 @@ pub async fn start_websocket_server(
                     let payload = AgentErrorPayload {
                         agent_id: agent_id.clone(),
                         error: message,
-                        timestamp: Utc::now().to_rfc3339(),
+                        timestamp,
+                        seq,
                     };
                     let msg = WsServerMessage::AgentError(payload);
                     Some((agent_id, serde_json::to_string(&msg).ok()))
@@ -169,28 +498,279 @@ pub async fn start_websocket_server(
                     agent_id,
                     code,
                     signal,
+                    ..
                 } => {
                     let payload = AgentTerminatedPayload {
                         agent_id: agent_id.clone(),
                         exit_code: code,
                         signal,
-                        timestamp: Utc::now().to_rfc3339(),
+                        timestamp,
+                        seq,
                     };
                     let msg = WsServerMessage::AgentTerminated(payload);
                     Some((agent_id, serde_json::to_string(&msg).ok()))
                 }
+                ProcessEvent::IdleWarning {
+                    agent_id,
+                    shutdown_in_secs,
+                } => {
+                    let payload = AgentIdleWarningPayload {
+                        agent_id: agent_id.clone(),
+                        shutdown_in_secs,
+                        timestamp,
+                        seq,
+                    };
+                    let msg = WsServerMessage::AgentIdleWarning(payload);
+                    Some((agent_id, serde_json::to_string(&msg).ok()))
+                }
+                ProcessEvent::IdleTimeout {
+                    agent_id,
+                    idle_secs,
+                } => {
+                    let payload = AgentIdleShutdownPayload {
+                        agent_id: agent_id.clone(),
+                        idle_secs,
+                        timestamp,
+                        seq,
+                    };
+                    let msg = WsServerMessage::AgentIdleShutdown(payload);
+                    Some((agent_id, serde_json::to_string(&msg).ok()))
+                }
             };
 
             if let Some((agent_id, Some(json))) = message {
-                cm.send_to_agent_subscribers(&agent_id, &json);
+                let workspace_id = workspace_wide
+                    .then(|| workspaces.resolve(&agent_id))
+                    .flatten();
+                cm.send_to_agent_subscribers(&agent_id, workspace_id.as_deref(), &json);
+            }
+        }
+    });
+
+    // Spawn task to push worktree git status changes
+    let cm = client_manager.clone();
+    let mut git_status_rx = context.git_status_rx;
+    tokio::spawn(async move {
+        loop {
+            let event = match git_status_rx.recv().await {
+                Ok(event) => event,
+                // Only the latest status matters, so skipped updates are fine
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => break,
+            };
+            let msg = WsServerMessage::WorktreeGitStatus(WorktreeGitStatusPayload {
+                workspace_id: event.workspace_id.clone(),
+                worktree_id: event.worktree_id,
+                status: event.status,
+                timestamp: Utc::now().to_rfc3339(),
+            });
+            if let Ok(json) = serde_json::to_string(&msg) {
+                cm.send_to_workspace_subscribers(&event.workspace_id, &json);
+            }
+        }
+    });
+
+    // Spawn task to push finished workspace refreshes
+    let cm = client_manager.clone();
+    let mut workspace_refresh_rx = context.workspace_refresh_rx;
+    tokio::spawn(async move {
+        loop {
+            let event = match workspace_refresh_rx.recv().await {
+                Ok(event) => event,
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => break,
+            };
+            let workspace_id = event.job.workspace_id.clone();
+            let msg = WsServerMessage::WorkspaceRefreshed(WorkspaceRefreshedPayload {
+                job: event.job,
+                workspace: event.workspace,
+                timestamp: Utc::now().to_rfc3339(),
+            });
+            if let Ok(json) = serde_json::to_string(&msg) {
+                cm.send_to_workspace_subscribers(&workspace_id, &json);
+            }
+        }
+    });
+
+    // Spawn task to push background job progress
+    let cm = client_manager.clone();
+    let mut job_rx = context.job_rx;
+    tokio::spawn(async move {
+        loop {
+            let job = match job_rx.recv().await {
+                Ok(job) => job,
+                // Later updates carry the whole job, so skipped ones are fine
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => break,
+            };
+            let msg = WsServerMessage::JobUpdated(JobUpdatedPayload {
+                job,
+                timestamp: Utc::now().to_rfc3339(),
+            });
+            if let Ok(json) = serde_json::to_string(&msg) {
+                cm.send_to_all(&json);
+            }
+        }
+    });
+
+    // Spawn task to warn every client when usage crosses a budget threshold
+    let cm = client_manager.clone();
+    let mut budget_alert_rx = state.usage_service.subscribe();
+    tokio::spawn(async move {
+        loop {
+            let alert = match budget_alert_rx.recv().await {
+                Ok(alert) => alert,
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => break,
+            };
+            let msg = WsServerMessage::UsageBudget(UsageBudgetPayload {
+                alert,
+                timestamp: Utc::now().to_rfc3339(),
+            });
+            if let Ok(json) = serde_json::to_string(&msg) {
+                cm.send_to_all(&json);
+            }
+        }
+    });
+
+    // Spawn task to tell every client when the app goes offline or back online
+    let cm = client_manager.clone();
+    let mut network_status_rx = context.network_status_rx;
+    tokio::spawn(async move {
+        loop {
+            let status = match network_status_rx.recv().await {
+                Ok(status) => status,
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => break,
+            };
+            let msg = WsServerMessage::NetworkStatus(NetworkStatusPayload {
+                status,
+                timestamp: Utc::now().to_rfc3339(),
+            });
+            if let Ok(json) = serde_json::to_string(&msg) {
+                cm.send_to_all(&json);
             }
         }
     });
 
+    // Spawn task to push agent process resource samples to the agents'
+    // subscribers, workspace subscribers included
+    let cm = client_manager.clone();
+    let workspaces = agent_workspaces.clone();
+    let mut resources_rx = context.resources_rx;
+    tokio::spawn(async move {
+        loop {
+            let resources = match resources_rx.recv().await {
+                Ok(resources) => resources,
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => break,
+            };
+            let agent_id = resources.agent_id.clone();
+            let msg = WsServerMessage::AgentResources(AgentResourcesPayload {
+                resources,
+                timestamp: Utc::now().to_rfc3339(),
+            });
+            if let Ok(json) = serde_json::to_string(&msg) {
+                let workspace_id = workspaces.resolve(&agent_id);
+                cm.send_to_agent_subscribers(&agent_id, workspace_id.as_deref(), &json);
+            }
+        }
+    });
+
+    // Spawn task to keep every client's view of entities in sync
+    let cm = client_manager.clone();
+    let mut entity_change_rx = context.entity_change_rx;
+    tokio::spawn(async move {
+        loop {
+            let change = match entity_change_rx.recv().await {
+                Ok(change) => change,
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    tracing::warn!("Dropped {} entity changes for WebSocket clients", skipped);
+                    cm.send_resync_required(skipped);
+                    continue;
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            };
+            if change.entity == EntityKind::Agent {
+                agent_workspaces.invalidate(&change.id);
+            }
+            let msg = WsServerMessage::EntityChanged(EntityChangedPayload {
+                entity: change.entity,
+                action: change.action,
+                id: change.id,
+                data: change.data,
+                timestamp: Utc::now().to_rfc3339(),
+            });
+            if let Ok(json) = serde_json::to_string(&msg) {
+                cm.send_to_all(&json);
+            }
+        }
+    });
+
+    if let Some(remote) = context.remote {
+        // Everything is token-guarded remotely, and hooks are only meant for local CLIs
+        let remote_app = Router::new()
+            .route("/ws", get(ws_handler))
+            .route("/ws/pty/:agent_id", get(pty_ws_handler))
+            .route("/ws/log/:agent_id", get(log_ws_handler))
+            .route("/ws/bootstrap/:worktree_id", get(bootstrap_ws_handler))
+            .nest("/api", api_routes())
+            .route("/metrics", get(metrics_handler))
+            .route_layer(middleware::from_fn_with_state(
+                state.clone(),
+                require_api_token,
+            ))
+            .layer(Extension(ClientAccess {
+                origin: CallerOrigin::Remote,
+                allow_control: remote.allow_control,
+                authenticated: true,
+            }))
+            .with_state(state.clone());
+
+        tokio::spawn(async move {
+            tracing::info!(
+                "Remote access listening on wss://{} (control {})",
+                remote.bind_address,
+                if remote.allow_control {
+                    "allowed"
+                } else {
+                    "disabled"
+                }
+            );
+            if let Err(e) = axum_server::bind_rustls(remote.bind_address, remote.tls)
+                .serve(remote_app.into_make_service())
+                .await
+            {
+                tracing::error!("Remote access server error: {}", e);
+            }
+        });
+    }
+
     let app = Router::new()
         .route("/ws", get(ws_handler))
         .route("/ws/pty/:agent_id", get(pty_ws_handler))
+        .route("/ws/log/:agent_id", get(log_ws_handler))
+        .route("/ws/bootstrap/:worktree_id", get(bootstrap_ws_handler))
         .route("/hooks", post(hooks_handler))
+        .nest(
+            "/api",
+            api_routes().route_layer(middleware::from_fn_with_state(
+                state.clone(),
+                require_api_token,
+            )),
+        )
+        .route(
+            "/metrics",
+            get(metrics_handler).route_layer(middleware::from_fn_with_state(
+                state.clone(),
+                require_api_token,
+            )),
+        )
+        .layer(Extension(ClientAccess {
+            origin: CallerOrigin::Local,
+            allow_control: true,
+            authenticated: false,
+        }))
         .with_state(state);
 
     let listener = tokio::net::TcpListener::bind("127.0.0.1:3001").await?;
@@ -203,23 +783,44 @@ pub async fn start_websocket_server(
 
 async fn ws_handler(
     ws: WebSocketUpgrade,
+    headers: HeaderMap,
+    RawQuery(query): RawQuery,
+    Query(params): Query<ConnectParams>,
+    Extension(access): Extension<ClientAccess>,
     State(state): State<Arc<WsState>>,
 ) -> impl IntoResponse {
-    ws.on_upgrade(move |socket| handle_socket(socket, state))
+    let token_valid = request_token(&headers, query.as_deref())
+        .is_some_and(|token| tokens_match(token, &state.api_token));
+    let access = access.for_client(&params).with_token(token_valid);
+    ws.on_upgrade(move |socket| handle_socket(socket, access, state))
 }
 
-async fn handle_socket(socket: WebSocket, state: Arc<WsState>) {
+async fn handle_socket(socket: WebSocket, access: ClientAccess, state: Arc<WsState>) {
     let (mut sender, mut receiver) = socket.split();
     let client_id = uuid::Uuid::new_v4().to_string();
 
     // Create channel for sending messages to this client
     let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel::<String>();
-    state.client_manager.add_client(&client_id, tx);
+    let ClientConnection {
+        queued,
+        mut removed,
+    } = state.client_manager.add_client(&client_id, tx);
 
-    // Task to send messages to the WebSocket
+    // Task to send messages and heartbeat pings to the WebSocket
     let send_task = tokio::spawn(async move {
-        while let Some(msg) = rx.recv().await {
-            if sender.send(Message::Text(msg)).await.is_err() {
+        let mut heartbeat = tokio::time::interval(HEARTBEAT_INTERVAL);
+        loop {
+            let message = tokio::select! {
+                msg = rx.recv() => match msg {
+                    Some(text) => {
+                        queued.fetch_sub(1, Ordering::Relaxed);
+                        Message::Text(text)
+                    }
+                    None => break,
+                },
+                _ = heartbeat.tick() => Message::Ping(Vec::new()),
+            };
+            if sender.send(message).await.is_err() {
                 break;
             }
         }
@@ -229,12 +830,22 @@ async fn handle_socket(socket: WebSocket, state: Arc<WsState>) {
     let client_manager = state.client_manager.clone();
     let client_id_clone = client_id.clone();
 
-    while let Some(msg) = receiver.next().await {
+    loop {
+        let msg = tokio::select! {
+            msg = receiver.next() => msg,
+            // Swept as dead; a write to a vanished peer may never finish
+            _ = &mut removed => None,
+        };
+        let Some(msg) = msg else {
+            break;
+        };
+        client_manager.touch(&client_id_clone);
         if let Ok(Message::Text(text)) = msg {
             if let Ok(parsed) = serde_json::from_str::<WsClientMessage>(&text) {
                 match parsed {
                     WsClientMessage::SubscribeAgent { payload } => {
                         client_manager.subscribe_to_agent(&client_id_clone, &payload.agent_id);
+                        send_agent_snapshot(&state, &client_id_clone, &payload.agent_id);
                     }
                     WsClientMessage::UnsubscribeAgent { payload } => {
                         client_manager.unsubscribe_from_agent(&client_id_clone, &payload.agent_id);
@@ -247,6 +858,76 @@ async fn handle_socket(socket: WebSocket, state: Arc<WsState>) {
                         client_manager
                             .unsubscribe_from_workspace(&client_id_clone, &payload.workspace_id);
                     }
+                    WsClientMessage::StartAgent { payload } => {
+                        let result = access
+                            .check_token()
+                            .and_then(|()| {
+                                state
+                                    .authorization_service
+                                    .authorize(
+                                        &access.caller(),
+                                        "start_agent",
+                                        Capability::AgentControl,
+                                        Some(&payload.agent_id),
+                                    )
+                                    .map_err(|e| e.to_string())
+                            })
+                            .and_then(|()| {
+                                state
+                                    .agent_service
+                                    .get_agent(&payload.agent_id)
+                                    .map_err(|e| e.to_string())
+                            })
+                            .and_then(|agent| {
+                                state
+                                    .worktree_service
+                                    .get_worktree(&agent.worktree_id)
+                                    .map_err(|e| e.to_string())
+                            })
+                            .and_then(|worktree| {
+                                state
+                                    .agent_service
+                                    .start_agent(
+                                        &payload.agent_id,
+                                        &worktree.path,
+                                        payload.initial_prompt.as_deref(),
+                                    )
+                                    .map_err(|e| e.to_string())
+                            });
+                        send_agent_result(
+                            &client_manager,
+                            &client_id_clone,
+                            &payload.agent_id,
+                            result,
+                        );
+                    }
+                    WsClientMessage::StopAgent { payload } => {
+                        let result = access
+                            .check_token()
+                            .and_then(|()| {
+                                state
+                                    .authorization_service
+                                    .authorize(
+                                        &access.caller(),
+                                        "stop_agent",
+                                        Capability::AgentControl,
+                                        Some(&payload.agent_id),
+                                    )
+                                    .map_err(|e| e.to_string())
+                            })
+                            .and_then(|()| {
+                                state
+                                    .agent_service
+                                    .stop_agent(&payload.agent_id, payload.force)
+                                    .map_err(|e| e.to_string())
+                            });
+                        send_agent_result(
+                            &client_manager,
+                            &client_id_clone,
+                            &payload.agent_id,
+                            result,
+                        );
+                    }
                     WsClientMessage::Ping => {
                         client_manager.send_pong(&client_id_clone);
                     }
@@ -260,6 +941,308 @@ async fn handle_socket(socket: WebSocket, state: Arc<WsState>) {
     send_task.abort();
 }
 
+/// Send a newly subscribed client the agent's current state, so it renders
+/// correctly without waiting for the next event
+fn send_agent_snapshot(state: &WsState, client_id: &str, agent_id: &str) {
+    let Ok(agent) = state.agent_service.get_agent(agent_id) else {
+        return;
+    };
+    let process = state.process_manager.snapshot(agent_id);
+    let message = WsServerMessage::AgentSnapshot(AgentSnapshotPayload {
+        agent_id: agent.id,
+        status: agent.status,
+        reason: agent.status_reason,
+        context_level: process.context_level.unwrap_or(agent.context_level),
+        is_running: process.is_running,
+        last_activity_at: process
+            .last_activity
+            .map(|at| at.to_rfc3339())
+            .unwrap_or(agent.updated_at),
+        timestamp: Utc::now().to_rfc3339(),
+    });
+    state.client_manager.send_to_client(client_id, &message);
+}
+
+/// Reply to a start/stop request with the agent's new status or the failure
+fn send_agent_result(
+    client_manager: &ClientManager,
+    client_id: &str,
+    agent_id: &str,
+    result: Result<crate::types::Agent, String>,
+) {
+    let message = match result {
+        Ok(agent) => WsServerMessage::AgentStatus(AgentStatusPayload {
+            agent_id: agent.id,
+            status: agent.status,
+            reason: None,
+            timestamp: Utc::now().to_rfc3339(),
+            seq: None,
+        }),
+        Err(error) => WsServerMessage::AgentError(AgentErrorPayload {
+            agent_id: agent_id.to_string(),
+            error,
+            timestamp: Utc::now().to_rfc3339(),
+            seq: None,
+        }),
+    };
+    client_manager.send_to_client(client_id, &message);
+}
+
+// --- REST API ---
+
+fn api_routes() -> Router<Arc<WsState>> {
+    Router::new()
+        .route("/workspaces", get(api_list_workspaces))
+        .route(
+            "/workspaces/:workspace_id/worktrees",
+            get(api_list_worktrees),
+        )
+        .route("/worktrees/:worktree_id/agents", get(api_list_agents))
+        .route("/agents/:agent_id", get(api_get_agent))
+        .route("/agents/:agent_id/log", get(api_tail_agent_log))
+        .route("/usage", get(api_get_usage))
+}
+
+/// Load the REST API token from the data directory, generating it on first use
+///
+/// The file is only readable by the current user so local tools can pick it up.
+pub fn load_or_create_api_token(data_dir: &std::path::Path) -> std::io::Result<String> {
+    let path = data_dir.join("api-token");
+    if let Ok(token) = std::fs::read_to_string(&path) {
+        let token = token.trim();
+        if !token.is_empty() {
+            return Ok(token.to_string());
+        }
+    }
+
+    let token = format!(
+        "{}{}",
+        uuid::Uuid::new_v4().simple(),
+        uuid::Uuid::new_v4().simple()
+    );
+    std::fs::create_dir_all(data_dir)?;
+    std::fs::write(&path, &token)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600))?;
+    }
+
+    Ok(token)
+}
+
+/// Reject requests without `Authorization: Bearer <token>` or a `?token=` query
+///
+/// Browsers cannot set headers on WebSocket upgrades, hence the query fallback.
+async fn require_api_token(
+    State(state): State<Arc<WsState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    match request_token(request.headers(), request.uri().query()) {
+        Some(token) if tokens_match(token, &state.api_token) => next.run(request).await,
+        _ => api_error(
+            StatusCode::UNAUTHORIZED,
+            "UNAUTHORIZED",
+            "Missing or invalid API token".to_string(),
+        ),
+    }
+}
+
+/// The token from an `Authorization: Bearer` header or a `token=` query pair
+fn request_token<'a>(headers: &'a HeaderMap, query: Option<&'a str>) -> Option<&'a str> {
+    headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .or_else(|| {
+            query.and_then(|query| {
+                query
+                    .split('&')
+                    .find_map(|pair| pair.strip_prefix("token="))
+            })
+        })
+}
+
+/// Compare tokens without short-circuiting on the first differing byte
+fn tokens_match(provided: &str, expected: &str) -> bool {
+    provided.len() == expected.len()
+        && provided
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+fn api_error(status: StatusCode, code: &str, message: String) -> Response {
+    let body = ErrorResponse {
+        code: code.to_string(),
+        message,
+        details: None,
+    };
+    (status, Json(body)).into_response()
+}
+
+/// GET /api/workspaces
+async fn api_list_workspaces(State(state): State<Arc<WsState>>) -> Response {
+    match state.workspace_service.list_workspaces() {
+        Ok(workspaces) => Json(WorkspaceListResponse { workspaces }).into_response(),
+        Err(e) => api_error(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "WORKSPACE_ERROR",
+            e.to_string(),
+        ),
+    }
+}
+
+/// GET /api/workspaces/:workspace_id/worktrees
+async fn api_list_worktrees(
+    Path(workspace_id): Path<String>,
+    State(state): State<Arc<WsState>>,
+) -> Response {
+    match state.worktree_service.list_worktrees(&workspace_id) {
+        Ok(worktrees) => Json(WorktreeListResponse { worktrees }).into_response(),
+        Err(e) => api_error(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "WORKTREE_ERROR",
+            e.to_string(),
+        ),
+    }
+}
+
+/// GET /api/worktrees/:worktree_id/agents
+async fn api_list_agents(
+    Path(worktree_id): Path<String>,
+    State(state): State<Arc<WsState>>,
+) -> Response {
+    match state.agent_service.list_agents(&worktree_id, false, &[]) {
+        Ok(agents) => Json(AgentListResponse { agents }).into_response(),
+        Err(e) => api_error(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "AGENT_ERROR",
+            e.to_string(),
+        ),
+    }
+}
+
+/// GET /api/agents/:agent_id
+async fn api_get_agent(
+    Path(agent_id): Path<String>,
+    State(state): State<Arc<WsState>>,
+) -> Response {
+    match state.agent_service.get_agent(&agent_id) {
+        Ok(agent) => Json(agent).into_response(),
+        Err(AgentError::NotFound(id)) => api_error(
+            StatusCode::NOT_FOUND,
+            "NOT_FOUND",
+            format!("Agent not found: {}", id),
+        ),
+        Err(e) => api_error(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "AGENT_ERROR",
+            e.to_string(),
+        ),
+    }
+}
+
+/// GET /api/agents/:agent_id/log — plain-text output lines, streamed while the agent runs
+async fn api_tail_agent_log(
+    Path(agent_id): Path<String>,
+    State(state): State<Arc<WsState>>,
+) -> Response {
+    let Some((output_rx, buffer)) = state.process_manager.subscribe_pty_output(&agent_id) else {
+        return api_error(
+            StatusCode::NOT_FOUND,
+            "NOT_FOUND",
+            format!("Agent is not running: {}", agent_id),
+        );
+    };
+
+    let mut lines = LineAssembler::default();
+    let replay = lines.push(&buffer);
+    let live = futures::stream::unfold((output_rx, lines), |(mut rx, mut lines)| async move {
+        loop {
+            match rx.recv().await {
+                Ok(bytes) => {
+                    let new_lines = lines.push(&bytes);
+                    if !new_lines.is_empty() {
+                        return Some((new_lines, (rx, lines)));
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    });
+    let body = futures::stream::iter([replay])
+        .chain(live)
+        .filter(|lines| futures::future::ready(!lines.is_empty()))
+        .map(|lines| Ok::<_, Infallible>(lines.join("\n") + "\n"));
+
+    (
+        [(header::CONTENT_TYPE, "text/plain; charset=utf-8")],
+        Body::from_stream(body),
+    )
+        .into_response()
+}
+
+/// GET /api/usage
+async fn api_get_usage(State(state): State<Arc<WsState>>) -> Response {
+    match state.usage_service.get_usage_summary() {
+        Ok(summary) => Json(summary).into_response(),
+        Err(e) => api_error(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "USAGE_ERROR",
+            e.to_string(),
+        ),
+    }
+}
+
+/// GET /metrics — Prometheus text format
+async fn metrics_handler(State(state): State<Arc<WsState>>) -> Response {
+    let agents_by_status = match state.agent_service.count_by_status() {
+        Ok(counts) => counts,
+        Err(e) => {
+            return api_error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "AGENT_ERROR",
+                e.to_string(),
+            )
+        }
+    };
+    let usage = match state.usage_service.get_today_usage() {
+        Ok(usage) => usage,
+        Err(e) => {
+            return api_error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "USAGE_ERROR",
+                e.to_string(),
+            )
+        }
+    };
+    let pool_state = state.pool.state();
+
+    let snapshot = MetricsSnapshot {
+        running_agents: state.process_manager.get_running_count(),
+        agents_by_status,
+        pty_output_bytes: state.process_manager.pty_output_bytes(),
+        ws_event_clients: state.client_manager.client_count(),
+        ws_pty_clients: state.pty_clients.load(Ordering::Relaxed),
+        ws_client_queue_depths: state.client_manager.queue_depths(),
+        ws_dead_clients: state.client_manager.dead_clients.load(Ordering::Relaxed),
+        db_pool_in_use: pool_state.connections - pool_state.idle_connections,
+        db_pool_max: state.pool.max_size(),
+        usage_input_tokens_today: usage.input_tokens,
+        usage_output_tokens_today: usage.output_tokens,
+    };
+
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        snapshot.render(),
+    )
+        .into_response()
+}
+
 // --- Hook notification endpoint ---
 
 /// POST /hooks — receives Claude Code hook notifications for instant status detection
@@ -267,34 +1250,34 @@ async fn hooks_handler(
     State(state): State<Arc<WsState>>,
     Json(notification): Json<HookNotification>,
 ) -> impl IntoResponse {
-    let status = match notification.notification_type.as_deref() {
-        Some("permission_prompt") => Some(AgentStatus::Waiting),
-        Some("idle_prompt") => Some(AgentStatus::Idle),
-        Some("elicitation_dialog") => Some(AgentStatus::Waiting),
-        _ => None,
-    };
+    dispatch_hook(&state.process_manager, &notification);
+    StatusCode::OK
+}
 
-    if let Some(status) = status {
-        if let Some(agent_id) = state
-            .process_manager
-            .find_agent_by_session(notification.session_id.as_deref())
-        {
-            tracing::debug!(
-                "Hook: agent {} → {:?} (type: {:?})",
-                agent_id,
-                status,
-                notification.notification_type,
-            );
-            state.process_manager.set_hook_status(&agent_id, status);
-        } else {
-            tracing::debug!(
-                "Hook: no agent found for session_id={:?}",
-                notification.session_id
-            );
-        }
-    }
+/// Apply the status a hook notification reports to the agent running its
+/// session, returning that agent's ID
+fn dispatch_hook(
+    process_manager: &ProcessManager,
+    notification: &HookNotification,
+) -> Option<String> {
+    let status = notification.agent_status()?;
+    let Some(agent_id) = process_manager.find_agent_by_session(notification.session_id.as_deref())
+    else {
+        tracing::debug!(
+            "Hook: no agent found for session_id={:?}",
+            notification.session_id
+        );
+        return None;
+    };
 
-    axum::http::StatusCode::OK
+    tracing::debug!(
+        "Hook: agent {} → {:?} (matcher: {:?})",
+        agent_id,
+        status,
+        notification.matcher(),
+    );
+    process_manager.set_hook_status(&agent_id, status);
+    Some(agent_id)
 }
 
 // --- PTY WebSocket endpoint ---
@@ -311,12 +1294,20 @@ struct ResizeMsg {
 async fn pty_ws_handler(
     ws: WebSocketUpgrade,
     Path(agent_id): Path<String>,
+    Query(params): Query<ConnectParams>,
+    Extension(access): Extension<ClientAccess>,
     State(state): State<Arc<WsState>>,
 ) -> impl IntoResponse {
-    ws.on_upgrade(move |socket| handle_pty_socket(socket, agent_id, state))
+    let access = access.for_client(&params);
+    ws.on_upgrade(move |socket| handle_pty_socket(socket, agent_id, access, state))
 }
 
-async fn handle_pty_socket(socket: WebSocket, agent_id: String, state: Arc<WsState>) {
+async fn handle_pty_socket(
+    socket: WebSocket,
+    agent_id: String,
+    access: ClientAccess,
+    state: Arc<WsState>,
+) {
     let (mut ws_sender, mut ws_receiver) = socket.split();
 
     // Subscribe to PTY output (broadcast — multiple subscribers OK)
@@ -333,7 +1324,9 @@ async fn handle_pty_socket(socket: WebSocket, agent_id: String, state: Arc<WsSta
     };
 
     let input_tx = match input_tx {
-        Some(tx) => tx,
+        Some(tx) => Some(tx),
+        // Observer mode withholds input, but output still streams
+        None if state.process_manager.is_read_only() => None,
         None => {
             tracing::warn!("PTY WebSocket: no input channel for agent {}", agent_id);
             let _ = ws_sender.close().await;
@@ -355,6 +1348,8 @@ async fn handle_pty_socket(socket: WebSocket, agent_id: String, state: Arc<WsSta
         }
     }
 
+    state.pty_clients.fetch_add(1, Ordering::Relaxed);
+
     // Task: PTY output → WebSocket binary frames (broadcast receiver)
     let send_task = tokio::spawn(async move {
         loop {
@@ -383,14 +1378,18 @@ async fn handle_pty_socket(socket: WebSocket, agent_id: String, state: Arc<WsSta
     let agent_id_clone = agent_id.clone();
     while let Some(Ok(msg)) = ws_receiver.next().await {
         match msg {
+            // Read-only clients still get output, but never reach the PTY
+            Message::Binary(_) | Message::Text(_) if !access.can_control(&pm) => {}
             Message::Binary(data) => {
-                let _ = input_tx.send(data.to_vec());
+                if let Some(input_tx) = &input_tx {
+                    let _ = input_tx.send(data.to_vec());
+                }
             }
             Message::Text(text) => {
                 // Check for resize JSON, otherwise treat as terminal input
                 if let Ok(resize) = serde_json::from_str::<ResizeMsg>(&text) {
                     let _ = pm.resize_pty(&agent_id_clone, resize.rows, resize.cols);
-                } else {
+                } else if let Some(input_tx) = &input_tx {
                     let _ = input_tx.send(text.into_bytes());
                 }
             }
@@ -400,4 +1399,327 @@ async fn handle_pty_socket(socket: WebSocket, agent_id: String, state: Arc<WsSta
     }
 
     send_task.abort();
+    state.pty_clients.fetch_sub(1, Ordering::Relaxed);
+}
+
+// --- Log tail WebSocket endpoint ---
+
+/// Longest partial line held back while waiting for its newline
+const MAX_PARTIAL_LINE: usize = 16 * 1024;
+
+/// Turns raw PTY output into complete lines of plain text
+#[derive(Default)]
+struct LineAssembler {
+    partial: Vec<u8>,
+}
+
+impl LineAssembler {
+    /// Feed PTY bytes, returning the lines they complete
+    fn push(&mut self, bytes: &[u8]) -> Vec<String> {
+        self.partial.extend_from_slice(bytes);
+        let end = match self.partial.iter().rposition(|&b| b == b'\n') {
+            Some(newline) => newline + 1,
+            None if self.partial.len() > MAX_PARTIAL_LINE => self.partial.len(),
+            None => return Vec::new(),
+        };
+
+        let complete: Vec<u8> = self.partial.drain(..end).collect();
+        let text = strip_ansi_escapes(&String::from_utf8_lossy(&complete));
+        text.strip_suffix('\n')
+            .unwrap_or(&text)
+            .split('\n')
+            .map(|line| after_carriage_return(line).to_string())
+            .collect()
+    }
+}
+
+async fn log_ws_handler(
+    ws: WebSocketUpgrade,
+    Path(agent_id): Path<String>,
+    State(state): State<Arc<WsState>>,
+) -> impl IntoResponse {
+    ws.on_upgrade(move |socket| handle_log_socket(socket, agent_id, state))
+}
+
+/// Send each output line as a text frame. The socket is read-only.
+async fn handle_log_socket(socket: WebSocket, agent_id: String, state: Arc<WsState>) {
+    let (mut ws_sender, mut ws_receiver) = socket.split();
+
+    let Some((mut output_rx, buffer)) = state.process_manager.subscribe_pty_output(&agent_id)
+    else {
+        tracing::warn!("Log WebSocket: no output channel for agent {}", agent_id);
+        let _ = ws_sender.close().await;
+        return;
+    };
+
+    let mut lines = LineAssembler::default();
+    let replay = lines.push(&buffer);
+    let send_task = tokio::spawn(async move {
+        for line in replay {
+            if ws_sender.send(Message::Text(line)).await.is_err() {
+                return;
+            }
+        }
+        loop {
+            match output_rx.recv().await {
+                Ok(bytes) => {
+                    for line in lines.push(&bytes) {
+                        if ws_sender.send(Message::Text(line)).await.is_err() {
+                            return;
+                        }
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(n)) => {
+                    tracing::debug!("Log WebSocket lagged by {} messages, continuing", n);
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            }
+        }
+        let _ = ws_sender.close().await;
+    });
+
+    while let Some(Ok(msg)) = ws_receiver.next().await {
+        if let Message::Close(_) = msg {
+            break;
+        }
+    }
+
+    send_task.abort();
+}
+
+// --- Worktree bootstrap WebSocket endpoint ---
+
+async fn bootstrap_ws_handler(
+    ws: WebSocketUpgrade,
+    Path(worktree_id): Path<String>,
+    State(state): State<Arc<WsState>>,
+) -> impl IntoResponse {
+    ws.on_upgrade(move |socket| handle_bootstrap_socket(socket, worktree_id, state))
+}
+
+/// Send a running bootstrap's terminal output as binary frames, closing
+/// when it finishes. The socket is read-only.
+async fn handle_bootstrap_socket(socket: WebSocket, worktree_id: String, state: Arc<WsState>) {
+    let (mut ws_sender, mut ws_receiver) = socket.split();
+
+    let Some((mut output_rx, buffer)) = state.bootstrap_service.subscribe_output(&worktree_id)
+    else {
+        let _ = ws_sender.close().await;
+        return;
+    };
+
+    let send_task = tokio::spawn(async move {
+        for chunk in buffer.chunks(4096) {
+            if ws_sender
+                .send(Message::Binary(chunk.to_vec()))
+                .await
+                .is_err()
+            {
+                return;
+            }
+        }
+        loop {
+            match output_rx.recv().await {
+                Ok(bytes) => {
+                    if ws_sender.send(Message::Binary(bytes)).await.is_err() {
+                        return;
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(n)) => {
+                    tracing::debug!("Bootstrap WebSocket lagged by {} messages, continuing", n);
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            }
+        }
+        let _ = ws_sender.close().await;
+    });
+
+    while let Some(Ok(msg)) = ws_receiver.next().await {
+        if let Message::Close(_) = msg {
+            break;
+        }
+    }
+
+    send_task.abort();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_api_token_is_persisted() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let first = load_or_create_api_token(dir.path()).unwrap();
+        let second = load_or_create_api_token(dir.path()).unwrap();
+
+        assert_eq!(first.len(), 64);
+        assert_eq!(first, second);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_api_token_file_is_private() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        load_or_create_api_token(dir.path()).unwrap();
+
+        let mode = std::fs::metadata(dir.path().join("api-token"))
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+
+    #[test]
+    fn test_tokens_match() {
+        assert!(tokens_match("secret", "secret"));
+        assert!(!tokens_match("secret", "secreT"));
+        assert!(!tokens_match("secret", "secret2"));
+        assert!(!tokens_match("", "secret"));
+    }
+
+    #[test]
+    fn test_observers_cannot_control() {
+        let pm = ProcessManager::new("echo".to_string());
+        let local = ClientAccess {
+            origin: CallerOrigin::Local,
+            allow_control: true,
+            authenticated: false,
+        };
+        let params: ConnectParams = serde_json::from_str(r#"{"observer": true}"#).unwrap();
+
+        assert!(local.can_control(&pm));
+        assert!(!local.for_client(&params).can_control(&pm));
+
+        pm.set_read_only(true);
+        assert!(!local.can_control(&pm));
+    }
+
+    #[test]
+    fn test_local_agent_control_requires_token() {
+        let local = ClientAccess {
+            origin: CallerOrigin::Local,
+            allow_control: true,
+            authenticated: false,
+        };
+        let remote = ClientAccess {
+            origin: CallerOrigin::Remote,
+            allow_control: true,
+            authenticated: true,
+        };
+
+        assert!(local.check_token().is_err());
+        assert!(local.with_token(false).check_token().is_err());
+        assert!(local.with_token(true).check_token().is_ok());
+        assert!(remote.with_token(false).check_token().is_ok());
+    }
+
+    #[test]
+    fn test_request_token_from_header_or_query() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(
+            request_token(&headers, Some("observer=true&token=abc")),
+            Some("abc")
+        );
+        assert_eq!(request_token(&headers, Some("observer=true")), None);
+
+        headers.insert(header::AUTHORIZATION, "Bearer xyz".parse().unwrap());
+        assert_eq!(request_token(&headers, Some("token=abc")), Some("xyz"));
+    }
+
+    #[tokio::test]
+    async fn test_sweep_drops_silent_clients() {
+        let manager = ClientManager::new();
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let mut connection = manager.add_client("client-1", tx);
+
+        manager.send_to_all("hello");
+        assert_eq!(manager.queue_depths(), vec![("client-1".to_string(), 1)]);
+        assert_eq!(rx.recv().await.as_deref(), Some("hello"));
+
+        // Heard from recently
+        manager.touch("client-1");
+        assert!(manager.sweep(CLIENT_TIMEOUT).is_empty());
+        assert!(connection.removed.try_recv().is_err());
+
+        assert_eq!(manager.sweep(Duration::ZERO), vec!["client-1".to_string()]);
+        assert_eq!(manager.client_count(), 0);
+        assert_eq!(manager.dead_clients.load(Ordering::Relaxed), 1);
+        // The connection is told to close, and its message queue ends
+        assert!(connection.removed.await.is_err());
+        assert!(rx.recv().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_agent_events_reach_workspace_subscribers_once() {
+        let manager = ClientManager::new();
+        let mut receivers = Vec::new();
+        for id in ["agent-sub", "workspace-sub", "both-sub", "other-sub"] {
+            let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+            let _ = manager.add_client(id, tx);
+            receivers.push(rx);
+        }
+        manager.subscribe_to_agent("agent-sub", "ag_1");
+        manager.subscribe_to_workspace("workspace-sub", "ws_1");
+        manager.subscribe_to_agent("both-sub", "ag_1");
+        manager.subscribe_to_workspace("both-sub", "ws_1");
+        manager.subscribe_to_workspace("other-sub", "ws_2");
+
+        manager.send_to_agent_subscribers("ag_1", Some("ws_1"), "status");
+        manager.send_to_agent_subscribers("ag_1", None, "output");
+
+        let received: Vec<Vec<String>> = receivers
+            .iter_mut()
+            .map(|rx| std::iter::from_fn(|| rx.try_recv().ok()).collect())
+            .collect();
+        assert_eq!(received[0], vec!["status", "output"]);
+        assert_eq!(received[1], vec!["status"]);
+        assert_eq!(received[2], vec!["status", "output"]);
+        assert!(received[3].is_empty());
+    }
+
+    #[test]
+    fn test_hooks_without_status_or_agent_are_ignored() {
+        let pm = ProcessManager::new("echo".to_string());
+        let mut rx = pm.subscribe();
+        let parse = |json: &str| serde_json::from_str::<HookNotification>(json).unwrap();
+
+        let tool_event =
+            parse(r#"{"session_id": "s1", "hook_event_name": "PostToolUse", "tool_name": "Edit"}"#);
+        assert!(dispatch_hook(&pm, &tool_event).is_none());
+        let unknown_session = parse(
+            r#"{"session_id": "s1", "hook_event_name": "Notification", "notification_type": "idle_prompt"}"#,
+        );
+        assert!(dispatch_hook(&pm, &unknown_session).is_none());
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_line_assembler_joins_chunks_and_strips_escapes() {
+        let mut lines = LineAssembler::default();
+
+        assert!(lines.push(b"\x1b[32mBuild").is_empty());
+        assert_eq!(
+            lines.push(b"ing\x1b[0m...\r\nDone\r\npartial"),
+            vec!["Building...".to_string(), "Done".to_string()]
+        );
+        assert_eq!(lines.push(b" line\n"), vec!["partial line".to_string()]);
+    }
+
+    #[test]
+    fn test_line_assembler_keeps_text_after_carriage_return() {
+        let mut lines = LineAssembler::default();
+
+        assert_eq!(
+            lines.push(b"Progress 10%\rProgress 100%\r\n"),
+            vec!["Progress 100%".to_string()]
+        );
+
+        let long = vec![b'x'; MAX_PARTIAL_LINE + 1];
+        assert_eq!(lines.push(&long).len(), 1);
+        assert!(lines.partial.is_empty());
+    }
 }
@@ -0,0 +1,357 @@
+//! Workspace refresh queue
+//!
+//! Refreshing a workspace rescans git for its worktrees, which can take a
+//! while in a big repository. Commands queue the refresh and return the job
+//! at once; a single worker thread runs queued jobs in order and publishes
+//! each one when it finishes, so the WebSocket server can tell the
+//! workspace's subscribers.
+
+use std::collections::HashMap;
+use std::sync::{mpsc, Arc};
+
+use parking_lot::Mutex;
+use tokio::sync::broadcast;
+
+use super::{ChangeFeedService, GitWatchService, WorkspaceError, WorkspaceService};
+use crate::types::{EntityKind, RefreshJobStatus, WorkspaceRefreshJob, WorkspaceWithDetails};
+use crate::util::id::new_id;
+
+/// Finished jobs kept for `get_job` before the oldest are dropped
+const FINISHED_JOBS_KEPT: usize = 50;
+
+/// A refresh job that finished, with the workspace as it is now
+#[derive(Debug, Clone)]
+pub struct WorkspaceRefreshEvent {
+    pub job: WorkspaceRefreshJob,
+    /// None when the refresh failed
+    pub workspace: Option<WorkspaceWithDetails>,
+}
+
+type Jobs = Arc<Mutex<HashMap<String, WorkspaceRefreshJob>>>;
+
+pub struct WorkspaceRefreshService {
+    workspace_service: Arc<WorkspaceService>,
+    git_watch: Option<Arc<GitWatchService>>,
+    change_feed: Option<Arc<ChangeFeedService>>,
+    jobs: Jobs,
+    /// Job ids for the worker thread, which starts with the first job
+    queue: Mutex<Option<mpsc::Sender<String>>>,
+    event_tx: broadcast::Sender<WorkspaceRefreshEvent>,
+}
+
+impl WorkspaceRefreshService {
+    pub fn new(workspace_service: Arc<WorkspaceService>) -> Self {
+        let (event_tx, _) = broadcast::channel(64);
+        Self {
+            workspace_service,
+            git_watch: None,
+            change_feed: None,
+            jobs: Arc::new(Mutex::new(HashMap::new())),
+            queue: Mutex::new(None),
+            event_tx,
+        }
+    }
+
+    /// Watch the worktrees a refresh finds for git changes
+    pub fn with_git_watch(mut self, git_watch: Arc<GitWatchService>) -> Self {
+        self.git_watch = Some(git_watch);
+        self
+    }
+
+    /// Publish refreshed workspaces to every window
+    pub fn with_change_feed(mut self, change_feed: Arc<ChangeFeedService>) -> Self {
+        self.change_feed = Some(change_feed);
+        self
+    }
+
+    /// Subscribe to finished refresh jobs
+    pub fn subscribe(&self) -> broadcast::Receiver<WorkspaceRefreshEvent> {
+        self.event_tx.subscribe()
+    }
+
+    /// Queue a refresh of a workspace.
+    ///
+    /// Returns the job already waiting for the workspace if there is one; a
+    /// running job may miss later changes, so it doesn't count.
+    pub fn enqueue(&self, workspace_id: &str) -> Result<WorkspaceRefreshJob, WorkspaceError> {
+        self.workspace_service.get_workspace(workspace_id)?;
+
+        let job = {
+            let mut jobs = self.jobs.lock();
+            if let Some(queued) = jobs.values().find(|job| {
+                job.workspace_id == workspace_id && job.status == RefreshJobStatus::Queued
+            }) {
+                return Ok(queued.clone());
+            }
+            let job = WorkspaceRefreshJob {
+                id: new_id("refresh"),
+                workspace_id: workspace_id.to_string(),
+                status: RefreshJobStatus::Queued,
+                error: None,
+                queued_at: chrono::Utc::now().to_rfc3339(),
+                finished_at: None,
+            };
+            jobs.insert(job.id.clone(), job.clone());
+            job
+        };
+
+        let mut queue = self.queue.lock();
+        let sent = queue
+            .as_ref()
+            .is_some_and(|tx| tx.send(job.id.clone()).is_ok());
+        if !sent {
+            // First job, or the worker died: start a new one
+            let tx = self.start_worker();
+            let _ = tx.send(job.id.clone());
+            *queue = Some(tx);
+        }
+
+        Ok(job)
+    }
+
+    /// Get a queued, running or recently finished job
+    pub fn get_job(&self, job_id: &str) -> Option<WorkspaceRefreshJob> {
+        self.jobs.lock().get(job_id).cloned()
+    }
+
+    fn start_worker(&self) -> mpsc::Sender<String> {
+        let (tx, rx) = mpsc::channel::<String>();
+        let worker = Worker {
+            workspace_service: self.workspace_service.clone(),
+            git_watch: self.git_watch.clone(),
+            change_feed: self.change_feed.clone(),
+            jobs: self.jobs.clone(),
+            event_tx: self.event_tx.clone(),
+        };
+        std::thread::spawn(move || {
+            for job_id in rx {
+                worker.run(&job_id);
+            }
+        });
+        tx
+    }
+}
+
+/// Runs queued jobs one at a time off the command thread pool
+struct Worker {
+    workspace_service: Arc<WorkspaceService>,
+    git_watch: Option<Arc<GitWatchService>>,
+    change_feed: Option<Arc<ChangeFeedService>>,
+    jobs: Jobs,
+    event_tx: broadcast::Sender<WorkspaceRefreshEvent>,
+}
+
+impl Worker {
+    fn run(&self, job_id: &str) {
+        let workspace_id = {
+            let mut jobs = self.jobs.lock();
+            let Some(job) = jobs.get_mut(job_id) else {
+                return;
+            };
+            job.status = RefreshJobStatus::Running;
+            job.workspace_id.clone()
+        };
+
+        let result = self.workspace_service.refresh_workspace(&workspace_id);
+        match &result {
+            Ok(details) => {
+                if let Some(git_watch) = &self.git_watch {
+                    if let Err(e) = git_watch.sync() {
+                        tracing::warn!("Failed to sync git watchers: {}", e);
+                    }
+                }
+                if let Some(change_feed) = &self.change_feed {
+                    change_feed.updated(EntityKind::Workspace, &workspace_id, details);
+                }
+            }
+            Err(e) => tracing::warn!("Failed to refresh workspace {}: {}", workspace_id, e),
+        }
+
+        let job = {
+            let mut jobs = self.jobs.lock();
+            let Some(job) = jobs.get_mut(job_id) else {
+                return;
+            };
+            match &result {
+                Ok(_) => job.status = RefreshJobStatus::Completed,
+                Err(e) => {
+                    job.status = RefreshJobStatus::Failed;
+                    job.error = Some(e.to_string());
+                }
+            }
+            job.finished_at = Some(chrono::Utc::now().to_rfc3339());
+            let job = job.clone();
+            prune_finished(&mut jobs);
+            job
+        };
+
+        // No subscribers just means no window is connected yet
+        let _ = self.event_tx.send(WorkspaceRefreshEvent {
+            job,
+            workspace: result.ok(),
+        });
+    }
+}
+
+/// Drop the oldest finished jobs beyond `FINISHED_JOBS_KEPT`
+fn prune_finished(jobs: &mut HashMap<String, WorkspaceRefreshJob>) {
+    let mut finished: Vec<(String, String)> = jobs
+        .values()
+        .filter_map(|job| Some((job.finished_at.clone()?, job.id.clone())))
+        .collect();
+    if finished.len() <= FINISHED_JOBS_KEPT {
+        return;
+    }
+    finished.sort();
+    let excess = finished.len() - FINISHED_JOBS_KEPT;
+    for (_, id) in finished.into_iter().take(excess) {
+        jobs.remove(&id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+    use std::time::Duration;
+
+    use git2::Repository;
+    use tempfile::TempDir;
+
+    fn init_repo(path: &Path) {
+        let repo = Repository::init(path).unwrap();
+        std::fs::write(path.join("README.md"), "readme\n").unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new("README.md")).unwrap();
+        index.write().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let sig = git2::Signature::now("Test", "test@example.com").unwrap();
+        repo.commit(Some("HEAD"), &sig, &sig, "Initial commit", &tree, &[])
+            .unwrap();
+    }
+
+    /// A service with workspace `ws_repo` on a git repository and `ws_gone`
+    /// on a directory that isn't one
+    fn create_test_service() -> (WorkspaceRefreshService, TempDir, TempDir) {
+        let (pool, db_dir) = crate::db::test_pool();
+        let repo_dir = tempfile::tempdir().unwrap();
+        init_repo(repo_dir.path());
+
+        let conn = pool.get().unwrap();
+        conn.execute(
+            "INSERT INTO workspaces (id, name, path) VALUES ('ws_repo', 'Repo', ?1)",
+            [repo_dir.path().to_str().unwrap()],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO workspaces (id, name, path) VALUES ('ws_gone', 'Gone', ?1)",
+            [db_dir.path().join("missing").to_str().unwrap()],
+        )
+        .unwrap();
+
+        let workspace_service = Arc::new(WorkspaceService::new(pool.clone()));
+        (
+            WorkspaceRefreshService::new(workspace_service),
+            db_dir,
+            repo_dir,
+        )
+    }
+
+    async fn next_event(
+        rx: &mut broadcast::Receiver<WorkspaceRefreshEvent>,
+    ) -> WorkspaceRefreshEvent {
+        tokio::time::timeout(Duration::from_secs(10), rx.recv())
+            .await
+            .expect("Should receive a finished job")
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_refresh_runs_in_background_and_publishes_result() {
+        let (service, _db_dir, _repo_dir) = create_test_service();
+        let mut rx = service.subscribe();
+
+        let job = service.enqueue("ws_repo").expect("Should queue refresh");
+        assert_eq!(job.workspace_id, "ws_repo");
+        assert_eq!(job.status, RefreshJobStatus::Queued);
+
+        let event = next_event(&mut rx).await;
+        assert_eq!(event.job.id, job.id);
+        assert_eq!(event.job.status, RefreshJobStatus::Completed);
+        assert!(event.job.finished_at.is_some());
+        let workspace = event.workspace.expect("Should include the workspace");
+        assert_eq!(workspace.worktrees.len(), 1);
+        assert!(workspace.worktrees[0].worktree.is_main);
+
+        let stored = service.get_job(&job.id).unwrap();
+        assert_eq!(stored.status, RefreshJobStatus::Completed);
+    }
+
+    #[tokio::test]
+    async fn test_failed_refresh_reports_error() {
+        let (service, _db_dir, _repo_dir) = create_test_service();
+        let mut rx = service.subscribe();
+
+        let job = service.enqueue("ws_gone").unwrap();
+
+        let event = next_event(&mut rx).await;
+        assert_eq!(event.job.id, job.id);
+        assert_eq!(event.job.status, RefreshJobStatus::Failed);
+        assert!(event.job.error.is_some());
+        assert!(event.workspace.is_none());
+
+        // The worker keeps going after a failure
+        service.enqueue("ws_repo").unwrap();
+        let event = next_event(&mut rx).await;
+        assert_eq!(event.job.status, RefreshJobStatus::Completed);
+    }
+
+    #[test]
+    fn test_enqueue_rejects_unknown_workspace() {
+        let (service, _db_dir, _repo_dir) = create_test_service();
+
+        assert!(matches!(
+            service.enqueue("ws_missing"),
+            Err(WorkspaceError::NotFound(_))
+        ));
+        assert!(service.get_job("refresh_missing").is_none());
+    }
+
+    #[test]
+    fn test_prune_finished_keeps_queued_and_latest_jobs() {
+        let mut jobs = HashMap::new();
+        for i in 0..FINISHED_JOBS_KEPT + 2 {
+            let id = format!("job_{:03}", i);
+            jobs.insert(
+                id.clone(),
+                WorkspaceRefreshJob {
+                    id,
+                    workspace_id: "ws_1".to_string(),
+                    status: RefreshJobStatus::Completed,
+                    error: None,
+                    queued_at: "2026-01-01T00:00:00Z".to_string(),
+                    finished_at: Some(format!("2026-01-01T00:00:{:02}Z", i)),
+                },
+            );
+        }
+        jobs.insert(
+            "queued".to_string(),
+            WorkspaceRefreshJob {
+                id: "queued".to_string(),
+                workspace_id: "ws_1".to_string(),
+                status: RefreshJobStatus::Queued,
+                error: None,
+                queued_at: "2026-01-01T00:00:00Z".to_string(),
+                finished_at: None,
+            },
+        );
+
+        prune_finished(&mut jobs);
+
+        assert_eq!(jobs.len(), FINISHED_JOBS_KEPT + 1);
+        assert!(jobs.contains_key("queued"));
+        assert!(!jobs.contains_key("job_000"));
+        assert!(!jobs.contains_key("job_001"));
+        assert!(jobs.contains_key("job_002"));
+    }
+}
@@ -1,11 +1,20 @@
 //! Workspace service for managing git workspaces
 
+use std::collections::HashSet;
+use std::path::Path;
+use std::sync::Arc;
+
 use thiserror::Error;
-use uuid::Uuid;
 
-use crate::db::{AgentRepository, DbPool, WorkspaceRepository, WorktreeRepository};
-use crate::services::GitService;
-use crate::types::{Workspace, WorkspaceWithDetails, WorktreeWithAgents};
+use crate::db::{
+    AgentRepository, DbPool, SettingsRepository, WorkspaceRepository, WorktreeRepository,
+};
+use crate::services::worktree_service::{status_rank, worktree_sort_key};
+use crate::services::{GitOps, GitService, WorktreeInfo};
+use crate::types::{
+    Agent, SortMode, Workspace, WorkspaceWithDetails, Worktree, WorktreeWithAgents,
+};
+use crate::util::id::new_id;
 
 #[derive(Error, Debug)]
 pub enum WorkspaceError {
@@ -13,6 +22,9 @@ pub enum WorkspaceError {
     NotFound(String),
     #[error("Invalid path: {0}")]
     InvalidPath(String),
+    /// Carries the id of the workspace that already tracks the repository
+    #[error("Workspace already exists for this repository: {0}")]
+    AlreadyExists(String),
     #[error("Database error: {0}")]
     Database(String),
     #[error("Git error: {0}")]
@@ -23,6 +35,8 @@ pub struct WorkspaceService {
     workspace_repo: WorkspaceRepository,
     worktree_repo: WorktreeRepository,
     agent_repo: AgentRepository,
+    settings_repo: SettingsRepository,
+    git: Arc<dyn GitOps>,
 }
 
 impl WorkspaceService {
@@ -30,10 +44,18 @@ impl WorkspaceService {
         Self {
             workspace_repo: WorkspaceRepository::new(pool.clone()),
             worktree_repo: WorktreeRepository::new(pool.clone()),
-            agent_repo: AgentRepository::new(pool),
+            agent_repo: AgentRepository::new(pool.clone()),
+            settings_repo: SettingsRepository::new(pool),
+            git: Arc::new(GitService),
         }
     }
 
+    /// Read repositories through something other than git2, e.g. in tests
+    pub fn with_git(mut self, git: Arc<dyn GitOps>) -> Self {
+        self.git = git;
+        self
+    }
+
     /// Create a new workspace from a git repository path
     pub fn create_workspace(
         &self,
@@ -41,12 +63,15 @@ impl WorkspaceService {
         name: Option<&str>,
     ) -> Result<Workspace, WorkspaceError> {
         // Validate path is a git repository
-        if !GitService::is_valid_repository(path) {
+        if !self.git.is_valid_repository(path) {
             return Err(WorkspaceError::InvalidPath(format!(
                 "Not a valid git repository: {}",
                 path
             )));
         }
+        if let Some(existing) = self.find_duplicate(path, None)? {
+            return Err(WorkspaceError::AlreadyExists(existing.id));
+        }
 
         // Get repository name from path or use provided name
         let repo_name = name
@@ -61,11 +86,7 @@ impl WorkspaceService {
 
         let now = chrono::Utc::now().to_rfc3339();
         let workspace = Workspace {
-            id: format!(
-                "ws_{}{}",
-                chrono::Utc::now().timestamp_millis(),
-                &Uuid::new_v4().to_string()[..8]
-            ),
+            id: new_id("ws"),
             name: repo_name,
             path: path.to_string(),
             created_at: now.clone(),
@@ -94,7 +115,7 @@ impl WorkspaceService {
             .ok_or_else(|| WorkspaceError::NotFound(id.to_string()))
     }
 
-    /// Get a workspace with full details
+    /// Get a workspace with full details, its worktrees in the workspace's sort mode
     pub fn get_workspace_with_details(&self, id: &str) -> Result<WorkspaceWithDetails, WorkspaceError> {
         let workspace = self.get_workspace(id)?;
 
@@ -122,6 +143,21 @@ impl WorkspaceService {
             });
         }
 
+        let sort_mode = self
+            .settings_repo
+            .get_string(&worktree_sort_key(id), SortMode::Free.as_str())
+            .map_err(|e| WorkspaceError::Database(e.to_string()))?;
+        match SortMode::parse(&sort_mode) {
+            SortMode::Free => {}
+            SortMode::Name => {
+                worktrees_with_agents.sort_by_cached_key(|w| w.worktree.name.to_lowercase())
+            }
+            // Statuses are kept in step with the live processes by status sync
+            SortMode::Status => {
+                worktrees_with_agents.sort_by_cached_key(|w| status_rank(&w.agents, |_| true))
+            }
+        }
+
         Ok(WorkspaceWithDetails {
             workspace,
             worktrees: worktrees_with_agents,
@@ -145,6 +181,35 @@ impl WorkspaceService {
             .map_err(|e| WorkspaceError::Database(e.to_string()))
     }
 
+    /// Agents kept from worktrees that were removed outside the app, most
+    /// recently removed first within each worktree
+    pub fn list_orphaned_agents(&self, workspace_id: &str) -> Result<Vec<Agent>, WorkspaceError> {
+        let workspace = self.get_workspace(workspace_id)?;
+        let git_paths = listed_paths(
+            &self
+                .git
+                .list_worktrees(&workspace.path)
+                .map_err(|e| WorkspaceError::Git(e.to_string()))?,
+        );
+
+        let mut orphaned = Vec::new();
+        for worktree in self
+            .worktree_repo
+            .find_archived_by_workspace_id(workspace_id)
+            .map_err(|e| WorkspaceError::Database(e.to_string()))?
+        {
+            if worktree.is_main || is_listed(&git_paths, &worktree.path) {
+                continue;
+            }
+            orphaned.extend(
+                self.agent_repo
+                    .find_deleted_by_worktree_id(&worktree.id)
+                    .map_err(|e| WorkspaceError::Database(e.to_string()))?,
+            );
+        }
+        Ok(orphaned)
+    }
+
     /// Refresh workspace data
     pub fn refresh_workspace(&self, id: &str) -> Result<WorkspaceWithDetails, WorkspaceError> {
         let workspace = self.get_workspace(id)?;
@@ -155,10 +220,92 @@ impl WorkspaceService {
         self.get_workspace_with_details(id)
     }
 
+    /// Point an existing workspace at a new path, e.g. after the repository moved.
+    ///
+    /// Worktree records are carried over by matching the main worktree and
+    /// branch names, so their agents are kept.
+    pub fn relink_workspace(
+        &self,
+        id: &str,
+        path: &str,
+    ) -> Result<WorkspaceWithDetails, WorkspaceError> {
+        self.get_workspace(id)?;
+        if !self.git.is_valid_repository(path) {
+            return Err(WorkspaceError::InvalidPath(format!(
+                "Not a valid git repository: {}",
+                path
+            )));
+        }
+        if let Some(existing) = self.find_duplicate(path, Some(id))? {
+            return Err(WorkspaceError::AlreadyExists(existing.id));
+        }
+
+        let git_worktrees = self
+            .git
+            .list_worktrees(path)
+            .map_err(|e| WorkspaceError::Git(e.to_string()))?;
+        let mut worktrees = self
+            .worktree_repo
+            .find_by_workspace_id(id)
+            .map_err(|e| WorkspaceError::Database(e.to_string()))?;
+        worktrees.extend(
+            self.worktree_repo
+                .find_archived_by_workspace_id(id)
+                .map_err(|e| WorkspaceError::Database(e.to_string()))?,
+        );
+        for worktree in worktrees {
+            let relinked = git_worktrees.iter().find(|info| {
+                if worktree.is_main {
+                    info.is_main
+                } else {
+                    !info.is_main && info.branch == worktree.branch
+                }
+            });
+            if let Some(info) = relinked {
+                self.worktree_repo
+                    .update_path(&worktree.id, &info.path)
+                    .map_err(|e| WorkspaceError::Database(e.to_string()))?;
+            }
+        }
+
+        self.workspace_repo
+            .update_path(id, path)
+            .map_err(|e| WorkspaceError::Database(e.to_string()))?;
+        self.scan_worktrees(id, path)?;
+
+        self.get_workspace_with_details(id)
+    }
+
+    /// Find a workspace for the same repository, by canonical path or shared git dir
+    fn find_duplicate(
+        &self,
+        path: &str,
+        exclude_id: Option<&str>,
+    ) -> Result<Option<Workspace>, WorkspaceError> {
+        let canonical = Path::new(path).canonicalize().ok();
+        let common_dir = self.git.common_dir(path).ok();
+
+        let workspaces = self.list_workspaces()?;
+        Ok(workspaces.into_iter().find(|workspace| {
+            if Some(workspace.id.as_str()) == exclude_id {
+                return false;
+            }
+            let same_path = match (&canonical, Path::new(&workspace.path).canonicalize()) {
+                (Some(canonical), Ok(existing)) => *canonical == existing,
+                _ => workspace.path == path,
+            };
+            same_path
+                || common_dir.is_some() && self.git.common_dir(&workspace.path).ok() == common_dir
+        }))
+    }
+
     /// Scan and sync worktrees from git
     fn scan_worktrees(&self, workspace_id: &str, repo_path: &str) -> Result<(), WorkspaceError> {
-        let git_worktrees =
-            GitService::list_worktrees(repo_path).map_err(|e| WorkspaceError::Git(e.to_string()))?;
+        let git_worktrees = self
+            .git
+            .list_worktrees(repo_path)
+            .map_err(|e| WorkspaceError::Git(e.to_string()))?;
+        let git_paths = listed_paths(&git_worktrees);
 
         for wt_info in git_worktrees {
             // Check if worktree already exists
@@ -171,11 +318,7 @@ impl WorkspaceService {
                 // Create new worktree record
                 let now = chrono::Utc::now().to_rfc3339();
                 let worktree = crate::types::Worktree {
-                    id: format!(
-                        "wt_{}{}",
-                        chrono::Utc::now().timestamp_millis(),
-                        &Uuid::new_v4().to_string()[..8]
-                    ),
+                    id: new_id("wt"),
                     workspace_id: workspace_id.to_string(),
                     name: std::path::Path::new(&wt_info.path)
                         .file_name()
@@ -189,6 +332,11 @@ impl WorkspaceService {
                     is_main: wt_info.is_main,
                     created_at: now.clone(),
                     updated_at: now,
+                    pr_url: None,
+                    sparse_patterns: None,
+                    archived_at: None,
+                    pinned: false,
+                    missing_since: None,
                 };
 
                 self.worktree_repo
@@ -197,6 +345,31 @@ impl WorkspaceService {
             }
         }
 
+        // Prune records of worktrees git no longer knows about, unless they
+        // are pinned or their directory is still there, which only marks
+        // them missing
+        let mut known = self
+            .worktree_repo
+            .find_by_workspace_id(workspace_id)
+            .map_err(|e| WorkspaceError::Database(e.to_string()))?;
+        known.extend(
+            self.worktree_repo
+                .find_archived_by_workspace_id(workspace_id)
+                .map_err(|e| WorkspaceError::Database(e.to_string()))?,
+        );
+        for worktree in known {
+            if worktree.is_main {
+                continue;
+            }
+            let listed = is_listed(&git_paths, &worktree.path);
+            let on_disk = Path::new(&worktree.path).exists();
+            if !listed && !on_disk && !worktree.pinned {
+                self.prune_worktree(&worktree)?;
+            } else {
+                self.mark_missing(&worktree, !(listed && on_disk))?;
+            }
+        }
+
         // Update workspace counts
         self.workspace_repo
             .update_counts(workspace_id)
@@ -204,4 +377,83 @@ impl WorkspaceService {
 
         Ok(())
     }
+
+    /// Record whether a worktree's checkout is unavailable, keeping the time
+    /// it first went missing
+    fn mark_missing(&self, worktree: &Worktree, missing: bool) -> Result<(), WorkspaceError> {
+        if missing == worktree.missing_since.is_some() {
+            return Ok(());
+        }
+        let now = chrono::Utc::now().to_rfc3339();
+        self.worktree_repo
+            .set_missing(&worktree.id, missing.then_some(now.as_str()))
+            .map_err(|e| WorkspaceError::Database(e.to_string()))?;
+        if missing {
+            tracing::warn!(
+                "Worktree {} at {} is unavailable; keeping it as missing",
+                worktree.id,
+                worktree.path
+            );
+        }
+        Ok(())
+    }
+
+    /// Drop the record of a removed worktree. Deleting it would take its
+    /// agents and their messages, schedules and snippets with it, so a
+    /// worktree that has any is archived instead and its agents soft-deleted,
+    /// which leaves them to `list_orphaned_agents`.
+    fn prune_worktree(&self, worktree: &Worktree) -> Result<(), WorkspaceError> {
+        let agents = self
+            .agent_repo
+            .find_by_worktree_id(&worktree.id, true)
+            .map_err(|e| WorkspaceError::Database(e.to_string()))?;
+        let has_dependents = self
+            .worktree_repo
+            .has_dependents(&worktree.id)
+            .map_err(|e| WorkspaceError::Database(e.to_string()))?;
+        if agents.is_empty() && !has_dependents {
+            return self
+                .worktree_repo
+                .delete(&worktree.id)
+                .map_err(|e| WorkspaceError::Database(e.to_string()));
+        }
+
+        for agent in agents.iter().filter(|a| a.deleted_at.is_none()) {
+            self.agent_repo
+                .soft_delete(&agent.id)
+                .map_err(|e| WorkspaceError::Database(e.to_string()))?;
+        }
+        if worktree.archived_at.is_none() {
+            let now = chrono::Utc::now().to_rfc3339();
+            self.worktree_repo
+                .set_archived(&worktree.id, Some(&now))
+                .map_err(|e| WorkspaceError::Database(e.to_string()))?;
+        }
+        tracing::info!(
+            "Worktree {} is gone from git; archived it with its {} agents",
+            worktree.id,
+            agents.len()
+        );
+        Ok(())
+    }
+}
+
+/// Paths of the worktrees git lists, both as listed and canonical
+fn listed_paths(worktrees: &[WorktreeInfo]) -> HashSet<String> {
+    let mut paths = HashSet::new();
+    for info in worktrees {
+        if let Ok(canonical) = Path::new(&info.path).canonicalize() {
+            paths.insert(canonical.to_string_lossy().to_string());
+        }
+        paths.insert(info.path.clone());
+    }
+    paths
+}
+
+/// Whether a recorded worktree path is one git lists, however it was spelled
+fn is_listed(listed: &HashSet<String>, path: &str) -> bool {
+    listed.contains(path)
+        || Path::new(path)
+            .canonicalize()
+            .is_ok_and(|canonical| listed.contains(canonical.to_string_lossy().as_ref()))
 }
@@ -1,11 +1,45 @@
 //! Worktree service for managing git worktrees
 
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::path::{Component, Path};
+use std::sync::Arc;
+
 use thiserror::Error;
-use uuid::Uuid;
 
-use crate::db::{DbPool, WorkspaceRepository, WorktreeRepository};
-use crate::services::GitService;
-use crate::types::{BranchInfo, GitStatusInfo, UpdateWorktreeInput, Worktree};
+use crate::db::{
+    AgentRepository, DbError, DbPool, SettingsRepository, WorkspaceRepository, WorktreeRepository,
+};
+use crate::services::git_service::{wildcard_match, GitError, WorktreeInfo};
+use crate::services::{
+    BootstrapService, GitOps, GitService, GitWatchService, ProcessControl, SettingsSyncService,
+};
+use crate::types::{
+    Agent, AgentStatus, BranchInfo, BranchTemplate, BusyAgent, CommitInfo, ConflictReport,
+    GitStatusInfo, GitStatusOptions, SharedFileMode, SharedFilesConfig, SharedFilesPreview,
+    SortMode, StashEntry, UpdateWorktreeInput, Workspace, Worktree, WorktreeConflict,
+    WorktreeHealth, WorktreeState,
+};
+use crate::util::cancel::CancellationToken;
+use crate::util::id::new_id;
+
+/// Settings key prefix for the files a workspace shares with new worktrees
+const SHARED_FILES_KEY: &str = "worktree_shared_files";
+/// Settings key prefix for a workspace's branch naming template
+const BRANCH_TEMPLATE_KEY: &str = "branch_template";
+/// Branches are named after their worktree unless a workspace says otherwise
+const DEFAULT_BRANCH_TEMPLATE: &str = "{slug}";
+const EXAMPLE_WORKTREE_NAME: &str = "My feature";
+/// Longest slug taken from a worktree name
+const MAX_SLUG_LENGTH: usize = 50;
+/// Settings key prefix for the directories sparse worktrees check out
+const SPARSE_CHECKOUT_KEY: &str = "sparse_checkout";
+/// Settings key prefix for the branch a workspace's worktrees are compared with
+pub(crate) const BASE_BRANCH_KEY: &str = "base_branch";
+pub(crate) const DEFAULT_BASE_BRANCH: &str = "main";
+/// Settings key prefix for how a workspace's worktrees are ordered
+const WORKTREE_SORT_KEY: &str = "worktree_sort_mode";
+/// HTTPS fetches authenticate with the token stored for the GitHub API
+const GIT_TOKEN_KEY: &str = "github_token";
 
 #[derive(Error, Debug)]
 pub enum WorktreeError {
@@ -19,28 +53,136 @@ pub enum WorktreeError {
     Database(String),
     #[error("Git error: {0}")]
     Git(String),
+    #[error("Validation error: {0}")]
+    Validation(String),
+    #[error("Cancelled")]
+    Cancelled,
+    #[error("Worktree {id} was changed since it was loaded")]
+    Conflict { id: String, updated_at: String },
+    #[error(
+        "Worktree has running agents: {}",
+        .agents.iter().map(|a| a.name.as_str()).collect::<Vec<_>>().join(", ")
+    )]
+    Busy {
+        worktree_id: String,
+        agents: Vec<BusyAgent>,
+    },
 }
 
 pub struct WorktreeService {
     worktree_repo: WorktreeRepository,
     workspace_repo: WorkspaceRepository,
+    settings_repo: SettingsRepository,
+    agent_repo: AgentRepository,
+    bootstrap_service: Option<Arc<BootstrapService>>,
+    git_watch: Option<Arc<GitWatchService>>,
+    process_manager: Option<Arc<dyn ProcessControl>>,
+    settings_sync: Option<Arc<SettingsSyncService>>,
+    git: Arc<dyn GitOps>,
 }
 
 impl WorktreeService {
     pub fn new(pool: DbPool) -> Self {
         Self {
             worktree_repo: WorktreeRepository::new(pool.clone()),
-            workspace_repo: WorkspaceRepository::new(pool),
+            workspace_repo: WorkspaceRepository::new(pool.clone()),
+            settings_repo: SettingsRepository::new(pool.clone()),
+            agent_repo: AgentRepository::new(pool),
+            bootstrap_service: None,
+            git_watch: None,
+            process_manager: None,
+            settings_sync: None,
+            git: Arc::new(GitService),
         }
     }
 
-    /// List worktrees for a workspace
+    /// Work on repositories through something other than git2, e.g. in tests
+    pub fn with_git(mut self, git: Arc<dyn GitOps>) -> Self {
+        self.git = git;
+        self
+    }
+
+    /// Run the workspace's bootstrap commands in each worktree created
+    pub fn with_bootstrap(mut self, bootstrap_service: Arc<BootstrapService>) -> Self {
+        self.bootstrap_service = Some(bootstrap_service);
+        self
+    }
+
+    /// Only count agents as running while their process is alive, rather
+    /// than trusting a status that may be stale after a crash
+    pub fn with_process_manager(mut self, process_manager: Arc<dyn ProcessControl>) -> Self {
+        self.process_manager = Some(process_manager);
+        self
+    }
+
+    /// Serve repeated git status requests from the watcher's cache
+    pub fn with_git_watch(mut self, git_watch: Arc<GitWatchService>) -> Self {
+        self.git_watch = Some(git_watch);
+        self
+    }
+
+    /// Take the manager's hooks out of worktrees before deleting them
+    pub fn with_settings_sync(mut self, settings_sync: Arc<SettingsSyncService>) -> Self {
+        self.settings_sync = Some(settings_sync);
+        self
+    }
+
+    /// List worktrees for a workspace, in the workspace's sort mode
     pub fn list_worktrees(&self, workspace_id: &str) -> Result<Vec<Worktree>, WorktreeError> {
-        self.worktree_repo
+        let mut worktrees = self
+            .worktree_repo
             .find_by_workspace_id(workspace_id)
+            .map_err(|e| WorktreeError::Database(e.to_string()))?;
+
+        match self.get_sort_mode(workspace_id)? {
+            SortMode::Free => {}
+            SortMode::Name => worktrees.sort_by_cached_key(|w| w.name.to_lowercase()),
+            SortMode::Status => {
+                let mut ranks = HashMap::new();
+                for worktree in &worktrees {
+                    let agents = self
+                        .agent_repo
+                        .find_by_worktree_id(&worktree.id, false)
+                        .map_err(|e| WorktreeError::Database(e.to_string()))?;
+                    let rank = status_rank(&agents, |agent| {
+                        self.process_manager
+                            .as_ref()
+                            .map_or(true, |pm| pm.is_running(&agent.id))
+                    });
+                    ranks.insert(worktree.id.clone(), rank);
+                }
+                worktrees.sort_by_key(|w| ranks[&w.id]);
+            }
+        }
+        Ok(worktrees)
+    }
+
+    /// Get how a workspace's worktrees are ordered
+    pub fn get_sort_mode(&self, workspace_id: &str) -> Result<SortMode, WorktreeError> {
+        self.settings_repo
+            .get_string(&worktree_sort_key(workspace_id), SortMode::Free.as_str())
+            .map(|mode| SortMode::parse(&mode))
             .map_err(|e| WorktreeError::Database(e.to_string()))
     }
 
+    /// Set how a workspace's worktrees are ordered
+    pub fn set_sort_mode(
+        &self,
+        workspace_id: &str,
+        sort_mode: SortMode,
+    ) -> Result<SortMode, WorktreeError> {
+        self.find_workspace(workspace_id)?;
+        let key = worktree_sort_key(workspace_id);
+        let result = if sort_mode == SortMode::Free {
+            self.settings_repo.delete(&key)
+        } else {
+            self.settings_repo.set(&key, sort_mode.as_str(), "string")
+        };
+        result.map_err(|e| WorktreeError::Database(e.to_string()))?;
+
+        Ok(sort_mode)
+    }
+
     /// Get a worktree by ID
     pub fn get_worktree(&self, id: &str) -> Result<Worktree, WorktreeError> {
         self.worktree_repo
@@ -57,6 +199,28 @@ impl WorktreeService {
         branch: &str,
         path: Option<&str>,
         create_branch: bool,
+    ) -> Result<Worktree, WorktreeError> {
+        self.create_worktree_cancellable(
+            workspace_id,
+            name,
+            branch,
+            path,
+            create_branch,
+            &CancellationToken::new(),
+        )
+    }
+
+    /// Create a new worktree, giving up while git is still adding it if
+    /// `cancel` is set. A cancelled worktree leaves no checkout, branch or
+    /// database record behind.
+    pub fn create_worktree_cancellable(
+        &self,
+        workspace_id: &str,
+        name: &str,
+        branch: &str,
+        path: Option<&str>,
+        create_branch: bool,
+        cancel: &CancellationToken,
     ) -> Result<Worktree, WorktreeError> {
         // Get workspace to get repo path
         let workspace = self
@@ -76,17 +240,26 @@ impl WorktreeService {
             });
 
         // Create worktree using git
-        let wt_info = GitService::add_worktree(&workspace.path, &worktree_path, branch, create_branch)
-            .map_err(|e| WorktreeError::Git(e.to_string()))?;
+        let sparse_dirs = self.get_sparse_checkout(workspace_id)?;
+        let wt_info = self
+            .git
+            .add_worktree(
+                &workspace.path,
+                &worktree_path,
+                branch,
+                create_branch,
+                &sparse_dirs,
+                cancel,
+            )
+            .map_err(|e| match e {
+                GitError::Cancelled => WorktreeError::Cancelled,
+                e => WorktreeError::Git(e.to_string()),
+            })?;
 
         // Create database record
         let now = chrono::Utc::now().to_rfc3339();
         let worktree = Worktree {
-            id: format!(
-                "wt_{}{}",
-                chrono::Utc::now().timestamp_millis(),
-                &Uuid::new_v4().to_string()[..8]
-            ),
+            id: new_id("wt"),
             workspace_id: workspace_id.to_string(),
             name: name.to_string(),
             branch: wt_info.branch,
@@ -96,6 +269,11 @@ impl WorktreeService {
             is_main: false,
             created_at: now.clone(),
             updated_at: now,
+            pr_url: None,
+            sparse_patterns: (!sparse_dirs.is_empty()).then_some(sparse_dirs),
+            archived_at: None,
+            pinned: false,
+            missing_since: None,
         };
 
         let created = self
@@ -108,9 +286,291 @@ impl WorktreeService {
             .update_counts(workspace_id)
             .map_err(|e| WorktreeError::Database(e.to_string()))?;
 
+        // Before bootstrapping, which may need files such as .env
+        self.share_files(&workspace.path, &created);
+
+        // The worktree is usable even if its bootstrap cannot start
+        if let Some(bootstrap_service) = &self.bootstrap_service {
+            if let Err(e) = bootstrap_service.run(&created) {
+                tracing::warn!("Failed to bootstrap worktree {}: {}", created.id, e);
+            }
+        }
+
         Ok(created)
     }
 
+    /// Get the template for branches generated for a workspace's worktrees
+    pub fn get_branch_template(&self, workspace_id: &str) -> Result<BranchTemplate, WorktreeError> {
+        let workspace = self.find_workspace(workspace_id)?;
+        let template = self
+            .settings_repo
+            .get(&format!("{}:{}", BRANCH_TEMPLATE_KEY, workspace_id))
+            .map_err(|e| WorktreeError::Database(e.to_string()))?
+            .unwrap_or_else(|| DEFAULT_BRANCH_TEMPLATE.to_string());
+        Ok(BranchTemplate {
+            example: render_branch_name(&template, EXAMPLE_WORKTREE_NAME, &workspace.name),
+            template,
+        })
+    }
+
+    /// Set a workspace's branch template; an empty template restores the default
+    pub fn set_branch_template(
+        &self,
+        workspace_id: &str,
+        template: &str,
+    ) -> Result<BranchTemplate, WorktreeError> {
+        let workspace = self.find_workspace(workspace_id)?;
+        let key = format!("{}:{}", BRANCH_TEMPLATE_KEY, workspace_id);
+        let template = template.trim();
+
+        let result = if template.is_empty() || template == DEFAULT_BRANCH_TEMPLATE {
+            self.settings_repo.delete(&key)
+        } else {
+            if !template.contains("{slug}") {
+                return Err(WorktreeError::Validation(
+                    "Branch template must contain {slug}".to_string(),
+                ));
+            }
+            let example = render_branch_name(template, EXAMPLE_WORKTREE_NAME, &workspace.name);
+            if !git2::Branch::name_is_valid(&example).unwrap_or(false) {
+                return Err(WorktreeError::Validation(format!(
+                    "Branch template gives an invalid branch name: {}",
+                    example
+                )));
+            }
+            self.settings_repo.set(&key, template, "string")
+        };
+        result.map_err(|e| WorktreeError::Database(e.to_string()))?;
+
+        self.get_branch_template(workspace_id)
+    }
+
+    /// Get the branch a workspace's worktrees report ahead/behind counts against
+    pub fn get_base_branch(&self, workspace_id: &str) -> Result<String, WorktreeError> {
+        self.find_workspace(workspace_id)?;
+        self.settings_repo
+            .get_string(&base_branch_key(workspace_id), DEFAULT_BASE_BRANCH)
+            .map_err(|e| WorktreeError::Database(e.to_string()))
+    }
+
+    /// Set a workspace's base branch; an empty name restores the default
+    pub fn set_base_branch(
+        &self,
+        workspace_id: &str,
+        branch: &str,
+    ) -> Result<String, WorktreeError> {
+        self.find_workspace(workspace_id)?;
+        let key = base_branch_key(workspace_id);
+        let branch = branch.trim();
+
+        let result = if branch.is_empty() || branch == DEFAULT_BASE_BRANCH {
+            self.settings_repo.delete(&key)
+        } else {
+            if !git2::Branch::name_is_valid(branch).unwrap_or(false) {
+                return Err(WorktreeError::Validation(format!(
+                    "Invalid branch name: {}",
+                    branch
+                )));
+            }
+            self.settings_repo.set(&key, branch, "string")
+        };
+        result.map_err(|e| WorktreeError::Database(e.to_string()))?;
+
+        self.get_base_branch(workspace_id)
+    }
+
+    /// Branch name for a new worktree from the workspace's template, with a
+    /// numeric suffix if a local or remote branch already has that name
+    pub fn generate_branch_name(
+        &self,
+        workspace_id: &str,
+        worktree_name: &str,
+    ) -> Result<String, WorktreeError> {
+        self.generate_branch_name_or(workspace_id, worktree_name, DEFAULT_BRANCH_TEMPLATE)
+    }
+
+    /// Like `generate_branch_name`, with the template to use when the
+    /// workspace has not set one
+    pub fn generate_branch_name_or(
+        &self,
+        workspace_id: &str,
+        worktree_name: &str,
+        default_template: &str,
+    ) -> Result<String, WorktreeError> {
+        let workspace = self.find_workspace(workspace_id)?;
+        let template = self
+            .settings_repo
+            .get(&format!("{}:{}", BRANCH_TEMPLATE_KEY, workspace_id))
+            .map_err(|e| WorktreeError::Database(e.to_string()))?
+            .unwrap_or_else(|| default_template.to_string());
+        let base = render_branch_name(&template, worktree_name, &workspace.name);
+        if !git2::Branch::name_is_valid(&base).unwrap_or(false) {
+            return Err(WorktreeError::Validation(format!(
+                "Invalid branch name: {}",
+                base
+            )));
+        }
+
+        let branches = self
+            .git
+            .list_branches(&workspace.path)
+            .map_err(|e| WorktreeError::Git(e.to_string()))?;
+        let taken: HashSet<String> = branches.local.into_iter().chain(branches.remote).collect();
+        if !taken.contains(&base) {
+            return Ok(base);
+        }
+        Ok((2..)
+            .map(|n| format!("{}-{}", base, n))
+            .find(|candidate| !taken.contains(candidate))
+            .unwrap_or(base))
+    }
+
+    /// Get the untracked files a workspace copies into new worktrees
+    pub fn get_shared_files(&self, workspace_id: &str) -> Result<SharedFilesConfig, WorktreeError> {
+        self.find_workspace(workspace_id)?;
+        let value = self
+            .settings_repo
+            .get(&format!("{}:{}", SHARED_FILES_KEY, workspace_id))
+            .map_err(|e| WorktreeError::Database(e.to_string()))?;
+        Ok(value
+            .and_then(|v| serde_json::from_str(&v).ok())
+            .unwrap_or_default())
+    }
+
+    /// Replace the files a workspace copies into new worktrees, returning
+    /// what the next worktree would get
+    pub fn set_shared_files(
+        &self,
+        workspace_id: &str,
+        config: SharedFilesConfig,
+    ) -> Result<SharedFilesPreview, WorktreeError> {
+        self.find_workspace(workspace_id)?;
+
+        let mut paths: Vec<String> = Vec::new();
+        for path in &config.paths {
+            let path = path.trim().trim_start_matches("./").trim_end_matches('/');
+            if path.is_empty() || paths.iter().any(|p| p == path) {
+                continue;
+            }
+            validate_worktree_path(path)?;
+            paths.push(path.to_string());
+        }
+        let config = SharedFilesConfig {
+            paths,
+            mode: config.mode,
+        };
+
+        let key = format!("{}:{}", SHARED_FILES_KEY, workspace_id);
+        let result = if config.paths.is_empty() {
+            self.settings_repo.delete(&key)
+        } else {
+            let value = serde_json::to_string(&config).unwrap_or_default();
+            self.settings_repo.set(&key, &value, "json")
+        };
+        result.map_err(|e| WorktreeError::Database(e.to_string()))?;
+
+        self.preview_shared_files(workspace_id)
+    }
+
+    /// Get the directories new worktrees of a workspace check out; empty
+    /// means a full checkout
+    pub fn get_sparse_checkout(&self, workspace_id: &str) -> Result<Vec<String>, WorktreeError> {
+        let dirs = self
+            .settings_repo
+            .get(&format!("{}:{}", SPARSE_CHECKOUT_KEY, workspace_id))
+            .map_err(|e| WorktreeError::Database(e.to_string()))?;
+        Ok(dirs
+            .and_then(|dirs| serde_json::from_str(&dirs).ok())
+            .unwrap_or_default())
+    }
+
+    /// Set the directories new worktrees check out. Existing worktrees keep
+    /// their checkout; an empty list restores full checkouts.
+    pub fn set_sparse_checkout(
+        &self,
+        workspace_id: &str,
+        dirs: Vec<String>,
+    ) -> Result<Vec<String>, WorktreeError> {
+        self.find_workspace(workspace_id)?;
+
+        let mut cone: Vec<String> = Vec::new();
+        for dir in &dirs {
+            let dir = dir.trim().trim_start_matches("./").trim_end_matches('/');
+            if dir.is_empty() || cone.iter().any(|d| d == dir) {
+                continue;
+            }
+            validate_worktree_path(dir)?;
+            if dir.contains('*') {
+                return Err(WorktreeError::Validation(format!(
+                    "Sparse checkout takes directories, not patterns: {}",
+                    dir
+                )));
+            }
+            cone.push(dir.to_string());
+        }
+
+        let key = format!("{}:{}", SPARSE_CHECKOUT_KEY, workspace_id);
+        let result = if cone.is_empty() {
+            self.settings_repo.delete(&key)
+        } else {
+            let value = serde_json::to_string(&cone).unwrap_or_default();
+            self.settings_repo.set(&key, &value, "json")
+        };
+        result.map_err(|e| WorktreeError::Database(e.to_string()))?;
+
+        Ok(cone)
+    }
+
+    /// List what creating a worktree would copy, without copying anything
+    pub fn preview_shared_files(
+        &self,
+        workspace_id: &str,
+    ) -> Result<SharedFilesPreview, WorktreeError> {
+        let workspace = self.find_workspace(workspace_id)?;
+        let config = self.get_shared_files(workspace_id)?;
+        let (files, missing) = resolve_shared_files(Path::new(&workspace.path), &config.paths);
+        Ok(SharedFilesPreview {
+            mode: config.mode,
+            files,
+            missing,
+        })
+    }
+
+    /// Copy or link the workspace's shared files into a new worktree.
+    ///
+    /// Files the new branch already has are left alone, and failures are
+    /// only logged since the worktree itself was created.
+    fn share_files(&self, main_path: &str, worktree: &Worktree) {
+        let config = match self.get_shared_files(&worktree.workspace_id) {
+            Ok(config) => config,
+            Err(e) => {
+                tracing::warn!("Failed to read shared files config: {}", e);
+                return;
+            }
+        };
+
+        let main_root = Path::new(main_path);
+        let (files, _) = resolve_shared_files(main_root, &config.paths);
+        for file in files {
+            let target = Path::new(&worktree.path).join(&file);
+            if let Err(e) = share_file(&main_root.join(&file), &target, config.mode) {
+                tracing::warn!(
+                    "Failed to share {} with worktree {}: {}",
+                    file,
+                    worktree.id,
+                    e
+                );
+            }
+        }
+    }
+
+    fn find_workspace(&self, workspace_id: &str) -> Result<Workspace, WorktreeError> {
+        self.workspace_repo
+            .find_by_id(workspace_id)
+            .map_err(|e| WorktreeError::Database(e.to_string()))?
+            .ok_or_else(|| WorktreeError::WorkspaceNotFound(workspace_id.to_string()))
+    }
+
     /// Update a worktree
     pub fn update_worktree(
         &self,
@@ -128,21 +588,27 @@ impl WorktreeService {
         if let Some(display_order) = input.display_order {
             worktree.display_order = display_order;
         }
-
-        worktree.updated_at = chrono::Utc::now().to_rfc3339();
+        // Don't overwrite what was changed after the caller loaded the worktree
+        if let Some(expected_updated_at) = input.expected_updated_at {
+            worktree.updated_at = expected_updated_at;
+        }
 
         self.worktree_repo
             .update(&worktree)
-            .map_err(|e| WorktreeError::Database(e.to_string()))
+            .map_err(|e| save_error(id, e))
     }
 
-    /// Delete a worktree
-    pub fn delete_worktree(&self, id: &str) -> Result<(), WorktreeError> {
+    /// Delete a worktree, removing its checkout from git. Also purges an
+    /// archived worktree.
+    pub fn delete_worktree(&self, id: &str, force: bool) -> Result<(), WorktreeError> {
         let worktree = self.get_worktree(id)?;
 
         if worktree.is_main {
             return Err(WorktreeError::CannotDeleteMain);
         }
+        if !force {
+            self.ensure_not_busy(id)?;
+        }
 
         // Get workspace to get repo path
         let workspace = self
@@ -151,8 +617,20 @@ impl WorktreeService {
             .map_err(|e| WorktreeError::Database(e.to_string()))?
             .ok_or_else(|| WorktreeError::WorkspaceNotFound(worktree.workspace_id.clone()))?;
 
+        // Stop tracking the manager's hooks; they go with the directory
+        if let Some(settings_sync) = &self.settings_sync {
+            if let Err(e) = settings_sync.release_worktree(&worktree.path) {
+                tracing::warn!(
+                    "Failed to remove hook settings from {}: {}",
+                    worktree.path,
+                    e
+                );
+            }
+        }
+
         // Remove worktree from git
-        GitService::remove_worktree(&workspace.path, &worktree.path)
+        self.git
+            .remove_worktree(&workspace.path, &worktree.path)
             .map_err(|e| WorktreeError::Git(e.to_string()))?;
 
         // Delete database record
@@ -168,24 +646,157 @@ impl WorktreeService {
         Ok(())
     }
 
-    /// Checkout a branch in a worktree
+    /// Archive a worktree: hide it from the workspace but keep its git
+    /// checkout, branch and agents until it is restored or deleted
+    pub fn archive_worktree(&self, id: &str, force: bool) -> Result<Worktree, WorktreeError> {
+        let worktree = self.get_worktree(id)?;
+
+        if worktree.is_main {
+            return Err(WorktreeError::Validation(
+                "Cannot archive main worktree".to_string(),
+            ));
+        }
+        if worktree.archived_at.is_some() {
+            return Err(WorktreeError::Validation(format!(
+                "Worktree is already archived: {}",
+                id
+            )));
+        }
+        if !force {
+            self.ensure_not_busy(id)?;
+        }
+
+        if let Some(settings_sync) = &self.settings_sync {
+            if let Err(e) = settings_sync.release_worktree(&worktree.path) {
+                tracing::warn!(
+                    "Failed to remove hook settings from {}: {}",
+                    worktree.path,
+                    e
+                );
+            }
+        }
+
+        let archived_at = chrono::Utc::now().to_rfc3339();
+        self.worktree_repo
+            .set_archived(id, Some(&archived_at))
+            .map_err(|e| WorktreeError::Database(e.to_string()))?;
+        self.workspace_repo
+            .update_counts(&worktree.workspace_id)
+            .map_err(|e| WorktreeError::Database(e.to_string()))?;
+
+        self.get_worktree(id)
+    }
+
+    /// Bring an archived worktree back into its workspace
+    pub fn restore_worktree(&self, id: &str) -> Result<Worktree, WorktreeError> {
+        let worktree = self.get_worktree(id)?;
+
+        if worktree.archived_at.is_none() {
+            return Err(WorktreeError::Validation(format!(
+                "Worktree is not archived: {}",
+                id
+            )));
+        }
+
+        self.worktree_repo
+            .set_archived(id, None)
+            .map_err(|e| WorktreeError::Database(e.to_string()))?;
+        self.workspace_repo
+            .update_counts(&worktree.workspace_id)
+            .map_err(|e| WorktreeError::Database(e.to_string()))?;
+
+        self.get_worktree(id)
+    }
+
+    /// Pin a worktree so workspace scans keep it while its checkout is
+    /// unavailable, or unpin it so they prune it again
+    pub fn set_pinned(&self, id: &str, pinned: bool) -> Result<Worktree, WorktreeError> {
+        self.get_worktree(id)?;
+
+        self.worktree_repo
+            .set_pinned(id, pinned)
+            .map_err(|e| WorktreeError::Database(e.to_string()))?;
+
+        self.get_worktree(id)
+    }
+
+    /// Archived worktrees of a workspace, most recently archived first
+    pub fn list_archived_worktrees(
+        &self,
+        workspace_id: &str,
+    ) -> Result<Vec<Worktree>, WorktreeError> {
+        self.find_workspace(workspace_id)?;
+
+        self.worktree_repo
+            .find_archived_by_workspace_id(workspace_id)
+            .map_err(|e| WorktreeError::Database(e.to_string()))
+    }
+
+    /// Agents actively running in a worktree
+    pub fn running_agents(&self, id: &str) -> Result<Vec<BusyAgent>, WorktreeError> {
+        let agents = self
+            .agent_repo
+            .find_by_worktree_id(id, false)
+            .map_err(|e| WorktreeError::Database(e.to_string()))?;
+        Ok(agents
+            .into_iter()
+            .filter(|agent| agent.status == AgentStatus::Running)
+            .filter(|agent| {
+                self.process_manager
+                    .as_ref()
+                    .map_or(true, |pm| pm.is_running(&agent.id))
+            })
+            .map(|agent| BusyAgent {
+                id: agent.id,
+                name: agent.name,
+            })
+            .collect())
+    }
+
+    fn ensure_not_busy(&self, id: &str) -> Result<(), WorktreeError> {
+        let agents = self.running_agents(id)?;
+        if agents.is_empty() {
+            Ok(())
+        } else {
+            Err(WorktreeError::Busy {
+                worktree_id: id.to_string(),
+                agents,
+            })
+        }
+    }
+
+    /// Checkout a branch in a worktree, optionally stashing uncommitted
+    /// changes first so they don't block the checkout
     pub fn checkout_branch(
         &self,
         id: &str,
         branch: &str,
         create: bool,
+        stash: bool,
+        force: bool,
     ) -> Result<Worktree, WorktreeError> {
-        let mut worktree = self.get_worktree(id)?;
+        let worktree = self.get_worktree(id)?;
+        if !force {
+            self.ensure_not_busy(id)?;
+        }
+
+        if stash {
+            let message = format!("Auto-stash before checking out {}", branch);
+            self.git
+                .stash_save(&worktree.path, Some(&message), true)
+                .map_err(|e| WorktreeError::Git(e.to_string()))?;
+        }
 
-        GitService::checkout_branch(&worktree.path, branch, create)
+        self.git
+            .checkout_branch(&worktree.path, branch, create)
             .map_err(|e| WorktreeError::Git(e.to_string()))?;
 
+        // The checkout happened, so record it over any edit made meanwhile
+        let mut worktree = self.get_worktree(id)?;
         worktree.branch = branch.to_string();
-        worktree.updated_at = chrono::Utc::now().to_rfc3339();
-
         self.worktree_repo
             .update(&worktree)
-            .map_err(|e| WorktreeError::Database(e.to_string()))
+            .map_err(|e| save_error(id, e))
     }
 
     /// Reorder worktrees
@@ -201,15 +812,412 @@ impl WorktreeService {
         self.list_worktrees(workspace_id)
     }
 
-    /// Get git status for a worktree
+    /// Get git status for a worktree, compared with its workspace's base branch
     pub fn get_git_status(&self, id: &str) -> Result<GitStatusInfo, WorktreeError> {
+        self.get_git_status_with(id, &GitStatusOptions::default())
+    }
+
+    /// Get one page of a worktree's git status
+    pub fn get_git_status_with(
+        &self,
+        id: &str,
+        options: &GitStatusOptions,
+    ) -> Result<GitStatusInfo, WorktreeError> {
         let worktree = self.get_worktree(id)?;
-        GitService::get_status(&worktree.path).map_err(|e| WorktreeError::Git(e.to_string()))
+        let base_branch = self.get_base_branch(&worktree.workspace_id)?;
+        if let Some(status) = self
+            .git_watch
+            .as_ref()
+            .and_then(|watch| watch.cached_status(id, &base_branch, options))
+        {
+            return Ok(status);
+        }
+
+        let status = self
+            .git
+            .get_status_against(&worktree.path, &base_branch, options)
+            .map_err(|e| WorktreeError::Git(e.to_string()))?;
+        if let Some(watch) = &self.git_watch {
+            watch.cache_status(id, &base_branch, options, status.clone());
+        }
+        Ok(status)
+    }
+
+    /// Check each of a workspace's worktrees on disk and in git: whether its
+    /// directory exists, git still lists it, locks it or would prune it, and
+    /// whether it has uncommitted changes
+    pub fn get_worktree_health(
+        &self,
+        workspace_id: &str,
+    ) -> Result<Vec<WorktreeHealth>, WorktreeError> {
+        let workspace = self.find_workspace(workspace_id)?;
+        let listed = self
+            .git
+            .list_worktrees(&workspace.path)
+            .map_err(|e| WorktreeError::Git(e.to_string()))?;
+        let status_options = GitStatusOptions {
+            max_entries: Some(0),
+            ..Default::default()
+        };
+
+        Ok(self
+            .list_worktrees(workspace_id)?
+            .into_iter()
+            .map(|worktree| {
+                let info = listed_entry(&listed, &worktree.path);
+                let exists = Path::new(&worktree.path).exists();
+                let dirty = exists
+                    .then(|| self.get_git_status_with(&worktree.id, &status_options))
+                    .and_then(|status| status.ok())
+                    .map(|status| !status.is_clean);
+                let usable = info.is_some_and(|info| !info.prunable) && dirty.is_some();
+                let state = if !exists {
+                    WorktreeState::Missing
+                } else if usable {
+                    WorktreeState::Ok
+                } else {
+                    WorktreeState::Broken
+                };
+                WorktreeHealth {
+                    worktree_id: worktree.id,
+                    path: worktree.path,
+                    state,
+                    exists,
+                    registered: info.is_some(),
+                    locked: info.is_some_and(|info| info.locked),
+                    prunable: info.is_some_and(|info| info.prunable),
+                    dirty,
+                }
+            })
+            .collect())
     }
 
     /// List branches for a worktree
     pub fn list_branches(&self, id: &str) -> Result<BranchInfo, WorktreeError> {
         let worktree = self.get_worktree(id)?;
-        GitService::list_branches(&worktree.path).map_err(|e| WorktreeError::Git(e.to_string()))
+        self.git
+            .list_branches(&worktree.path)
+            .map_err(|e| WorktreeError::Git(e.to_string()))
+    }
+
+    /// Commits on a worktree's branch that are not on the main worktree's
+    /// branch; the main worktree gets its full history
+    pub fn get_commit_log(
+        &self,
+        id: &str,
+        limit: usize,
+        skip: usize,
+    ) -> Result<Vec<CommitInfo>, WorktreeError> {
+        let worktree = self.get_worktree(id)?;
+        let base_branch = if worktree.is_main {
+            None
+        } else {
+            self.list_worktrees(&worktree.workspace_id)?
+                .into_iter()
+                .find(|w| w.is_main)
+                .and_then(|main| self.git.get_current_branch(&main.path).ok())
+                .filter(|branch| *branch != worktree.branch)
+        };
+
+        self.git
+            .commit_log(&worktree.path, base_branch.as_deref(), limit, skip)
+            .map_err(|e| WorktreeError::Git(e.to_string()))
+    }
+
+    /// Stash a worktree's uncommitted changes; None when it was clean
+    pub fn stash_save(
+        &self,
+        id: &str,
+        message: Option<&str>,
+        include_untracked: bool,
+    ) -> Result<Option<StashEntry>, WorktreeError> {
+        let worktree = self.get_worktree(id)?;
+        self.git
+            .stash_save(&worktree.path, message, include_untracked)
+            .map_err(|e| WorktreeError::Git(e.to_string()))
+    }
+
+    /// List the stashes of a worktree's repository
+    pub fn stash_list(&self, id: &str) -> Result<Vec<StashEntry>, WorktreeError> {
+        let worktree = self.get_worktree(id)?;
+        self.git
+            .stash_list(&worktree.path)
+            .map_err(|e| WorktreeError::Git(e.to_string()))
+    }
+
+    /// Apply a stash to a worktree and drop it
+    pub fn stash_pop(&self, id: &str, index: usize) -> Result<(), WorktreeError> {
+        let worktree = self.get_worktree(id)?;
+        self.git
+            .stash_pop(&worktree.path, index)
+            .map_err(|e| WorktreeError::Git(e.to_string()))
+    }
+
+    /// Fetch the remotes of a worktree's repository so remote branches are current
+    pub fn fetch(&self, id: &str, prune: bool) -> Result<(), WorktreeError> {
+        let worktree = self.get_worktree(id)?;
+        let token = self
+            .settings_repo
+            .get(GIT_TOKEN_KEY)
+            .map_err(|e| WorktreeError::Database(e.to_string()))?
+            .filter(|t| !t.is_empty());
+        self.git
+            .fetch(&worktree.path, prune, token.as_deref())
+            .map_err(|e| WorktreeError::Git(e.to_string()))
+    }
+
+    /// Find files edited in more than one worktree of a workspace.
+    ///
+    /// Each worktree's changes since it forked from the main worktree's branch
+    /// (committed or not) are compared with every other worktree, and with what
+    /// changed on the main branch or in the main worktree since the fork.
+    pub fn detect_conflicts(&self, workspace_id: &str) -> Result<ConflictReport, WorktreeError> {
+        let worktrees = self.list_worktrees(workspace_id)?;
+        let main = worktrees
+            .iter()
+            .find(|w| w.is_main)
+            .ok_or_else(|| WorktreeError::NotFound(format!("main worktree of {}", workspace_id)))?;
+        let base_branch = self
+            .git
+            .get_current_branch(&main.path)
+            .map_err(|e| WorktreeError::Git(e.to_string()))?;
+
+        // Uncommitted changes in the main worktree
+        let main_changes = self
+            .git
+            .diverged_files(&main.path, &base_branch)
+            .map_err(|e| WorktreeError::Git(e.to_string()))?
+            .branch;
+
+        let mut diverged = Vec::new();
+        for worktree in worktrees.iter().filter(|w| !w.is_main) {
+            match self.git.diverged_files(&worktree.path, &base_branch) {
+                Ok(files) => diverged.push((worktree, files)),
+                Err(e) => tracing::warn!(
+                    "Skipping worktree {} in conflict detection: {}",
+                    worktree.id,
+                    e
+                ),
+            }
+        }
+
+        let mut conflicts = Vec::new();
+        for (i, (worktree, files)) in diverged.iter().enumerate() {
+            let against_main: BTreeSet<&String> = files.base.union(&main_changes).collect();
+            push_conflict(
+                &mut conflicts,
+                &worktree.id,
+                &main.id,
+                files.branch.iter().filter(|p| against_main.contains(p)),
+            );
+
+            for (other, other_files) in &diverged[i + 1..] {
+                push_conflict(
+                    &mut conflicts,
+                    &worktree.id,
+                    &other.id,
+                    files.branch.intersection(&other_files.branch),
+                );
+            }
+        }
+
+        Ok(ConflictReport {
+            workspace_id: workspace_id.to_string(),
+            base_branch,
+            conflicts,
+        })
+    }
+}
+
+/// The entry git lists for a worktree path, however either was spelled
+fn listed_entry<'a>(listed: &'a [WorktreeInfo], path: &str) -> Option<&'a WorktreeInfo> {
+    let canonical = Path::new(path).canonicalize().ok();
+    listed.iter().find(|info| {
+        info.path == path
+            || canonical.is_some() && Path::new(&info.path).canonicalize().ok() == canonical
+    })
+}
+
+/// Settings key holding a workspace's base branch
+pub(crate) fn base_branch_key(workspace_id: &str) -> String {
+    format!("{}:{}", BASE_BRANCH_KEY, workspace_id)
+}
+
+/// Settings key holding how a workspace's worktrees are ordered
+pub(crate) fn worktree_sort_key(workspace_id: &str) -> String {
+    format!("{}:{}", WORKTREE_SORT_KEY, workspace_id)
+}
+
+/// Where a worktree goes when sorted by status: one with an agent waiting on
+/// the user first, then one with an agent running, then idle ones, then those
+/// without agents. `is_live` says whether an agent's process is still running.
+pub(crate) fn status_rank(agents: &[Agent], is_live: impl Fn(&Agent) -> bool) -> u8 {
+    agents
+        .iter()
+        .map(|agent| match agent.status {
+            AgentStatus::Waiting if is_live(agent) => 0,
+            AgentStatus::Running if is_live(agent) => 1,
+            _ => 2,
+        })
+        .min()
+        .unwrap_or(3)
+}
+
+/// Error for a worktree that could not be saved, telling concurrent edits apart
+fn save_error(id: &str, e: DbError) -> WorktreeError {
+    match e {
+        DbError::Conflict(updated_at) => WorktreeError::Conflict {
+            id: id.to_string(),
+            updated_at,
+        },
+        e => WorktreeError::Database(e.to_string()),
+    }
+}
+
+/// Fill in a branch template's placeholders
+fn render_branch_name(template: &str, worktree_name: &str, workspace_name: &str) -> String {
+    template
+        .replace("{slug}", &slugify(worktree_name))
+        .replace("{workspace}", &slugify(workspace_name))
+        .replace("{date}", &chrono::Utc::now().format("%Y%m%d").to_string())
+}
+
+/// Lowercase words joined by dashes, e.g. "Fix login bug!" -> "fix-login-bug"
+fn slugify(name: &str) -> String {
+    let slug = name
+        .to_lowercase()
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join("-");
+    let slug = slug[..slug.len().min(MAX_SLUG_LENGTH)].trim_end_matches('-');
+    if slug.is_empty() {
+        "worktree".to_string()
+    } else {
+        slug.to_string()
+    }
+}
+
+/// A configured path must stay inside the worktree and may only use `*` in its last segment
+fn validate_worktree_path(path: &str) -> Result<(), WorktreeError> {
+    let invalid = |reason: &str| WorktreeError::Validation(format!("{}: {}", reason, path));
+    let parsed = Path::new(path);
+    if parsed.is_absolute()
+        || parsed
+            .components()
+            .any(|c| !matches!(c, Component::Normal(_)))
+    {
+        return Err(invalid("Paths must be relative to the worktree"));
+    }
+    if parsed.components().next() == Some(Component::Normal(".git".as_ref())) {
+        return Err(invalid("Paths cannot be inside .git"));
+    }
+    if path
+        .rsplit_once('/')
+        .is_some_and(|(dir, _)| dir.contains('*'))
+    {
+        return Err(invalid(
+            "Wildcards are only allowed in the last path segment",
+        ));
+    }
+    Ok(())
+}
+
+/// Expand shared paths against the main worktree, returning the matching
+/// paths and the configured paths that match nothing
+fn resolve_shared_files(root: &Path, paths: &[String]) -> (Vec<String>, Vec<String>) {
+    let mut files = BTreeSet::new();
+    let mut missing = Vec::new();
+
+    for path in paths {
+        let (dir, name) = match path.rsplit_once('/') {
+            Some((dir, name)) => (Some(dir), name),
+            None => (None, path.as_str()),
+        };
+        let relative = |name: &str| match dir {
+            Some(dir) => format!("{}/{}", dir, name),
+            None => name.to_string(),
+        };
+
+        let matches: Vec<String> = if name.contains('*') {
+            std::fs::read_dir(root.join(dir.unwrap_or_default()))
+                .map(|entries| {
+                    entries
+                        .filter_map(|entry| entry.ok())
+                        .filter_map(|entry| entry.file_name().into_string().ok())
+                        .filter(|entry| entry != ".git" && wildcard_match(name, entry))
+                        .map(|entry| relative(&entry))
+                        .collect()
+                })
+                .unwrap_or_default()
+        } else if root.join(path).symlink_metadata().is_ok() {
+            vec![path.clone()]
+        } else {
+            Vec::new()
+        };
+
+        if matches.is_empty() {
+            missing.push(path.clone());
+        }
+        files.extend(matches);
+    }
+
+    (files.into_iter().collect(), missing)
+}
+
+/// Copy or link one shared file or directory, leaving existing targets alone
+fn share_file(source: &Path, target: &Path, mode: SharedFileMode) -> std::io::Result<()> {
+    if target.symlink_metadata().is_ok() {
+        return Ok(());
+    }
+    if let Some(parent) = target.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    match mode {
+        SharedFileMode::Copy => copy_recursive(source, target),
+        SharedFileMode::Symlink => symlink(source, target),
+    }
+}
+
+fn copy_recursive(source: &Path, target: &Path) -> std::io::Result<()> {
+    if source.is_dir() {
+        std::fs::create_dir_all(target)?;
+        for entry in std::fs::read_dir(source)? {
+            let entry = entry?;
+            copy_recursive(&entry.path(), &target.join(entry.file_name()))?;
+        }
+        Ok(())
+    } else {
+        std::fs::copy(source, target).map(|_| ())
+    }
+}
+
+#[cfg(unix)]
+fn symlink(source: &Path, target: &Path) -> std::io::Result<()> {
+    std::os::unix::fs::symlink(source, target)
+}
+
+#[cfg(windows)]
+fn symlink(source: &Path, target: &Path) -> std::io::Result<()> {
+    if source.is_dir() {
+        std::os::windows::fs::symlink_dir(source, target)
+    } else {
+        std::os::windows::fs::symlink_file(source, target)
+    }
+}
+
+fn push_conflict<'a>(
+    conflicts: &mut Vec<WorktreeConflict>,
+    worktree_id: &str,
+    other_worktree_id: &str,
+    paths: impl Iterator<Item = &'a String>,
+) {
+    let paths: Vec<String> = paths.cloned().collect();
+    if !paths.is_empty() {
+        conflicts.push(WorktreeConflict {
+            worktree_id: worktree_id.to_string(),
+            other_worktree_id: other_worktree_id.to_string(),
+            paths,
+        });
     }
 }
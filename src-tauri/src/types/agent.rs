@@ -1,5 +1,6 @@
 //! Agent type definitions
 
+use chrono::{DateTime, NaiveDateTime, Utc};
 use serde::{Deserialize, Serialize};
 
 /// Agent status enum
@@ -100,6 +101,14 @@ pub struct AgentRow {
     pub stopped_at: Option<String>,
     pub deleted_at: Option<String>,
     pub parent_agent_id: Option<String>,
+    pub status_reason: Option<String>,
+    pub task_group_id: Option<String>,
+    pub fork_session_id: Option<String>,
+    pub system_prompt: Option<String>,
+    pub append_system_prompt: Option<String>,
+    pub allowed_tools: String,    // JSON array
+    pub disallowed_tools: String, // JSON array
+    pub tags: String,             // JSON array
 }
 
 /// API representation (camelCase via serde)
@@ -124,14 +133,46 @@ pub struct Agent {
     pub started_at: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub stopped_at: Option<String>,
+    /// How long the current run has lasted, or the last one lasted once it
+    /// stopped; None if the agent never started
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub run_duration_ms: Option<i64>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub deleted_at: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub parent_agent_id: Option<String>,
+    /// Why the agent last stopped or failed, e.g. "Exited with code 1"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status_reason: Option<String>,
+    /// Fan-out task this agent is one attempt of
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub task_group_id: Option<String>,
+    /// Parent session to branch from on first start, until the agent has its own
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fork_session_id: Option<String>,
+    /// Replaces the Claude CLI's default system prompt
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub system_prompt: Option<String>,
+    /// Added to the end of the system prompt, e.g. role instructions
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub append_system_prompt: Option<String>,
+    /// Tool patterns allowed on top of the permission presets, e.g.
+    /// `mcp__github__create_issue` or `Bash(npm test:*)`
+    #[serde(default)]
+    pub allowed_tools: Vec<String>,
+    /// Tool patterns never allowed, e.g. `Bash(rm:*)`; these win over allowed ones
+    #[serde(default)]
+    pub disallowed_tools: Vec<String>,
+    /// Labels for grouping agents by task, sorted
+    #[serde(default)]
+    pub tags: Vec<String>,
 }
 
 impl From<AgentRow> for Agent {
     fn from(row: AgentRow) -> Self {
+        let mut tags: Vec<String> = serde_json::from_str(&row.tags).unwrap_or_default();
+        tags.sort();
+
         Agent {
             id: row.id,
             worktree_id: row.worktree_id,
@@ -145,14 +186,54 @@ impl From<AgentRow> for Agent {
             session_id: row.session_id,
             created_at: row.created_at,
             updated_at: row.updated_at,
+            run_duration_ms: run_duration_ms(
+                row.started_at.as_deref(),
+                row.stopped_at.as_deref(),
+                Utc::now(),
+            ),
             started_at: row.started_at,
             stopped_at: row.stopped_at,
             deleted_at: row.deleted_at,
             parent_agent_id: row.parent_agent_id,
+            status_reason: row.status_reason,
+            task_group_id: row.task_group_id,
+            fork_session_id: row.fork_session_id,
+            system_prompt: row.system_prompt,
+            append_system_prompt: row.append_system_prompt,
+            allowed_tools: serde_json::from_str(&row.allowed_tools).unwrap_or_default(),
+            disallowed_tools: serde_json::from_str(&row.disallowed_tools).unwrap_or_default(),
+            tags,
         }
     }
 }
 
+/// Milliseconds from `started_at` to `stopped_at`, or to `now` while the run
+/// goes on
+fn run_duration_ms(
+    started_at: Option<&str>,
+    stopped_at: Option<&str>,
+    now: DateTime<Utc>,
+) -> Option<i64> {
+    let started = parse_timestamp(started_at?)?;
+    let ended = match stopped_at.and_then(parse_timestamp) {
+        Some(stopped) if stopped >= started => stopped,
+        _ => now,
+    };
+    Some((ended - started).num_milliseconds().max(0))
+}
+
+/// A stored timestamp, either RFC 3339 or SQLite's `YYYY-MM-DD HH:MM:SS` in UTC
+fn parse_timestamp(value: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|at| at.with_timezone(&Utc))
+        .ok()
+        .or_else(|| {
+            NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S%.f")
+                .ok()
+                .map(|at| at.and_utc())
+        })
+}
+
 /// Input for creating a new agent
 #[derive(Debug, Clone, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -162,6 +243,10 @@ pub struct CreateAgentInput {
     pub mode: Option<AgentMode>,
     pub permissions: Option<Vec<Permission>>,
     pub initial_prompt: Option<String>,
+    pub system_prompt: Option<String>,
+    pub append_system_prompt: Option<String>,
+    pub allowed_tools: Option<Vec<String>>,
+    pub disallowed_tools: Option<Vec<String>>,
 }
 
 /// Input for updating an agent
@@ -172,6 +257,22 @@ pub struct UpdateAgentInput {
     pub mode: Option<AgentMode>,
     pub permissions: Option<Vec<Permission>>,
     pub display_order: Option<i32>,
+    /// Takes effect on the next start; an empty string clears it
+    #[serde(default)]
+    pub system_prompt: Option<String>,
+    /// Takes effect on the next start; an empty string clears it
+    #[serde(default)]
+    pub append_system_prompt: Option<String>,
+    /// Replaces the allowed tool patterns; takes effect on the next start
+    #[serde(default)]
+    pub allowed_tools: Option<Vec<String>>,
+    /// Replaces the disallowed tool patterns; takes effect on the next start
+    #[serde(default)]
+    pub disallowed_tools: Option<Vec<String>>,
+    /// The `updated_at` the agent was loaded with; the update fails with a
+    /// conflict if it changed since
+    #[serde(default)]
+    pub expected_updated_at: Option<String>,
 }
 
 /// Response for agent list
@@ -181,6 +282,108 @@ pub struct AgentListResponse {
     pub agents: Vec<Agent>,
 }
 
+/// Outcome of stopping one agent during a bulk stop
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AgentStopResult {
+    pub agent_id: String,
+    pub agent_name: String,
+    pub stopped: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Response for stopping every running agent in a workspace or worktree
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StopAgentsResponse {
+    pub results: Vec<AgentStopResult>,
+    pub stopped: usize,
+    pub failed: usize,
+}
+
+/// Unsent message the user was composing for an agent
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MessageDraft {
+    pub agent_id: String,
+    pub content: String,
+    pub updated_at: String,
+}
+
+/// One process lifetime of an agent, from spawn until exit
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AgentRun {
+    pub id: i64,
+    pub agent_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+    pub started_at: String,
+    /// None while the process is running
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ended_at: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exit_code: Option<i32>,
+    /// Signal that terminated the process, on unix
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signal: Option<String>,
+    pub stopped_by_user: bool,
+    /// The run this one retried
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retry_of: Option<i64>,
+    /// 1 for a first run, one more for each retry after it
+    pub attempt: i64,
+    /// Time spent working, up to the last status change
+    pub active_ms: i64,
+    /// Time spent waiting on the user, e.g. at a permission prompt
+    pub waiting_ms: i64,
+    /// Time spent idle at the prompt
+    pub idle_ms: i64,
+    /// Highest resident memory sampled from the process, in bytes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub peak_memory_bytes: Option<i64>,
+    /// Highest CPU use sampled from the process, in percent of one core
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub peak_cpu_percent: Option<f64>,
+}
+
+/// What the process manager knows about an agent's process, next to the
+/// status the database records
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AgentRuntimeInfo {
+    pub agent_id: String,
+    pub status: AgentStatus,
+    pub is_running: bool,
+    /// The status says the agent is working or waiting but it has no process
+    pub stale: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pid: Option<u32>,
+    /// When the process last produced output
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_output_at: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_output_age_ms: Option<i64>,
+    /// Terminal output kept for replay
+    pub buffer_bytes: usize,
+    /// Terminals attached to the live output
+    pub subscribers: usize,
+}
+
+/// Where an agent's time went across all its runs, including the current one
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AgentActivitySummary {
+    pub agent_id: String,
+    pub run_count: i64,
+    pub active_ms: i64,
+    pub waiting_ms: i64,
+    pub idle_ms: i64,
+    /// Share of the tracked time spent waiting on the user, 0-100
+    pub waiting_percent: f64,
+}
+
 /// Input for reordering agents
 #[derive(Debug, Clone, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -0,0 +1,83 @@
+//! Command authorization and audit log type definitions
+
+use serde::{Deserialize, Serialize};
+
+/// What a command needs to be allowed to do
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Capability {
+    /// Look at workspaces, agents, logs and settings
+    Read,
+    /// Create, start, stop or type into agents
+    AgentControl,
+    /// Change repositories, worktrees or files in them
+    GitWrite,
+    /// Change app settings
+    Settings,
+}
+
+impl Capability {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Capability::Read => "read",
+            Capability::AgentControl => "agent_control",
+            Capability::GitWrite => "git_write",
+            Capability::Settings => "settings",
+        }
+    }
+
+    pub fn parse(s: &str) -> Self {
+        match s {
+            "agent_control" => Capability::AgentControl,
+            "git_write" => Capability::GitWrite,
+            "settings" => Capability::Settings,
+            _ => Capability::Read,
+        }
+    }
+}
+
+/// Where a command came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CallerOrigin {
+    /// The app's own window, over Tauri IPC
+    Desktop,
+    /// A client of the local WebSocket server
+    Local,
+    /// A client of the remote access listener
+    Remote,
+}
+
+impl CallerOrigin {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CallerOrigin::Desktop => "desktop",
+            CallerOrigin::Local => "local",
+            CallerOrigin::Remote => "remote",
+        }
+    }
+
+    pub fn parse(s: &str) -> Self {
+        match s {
+            "local" => CallerOrigin::Local,
+            "remote" => CallerOrigin::Remote,
+            _ => CallerOrigin::Desktop,
+        }
+    }
+}
+
+/// A privileged command someone ran or tried to run
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditEntry {
+    pub id: i64,
+    pub command: String,
+    pub capability: Capability,
+    pub origin: CallerOrigin,
+    /// The agent, worktree or workspace the command acted on
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target_id: Option<String>,
+    /// False when the command was refused
+    pub allowed: bool,
+    pub created_at: String,
+}
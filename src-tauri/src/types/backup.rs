@@ -0,0 +1,42 @@
+//! Database backup type definitions
+
+use serde::{Deserialize, Serialize};
+
+use crate::db::MigrationStats;
+
+/// Information about a database backup file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupInfo {
+    pub path: String,
+    pub size_bytes: u64,
+    pub schema_version: i64,
+    pub created_at: String,
+}
+
+/// Result of restoring the application database from a backup
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RestoreResult {
+    /// Backup that was restored
+    pub restored_from: String,
+    /// Snapshot of the database taken right before the restore
+    pub pre_restore_backup: BackupInfo,
+    /// Schema version of the restored data before pending migrations were applied
+    pub schema_version: i64,
+    /// Number of agents that were stopped before restoring
+    pub agents_stopped: usize,
+}
+
+/// Result of importing the Node.js backend's database
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LegacyMigrationResult {
+    /// Database the data was imported from
+    pub source_path: String,
+    /// Snapshot of the database taken right before the import; none on a dry run
+    pub backup: Option<BackupInfo>,
+    pub stats: MigrationStats,
+    /// Problems the verification found after the import
+    pub warnings: Vec<String>,
+}
@@ -0,0 +1,39 @@
+//! Worktree bootstrap type definitions
+
+use serde::{Deserialize, Serialize};
+
+/// Commands a workspace runs in each new worktree, e.g. `npm install`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BootstrapConfig {
+    /// Run in order; a failing command stops the rest
+    pub commands: Vec<String>,
+    /// Refuse to start agents in the worktree until bootstrap finishes
+    #[serde(default)]
+    pub block_agent_start: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BootstrapStatus {
+    Running,
+    Succeeded,
+    Failed,
+}
+
+/// The latest bootstrap run of a worktree
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WorktreeBootstrap {
+    pub worktree_id: String,
+    pub status: BootstrapStatus,
+    pub commands: Vec<String>,
+    pub block_agent_start: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exit_code: Option<i32>,
+    /// Output so far as plain text; stream `/ws/bootstrap/:worktree_id` for live output
+    pub output: String,
+    pub started_at: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub finished_at: Option<String>,
+}
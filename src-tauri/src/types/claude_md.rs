@@ -0,0 +1,46 @@
+//! CLAUDE.md memory file type definitions
+
+use serde::{Deserialize, Serialize};
+
+/// Which memory file of a worktree
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ClaudeMdScope {
+    /// `CLAUDE.md`, shared through the repository
+    #[default]
+    Project,
+    /// `.claude/CLAUDE.local.md`, personal and not committed
+    Local,
+}
+
+impl ClaudeMdScope {
+    /// Path of the file relative to the worktree root
+    pub fn relative_path(&self) -> &'static str {
+        match self {
+            ClaudeMdScope::Project => "CLAUDE.md",
+            ClaudeMdScope::Local => ".claude/CLAUDE.local.md",
+        }
+    }
+}
+
+/// A memory file as it is on disk
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClaudeMdFile {
+    pub scope: ClaudeMdScope,
+    pub path: String,
+    pub exists: bool,
+    /// Empty when the file does not exist
+    pub content: String,
+    /// Modification time in milliseconds since the epoch; pass it back when
+    /// updating so edits made elsewhere are not overwritten
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub modified_at: Option<i64>,
+}
+
+/// Response for a worktree's memory files
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClaudeMdResponse {
+    pub files: Vec<ClaudeMdFile>,
+}
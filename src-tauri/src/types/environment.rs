@@ -0,0 +1,111 @@
+//! Environment check type definitions
+
+use serde::Serialize;
+
+/// A problem found with the Claude CLI setup
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CliIssue {
+    /// Nothing executable at the configured path
+    NotFound,
+    /// The binary ran but `--version` failed or printed no version
+    VersionUnknown,
+    /// Older than the minimum supported version
+    Outdated,
+    /// No stored credentials or API key
+    NotLoggedIn,
+}
+
+/// An issue with a message saying how to fix it
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CliDiagnostic {
+    pub issue: CliIssue,
+    pub message: String,
+}
+
+/// Whether the Claude CLI is installed, supported and logged in
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClaudeCliStatus {
+    /// Configured path, as given
+    pub path: String,
+    /// Where the binary was found
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resolved_path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    pub min_version: String,
+    pub logged_in: bool,
+    /// Empty when the CLI is ready to use
+    pub diagnostics: Vec<CliDiagnostic>,
+}
+
+impl ClaudeCliStatus {
+    /// The CLI can run agents, though a login may still be needed
+    pub fn is_installed(&self) -> bool {
+        !self
+            .diagnostics
+            .iter()
+            .any(|d| matches!(d.issue, CliIssue::NotFound | CliIssue::Outdated))
+    }
+}
+
+/// How usable the stored Claude login is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuthState {
+    /// The API accepted the token
+    Valid,
+    /// No credentials file, or no OAuth login in it
+    Missing,
+    /// The credentials file could not be read
+    Invalid,
+    /// The token is past its expiry time
+    Expired,
+    /// The API turned the token down
+    Rejected,
+    /// The token looks current but the API could not be reached
+    Unverified,
+}
+
+/// Result of checking a Claude account's login
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClaudeAuthStatus {
+    pub state: AuthState,
+    /// When the access token expires, RFC 3339
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub subscription_type: Option<String>,
+    /// What went wrong, for anything but a valid login
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+/// State of a `claude login` run
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LoginStatus {
+    Running,
+    Succeeded,
+    Failed,
+    Cancelled,
+}
+
+/// A `claude login` run in a terminal the app manages
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClaudeLogin {
+    /// Profile whose account is logging in; None for the default account
+    pub profile_id: Option<String>,
+    pub status: LoginStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exit_code: Option<i32>,
+    /// Output so far as plain text, e.g. the URL to open and the code prompt
+    pub output: String,
+    pub started_at: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub finished_at: Option<String>,
+}
@@ -4,7 +4,49 @@
 //! provides deterministic status signals (permission_prompt, idle_prompt,
 //! elicitation_dialog) that replace the fragile PTY buffer heuristic.
 
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+
+use super::AgentStatus;
+
+/// Hook event that fired, from `hook_event_name`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum HookEventName {
+    PreToolUse,
+    PostToolUse,
+    Notification,
+    UserPromptSubmit,
+    Stop,
+    SubagentStop,
+    PreCompact,
+    SessionStart,
+    SessionEnd,
+    /// An event added by a newer CLI
+    #[serde(other)]
+    Other,
+}
+
+/// Notification sub-type, from `notification_type`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationType {
+    PermissionPrompt,
+    IdlePrompt,
+    ElicitationDialog,
+    /// A notification the manager doesn't act on
+    #[serde(other)]
+    Other,
+}
+
+impl NotificationType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NotificationType::PermissionPrompt => "permission_prompt",
+            NotificationType::IdlePrompt => "idle_prompt",
+            NotificationType::ElicitationDialog => "elicitation_dialog",
+            NotificationType::Other => "other",
+        }
+    }
+}
 
 /// JSON payload received from Claude Code hook commands.
 ///
@@ -15,17 +57,89 @@ pub struct HookNotification {
     /// The Claude session ID (matches --session-id passed at spawn)
     pub session_id: Option<String>,
 
+    /// Path to the session's transcript file
+    pub transcript_path: Option<String>,
+
     /// Working directory of the Claude session
     pub cwd: Option<String>,
 
     /// Hook event name, e.g. "Notification"
-    pub hook_event_name: Option<String>,
+    pub hook_event_name: Option<HookEventName>,
 
     /// Notification sub-type: "permission_prompt", "idle_prompt", "elicitation_dialog"
-    pub notification_type: Option<String>,
+    pub notification_type: Option<NotificationType>,
 
     /// Human-readable message from the notification
     pub message: Option<String>,
+
+    /// Tool the event is about, for PreToolUse and PostToolUse
+    pub tool_name: Option<String>,
+
+    /// Arguments the tool was called with
+    pub tool_input: Option<serde_json::Value>,
+
+    /// What the tool returned, for PostToolUse
+    pub tool_response: Option<serde_json::Value>,
+}
+
+impl HookNotification {
+    /// The value hook matchers are tested against: the notification type for
+    /// notifications, the tool name for tool events
+    pub fn matcher(&self) -> Option<&str> {
+        match self.hook_event_name {
+            Some(HookEventName::PreToolUse | HookEventName::PostToolUse) => {
+                self.tool_name.as_deref()
+            }
+            _ => self.notification_type.map(|t| t.as_str()),
+        }
+    }
+
+    /// Agent status the notification reports, if any. A session starting or
+    /// a subagent handing back means the agent is working; `Stop` means it
+    /// finished its turn and is back at the prompt.
+    pub fn agent_status(&self) -> Option<AgentStatus> {
+        match self.hook_event_name {
+            Some(HookEventName::SessionStart | HookEventName::SubagentStop) => {
+                return Some(AgentStatus::Running)
+            }
+            Some(HookEventName::Stop) => return Some(AgentStatus::Idle),
+            None | Some(HookEventName::Notification) => {}
+            Some(_) => return None,
+        }
+        match self.notification_type? {
+            NotificationType::PermissionPrompt | NotificationType::ElicitationDialog => {
+                Some(AgentStatus::Waiting)
+            }
+            NotificationType::IdlePrompt => Some(AgentStatus::Idle),
+            NotificationType::Other => None,
+        }
+    }
+}
+
+/// Hook shared by every worktree in a workspace, written to each worktree's
+/// `.claude/settings.local.json` next to the manager's own hooks
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SharedHook {
+    /// Hook event, e.g. "PostToolUse"
+    pub event: String,
+    /// Tool or notification matcher, e.g. "Edit|Write"; None matches everything
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub matcher: Option<String>,
+    /// Shell command to run
+    pub command: String,
+}
+
+/// Outcome of syncing the manager's hooks into one worktree
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SettingsSyncResult {
+    pub worktree_id: String,
+    pub worktree_path: String,
+    /// Whether the settings file was rewritten
+    pub changed: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
 }
 
 #[cfg(test)]
@@ -43,7 +157,13 @@ mod tests {
         }"#;
         let notif: HookNotification = serde_json::from_str(json).unwrap();
         assert_eq!(notif.session_id.as_deref(), Some("abc-123"));
-        assert_eq!(notif.notification_type.as_deref(), Some("permission_prompt"));
+        assert_eq!(
+            notif.notification_type,
+            Some(NotificationType::PermissionPrompt)
+        );
+        assert_eq!(notif.hook_event_name, Some(HookEventName::Notification));
+        assert_eq!(notif.matcher(), Some("permission_prompt"));
+        assert_eq!(notif.agent_status(), Some(AgentStatus::Waiting));
     }
 
     #[test]
@@ -54,9 +174,10 @@ mod tests {
             "notification_type": "idle_prompt"
         }"#;
         let notif: HookNotification = serde_json::from_str(json).unwrap();
-        assert_eq!(notif.notification_type.as_deref(), Some("idle_prompt"));
+        assert_eq!(notif.notification_type, Some(NotificationType::IdlePrompt));
         assert!(notif.cwd.is_none());
         assert!(notif.message.is_none());
+        assert_eq!(notif.agent_status(), Some(AgentStatus::Idle));
     }
 
     #[test]
@@ -65,6 +186,7 @@ mod tests {
         let notif: HookNotification = serde_json::from_str(json).unwrap();
         assert!(notif.session_id.is_none());
         assert!(notif.notification_type.is_none());
+        assert!(notif.agent_status().is_none());
     }
 
     #[test]
@@ -76,6 +198,53 @@ mod tests {
         }"#;
         // serde default behavior: unknown fields are ignored (no deny_unknown_fields)
         let notif: HookNotification = serde_json::from_str(json).unwrap();
-        assert_eq!(notif.notification_type.as_deref(), Some("elicitation_dialog"));
+        assert_eq!(
+            notif.notification_type,
+            Some(NotificationType::ElicitationDialog)
+        );
+    }
+
+    #[test]
+    fn test_hook_notification_unknown_types_report_no_status() {
+        let json = r#"{
+            "session_id": "x",
+            "hook_event_name": "SomeFutureEvent",
+            "notification_type": "auth_success"
+        }"#;
+        let notif: HookNotification = serde_json::from_str(json).unwrap();
+        assert_eq!(notif.hook_event_name, Some(HookEventName::Other));
+        assert_eq!(notif.notification_type, Some(NotificationType::Other));
+        assert!(notif.agent_status().is_none());
+    }
+
+    #[test]
+    fn test_hook_notification_lifecycle_events() {
+        let status = |event: &str| {
+            let json = format!(r#"{{"session_id": "x", "hook_event_name": "{}"}}"#, event);
+            serde_json::from_str::<HookNotification>(&json)
+                .unwrap()
+                .agent_status()
+        };
+        assert_eq!(status("SessionStart"), Some(AgentStatus::Running));
+        assert_eq!(status("SubagentStop"), Some(AgentStatus::Running));
+        assert_eq!(status("Stop"), Some(AgentStatus::Idle));
+        assert_eq!(status("PreCompact"), None);
+    }
+
+    #[test]
+    fn test_hook_notification_deserialize_tool_event() {
+        let json = r#"{
+            "session_id": "abc-123",
+            "transcript_path": "/home/user/.claude/projects/p/abc-123.jsonl",
+            "hook_event_name": "PreToolUse",
+            "tool_name": "Bash",
+            "tool_input": { "command": "cargo test" }
+        }"#;
+        let notif: HookNotification = serde_json::from_str(json).unwrap();
+        assert_eq!(notif.hook_event_name, Some(HookEventName::PreToolUse));
+        assert_eq!(notif.matcher(), Some("Bash"));
+        assert_eq!(notif.tool_input.as_ref().unwrap()["command"], "cargo test");
+        // Tool events don't change the agent's status
+        assert!(notif.agent_status().is_none());
     }
 }
@@ -0,0 +1,44 @@
+//! Idle auto-shutdown type definitions
+
+use serde::{Deserialize, Serialize};
+
+/// When running agents left idle at the prompt are stopped
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IdleShutdownPolicy {
+    pub enabled: bool,
+    /// Minutes without output before the agent is stopped
+    pub idle_minutes: u32,
+}
+
+impl Default for IdleShutdownPolicy {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            idle_minutes: 60,
+        }
+    }
+}
+
+/// The idle policy chosen for an agent
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AgentIdlePolicy {
+    pub agent_id: String,
+    /// Set for this agent itself; None follows the global policy
+    pub policy: Option<IdleShutdownPolicy>,
+    /// What the agent runs with
+    pub effective: IdleShutdownPolicy,
+}
+
+/// An agent stopped for sitting idle, kept until it is started again so the
+/// UI can offer to resume its session
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IdleShutdown {
+    pub agent_id: String,
+    /// Session the agent resumes when started again
+    pub session_id: Option<String>,
+    pub idle_minutes: u32,
+    pub stopped_at: String,
+}
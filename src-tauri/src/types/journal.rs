@@ -0,0 +1,51 @@
+//! Process event journal type definitions
+
+use serde::{Deserialize, Serialize};
+
+use super::AgentStatus;
+
+/// Kind of journaled process event
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JournalEventType {
+    Status,
+    Error,
+    Exit,
+}
+
+impl JournalEventType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            JournalEventType::Status => "status",
+            JournalEventType::Error => "error",
+            JournalEventType::Exit => "exit",
+        }
+    }
+
+    pub fn parse(s: &str) -> Self {
+        match s {
+            "error" => JournalEventType::Error,
+            "exit" => JournalEventType::Exit,
+            _ => JournalEventType::Status,
+        }
+    }
+}
+
+/// A process event persisted before it is broadcast
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JournalEntry {
+    pub id: i64,
+    pub agent_id: String,
+    pub event_type: JournalEventType,
+    /// New status for status events
+    pub status: Option<AgentStatus>,
+    /// Status reason or error message
+    pub message: Option<String>,
+    pub exit_code: Option<i32>,
+    pub signal: Option<String>,
+    /// Exit events only: the process was stopped on request
+    pub stopped_by_user: bool,
+    pub created_at: String,
+    pub processed_at: Option<String>,
+}
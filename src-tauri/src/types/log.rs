@@ -0,0 +1,45 @@
+//! Application log type definitions
+
+use serde::{Deserialize, Serialize};
+
+/// Log level, from most to least severe
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl From<tracing::Level> for LogLevel {
+    fn from(level: tracing::Level) -> Self {
+        match level {
+            tracing::Level::ERROR => LogLevel::Error,
+            tracing::Level::WARN => LogLevel::Warn,
+            tracing::Level::INFO => LogLevel::Info,
+            tracing::Level::DEBUG => LogLevel::Debug,
+            tracing::Level::TRACE => LogLevel::Trace,
+        }
+    }
+}
+
+/// One backend log line
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogRecord {
+    pub timestamp: String,
+    pub level: LogLevel,
+    /// Module that logged it, e.g. "claude_manager_lib::services::process_service"
+    pub target: String,
+    /// The message followed by any structured fields as `key=value`
+    pub message: String,
+}
+
+/// Response for app log queries
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogListResponse {
+    pub records: Vec<LogRecord>,
+}
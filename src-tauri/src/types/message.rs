@@ -0,0 +1,89 @@
+//! Agent message type definitions
+
+use serde::{Deserialize, Serialize};
+
+/// Who a message came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MessageRole {
+    User,
+    Assistant,
+    System,
+    Tool,
+}
+
+impl MessageRole {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MessageRole::User => "user",
+            MessageRole::Assistant => "assistant",
+            MessageRole::System => "system",
+            MessageRole::Tool => "tool",
+        }
+    }
+
+    pub fn parse(s: &str) -> Self {
+        match s {
+            "assistant" => MessageRole::Assistant,
+            "system" => MessageRole::System,
+            "tool" => MessageRole::Tool,
+            _ => MessageRole::User,
+        }
+    }
+}
+
+/// A message of an agent's conversation
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Message {
+    pub id: String,
+    pub agent_id: String,
+    pub role: MessageRole,
+    pub content: String,
+    pub token_count: Option<i64>,
+    pub tool_name: Option<String>,
+    pub tool_input: Option<serde_json::Value>,
+    pub tool_output: Option<serde_json::Value>,
+    pub created_at: String,
+    pub is_complete: bool,
+    /// Position in the order messages were stored; pages are cut by it.
+    /// Not set until the message is read back from the database.
+    pub seq: i64,
+}
+
+/// A page of an agent's messages, oldest first
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MessageListResponse {
+    pub messages: Vec<Message>,
+    pub has_more: bool,
+    /// Pass as `before` to get the page of older messages
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<i64>,
+}
+
+/// Result of importing a Claude session transcript into an agent's messages
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionHistoryImport {
+    pub agent_id: String,
+    pub session_id: String,
+    /// Messages found in the transcript
+    pub found: usize,
+    /// Messages that were not imported before
+    pub imported: usize,
+}
+
+/// A Claude CLI session run in a managed worktree without the manager,
+/// e.g. by typing `claude` in a terminal
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExternalSession {
+    pub session_id: String,
+    pub worktree_id: String,
+    pub cwd: String,
+    /// When the transcript was last written
+    pub last_activity_at: String,
+    /// Written to in the last few minutes, so probably still running
+    pub active: bool,
+}
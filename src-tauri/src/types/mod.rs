@@ -4,14 +4,54 @@
 //! including database row types and API response types.
 
 pub mod agent;
+pub mod audit;
+pub mod backup;
+pub mod bootstrap;
+pub mod claude_md;
+pub mod environment;
 pub mod hook;
+pub mod idle_shutdown;
+pub mod job;
+pub mod journal;
+pub mod log;
+pub mod mcp;
+pub mod message;
+pub mod network;
+pub mod onboarding;
+pub mod profile;
+pub mod resources;
+pub mod schedule;
+pub mod session;
+pub mod settings;
+pub mod snippet;
+pub mod task_group;
 pub mod usage;
 pub mod websocket;
 pub mod workspace;
 pub mod worktree;
 
 pub use agent::*;
+pub use audit::*;
+pub use backup::*;
+pub use bootstrap::*;
+pub use claude_md::*;
+pub use environment::*;
 pub use hook::*;
+pub use idle_shutdown::*;
+pub use job::*;
+pub use journal::*;
+pub use log::*;
+pub use mcp::*;
+pub use message::*;
+pub use network::*;
+pub use onboarding::*;
+pub use profile::*;
+pub use resources::*;
+pub use schedule::*;
+pub use session::*;
+pub use settings::*;
+pub use snippet::*;
+pub use task_group::*;
 pub use usage::*;
 pub use websocket::*;
 pub use workspace::*;
@@ -0,0 +1,18 @@
+//! Network status type definitions
+
+use serde::Serialize;
+
+/// Whether the Claude API can be reached
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NetworkStatus {
+    pub online: bool,
+    /// When `online` last changed, RFC 3339
+    pub since: String,
+    /// When the last probe ran; None before the first one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub checked_at: Option<String>,
+    /// Why the last probe failed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
@@ -0,0 +1,46 @@
+//! First-run setup type definitions
+
+use serde::{Deserialize, Serialize};
+
+/// A step of the setup wizard, in the order they are shown
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OnboardingStep {
+    InstallCli,
+    LogIn,
+    AddWorkspace,
+}
+
+impl OnboardingStep {
+    pub const ALL: [OnboardingStep; 3] = [
+        OnboardingStep::InstallCli,
+        OnboardingStep::LogIn,
+        OnboardingStep::AddWorkspace,
+    ];
+}
+
+/// Progress on one setup step
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OnboardingStepState {
+    pub step: OnboardingStep,
+    /// The prerequisite is met right now
+    pub satisfied: bool,
+    /// The user finished or skipped the step
+    pub completed: bool,
+    /// What is missing, when not satisfied
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+/// Where the user is in first-run setup
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OnboardingState {
+    pub steps: Vec<OnboardingStepState>,
+    /// First step neither satisfied nor completed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub current_step: Option<OnboardingStep>,
+    /// Every step is satisfied or completed
+    pub finished: bool,
+}
@@ -0,0 +1,40 @@
+//! Claude account profile type definitions
+
+use serde::{Deserialize, Serialize};
+
+/// A Claude account agents can run under, e.g. a work and a personal one
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClaudeProfile {
+    pub id: String,
+    pub name: String,
+    /// Claude config directory holding the account's credentials, passed to
+    /// the CLI as `CLAUDE_CONFIG_DIR`; `~/.claude` when unset
+    pub config_dir: Option<String>,
+    /// Claude CLI to run instead of the app's default
+    pub cli_path: Option<String>,
+    /// Model agents start with, e.g. `opus`
+    pub model: Option<String>,
+}
+
+/// Input for creating a profile, or replacing one when `id` is given
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SaveProfileInput {
+    pub id: Option<String>,
+    pub name: String,
+    pub config_dir: Option<String>,
+    pub cli_path: Option<String>,
+    pub model: Option<String>,
+}
+
+/// The profile chosen for a workspace or agent
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProfileSelection {
+    /// Chosen for this workspace or agent itself
+    pub profile_id: Option<String>,
+    /// What its agents run with, after falling back from agent to workspace;
+    /// None means the default account
+    pub effective: Option<ClaudeProfile>,
+}
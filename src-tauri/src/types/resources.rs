@@ -0,0 +1,28 @@
+//! Agent process resource type definitions
+
+use serde::{Deserialize, Serialize};
+
+/// Memory and CPU of an agent's process at one sample
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AgentResources {
+    pub agent_id: String,
+    pub pid: u32,
+    /// Resident memory in bytes
+    pub memory_bytes: u64,
+    /// CPU use since the previous sample, in percent of one core
+    pub cpu_percent: f64,
+    pub memory_exceeded: bool,
+    /// CPU use stayed above the limit for several samples in a row
+    pub cpu_exceeded: bool,
+    pub sampled_at: String,
+}
+
+/// Resource use past which an agent is warned about; no limit when unset
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResourceLimits {
+    pub memory_mb: Option<u64>,
+    /// Percent of one core, so above 100 for several busy cores
+    pub cpu_percent: Option<f64>,
+}
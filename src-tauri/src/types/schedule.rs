@@ -0,0 +1,115 @@
+//! Agent schedule type definitions
+
+use serde::{Deserialize, Serialize};
+
+use super::{AgentMode, Permission};
+
+/// Database row representation (snake_case fields)
+#[derive(Debug, Clone)]
+pub struct ScheduleRow {
+    pub id: String,
+    pub name: String,
+    pub cron: String,
+    pub worktree_id: String,
+    pub mode: String,
+    pub permissions: String, // JSON array
+    pub prompt: String,
+    pub paused: bool,
+    pub next_run_at: Option<String>,
+    pub last_run_at: Option<String>,
+    pub last_agent_id: Option<String>,
+    pub last_error: Option<String>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+/// A recurring agent run: at each time the cron expression matches, a new
+/// agent is created in the worktree and started with the prompt
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Schedule {
+    pub id: String,
+    /// Also the name of each agent the schedule creates
+    pub name: String,
+    /// Five fields: minute, hour, day of month, month, day of week; in local time
+    pub cron: String,
+    pub worktree_id: String,
+    pub mode: AgentMode,
+    pub permissions: Vec<Permission>,
+    pub prompt: String,
+    pub paused: bool,
+    /// None while paused
+    pub next_run_at: Option<String>,
+    pub last_run_at: Option<String>,
+    pub last_agent_id: Option<String>,
+    /// Why the last run failed to start its agent
+    pub last_error: Option<String>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+impl From<ScheduleRow> for Schedule {
+    fn from(row: ScheduleRow) -> Self {
+        Schedule {
+            id: row.id,
+            name: row.name,
+            cron: row.cron,
+            worktree_id: row.worktree_id,
+            mode: AgentMode::parse(&row.mode),
+            permissions: serde_json::from_str(&row.permissions)
+                .unwrap_or_else(|_| vec![Permission::Read]),
+            prompt: row.prompt,
+            paused: row.paused,
+            next_run_at: row.next_run_at,
+            last_run_at: row.last_run_at,
+            last_agent_id: row.last_agent_id,
+            last_error: row.last_error,
+            created_at: row.created_at,
+            updated_at: row.updated_at,
+        }
+    }
+}
+
+/// Input for creating a schedule
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateScheduleInput {
+    pub name: String,
+    pub cron: String,
+    pub worktree_id: String,
+    pub mode: Option<AgentMode>,
+    pub permissions: Option<Vec<Permission>>,
+    pub prompt: String,
+}
+
+/// Response for schedule list
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScheduleListResponse {
+    pub schedules: Vec<Schedule>,
+}
+
+/// Daily window, in local time, during which agents are not started
+/// automatically. Wraps past midnight when `end` is before `start`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QuietHours {
+    /// "HH:MM", inclusive
+    pub start: String,
+    /// "HH:MM", exclusive
+    pub end: String,
+}
+
+/// Whether the scheduler is starting agents right now
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SchedulerStatus {
+    pub quiet_hours: Option<QuietHours>,
+    /// Start agents even during quiet hours
+    pub quiet_hours_override: bool,
+    pub in_quiet_hours: bool,
+    /// Due schedules wait until quiet hours end
+    pub deferring: bool,
+    /// The earliest next run of an unpaused schedule
+    pub next_run_at: Option<String>,
+}
@@ -0,0 +1,80 @@
+//! Agent session snapshot type definitions
+
+use serde::{Deserialize, Serialize};
+
+/// Database row representation of `agent_sessions` (snake_case fields)
+#[derive(Debug, Clone)]
+pub struct AgentSessionRow {
+    pub id: String,
+    pub agent_id: String,
+    pub session_data: String,             // JSON SessionData
+    pub context_snapshot: Option<String>, // JSON array of SnapshotMessage
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+/// Session metadata stored in `agent_sessions.session_data`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionData {
+    pub session_id: Option<String>,
+    pub context_level: i32,
+    pub worktree_id: String,
+    pub branch: Option<String>,
+    pub head_commit: Option<String>,
+}
+
+/// A message captured in a snapshot
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SnapshotMessage {
+    pub role: String,
+    pub content: String,
+    pub created_at: String,
+}
+
+/// Point-in-time copy of an agent's session an agent can be forked from
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AgentSnapshot {
+    pub id: String,
+    pub agent_id: String,
+    pub session_id: Option<String>,
+    pub context_level: i32,
+    pub worktree_id: String,
+    pub branch: Option<String>,
+    /// Commit checked out in the agent's worktree when the snapshot was taken
+    pub head_commit: Option<String>,
+    /// Most recent messages, oldest first
+    pub messages: Vec<SnapshotMessage>,
+    pub created_at: String,
+}
+
+impl From<AgentSessionRow> for AgentSnapshot {
+    fn from(row: AgentSessionRow) -> Self {
+        let data: SessionData = serde_json::from_str(&row.session_data).unwrap_or_default();
+        let messages = row
+            .context_snapshot
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default();
+
+        AgentSnapshot {
+            id: row.id,
+            agent_id: row.agent_id,
+            session_id: data.session_id,
+            context_level: data.context_level,
+            worktree_id: data.worktree_id,
+            branch: data.branch,
+            head_commit: data.head_commit,
+            messages,
+            created_at: row.created_at,
+        }
+    }
+}
+
+/// Response for snapshot list
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AgentSnapshotListResponse {
+    pub snapshots: Vec<AgentSnapshot>,
+}
@@ -0,0 +1,29 @@
+//! Settings type definitions
+
+use serde::{Deserialize, Serialize};
+
+/// Remote access configuration
+///
+/// Changes take effect the next time the app starts.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RemoteAccessSettings {
+    pub enabled: bool,
+    pub bind_address: String,
+    /// Whether remote clients may start/stop agents and send terminal input
+    pub allow_remote_control: bool,
+    /// Token remote clients must send as `Authorization: Bearer <token>` or `?token=`
+    pub access_token: String,
+    /// Self-signed certificate served by the remote listener, once generated
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub certificate_path: Option<String>,
+}
+
+/// Input for updating remote access settings
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateRemoteAccessInput {
+    pub enabled: Option<bool>,
+    pub bind_address: Option<String>,
+    pub allow_remote_control: Option<bool>,
+}
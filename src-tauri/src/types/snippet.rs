@@ -0,0 +1,103 @@
+//! Snippet type definitions
+
+use serde::{Deserialize, Serialize};
+
+/// Where a snippet is offered
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SnippetScope {
+    #[default]
+    Global,
+    Workspace,
+    Worktree,
+}
+
+impl SnippetScope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SnippetScope::Global => "global",
+            SnippetScope::Workspace => "workspace",
+            SnippetScope::Worktree => "worktree",
+        }
+    }
+
+    pub fn parse(s: &str) -> Self {
+        match s.to_lowercase().as_str() {
+            "workspace" => SnippetScope::Workspace,
+            "worktree" => SnippetScope::Worktree,
+            _ => SnippetScope::Global,
+        }
+    }
+}
+
+/// Database row representation (snake_case fields)
+#[derive(Debug, Clone)]
+pub struct SnippetRow {
+    pub id: String,
+    pub name: String,
+    pub body: String,
+    pub scope: String,
+    pub workspace_id: Option<String>,
+    pub worktree_id: Option<String>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+/// A canned prompt; `{{name}}` placeholders in the body are filled in when it is sent
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Snippet {
+    pub id: String,
+    pub name: String,
+    pub body: String,
+    pub scope: SnippetScope,
+    /// Set for workspace and worktree snippets
+    pub workspace_id: Option<String>,
+    /// Set for worktree snippets
+    pub worktree_id: Option<String>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+impl From<SnippetRow> for Snippet {
+    fn from(row: SnippetRow) -> Self {
+        Snippet {
+            id: row.id,
+            name: row.name,
+            body: row.body,
+            scope: SnippetScope::parse(&row.scope),
+            workspace_id: row.workspace_id,
+            worktree_id: row.worktree_id,
+            created_at: row.created_at,
+            updated_at: row.updated_at,
+        }
+    }
+}
+
+/// Input for creating a snippet
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateSnippetInput {
+    pub name: String,
+    pub body: String,
+    pub scope: Option<SnippetScope>,
+    /// Required for workspace snippets
+    pub workspace_id: Option<String>,
+    /// Required for worktree snippets
+    pub worktree_id: Option<String>,
+}
+
+/// Input for updating a snippet
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateSnippetInput {
+    pub name: Option<String>,
+    pub body: Option<String>,
+}
+
+/// Response for snippet list
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SnippetListResponse {
+    pub snippets: Vec<Snippet>,
+}
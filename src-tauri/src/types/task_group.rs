@@ -0,0 +1,92 @@
+//! Task group type definitions
+
+use serde::{Deserialize, Serialize};
+
+use super::{Agent, BranchDiff, Worktree};
+
+/// Agents launched on the same prompt in separate worktrees
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskGroup {
+    pub id: String,
+    pub workspace_id: String,
+    pub prompt: String,
+    /// Branch every attempt was forked from
+    pub base_branch: String,
+    /// Attempt that was kept when the group was resolved
+    pub winner_agent_id: Option<String>,
+    pub resolved_at: Option<String>,
+    pub created_at: String,
+}
+
+/// One attempt of a task group
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskGroupMember {
+    pub agent: Agent,
+    /// None once the attempt has been archived or its worktree removed
+    pub worktree: Option<Worktree>,
+}
+
+/// How many attempts are in each status
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskGroupStatusCounts {
+    pub running: usize,
+    pub waiting: usize,
+    pub idle: usize,
+    pub error: usize,
+}
+
+/// A task group with its attempts and their aggregate status
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskGroupDetails {
+    #[serde(flatten)]
+    pub group: TaskGroup,
+    pub members: Vec<TaskGroupMember>,
+    pub status_counts: TaskGroupStatusCounts,
+    /// No attempt is still running or waiting for input
+    pub is_complete: bool,
+}
+
+/// One attempt's changes relative to the group's base branch
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AttemptDiff {
+    pub agent_id: String,
+    pub agent_name: String,
+    pub worktree_id: String,
+    pub branch: Option<String>,
+    /// None once the attempt's worktree has been removed
+    pub diff: Option<BranchDiff>,
+}
+
+/// Side-by-side changes of every attempt in a task group
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskGroupComparison {
+    pub task_group_id: String,
+    pub base_branch: String,
+    pub attempts: Vec<AttemptDiff>,
+}
+
+/// Input for keeping one attempt of a task group and discarding the rest
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResolveTaskGroupInput {
+    pub winner_agent_id: String,
+    /// Merge the winner's branch into the base branch instead of only keeping it
+    pub merge: Option<bool>,
+    /// Discard uncommitted changes in losing worktrees
+    pub force: Option<bool>,
+}
+
+/// Input for launching a task on several agents at once
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FanOutTaskInput {
+    pub workspace_id: String,
+    pub prompt: String,
+    pub count: usize,
+}
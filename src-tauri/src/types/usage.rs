@@ -100,6 +100,102 @@ pub struct UsageLimits {
     pub daily_request_limit: Option<i64>,
 }
 
+/// Usage budget set by the user; every threshold is optional
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BudgetThresholds {
+    pub daily_token_limit: Option<i64>,
+    pub weekly_token_limit: Option<i64>,
+    /// Percent of Claude's five-hour limit
+    pub daily_utilization_limit: Option<f64>,
+    /// Percent of Claude's seven-day limit
+    pub weekly_utilization_limit: Option<f64>,
+}
+
+/// Refuse to start agents while Claude's utilization is past a cutoff
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageEnforcement {
+    pub enabled: bool,
+    /// Percent of Claude's five-hour or seven-day limit
+    pub cutoff: f64,
+}
+
+impl Default for UsageEnforcement {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            cutoff: 95.0,
+        }
+    }
+}
+
+/// Usage figure a budget threshold applies to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BudgetMetric {
+    DailyTokens,
+    WeeklyTokens,
+    DailyUtilization,
+    WeeklyUtilization,
+}
+
+impl BudgetMetric {
+    pub fn label(&self) -> &'static str {
+        match self {
+            BudgetMetric::DailyTokens => "Daily tokens",
+            BudgetMetric::WeeklyTokens => "Weekly tokens",
+            BudgetMetric::DailyUtilization => "Daily Claude usage",
+            BudgetMetric::WeeklyUtilization => "Weekly Claude usage",
+        }
+    }
+}
+
+/// How close usage is to its threshold
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BudgetLevel {
+    Ok,
+    /// At least 80% of the threshold
+    Warning,
+    /// At or over the threshold
+    Exceeded,
+}
+
+/// Current usage against one threshold
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BudgetMetricStatus {
+    pub metric: BudgetMetric,
+    pub used: f64,
+    pub limit: f64,
+    pub percent: f64,
+    pub level: BudgetLevel,
+}
+
+/// Current usage against every configured threshold
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BudgetStatus {
+    pub thresholds: BudgetThresholds,
+    pub metrics: Vec<BudgetMetricStatus>,
+    /// Whether Claude's utilization figures could be fetched
+    pub claude_usage_available: bool,
+    pub checked_at: String,
+}
+
+/// Raised when usage crosses 80% or 100% of a threshold
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BudgetAlert {
+    pub metric: BudgetMetric,
+    pub level: BudgetLevel,
+    pub used: f64,
+    pub limit: f64,
+    pub percent: f64,
+    pub message: String,
+}
+
 /// Response for usage history
 #[derive(Debug, Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
@@ -162,6 +258,7 @@ pub struct ClaudeCredentials {
 pub struct ClaudeOAuthCredentials {
     pub access_token: String,
     pub refresh_token: Option<String>,
-    pub expires_at: Option<String>,
+    /// Milliseconds since the Unix epoch
+    pub expires_at: Option<i64>,
     pub subscription_type: Option<String>,
 }
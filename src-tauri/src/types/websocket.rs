@@ -2,7 +2,10 @@
 
 use serde::{Deserialize, Serialize};
 
-use super::{AgentStatus, UsageStats};
+use super::{
+    AgentResources, AgentStatus, BudgetAlert, GitStatusInfo, Job, NetworkStatus, UsageStats,
+    WorkspaceRefreshJob, WorkspaceWithDetails,
+};
 
 /// Incoming WebSocket message types (client -> server)
 #[derive(Debug, Clone, Deserialize)]
@@ -16,6 +19,10 @@ pub enum WsClientMessage {
     SubscribeWorkspace { payload: SubscribeWorkspacePayload },
     #[serde(rename = "unsubscribe:workspace")]
     UnsubscribeWorkspace { payload: UnsubscribeWorkspacePayload },
+    #[serde(rename = "agent:start")]
+    StartAgent { payload: StartAgentPayload },
+    #[serde(rename = "agent:stop")]
+    StopAgent { payload: StopAgentPayload },
     Ping,
 }
 
@@ -33,10 +40,34 @@ pub enum WsServerMessage {
     AgentError(AgentErrorPayload),
     #[serde(rename = "agent:terminated")]
     AgentTerminated(AgentTerminatedPayload),
+    #[serde(rename = "agent:snapshot")]
+    AgentSnapshot(AgentSnapshotPayload),
+    #[serde(rename = "agent:idle_warning")]
+    AgentIdleWarning(AgentIdleWarningPayload),
+    #[serde(rename = "agent:idle_shutdown")]
+    AgentIdleShutdown(AgentIdleShutdownPayload),
+    #[serde(rename = "agent:resources")]
+    AgentResources(AgentResourcesPayload),
     #[serde(rename = "workspace:updated")]
     WorkspaceUpdated(WorkspaceUpdatedPayload),
     #[serde(rename = "usage:updated")]
     UsageUpdated(UsageUpdatedPayload),
+    #[serde(rename = "usage:budget")]
+    UsageBudget(UsageBudgetPayload),
+    #[serde(rename = "network:status")]
+    NetworkStatus(NetworkStatusPayload),
+    #[serde(rename = "worktree:git_status")]
+    WorktreeGitStatus(WorktreeGitStatusPayload),
+    #[serde(rename = "workspace:refreshed")]
+    WorkspaceRefreshed(WorkspaceRefreshedPayload),
+    #[serde(rename = "job:updated")]
+    JobUpdated(JobUpdatedPayload),
+    #[serde(rename = "entity:changed")]
+    EntityChanged(EntityChangedPayload),
+    /// Events were dropped; refetch agents and entities instead of relying
+    /// on incremental updates
+    #[serde(rename = "resync_required")]
+    ResyncRequired(ResyncRequiredPayload),
     Pong,
 }
 
@@ -66,15 +97,36 @@ pub struct UnsubscribeWorkspacePayload {
     pub workspace_id: String,
 }
 
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StartAgentPayload {
+    pub agent_id: String,
+    #[serde(default)]
+    pub initial_prompt: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StopAgentPayload {
+    pub agent_id: String,
+    #[serde(default)]
+    pub force: bool,
+}
+
 // Server -> Client payloads
 
 #[derive(Debug, Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct AgentOutputPayload {
     pub agent_id: String,
+    /// Message the content belongs to; deltas of one message share it
+    pub message_id: String,
+    /// Text added to the message since its last delta
     pub content: String,
     pub is_complete: bool,
     pub timestamp: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seq: Option<u64>,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -85,6 +137,9 @@ pub struct AgentStatusPayload {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub reason: Option<String>,
     pub timestamp: String,
+    /// Emission order of the process event; absent for replies to requests
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seq: Option<u64>,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -93,6 +148,8 @@ pub struct AgentContextPayload {
     pub agent_id: String,
     pub level: i32,
     pub timestamp: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seq: Option<u64>,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -101,6 +158,8 @@ pub struct AgentErrorPayload {
     pub agent_id: String,
     pub error: String,
     pub timestamp: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seq: Option<u64>,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -112,6 +171,54 @@ pub struct AgentTerminatedPayload {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub signal: Option<String>,
     pub timestamp: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seq: Option<u64>,
+}
+
+/// An idle agent is about to be stopped unless it gets input
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AgentIdleWarningPayload {
+    pub agent_id: String,
+    pub shutdown_in_secs: u64,
+    pub timestamp: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seq: Option<u64>,
+}
+
+/// An agent is being stopped for sitting idle; starting it again resumes
+/// its session
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AgentIdleShutdownPayload {
+    pub agent_id: String,
+    pub idle_secs: u64,
+    pub timestamp: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seq: Option<u64>,
+}
+
+/// Latest memory and CPU sample of a running agent's process
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AgentResourcesPayload {
+    pub resources: AgentResources,
+    pub timestamp: String,
+}
+
+/// Current state of an agent, sent as soon as a client subscribes to it
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AgentSnapshotPayload {
+    pub agent_id: String,
+    pub status: AgentStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    pub context_level: i32,
+    pub is_running: bool,
+    /// Last output of a running agent, otherwise its last recorded change
+    pub last_activity_at: String,
+    pub timestamp: String,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -128,3 +235,140 @@ pub struct UsageUpdatedPayload {
     pub usage: UsageStats,
     pub timestamp: String,
 }
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageBudgetPayload {
+    pub alert: BudgetAlert,
+    pub timestamp: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NetworkStatusPayload {
+    pub status: NetworkStatus,
+    pub timestamp: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WorktreeGitStatusPayload {
+    pub workspace_id: String,
+    pub worktree_id: String,
+    pub status: GitStatusInfo,
+    pub timestamp: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JobUpdatedPayload {
+    pub job: Job,
+    pub timestamp: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkspaceRefreshedPayload {
+    pub job: WorkspaceRefreshJob,
+    /// The refreshed workspace; absent when the job failed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub workspace: Option<WorkspaceWithDetails>,
+    pub timestamp: String,
+}
+
+/// Kind of entity in an `entity:changed` message
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EntityKind {
+    Workspace,
+    Worktree,
+    Agent,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeAction {
+    Created,
+    Updated,
+    Deleted,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EntityChangedPayload {
+    pub entity: EntityKind,
+    pub action: ChangeAction,
+    pub id: String,
+    /// The entity as serialized by its commands; absent for deletions
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<serde_json::Value>,
+    pub timestamp: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResyncRequiredPayload {
+    /// How many events the server dropped
+    pub skipped: u64,
+    pub timestamp: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_start_agent_message_deserialize() {
+        let json = r#"{
+            "type": "agent:start",
+            "payload": { "agentId": "ag_1", "initialPrompt": "run the tests" }
+        }"#;
+        match serde_json::from_str::<WsClientMessage>(json).unwrap() {
+            WsClientMessage::StartAgent { payload } => {
+                assert_eq!(payload.agent_id, "ag_1");
+                assert_eq!(payload.initial_prompt.as_deref(), Some("run the tests"));
+            }
+            other => panic!("Unexpected message: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_stop_agent_message_defaults_to_graceful() {
+        let json = r#"{ "type": "agent:stop", "payload": { "agentId": "ag_1" } }"#;
+        match serde_json::from_str::<WsClientMessage>(json).unwrap() {
+            WsClientMessage::StopAgent { payload } => {
+                assert_eq!(payload.agent_id, "ag_1");
+                assert!(!payload.force);
+            }
+            other => panic!("Unexpected message: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_entity_changed_message_serialize() {
+        let msg = WsServerMessage::EntityChanged(EntityChangedPayload {
+            entity: EntityKind::Worktree,
+            action: ChangeAction::Deleted,
+            id: "wt_1".to_string(),
+            data: None,
+            timestamp: "2024-01-01T00:00:00Z".to_string(),
+        });
+        let json = serde_json::to_value(&msg).unwrap();
+        assert_eq!(json["type"], "entity:changed");
+        assert_eq!(json["entity"], "worktree");
+        assert_eq!(json["action"], "deleted");
+        assert_eq!(json["id"], "wt_1");
+        assert!(json.get("data").is_none());
+    }
+
+    #[test]
+    fn test_resync_required_message_serialize() {
+        let msg = WsServerMessage::ResyncRequired(ResyncRequiredPayload {
+            skipped: 12,
+            timestamp: "2024-01-01T00:00:00Z".to_string(),
+        });
+        let json = serde_json::to_value(&msg).unwrap();
+        assert_eq!(json["type"], "resync_required");
+        assert_eq!(json["skipped"], 12);
+    }
+}
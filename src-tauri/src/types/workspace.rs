@@ -76,3 +76,37 @@ pub struct CreateWorkspaceInput {
 pub struct WorkspaceListResponse {
     pub workspaces: Vec<Workspace>,
 }
+
+/// Concurrency limit for a workspace's agents
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkspaceAgentLimit {
+    pub workspace_id: String,
+    /// None when any number of agents may run at once
+    pub max_concurrent_agents: Option<usize>,
+    /// Agents currently running across the workspace's worktrees
+    pub running: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RefreshJobStatus {
+    Queued,
+    Running,
+    Completed,
+    Failed,
+}
+
+/// A queued re-sync of a workspace with git
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkspaceRefreshJob {
+    pub id: String,
+    pub workspace_id: String,
+    pub status: RefreshJobStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub queued_at: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub finished_at: Option<String>,
+}
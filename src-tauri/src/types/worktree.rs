@@ -43,6 +43,11 @@ pub struct WorktreeRow {
     pub is_main: bool,
     pub created_at: String,
     pub updated_at: String,
+    pub pr_url: Option<String>,
+    pub sparse_patterns: Option<String>, // JSON array
+    pub archived_at: Option<String>,
+    pub pinned: bool,
+    pub missing_since: Option<String>,
 }
 
 /// API representation for worktree
@@ -59,6 +64,23 @@ pub struct Worktree {
     pub is_main: bool,
     pub created_at: String,
     pub updated_at: String,
+    /// Pull request opened from this worktree's branch
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pr_url: Option<String>,
+    /// Directories checked out when the worktree is sparse; None for a full checkout
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sparse_patterns: Option<Vec<String>>,
+    /// When the worktree was archived; archived worktrees keep their checkout
+    /// and agents but are left out of the workspace until restored
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub archived_at: Option<String>,
+    /// Pinned worktrees are kept by workspace scans even once git forgets them
+    #[serde(default)]
+    pub pinned: bool,
+    /// When a scan first found the checkout unavailable, e.g. on an unmounted
+    /// disk; None while it is there
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub missing_since: Option<String>,
 }
 
 impl From<WorktreeRow> for Worktree {
@@ -74,17 +96,61 @@ impl From<WorktreeRow> for Worktree {
             is_main: row.is_main,
             created_at: row.created_at,
             updated_at: row.updated_at,
+            pr_url: row.pr_url,
+            sparse_patterns: row
+                .sparse_patterns
+                .and_then(|patterns| serde_json::from_str(&patterns).ok()),
+            archived_at: row.archived_at,
+            pinned: row.pinned,
+            missing_since: row.missing_since,
         }
     }
 }
 
+/// A running agent that keeps a worktree from being deleted or switched
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BusyAgent {
+    pub id: String,
+    pub name: String,
+}
+
 /// Input for creating a new worktree
+/// Whether a worktree's checkout can be worked in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WorktreeState {
+    Ok,
+    /// The directory is gone, e.g. on a disk that isn't mounted
+    Missing,
+    /// The directory is there but git no longer knows it as a worktree, or
+    /// its status can't be read
+    Broken,
+}
+
+/// What a health check found on disk and in git for one worktree
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WorktreeHealth {
+    pub worktree_id: String,
+    pub path: String,
+    pub state: WorktreeState,
+    pub exists: bool,
+    /// Listed by `git worktree list`
+    pub registered: bool,
+    pub locked: bool,
+    pub prunable: bool,
+    /// Uncommitted changes; None when the status couldn't be read
+    pub dirty: Option<bool>,
+}
+
 #[derive(Debug, Clone, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct CreateWorktreeInput {
     pub workspace_id: String,
     pub name: String,
-    pub branch: String,
+    /// Generated from the workspace's branch template when absent
+    pub branch: Option<String>,
     pub path: Option<String>,
     pub create_branch: Option<bool>,
 }
@@ -96,6 +162,10 @@ pub struct UpdateWorktreeInput {
     pub name: Option<String>,
     pub sort_mode: Option<SortMode>,
     pub display_order: Option<i32>,
+    /// The `updated_at` the worktree was loaded with; the update fails with a
+    /// conflict if it changed since
+    #[serde(default)]
+    pub expected_updated_at: Option<String>,
 }
 
 /// Input for checking out a branch
@@ -104,6 +174,10 @@ pub struct UpdateWorktreeInput {
 pub struct CheckoutBranchInput {
     pub branch: String,
     pub create: Option<bool>,
+    /// Stash uncommitted changes first when the worktree is dirty
+    pub stash: Option<bool>,
+    /// Check out even while agents are running in the worktree
+    pub force: Option<bool>,
 }
 
 /// Input for reordering worktrees
@@ -130,13 +204,168 @@ pub struct BranchInfo {
 }
 
 /// Git status information
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct GitStatusInfo {
     pub is_clean: bool,
+    /// Commits ahead of the upstream tracking branch
     pub ahead: i32,
+    /// Commits behind the upstream tracking branch
     pub behind: i32,
     pub modified: Vec<String>,
     pub staged: Vec<String>,
     pub untracked: Vec<String>,
+    /// Branch compared against for `ahead_of_base`/`behind_base`; None when
+    /// it doesn't exist locally or on origin
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base_branch: Option<String>,
+    #[serde(default)]
+    pub ahead_of_base: i32,
+    #[serde(default)]
+    pub behind_base: i32,
+    /// Changed paths before pagination; the lists above hold one page of them
+    #[serde(default)]
+    pub total_entries: usize,
+    #[serde(default)]
+    pub has_more: bool,
+}
+
+/// Limits for collecting git status in large repositories
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GitStatusOptions {
+    /// Changed paths to return after `offset`; all of them when absent
+    pub max_entries: Option<usize>,
+    #[serde(default)]
+    pub offset: usize,
+    /// Leave out paths matching any of these globs, where `*` also matches `/`
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    /// Don't scan for untracked files; they then don't count against `is_clean`
+    #[serde(default)]
+    pub skip_untracked: bool,
+}
+
+/// A commit in a worktree's log
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommitInfo {
+    pub hash: String,
+    pub author: String,
+    pub email: String,
+    /// Author date as RFC 3339
+    pub date: String,
+    pub message: String,
+    /// Files changed relative to the first parent
+    pub files_changed: usize,
+}
+
+/// A stash entry; index 0 is the most recent
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StashEntry {
+    pub index: usize,
+    pub message: String,
+    pub oid: String,
+}
+
+/// Input for stashing a worktree's changes
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StashSaveInput {
+    pub message: Option<String>,
+    /// Stash untracked files too; defaults to true
+    pub include_untracked: Option<bool>,
+}
+
+/// Lines changed in a single file of a diff
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileDiffStat {
+    pub path: String,
+    /// One of "added", "deleted", "renamed" or "modified"
+    pub status: String,
+    pub insertions: usize,
+    pub deletions: usize,
+}
+
+/// Changes in a worktree since it diverged from a base branch
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BranchDiff {
+    pub files: Vec<FileDiffStat>,
+    pub insertions: usize,
+    pub deletions: usize,
+    /// Unified diff of all files, including uncommitted and untracked ones
+    pub patch: String,
+}
+
+/// Pull request opened from a worktree's branch
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PullRequestInfo {
+    pub worktree_id: String,
+    pub url: String,
+    pub head_branch: String,
+    /// Requested base branch; the repository default when absent
+    pub base_branch: Option<String>,
+}
+
+/// Files changed in two worktrees at the same time
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WorktreeConflict {
+    pub worktree_id: String,
+    pub other_worktree_id: String,
+    pub paths: Vec<String>,
+}
+
+/// Overlapping edits across the worktrees of a workspace
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConflictReport {
+    pub workspace_id: String,
+    /// Branch checked out in the main worktree, which the others are compared against
+    pub base_branch: String,
+    pub conflicts: Vec<WorktreeConflict>,
+}
+
+/// How shared files reach a new worktree
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SharedFileMode {
+    #[default]
+    Copy,
+    Symlink,
+}
+
+/// Untracked files, such as `.env`, that new worktrees take from the main worktree
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SharedFilesConfig {
+    /// Paths relative to the worktree root; `*` matches within the last segment
+    pub paths: Vec<String>,
+    #[serde(default)]
+    pub mode: SharedFileMode,
+}
+
+/// What creating a worktree would copy from the main worktree right now
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SharedFilesPreview {
+    pub mode: SharedFileMode,
+    /// Files and directories that would be copied or linked
+    pub files: Vec<String>,
+    /// Configured paths that match nothing in the main worktree
+    pub missing: Vec<String>,
+}
+
+/// Template for branches generated for new worktrees
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BranchTemplate {
+    /// Uses `{slug}` (the worktree name), `{date}` and `{workspace}`
+    pub template: String,
+    /// What the template gives today for a worktree named "My feature"
+    pub example: String,
 }
@@ -0,0 +1,169 @@
+//! Turning terminal output into plain text
+
+const ESC: char = '\x1b';
+const BEL: char = '\x07';
+// 8-bit C1 controls
+const DCS: char = '\u{90}';
+const SOS: char = '\u{98}';
+const CSI: char = '\u{9b}';
+const ST: char = '\u{9c}';
+const OSC: char = '\u{9d}';
+const PM: char = '\u{9e}';
+const APC: char = '\u{9f}';
+
+/// Where the parser is within an escape sequence
+#[derive(Clone, Copy)]
+enum State {
+    Ground,
+    /// After ESC
+    Escape,
+    /// After ESC and intermediate bytes, e.g. `ESC ( B`
+    EscapeIntermediate,
+    /// Parameter and intermediate bytes of a CSI sequence
+    Csi,
+    /// Body of an OSC, DCS, SOS, PM or APC string
+    String,
+    /// After ESC inside a string, which ends it when followed by `\`
+    StringEscape,
+}
+
+/// Strip ANSI escape sequences from a string
+///
+/// A state machine after the VT500 parser, covering CSI sequences,
+/// OSC/DCS/SOS/PM/APC strings terminated by BEL or ST, two-character and
+/// character set escapes, and their 8-bit C1 forms. Text, including
+/// multi-byte characters, passes through untouched.
+pub fn strip_ansi_escapes(s: &str) -> String {
+    let mut result = String::with_capacity(s.len());
+    let mut state = State::Ground;
+
+    for ch in s.chars() {
+        // CAN and SUB abort any sequence in progress
+        if matches!(ch, '\x18' | '\x1a') {
+            state = State::Ground;
+            continue;
+        }
+
+        state = match state {
+            State::Ground => match ch {
+                ESC => State::Escape,
+                CSI => State::Csi,
+                OSC | DCS | SOS | PM | APC => State::String,
+                '\u{80}'..='\u{9f}' => State::Ground,
+                _ => {
+                    result.push(ch);
+                    State::Ground
+                }
+            },
+            State::Escape => escape(ch),
+            State::EscapeIntermediate => match ch {
+                ' '..='/' => State::EscapeIntermediate,
+                ESC => State::Escape,
+                _ => State::Ground,
+            },
+            State::Csi => match ch {
+                '@'..='~' => State::Ground,
+                ESC => State::Escape,
+                _ => State::Csi,
+            },
+            State::String => match ch {
+                BEL | ST => State::Ground,
+                ESC => State::StringEscape,
+                _ => State::String,
+            },
+            State::StringEscape => match ch {
+                '\\' => State::Ground,
+                // An unterminated string followed by a new sequence
+                _ => escape(ch),
+            },
+        };
+    }
+    result
+}
+
+/// State after the character following ESC
+fn escape(ch: char) -> State {
+    match ch {
+        '[' => State::Csi,
+        ']' | 'P' | 'X' | '^' | '_' => State::String,
+        ' '..='/' => State::EscapeIntermediate,
+        ESC => State::Escape,
+        _ => State::Ground,
+    }
+}
+
+/// The part of a line still visible after carriage returns, since the
+/// terminal overwrites whatever came before them
+pub fn after_carriage_return(line: &str) -> &str {
+    line.trim_end_matches('\r')
+        .rsplit('\r')
+        .next()
+        .unwrap_or_default()
+}
+
+/// The last `count` lines of raw terminal output as plain text
+pub fn tail_lines(output: &[u8], count: usize) -> Vec<String> {
+    let text = strip_ansi_escapes(&String::from_utf8_lossy(output));
+    let lines: Vec<&str> = text.trim_end_matches(['\r', '\n']).split('\n').collect();
+    lines[lines.len().saturating_sub(count)..]
+        .iter()
+        .map(|line| after_carriage_return(line).to_string())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// (input, expected) pairs, many taken from Claude CLI and shell output
+    const CORPUS: &[(&str, &str)] = &[
+        ("no escapes ✓", "no escapes ✓"),
+        ("\x1b[1;32mok\x1b[0m", "ok"),
+        ("\x1b[38;2;215;119;87m✻\x1b[39m Thinking…", "✻ Thinking…"),
+        ("\x1b[?2004h\x1b[?25l> \x1b[?25h", "> "),
+        ("\x1b[2K\x1b[1A\x1b[Gredrawn", "redrawn"),
+        ("\x1b]0;claude\x07title", "title"),
+        ("\x1b]0;Do you want?\x1b\\Working", "Working"),
+        (
+            "\x1b]8;;https://example.com\x1b\\link\x1b]8;;\x1b\\",
+            "link",
+        ),
+        ("\x1b]133;A\x07$ ls\x1b]133;B\x07", "$ ls"),
+        ("\x1bP+q544e\x1b\\after", "after"),
+        ("\x1b_Gi=1;AAAA\x1b\\image", "image"),
+        ("\x1b(Bplain\x1b=text", "plaintext"),
+        ("\x1b#8\x1b7saved\x1b8", "saved"),
+        ("\u{9b}31mred\u{9b}0m", "red"),
+        ("\u{9d}0;title\u{9c}text", "text"),
+        ("\u{85}line", "line"),
+        ("\x1b]0;unterminated\x1b[31mred", "red"),
+        ("\x1b[31\x18cancelled", "cancelled"),
+        ("日本語 \x1b[1m🚀\x1b[0m", "日本語 🚀"),
+        ("tab\tand\r\nnewline", "tab\tand\r\nnewline"),
+        ("trailing escape\x1b", "trailing escape"),
+    ];
+
+    #[test]
+    fn test_strip_ansi_escapes_corpus() {
+        for (input, expected) in CORPUS {
+            assert_eq!(strip_ansi_escapes(input), *expected, "input: {:?}", input);
+        }
+    }
+
+    #[test]
+    fn test_strip_ansi_escapes_is_idempotent() {
+        for (input, _) in CORPUS {
+            let once = strip_ansi_escapes(input);
+            assert_eq!(strip_ansi_escapes(&once), once);
+        }
+    }
+
+    #[test]
+    fn test_tail_lines() {
+        let output = b"one\r\n\x1b[2Ktwo\r\nLoading 10%\rLoading 100%\r\n";
+
+        assert_eq!(tail_lines(output, 2), vec!["two", "Loading 100%"]);
+        assert_eq!(tail_lines(output, 10).len(), 3);
+        assert!(tail_lines(output, 0).is_empty());
+    }
+}
@@ -0,0 +1,69 @@
+//! IDs for database records
+//!
+//! An ID is a short prefix naming the kind of record, an underscore and a
+//! ULID: the milliseconds since the epoch in 48 bits, then 80 bits taken from
+//! a random UUID, written in Crockford's base32. IDs made in the same
+//! millisecond differ in their random part, and IDs sort by when they were
+//! made.
+
+/// Crockford's base32 digits, which leave out I, L, O and U
+const ALPHABET: &[u8; 32] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+/// Characters in a ULID
+const ULID_LENGTH: usize = 26;
+
+/// A new ID for a record of the kind `prefix` names, e.g. `ag_01J9Z...`
+pub fn new_id(prefix: &str) -> String {
+    format!("{}_{}", prefix, ulid())
+}
+
+/// A new ULID
+pub fn ulid() -> String {
+    let millis = chrono::Utc::now().timestamp_millis().max(0) as u128 & ((1 << 48) - 1);
+    let random = uuid::Uuid::new_v4().as_u128() & ((1 << 80) - 1);
+    encode((millis << 80) | random)
+}
+
+fn encode(value: u128) -> String {
+    (0..ULID_LENGTH)
+        .rev()
+        .map(|i| ALPHABET[((value >> (i * 5)) & 0x1f) as usize] as char)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_new_id_format() {
+        let id = new_id("ag");
+        let ulid = id.strip_prefix("ag_").unwrap();
+        assert_eq!(ulid.len(), ULID_LENGTH);
+        assert!(ulid.bytes().all(|b| ALPHABET.contains(&b)));
+    }
+
+    #[test]
+    fn test_encode() {
+        assert_eq!(encode(0), "0".repeat(ULID_LENGTH));
+        assert_eq!(
+            encode(u128::MAX),
+            format!("7{}", "Z".repeat(ULID_LENGTH - 1))
+        );
+        assert_eq!(encode(1 << 80), "0000000001".to_string() + &"0".repeat(16));
+    }
+
+    #[test]
+    fn test_ids_are_unique_within_a_millisecond() {
+        let ids: HashSet<String> = (0..10_000).map(|_| new_id("wt")).collect();
+        assert_eq!(ids.len(), 10_000);
+    }
+
+    #[test]
+    fn test_ids_sort_by_creation_time() {
+        let first = new_id("job");
+        std::thread::sleep(std::time::Duration::from_millis(2));
+        let second = new_id("job");
+        assert!(first < second);
+    }
+}
@@ -0,0 +1,8 @@
+//! Helpers shared across services and commands
+
+pub mod ansi;
+pub mod cancel;
+pub mod id;
+pub mod process_tree;
+pub mod redact;
+pub mod transcript;
@@ -0,0 +1,187 @@
+//! Handle on an agent process together with everything it spawned
+//!
+//! Killing only the agent's own process leaves its children, e.g. node or
+//! bash subprocesses of tools, running. On unix the PTY spawns the agent as
+//! the leader of a new session and process group, so signalling the group
+//! reaches the whole tree. On Windows the process is put in a Job Object,
+//! which children join automatically, and the job is terminated instead.
+
+use std::io;
+
+/// The process group (unix) or job (Windows) of a spawned agent
+pub struct ProcessTree {
+    #[cfg(unix)]
+    pgid: Option<i32>,
+    #[cfg(windows)]
+    job: Option<windows::Job>,
+}
+
+impl ProcessTree {
+    /// Take hold of the tree of a process just spawned in a PTY
+    pub fn attach(child: &(dyn portable_pty::Child + Send)) -> Self {
+        #[cfg(unix)]
+        {
+            // The PTY makes the child a session leader, so its PID is the group ID
+            Self {
+                pgid: child
+                    .process_id()
+                    .map(|pid| pid as i32)
+                    .filter(|&pid| pid > 0),
+            }
+        }
+        #[cfg(windows)]
+        {
+            let job = child
+                .as_raw_handle()
+                .and_then(|handle| match windows::Job::assign(handle) {
+                    Ok(job) => Some(job),
+                    Err(e) => {
+                        tracing::warn!("Failed to put agent process in a job object: {}", e);
+                        None
+                    }
+                });
+            Self { job }
+        }
+    }
+
+    /// Interrupt every process in the tree, as Ctrl+C in a terminal would
+    #[cfg(unix)]
+    pub fn interrupt(&self) -> io::Result<()> {
+        self.signal(libc::SIGINT)
+    }
+
+    /// Kill every process in the tree still running
+    pub fn kill(&self) -> io::Result<()> {
+        #[cfg(unix)]
+        {
+            self.signal(libc::SIGKILL)
+        }
+        #[cfg(windows)]
+        {
+            match &self.job {
+                Some(job) => job.terminate(),
+                None => Ok(()),
+            }
+        }
+    }
+
+    #[cfg(unix)]
+    fn signal(&self, signal: i32) -> io::Result<()> {
+        let Some(pgid) = self.pgid else {
+            return Ok(());
+        };
+        if unsafe { libc::killpg(pgid, signal) } == 0 {
+            return Ok(());
+        }
+        match io::Error::last_os_error() {
+            // Everything in the group already exited
+            e if e.raw_os_error() == Some(libc::ESRCH) => Ok(()),
+            e => Err(e),
+        }
+    }
+}
+
+#[cfg(windows)]
+mod windows {
+    use std::io;
+    use std::os::windows::io::RawHandle;
+
+    use windows_sys::Win32::Foundation::{CloseHandle, HANDLE};
+    use windows_sys::Win32::System::JobObjects::{
+        AssignProcessToJobObject, CreateJobObjectW, TerminateJobObject,
+    };
+
+    /// A Job Object holding an agent process and its children
+    pub struct Job(HANDLE);
+
+    // The handle is only used through the thread-safe job object APIs
+    unsafe impl Send for Job {}
+    unsafe impl Sync for Job {}
+
+    impl Job {
+        pub fn assign(process: RawHandle) -> io::Result<Self> {
+            let handle = unsafe { CreateJobObjectW(std::ptr::null(), std::ptr::null()) };
+            if handle.is_null() {
+                return Err(io::Error::last_os_error());
+            }
+            let job = Self(handle);
+            if unsafe { AssignProcessToJobObject(job.0, process) } == 0 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(job)
+        }
+
+        pub fn terminate(&self) -> io::Result<()> {
+            if unsafe { TerminateJobObject(self.0, 1) } == 0 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(())
+        }
+    }
+
+    impl Drop for Job {
+        fn drop(&mut self) {
+            unsafe {
+                CloseHandle(self.0);
+            }
+        }
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use portable_pty::{native_pty_system, CommandBuilder, PtySize};
+    use std::io::{BufRead, BufReader};
+
+    /// Whether a process still runs; zombies waiting to be reaped don't
+    fn is_running(pid: i32) -> bool {
+        let output = std::process::Command::new("ps")
+            .args(["-o", "stat=", "-p", &pid.to_string()])
+            .output()
+            .unwrap();
+        let stat = String::from_utf8_lossy(&output.stdout);
+        !stat.trim().is_empty() && !stat.trim().starts_with('Z')
+    }
+
+    #[test]
+    fn test_kill_reaches_grandchildren() {
+        let pair = native_pty_system()
+            .openpty(PtySize {
+                rows: 24,
+                cols: 80,
+                pixel_width: 0,
+                pixel_height: 0,
+            })
+            .unwrap();
+        let mut cmd = CommandBuilder::new("sh");
+        cmd.args(["-c", "sleep 30 & echo \"bg $!\"; wait"]);
+        let mut child = pair.slave.spawn_command(cmd).unwrap();
+        drop(pair.slave);
+        let tree = ProcessTree::attach(child.as_ref());
+
+        let mut reader = BufReader::new(pair.master.try_clone_reader().unwrap());
+        let mut line = String::new();
+        let grandchild = loop {
+            line.clear();
+            reader.read_line(&mut line).unwrap();
+            if let Some(pid) = line.trim().strip_prefix("bg ") {
+                break pid.parse::<i32>().unwrap();
+            }
+        };
+        assert!(is_running(grandchild));
+
+        tree.kill().unwrap();
+        child.wait().unwrap();
+        for _ in 0..100 {
+            if !is_running(grandchild) {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(20));
+        }
+        assert!(!is_running(grandchild));
+
+        // Killing a tree that is gone is fine
+        tree.kill().unwrap();
+    }
+}
@@ -0,0 +1,238 @@
+//! Reading Claude CLI session transcripts
+//!
+//! The CLI appends every turn of a session to
+//! `~/.claude/projects/<project>/<session_id>.jsonl`, one JSON object per line.
+//! `<project>` is the session's working directory with every character other
+//! than an ASCII letter or digit replaced by `-`.
+
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+
+use crate::types::MessageRole;
+
+/// A message read from a transcript
+#[derive(Debug, Clone, PartialEq)]
+pub struct TranscriptMessage {
+    /// Derived from the transcript, so importing a session twice adds nothing
+    pub id: String,
+    pub role: MessageRole,
+    pub content: String,
+    pub tool_name: Option<String>,
+    pub tool_input: Option<serde_json::Value>,
+    pub timestamp: DateTime<Utc>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Entry {
+    #[serde(rename = "type")]
+    kind: String,
+    uuid: Option<String>,
+    timestamp: Option<DateTime<Utc>>,
+    message: Option<EntryMessage>,
+    /// Injected context such as command caveats, never shown as a turn
+    #[serde(default)]
+    is_meta: bool,
+    /// Turns of sub-agents
+    #[serde(default)]
+    is_sidechain: bool,
+}
+
+/// Any line carrying the session's working directory
+#[derive(Deserialize)]
+struct CwdEntry {
+    cwd: String,
+}
+
+#[derive(Deserialize)]
+struct EntryMessage {
+    content: Content,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Content {
+    Text(String),
+    Blocks(Vec<Block>),
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Block {
+    Text {
+        text: String,
+    },
+    ToolUse {
+        id: String,
+        name: String,
+        input: serde_json::Value,
+    },
+    /// Tool results, thinking and images
+    #[serde(other)]
+    Other,
+}
+
+/// The project directory the CLI keeps the transcripts of sessions run in `cwd` in
+pub fn project_dir_name(cwd: &str) -> String {
+    cwd.chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect()
+}
+
+/// The working directory a transcript's session ran in. Reads only up to the
+/// first line that names it.
+pub fn read_cwd(path: &Path) -> Option<String> {
+    let file = std::fs::File::open(path).ok()?;
+    BufReader::new(file)
+        .lines()
+        .map_while(Result::ok)
+        .find_map(|line| serde_json::from_str::<CwdEntry>(&line).ok())
+        .map(|entry| entry.cwd)
+}
+
+/// Find a session's transcript in any project directory
+pub fn find_transcript(projects_dir: &Path, session_id: &str) -> Option<PathBuf> {
+    if session_id.is_empty() || session_id.contains(['/', '\\', '.']) {
+        return None;
+    }
+    let file_name = format!("{}.jsonl", session_id);
+    std::fs::read_dir(projects_dir)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path().join(&file_name))
+        .find(|path| path.is_file())
+}
+
+/// Read the user and assistant turns of a transcript, oldest first.
+///
+/// Each tool call becomes a tool message of its own. Tool results, thinking,
+/// sub-agent turns and lines that fail to parse are skipped.
+pub fn parse_transcript(contents: &str) -> Vec<TranscriptMessage> {
+    let mut messages = Vec::new();
+    for line in contents.lines() {
+        let Ok(entry) = serde_json::from_str::<Entry>(line) else {
+            continue;
+        };
+        let role = match entry.kind.as_str() {
+            "user" => MessageRole::User,
+            "assistant" => MessageRole::Assistant,
+            _ => continue,
+        };
+        if entry.is_meta || entry.is_sidechain {
+            continue;
+        }
+        let (Some(uuid), Some(timestamp), Some(message)) =
+            (entry.uuid, entry.timestamp, entry.message)
+        else {
+            continue;
+        };
+
+        let mut text = Vec::new();
+        let mut tool_uses = Vec::new();
+        match message.content {
+            Content::Text(content) => text.push(content),
+            Content::Blocks(blocks) => {
+                for block in blocks {
+                    match block {
+                        Block::Text { text: content } => text.push(content),
+                        Block::ToolUse { id, name, input } => tool_uses.push((id, name, input)),
+                        Block::Other => {}
+                    }
+                }
+            }
+        }
+
+        let content = text.join("\n");
+        if !content.trim().is_empty() {
+            messages.push(TranscriptMessage {
+                id: format!("msg_{}", uuid),
+                role,
+                content,
+                tool_name: None,
+                tool_input: None,
+                timestamp,
+            });
+        }
+        for (id, name, input) in tool_uses {
+            messages.push(TranscriptMessage {
+                id: format!("msg_{}", id),
+                role: MessageRole::Tool,
+                content: name.clone(),
+                tool_name: Some(name),
+                tool_input: Some(input),
+                timestamp,
+            });
+        }
+    }
+    messages
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TRANSCRIPT: &str = r#"{"type":"summary","summary":"Fix the build","leafUuid":"u3"}
+{"type":"user","uuid":"u0","timestamp":"2026-01-01T11:59:59.000Z","isMeta":true,"message":{"role":"user","content":"Caveat: local commands"}}
+{"type":"user","uuid":"u1","timestamp":"2026-01-01T12:00:00.000Z","message":{"role":"user","content":"Fix the build"}}
+{"type":"assistant","uuid":"u2","timestamp":"2026-01-01T12:00:05.250Z","message":{"role":"assistant","content":[{"type":"thinking","thinking":"..."},{"type":"text","text":"Running the tests."},{"type":"tool_use","id":"toolu_1","name":"Bash","input":{"command":"cargo test"}}]}}
+{"type":"user","uuid":"u3","timestamp":"2026-01-01T12:00:09.000Z","message":{"role":"user","content":[{"type":"tool_result","tool_use_id":"toolu_1","content":"ok"}]}}
+{"type":"assistant","uuid":"u4","timestamp":"2026-01-01T12:00:10.000Z","isSidechain":true,"message":{"role":"assistant","content":[{"type":"text","text":"sub-agent"}]}}
+not json
+"#;
+
+    #[test]
+    fn test_parse_transcript_keeps_turns_and_tool_calls() {
+        let messages = parse_transcript(TRANSCRIPT);
+
+        assert_eq!(messages.len(), 3);
+        assert_eq!(messages[0].id, "msg_u1");
+        assert_eq!(messages[0].role, MessageRole::User);
+        assert_eq!(messages[0].content, "Fix the build");
+        assert_eq!(messages[1].role, MessageRole::Assistant);
+        assert_eq!(messages[1].content, "Running the tests.");
+        assert_eq!(messages[2].id, "msg_toolu_1");
+        assert_eq!(messages[2].role, MessageRole::Tool);
+        assert_eq!(messages[2].tool_name.as_deref(), Some("Bash"));
+        assert_eq!(
+            messages[2].tool_input,
+            Some(serde_json::json!({ "command": "cargo test" }))
+        );
+        assert_eq!(messages[2].timestamp, messages[1].timestamp);
+    }
+
+    #[test]
+    fn test_find_transcript_searches_every_project() {
+        let dir = tempfile::tempdir().unwrap();
+        let project = dir.path().join("-home-me-repo");
+        std::fs::create_dir_all(&project).unwrap();
+        std::fs::create_dir_all(dir.path().join("-home-me-other")).unwrap();
+        std::fs::write(project.join("abc-123.jsonl"), TRANSCRIPT).unwrap();
+
+        assert_eq!(
+            find_transcript(dir.path(), "abc-123"),
+            Some(project.join("abc-123.jsonl"))
+        );
+        assert_eq!(find_transcript(dir.path(), "missing"), None);
+        assert_eq!(find_transcript(dir.path(), "../abc-123"), None);
+    }
+
+    #[test]
+    fn test_read_cwd_and_project_dir_name() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("abc-123.jsonl");
+        std::fs::write(
+            &path,
+            r#"{"type":"summary","summary":"Fix the build","leafUuid":"u1"}
+{"type":"user","uuid":"u1","cwd":"/home/me/my.repo","message":{"role":"user","content":"Hi"}}
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(read_cwd(&path).as_deref(), Some("/home/me/my.repo"));
+        assert_eq!(project_dir_name("/home/me/my.repo"), "-home-me-my-repo");
+        assert_eq!(read_cwd(&dir.path().join("missing.jsonl")), None);
+    }
+}
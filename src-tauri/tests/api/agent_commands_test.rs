@@ -6,11 +6,17 @@ mod common {
 
 use std::sync::Arc;
 
-use claude_manager_lib::db::AgentRepository;
-use claude_manager_lib::services::{AgentService, ProcessManager};
-use claude_manager_lib::types::{AgentMode, AgentStatus, Permission, UpdateAgentInput};
+use claude_manager_lib::db::{AgentRepository, EventJournalRepository};
+use claude_manager_lib::services::{
+    AgentError, AgentService, IdleShutdownService, ProcessControl, ProcessEvent, ProcessManager,
+    StatusSyncService,
+};
+use claude_manager_lib::types::{
+    AgentMode, AgentStatus, IdleShutdownPolicy, Permission, UpdateAgentInput,
+};
 
 use common::fixtures::AgentBuilder;
+use common::mocks::MockProcessManager;
 use common::TestContext;
 
 #[test]
@@ -50,6 +56,11 @@ fn test_agent_crud() {
                 mode: Some(AgentMode::Auto),
                 permissions: Some(vec![Permission::Read, Permission::Write]),
                 display_order: None,
+                system_prompt: None,
+                append_system_prompt: None,
+                allowed_tools: None,
+                disallowed_tools: None,
+                expected_updated_at: None,
             },
         )
         .expect("Should update agent");
@@ -68,13 +79,13 @@ fn test_agent_crud() {
 
     // Verify not in normal list
     let agents = service
-        .list_agents(&ctx.worktree_id, false)
+        .list_agents(&ctx.worktree_id, false, &[])
         .expect("Should list agents");
     assert!(!agents.iter().any(|a| a.id == created.id));
 
     // Verify in deleted list
     let agents = service
-        .list_agents(&ctx.worktree_id, true)
+        .list_agents(&ctx.worktree_id, true, &[])
         .expect("Should list all agents including deleted");
     assert!(agents.iter().any(|a| a.id == created.id));
 }
@@ -130,7 +141,7 @@ fn test_agent_fork() {
 
     // Fork agent
     let forked = service
-        .fork_agent(&parent.id, None)
+        .fork_agent(&parent.id, None, false)
         .expect("Should fork agent");
 
     assert_eq!(forked.name, "Parent Agent (fork)");
@@ -158,7 +169,7 @@ fn test_agent_fork_with_custom_name() {
         .expect("Should create parent");
 
     let forked = service
-        .fork_agent(&parent.id, Some("Custom Fork Name".to_string()))
+        .fork_agent(&parent.id, Some("Custom Fork Name".to_string()), false)
         .expect("Should fork agent");
 
     assert_eq!(forked.name, "Custom Fork Name");
@@ -193,7 +204,7 @@ fn test_agent_restore() {
 
     // Verify appears in normal list
     let agents = service
-        .list_agents(&ctx.worktree_id, false)
+        .list_agents(&ctx.worktree_id, false, &[])
         .expect("Should list agents");
     assert!(agents.iter().any(|a| a.id == agent.id));
 }
@@ -220,7 +231,7 @@ fn test_agent_permanent_delete() {
 
     // Verify not found even with include_deleted
     let agents = service
-        .list_agents(&ctx.worktree_id, true)
+        .list_agents(&ctx.worktree_id, true, &[])
         .expect("Should list all agents");
     assert!(!agents.iter().any(|a| a.id == agent.id));
 }
@@ -338,9 +349,343 @@ fn test_agent_list_empty_worktree() {
 
     // List agents for a worktree with no agents
     let agents = service
-        .list_agents(&ctx.worktree_id, false)
+        .list_agents(&ctx.worktree_id, false, &[])
         .expect("Should list agents");
 
     // Initially empty (test context doesn't create agents)
     assert!(agents.is_empty());
 }
+
+#[test]
+fn test_agent_lifecycle_with_mock_process_manager() {
+    let ctx = TestContext::new();
+    let mock = Arc::new(MockProcessManager::new());
+    let service = AgentService::new(ctx.pool.clone(), mock.clone());
+    let worktree_path = ctx.temp_path().to_string_lossy().to_string();
+
+    let agent = service
+        .create_agent(&ctx.worktree_id, None, AgentMode::Regular, vec![])
+        .expect("Should create agent");
+
+    // Starting records the PID and session the process manager hands back
+    let started = service
+        .start_agent(&agent.id, &worktree_path, None)
+        .expect("Should start agent");
+    assert_eq!(started.status, AgentStatus::Running);
+    assert!(started.pid.is_some_and(|pid| pid >= 10000));
+    let session_id = started.session_id.expect("Should assign a session");
+    assert!(session_id.starts_with("mock-session-"));
+    assert!(mock.is_running(&agent.id));
+
+    service
+        .send_message(&agent.id, "hello")
+        .expect("Should send message");
+    assert_eq!(
+        mock.sent_messages(),
+        vec![(agent.id.clone(), "hello".to_string())]
+    );
+
+    let stopped = service
+        .stop_agent(&agent.id, true)
+        .expect("Should stop agent");
+    assert_eq!(stopped.status, AgentStatus::Idle);
+    assert_eq!(mock.running_count(), 0);
+
+    // Messages to a stopped agent are refused
+    assert!(matches!(
+        service.send_message(&agent.id, "hello again"),
+        Err(AgentError::Process(_))
+    ));
+
+    // Starting again resumes the same session
+    let restarted = service
+        .start_agent(&agent.id, &worktree_path, None)
+        .expect("Should restart agent");
+    assert_eq!(restarted.session_id, Some(session_id));
+
+    // Deleting a running agent stops it first
+    service
+        .delete_agent(&agent.id, false)
+        .expect("Should delete agent");
+    assert!(!mock.is_running(&agent.id));
+}
+
+#[test]
+fn test_agent_spawn_failure_with_mock_process_manager() {
+    let ctx = TestContext::new();
+    let mock = Arc::new(MockProcessManager::new());
+    mock.set_spawn_fails(true);
+    let service = AgentService::new(ctx.pool.clone(), mock.clone());
+
+    let agent = service
+        .create_agent(&ctx.worktree_id, None, AgentMode::Regular, vec![])
+        .expect("Should create agent");
+    let result = service.start_agent(&agent.id, &ctx.temp_path().to_string_lossy(), None);
+    assert!(matches!(result, Err(AgentError::Process(_))));
+
+    // The agent never ran
+    assert_eq!(
+        service.get_agent(&agent.id).unwrap().status,
+        AgentStatus::Idle
+    );
+    assert_eq!(mock.running_count(), 0);
+}
+
+#[test]
+fn test_agent_update_conflict() {
+    let ctx = TestContext::new();
+    let pm = Arc::new(ProcessManager::new("echo".to_string()));
+    let service = AgentService::new(ctx.pool.clone(), pm);
+    let agent = service
+        .create_agent(&ctx.worktree_id, None, AgentMode::Regular, vec![])
+        .expect("Should create agent");
+    let rename = |name: &str, expected_updated_at: &str| UpdateAgentInput {
+        name: Some(name.to_string()),
+        mode: None,
+        permissions: None,
+        display_order: None,
+        system_prompt: None,
+        append_system_prompt: None,
+        allowed_tools: None,
+        disallowed_tools: None,
+        expected_updated_at: Some(expected_updated_at.to_string()),
+    };
+
+    // Two windows loaded the same agent; the second save would lose the first
+    let first = service
+        .update_agent(&agent.id, rename("First", &agent.updated_at))
+        .expect("Should update agent");
+    let result = service.update_agent(&agent.id, rename("Second", &agent.updated_at));
+    assert!(
+        matches!(result, Err(AgentError::Conflict { ref updated_at, .. }) if *updated_at == first.updated_at)
+    );
+    assert_eq!(service.get_agent(&agent.id).unwrap().name, "First");
+
+    let second = service
+        .update_agent(&agent.id, rename("Second", &first.updated_at))
+        .expect("Should update reloaded agent");
+    assert_eq!(second.name, "Second");
+}
+
+#[test]
+fn test_agent_run_timestamps() {
+    let ctx = TestContext::new();
+    let mock = Arc::new(MockProcessManager::new());
+    let service = AgentService::new(ctx.pool.clone(), mock.clone());
+    let status_sync = StatusSyncService::new(ctx.pool.clone());
+    let worktree_path = ctx.temp_path().to_string_lossy().to_string();
+
+    let agent = service
+        .create_agent(&ctx.worktree_id, None, AgentMode::Regular, vec![])
+        .expect("Should create agent");
+    assert!(agent.started_at.is_none());
+    assert!(agent.run_duration_ms.is_none());
+
+    let started = service
+        .start_agent(&agent.id, &worktree_path, None)
+        .expect("Should start agent");
+    let started_at = started.started_at.clone().expect("Should record the start");
+    assert!(started.stopped_at.is_none());
+    assert!(started.run_duration_ms.is_some());
+
+    // The exit is stamped when it happened, a minute into the run
+    let at = chrono::NaiveDateTime::parse_from_str(&started_at, "%Y-%m-%d %H:%M:%S%.f").unwrap()
+        + chrono::Duration::seconds(60);
+    let exit = ProcessEvent::Exit {
+        agent_id: agent.id.clone(),
+        code: Some(0),
+        signal: None,
+        stopped_by_user: false,
+    };
+    status_sync
+        .apply_event(&exit, &at.format("%Y-%m-%d %H:%M:%S%.3f").to_string())
+        .expect("Should record the exit");
+    let stopped = service.get_agent(&agent.id).unwrap();
+    assert_eq!(stopped.started_at, Some(started_at));
+    assert!(stopped.stopped_at.is_some());
+    assert_eq!(stopped.run_duration_ms, Some(60_000));
+
+    // A new run starts the clock again
+    mock.stop_all();
+    let restarted = service
+        .start_agent(&agent.id, &worktree_path, None)
+        .expect("Should restart agent");
+    assert!(restarted.stopped_at.is_none());
+    assert!(restarted.run_duration_ms.is_some_and(|ms| ms < 60_000));
+}
+
+#[test]
+fn test_agent_runtime_flags_status_without_process() {
+    let ctx = TestContext::new();
+    let mock = Arc::new(MockProcessManager::new());
+    let service = AgentService::new(ctx.pool.clone(), mock.clone());
+    let agent = service
+        .create_agent(&ctx.worktree_id, None, AgentMode::Regular, vec![])
+        .expect("Should create agent");
+
+    let runtime = service.get_runtime(&agent.id).expect("Should get runtime");
+    assert!(!runtime.is_running && !runtime.stale);
+    assert_eq!(runtime.pid, None);
+
+    let started = service
+        .start_agent(&agent.id, &ctx.temp_path().to_string_lossy(), None)
+        .unwrap();
+    mock.inject_output(&agent.id, "Working");
+    let runtime = service.get_runtime(&agent.id).unwrap();
+    assert!(runtime.is_running && !runtime.stale);
+    assert_eq!(runtime.pid.map(|pid| pid as i32), started.pid);
+    assert_eq!(runtime.buffer_bytes, "Working".len());
+
+    // The process dies without its exit reaching the database
+    mock.simulate_finish(&agent.id);
+    let runtime = service.get_runtime(&agent.id).unwrap();
+    assert_eq!(runtime.status, AgentStatus::Running);
+    assert!(!runtime.is_running);
+    assert!(runtime.stale);
+
+    assert!(matches!(
+        service.get_runtime("ag_missing"),
+        Err(AgentError::NotFound(_))
+    ));
+}
+
+#[test]
+fn test_list_agents_reconciles_status_with_processes() {
+    let ctx = TestContext::new();
+    let mock = Arc::new(MockProcessManager::new());
+    let service = AgentService::new(ctx.pool.clone(), mock.clone());
+    let worktree_path = ctx.temp_path().to_string_lossy().to_string();
+    let alive = service
+        .create_agent(&ctx.worktree_id, None, AgentMode::Regular, vec![])
+        .unwrap();
+    let dead = service
+        .create_agent(&ctx.worktree_id, None, AgentMode::Regular, vec![])
+        .unwrap();
+    service
+        .start_agent(&alive.id, &worktree_path, None)
+        .unwrap();
+    service.start_agent(&dead.id, &worktree_path, None).unwrap();
+
+    // The process dies without its exit reaching the database
+    mock.simulate_finish(&dead.id);
+    assert_eq!(
+        service.get_agent(&dead.id).unwrap().status,
+        AgentStatus::Running
+    );
+
+    let agents = service.list_agents(&ctx.worktree_id, false, &[]).unwrap();
+    let listed = |id: &str| agents.iter().find(|agent| agent.id == id).unwrap();
+    assert_eq!(listed(&alive.id).status, AgentStatus::Running);
+    assert_eq!(listed(&dead.id).status, AgentStatus::Error);
+    assert!(listed(&dead.id).status_reason.is_some());
+    assert_eq!(listed(&dead.id).pid, None);
+
+    // The correction is stored, and the run it ended is closed
+    let stored = service.get_agent(&dead.id).unwrap();
+    assert_eq!(stored.status, AgentStatus::Error);
+    assert!(stored.stopped_at.is_some());
+    let runs = service.get_runs(&dead.id, 10).unwrap();
+    assert!(runs[0].ended_at.is_some());
+}
+
+#[test]
+fn test_list_agents_leaves_journaled_exits_to_status_sync() {
+    let ctx = TestContext::new();
+    let mock = Arc::new(MockProcessManager::new());
+    let service = AgentService::new(ctx.pool.clone(), mock.clone());
+    let status_sync = StatusSyncService::new(ctx.pool.clone());
+    let agent = service
+        .create_agent(&ctx.worktree_id, None, AgentMode::Regular, vec![])
+        .unwrap();
+    service
+        .start_agent(&agent.id, &ctx.temp_path().to_string_lossy(), None)
+        .unwrap();
+
+    // The exit is journaled, but the status sync has yet to apply it
+    let exit = ProcessEvent::Exit {
+        agent_id: agent.id.clone(),
+        code: Some(0),
+        signal: None,
+        stopped_by_user: false,
+    };
+    EventJournalRepository::new(ctx.pool.clone())
+        .append(&exit.to_journal_entry().unwrap())
+        .unwrap();
+    mock.simulate_finish(&agent.id);
+
+    let agents = service.list_agents(&ctx.worktree_id, false, &[]).unwrap();
+    assert_eq!(agents[0].status, AgentStatus::Running);
+
+    assert_eq!(status_sync.drain_journal(true).unwrap(), 1);
+    let stopped = service.get_agent(&agent.id).unwrap();
+    assert_ne!(stopped.status, AgentStatus::Error);
+    assert!(stopped.status_reason.is_none());
+}
+
+#[test]
+fn test_idle_shutdown_policy_reaches_running_agents() {
+    let ctx = TestContext::new();
+    let mock = Arc::new(MockProcessManager::new());
+    let service = AgentService::new(ctx.pool.clone(), mock.clone());
+    let idle_shutdown = IdleShutdownService::new(ctx.pool.clone(), mock.clone());
+    let worktree_path = ctx.temp_path().to_string_lossy().to_string();
+    let agent = service
+        .create_agent(&ctx.worktree_id, None, AgentMode::Regular, vec![])
+        .unwrap();
+    service
+        .start_agent(&agent.id, &worktree_path, None)
+        .unwrap();
+
+    idle_shutdown
+        .set_policy(IdleShutdownPolicy {
+            enabled: true,
+            idle_minutes: 30,
+        })
+        .unwrap();
+    assert_eq!(
+        mock.idle_timeout(&agent.id),
+        Some(std::time::Duration::from_secs(30 * 60))
+    );
+
+    idle_shutdown
+        .set_agent_policy(
+            &agent.id,
+            Some(IdleShutdownPolicy {
+                enabled: false,
+                idle_minutes: 30,
+            }),
+        )
+        .unwrap();
+    assert_eq!(mock.idle_timeout(&agent.id), None);
+}
+
+#[test]
+fn test_idle_shutdown_spares_agents_active_again() {
+    let ctx = TestContext::new();
+    let mock = Arc::new(MockProcessManager::new());
+    let service = AgentService::new(ctx.pool.clone(), mock.clone());
+    let idle_shutdown = IdleShutdownService::new(ctx.pool.clone(), mock.clone());
+    let agent = service
+        .create_agent(&ctx.worktree_id, None, AgentMode::Regular, vec![])
+        .unwrap();
+    service
+        .start_agent(&agent.id, &ctx.temp_path().to_string_lossy(), None)
+        .unwrap();
+
+    // Output arrives after the timeout was sent for 30 idle minutes
+    mock.inject_output(&agent.id, "Working");
+    let spared = idle_shutdown
+        .shut_down(&agent.id, std::time::Duration::from_secs(30 * 60))
+        .unwrap();
+    assert!(spared.is_none());
+    assert!(mock.is_running(&agent.id));
+    assert!(idle_shutdown.list_shutdowns().unwrap().is_empty());
+
+    // Quiet for as long as the timeout says, it is stopped
+    let stopped = idle_shutdown
+        .shut_down(&agent.id, std::time::Duration::ZERO)
+        .unwrap();
+    assert!(stopped.is_some());
+    assert!(!mock.is_running(&agent.id));
+    assert_eq!(idle_shutdown.list_shutdowns().unwrap().len(), 1);
+}
@@ -0,0 +1,245 @@
+//! End-to-end agent process tests against a fake claude CLI
+//!
+//! These spawn real processes in a PTY, so they cover what `echo` can't:
+//! prompts, session flags, status detection from terminal output and
+//! graceful stops.
+
+mod common {
+    pub use crate::common::*;
+}
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::broadcast;
+
+use claude_manager_lib::db::{AgentRunRepository, EventJournalRepository};
+use claude_manager_lib::services::{
+    AgentService, ProcessEvent, ProcessManager, StampedEvent, StatusSyncService,
+};
+use claude_manager_lib::types::{AgentMode, AgentStatus, Permission};
+
+use common::{fake_claude, TestContext};
+
+/// Long enough for the idle monitor's three seconds of silence, plus slack
+const STATUS_TIMEOUT: Duration = Duration::from_secs(10);
+
+struct Harness {
+    ctx: TestContext,
+    pm: Arc<ProcessManager>,
+    service: AgentService,
+    events: broadcast::Receiver<StampedEvent>,
+}
+
+impl Harness {
+    fn new() -> Self {
+        let ctx = TestContext::new();
+        let pm = Arc::new(
+            ProcessManager::new(fake_claude::path())
+                .with_event_journal(EventJournalRepository::new(ctx.pool.clone())),
+        );
+        let status_sync = StatusSyncService::new(ctx.pool.clone());
+        let sync_rx = pm.subscribe();
+        tokio::spawn(async move {
+            status_sync.run(sync_rx).await;
+        });
+        let events = pm.subscribe();
+        let service = AgentService::new(ctx.pool.clone(), pm.clone());
+        Self {
+            ctx,
+            pm,
+            service,
+            events,
+        }
+    }
+
+    fn worktree_path(&self) -> String {
+        self.ctx.temp_path().to_string_lossy().to_string()
+    }
+
+    /// Next status or exit event of the agent, skipping everything else
+    async fn next_event(&mut self, agent_id: &str) -> ProcessEvent {
+        let wait = async {
+            loop {
+                let stamped = self.events.recv().await.expect("Event channel closed");
+                match &stamped.event {
+                    ProcessEvent::Status { agent_id: id, .. }
+                    | ProcessEvent::Exit { agent_id: id, .. }
+                        if id == agent_id =>
+                    {
+                        return stamped.event;
+                    }
+                    _ => {}
+                }
+            }
+        };
+        tokio::time::timeout(STATUS_TIMEOUT, wait)
+            .await
+            .expect("No status change in time")
+    }
+
+    async fn expect_status(&mut self, agent_id: &str, expected: AgentStatus) {
+        match self.next_event(agent_id).await {
+            ProcessEvent::Status { status, .. } => assert_eq!(status, expected),
+            other => panic!("Expected {:?}, got {:?}", expected, other),
+        }
+    }
+
+    /// Exit code of the agent's process and whether it was stopped, skipping
+    /// the status changes its last output causes
+    async fn expect_exit(&mut self, agent_id: &str) -> (Option<i32>, bool) {
+        loop {
+            if let ProcessEvent::Exit {
+                code,
+                stopped_by_user,
+                ..
+            } = self.next_event(agent_id).await
+            {
+                return (code, stopped_by_user);
+            }
+        }
+    }
+
+    async fn wait_for_output(&self, agent_id: &str, text: &str) {
+        fake_claude::wait_until(STATUS_TIMEOUT, || {
+            self.pm
+                .get_pty_buffer(agent_id)
+                .is_some_and(|buffer| String::from_utf8_lossy(&buffer).contains(text))
+        })
+        .await;
+    }
+}
+
+impl Drop for Harness {
+    // A failed assertion must not leave the fake CLI running, or the test
+    // runtime waits on its PTY reader forever
+    fn drop(&mut self) {
+        self.pm.stop_all();
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_spawn_prompt_message_and_graceful_stop() {
+    let mut h = Harness::new();
+    let agent = h
+        .service
+        .create_agent(
+            &h.ctx.worktree_id,
+            Some("Fake".to_string()),
+            AgentMode::Regular,
+            vec![Permission::Read],
+        )
+        .expect("Should create agent");
+    let worktree_path = h.worktree_path();
+
+    // Spawn with a first prompt that produces slow output
+    let started = h
+        .service
+        .start_agent(&agent.id, &worktree_path, Some("slow"))
+        .expect("Should start agent");
+    assert_eq!(started.status, AgentStatus::Running);
+    let session_id = started.session_id.clone().expect("Should assign a session");
+    h.expect_status(&agent.id, AgentStatus::Running).await;
+
+    let args = fake_claude::last_args(h.ctx.temp_path()).await;
+    assert!(args
+        .windows(2)
+        .any(|w| w == ["--session-id", session_id.as_str()]));
+    assert_eq!(args.last().map(String::as_str), Some("slow"));
+    h.wait_for_output(&agent.id, &format!("Session {}", session_id))
+        .await;
+
+    // Output keeps coming for two seconds, so the agent only goes idle after it
+    h.expect_status(&agent.id, AgentStatus::Idle).await;
+    let buffer = String::from_utf8_lossy(&h.pm.get_pty_buffer(&agent.id).unwrap()).to_string();
+    assert!(buffer.contains("Done"));
+
+    // A message that ends in a question leaves the agent waiting for input
+    h.service
+        .send_message(&agent.id, "ask")
+        .expect("Should send message");
+    h.expect_status(&agent.id, AgentStatus::Running).await;
+    h.expect_status(&agent.id, AgentStatus::Waiting).await;
+
+    h.service
+        .send_message(&agent.id, "y")
+        .expect("Should answer");
+    h.expect_status(&agent.id, AgentStatus::Running).await;
+    h.wait_for_output(&agent.id, "Proceeding").await;
+    h.expect_status(&agent.id, AgentStatus::Idle).await;
+
+    // Hooks report status by session, which maps back to the agent
+    assert_eq!(
+        h.pm.find_agent_by_session(Some(&session_id)),
+        Some(agent.id.clone())
+    );
+    h.pm.set_hook_status(&agent.id, AgentStatus::Waiting);
+    h.expect_status(&agent.id, AgentStatus::Waiting).await;
+
+    // A graceful stop interrupts the CLI and waits for it to exit
+    h.service
+        .stop_agent(&agent.id, false)
+        .expect("Should stop agent");
+    assert_eq!(h.expect_exit(&agent.id).await, (Some(130), true));
+    assert!(!h.pm.is_running(&agent.id));
+    h.wait_for_output(&agent.id, "Interrupted").await;
+
+    // The exit lands in the database as a clean stop
+    fake_claude::wait_until(STATUS_TIMEOUT, || {
+        h.service.get_agent(&agent.id).unwrap().status == AgentStatus::Idle
+    })
+    .await;
+    let runs = AgentRunRepository::new(h.ctx.pool.clone())
+        .find_by_agent_id(&agent.id, 10)
+        .unwrap();
+    assert_eq!(runs.len(), 1);
+    assert_eq!(runs[0].session_id.as_deref(), Some(session_id.as_str()));
+    assert!(runs[0].ended_at.is_some());
+    assert!(runs[0].stopped_by_user);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_restart_resumes_session() {
+    let mut h = Harness::new();
+    let agent = h
+        .service
+        .create_agent(
+            &h.ctx.worktree_id,
+            None,
+            AgentMode::Regular,
+            vec![Permission::Read],
+        )
+        .expect("Should create agent");
+    let worktree_path = h.worktree_path();
+
+    let started = h
+        .service
+        .start_agent(&agent.id, &worktree_path, None)
+        .expect("Should start agent");
+    let session_id = started.session_id.unwrap();
+    h.expect_status(&agent.id, AgentStatus::Running).await;
+
+    // The CLI exiting on its own is a clean exit too
+    h.wait_for_output(&agent.id, "> ").await;
+    h.service.send_message(&agent.id, "exit").unwrap();
+    assert_eq!(h.expect_exit(&agent.id).await, (Some(0), false));
+    fake_claude::wait_until(STATUS_TIMEOUT, || !h.pm.is_running(&agent.id)).await;
+
+    // Starting again resumes the same conversation
+    std::fs::remove_file(h.ctx.temp_path().join(fake_claude::ARGS_FILE)).unwrap();
+    h.service
+        .start_agent(&agent.id, &worktree_path, None)
+        .expect("Should restart agent");
+    let args = fake_claude::last_args(h.ctx.temp_path()).await;
+    assert!(args
+        .windows(2)
+        .any(|w| w == ["--resume", session_id.as_str()]));
+    assert!(!args.iter().any(|arg| arg == "--session-id"));
+    h.wait_for_output(&agent.id, &format!("Resuming session {}", session_id))
+        .await;
+
+    h.service
+        .stop_agent(&agent.id, true)
+        .expect("Should force stop agent");
+    assert!(!h.pm.is_running(&agent.id));
+}
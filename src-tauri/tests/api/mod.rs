@@ -1,5 +1,7 @@
 //! API integration tests
 
 mod agent_commands_test;
+#[cfg(unix)]
+mod agent_spawn_test;
 mod workspace_commands_test;
 mod worktree_commands_test;
@@ -4,10 +4,18 @@ mod common {
     pub use crate::common::*;
 }
 
-use claude_manager_lib::db::WorkspaceRepository;
-use claude_manager_lib::services::WorkspaceService;
+use std::sync::Arc;
 
-use common::TestContext;
+use claude_manager_lib::db::{
+    AgentRepository, MessageRepository, ScheduleRepository, WorkspaceRepository, WorktreeRepository,
+};
+use claude_manager_lib::services::{GitOps, WorkspaceError, WorkspaceService};
+use claude_manager_lib::types::{AgentMode, Message, MessageRole, Permission, Schedule};
+use claude_manager_lib::util::cancel::CancellationToken;
+
+use common::fixtures::AgentBuilder;
+use common::mocks::FakeGit;
+use common::{init_git_repo, TestContext};
 
 #[test]
 fn test_workspace_get() {
@@ -111,3 +119,307 @@ fn test_workspace_delete() {
     let found = repo.find_by_id("ws_to_delete").unwrap();
     assert!(found.is_none());
 }
+
+#[test]
+fn test_workspace_duplicate_path_rejected() {
+    let ctx = TestContext::new();
+    let service = WorkspaceService::new(ctx.pool.clone());
+    let repo_dir = tempfile::tempdir().unwrap();
+    let repo = init_git_repo(repo_dir.path());
+    let path = repo_dir.path().to_str().unwrap();
+
+    let workspace = service
+        .create_workspace(path, None)
+        .expect("Should create workspace");
+
+    // Same repository through a non-canonical path
+    let result = service.create_workspace(&format!("{}/.", path), None);
+    assert!(matches!(result, Err(WorkspaceError::AlreadyExists(ref id)) if *id == workspace.id));
+
+    // A linked worktree of the same repository
+    let head = repo.head().unwrap().peel_to_commit().unwrap();
+    repo.branch("feature", &head, false).unwrap();
+    let linked_dir = tempfile::tempdir().unwrap();
+    let linked_path = linked_dir.path().join("feature");
+    let reference = repo
+        .find_branch("feature", git2::BranchType::Local)
+        .unwrap()
+        .into_reference();
+    repo.worktree(
+        "feature",
+        &linked_path,
+        Some(git2::WorktreeAddOptions::new().reference(Some(&reference))),
+    )
+    .unwrap();
+
+    let result = service.create_workspace(linked_path.to_str().unwrap(), None);
+    assert!(matches!(result, Err(WorkspaceError::AlreadyExists(ref id)) if *id == workspace.id));
+}
+
+#[test]
+fn test_workspace_relink() {
+    let ctx = TestContext::new();
+    let service = WorkspaceService::new(ctx.pool.clone());
+    let old_dir = tempfile::tempdir().unwrap();
+    init_git_repo(old_dir.path());
+
+    let workspace = service
+        .create_workspace(old_dir.path().to_str().unwrap(), Some("Moved"))
+        .expect("Should create workspace");
+    let details = service
+        .get_workspace_with_details(&workspace.id)
+        .expect("Should get details");
+    let main_id = details.worktrees[0].worktree.id.clone();
+
+    // Moved repository
+    let new_dir = tempfile::tempdir().unwrap();
+    init_git_repo(new_dir.path());
+    let new_path = new_dir.path().to_str().unwrap();
+
+    let relinked = service
+        .relink_workspace(&workspace.id, new_path)
+        .expect("Should relink workspace");
+
+    assert_eq!(relinked.workspace.path, new_path);
+    assert_eq!(relinked.worktrees.len(), 1);
+    assert_eq!(relinked.worktrees[0].worktree.id, main_id);
+    assert_eq!(
+        std::path::Path::new(&relinked.worktrees[0].worktree.path)
+            .canonicalize()
+            .unwrap(),
+        new_dir.path().canonicalize().unwrap()
+    );
+
+    // Relinking onto a directory that is not a repository is rejected
+    let result = service.relink_workspace(&workspace.id, ctx.temp_path().to_str().unwrap());
+    assert!(matches!(result, Err(WorkspaceError::InvalidPath(_))));
+}
+
+#[test]
+fn test_workspace_scan_with_fake_git() {
+    let ctx = TestContext::new();
+    let git = Arc::new(FakeGit::new());
+    git.add_repo("/repos/app", "main");
+    git.add_linked_worktree("/repos/app", "/repos/app-feature", "feature");
+    let service = WorkspaceService::new(ctx.pool.clone()).with_git(git.clone());
+
+    let workspace = service
+        .create_workspace("/repos/app", None)
+        .expect("Should create workspace");
+    assert_eq!(workspace.name, "app");
+    let details = service
+        .get_workspace_with_details(&workspace.id)
+        .expect("Should get details");
+    let mut worktrees: Vec<(String, bool)> = details
+        .worktrees
+        .iter()
+        .map(|w| (w.worktree.branch.clone(), w.worktree.is_main))
+        .collect();
+    worktrees.sort();
+    assert_eq!(
+        worktrees,
+        vec![("feature".to_string(), false), ("main".to_string(), true)]
+    );
+
+    // Worktrees added outside the app show up on refresh
+    git.add_linked_worktree("/repos/app", "/repos/app-fix", "fix");
+    let refreshed = service
+        .refresh_workspace(&workspace.id)
+        .expect("Should refresh workspace");
+    assert_eq!(refreshed.worktrees.len(), 3);
+    assert!(refreshed
+        .worktrees
+        .iter()
+        .any(|w| w.worktree.name == "app-fix" && w.worktree.branch == "fix"));
+
+    // A linked worktree is the same repository
+    let result = service.create_workspace("/repos/app-feature", None);
+    assert!(matches!(result, Err(WorkspaceError::AlreadyExists(ref id)) if *id == workspace.id));
+
+    let result = service.create_workspace("/repos/unknown", None);
+    assert!(matches!(result, Err(WorkspaceError::InvalidPath(_))));
+}
+
+#[test]
+fn test_scan_keeps_agents_of_removed_worktrees() {
+    let ctx = TestContext::new();
+    let git = Arc::new(FakeGit::new());
+    git.add_repo("/repos/app", "main");
+    git.add_linked_worktree("/repos/app", "/repos/app-feature", "feature");
+    git.add_linked_worktree("/repos/app", "/repos/app-empty", "empty");
+    let service = WorkspaceService::new(ctx.pool.clone()).with_git(git.clone());
+    let workspace = service
+        .create_workspace("/repos/app", None)
+        .expect("Should create workspace");
+    let feature = service
+        .get_workspace_with_details(&workspace.id)
+        .unwrap()
+        .worktrees
+        .into_iter()
+        .find(|w| w.worktree.branch == "feature")
+        .unwrap()
+        .worktree;
+
+    let agent = AgentRepository::new(ctx.pool.clone())
+        .create(&AgentBuilder::new(&feature.id).name("Keeper").build())
+        .unwrap();
+    let messages = MessageRepository::new(ctx.pool.clone());
+    messages
+        .insert_missing(&[Message {
+            id: "msg_1".to_string(),
+            agent_id: agent.id.clone(),
+            role: MessageRole::User,
+            content: "Fix the login bug".to_string(),
+            token_count: None,
+            tool_name: None,
+            tool_input: None,
+            tool_output: None,
+            created_at: chrono::Utc::now().to_rfc3339(),
+            is_complete: true,
+            seq: 0,
+        }])
+        .unwrap();
+
+    // Both linked worktrees are removed outside the app
+    git.remove_worktree("/repos/app", "/repos/app-feature")
+        .unwrap();
+    git.remove_worktree("/repos/app", "/repos/app-empty")
+        .unwrap();
+    let refreshed = service
+        .refresh_workspace(&workspace.id)
+        .expect("Should refresh workspace");
+    assert_eq!(refreshed.worktrees.len(), 1);
+    assert!(refreshed.worktrees[0].worktree.is_main);
+
+    // The worktree without agents is gone; the other is archived so its
+    // agent and the agent's messages survive
+    let archived = WorktreeRepository::new(ctx.pool.clone())
+        .find_archived_by_workspace_id(&workspace.id)
+        .unwrap();
+    assert_eq!(archived.len(), 1);
+    assert_eq!(archived[0].id, feature.id);
+    let orphaned = service
+        .list_orphaned_agents(&workspace.id)
+        .expect("Should list orphaned agents");
+    assert_eq!(orphaned.len(), 1);
+    assert_eq!(orphaned[0].id, agent.id);
+    assert!(orphaned[0].deleted_at.is_some());
+    let (kept, _) = messages.find_page(&agent.id, 10, None).unwrap();
+    assert_eq!(kept.len(), 1);
+
+    // Scanning again changes nothing
+    service.refresh_workspace(&workspace.id).unwrap();
+    assert_eq!(
+        service.list_orphaned_agents(&workspace.id).unwrap().len(),
+        1
+    );
+}
+
+#[test]
+fn test_scan_keeps_schedules_of_removed_worktrees() {
+    let ctx = TestContext::new();
+    let git = Arc::new(FakeGit::new());
+    git.add_repo("/repos/app", "main");
+    git.add_linked_worktree("/repos/app", "/repos/app-nightly", "nightly");
+    let service = WorkspaceService::new(ctx.pool.clone()).with_git(git.clone());
+    let workspace = service
+        .create_workspace("/repos/app", None)
+        .expect("Should create workspace");
+    let nightly = service
+        .get_workspace_with_details(&workspace.id)
+        .unwrap()
+        .worktrees
+        .into_iter()
+        .find(|w| w.worktree.branch == "nightly")
+        .unwrap()
+        .worktree;
+
+    let now = chrono::Utc::now().to_rfc3339();
+    let schedules = ScheduleRepository::new(ctx.pool.clone());
+    let schedule = schedules
+        .create(&Schedule {
+            id: "sch_1".to_string(),
+            name: "Nightly review".to_string(),
+            cron: "0 2 * * *".to_string(),
+            worktree_id: nightly.id.clone(),
+            mode: AgentMode::Regular,
+            permissions: vec![Permission::Read],
+            prompt: "Review yesterday's changes".to_string(),
+            paused: true,
+            next_run_at: None,
+            last_run_at: None,
+            last_agent_id: None,
+            last_error: None,
+            created_at: now.clone(),
+            updated_at: now,
+        })
+        .unwrap();
+
+    // The worktree is removed outside the app; it never had agents
+    git.remove_worktree("/repos/app", "/repos/app-nightly")
+        .unwrap();
+    service
+        .refresh_workspace(&workspace.id)
+        .expect("Should refresh workspace");
+
+    let archived = WorktreeRepository::new(ctx.pool.clone())
+        .find_archived_by_workspace_id(&workspace.id)
+        .unwrap();
+    assert_eq!(archived.len(), 1);
+    assert_eq!(archived[0].id, nightly.id);
+    assert!(schedules.find_by_id(&schedule.id).unwrap().is_some());
+}
+
+#[test]
+fn test_scan_keeps_pinned_and_present_worktrees_as_missing() {
+    let ctx = TestContext::new();
+    let on_disk = ctx.temp_path().join("app-mounted");
+    std::fs::create_dir_all(&on_disk).unwrap();
+    let on_disk = on_disk.to_string_lossy().to_string();
+    let git = Arc::new(FakeGit::new());
+    git.add_repo("/repos/app", "main");
+    git.add_linked_worktree("/repos/app", "/repos/app-pinned", "pinned");
+    git.add_linked_worktree("/repos/app", "/repos/app-gone", "gone");
+    git.add_linked_worktree("/repos/app", &on_disk, "mounted");
+    let service = WorkspaceService::new(ctx.pool.clone()).with_git(git.clone());
+    let workspace = service
+        .create_workspace("/repos/app", None)
+        .expect("Should create workspace");
+    let worktrees = WorktreeRepository::new(ctx.pool.clone());
+    let find = |path: &str| worktrees.find_by_path(path).unwrap();
+    let pinned = find("/repos/app-pinned").unwrap();
+    worktrees.set_pinned(&pinned.id, true).unwrap();
+    assert!(find(&on_disk).unwrap().missing_since.is_none());
+
+    // git forgets all three, but only the one that is neither pinned nor on
+    // disk is pruned
+    for path in ["/repos/app-pinned", "/repos/app-gone", on_disk.as_str()] {
+        git.remove_worktree("/repos/app", path).unwrap();
+    }
+    service.refresh_workspace(&workspace.id).unwrap();
+    assert!(find("/repos/app-gone").is_none());
+    let pinned = find("/repos/app-pinned").expect("Pinned worktree is kept");
+    assert!(pinned.pinned);
+    let missing_since = pinned.missing_since.expect("Should be marked missing");
+    assert!(find(&on_disk).unwrap().missing_since.is_some());
+
+    // Scanning again keeps when it first went missing
+    service.refresh_workspace(&workspace.id).unwrap();
+    assert_eq!(
+        find("/repos/app-pinned").unwrap().missing_since,
+        Some(missing_since)
+    );
+
+    // Once git lists the worktree on disk again it is no longer missing
+    git.add_worktree(
+        "/repos/app",
+        &on_disk,
+        "mounted",
+        false,
+        &[],
+        &CancellationToken::new(),
+    )
+    .unwrap();
+    service.refresh_workspace(&workspace.id).unwrap();
+    assert!(find(&on_disk).unwrap().missing_since.is_none());
+}
@@ -4,11 +4,18 @@ mod common {
     pub use crate::common::*;
 }
 
-use claude_manager_lib::db::WorktreeRepository;
-use claude_manager_lib::services::WorktreeService;
-use claude_manager_lib::types::{SortMode, UpdateWorktreeInput};
+use std::sync::Arc;
 
-use common::TestContext;
+use claude_manager_lib::db::{AgentRepository, WorktreeRepository};
+use claude_manager_lib::services::{GitOps, WorkspaceService, WorktreeError, WorktreeService};
+use claude_manager_lib::types::{
+    AgentStatus, GitStatusOptions, SharedFileMode, SharedFilesConfig, SortMode,
+    UpdateWorktreeInput, WorktreeState,
+};
+use claude_manager_lib::util::cancel::CancellationToken;
+
+use common::mocks::FakeGit;
+use common::{fixtures, init_git_repo, TestContext};
 
 #[test]
 fn test_worktree_get() {
@@ -72,6 +79,7 @@ fn test_worktree_update() {
                 name: Some("Updated Name".to_string()),
                 sort_mode: Some(SortMode::Status),
                 display_order: Some(5),
+                expected_updated_at: None,
             },
         )
         .expect("Should update worktree");
@@ -133,6 +141,11 @@ fn test_worktree_reorder() {
         is_main: false,
         created_at: now.clone(),
         updated_at: now.clone(),
+        pr_url: None,
+        sparse_patterns: None,
+        archived_at: None,
+        pinned: false,
+        missing_since: None,
     };
 
     let wt2 = claude_manager_lib::types::Worktree {
@@ -146,6 +159,11 @@ fn test_worktree_reorder() {
         is_main: false,
         created_at: now.clone(),
         updated_at: now,
+        pr_url: None,
+        sparse_patterns: None,
+        archived_at: None,
+        pinned: false,
+        missing_since: None,
     };
 
     repo.create(&wt1).expect("Should create wt1");
@@ -163,3 +181,916 @@ fn test_worktree_reorder() {
     assert_eq!(wt2_updated.display_order, 0);
     assert_eq!(wt1_updated.display_order, 1);
 }
+
+#[test]
+fn test_detect_conflicts_between_worktrees() {
+    let ctx = TestContext::new();
+    std::fs::write(ctx.temp_path().join("shared.txt"), "base\n").unwrap();
+    std::fs::write(ctx.temp_path().join("other.txt"), "base\n").unwrap();
+    init_git_repo(ctx.temp_path());
+    let service = WorktreeService::new(ctx.pool.clone());
+    let worktrees_dir = tempfile::tempdir().unwrap();
+
+    let create = |name: &str| {
+        let path = worktrees_dir.path().join(name);
+        service
+            .create_worktree(&ctx.workspace_id, name, name, path.to_str(), true)
+            .expect("Should create worktree")
+    };
+    let wt_a = create("feature-a");
+    let wt_b = create("feature-b");
+    let wt_c = create("feature-c");
+
+    std::fs::write(format!("{}/shared.txt", wt_a.path), "a\n").unwrap();
+    std::fs::write(format!("{}/shared.txt", wt_b.path), "b\n").unwrap();
+    std::fs::write(format!("{}/other.txt", wt_c.path), "c\n").unwrap();
+    std::fs::write(ctx.temp_path().join("other.txt"), "main\n").unwrap();
+
+    let report = service
+        .detect_conflicts(&ctx.workspace_id)
+        .expect("Should detect conflicts");
+
+    assert_eq!(report.base_branch, "main");
+    assert_eq!(report.conflicts.len(), 2);
+
+    let between = report
+        .conflicts
+        .iter()
+        .find(|c| c.worktree_id == wt_a.id && c.other_worktree_id == wt_b.id)
+        .expect("feature-a and feature-b should conflict");
+    assert_eq!(between.paths, vec!["shared.txt".to_string()]);
+
+    let with_main = report
+        .conflicts
+        .iter()
+        .find(|c| c.worktree_id == wt_c.id && c.other_worktree_id == ctx.worktree_id)
+        .expect("feature-c should conflict with main");
+    assert_eq!(with_main.paths, vec!["other.txt".to_string()]);
+}
+
+#[test]
+fn test_shared_files_are_copied_into_new_worktrees() {
+    let ctx = TestContext::new();
+    std::fs::write(ctx.temp_path().join("README.md"), "readme\n").unwrap();
+    init_git_repo(ctx.temp_path());
+    // Untracked in the main worktree
+    std::fs::write(ctx.temp_path().join(".env"), "SECRET=1\n").unwrap();
+    std::fs::create_dir_all(ctx.temp_path().join("config")).unwrap();
+    std::fs::write(ctx.temp_path().join("config/local.json"), "{}").unwrap();
+    std::fs::write(ctx.temp_path().join("config/notes.txt"), "notes").unwrap();
+    let service = WorktreeService::new(ctx.pool.clone());
+
+    let preview = service
+        .set_shared_files(
+            &ctx.workspace_id,
+            SharedFilesConfig {
+                paths: vec![
+                    "./.env".to_string(),
+                    "config/*.json".to_string(),
+                    "missing.txt".to_string(),
+                ],
+                mode: SharedFileMode::Copy,
+            },
+        )
+        .expect("Should save shared files");
+    assert_eq!(preview.files, vec![".env", "config/local.json"]);
+    assert_eq!(preview.missing, vec!["missing.txt"]);
+    assert_eq!(
+        service.get_shared_files(&ctx.workspace_id).unwrap().paths,
+        vec![".env", "config/*.json", "missing.txt"]
+    );
+
+    let worktrees_dir = tempfile::tempdir().unwrap();
+    let path = worktrees_dir.path().join("feature");
+    let worktree = service
+        .create_worktree(&ctx.workspace_id, "feature", "feature", path.to_str(), true)
+        .expect("Should create worktree");
+
+    let read = |name: &str| std::fs::read_to_string(format!("{}/{}", worktree.path, name));
+    assert_eq!(read(".env").unwrap(), "SECRET=1\n");
+    assert_eq!(read("config/local.json").unwrap(), "{}");
+    assert!(read("config/notes.txt").is_err());
+
+    for invalid in ["../secrets", "/etc/passwd", ".git/config", "*/local.json"] {
+        let result = service.set_shared_files(
+            &ctx.workspace_id,
+            SharedFilesConfig {
+                paths: vec![invalid.to_string()],
+                mode: SharedFileMode::Symlink,
+            },
+        );
+        assert!(
+            matches!(result, Err(WorktreeError::Validation(_))),
+            "{} should be rejected",
+            invalid
+        );
+    }
+}
+
+#[test]
+fn test_branch_names_follow_workspace_template() {
+    let ctx = TestContext::new();
+    std::fs::write(ctx.temp_path().join("README.md"), "readme\n").unwrap();
+    init_git_repo(ctx.temp_path());
+    let service = WorktreeService::new(ctx.pool.clone());
+
+    assert_eq!(
+        service
+            .generate_branch_name(&ctx.workspace_id, "Fix Login Bug!")
+            .unwrap(),
+        "fix-login-bug"
+    );
+
+    let template = service
+        .set_branch_template(&ctx.workspace_id, "agent/{slug}")
+        .expect("Should save branch template");
+    assert_eq!(template.example, "agent/my-feature");
+
+    let worktrees_dir = tempfile::tempdir().unwrap();
+    let branch = service
+        .generate_branch_name(&ctx.workspace_id, "Login")
+        .unwrap();
+    assert_eq!(branch, "agent/login");
+    let path = worktrees_dir.path().join("login");
+    service
+        .create_worktree(&ctx.workspace_id, "login", &branch, path.to_str(), true)
+        .expect("Should create worktree");
+
+    // The first name is taken now
+    assert_eq!(
+        service
+            .generate_branch_name(&ctx.workspace_id, "Login")
+            .unwrap(),
+        "agent/login-2"
+    );
+
+    for invalid in ["agent/fixed", "agent..{slug}"] {
+        let result = service.set_branch_template(&ctx.workspace_id, invalid);
+        assert!(
+            matches!(result, Err(WorktreeError::Validation(_))),
+            "{} should be rejected",
+            invalid
+        );
+    }
+    assert_eq!(
+        service
+            .get_branch_template(&ctx.workspace_id)
+            .unwrap()
+            .template,
+        "agent/{slug}"
+    );
+}
+
+#[test]
+fn test_fetch_updates_and_prunes_remote_branches() {
+    let ctx = TestContext::new();
+    std::fs::write(ctx.temp_path().join("README.md"), "readme\n").unwrap();
+    let repo = init_git_repo(ctx.temp_path());
+    let upstream_dir = tempfile::tempdir().unwrap();
+    let upstream = init_git_repo(upstream_dir.path());
+    repo.remote("origin", upstream_dir.path().to_str().unwrap())
+        .unwrap();
+    let service = WorktreeService::new(ctx.pool.clone());
+
+    let head = upstream.head().unwrap().peel_to_commit().unwrap();
+    upstream.branch("feature", &head, false).unwrap();
+    assert!(!service
+        .list_branches(&ctx.worktree_id)
+        .unwrap()
+        .remote
+        .contains(&"feature".to_string()));
+
+    service
+        .fetch(&ctx.worktree_id, true)
+        .expect("Should fetch from a local remote");
+    assert!(service
+        .list_branches(&ctx.worktree_id)
+        .unwrap()
+        .remote
+        .contains(&"feature".to_string()));
+
+    upstream
+        .find_branch("feature", git2::BranchType::Local)
+        .unwrap()
+        .delete()
+        .unwrap();
+    service.fetch(&ctx.worktree_id, false).unwrap();
+    assert!(service
+        .list_branches(&ctx.worktree_id)
+        .unwrap()
+        .remote
+        .contains(&"feature".to_string()));
+    service.fetch(&ctx.worktree_id, true).unwrap();
+    assert!(!service
+        .list_branches(&ctx.worktree_id)
+        .unwrap()
+        .remote
+        .contains(&"feature".to_string()));
+}
+
+#[test]
+fn test_stash_parks_changes_that_block_checkout() {
+    let ctx = TestContext::new();
+    std::fs::write(ctx.temp_path().join("README.md"), "readme\n").unwrap();
+    let repo = init_git_repo(ctx.temp_path());
+    let service = WorktreeService::new(ctx.pool.clone());
+
+    assert!(service
+        .stash_save(&ctx.worktree_id, None, true)
+        .unwrap()
+        .is_none());
+
+    // A branch whose README differs, so a dirty README blocks checkout
+    let head = repo.head().unwrap().peel_to_commit().unwrap();
+    repo.branch("other", &head, false).unwrap();
+    service
+        .checkout_branch(&ctx.worktree_id, "other", false, false, false)
+        .unwrap();
+    std::fs::write(ctx.temp_path().join("README.md"), "other\n").unwrap();
+    let mut index = repo.index().unwrap();
+    index.add_path(std::path::Path::new("README.md")).unwrap();
+    index.write().unwrap();
+    let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+    let sig = git2::Signature::now("Test", "test@example.com").unwrap();
+    repo.commit(Some("HEAD"), &sig, &sig, "Change README", &tree, &[&head])
+        .unwrap();
+
+    std::fs::write(ctx.temp_path().join("README.md"), "dirty\n").unwrap();
+    assert!(service
+        .checkout_branch(&ctx.worktree_id, "main", false, false, false)
+        .is_err());
+
+    let worktree = service
+        .checkout_branch(&ctx.worktree_id, "main", false, true, false)
+        .expect("Should stash and check out");
+    assert_eq!(worktree.branch, "main");
+    let read = || std::fs::read_to_string(ctx.temp_path().join("README.md")).unwrap();
+    assert_eq!(read(), "readme\n");
+
+    let stashes = service.stash_list(&ctx.worktree_id).unwrap();
+    assert_eq!(stashes.len(), 1);
+    assert!(stashes[0]
+        .message
+        .contains("Auto-stash before checking out main"));
+
+    service
+        .checkout_branch(&ctx.worktree_id, "other", false, false, false)
+        .unwrap();
+    service.stash_pop(&ctx.worktree_id, 0).unwrap();
+    assert_eq!(read(), "dirty\n");
+    assert!(service.stash_list(&ctx.worktree_id).unwrap().is_empty());
+}
+
+#[test]
+fn test_commit_log_lists_commits_not_on_base() {
+    let ctx = TestContext::new();
+    std::fs::write(ctx.temp_path().join("README.md"), "readme\n").unwrap();
+    init_git_repo(ctx.temp_path());
+    let service = WorktreeService::new(ctx.pool.clone());
+
+    let worktrees_dir = tempfile::tempdir().unwrap();
+    let path = worktrees_dir.path().join("feature");
+    let worktree = service
+        .create_worktree(&ctx.workspace_id, "feature", "feature", path.to_str(), true)
+        .expect("Should create worktree");
+    assert!(service
+        .get_commit_log(&worktree.id, 50, 0)
+        .unwrap()
+        .is_empty());
+
+    let repo = git2::Repository::open(&worktree.path).unwrap();
+    let sig = git2::Signature::now("Agent", "agent@example.com").unwrap();
+    for (message, files) in [
+        ("Add a", &["a.txt"][..]),
+        ("Add b and c", &["b.txt", "c.txt"]),
+    ] {
+        for file in files {
+            std::fs::write(path.join(file), *file).unwrap();
+        }
+        let mut index = repo.index().unwrap();
+        index
+            .add_all(["*"], git2::IndexAddOption::DEFAULT, None)
+            .unwrap();
+        index.write().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let parent = repo.head().unwrap().peel_to_commit().unwrap();
+        repo.commit(Some("HEAD"), &sig, &sig, message, &tree, &[&parent])
+            .unwrap();
+    }
+
+    let log = service.get_commit_log(&worktree.id, 50, 0).unwrap();
+    assert_eq!(log.len(), 2);
+    assert_eq!(log[0].message, "Add b and c");
+    assert_eq!(log[0].files_changed, 2);
+    assert_eq!(log[0].author, "Agent");
+    assert_eq!(log[1].files_changed, 1);
+
+    let page = service.get_commit_log(&worktree.id, 1, 1).unwrap();
+    assert_eq!(page.len(), 1);
+    assert_eq!(page[0].message, "Add a");
+
+    // The main worktree has no base, so its whole history is listed
+    let main_log = service.get_commit_log(&ctx.worktree_id, 50, 0).unwrap();
+    assert_eq!(main_log.len(), 1);
+    assert_eq!(main_log[0].message, "Initial commit");
+}
+
+#[test]
+fn test_git_status_compares_with_base_branch() {
+    let ctx = TestContext::new();
+    std::fs::write(ctx.temp_path().join("README.md"), "readme\n").unwrap();
+    init_git_repo(ctx.temp_path());
+    let service = WorktreeService::new(ctx.pool.clone());
+
+    let worktrees_dir = tempfile::tempdir().unwrap();
+    let path = worktrees_dir.path().join("feature");
+    let worktree = service
+        .create_worktree(&ctx.workspace_id, "feature", "feature", path.to_str(), true)
+        .expect("Should create worktree");
+
+    let commit = |repo_path: &std::path::Path, file: &str| {
+        let repo = git2::Repository::open(repo_path).unwrap();
+        std::fs::write(repo_path.join(file), file).unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_path(std::path::Path::new(file)).unwrap();
+        index.write().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let sig = git2::Signature::now("Test", "test@example.com").unwrap();
+        let parent = repo.head().unwrap().peel_to_commit().unwrap();
+        repo.commit(Some("HEAD"), &sig, &sig, file, &tree, &[&parent])
+            .unwrap();
+    };
+    commit(&path, "feature.txt");
+    commit(ctx.temp_path(), "main-1.txt");
+    commit(ctx.temp_path(), "main-2.txt");
+
+    // Fresh branches have no upstream, but are still compared with main
+    let status = service.get_git_status(&worktree.id).unwrap();
+    assert_eq!((status.ahead, status.behind), (0, 0));
+    assert_eq!(status.base_branch.as_deref(), Some("main"));
+    assert_eq!((status.ahead_of_base, status.behind_base), (1, 2));
+
+    assert_eq!(
+        service
+            .set_base_branch(&ctx.workspace_id, "develop")
+            .unwrap(),
+        "develop"
+    );
+    let status = service.get_git_status(&worktree.id).unwrap();
+    assert!(status.base_branch.is_none());
+    assert_eq!((status.ahead_of_base, status.behind_base), (0, 0));
+
+    assert!(matches!(
+        service.set_base_branch(&ctx.workspace_id, "bad..name"),
+        Err(WorktreeError::Validation(_))
+    ));
+    assert_eq!(
+        service.set_base_branch(&ctx.workspace_id, "").unwrap(),
+        "main"
+    );
+}
+
+#[test]
+fn test_git_status_options_limit_and_paginate() {
+    let ctx = TestContext::new();
+    std::fs::write(ctx.temp_path().join("README.md"), "readme\n").unwrap();
+    init_git_repo(ctx.temp_path());
+    let service = WorktreeService::new(ctx.pool.clone());
+
+    std::fs::write(ctx.temp_path().join("README.md"), "changed\n").unwrap();
+    for name in ["a.txt", "b.txt", "c.log"] {
+        std::fs::write(ctx.temp_path().join(name), name).unwrap();
+    }
+
+    let status = service.get_git_status(&ctx.worktree_id).unwrap();
+    assert_eq!(status.total_entries, 4);
+    assert!(!status.has_more);
+
+    let options = GitStatusOptions {
+        max_entries: Some(2),
+        offset: 1,
+        exclude: vec!["*.log".to_string()],
+        skip_untracked: false,
+    };
+    let page = service
+        .get_git_status_with(&ctx.worktree_id, &options)
+        .unwrap();
+    assert_eq!(page.total_entries, 3);
+    assert!(!page.has_more);
+    assert!(page.modified.is_empty());
+    assert_eq!(page.untracked, vec!["a.txt", "b.txt"]);
+
+    let options = GitStatusOptions {
+        max_entries: Some(1),
+        skip_untracked: true,
+        ..Default::default()
+    };
+    let page = service
+        .get_git_status_with(&ctx.worktree_id, &options)
+        .unwrap();
+    assert_eq!(page.total_entries, 1);
+    assert_eq!(page.modified, vec!["README.md"]);
+    assert!(page.untracked.is_empty());
+    assert!(!page.is_clean);
+}
+
+#[test]
+fn test_sparse_checkout_only_checks_out_configured_dirs() {
+    let ctx = TestContext::new();
+    std::fs::write(ctx.temp_path().join("README.md"), "readme\n").unwrap();
+    for dir in ["apps/web", "apps/api", "libs"] {
+        std::fs::create_dir_all(ctx.temp_path().join(dir)).unwrap();
+        std::fs::write(ctx.temp_path().join(dir).join("index.ts"), dir).unwrap();
+    }
+    init_git_repo(ctx.temp_path());
+    let service = WorktreeService::new(ctx.pool.clone());
+
+    let dirs = service
+        .set_sparse_checkout(
+            &ctx.workspace_id,
+            vec![
+                "./apps/web/".to_string(),
+                "libs".to_string(),
+                "".to_string(),
+            ],
+        )
+        .expect("Should save sparse checkout");
+    assert_eq!(dirs, vec!["apps/web", "libs"]);
+
+    let worktrees_dir = tempfile::tempdir().unwrap();
+    let path = worktrees_dir.path().join("sparse");
+    let worktree = service
+        .create_worktree(&ctx.workspace_id, "sparse", "sparse", path.to_str(), true)
+        .expect("Should create sparse worktree");
+    assert_eq!(
+        worktree.sparse_patterns,
+        Some(vec!["apps/web".to_string(), "libs".to_string()])
+    );
+    assert!(path.join("README.md").exists());
+    assert!(path.join("apps/web/index.ts").exists());
+    assert!(path.join("libs/index.ts").exists());
+    assert!(!path.join("apps/api").exists());
+    assert!(service.get_git_status(&worktree.id).unwrap().is_clean);
+
+    // The main worktree is untouched
+    assert!(ctx.temp_path().join("apps/api/index.ts").exists());
+
+    for invalid in ["../other", "apps/*"] {
+        assert!(matches!(
+            service.set_sparse_checkout(&ctx.workspace_id, vec![invalid.to_string()]),
+            Err(WorktreeError::Validation(_))
+        ));
+    }
+    assert!(service
+        .set_sparse_checkout(&ctx.workspace_id, Vec::new())
+        .unwrap()
+        .is_empty());
+    let full = service
+        .create_worktree(
+            &ctx.workspace_id,
+            "full",
+            "full",
+            worktrees_dir.path().join("full").to_str(),
+            true,
+        )
+        .unwrap();
+    assert!(full.sparse_patterns.is_none());
+    assert!(worktrees_dir.path().join("full/apps/api/index.ts").exists());
+}
+
+#[test]
+fn test_running_agents_keep_worktree_from_being_deleted_or_switched() {
+    let ctx = TestContext::new();
+    std::fs::write(ctx.temp_path().join("README.md"), "readme\n").unwrap();
+    init_git_repo(ctx.temp_path());
+    let service = WorktreeService::new(ctx.pool.clone());
+    let agent_repo = AgentRepository::new(ctx.pool.clone());
+
+    let worktrees_dir = tempfile::tempdir().unwrap();
+    let path = worktrees_dir.path().join("feature");
+    let worktree = service
+        .create_worktree(&ctx.workspace_id, "feature", "feature", path.to_str(), true)
+        .expect("Should create worktree");
+    let agent = fixtures::create_agent_with_name(&worktree.id, "Builder");
+    agent_repo.create(&agent).unwrap();
+    agent_repo
+        .update_status(&agent.id, AgentStatus::Running, Some(1))
+        .unwrap();
+
+    match service.delete_worktree(&worktree.id, false) {
+        Err(WorktreeError::Busy {
+            worktree_id,
+            agents,
+        }) => {
+            assert_eq!(worktree_id, worktree.id);
+            assert_eq!(agents.len(), 1);
+            assert_eq!(agents[0].id, agent.id);
+            assert_eq!(agents[0].name, "Builder");
+        }
+        other => panic!("Expected WorktreeBusy, got {:?}", other),
+    }
+    assert!(matches!(
+        service.checkout_branch(&worktree.id, "main-copy", true, false, false),
+        Err(WorktreeError::Busy { .. })
+    ));
+
+    agent_repo
+        .update_status(&agent.id, AgentStatus::Waiting, Some(1))
+        .unwrap();
+    assert!(service.running_agents(&worktree.id).unwrap().is_empty());
+    agent_repo
+        .update_status(&agent.id, AgentStatus::Running, Some(1))
+        .unwrap();
+
+    service
+        .delete_worktree(&worktree.id, true)
+        .expect("Force should delete a busy worktree");
+    assert!(!path.exists());
+}
+
+#[test]
+fn test_worktrees_follow_workspace_sort_mode() {
+    let ctx = TestContext::new();
+    let service = WorktreeService::new(ctx.pool.clone());
+    let worktree_repo = WorktreeRepository::new(ctx.pool.clone());
+    let agent_repo = AgentRepository::new(ctx.pool.clone());
+
+    // Added after the context's "main" worktree, in this display order
+    let agents = [
+        ("charlie", vec![AgentStatus::Idle]),
+        ("Bravo", vec![AgentStatus::Idle, AgentStatus::Running]),
+        ("delta", vec![]),
+        ("alpha", vec![AgentStatus::Running, AgentStatus::Waiting]),
+    ];
+    for (order, (name, statuses)) in agents.iter().enumerate() {
+        let mut worktree = fixtures::create_worktree_with_branch(&ctx.workspace_id, name);
+        worktree.display_order = order as i32 + 1;
+        worktree_repo.create(&worktree).unwrap();
+        for status in statuses {
+            let agent = fixtures::create_agent(&worktree.id);
+            agent_repo.create(&agent).unwrap();
+            agent_repo
+                .update_status(&agent.id, *status, Some(1))
+                .unwrap();
+        }
+    }
+    let names = |sort_mode: SortMode| {
+        service
+            .set_sort_mode(&ctx.workspace_id, sort_mode)
+            .expect("Should save sort mode");
+        service
+            .list_worktrees(&ctx.workspace_id)
+            .unwrap()
+            .into_iter()
+            .map(|w| w.name)
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(
+        service.get_sort_mode(&ctx.workspace_id).unwrap(),
+        SortMode::Free
+    );
+    assert_eq!(
+        names(SortMode::Free),
+        ["main", "charlie", "Bravo", "delta", "alpha"]
+    );
+    assert_eq!(
+        names(SortMode::Name),
+        ["alpha", "Bravo", "charlie", "delta", "main"]
+    );
+    // Waiting first, then running, then idle, then worktrees without agents
+    assert_eq!(
+        names(SortMode::Status),
+        ["alpha", "Bravo", "charlie", "main", "delta"]
+    );
+
+    // The workspace view lists worktrees in the same order
+    let details = WorkspaceService::new(ctx.pool.clone())
+        .get_workspace_with_details(&ctx.workspace_id)
+        .unwrap();
+    let detail_names: Vec<_> = details
+        .worktrees
+        .iter()
+        .map(|w| w.worktree.name.as_str())
+        .collect();
+    assert_eq!(detail_names, ["alpha", "Bravo", "charlie", "main", "delta"]);
+
+    assert!(service.set_sort_mode("ws_missing", SortMode::Name).is_err());
+}
+
+#[test]
+fn test_archived_worktrees_keep_checkout_and_agents_until_purged() {
+    let ctx = TestContext::new();
+    std::fs::write(ctx.temp_path().join("README.md"), "readme\n").unwrap();
+    init_git_repo(ctx.temp_path());
+    let service = WorktreeService::new(ctx.pool.clone());
+    let workspace_service = WorkspaceService::new(ctx.pool.clone());
+    let agent_repo = AgentRepository::new(ctx.pool.clone());
+
+    let worktrees_dir = tempfile::tempdir().unwrap();
+    let path = worktrees_dir.path().join("feature");
+    let worktree = service
+        .create_worktree(&ctx.workspace_id, "feature", "feature", path.to_str(), true)
+        .expect("Should create worktree");
+    let agent = fixtures::create_agent(&worktree.id);
+    agent_repo.create(&agent).unwrap();
+
+    assert!(matches!(
+        service.archive_worktree(&ctx.worktree_id, false),
+        Err(WorktreeError::Validation(_))
+    ));
+
+    let archived = service
+        .archive_worktree(&worktree.id, false)
+        .expect("Should archive worktree");
+    assert!(archived.archived_at.is_some());
+    assert!(path.exists());
+    assert!(agent_repo.find_by_id(&agent.id).unwrap().is_some());
+    let ids = |worktrees: Vec<claude_manager_lib::types::Worktree>| {
+        worktrees.into_iter().map(|w| w.id).collect::<Vec<_>>()
+    };
+    assert_eq!(
+        ids(service.list_worktrees(&ctx.workspace_id).unwrap()),
+        [ctx.worktree_id.as_str()]
+    );
+    assert_eq!(
+        ids(service.list_archived_worktrees(&ctx.workspace_id).unwrap()),
+        [worktree.id.as_str()]
+    );
+    assert_eq!(
+        workspace_service
+            .get_workspace(&ctx.workspace_id)
+            .unwrap()
+            .worktree_count,
+        1
+    );
+    assert!(service.archive_worktree(&worktree.id, false).is_err());
+
+    let restored = service
+        .restore_worktree(&worktree.id)
+        .expect("Should restore worktree");
+    assert!(restored.archived_at.is_none());
+    assert_eq!(service.list_worktrees(&ctx.workspace_id).unwrap().len(), 2);
+    assert!(service
+        .list_archived_worktrees(&ctx.workspace_id)
+        .unwrap()
+        .is_empty());
+    assert!(service.restore_worktree(&worktree.id).is_err());
+
+    // Deleting an archived worktree purges it from git
+    service.archive_worktree(&worktree.id, false).unwrap();
+    service
+        .delete_worktree(&worktree.id, false)
+        .expect("Should purge archived worktree");
+    assert!(!path.exists());
+    assert!(service
+        .list_archived_worktrees(&ctx.workspace_id)
+        .unwrap()
+        .is_empty());
+}
+
+#[test]
+fn test_cancelled_or_failed_creation_leaves_nothing_behind() {
+    let ctx = TestContext::new();
+    std::fs::write(ctx.temp_path().join("README.md"), "readme\n").unwrap();
+    init_git_repo(ctx.temp_path());
+    let service = WorktreeService::new(ctx.pool.clone());
+    let repo = git2::Repository::open(ctx.temp_path()).unwrap();
+    let worktrees_dir = tempfile::tempdir().unwrap();
+
+    let cancel = CancellationToken::new();
+    cancel.cancel();
+    let path = worktrees_dir.path().join("cancelled");
+    let result = service.create_worktree_cancellable(
+        &ctx.workspace_id,
+        "cancelled",
+        "cancelled",
+        path.to_str(),
+        true,
+        &cancel,
+    );
+    assert!(matches!(result, Err(WorktreeError::Cancelled)));
+    assert!(!path.exists());
+    assert!(repo
+        .find_branch("cancelled", git2::BranchType::Local)
+        .is_err());
+
+    // Git refuses to add a worktree into a directory that has files in it
+    let occupied = worktrees_dir.path().join("occupied");
+    std::fs::create_dir_all(&occupied).unwrap();
+    std::fs::write(occupied.join("keep.txt"), "keep").unwrap();
+    let result = service.create_worktree(
+        &ctx.workspace_id,
+        "occupied",
+        "occupied",
+        occupied.to_str(),
+        true,
+    );
+    assert!(matches!(result, Err(WorktreeError::Git(_))));
+    assert_eq!(
+        std::fs::read_to_string(occupied.join("keep.txt")).unwrap(),
+        "keep"
+    );
+    assert!(repo
+        .find_branch("occupied", git2::BranchType::Local)
+        .is_err());
+
+    // A failed add at another worktree's path leaves that worktree alone
+    let existing_path = worktrees_dir.path().join("existing");
+    let existing = service
+        .create_worktree(
+            &ctx.workspace_id,
+            "existing",
+            "existing",
+            existing_path.to_str(),
+            true,
+        )
+        .expect("Should create worktree");
+    std::fs::write(existing_path.join("precious.txt"), "uncommitted").unwrap();
+    let result = service.create_worktree(
+        &ctx.workspace_id,
+        "other",
+        "other",
+        existing_path.to_str(),
+        true,
+    );
+    assert!(result.is_err());
+    assert_eq!(
+        std::fs::read_to_string(existing_path.join("precious.txt")).unwrap(),
+        "uncommitted"
+    );
+    assert!(repo.find_worktree("existing").is_ok());
+    assert!(repo.find_branch("other", git2::BranchType::Local).is_err());
+
+    let worktrees = service.list_worktrees(&ctx.workspace_id).unwrap();
+    assert!(worktrees
+        .iter()
+        .all(|w| w.name != "cancelled" && w.name != "occupied" && w.name != "other"));
+    assert!(worktrees.iter().any(|w| w.id == existing.id));
+}
+
+#[test]
+fn test_worktree_lifecycle_with_fake_git() {
+    let ctx = TestContext::new();
+    let git = Arc::new(FakeGit::new());
+    git.add_repo(ctx.temp_path().to_str().unwrap(), "main");
+    let service = WorktreeService::new(ctx.pool.clone()).with_git(git.clone());
+
+    let worktree = service
+        .create_worktree(
+            &ctx.workspace_id,
+            "feature",
+            "feature",
+            Some("/repos/feature"),
+            true,
+        )
+        .expect("Should create worktree");
+    assert_eq!(worktree.branch, "feature");
+    assert_eq!(worktree.path, "/repos/feature");
+    assert!(git.is_valid_repository("/repos/feature"));
+
+    // Branch names already in the repository are skipped
+    assert_eq!(
+        service
+            .generate_branch_name(&ctx.workspace_id, "Feature")
+            .unwrap(),
+        "feature-2"
+    );
+
+    // Switching branches can stash local changes first
+    let switched = service
+        .checkout_branch(&worktree.id, "fix", true, true, false)
+        .expect("Should check out branch");
+    assert_eq!(switched.branch, "fix");
+    assert_eq!(git.get_current_branch("/repos/feature").unwrap(), "fix");
+    let stashes = service.stash_list(&worktree.id).unwrap();
+    assert_eq!(stashes.len(), 1);
+    assert_eq!(stashes[0].message, "Auto-stash before checking out fix");
+
+    // Git failures surface as git errors and leave no record behind
+    let result = service.create_worktree(
+        &ctx.workspace_id,
+        "again",
+        "fix",
+        Some("/repos/again"),
+        true,
+    );
+    assert!(matches!(result, Err(WorktreeError::Git(_))));
+    assert!(!service
+        .list_worktrees(&ctx.workspace_id)
+        .unwrap()
+        .iter()
+        .any(|w| w.name == "again"));
+
+    service
+        .delete_worktree(&worktree.id, false)
+        .expect("Should delete worktree");
+    assert!(!git.is_valid_repository("/repos/feature"));
+    assert!(matches!(
+        service.get_worktree(&worktree.id),
+        Err(WorktreeError::NotFound(_))
+    ));
+}
+
+#[test]
+fn test_worktree_update_conflict() {
+    let ctx = TestContext::new();
+    let service = WorktreeService::new(ctx.pool.clone());
+    let loaded = service.get_worktree(&ctx.worktree_id).unwrap();
+    let rename = |name: &str, expected_updated_at: Option<String>| UpdateWorktreeInput {
+        name: Some(name.to_string()),
+        sort_mode: None,
+        display_order: None,
+        expected_updated_at,
+    };
+
+    // Both windows loaded the same worktree; the first save wins
+    let first = service
+        .update_worktree(
+            &ctx.worktree_id,
+            rename("First", Some(loaded.updated_at.clone())),
+        )
+        .expect("Should update worktree");
+    let result = service.update_worktree(
+        &ctx.worktree_id,
+        rename("Second", Some(loaded.updated_at.clone())),
+    );
+    match result {
+        Err(WorktreeError::Conflict { id, updated_at }) => {
+            assert_eq!(id, ctx.worktree_id);
+            assert_eq!(updated_at, first.updated_at);
+        }
+        other => panic!("Expected a conflict, got {:?}", other),
+    }
+    assert_eq!(
+        service.get_worktree(&ctx.worktree_id).unwrap().name,
+        "First"
+    );
+
+    // Reloading resolves it
+    let second = service
+        .update_worktree(&ctx.worktree_id, rename("Second", Some(first.updated_at)))
+        .expect("Should update reloaded worktree");
+    assert_eq!(second.name, "Second");
+
+    // Without an expected version the latest save wins
+    service
+        .update_worktree(&ctx.worktree_id, rename("Third", None))
+        .expect("Should update worktree");
+}
+
+#[test]
+fn test_worktree_health() {
+    let ctx = TestContext::new();
+    let repo = init_git_repo(ctx.temp_path());
+    let service = WorktreeService::new(ctx.pool.clone());
+    let worktrees_dir = tempfile::tempdir().unwrap();
+    let create = |name: &str| {
+        let path = worktrees_dir.path().join(name);
+        service
+            .create_worktree(&ctx.workspace_id, name, name, path.to_str(), true)
+            .expect("Should create worktree")
+    };
+    let dirty = create("dirty");
+    let locked = create("locked");
+    let removed = create("removed");
+    let forgotten = create("forgotten");
+
+    std::fs::write(format!("{}/notes.txt", dirty.path), "wip\n").unwrap();
+    // A locked worktree on a disk that isn't mounted
+    repo.find_worktree("locked")
+        .unwrap()
+        .lock(Some("on a usb drive"))
+        .unwrap();
+    std::fs::remove_dir_all(&locked.path).unwrap();
+    std::fs::remove_dir_all(&removed.path).unwrap();
+    // The checkout is left but git has dropped its record of it
+    std::fs::remove_dir_all(ctx.temp_path().join(".git/worktrees/forgotten")).unwrap();
+
+    let health = service
+        .get_worktree_health(&ctx.workspace_id)
+        .expect("Should check worktree health");
+    let of = |id: &str| health.iter().find(|h| h.worktree_id == id).unwrap();
+
+    let main = of(&ctx.worktree_id);
+    assert_eq!(main.state, WorktreeState::Ok);
+    assert!(main.registered);
+
+    let dirty = of(&dirty.id);
+    assert_eq!(dirty.state, WorktreeState::Ok);
+    assert_eq!(dirty.dirty, Some(true));
+
+    let locked = of(&locked.id);
+    assert_eq!(locked.state, WorktreeState::Missing);
+    assert!(!locked.exists && locked.registered && locked.locked);
+    assert!(!locked.prunable);
+    assert_eq!(locked.dirty, None);
+
+    let removed = of(&removed.id);
+    assert_eq!(removed.state, WorktreeState::Missing);
+    assert!(removed.prunable && !removed.locked);
+
+    let forgotten = of(&forgotten.id);
+    assert_eq!(forgotten.state, WorktreeState::Broken);
+    assert!(forgotten.exists && !forgotten.registered);
+}
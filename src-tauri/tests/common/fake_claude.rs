@@ -0,0 +1,57 @@
+//! Fake `claude` binary for spawning real agent processes in tests
+//!
+//! The script in `fake_claude.sh` plays the interactive CLI in a PTY:
+//! prompts, sessions, slow output and SIGINT. It is written to a temporary
+//! directory once per test binary, since executing a file while another
+//! thread still has it open for writing fails with "text file busy".
+
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+use tempfile::TempDir;
+
+const SCRIPT: &str = include_str!("fake_claude.sh");
+
+/// File the fake CLI writes its arguments to, in its working directory
+pub const ARGS_FILE: &str = ".fake-claude-args";
+
+static INSTALL_DIR: OnceLock<TempDir> = OnceLock::new();
+
+/// Path of the fake CLI, to pass to `ProcessManager::new`
+pub fn path() -> String {
+    let dir = INSTALL_DIR.get_or_init(|| {
+        let dir = tempfile::tempdir().expect("Failed to create fake claude dir");
+        let path = dir.path().join("claude");
+        std::fs::write(&path, SCRIPT).expect("Failed to write fake claude");
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755))
+            .expect("Failed to make fake claude executable");
+        dir
+    });
+    dir.path().join("claude").to_string_lossy().to_string()
+}
+
+/// Arguments the fake CLI was last started with in `worktree_path`
+pub async fn last_args(worktree_path: &Path) -> Vec<String> {
+    let path: PathBuf = worktree_path.join(ARGS_FILE);
+    wait_until(Duration::from_secs(5), || path.exists()).await;
+    std::fs::read_to_string(&path)
+        .expect("Fake claude did not record its arguments")
+        .lines()
+        .map(str::to_string)
+        .collect()
+}
+
+/// Poll `condition` until it holds, panicking after `timeout`
+pub async fn wait_until(timeout: Duration, mut condition: impl FnMut() -> bool) {
+    let deadline = Instant::now() + timeout;
+    while !condition() {
+        assert!(
+            Instant::now() < deadline,
+            "Condition not met within {:?}",
+            timeout
+        );
+        tokio::time::sleep(Duration::from_millis(25)).await;
+    }
+}
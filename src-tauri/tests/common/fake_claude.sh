@@ -0,0 +1,68 @@
+#!/bin/sh
+# Stand-in for the claude CLI in integration tests.
+#
+# Writes its arguments to .fake-claude-args in the working directory, prints
+# the session it runs, answers the first prompt, then reads messages at a
+# "> " prompt like the interactive CLI does:
+#   ask   asks "Do you want to proceed? (y/n)" and waits for the answer
+#   slow  prints a line every half second for two seconds
+#   exit  exits with code 0
+# Anything else is echoed back. SIGINT prints "Interrupted" and exits 130.
+
+printf '%s\n' "$@" > .fake-claude-args
+
+session=""
+resumed=""
+prompt=""
+while [ $# -gt 0 ]; do
+    case "$1" in
+        --session-id) session="$2"; shift 2 ;;
+        --resume) resumed="$2"; session="${session:-$2}"; shift 2 ;;
+        --model|--system-prompt|--append-system-prompt) shift 2 ;;
+        --*) shift ;;
+        *) prompt="$1"; shift ;;
+    esac
+done
+
+trap 'echo "Interrupted"; exit 130' INT
+
+if [ -n "$resumed" ]; then
+    echo "Resuming session $resumed"
+else
+    echo "Session $session"
+fi
+
+respond() {
+    case "$1" in
+        ask)
+            printf 'Do you want to proceed? (y/n) '
+            IFS= read -r answer
+            # The real CLI redraws its screen once a prompt is answered
+            printf '\033[2J\033[H'
+            if [ "$answer" = y ]; then echo "Proceeding"; else echo "Cancelled"; fi
+            ;;
+        slow)
+            for step in 1 2 3 4; do
+                echo "Working $step"
+                sleep 0.5
+            done
+            echo "Done"
+            ;;
+        exit)
+            echo "Bye"
+            exit 0
+            ;;
+        *)
+            echo "You said: $1"
+            ;;
+    esac
+}
+
+if [ -n "$prompt" ]; then
+    respond "$prompt"
+fi
+while true; do
+    printf '> '
+    IFS= read -r line || exit 0
+    respond "$line"
+done
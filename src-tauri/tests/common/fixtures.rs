@@ -44,6 +44,11 @@ pub fn create_worktree(workspace_id: &str) -> Worktree {
         is_main: true,
         created_at: now.clone(),
         updated_at: now,
+        pr_url: None,
+        sparse_patterns: None,
+        archived_at: None,
+        pinned: false,
+        missing_since: None,
     }
 }
 
@@ -77,8 +82,17 @@ pub fn create_agent(worktree_id: &str) -> Agent {
         updated_at: now,
         started_at: None,
         stopped_at: None,
+        run_duration_ms: None,
         deleted_at: None,
         parent_agent_id: None,
+        status_reason: None,
+        task_group_id: None,
+        fork_session_id: None,
+        system_prompt: None,
+        append_system_prompt: None,
+        allowed_tools: Vec::new(),
+        disallowed_tools: Vec::new(),
+        tags: Vec::new(),
     }
 }
 
@@ -6,9 +6,22 @@
 #![allow(dead_code)]
 
 use std::collections::HashMap;
+use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
-use claude_manager_lib::types::{Agent, AgentMode, AgentStatus, Permission};
+use tokio::sync::broadcast;
+
+use claude_manager_lib::services::{
+    DivergedFiles, GitError, GitOps, LaunchPrompts, ProcessControl, ProcessError, ProcessEvent,
+    ProcessSnapshot, SessionLaunch, StampedEvent, ToolAccess, WorktreeInfo,
+};
+use claude_manager_lib::types::{
+    Agent, AgentMode, AgentStatus, BranchInfo, ClaudeProfile, CommitInfo, GitStatusInfo,
+    GitStatusOptions, Permission, StashEntry,
+};
+use claude_manager_lib::util::cancel::CancellationToken;
+use claude_manager_lib::util::redact::Redactor;
 
 /// Mock process that simulates a running agent
 #[derive(Debug, Clone)]
@@ -17,6 +30,7 @@ pub struct MockProcess {
     pub pid: u32,
     pub output_lines: Vec<String>,
     pub is_running: bool,
+    pub last_activity: Option<chrono::DateTime<chrono::Utc>>,
 }
 
 impl MockProcess {
@@ -26,6 +40,7 @@ impl MockProcess {
             pid: 12345,
             output_lines: Vec::new(),
             is_running: false,
+            last_activity: None,
         }
     }
 
@@ -44,23 +59,43 @@ impl MockProcess {
 
     pub fn add_output(&mut self, line: &str) {
         self.output_lines.push(line.to_string());
+        self.last_activity = Some(chrono::Utc::now());
     }
 }
 
 /// Mock process manager for testing without spawning real processes
-#[derive(Debug, Default)]
+///
+/// Implements `ProcessControl`, so services can be built on it in place of
+/// the real `ProcessManager`.
 pub struct MockProcessManager {
     processes: Arc<Mutex<HashMap<String, MockProcess>>>,
     spawn_should_fail: Arc<Mutex<bool>>,
     next_pid: Arc<Mutex<u32>>,
+    sent_messages: Arc<Mutex<Vec<(String, String)>>>,
+    read_only: Arc<Mutex<bool>>,
+    idle_timeouts: Arc<Mutex<HashMap<String, Duration>>>,
+    event_tx: broadcast::Sender<StampedEvent>,
+    next_seq: Arc<Mutex<u64>>,
+}
+
+impl Default for MockProcessManager {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl MockProcessManager {
     pub fn new() -> Self {
+        let (event_tx, _) = broadcast::channel(100);
         Self {
             processes: Arc::new(Mutex::new(HashMap::new())),
             spawn_should_fail: Arc::new(Mutex::new(false)),
             next_pid: Arc::new(Mutex::new(10000)),
+            sent_messages: Arc::new(Mutex::new(Vec::new())),
+            read_only: Arc::new(Mutex::new(false)),
+            idle_timeouts: Arc::new(Mutex::new(HashMap::new())),
+            event_tx,
+            next_seq: Arc::new(Mutex::new(0)),
         }
     }
 
@@ -69,46 +104,182 @@ impl MockProcessManager {
         *self.spawn_should_fail.lock().unwrap() = fails;
     }
 
-    /// Spawn a mock agent process
-    pub fn spawn_agent(
+    /// Messages sent to agents so far, as (agent ID, message)
+    pub fn sent_messages(&self) -> Vec<(String, String)> {
+        self.sent_messages.lock().unwrap().clone()
+    }
+
+    /// The idle timeout set for an agent, if any
+    pub fn idle_timeout(&self, agent_id: &str) -> Option<Duration> {
+        self.idle_timeouts.lock().unwrap().get(agent_id).copied()
+    }
+
+    /// Get the output lines for an agent
+    pub fn get_output(&self, agent_id: &str) -> Vec<String> {
+        self.processes
+            .lock()
+            .unwrap()
+            .get(agent_id)
+            .map(|p| p.output_lines.clone())
+            .unwrap_or_default()
+    }
+
+    /// Get the number of running processes
+    pub fn running_count(&self) -> usize {
+        self.get_running_count()
+    }
+
+    /// Inject output for a specific agent (for testing)
+    pub fn inject_output(&self, agent_id: &str, output: &str) {
+        let mut processes = self.processes.lock().unwrap();
+        if let Some(process) = processes.get_mut(agent_id) {
+            process.add_output(output);
+        }
+    }
+
+    /// Simulate an agent finishing
+    pub fn simulate_finish(&self, agent_id: &str) {
+        self.exit(agent_id, Some(0), false);
+    }
+
+    /// Simulate an agent error
+    pub fn simulate_error(&self, agent_id: &str, error_msg: &str) {
+        self.inject_output(agent_id, &format!("Error: {}", error_msg));
+        self.exit(agent_id, Some(1), false);
+    }
+
+    /// Publish an event to subscribers as the real manager would
+    pub fn emit(&self, event: ProcessEvent) {
+        let mut seq = self.next_seq.lock().unwrap();
+        *seq += 1;
+        // No subscribers is fine
+        let _ = self.event_tx.send(StampedEvent {
+            seq: *seq,
+            timestamp: chrono::Utc::now(),
+            event,
+        });
+    }
+
+    fn exit(&self, agent_id: &str, code: Option<i32>, stopped_by_user: bool) {
+        let was_running = match self.processes.lock().unwrap().get_mut(agent_id) {
+            Some(process) if process.is_running => {
+                process.stop();
+                true
+            }
+            _ => false,
+        };
+        if was_running {
+            self.emit(ProcessEvent::Exit {
+                agent_id: agent_id.to_string(),
+                code,
+                signal: None,
+                stopped_by_user,
+            });
+        }
+    }
+}
+
+impl ProcessControl for MockProcessManager {
+    fn spawn_agent(
         &self,
         agent_id: &str,
         _worktree_path: &str,
         _mode: AgentMode,
-        _permissions: &[Permission],
-        _initial_prompt: Option<&str>,
-        _session_id: Option<&str>,
-    ) -> Result<u32, MockProcessError> {
+        _tools: ToolAccess<'_>,
+        _prompts: LaunchPrompts<'_>,
+        session: SessionLaunch<'_>,
+    ) -> Result<(u32, String), ProcessError> {
         if *self.spawn_should_fail.lock().unwrap() {
-            return Err(MockProcessError::SpawnFailed);
+            return Err(ProcessError::SpawnFailed("mock spawn failure".to_string()));
+        }
+        if self.is_running(agent_id) {
+            return Err(ProcessError::AlreadyRunning(agent_id.to_string()));
         }
 
-        let mut next_pid = self.next_pid.lock().unwrap();
-        let pid = *next_pid;
-        *next_pid += 1;
-
-        let mut process = MockProcess::new(agent_id);
-        process.pid = pid;
+        let pid = {
+            let mut next_pid = self.next_pid.lock().unwrap();
+            let pid = *next_pid;
+            *next_pid += 1;
+            pid
+        };
+        let session_id = match session {
+            SessionLaunch::Resume(session_id) => session_id.to_string(),
+            SessionLaunch::New | SessionLaunch::Fork(_) => format!("mock-session-{}", pid),
+        };
+
+        let mut process = MockProcess::new(agent_id).with_pid(pid);
         process.start();
+        self.processes
+            .lock()
+            .unwrap()
+            .insert(agent_id.to_string(), process);
+        self.emit(ProcessEvent::Status {
+            agent_id: agent_id.to_string(),
+            status: AgentStatus::Running,
+            reason: None,
+        });
+
+        Ok((pid, session_id))
+    }
 
-        self.processes.lock().unwrap().insert(agent_id.to_string(), process);
+    fn stop_agent(&self, agent_id: &str, _force: bool) -> Result<(), ProcessError> {
+        if !self.is_running(agent_id) {
+            return Err(ProcessError::AgentNotFound(agent_id.to_string()));
+        }
+        self.exit(agent_id, None, true);
+        Ok(())
+    }
 
-        Ok(pid)
+    fn stop_all(&self) {
+        let running: Vec<String> = self
+            .processes
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|p| p.is_running)
+            .map(|p| p.id.clone())
+            .collect();
+        for agent_id in running {
+            self.exit(&agent_id, None, true);
+        }
     }
 
-    /// Stop a mock agent process
-    pub fn stop_agent(&self, agent_id: &str, _force: bool) -> Result<(), MockProcessError> {
-        let mut processes = self.processes.lock().unwrap();
-        if let Some(process) = processes.get_mut(agent_id) {
-            process.stop();
-            Ok(())
-        } else {
-            Err(MockProcessError::NotFound)
+    fn send_message(&self, agent_id: &str, message: &str) -> Result<(), ProcessError> {
+        if self.is_read_only() {
+            return Err(ProcessError::ReadOnly);
+        }
+        if !self.is_running(agent_id) {
+            return Err(ProcessError::NotRunning(agent_id.to_string()));
         }
+        self.sent_messages
+            .lock()
+            .unwrap()
+            .push((agent_id.to_string(), message.to_string()));
+        Ok(())
     }
 
-    /// Check if a mock agent is running
-    pub fn is_running(&self, agent_id: &str) -> bool {
+    fn subscribe(&self) -> broadcast::Receiver<StampedEvent> {
+        self.event_tx.subscribe()
+    }
+
+    /// Mock agents have no terminal
+    fn subscribe_pty_output(
+        &self,
+        _agent_id: &str,
+    ) -> Option<(broadcast::Receiver<Vec<u8>>, Vec<u8>)> {
+        None
+    }
+
+    fn emit_output(&self, agent_id: &str, message_id: &str, content: &str, is_complete: bool) {
+        self.emit(ProcessEvent::Output {
+            agent_id: agent_id.to_string(),
+            message_id: message_id.to_string(),
+            content: content.to_string(),
+            is_complete,
+        });
+    }
+
+    fn is_running(&self, agent_id: &str) -> bool {
         self.processes
             .lock()
             .unwrap()
@@ -117,71 +288,354 @@ impl MockProcessManager {
             .unwrap_or(false)
     }
 
-    /// Get the output lines for an agent
-    pub fn get_output(&self, agent_id: &str) -> Vec<String> {
+    fn get_running_count(&self) -> usize {
         self.processes
             .lock()
             .unwrap()
-            .get(agent_id)
-            .map(|p| p.output_lines.clone())
-            .unwrap_or_default()
+            .values()
+            .filter(|p| p.is_running)
+            .count()
     }
 
-    /// Get the number of running processes
-    pub fn running_count(&self) -> usize {
+    fn running_agent_ids(&self) -> Vec<String> {
+        self.running_pids()
+            .into_iter()
+            .map(|(agent_id, _)| agent_id)
+            .collect()
+    }
+
+    fn running_pids(&self) -> Vec<(String, u32)> {
         self.processes
             .lock()
             .unwrap()
             .values()
             .filter(|p| p.is_running)
-            .count()
+            .map(|p| (p.id.clone(), p.pid))
+            .collect()
     }
 
-    /// Inject output for a specific agent (for testing)
-    pub fn inject_output(&self, agent_id: &str, output: &str) {
-        let mut processes = self.processes.lock().unwrap();
-        if let Some(process) = processes.get_mut(agent_id) {
-            process.add_output(output);
-        }
+    fn set_idle_timeout(&self, agent_id: &str, timeout: Option<Duration>) {
+        let mut idle_timeouts = self.idle_timeouts.lock().unwrap();
+        match timeout {
+            Some(timeout) => idle_timeouts.insert(agent_id.to_string(), timeout),
+            None => idle_timeouts.remove(agent_id),
+        };
     }
 
-    /// Simulate an agent finishing
-    pub fn simulate_finish(&self, agent_id: &str) {
-        let mut processes = self.processes.lock().unwrap();
-        if let Some(process) = processes.get_mut(agent_id) {
-            process.stop();
+    fn snapshot(&self, agent_id: &str) -> ProcessSnapshot {
+        let processes = self.processes.lock().unwrap();
+        let Some(process) = processes.get(agent_id) else {
+            return ProcessSnapshot::default();
+        };
+        ProcessSnapshot {
+            is_running: process.is_running,
+            pid: process.is_running.then_some(process.pid),
+            buffer_bytes: process.output_lines.join("\r\n").len(),
+            last_activity: process.last_activity,
+            ..Default::default()
         }
     }
 
-    /// Simulate an agent error
-    pub fn simulate_error(&self, agent_id: &str, error_msg: &str) {
-        let mut processes = self.processes.lock().unwrap();
-        if let Some(process) = processes.get_mut(agent_id) {
-            process.add_output(&format!("Error: {}", error_msg));
-            process.stop();
-        }
+    fn get_pty_buffer(&self, agent_id: &str) -> Option<Vec<u8>> {
+        self.processes
+            .lock()
+            .unwrap()
+            .get(agent_id)
+            .map(|p| p.output_lines.join("\r\n").into_bytes())
+    }
+
+    fn set_output_redactor(&self, _agent_id: &str, _redactor: Option<Arc<Redactor>>) {}
+
+    fn replace_output_redactors(&self, _redactor: Arc<Redactor>) {}
+
+    fn set_account(&self, _agent_id: &str, _profile: Option<ClaudeProfile>) {}
+
+    fn set_read_only(&self, read_only: bool) {
+        *self.read_only.lock().unwrap() = read_only;
+    }
+
+    fn is_read_only(&self) -> bool {
+        *self.read_only.lock().unwrap()
     }
 }
 
-/// Errors from the mock process manager
-#[derive(Debug, Clone, PartialEq, Eq)]
-pub enum MockProcessError {
-    SpawnFailed,
-    NotFound,
-    NotRunning,
+/// A repository known to `FakeGit`
+#[derive(Debug, Clone)]
+struct FakeRepo {
+    /// The main worktree first
+    worktrees: Vec<WorktreeInfo>,
+    branches: Vec<String>,
+    /// Most recent first
+    stashes: Vec<StashEntry>,
+}
+
+/// In-memory git repositories for testing services without real ones
+///
+/// Implements `GitOps`. Repositories and their worktrees are identified by
+/// path only; nothing is read from or written to disk.
+#[derive(Default)]
+pub struct FakeGit {
+    repos: Mutex<Vec<FakeRepo>>,
 }
 
-impl std::fmt::Display for MockProcessError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Self::SpawnFailed => write!(f, "Failed to spawn process"),
-            Self::NotFound => write!(f, "Process not found"),
-            Self::NotRunning => write!(f, "Process is not running"),
+impl FakeGit {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a repository whose main worktree is at `path`, on `branch`
+    pub fn add_repo(&self, path: &str, branch: &str) {
+        self.repos.lock().unwrap().push(FakeRepo {
+            worktrees: vec![WorktreeInfo {
+                path: path.to_string(),
+                branch: branch.to_string(),
+                is_main: true,
+                locked: false,
+                prunable: false,
+            }],
+            branches: vec![branch.to_string()],
+            stashes: Vec::new(),
+        });
+    }
+
+    /// Add a branch to the repository at `repo_path`
+    pub fn add_branch(&self, repo_path: &str, branch: &str) {
+        self.with_repo(repo_path, |repo| {
+            repo.branches.push(branch.to_string());
+            Ok(())
+        })
+        .unwrap();
+    }
+
+    /// Add a linked worktree as if it was created outside the app
+    pub fn add_linked_worktree(&self, repo_path: &str, path: &str, branch: &str) {
+        self.add_worktree(
+            repo_path,
+            path,
+            branch,
+            true,
+            &[],
+            &CancellationToken::new(),
+        )
+        .unwrap();
+    }
+
+    /// Run `f` on the repository one of whose worktrees is at `path`
+    fn with_repo<T>(
+        &self,
+        path: &str,
+        f: impl FnOnce(&mut FakeRepo) -> Result<T, GitError>,
+    ) -> Result<T, GitError> {
+        let mut repos = self.repos.lock().unwrap();
+        let repo = repos
+            .iter_mut()
+            .find(|repo| repo.worktrees.iter().any(|w| w.path == path))
+            .ok_or_else(|| GitError::NotARepo(path.to_string()))?;
+        f(repo)
+    }
+
+    fn worktree<'a>(repo: &'a mut FakeRepo, path: &str) -> &'a mut WorktreeInfo {
+        repo.worktrees.iter_mut().find(|w| w.path == path).unwrap()
+    }
+}
+
+impl GitOps for FakeGit {
+    fn is_valid_repository(&self, path: &str) -> bool {
+        self.with_repo(path, |_| Ok(())).is_ok()
+    }
+
+    fn common_dir(&self, path: &str) -> Result<PathBuf, GitError> {
+        self.with_repo(path, |repo| {
+            Ok(PathBuf::from(&repo.worktrees[0].path).join(".git"))
+        })
+    }
+
+    fn get_current_branch(&self, path: &str) -> Result<String, GitError> {
+        self.with_repo(path, |repo| Ok(Self::worktree(repo, path).branch.clone()))
+    }
+
+    fn list_worktrees(&self, path: &str) -> Result<Vec<WorktreeInfo>, GitError> {
+        self.with_repo(path, |repo| Ok(repo.worktrees.clone()))
+    }
+
+    fn add_worktree(
+        &self,
+        repo_path: &str,
+        worktree_path: &str,
+        branch: &str,
+        create_branch: bool,
+        _sparse_dirs: &[String],
+        cancel: &CancellationToken,
+    ) -> Result<WorktreeInfo, GitError> {
+        if cancel.is_cancelled() {
+            return Err(GitError::Cancelled);
+        }
+        if self.is_valid_repository(worktree_path) {
+            return Err(GitError::Command(format!(
+                "'{}' already exists",
+                worktree_path
+            )));
         }
+        self.with_repo(repo_path, |repo| {
+            let exists = repo.branches.iter().any(|b| b == branch);
+            if create_branch && exists {
+                return Err(GitError::Command(format!(
+                    "a branch named '{}' already exists",
+                    branch
+                )));
+            }
+            if !create_branch && !exists {
+                return Err(GitError::Command(format!("invalid reference: {}", branch)));
+            }
+            if !exists {
+                repo.branches.push(branch.to_string());
+            }
+            let info = WorktreeInfo {
+                path: worktree_path.to_string(),
+                branch: branch.to_string(),
+                is_main: false,
+                locked: false,
+                prunable: false,
+            };
+            repo.worktrees.push(info.clone());
+            Ok(info)
+        })
+    }
+
+    fn remove_worktree(&self, repo_path: &str, worktree_path: &str) -> Result<(), GitError> {
+        self.with_repo(repo_path, |repo| {
+            let before = repo.worktrees.len();
+            repo.worktrees
+                .retain(|w| w.is_main || w.path != worktree_path);
+            if repo.worktrees.len() == before {
+                return Err(GitError::Command(format!(
+                    "'{}' is not a working tree",
+                    worktree_path
+                )));
+            }
+            Ok(())
+        })
+    }
+
+    fn checkout_branch(
+        &self,
+        worktree_path: &str,
+        branch: &str,
+        create: bool,
+    ) -> Result<(), GitError> {
+        self.with_repo(worktree_path, |repo| {
+            let exists = repo.branches.iter().any(|b| b == branch);
+            match (create, exists) {
+                (true, true) => {
+                    return Err(GitError::Command(format!(
+                        "a branch named '{}' already exists",
+                        branch
+                    )))
+                }
+                (false, false) => {
+                    return Err(GitError::Command(format!("invalid reference: {}", branch)))
+                }
+                (true, false) => repo.branches.push(branch.to_string()),
+                (false, true) => {}
+            }
+            Self::worktree(repo, worktree_path).branch = branch.to_string();
+            Ok(())
+        })
+    }
+
+    fn list_branches(&self, path: &str) -> Result<BranchInfo, GitError> {
+        self.with_repo(path, |repo| {
+            Ok(BranchInfo {
+                local: repo.branches.clone(),
+                remote: Vec::new(),
+                current: Self::worktree(repo, path).branch.clone(),
+            })
+        })
+    }
+
+    fn fetch(&self, path: &str, _prune: bool, _token: Option<&str>) -> Result<(), GitError> {
+        self.with_repo(path, |_| Ok(()))
+    }
+
+    fn stash_save(
+        &self,
+        path: &str,
+        message: Option<&str>,
+        _include_untracked: bool,
+    ) -> Result<Option<StashEntry>, GitError> {
+        self.with_repo(path, |repo| {
+            let entry = StashEntry {
+                index: 0,
+                message: message.unwrap_or("Claude Manager stash").to_string(),
+                oid: format!("stash{}", repo.stashes.len()),
+            };
+            repo.stashes.insert(0, entry.clone());
+            reindex(&mut repo.stashes);
+            Ok(Some(entry))
+        })
+    }
+
+    fn stash_list(&self, path: &str) -> Result<Vec<StashEntry>, GitError> {
+        self.with_repo(path, |repo| Ok(repo.stashes.clone()))
+    }
+
+    fn stash_pop(&self, path: &str, index: usize) -> Result<(), GitError> {
+        self.with_repo(path, |repo| {
+            if index >= repo.stashes.len() {
+                return Err(GitError::Command(format!(
+                    "no stash entry at index {}",
+                    index
+                )));
+            }
+            repo.stashes.remove(index);
+            reindex(&mut repo.stashes);
+            Ok(())
+        })
+    }
+
+    fn get_status_against(
+        &self,
+        path: &str,
+        base_branch: &str,
+        _options: &GitStatusOptions,
+    ) -> Result<GitStatusInfo, GitError> {
+        self.with_repo(path, |repo| {
+            Ok(GitStatusInfo {
+                is_clean: true,
+                ahead: 0,
+                behind: 0,
+                modified: Vec::new(),
+                staged: Vec::new(),
+                untracked: Vec::new(),
+                base_branch: repo.branches.iter().find(|b| *b == base_branch).cloned(),
+                ahead_of_base: 0,
+                behind_base: 0,
+                total_entries: 0,
+                has_more: false,
+            })
+        })
+    }
+
+    fn commit_log(
+        &self,
+        path: &str,
+        _base_branch: Option<&str>,
+        _limit: usize,
+        _skip: usize,
+    ) -> Result<Vec<CommitInfo>, GitError> {
+        self.with_repo(path, |_| Ok(Vec::new()))
+    }
+
+    fn diverged_files(&self, path: &str, _base_branch: &str) -> Result<DivergedFiles, GitError> {
+        self.with_repo(path, |_| Ok(DivergedFiles::default()))
     }
 }
 
-impl std::error::Error for MockProcessError {}
+fn reindex(stashes: &mut [StashEntry]) {
+    for (index, entry) in stashes.iter_mut().enumerate() {
+        entry.index = index;
+    }
+}
 
 /// Helper trait for asserting agent states
 pub trait AgentAssertions {
@@ -226,14 +680,19 @@ mod tests {
     #[test]
     fn test_mock_process_manager_spawn() {
         let pm = MockProcessManager::new();
-        let pid = pm.spawn_agent(
-            "test_agent",
-            "/tmp",
-            AgentMode::Regular,
-            &[Permission::Read],
-            None,
-            None,
-        ).unwrap();
+        let (pid, _) = pm
+            .spawn_agent(
+                "test_agent",
+                "/tmp",
+                AgentMode::Regular,
+                ToolAccess {
+                    permissions: &[Permission::Read],
+                    ..Default::default()
+                },
+                LaunchPrompts::default(),
+                SessionLaunch::New,
+            )
+            .unwrap();
 
         assert!(pid >= 10000);
         assert!(pm.is_running("test_agent"));
@@ -246,10 +705,11 @@ mod tests {
             "test_agent",
             "/tmp",
             AgentMode::Regular,
-            &[],
-            None,
-            None,
-        ).unwrap();
+            ToolAccess::default(),
+            LaunchPrompts::default(),
+            SessionLaunch::New,
+        )
+        .unwrap();
 
         assert!(pm.is_running("test_agent"));
 
@@ -266,9 +726,9 @@ mod tests {
             "test_agent",
             "/tmp",
             AgentMode::Regular,
-            &[],
-            None,
-            None,
+            ToolAccess::default(),
+            LaunchPrompts::default(),
+            SessionLaunch::New,
         );
 
         assert!(result.is_err());
@@ -4,6 +4,8 @@
 
 #![allow(dead_code)]
 
+#[cfg(unix)]
+pub mod fake_claude;
 pub mod fixtures;
 pub mod mocks;
 
@@ -173,6 +175,11 @@ impl TestContext {
                 is_main: is_main != 0,
                 created_at: row.get(8)?,
                 updated_at: row.get(9)?,
+                pr_url: None,
+                sparse_patterns: None,
+                archived_at: None,
+                pinned: false,
+                missing_since: None,
             })
         })
         .expect("Failed to get worktree")
@@ -226,3 +233,28 @@ pub fn create_empty_test_pool() -> (DbPool, TempDir) {
 
     (pool, temp_dir)
 }
+
+/// Initialise `path` as a git repo with everything in it committed on `main`.
+///
+/// Database files are ignored so a test context's own directory can be used.
+pub fn init_git_repo(path: &std::path::Path) -> git2::Repository {
+    let repo = git2::Repository::init_opts(
+        path,
+        git2::RepositoryInitOptions::new().initial_head("main"),
+    )
+    .expect("Failed to init repo");
+    std::fs::write(path.join(".gitignore"), "*.db*\n").expect("Failed to write .gitignore");
+
+    {
+        let mut index = repo.index().unwrap();
+        index
+            .add_all(["*"], git2::IndexAddOption::DEFAULT, None)
+            .unwrap();
+        index.write().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let sig = git2::Signature::now("Test", "test@example.com").unwrap();
+        repo.commit(Some("HEAD"), &sig, &sig, "Initial commit", &tree, &[])
+            .expect("Failed to commit");
+    }
+    repo
+}
@@ -4,7 +4,7 @@ use r2d2::Pool;
 use r2d2_sqlite::SqliteConnectionManager;
 use tempfile::tempdir;
 
-use claude_manager_lib::db::migrations;
+use claude_manager_lib::db::{self, migrations};
 
 #[test]
 fn test_migrations_run_successfully() {
@@ -362,3 +362,164 @@ fn test_cascade_delete_worktrees() {
         .unwrap();
     assert_eq!(agent_count, 0, "Agents should be cascade deleted");
 }
+
+#[test]
+fn test_stepwise_downgrade_and_upgrade() {
+    let temp_dir = tempdir().expect("Failed to create temp dir");
+    let db_path = temp_dir.path().join("test_stepwise.db");
+
+    let manager = SqliteConnectionManager::file(&db_path).with_init(|conn| {
+        conn.execute_batch("PRAGMA foreign_keys = ON;")?;
+        Ok(())
+    });
+
+    let pool = Pool::builder()
+        .max_size(1)
+        .build(manager)
+        .expect("Failed to create pool");
+
+    let conn = pool.get().expect("Failed to get connection");
+    migrations::run_migrations(&conn).expect("Migrations should succeed");
+
+    conn.execute_batch(
+        r#"INSERT INTO workspaces (id, name, path) VALUES ('ws_test', 'Test', '/tmp');
+           INSERT INTO worktrees (id, workspace_id, name, branch, path)
+           VALUES ('wt_test', 'ws_test', 'main', 'main', '/tmp');
+           INSERT INTO agents (id, worktree_id, name, status) VALUES ('ag_test', 'wt_test', 'Test', 'idle');
+           INSERT INTO messages (id, agent_id, role, content) VALUES ('msg_test', 'ag_test', 'user', 'hi');"#,
+    )
+    .expect("Failed to seed data");
+
+    // Downgrade to version 1 restores the legacy 'finished' status
+    migrations::migrate_to(&conn, 1).expect("Downgrade should succeed");
+    assert_eq!(migrations::current_version(&conn).unwrap(), 1);
+
+    let status: String = conn
+        .query_row("SELECT status FROM agents WHERE id = 'ag_test'", [], |row| {
+            row.get(0)
+        })
+        .unwrap();
+    assert_eq!(status, "finished");
+
+    // Upgrading again converts it back without losing dependent rows
+    migrations::migrate_to(&conn, 2).expect("Upgrade should succeed");
+    assert_eq!(migrations::current_version(&conn).unwrap(), 2);
+
+    let status: String = conn
+        .query_row("SELECT status FROM agents WHERE id = 'ag_test'", [], |row| {
+            row.get(0)
+        })
+        .unwrap();
+    assert_eq!(status, "idle");
+
+    let message_count: i32 = conn
+        .query_row("SELECT COUNT(*) FROM messages", [], |row| row.get(0))
+        .unwrap();
+    assert_eq!(message_count, 1, "Table rebuilds should not cascade deletes");
+
+    let fk_enabled: i32 = conn
+        .query_row("PRAGMA foreign_keys", [], |row| row.get(0))
+        .unwrap();
+    assert_eq!(fk_enabled, 1, "Foreign keys should be re-enabled");
+}
+
+#[test]
+fn test_downgrade_to_empty_schema() {
+    let temp_dir = tempdir().expect("Failed to create temp dir");
+    let db_path = temp_dir.path().join("test_downgrade_all.db");
+
+    let manager = SqliteConnectionManager::file(&db_path);
+    let pool = Pool::builder()
+        .max_size(1)
+        .build(manager)
+        .expect("Failed to create pool");
+
+    let conn = pool.get().expect("Failed to get connection");
+    migrations::run_migrations(&conn).expect("Migrations should succeed");
+    migrations::migrate_to(&conn, 0).expect("Downgrade should succeed");
+
+    assert_eq!(migrations::current_version(&conn).unwrap(), 0);
+    let table_count: i32 = conn
+        .query_row(
+            "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name NOT IN ('schema_migrations', 'sqlite_sequence')",
+            [],
+            |row| row.get(0),
+        )
+        .unwrap();
+    assert_eq!(table_count, 0, "All tables should be dropped");
+
+    // Every version can be reached again from scratch
+    for migration in migrations::migrations() {
+        migrations::migrate_to(&conn, migration.version).expect("Upgrade should succeed");
+        assert_eq!(
+            migrations::current_version(&conn).unwrap(),
+            migration.version
+        );
+    }
+}
+
+#[test]
+fn test_migrations_record_checksums() {
+    let temp_dir = tempdir().expect("Failed to create temp dir");
+    let db_path = temp_dir.path().join("test_checksums.db");
+
+    let manager = SqliteConnectionManager::file(&db_path);
+    let pool = Pool::builder()
+        .max_size(1)
+        .build(manager)
+        .expect("Failed to create pool");
+
+    let conn = pool.get().expect("Failed to get connection");
+    migrations::run_migrations(&conn).expect("Migrations should succeed");
+
+    for migration in migrations::migrations() {
+        let checksum: Option<String> = conn
+            .query_row(
+                "SELECT checksum FROM schema_migrations WHERE version = ?",
+                [migration.version],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(checksum, Some(migration.checksum()));
+    }
+}
+
+#[test]
+fn test_migrate_to_unknown_version_fails() {
+    let temp_dir = tempdir().expect("Failed to create temp dir");
+    let db_path = temp_dir.path().join("test_unknown_version.db");
+
+    let manager = SqliteConnectionManager::file(&db_path);
+    let pool = Pool::builder()
+        .max_size(1)
+        .build(manager)
+        .expect("Failed to create pool");
+
+    let conn = pool.get().expect("Failed to get connection");
+    let result = migrations::migrate_to(&conn, migrations::latest_version() + 1);
+    assert!(result.is_err(), "Unknown target version should be rejected");
+}
+
+#[test]
+fn test_open_database_refuses_to_migrate() {
+    let temp_dir = tempdir().expect("Failed to create temp dir");
+    assert!(matches!(
+        db::open_database(temp_dir.path()),
+        Err(db::DbError::NotFound)
+    ));
+
+    let pool = db::init_database(temp_dir.path().to_path_buf()).expect("Failed to init");
+    assert!(db::open_database(temp_dir.path()).is_ok());
+
+    // As if the app were on an older build, or a newer one than this
+    let previous = migrations::latest_version() - 1;
+    migrations::migrate_to(&pool.get().unwrap(), previous).unwrap();
+    assert!(matches!(
+        db::open_database(temp_dir.path()),
+        Err(db::DbError::Migration(_))
+    ));
+    assert_eq!(
+        migrations::current_version(&pool.get().unwrap()).unwrap(),
+        previous
+    );
+}
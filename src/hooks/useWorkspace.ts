@@ -117,7 +117,9 @@ export function useWorkspace(workspaceId: string | null) {
 
   // Add worktree mutation
   const addWorktreeMutation = useMutation({
-    mutationFn: (data: CreateWorktreeDto) => api.worktrees.create(workspaceId!, data),
+    // Creation runs as a job; wait for it so the worktree exists before refetching
+    mutationFn: async (data: CreateWorktreeDto) =>
+      api.jobs.wait(await api.worktrees.create(workspaceId!, data)),
     onSuccess: () => {
       queryClient.invalidateQueries({
         queryKey: queryKeys.workspaces.detail(workspaceId!),
@@ -411,6 +413,7 @@ export function useWorkspace(workspaceId: string | null) {
 
     // Mutation states
     isAddingWorktree: addWorktreeMutation.isPending,
+    addWorktreeError: addWorktreeMutation.error,
     isRemovingWorktree: removeWorktreeMutation.isPending,
     isAddingAgent: addAgentMutation.isPending,
     isRefreshing: refreshWorkspaceMutation.isPending,
@@ -1,6 +1,7 @@
 import type {
   Workspace,
   Worktree,
+  WorktreeState,
   Agent,
   SortMode,
   AgentStatus,
@@ -8,10 +9,35 @@ import type {
   Permission,
 } from '@claude-manager/shared'
 
+// Error returned by Tauri commands
+export class ApiError extends Error {
+  constructor(
+    public code: string,
+    message: string,
+    public details?: Record<string, unknown>
+  ) {
+    super(message)
+    this.name = 'ApiError'
+  }
+}
+
+function isErrorResponse(
+  error: unknown
+): error is { code: string; message: string; details?: Record<string, unknown> } {
+  return typeof error === 'object' && error !== null && 'code' in error && 'message' in error
+}
+
 // Tauri invoke function
 async function tauriInvoke<T>(command: string, args?: Record<string, unknown>): Promise<T> {
   const { invoke } = await import('@tauri-apps/api/core')
-  return invoke<T>(command, args)
+  try {
+    return await invoke<T>(command, args)
+  } catch (error) {
+    if (isErrorResponse(error)) {
+      throw new ApiError(error.code, error.message, error.details)
+    }
+    throw error
+  }
 }
 
 // Extended Worktree type that includes agents (from workspace details endpoint)
@@ -25,6 +51,67 @@ export interface WorkspaceWithDetails extends Workspace {
   worktrees: WorktreeWithAgents[]
 }
 
+// Long-running operation, e.g. worktree creation; watch `job:updated` events
+export interface Job<T = unknown> {
+  id: string
+  kind: string
+  targetId?: string
+  status: 'queued' | 'running' | 'completed' | 'failed' | 'cancelled'
+  progress: number
+  message?: string
+  result?: T
+  error?: string
+  createdAt: string
+  updatedAt: string
+  finishedAt?: string
+}
+
+// Result of a `legacy_migration` job importing the Node.js backend's database
+export interface LegacyMigrationResult {
+  sourcePath: string
+  // Snapshot of the database taken right before the import; absent on a dry run
+  backup: { path: string; sizeBytes: number; schemaVersion: number; createdAt: string } | null
+  stats: {
+    // Nothing was written; the counts are what the import would copy
+    dryRun: boolean
+    workspacesMigrated: number
+    worktreesMigrated: number
+    agentsMigrated: number
+    messagesMigrated: number
+    sessionsMigrated: number
+    usageStatsMigrated: number
+    // Agents whose 'finished' status became 'idle'
+    statusesConverted: number
+    // Tables and columns either database lacks; only checked on a dry run
+    schemaMismatches: string[]
+  }
+  // Problems the verification found after the import
+  warnings: string[]
+}
+
+// What a health check found on disk and in git for one worktree
+export interface WorktreeHealth {
+  worktreeId: string
+  path: string
+  state: WorktreeState
+  exists: boolean
+  registered: boolean
+  locked: boolean
+  prunable: boolean
+  // null when the status couldn't be read
+  dirty: boolean | null
+}
+
+// Background re-sync of a workspace with git
+export interface WorkspaceRefreshJob {
+  id: string
+  workspaceId: string
+  status: 'queued' | 'running' | 'completed' | 'failed'
+  error?: string
+  queuedAt: string
+  finishedAt?: string
+}
+
 // Usage types
 export interface UsageSummary {
   daily: {
@@ -59,6 +146,77 @@ export interface UsageHistoryEntry {
   errorCount: number
 }
 
+export interface ClaudeProfile {
+  id: string
+  name: string
+  configDir: string | null
+  cliPath: string | null
+  model: string | null
+}
+
+export interface SaveProfileDto {
+  id?: string
+  name: string
+  configDir?: string
+  cliPath?: string
+  model?: string
+}
+
+export interface ProfileSelection {
+  profileId: string | null
+  effective: ClaudeProfile | null
+}
+
+export interface IdleShutdownPolicy {
+  enabled: boolean
+  idleMinutes: number
+}
+
+export interface AgentIdlePolicy {
+  agentId: string
+  policy: IdleShutdownPolicy | null
+  effective: IdleShutdownPolicy
+}
+
+// An agent stopped for sitting idle; starting it again resumes the session
+export interface IdleShutdown {
+  agentId: string
+  sessionId: string | null
+  idleMinutes: number
+  stoppedAt: string
+}
+
+// Memory and CPU of an agent's process at one sample
+export interface AgentResources {
+  agentId: string
+  pid: number
+  memoryBytes: number
+  cpuPercent: number
+  memoryExceeded: boolean
+  cpuExceeded: boolean
+  sampledAt: string
+}
+
+// An agent's process as the process manager sees it; `stale` when the status
+// says running or waiting but there is no process
+export interface AgentRuntime {
+  agentId: string
+  status: AgentStatus
+  isRunning: boolean
+  stale: boolean
+  pid?: number
+  lastOutputAt?: string
+  lastOutputAgeMs?: number
+  bufferBytes: number
+  subscribers: number
+}
+
+// Percent of one core for CPU; no limit when null
+export interface ResourceLimits {
+  memoryMb: number | null
+  cpuPercent: number | null
+}
+
 // DTOs
 export interface CreateWorktreeDto {
   name: string
@@ -70,6 +228,8 @@ export interface UpdateWorktreeDto {
   name?: string
   sortMode?: SortMode
   order?: number
+  // updatedAt of the worktree being edited; a newer one fails with CONFLICT
+  expectedUpdatedAt?: string
 }
 
 export interface CreateAgentDto {
@@ -84,6 +244,8 @@ export interface UpdateAgentDto {
   name?: string
   mode?: AgentMode
   permissions?: Permission[]
+  // updatedAt of the agent being edited; a newer one fails with CONFLICT
+  expectedUpdatedAt?: string
 }
 
 // Tauri-specific input types (matching Rust struct names)
@@ -104,6 +266,7 @@ interface UpdateWorktreeInput {
   name?: string
   sortMode?: SortMode
   displayOrder?: number
+  expectedUpdatedAt?: string
 }
 
 interface CheckoutBranchInput {
@@ -127,6 +290,7 @@ interface UpdateAgentInput {
   mode?: AgentMode
   permissions?: Permission[]
   displayOrder?: number
+  expectedUpdatedAt?: string
 }
 
 interface ReorderAgentsInput {
@@ -155,7 +319,54 @@ export const api = {
     },
 
     refresh: async (id: string) => {
-      return tauriInvoke<WorkspaceWithDetails>('refresh_workspace', { id })
+      return tauriInvoke<WorkspaceRefreshJob>('refresh_workspace', { id })
+    },
+
+    getRefreshJob: async (jobId: string) => {
+      return tauriInvoke<WorkspaceRefreshJob>('get_workspace_refresh_job', { jobId })
+    },
+
+    // Agents kept from worktrees removed outside the app
+    orphanedAgents: async (workspaceId: string) => {
+      return tauriInvoke<Agent[]>('list_orphaned_agents', { workspaceId })
+    },
+  },
+
+  // Background jobs
+  jobs: {
+    get: async (id: string) => {
+      return tauriInvoke<Job>('get_job', { id })
+    },
+
+    cancel: async (id: string) => {
+      return tauriInvoke<Job>('cancel_job', { id })
+    },
+
+    // Poll until the job completes; rejects with its error if it fails or is cancelled
+    wait: async <T>(job: Job<T>, intervalMs = 500): Promise<T> => {
+      let current = job
+      while (current.status === 'queued' || current.status === 'running') {
+        await new Promise((resolve) => setTimeout(resolve, intervalMs))
+        current = await tauriInvoke<Job<T>>('get_job', { id: job.id })
+      }
+      if (current.status !== 'completed') {
+        throw new Error(current.error ?? `Job ${current.status}`)
+      }
+      return current.result as T
+    },
+  },
+
+  // Import from the Node.js backend; the source defaults to its database
+  legacyMigration: {
+    run: async (sourcePath?: string, dryRun?: boolean) => {
+      return tauriInvoke<Job<LegacyMigrationResult>>('run_legacy_migration', {
+        sourcePath,
+        dryRun,
+      })
+    },
+
+    verify: async (sourcePath?: string) => {
+      return tauriInvoke<string[]>('verify_legacy_migration', { sourcePath })
     },
   },
 
@@ -176,7 +387,7 @@ export const api = {
         branch: data.branch,
         createBranch: data.createBranch,
       }
-      return tauriInvoke<Worktree>('create_worktree', { input })
+      return tauriInvoke<Job<Worktree>>('create_worktree', { input })
     },
 
     update: async (_workspaceId: string, id: string, data: UpdateWorktreeDto) => {
@@ -184,6 +395,7 @@ export const api = {
         name: data.name,
         sortMode: data.sortMode,
         displayOrder: data.order,
+        expectedUpdatedAt: data.expectedUpdatedAt,
       }
       return tauriInvoke<Worktree>('update_worktree', { id, input })
     },
@@ -192,6 +404,15 @@ export const api = {
       return tauriInvoke<void>('delete_worktree', { id })
     },
 
+    health: async (workspaceId: string) => {
+      return tauriInvoke<WorktreeHealth[]>('get_worktree_health', { workspaceId })
+    },
+
+    // Pinned worktrees are kept, marked missing, when a scan can't find them
+    setPinned: async (id: string, pinned: boolean) => {
+      return tauriInvoke<Worktree>('set_worktree_pinned', { id, pinned })
+    },
+
     checkout: async (_workspaceId: string, id: string, branch: string, createBranch = false) => {
       const input: CheckoutBranchInput = { branch, create: createBranch }
       return tauriInvoke<Worktree>('checkout_branch', { id, input })
@@ -241,6 +462,10 @@ export const api = {
       return tauriInvoke<Agent>('get_agent', { id })
     },
 
+    runtime: async (agentId: string) => {
+      return tauriInvoke<AgentRuntime>('get_agent_runtime', { agentId })
+    },
+
     create: async (data: CreateAgentDto) => {
       const input: CreateAgentInput = {
         worktreeId: data.worktreeId,
@@ -256,6 +481,7 @@ export const api = {
         name: data.name,
         mode: data.mode,
         permissions: data.permissions,
+        expectedUpdatedAt: data.expectedUpdatedAt,
       }
       return tauriInvoke<Agent>('update_agent', { id, input })
     },
@@ -308,8 +534,8 @@ export const api = {
 
   // Usage
   usage: {
-    get: async () => {
-      return tauriInvoke<UsageSummary>('get_claude_usage')
+    get: async (profileId?: string) => {
+      return tauriInvoke<UsageSummary>('get_claude_usage', { profileId })
     },
 
     getHistory: async (period: 'daily' | 'weekly' | 'monthly', _start?: string, _end?: string) => {
@@ -335,6 +561,78 @@ export const api = {
       return tauriInvoke<UsageLimits>('get_usage_limits')
     },
   },
+
+  // Claude account profiles
+  profiles: {
+    list: async () => {
+      return tauriInvoke<ClaudeProfile[]>('list_claude_profiles')
+    },
+
+    save: async (input: SaveProfileDto) => {
+      return tauriInvoke<ClaudeProfile>('save_claude_profile', { input })
+    },
+
+    delete: async (id: string) => {
+      return tauriInvoke<void>('delete_claude_profile', { id })
+    },
+
+    getForWorkspace: async (workspaceId: string) => {
+      return tauriInvoke<ProfileSelection>('get_workspace_claude_profile', { workspaceId })
+    },
+
+    setForWorkspace: async (workspaceId: string, profileId: string | null) => {
+      return tauriInvoke<ProfileSelection>('set_workspace_claude_profile', {
+        workspaceId,
+        profileId,
+      })
+    },
+
+    getForAgent: async (agentId: string) => {
+      return tauriInvoke<ProfileSelection>('get_agent_claude_profile', { agentId })
+    },
+
+    setForAgent: async (agentId: string, profileId: string | null) => {
+      return tauriInvoke<ProfileSelection>('set_agent_claude_profile', { agentId, profileId })
+    },
+  },
+
+  // Idle auto-shutdown
+  idleShutdown: {
+    getPolicy: async () => {
+      return tauriInvoke<IdleShutdownPolicy>('get_idle_shutdown_policy')
+    },
+
+    setPolicy: async (policy: IdleShutdownPolicy) => {
+      return tauriInvoke<IdleShutdownPolicy>('set_idle_shutdown_policy', { policy })
+    },
+
+    getForAgent: async (agentId: string) => {
+      return tauriInvoke<AgentIdlePolicy>('get_agent_idle_shutdown_policy', { agentId })
+    },
+
+    setForAgent: async (agentId: string, policy: IdleShutdownPolicy | null) => {
+      return tauriInvoke<AgentIdlePolicy>('set_agent_idle_shutdown_policy', { agentId, policy })
+    },
+
+    listShutdowns: async () => {
+      return tauriInvoke<IdleShutdown[]>('list_idle_shutdowns')
+    },
+  },
+
+  // Agent process resources
+  resources: {
+    getForAgent: async (agentId: string) => {
+      return tauriInvoke<AgentResources | null>('get_agent_resources', { agentId })
+    },
+
+    getLimits: async () => {
+      return tauriInvoke<ResourceLimits>('get_resource_limits')
+    },
+
+    setLimits: async (limits: ResourceLimits) => {
+      return tauriInvoke<ResourceLimits>('set_resource_limits', { limits })
+    },
+  },
 }
 
 // Dialog utilities
@@ -30,6 +30,7 @@ interface WebSocketMessage {
 
 interface AgentOutputPayload {
   agentId: string
+  messageId?: string
   content: string
   role?: 'user' | 'assistant' | 'system' | 'tool'
   isComplete?: boolean
@@ -205,6 +206,7 @@ class WebSocketClient {
     const p = payload as Record<string, unknown>
     return {
       agentId: (p.agentId as string) || '',
+      messageId: p.messageId as string | undefined,
       content: (p.content as string) || '',
       role: p.role as 'user' | 'assistant' | 'system' | 'tool' | undefined,
       isComplete: (p.isComplete as boolean) ?? true,
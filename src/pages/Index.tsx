@@ -42,6 +42,7 @@ const Index = () => {
     reorderWorktrees,
     refresh,
     isRefreshing,
+    addWorktreeError,
   } = useWorkspace(selectedWorkspaceId)
 
   // Usage stats
@@ -150,6 +151,11 @@ const Index = () => {
           </AlertDescription>
         </Alert>
       )}
+      {addWorktreeError && (
+        <Alert variant="destructive" className="mx-6 mt-2">
+          <AlertDescription>Failed to add worktree: {addWorktreeError.message}</AlertDescription>
+        </Alert>
+      )}
 
       {/* Main Content */}
       <ScrollArea className="flex-1">